@@ -0,0 +1,219 @@
+//! Parsing of rustc's `--error-format=json` diagnostics into compact,
+//! classified summaries for repair prompts.
+
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+/// Notes kept per diagnostic; the rest are usually repeats or rustc trivia.
+const MAX_NOTES: usize = 3;
+
+pub(crate) struct Diagnostic {
+    pub kind: &'static str,
+    pub code: Option<String>,
+    pub message: String,
+    pub item: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+}
+
+/// Line range and name of a top-level item (or impl method) in the source.
+struct ItemRange {
+    start: usize,
+    end: usize,
+    name: String,
+}
+
+/// Parses every error in `output`, which may be rustc's JSON diagnostics or
+/// cargo's `--message-format=json` records wrapping them. Other lines (cargo
+/// artifacts, human-readable text) are ignored. Errors are mapped to the item
+/// of `source` that contains their primary span, and duplicates are dropped.
+pub(crate) fn parse_diagnostics(output: &str, source: Option<&syn::File>) -> Vec<Diagnostic> {
+    let items = source.map(item_ranges).unwrap_or_default();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            continue;
+        };
+        let message = match value.get("reason").and_then(|r| r.as_str()) {
+            Some("compiler-message") => match value.get("message") {
+                Some(message) => message,
+                None => continue,
+            },
+            Some(_) => continue,
+            None => &value,
+        };
+        let Some(diagnostic) = parse_message(message, &items) else {
+            continue;
+        };
+        let duplicate = diagnostics.iter().any(|d| {
+            d.code == diagnostic.code
+                && d.message == diagnostic.message
+                && d.line == diagnostic.line
+        });
+        if !duplicate {
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
+fn parse_message(message: &serde_json::Value, items: &[ItemRange]) -> Option<Diagnostic> {
+    let level = message.get("level")?.as_str()?;
+    if level != "error" {
+        return None;
+    }
+    let text = message.get("message")?.as_str()?.to_string();
+    let code = message
+        .get("code")
+        .and_then(|c| c.get("code"))
+        .and_then(|c| c.as_str())
+        .map(str::to_string);
+    let spans = message
+        .get("spans")
+        .and_then(|s| s.as_array())
+        .cloned()
+        .unwrap_or_default();
+    // "aborting due to N previous errors" and friends carry no code or span
+    if code.is_none() && spans.is_empty() {
+        return None;
+    }
+
+    let primary = spans
+        .iter()
+        .find(|s| s.get("is_primary").and_then(|p| p.as_bool()) == Some(true))
+        .or_else(|| spans.first());
+    let line = primary
+        .and_then(|s| s.get("line_start"))
+        .and_then(|l| l.as_u64())
+        .map(|l| l as usize);
+    let column = primary
+        .and_then(|s| s.get("column_start"))
+        .and_then(|c| c.as_u64())
+        .map(|c| c as usize);
+    let label = primary
+        .and_then(|s| s.get("label"))
+        .and_then(|l| l.as_str())
+        .map(str::to_string);
+
+    let mut notes = Vec::new();
+    for child in message
+        .get("children")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let Some(child_text) = child.get("message").and_then(|m| m.as_str()) else {
+            continue;
+        };
+        let replacement = child
+            .get("spans")
+            .and_then(|s| s.as_array())
+            .and_then(|s| s.first())
+            .and_then(|s| s.get("suggested_replacement"))
+            .and_then(|r| r.as_str())
+            .map(str::trim)
+            .filter(|r| !r.is_empty());
+        let note = match replacement {
+            Some(replacement) => format!("{}: `{}`", child_text, replacement),
+            None => child_text.to_string(),
+        };
+        if notes.len() < MAX_NOTES && !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+
+    let item = line.and_then(|line| {
+        // the innermost range wins, so impl methods beat their impl block
+        items
+            .iter()
+            .filter(|range| range.start <= line && line <= range.end)
+            .min_by_key(|range| range.end - range.start)
+            .map(|range| range.name.clone())
+    });
+
+    Some(Diagnostic {
+        kind: classify(code.as_deref(), &text, &notes),
+        code,
+        message: text,
+        item,
+        line,
+        column,
+        label,
+        notes,
+    })
+}
+
+/// Buckets an error into `missing_import`, `unresolved_name`, `type_mismatch`,
+/// `borrowck`, `lifetime` or `other`, mostly by its error code.
+fn classify(code: Option<&str>, message: &str, notes: &[String]) -> &'static str {
+    let suggests_import = notes
+        .iter()
+        .any(|note| note.contains("consider importing") || note.contains("trait is in scope"))
+        || message.contains("use of undeclared crate or module");
+    match code {
+        Some("E0432" | "E0433") => "missing_import",
+        Some("E0405" | "E0412" | "E0422" | "E0423" | "E0425" | "E0531" | "E0574" | "E0599") => {
+            if suggests_import {
+                "missing_import"
+            } else {
+                "unresolved_name"
+            }
+        }
+        Some(
+            "E0053" | "E0061" | "E0069" | "E0277" | "E0308" | "E0369" | "E0604" | "E0605" | "E0606"
+            | "E0607" | "E0614" | "E0620",
+        ) => "type_mismatch",
+        Some(
+            "E0373" | "E0381" | "E0382" | "E0384" | "E0499" | "E0500" | "E0501" | "E0502" | "E0503"
+            | "E0505" | "E0506" | "E0507" | "E0508" | "E0509" | "E0594" | "E0596",
+        ) => "borrowck",
+        Some(
+            "E0106" | "E0261" | "E0262" | "E0263" | "E0495" | "E0515" | "E0521" | "E0597" | "E0621"
+            | "E0623" | "E0700" | "E0712" | "E0713" | "E0716",
+        ) => "lifetime",
+        None if message.contains("lifetime may not live long enough") => "lifetime",
+        _ => "other",
+    }
+}
+
+fn item_ranges(file: &syn::File) -> Vec<ItemRange> {
+    let mut ranges = Vec::new();
+    for item in file.items.iter() {
+        let span = item.span();
+        let (start, end) = (span.start().line, span.end().line);
+        match item {
+            syn::Item::Impl(imp) => {
+                let self_ty =
+                    crate::normalize_token_string(&imp.self_ty.to_token_stream().to_string());
+                for impl_item in imp.items.iter() {
+                    if let syn::ImplItem::Fn(f) = impl_item {
+                        let span = f.span();
+                        ranges.push(ItemRange {
+                            start: span.start().line,
+                            end: span.end().line,
+                            name: format!("{}::{}", self_ty, f.sig.ident),
+                        });
+                    }
+                }
+                ranges.push(ItemRange {
+                    start,
+                    end,
+                    name: format!("impl {}", self_ty),
+                });
+            }
+            syn::Item::Use(_) => ranges.push(ItemRange {
+                start,
+                end,
+                name: "use declaration".to_string(),
+            }),
+            _ => {
+                if let Some(name) = crate::item_name(item) {
+                    ranges.push(ItemRange { start, end, name });
+                }
+            }
+        }
+    }
+    ranges
+}
@@ -1,13 +1,14 @@
-#![feature(proc_macro_span)]
+#![cfg_attr(feature = "nightly", feature(proc_macro_span))]
 
 use proc_macro2::Span;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 use pyo3_stub_gen::derive::gen_stub_pyfunction;
 use quote::{quote, ToTokens};
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::mem;
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock};
 use syn::{
     parse::{Parse, ParseStream},
     parse_quote, parse_str,
@@ -18,6 +19,9 @@ use syn::{
     Abi, AttrStyle, Attribute, File, GenericArgument, ItemStatic, LitStr, Meta, PatIdent,
     PathArguments, Result, Token, TypePath,
 };
+
+mod diagnostics;
+
 static LIBC_SCALAR_MAP_TEXT: &str = include_str!(concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/../sactor/_resources/libc_scalar_map.txt"
@@ -25,10 +29,62 @@ static LIBC_SCALAR_MAP_TEXT: &str = include_str!(concat!(
 
 static LIBC_SCALAR_TO_PRIMITIVE: OnceLock<Vec<(&'static str, &'static str)>> = OnceLock::new();
 
+/// Mappings registered at runtime for platform typedefs; later entries win
+/// and all of them take precedence over `libc_scalar_map.txt`.
+static EXTRA_SCALAR_MAPPINGS: RwLock<Vec<(&'static str, &'static str)>> = RwLock::new(Vec::new());
+
 const NUMERIC_PRIMITIVES: &[&str] = &[
     "u8", "i8", "u16", "i16", "u32", "i32", "u64", "i64", "usize", "isize", "f32", "f64",
 ];
 
+/// `rustfmt.toml` that emitted code is post-formatted with, if any.
+static RUSTFMT_CONFIG: RwLock<Option<String>> = RwLock::new(None);
+
+/// Runs `rustfmt` over `code` using the given config file (or directory).
+fn run_rustfmt(code: &str, config_path: Option<&str>) -> std::result::Result<String, String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut command = Command::new("rustfmt");
+    command.args(["--edition", "2021", "--emit", "stdout", "--quiet"]);
+    if let Some(path) = config_path {
+        command.args(["--config-path", path]);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run rustfmt: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(code.as_bytes())
+            .map_err(|e| format!("failed to write to rustfmt: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run rustfmt: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Pretty-prints emitted code: prettyplease, then rustfmt when a
+/// `rustfmt.toml` was set with `set_rustfmt_config`. If rustfmt fails the
+/// prettyplease output is kept.
+fn unparse(file: &File) -> String {
+    let code = prettyplease::unparse(file);
+    let config = RUSTFMT_CONFIG
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    match config {
+        Some(path) => run_rustfmt(&code, Some(&path)).unwrap_or(code),
+        None => code,
+    }
+}
+
 fn get_error_context(source: &str, error: &syn::Error) -> String {
     let lines: Vec<_> = source.lines().collect();
     let span = error.span();
@@ -107,7 +163,7 @@ fn expose_function_to_c(source_code: &str, function_name: &str) -> PyResult<Stri
         }
     }
     // return the modified source code
-    Ok(prettyplease::unparse(&ast))
+    Ok(unparse(&ast))
 }
 
 fn normalize_stmt_with_semi(stmt: syn::Stmt) -> syn::Stmt {
@@ -146,7 +202,7 @@ fn append_stmt_to_function(
                 .iter()
                 .any(|existing| existing.to_token_stream().to_string() == target_tokens)
             {
-                return Ok(prettyplease::unparse(&ast));
+                return Ok(unparse(&ast));
             }
 
             if let Some(last_idx) = f.block.stmts.len().checked_sub(1) {
@@ -160,7 +216,7 @@ fn append_stmt_to_function(
             }
 
             f.block.stmts.push(target_stmt.clone());
-            return Ok(prettyplease::unparse(&ast));
+            return Ok(unparse(&ast));
         }
     }
 
@@ -190,6 +246,12 @@ fn get_func_signatures(source_code: &str) -> PyResult<HashMap<String, String>> {
                     }
                 }
             }
+            // `mut args: ...` of C-variadic functions
+            if let Some((pat, _)) = sig.variadic.as_mut().and_then(|v| v.pat.as_mut()) {
+                if let syn::Pat::Ident(ident) = &mut **pat {
+                    ident.mutability = None;
+                }
+            }
             signatures.insert(sig.ident.to_string(), quote!(#sig).to_string());
         }
     }
@@ -212,7 +274,7 @@ fn get_struct_definition(source_code: &str, struct_name: &str) -> PyResult<Strin
                     attrs: vec![],
                     items,
                 };
-                return Ok(prettyplease::unparse(&file));
+                return Ok(unparse(&file));
             }
         }
 
@@ -243,7 +305,7 @@ fn get_enum_definition(source_code: &str, enum_name: &str) -> PyResult<String> {
                     attrs: vec![],
                     items: vec![syn::Item::Enum(e.clone())],
                 };
-                return Ok(prettyplease::unparse(&file));
+                return Ok(unparse(&file));
             }
         }
     }
@@ -285,6 +347,7 @@ fn dedup_ast(ast: syn::File) -> String {
     let mut seen_struct: HashSet<String> = HashSet::new();
     let mut seen_enum: HashSet<String> = HashSet::new();
     let mut seen_union: HashSet<String> = HashSet::new();
+    let mut seen_macro: HashSet<String> = HashSet::new();
 
     let mut use_idents: HashSet<String> = HashSet::new();
     for item in ast.items.iter() {
@@ -310,7 +373,12 @@ fn dedup_ast(ast: syn::File) -> String {
                 }
             }
             syn::Item::Const(c) => {
-                let key = c.ident.to_string();
+                // anonymous consts (`const _: () = ...;`) only repeat when identical
+                let key = if c.ident == "_" {
+                    quote!(#c).to_string()
+                } else {
+                    c.ident.to_string()
+                };
                 seen_const.insert(key)
             }
             syn::Item::Static(s) => {
@@ -329,6 +397,10 @@ fn dedup_ast(ast: syn::File) -> String {
                 let key = u.ident.to_string();
                 seen_union.insert(key)
             }
+            syn::Item::Macro(m) => match &m.ident {
+                Some(ident) => seen_macro.insert(ident.to_string()),
+                None => true,
+            },
             _ => true,
         };
 
@@ -343,7 +415,7 @@ fn dedup_ast(ast: syn::File) -> String {
         items: new_items,
     };
 
-    prettyplease::unparse(&deduped)
+    unparse(&deduped)
 }
 
 fn collect_use_idents(tree: &syn::UseTree, acc: &mut HashSet<String>) {
@@ -384,7 +456,7 @@ fn strip_to_struct_items(source_code: &str) -> PyResult<String> {
         items: filtered,
     };
 
-    Ok(prettyplease::unparse(&file))
+    Ok(unparse(&file))
 }
 
 #[gen_stub_pyfunction]
@@ -481,32 +553,42 @@ fn normalize_token_string(input: &str) -> String {
     result
 }
 
+/// Parses `<path> = <primitive>` lines, skipping blanks and `#` comments.
+/// Errors carry the 1-based number of the offending line.
+fn parse_scalar_map(text: &str) -> std::result::Result<Vec<(&str, &str)>, usize> {
+    let mut pairs = Vec::new();
+    for (idx, raw_line) in text.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (lhs, rhs) = line.split_once('=').ok_or(idx + 1)?;
+        let src = lhs.trim();
+        let dst = rhs.trim();
+        if src.is_empty() || dst.is_empty() {
+            return Err(idx + 1);
+        }
+        pairs.push((src, dst));
+    }
+    Ok(pairs)
+}
+
 fn libc_scalar_pairs() -> &'static [(&'static str, &'static str)] {
     LIBC_SCALAR_TO_PRIMITIVE
         .get_or_init(|| {
-            let mut pairs: Vec<(&'static str, &'static str)> = Vec::new();
-            for (idx, raw_line) in LIBC_SCALAR_MAP_TEXT.lines().enumerate() {
-                let line = raw_line.trim();
-                if line.is_empty() || line.starts_with('#') {
-                    continue;
-                }
-                let (lhs, rhs) = line.split_once('=').unwrap_or_else(|| {
-                    panic!("Invalid entry in libc_scalar_map.txt on line {}", idx + 1)
-                });
-                let src = lhs.trim();
-                let dst = rhs.trim();
-                if src.is_empty() || dst.is_empty() {
-                    panic!("Invalid entry in libc_scalar_map.txt on line {}", idx + 1);
-                }
-                pairs.push((src, dst));
-            }
-            pairs
+            parse_scalar_map(LIBC_SCALAR_MAP_TEXT).unwrap_or_else(|line| {
+                panic!("Invalid entry in libc_scalar_map.txt on line {}", line)
+            })
         })
         .as_slice()
 }
 
 fn map_libc_scalar(name: &str) -> Option<&'static str> {
-    for (src, dst) in libc_scalar_pairs().iter() {
+    let extra = EXTRA_SCALAR_MAPPINGS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let registered = extra.iter().rev();
+    for (src, dst) in registered.chain(libc_scalar_pairs().iter()) {
         if *src == name {
             return Some(*dst);
         }
@@ -519,10 +601,114 @@ fn map_libc_scalar(name: &str) -> Option<&'static str> {
     None
 }
 
+/// A runtime-registered mapping whose source is exactly `name`.
+fn map_registered_scalar(name: &str) -> Option<&'static str> {
+    EXTRA_SCALAR_MAPPINGS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .rev()
+        .find(|(src, _)| *src == name)
+        .map(|(_, dst)| *dst)
+}
+
 fn is_numeric_primitive(name: &str) -> bool {
     NUMERIC_PRIMITIVES.iter().any(|item| *item == name)
 }
 
+fn add_scalar_mapping(src: &str, dst: &str) -> PyResult<()> {
+    if syn::parse_str::<syn::Path>(src).is_err() {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Invalid scalar type path '{}'",
+            src
+        )));
+    }
+    if !is_numeric_primitive(dst) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "'{}' is not a numeric primitive",
+            dst
+        )));
+    }
+    // the mapping lives for the rest of the process, like the built-in one
+    let src: &'static str = Box::leak(src.to_string().into_boxed_str());
+    let dst = NUMERIC_PRIMITIVES
+        .iter()
+        .find(|primitive| **primitive == dst)
+        .copied()
+        .unwrap_or_default();
+    EXTRA_SCALAR_MAPPINGS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push((src, dst));
+    Ok(())
+}
+
+/// Post-formats all code returned by this module with rustfmt using the
+/// `rustfmt.toml` at `path` (a file or a directory containing one), so output
+/// matches a downstream project's style. `None` restores plain prettyplease
+/// output.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (path=None))]
+fn set_rustfmt_config(path: Option<&str>) -> PyResult<()> {
+    if let Some(path) = path {
+        if !std::path::Path::new(path).exists() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "rustfmt config '{}' not found",
+                path
+            )));
+        }
+    }
+    *RUSTFMT_CONFIG
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = path.map(str::to_string);
+    Ok(())
+}
+
+/// Formats `code` with rustfmt, using `config_path` (a `rustfmt.toml` or its
+/// directory) when given and the module-level config otherwise.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (code, config_path=None))]
+fn format_with_rustfmt(code: &str, config_path: Option<&str>) -> PyResult<String> {
+    let config = match config_path {
+        Some(path) => Some(path.to_string()),
+        None => RUSTFMT_CONFIG
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone(),
+    };
+    run_rustfmt(code, config.as_deref()).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!("rustfmt failed: {}", e.trim()))
+    })
+}
+
+/// Maps the scalar typedef `src` (e.g. `DWORD` or `libc::time_t`) to the Rust
+/// primitive `dst`, overriding `libc_scalar_map.txt` for this process.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn register_scalar_mapping(src: &str, dst: &str) -> PyResult<()> {
+    add_scalar_mapping(src.trim(), dst.trim())
+}
+
+/// Registers every mapping in a file using the `libc_scalar_map.txt` format
+/// and returns how many were added.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn load_scalar_map(path: &str) -> PyResult<usize> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!("Failed to read {}: {}", path, e))
+    })?;
+    let pairs = parse_scalar_map(&text).map_err(|line| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Invalid entry in {} on line {}",
+            path, line
+        ))
+    })?;
+    for (src, dst) in &pairs {
+        add_scalar_mapping(src, dst)?;
+    }
+    Ok(pairs.len())
+}
+
 fn push_unique(vec: &mut Vec<String>, value: String) {
     if !value.is_empty() && !vec.iter().any(|existing| existing == &value) {
         vec.push(value);
@@ -692,6 +878,17 @@ struct TypeTraits {
     pointer_base_raw: Option<String>,
     pointer_element: Option<String>,
     box_innermost: Option<String>,
+    is_array: bool,
+    array_len: Option<usize>,
+    array_elem: Option<String>,
+    is_tuple: bool,
+    tuple_elems: Vec<TypeTraits>,
+    is_fn_pointer: bool,
+    fn_params: Vec<TypeTraits>,
+    fn_return: Option<Box<TypeTraits>>,
+    is_result: bool,
+    result_ok: Option<Box<TypeTraits>>,
+    result_err: Option<Box<TypeTraits>>,
 }
 
 impl TypeTraits {
@@ -720,6 +917,17 @@ impl TypeTraits {
             pointer_base_raw: None,
             pointer_element: None,
             box_innermost: None,
+            is_array: false,
+            array_len: None,
+            array_elem: None,
+            is_tuple: false,
+            tuple_elems: Vec::new(),
+            is_fn_pointer: false,
+            fn_params: Vec::new(),
+            fn_return: None,
+            is_result: false,
+            result_ok: None,
+            result_err: None,
         }
     }
 
@@ -767,6 +975,37 @@ impl TypeTraits {
         dict.set_item("pointer_base_raw", self.pointer_base_raw.clone())?;
         dict.set_item("pointer_element", self.pointer_element.clone())?;
         dict.set_item("box_innermost", self.box_innermost.clone())?;
+        dict.set_item("is_array", self.is_array)?;
+        dict.set_item("array_len", self.array_len)?;
+        dict.set_item("array_elem", self.array_elem)?;
+        dict.set_item("is_tuple", self.is_tuple)?;
+        let tuple_elems = PyList::empty(py);
+        for elem in self.tuple_elems {
+            tuple_elems.append(elem.into_py(py)?)?;
+        }
+        dict.set_item("tuple_elems", tuple_elems)?;
+        dict.set_item("is_fn_pointer", self.is_fn_pointer)?;
+        let fn_params = PyList::empty(py);
+        for param in self.fn_params {
+            fn_params.append(param.into_py(py)?)?;
+        }
+        dict.set_item("fn_params", fn_params)?;
+        if let Some(ret) = self.fn_return {
+            dict.set_item("fn_return", ret.into_py(py)?)?;
+        } else {
+            dict.set_item("fn_return", py.None())?;
+        }
+        dict.set_item("is_result", self.is_result)?;
+        if let Some(ok) = self.result_ok {
+            dict.set_item("result_ok", ok.into_py(py)?)?;
+        } else {
+            dict.set_item("result_ok", py.None())?;
+        }
+        if let Some(err) = self.result_err {
+            dict.set_item("result_err", err.into_py(py)?)?;
+        } else {
+            dict.set_item("result_err", py.None())?;
+        }
         Ok(dict.into())
     }
 
@@ -860,6 +1099,20 @@ fn analyze_type(ty: &syn::Type) -> TypeTraits {
                                     }
                                 }
                             }
+                            "Result" => {
+                                traits.is_result = true;
+                                if let PathArguments::AngleBracketed(args) = &last.arguments {
+                                    // aliases such as `io::Result<T>` only carry the ok type
+                                    let mut types = args.args.iter().filter_map(|arg| match arg {
+                                        GenericArgument::Type(ty) => Some(ty),
+                                        _ => None,
+                                    });
+                                    traits.result_ok =
+                                        types.next().map(|ty| Box::new(analyze_type(ty)));
+                                    traits.result_err =
+                                        types.next().map(|ty| Box::new(analyze_type(ty)));
+                                }
+                            }
                             "Box" => {
                                 traits.is_box = true;
                                 if let PathArguments::AngleBracketed(args) = &last.arguments {
@@ -903,7 +1156,32 @@ fn analyze_type(ty: &syn::Type) -> TypeTraits {
                     }
                     traits.pointer_inner = Some(Box::new(inner));
                 }
-                syn::Type::Tuple(_) | syn::Type::Array(_) | syn::Type::BareFn(_) => {}
+                syn::Type::Array(array) => {
+                    traits.is_array = true;
+                    traits.array_elem = Some(array.elem.to_token_stream().to_string());
+                    if let syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Int(len),
+                        ..
+                    }) = &array.len
+                    {
+                        traits.array_len = len.base10_parse().ok();
+                    }
+                }
+                syn::Type::Tuple(tuple) => {
+                    traits.is_tuple = !tuple.elems.is_empty();
+                    traits.tuple_elems = tuple.elems.iter().map(analyze_type).collect();
+                }
+                syn::Type::BareFn(bare_fn) => {
+                    traits.is_fn_pointer = true;
+                    traits.fn_params = bare_fn
+                        .inputs
+                        .iter()
+                        .map(|arg| analyze_type(&arg.ty))
+                        .collect();
+                    if let syn::ReturnType::Type(_, ret) = &bare_fn.output {
+                        traits.fn_return = Some(Box::new(analyze_type(ret)));
+                    }
+                }
                 syn::Type::ImplTrait(_)
                 | syn::Type::Infer(_)
                 | syn::Type::Macro(_)
@@ -950,9 +1228,7 @@ fn analyze_type(ty: &syn::Type) -> TypeTraits {
     }
 }
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn parse_function_signature(py: Python<'_>, signature: &str) -> PyResult<PyObject> {
+fn parse_signature_item(signature: &str) -> PyResult<syn::ItemFn> {
     let trimmed = signature.trim();
     if trimmed.is_empty() {
         return Err(pyo3::exceptions::PyValueError::new_err(
@@ -967,15 +1243,131 @@ fn parse_function_signature(py: Python<'_>, signature: &str) -> PyResult<PyObjec
         format!("{cleaned} {{}}")
     };
 
-    let item: syn::ItemFn = syn::parse_str(&snippet).map_err(|e| {
+    syn::parse_str(&snippet).map_err(|e| {
         pyo3::exceptions::PyValueError::new_err(format!(
             "Failed to parse function signature: {:?}",
             e
         ))
-    })?;
+    })
+}
+
+/// Drops named lifetimes from types (`&'a str` -> `&str`, `Foo<'a>` ->
+/// `Foo<'_>`) so they can be written at a call site; `'static` is kept.
+struct LifetimeEraser;
+
+impl VisitMut for LifetimeEraser {
+    fn visit_type_reference_mut(&mut self, reference: &mut syn::TypeReference) {
+        if reference
+            .lifetime
+            .as_ref()
+            .is_some_and(|lifetime| lifetime.ident != "static")
+        {
+            reference.lifetime = None;
+        }
+        visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+        if lifetime.ident != "static" {
+            *lifetime = syn::Lifetime::new("'_", lifetime.span());
+        }
+    }
+}
+
+fn erase_signature_lifetimes(sig: &mut syn::Signature) {
+    for input in sig.inputs.iter_mut() {
+        if let syn::FnArg::Typed(pat_type) = input {
+            LifetimeEraser.visit_type_mut(&mut pat_type.ty);
+        }
+    }
+    if let syn::ReturnType::Type(_, ty) = &mut sig.output {
+        LifetimeEraser.visit_type_mut(ty);
+    }
+}
+
+/// Type and const parameters of a signature, which a harness cannot call
+/// without picking concrete types. Lifetimes are not included.
+fn signature_type_params(sig: &syn::Signature) -> Vec<String> {
+    sig.generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(ty) => Some(ty.ident.to_string()),
+            syn::GenericParam::Const(c) => Some(c.ident.to_string()),
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .chain(sig.inputs.iter().filter_map(|input| match input {
+            syn::FnArg::Typed(pat_type) if matches!(*pat_type.ty, syn::Type::ImplTrait(_)) => Some(
+                normalize_token_string(&pat_type.ty.to_token_stream().to_string()),
+            ),
+            _ => None,
+        }))
+        .collect()
+}
+
+fn generics_into_py(py: Python<'_>, sig: &syn::Signature) -> PyResult<PyObject> {
+    let generics = PyDict::new(py);
+    let lifetimes = PyList::empty(py);
+    let type_params = PyList::empty(py);
+    let const_params = PyList::empty(py);
+    for param in sig.generics.params.iter() {
+        match param {
+            syn::GenericParam::Lifetime(lifetime) => {
+                lifetimes.append(lifetime.lifetime.to_string())?;
+            }
+            syn::GenericParam::Type(ty) => {
+                let entry = PyDict::new(py);
+                entry.set_item("name", ty.ident.to_string())?;
+                let bounds: Vec<String> = ty
+                    .bounds
+                    .iter()
+                    .map(|bound| normalize_token_string(&bound.to_token_stream().to_string()))
+                    .collect();
+                entry.set_item("bounds", bounds)?;
+                type_params.append(entry)?;
+            }
+            syn::GenericParam::Const(c) => {
+                let entry = PyDict::new(py);
+                entry.set_item("name", c.ident.to_string())?;
+                entry.set_item(
+                    "type",
+                    normalize_token_string(&c.ty.to_token_stream().to_string()),
+                )?;
+                const_params.append(entry)?;
+            }
+        }
+    }
+    generics.set_item("lifetimes", lifetimes)?;
+    generics.set_item("type_params", type_params)?;
+    generics.set_item("const_params", const_params)?;
+    let predicates: Vec<String> = sig
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|clause| clause.predicates.iter())
+        .map(|predicate| {
+            normalize_token_string(&predicate.to_token_stream().to_string()).replace(" : ", ": ")
+        })
+        .collect();
+    generics.set_item("where_predicates", predicates)?;
+    generics.set_item("monomorphic", signature_type_params(sig).is_empty())?;
+    Ok(generics.into())
+}
+
+/// Parses a function signature into its name, parameter and return traits,
+/// and `generics` (lifetimes, type/const parameters with bounds, where-clause
+/// predicates, and whether the signature is `monomorphic`, i.e. callable without
+/// choosing types). Named lifetimes are erased from the reported types so
+/// they can be reused in harness code.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn parse_function_signature(py: Python<'_>, signature: &str) -> PyResult<PyObject> {
+    let mut item = parse_signature_item(signature)?;
 
     let result = PyDict::new(py);
     result.set_item("name", item.sig.ident.to_string())?;
+    result.set_item("generics", generics_into_py(py, &item.sig)?)?;
+    erase_signature_lifetimes(&mut item.sig);
 
     let params = PyList::empty(py);
     for input in item.sig.inputs.iter() {
@@ -1038,7 +1430,7 @@ fn get_function_definition(source_code: &str, function_name: &str) -> PyResult<S
                     attrs: vec![],
                     items: vec![syn::Item::Fn(f.clone())],
                 };
-                return Ok(prettyplease::unparse(&file));
+                return Ok(unparse(&file));
             }
         }
     }
@@ -1051,1126 +1443,14335 @@ fn get_function_definition(source_code: &str, function_name: &str) -> PyResult<S
 
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn get_static_item_definition(source_code: &str, item_name: &str) -> PyResult<String> {
+fn get_macro_definition(source_code: &str, macro_name: &str) -> PyResult<String> {
     let ast = parse_src(source_code)?;
 
     for item in ast.items.iter() {
-        if let syn::Item::Static(s) = item {
-            if s.ident == item_name {
+        if let syn::Item::Macro(m) = item {
+            if m.ident.as_ref().is_some_and(|ident| ident == macro_name) {
                 let file = syn::File {
                     shebang: None,
                     attrs: vec![],
-                    items: vec![syn::Item::Static(s.clone())],
+                    items: vec![item.clone()],
                 };
-                return Ok(prettyplease::unparse(&file));
+                return Ok(unparse(&file));
             }
         }
     }
 
     Err(pyo3::exceptions::PyValueError::new_err(format!(
-        "Static item '{}' not found",
-        item_name
+        "Macro '{}' not found",
+        macro_name
     )))
 }
 
+#[derive(Default)]
+struct DependencyCollector {
+    functions: BTreeSet<String>,
+    macros: BTreeSet<String>,
+}
+
+impl DependencyCollector {
+    /// Fallback for macro bodies that are not expression lists: `name!(...)`.
+    fn scan_macro_tokens(&mut self, tokens: proc_macro2::TokenStream) {
+        let trees: Vec<proc_macro2::TokenTree> = tokens.into_iter().collect();
+        for (idx, tree) in trees.iter().enumerate() {
+            match tree {
+                proc_macro2::TokenTree::Ident(ident) => {
+                    let bang = matches!(trees.get(idx + 1),
+                        Some(proc_macro2::TokenTree::Punct(p)) if p.as_char() == '!');
+                    let args = matches!(trees.get(idx + 2), Some(proc_macro2::TokenTree::Group(_)));
+                    if bang && args {
+                        self.macros.insert(ident.to_string());
+                    }
+                }
+                proc_macro2::TokenTree::Group(group) => self.scan_macro_tokens(group.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for DependencyCollector {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(path) = &*call.func {
+            if let Some(segment) = path.path.segments.last() {
+                self.functions.insert(segment.ident.to_string());
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Some(segment) = node.path.segments.last() {
+            self.macros.insert(segment.ident.to_string());
+        }
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        match syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            Ok(args) => {
+                for arg in args.iter() {
+                    self.visit_expr(arg);
+                }
+            }
+            Err(_) => self.scan_macro_tokens(node.tokens.clone()),
+        }
+    }
+}
+
+/// Names a function calls and the macros it invokes, including calls inside
+/// macro arguments: `{"functions": [...], "macros": [...]}`, each sorted.
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn get_union_definition(source_code: &str, union_name: &str) -> PyResult<String> {
+fn get_function_dependencies(
+    source_code: &str,
+    function_name: &str,
+) -> PyResult<HashMap<String, Vec<String>>> {
+    let ast = parse_src(source_code)?;
+    let Some(function) = ast.items.iter().find_map(|item| match item {
+        syn::Item::Fn(f) if f.sig.ident == function_name => Some(f),
+        _ => None,
+    }) else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' not found",
+            function_name
+        )));
+    };
+
+    let mut collector = DependencyCollector::default();
+    collector.visit_block(&function.block);
+    Ok(HashMap::from([
+        (
+            "functions".to_string(),
+            collector.functions.into_iter().collect(),
+        ),
+        ("macros".to_string(), collector.macros.into_iter().collect()),
+    ]))
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn get_static_item_definition(source_code: &str, item_name: &str) -> PyResult<String> {
     let ast = parse_src(source_code)?;
-    let mut prefix_items: Vec<syn::Item> = Vec::new();
 
     for item in ast.items.iter() {
-        if let syn::Item::Union(s) = item {
-            if s.ident == union_name {
-                let mut items = prefix_items;
-                items.push(syn::Item::Union(s.clone()));
+        if let syn::Item::Static(s) = item {
+            if s.ident == item_name {
                 let file = syn::File {
                     shebang: None,
                     attrs: vec![],
-                    items,
+                    items: vec![syn::Item::Static(s.clone())],
                 };
-                return Ok(prettyplease::unparse(&file));
-            }
-        }
-
-        match item {
-            syn::Item::Use(_) | syn::Item::Type(_) | syn::Item::Const(_) => {
-                prefix_items.push(item.clone())
+                return Ok(unparse(&file));
             }
-            _ => {}
         }
     }
 
     Err(pyo3::exceptions::PyValueError::new_err(format!(
-        "Union '{}' not found",
-        union_name
+        "Static item '{}' not found",
+        item_name
     )))
 }
 
+/// The function, static and type declarations inside `extern` blocks, one
+/// dict per declaration with its `kind`, `name`, `abi` and rendered `declaration`.
+/// Functions also carry `params` (name/type pairs), `return_type` and `variadic`;
+/// statics carry `type` and `mutable`.
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn get_uses_code(code: &str) -> PyResult<Vec<String>> {
+fn extract_extern_declarations(py: Python<'_>, code: &str) -> PyResult<PyObject> {
     let ast = parse_src(code)?;
-    let mut uses = vec![];
+    let result = PyList::empty(py);
+
     for item in ast.items.iter() {
-        if let syn::Item::Use(u) = item {
-            uses.push(quote!(#u).to_string());
+        let syn::Item::ForeignMod(block) = item else {
+            continue;
+        };
+        let abi = block
+            .abi
+            .name
+            .as_ref()
+            .map(|name| name.value())
+            .unwrap_or_else(|| "C".to_string());
+        for foreign in block.items.iter() {
+            let entry = PyDict::new(py);
+            entry.set_item("abi", &abi)?;
+            entry.set_item("declaration", foreign.to_token_stream().to_string())?;
+            match foreign {
+                syn::ForeignItem::Fn(f) => {
+                    let params: Vec<(String, String)> = f
+                        .sig
+                        .inputs
+                        .iter()
+                        .filter_map(|input| match input {
+                            syn::FnArg::Typed(pat_type) => Some((
+                                pat_type.pat.to_token_stream().to_string(),
+                                pat_type.ty.to_token_stream().to_string(),
+                            )),
+                            syn::FnArg::Receiver(_) => None,
+                        })
+                        .collect();
+                    let return_type = match &f.sig.output {
+                        syn::ReturnType::Type(_, ty) => Some(ty.to_token_stream().to_string()),
+                        syn::ReturnType::Default => None,
+                    };
+                    entry.set_item("kind", "fn")?;
+                    entry.set_item("name", f.sig.ident.to_string())?;
+                    entry.set_item("params", params)?;
+                    entry.set_item("return_type", return_type)?;
+                    entry.set_item("variadic", f.sig.variadic.is_some())?;
+                }
+                syn::ForeignItem::Static(s) => {
+                    entry.set_item("kind", "static")?;
+                    entry.set_item("name", s.ident.to_string())?;
+                    entry.set_item("type", s.ty.to_token_stream().to_string())?;
+                    entry.set_item(
+                        "mutable",
+                        matches!(s.mutability, syn::StaticMutability::Mut(_)),
+                    )?;
+                }
+                syn::ForeignItem::Type(t) => {
+                    entry.set_item("kind", "type")?;
+                    entry.set_item("name", t.ident.to_string())?;
+                }
+                _ => continue,
+            }
+            result.append(entry)?;
         }
     }
 
-    Ok(uses)
+    Ok(result.into())
 }
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn get_code_other_than_uses(code: &str) -> PyResult<String> {
-    let ast = parse_src(code)?;
-    let mut code_other_than_uses = String::new();
-    for item in ast.items.iter() {
-        if let syn::Item::Use(_) = item {
-            continue;
-        }
-        code_other_than_uses.push_str(&quote!(#item).to_string());
+fn foreign_item_name(item: &syn::ForeignItem) -> Option<String> {
+    match item {
+        syn::ForeignItem::Fn(f) => Some(f.sig.ident.to_string()),
+        syn::ForeignItem::Static(s) => Some(s.ident.to_string()),
+        syn::ForeignItem::Type(t) => Some(t.ident.to_string()),
+        _ => None,
     }
+}
 
-    Ok(code_other_than_uses)
+/// Removes `extern` blocks, or only the declarations listed in `names`
+/// (e.g. those that now have real implementations); blocks left empty are dropped.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (code, names=None))]
+fn strip_extern_blocks(code: &str, names: Option<Vec<String>>) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    ast.items.retain_mut(|item| {
+        let syn::Item::ForeignMod(block) = item else {
+            return true;
+        };
+        let Some(names) = &names else {
+            return false;
+        };
+        block
+            .items
+            .retain(|foreign| foreign_item_name(foreign).is_none_or(|name| !names.contains(&name)));
+        !block.items.is_empty()
+    });
+    Ok(unparse(&ast))
 }
 
-fn collect_paths(
-    tree: &syn::UseTree,
-    current_path: &mut Vec<String>,
-    all_paths: &mut Vec<Vec<String>>,
-) -> PyResult<()> {
-    match tree {
-        syn::UseTree::Path(path) => {
-            current_path.push(path.ident.to_string());
-            collect_paths(&path.tree, current_path, all_paths)?;
-            current_path.pop();
-        }
-        syn::UseTree::Name(name) => {
-            if name.ident != "self" {
-                current_path.push(name.ident.to_string());
-                all_paths.push(current_path.clone());
-                current_path.pop();
-            } else {
-                // For self, add the current path without pushing 'self'
-                all_paths.push(current_path.clone());
-            }
-        }
-        syn::UseTree::Rename(_) => {
-            return Err(pyo3::exceptions::PyValueError::new_err(
-                "Use statements with 'as' are not supported",
-            ));
-        }
-        syn::UseTree::Glob(_) => {
-            // Handle glob imports
-            current_path.push("*".to_string());
-            all_paths.push(current_path.clone());
-            current_path.pop();
-        }
-        syn::UseTree::Group(group) => {
-            // Handle nested groups like a::{b, c}
-            for tree in &group.items {
-                collect_paths(tree, current_path, all_paths)?;
+fn collect_token_idents(tokens: proc_macro2::TokenStream, out: &mut HashSet<String>) {
+    for tree in tokens {
+        match tree {
+            proc_macro2::TokenTree::Ident(ident) => {
+                out.insert(ident.to_string());
             }
+            proc_macro2::TokenTree::Group(group) => collect_token_idents(group.stream(), out),
+            _ => {}
         }
     }
-    Ok(())
 }
 
-struct UseAliasExpander {
-    aliases: std::collections::HashMap<String, syn::Path>,
+fn item_name(item: &syn::Item) -> Option<String> {
+    let ident = match item {
+        syn::Item::Fn(f) => &f.sig.ident,
+        syn::Item::Struct(s) => &s.ident,
+        syn::Item::Enum(e) => &e.ident,
+        syn::Item::Union(u) => &u.ident,
+        syn::Item::Type(t) => &t.ident,
+        syn::Item::Static(s) => &s.ident,
+        syn::Item::Const(c) => &c.ident,
+        syn::Item::Trait(t) => &t.ident,
+        syn::Item::Macro(m) => m.ident.as_ref()?,
+        _ => return None,
+    };
+    (ident != "_").then(|| ident.to_string())
 }
 
-impl UseAliasExpander {
-    fn new() -> Self {
-        Self {
-            aliases: std::collections::HashMap::new(),
-        }
+fn is_exposed_item(item: &syn::Item) -> bool {
+    let no_mangle =
+        |attrs: &[Attribute]| attrs.iter().any(|attr| attr.path().is_ident("no_mangle"));
+    match item {
+        syn::Item::Fn(f) => f.sig.ident == "main" || f.sig.abi.is_some() || no_mangle(&f.attrs),
+        syn::Item::Static(s) => no_mangle(&s.attrs),
+        _ => false,
     }
+}
 
-    fn collect_aliases(&mut self, file: &syn::File) {
-        for item in &file.items {
-            if let syn::Item::Use(use_item) = item {
-                self.collect_use_tree_aliases(&use_item.tree, &mut Vec::new());
-            }
+/// Drops items not transitively referenced from `roots` (default: `main` and
+/// `#[no_mangle]`/`extern "C"` items). References are found by identifier, so
+/// shadowing keeps an item alive rather than removing a used one. Impl blocks
+/// follow their local self type (or trait), anonymous consts follow the items
+/// they mention, and `use`/`mod`/macro invocations are always kept.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (code, roots=None))]
+fn remove_unreachable_items(code: &str, roots: Option<Vec<String>>) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    let mut defined: HashSet<String> = ast.items.iter().filter_map(item_name).collect();
+    for item in &ast.items {
+        if let syn::Item::ForeignMod(block) = item {
+            defined.extend(block.items.iter().filter_map(foreign_item_name));
         }
     }
+    let references = |tokens: proc_macro2::TokenStream| {
+        let mut idents = HashSet::new();
+        collect_token_idents(tokens, &mut idents);
+        idents.retain(|ident| defined.contains(ident));
+        idents
+    };
 
-    fn collect_use_tree_aliases(&mut self, tree: &syn::UseTree, current_path: &mut Vec<String>) {
-        match tree {
-            syn::UseTree::Path(path) => {
-                current_path.push(path.ident.to_string());
-                self.collect_use_tree_aliases(&path.tree, current_path);
-                current_path.pop();
+    // name -> names referenced by its definitions and by impls anchored on it
+    let mut edges: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut impl_anchors = Vec::new();
+    let mut worklist: Vec<String> = Vec::new();
+    for item in &ast.items {
+        match item {
+            syn::Item::ForeignMod(block) => {
+                for foreign in &block.items {
+                    if let Some(name) = foreign_item_name(foreign) {
+                        let refs = references(foreign.to_token_stream());
+                        edges.entry(name).or_default().extend(refs);
+                    }
+                }
             }
-            syn::UseTree::Rename(rename) => {
-                // Handle self aliases specially
-                if rename.ident == "self" {
-                    // E.g. for `self as collections`, map `collections` to the current path
-                    let full_path = syn::Path {
-                        leading_colon: None,
-                        segments: current_path
-                            .iter()
-                            .map(|s| syn::PathSegment {
-                                ident: syn::Ident::new(s, proc_macro2::Span::call_site()),
-                                arguments: syn::PathArguments::None,
-                            })
-                            .collect(),
-                    };
-                    self.aliases.insert(rename.rename.to_string(), full_path);
-                } else {
-                    current_path.push(rename.ident.to_string());
-
-                    // Create the full path
-                    let full_path = syn::Path {
-                        leading_colon: None,
-                        segments: current_path
-                            .iter()
-                            .map(|s| syn::PathSegment {
-                                ident: syn::Ident::new(s, proc_macro2::Span::call_site()),
-                                arguments: syn::PathArguments::None,
-                            })
-                            .collect(),
-                    };
-
-                    // Store the alias mapping
-                    self.aliases.insert(rename.rename.to_string(), full_path);
-                    current_path.pop();
+            syn::Item::Impl(imp) => {
+                let trait_name = imp
+                    .trait_
+                    .as_ref()
+                    .and_then(|(_, path, _)| path.segments.last())
+                    .map(|segment| segment.ident.to_string());
+                let anchor = type_last_ident(&imp.self_ty)
+                    .filter(|name| defined.contains(name))
+                    .or(trait_name.filter(|name| defined.contains(name)));
+                let refs = references(imp.to_token_stream());
+                match &anchor {
+                    Some(name) => edges.entry(name.clone()).or_default().extend(refs),
+                    None => worklist.extend(refs),
                 }
+                impl_anchors.push(anchor);
             }
-            syn::UseTree::Group(group) => {
-                for tree in &group.items {
-                    self.collect_use_tree_aliases(tree, current_path);
+            syn::Item::Mod(_) | syn::Item::Macro(syn::ItemMacro { ident: None, .. }) => {
+                worklist.extend(references(item.to_token_stream()));
+            }
+            _ => {
+                if let Some(name) = item_name(item) {
+                    let refs = references(item.to_token_stream());
+                    edges.entry(name).or_default().extend(refs);
                 }
             }
-            _ => {} // Handle other cases as needed
         }
     }
-}
-
-impl syn::visit_mut::VisitMut for UseAliasExpander {
-    fn visit_path_mut(&mut self, path: &mut syn::Path) {
-        if let Some(first_segment) = path.segments.first() {
-            let first_ident = first_segment.ident.to_string();
-            if let Some(expanded_path) = self.aliases.get(&first_ident) {
-                // Create new segments starting with the expanded path
-                let mut new_segments = expanded_path.segments.clone();
 
-                // Preserve generic arguments from the original first segment
-                if !first_segment.arguments.is_empty() {
-                    if let Some(last_segment) = new_segments.last_mut() {
-                        last_segment.arguments = first_segment.arguments.clone();
-                    }
-                }
-
-                // If there are remaining segments after the alias, append them
-                if path.segments.len() > 1 {
-                    let remaining_segments: Vec<_> =
-                        path.segments.iter().skip(1).cloned().collect();
-                    new_segments.extend(remaining_segments);
+    match roots {
+        Some(roots) => {
+            for root in roots {
+                if !defined.contains(&root) {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "Root item not found: {}",
+                        root
+                    )));
                 }
-
-                path.segments = new_segments;
+                worklist.push(root);
+            }
+        }
+        None => {
+            let exposed: Vec<String> = ast
+                .items
+                .iter()
+                .filter(|item| is_exposed_item(item))
+                .filter_map(item_name)
+                .collect();
+            if exposed.is_empty() {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "No root items found: expected `main` or exposed extern items",
+                ));
             }
+            worklist.extend(exposed);
         }
+    }
 
-        // Continue visiting nested paths
-        syn::visit_mut::visit_path_mut(self, path);
+    let mut reachable = HashSet::new();
+    while let Some(name) = worklist.pop() {
+        if !reachable.insert(name.clone()) {
+            continue;
+        }
+        if let Some(refs) = edges.get(&name) {
+            worklist.extend(refs.iter().filter(|r| !reachable.contains(*r)).cloned());
+        }
     }
 
-    fn visit_use_tree_mut(&mut self, tree: &mut syn::UseTree) {
-        // Remove alias use statements and convert them to regular use statements
-        if let syn::UseTree::Rename(rename) = tree {
-            // Replace rename with regular name
-            *tree = syn::UseTree::Name(syn::UseName {
-                ident: rename.ident.clone(),
+    let mut impl_anchors = impl_anchors.into_iter();
+    ast.items.retain_mut(|item| match item {
+        syn::Item::Impl(_) => impl_anchors
+            .next()
+            .flatten()
+            .is_none_or(|anchor| reachable.contains(&anchor)),
+        syn::Item::ForeignMod(block) => {
+            block.items.retain(|foreign| {
+                foreign_item_name(foreign).is_none_or(|name| reachable.contains(&name))
             });
+            !block.items.is_empty()
+        }
+        syn::Item::Const(c) if c.ident == "_" => {
+            let refs = references(c.to_token_stream());
+            refs.is_empty() || refs.iter().any(|name| reachable.contains(name))
         }
+        _ => item_name(item).is_none_or(|name| reachable.contains(&name)),
+    });
+    Ok(unparse(&ast))
+}
 
-        // Continue visiting
-        syn::visit_mut::visit_use_tree_mut(self, tree);
+/// Turns a file or directory name into a module identifier.
+fn module_ident(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    // keywords such as `type.c` or `mod.c`
+    if parse_str::<syn::Ident>(&ident).is_err() {
+        ident.push('_');
     }
+    ident
 }
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn expand_use_aliases(code: &str) -> PyResult<String> {
-    use std::panic;
-    let res = panic::catch_unwind(|| {
-        let mut ast: File = parse_src(code)?;
-        let mut expander = UseAliasExpander::new();
-        // First pass: collect all aliases
-        expander.collect_aliases(&ast);
-
-        // Second pass: expand all usages
-        expander.visit_file_mut(&mut ast);
-
-        Ok(prettyplease::unparse(&ast))
-    });
-    match res {
-        Ok(inner_res) => inner_res,
-        Err(e) => if let Some(msg) = e.downcast_ref::<&str>() {
-            Err(format!("Error when expand_use_aliases: {}", msg))
-        } else if let Some(msg) = e.downcast_ref::<String>() {
-            Err(format!("Error when expand_use_aliases: {}", msg))
-        } else {
-            Err("Error when expand_use_aliases.".to_string())
-        }
-        .map_err(|msg| pyo3::exceptions::PySyntaxError::new_err(msg)),
+/// Module path mirroring a C source path: `src/util/list.c` -> `util::list`.
+fn c_path_to_module(path: &str) -> Vec<String> {
+    let mut parts: Vec<&str> = path
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    if parts.len() > 1 && parts[0] == "src" {
+        parts.remove(0);
     }
+    let file = parts.pop().unwrap_or_default();
+    let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+    parts.into_iter().chain([stem]).map(module_ident).collect()
 }
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn get_standalone_uses_code_paths(code: &str) -> PyResult<Vec<Vec<String>>> {
-    let ast = parse_src(code)?;
-    let mut all_paths = Vec::new();
+fn item_vis_mut(item: &mut syn::Item) -> Option<&mut syn::Visibility> {
+    match item {
+        syn::Item::Fn(f) => Some(&mut f.vis),
+        syn::Item::Struct(s) => Some(&mut s.vis),
+        syn::Item::Enum(e) => Some(&mut e.vis),
+        syn::Item::Union(u) => Some(&mut u.vis),
+        syn::Item::Type(t) => Some(&mut t.vis),
+        syn::Item::Static(s) => Some(&mut s.vis),
+        syn::Item::Const(c) => Some(&mut c.vis),
+        syn::Item::Trait(t) => Some(&mut t.vis),
+        _ => None,
+    }
+}
 
-    for item in ast.items.iter() {
-        if let syn::Item::Use(use_item) = item {
-            collect_paths(&use_item.tree, &mut Vec::new(), &mut all_paths)?;
+/// Makes an item (and, for records, its fields) visible to sibling modules.
+fn make_item_pub(item: &mut syn::Item) {
+    let fields = match item {
+        syn::Item::Struct(s) => Some(s.fields.iter_mut().collect::<Vec<_>>()),
+        syn::Item::Union(u) => Some(u.fields.named.iter_mut().collect()),
+        _ => None,
+    };
+    for field in fields.into_iter().flatten() {
+        if matches!(field.vis, syn::Visibility::Inherited) {
+            field.vis = parse_quote!(pub);
+        }
+    }
+    if let Some(vis) = item_vis_mut(item) {
+        if matches!(vis, syn::Visibility::Inherited) {
+            *vis = parse_quote!(pub);
         }
     }
-
-    Ok(all_paths)
 }
 
-enum RenameModifier {
-    Function,
-    StructUnion,
+/// Parses a map from C source paths to translated Rust code, ordered by path.
+fn parse_files(files: HashMap<String, String>) -> PyResult<Vec<(String, File)>> {
+    let files: BTreeMap<String, String> = files.into_iter().collect();
+    files
+        .into_iter()
+        .map(|(path, code)| Ok((path, parse_src(&code)?)))
+        .collect()
 }
 
-struct RenameVisitor {
-    old_name: String,
-    new_name: String,
-    modifer: RenameModifier,
+#[derive(Default)]
+struct ModuleNode {
+    attrs: Vec<Attribute>,
+    items: Vec<syn::Item>,
+    children: BTreeMap<String, ModuleNode>,
 }
 
-impl syn::visit_mut::VisitMut for RenameVisitor {
-    fn visit_item_fn_mut(&mut self, item_fn: &mut syn::ItemFn) {
-        if let RenameModifier::Function = self.modifer {
-            // rename definition
-            if item_fn.sig.ident == self.old_name {
-                item_fn.sig.ident = syn::Ident::new(&self.new_name, item_fn.sig.ident.span());
-            }
+impl ModuleNode {
+    fn emit(mut self, file_path: String, dir: &str, out: &mut HashMap<String, String>) {
+        let decls = self.children.keys().map(|name| {
+            let ident = syn::Ident::new(name, Span::call_site());
+            parse_quote!(pub mod #ident;)
+        });
+        self.items.splice(0..0, decls.collect::<Vec<syn::Item>>());
+        for (name, child) in self.children {
+            let (child_file, child_dir) = if child.children.is_empty() {
+                (format!("{}{}.rs", dir, name), String::new())
+            } else {
+                (
+                    format!("{}{}/mod.rs", dir, name),
+                    format!("{}{}/", dir, name),
+                )
+            };
+            child.emit(child_file, &child_dir, out);
         }
-        syn::visit_mut::visit_item_fn_mut(self, item_fn);
+        let file = File {
+            shebang: None,
+            attrs: self.attrs,
+            items: self.items,
+        };
+        out.insert(file_path, unparse(&file));
     }
+}
 
-    fn visit_item_struct_mut(&mut self, item_struct: &mut syn::ItemStruct) {
-        if let RenameModifier::StructUnion = self.modifer {
-            // rename definition
-            if item_struct.ident == self.old_name {
-                item_struct.ident = syn::Ident::new(&self.new_name, item_struct.ident.span());
-            }
+/// Lays translated files out as a module tree mirroring their C paths.
+/// `files` maps C source paths to Rust code; the result maps crate-relative
+/// paths (`main.rs` when a file defines `main`, otherwise `lib.rs`, plus one
+/// file per module) to code. Names defined in another file are imported with
+/// `use crate::module::name;`, `extern` declarations they replace are dropped,
+/// and imported items are made `pub`. When several files define a name, the
+/// first by path owns it.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn combine_as_modules(files: HashMap<String, String>) -> PyResult<HashMap<String, String>> {
+    let mut units: Vec<(Vec<String>, File)> = Vec::new();
+    let mut module_sources: HashMap<Vec<String>, String> = HashMap::new();
+    for (path, ast) in parse_files(files)? {
+        let module = c_path_to_module(&path);
+        if let Some(other) = module_sources.insert(module.clone(), path.clone()) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Files {} and {} map to the same module",
+                other, path
+            )));
         }
+        units.push((module, ast));
+    }
 
-        syn::visit_mut::visit_item_struct_mut(self, item_struct);
+    // the file defining `main` becomes the crate root
+    let entries: Vec<usize> = units
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, ast))| {
+            ast.items
+                .iter()
+                .any(|item| matches!(item, syn::Item::Fn(f) if f.sig.ident == "main"))
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    if entries.len() > 1 {
+        let paths: Vec<String> = entries
+            .iter()
+            .map(|idx| module_sources[&units[*idx].0].clone())
+            .collect();
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Multiple files define `main`: {}",
+            paths.join(", ")
+        )));
+    }
+    let has_entry = !entries.is_empty();
+    if let Some(idx) = entries.first() {
+        units[*idx].0.clear();
     }
 
-    fn visit_item_union_mut(&mut self, item_union: &mut syn::ItemUnion) {
-        if let RenameModifier::StructUnion = self.modifer {
-            // rename definition
-            if item_union.ident == self.old_name {
-                item_union.ident = syn::Ident::new(&self.new_name, item_union.ident.span());
-            }
+    let mut owners: HashMap<String, Vec<String>> = HashMap::new();
+    for (module, ast) in &units {
+        for name in ast.items.iter().filter_map(item_name) {
+            owners.entry(name).or_insert_with(|| module.clone());
         }
-
-        syn::visit_mut::visit_item_union_mut(self, item_union);
     }
 
-    fn visit_path_mut(&mut self, path: &mut syn::Path) {
-        if let Some(ident) = path.get_ident() {
-            if ident == self.old_name.as_str() {
-                path.segments.last_mut().unwrap().ident =
-                    syn::Ident::new(&self.new_name, ident.span());
+    let mut exported: HashSet<(Vec<String>, String)> = HashSet::new();
+    for (module, ast) in units.iter_mut() {
+        let owned_elsewhere = |name: &String| owners.get(name).is_some_and(|owner| owner != module);
+        ast.items.retain_mut(|item| {
+            let syn::Item::ForeignMod(block) = item else {
+                return true;
+            };
+            block
+                .items
+                .retain(|foreign| foreign_item_name(foreign).is_none_or(|n| !owned_elsewhere(&n)));
+            !block.items.is_empty()
+        });
+
+        let local: HashSet<String> = ast.items.iter().filter_map(item_name).collect();
+        let mut referenced = HashSet::new();
+        let mut already_imported = HashSet::new();
+        for item in &ast.items {
+            match item {
+                syn::Item::Use(item_use) => {
+                    collect_use_idents(&item_use.tree, &mut already_imported)
+                }
+                _ => collect_token_idents(item.to_token_stream(), &mut referenced),
             }
         }
+        let mut imports: Vec<(Vec<String>, String)> = referenced
+            .into_iter()
+            .filter(|name| !local.contains(name) && !already_imported.contains(name))
+            .filter(|name| owned_elsewhere(name))
+            .map(|name| (owners[&name].clone(), name))
+            .collect();
+        imports.sort();
+        let uses: Vec<syn::Item> = imports
+            .iter()
+            .map(|(owner, name)| {
+                let segments = owner
+                    .iter()
+                    .chain([name])
+                    .map(|segment| syn::Ident::new(segment, Span::call_site()));
+                parse_quote!(use crate::#(#segments)::*;)
+            })
+            .collect();
+        ast.items.splice(0..0, uses);
+        exported.extend(imports);
+    }
 
-        syn::visit_mut::visit_path_mut(self, path);
+    let mut root = ModuleNode::default();
+    for (module, ast) in units {
+        let mut items = ast.items;
+        for item in items.iter_mut() {
+            if item_name(item).is_some_and(|name| exported.contains(&(module.clone(), name))) {
+                make_item_pub(item);
+            }
+        }
+        let node = module.into_iter().fold(&mut root, |node, segment| {
+            node.children.entry(segment).or_default()
+        });
+        node.attrs.extend(ast.attrs);
+        node.items.extend(items);
     }
+
+    let mut out = HashMap::new();
+    let root_file = if has_entry { "main.rs" } else { "lib.rs" };
+    root.emit(root_file.to_string(), "", &mut out);
+    Ok(out)
 }
 
-// Need to rename both function definition and function calls
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn rename_function(code: &str, old_name: &str, new_name: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-    // Create and run our visitor
-    let mut visitor = RenameVisitor {
-        old_name: old_name.to_string(),
-        new_name: new_name.to_string(),
-        modifer: RenameModifier::Function,
-    };
-    visitor.visit_file_mut(&mut ast);
+/// Module path of a file emitted by `combine_as_modules`:
+/// `main.rs`/`lib.rs` -> root, `util/mod.rs` -> `util`, `util/list.rs` -> `util::list`.
+fn module_of_file(path: &str) -> Vec<String> {
+    let stem = path.strip_suffix(".rs").unwrap_or(path);
+    let mut parts: Vec<String> = stem.split('/').map(str::to_string).collect();
+    let crate_root = parts.len() == 1 && matches!(parts[0].as_str(), "main" | "lib");
+    if crate_root || parts.last().is_some_and(|part| part == "mod") {
+        parts.pop();
+    }
+    parts
+}
 
-    // Return the modified source code
-    Ok(prettyplease::unparse(&ast))
+fn parse_module_path(module: &str) -> Vec<String> {
+    module
+        .split("::")
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .skip_while(|segment| *segment == "crate")
+        .map(str::to_string)
+        .collect()
 }
-//
-// Need to rename both function definition and function calls
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn rename_struct_union(code: &str, old_name: &str, new_name: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-    // Create and run our visitor
-    let mut visitor = RenameVisitor {
-        old_name: old_name.to_string(),
-        new_name: new_name.to_string(),
-        modifer: RenameModifier::StructUnion,
-    };
-    visitor.visit_file_mut(&mut ast);
 
-    // Return the modified source code
-    Ok(prettyplease::unparse(&ast))
+fn crate_path(module: &[String], name: &str) -> Vec<String> {
+    std::iter::once("crate".to_string())
+        .chain(module.iter().cloned())
+        .chain([name.to_string()])
+        .collect()
 }
 
-struct TokenCounter {
-    total_tokens: usize,
-    unsafe_tokens: usize,
+fn use_item_for(path: &[String], rename: Option<&syn::Ident>) -> syn::Item {
+    let segments = path
+        .iter()
+        .map(|segment| syn::Ident::new(segment, Span::call_site()));
+    match rename {
+        Some(alias) => parse_quote!(use #(#segments)::* as #alias;),
+        None => parse_quote!(use #(#segments)::*;),
+    }
 }
 
-fn count_tokens(tokens: proc_macro2::TokenStream) -> usize {
-    let mut total = 0;
-    for token in tokens.into_iter() {
-        match &token {
-            proc_macro2::TokenTree::Group(group) => {
-                total += count_tokens(group.stream());
-            }
-            _ => {
-                total += 1;
+/// Removes the leaves importing exactly `target` from a use tree, returning
+/// their `as` aliases (if any) and whether the remaining tree is empty.
+fn extract_use_leaves(
+    tree: &mut syn::UseTree,
+    target: &[String],
+) -> (Vec<Option<syn::Ident>>, bool) {
+    match tree {
+        syn::UseTree::Path(path) if target.len() > 1 && path.ident == target[0] => {
+            extract_use_leaves(&mut path.tree, &target[1..])
+        }
+        syn::UseTree::Name(name) if target.len() == 1 && name.ident == target[0] => {
+            (vec![None], true)
+        }
+        syn::UseTree::Rename(rename) if target.len() == 1 && rename.ident == target[0] => {
+            (vec![Some(rename.rename.clone())], true)
+        }
+        syn::UseTree::Group(group) => {
+            let mut found = Vec::new();
+            let items = mem::take(&mut group.items);
+            for mut item in items {
+                let (leaves, empty) = extract_use_leaves(&mut item, target);
+                found.extend(leaves);
+                if !empty {
+                    group.items.push(item);
+                }
             }
+            let empty = group.items.is_empty();
+            (found, empty)
         }
+        _ => (Vec::new(), false),
     }
-    total
 }
 
-impl syn::visit_mut::VisitMut for TokenCounter {
-    fn visit_item_fn_mut(&mut self, func: &mut syn::ItemFn) {
-        if func.sig.unsafety.is_some() {
-            let unsafe_tokens = count_tokens(func.block.to_token_stream());
-            self.unsafe_tokens += unsafe_tokens;
-            self.total_tokens += unsafe_tokens;
-        } else {
-            self.total_tokens += count_tokens(func.block.to_token_stream());
-            syn::visit_mut::visit_item_fn_mut(self, func);
+fn has_glob_import(tree: &syn::UseTree, target: &[String]) -> bool {
+    match tree {
+        syn::UseTree::Path(path) => {
+            target.first().is_some_and(|first| path.ident == first)
+                && has_glob_import(&path.tree, &target[1..])
         }
+        syn::UseTree::Glob(_) => target.is_empty(),
+        syn::UseTree::Group(group) => group.items.iter().any(|item| has_glob_import(item, target)),
+        _ => false,
     }
+}
 
-    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
-        if let syn::Expr::Unsafe(unsafe_expr) = expr {
-            let unsafe_tokens = count_tokens(unsafe_expr.block.to_token_stream());
-            self.unsafe_tokens += unsafe_tokens;
-        } else {
-            syn::visit_mut::visit_expr_mut(self, expr);
+/// Number of `use`/`mod` declarations heading a file, where new imports go.
+fn leading_use_count(items: &[syn::Item]) -> usize {
+    items
+        .iter()
+        .take_while(|item| matches!(item, syn::Item::Use(_) | syn::Item::Mod(_)))
+        .count()
+}
+
+/// Rewrites `crate::<from>::<name>...` paths to `crate::<to>::<name>...`.
+struct ModulePathRewriter<'a> {
+    from: &'a [String],
+    to: &'a [String],
+}
+
+impl VisitMut for ModulePathRewriter<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        visit_mut::visit_path_mut(self, path);
+        let prefix = self.from.len();
+        if path.segments.len() <= prefix || path.segments[0].ident != "crate" {
+            return;
+        }
+        let matches = path
+            .segments
+            .iter()
+            .take(prefix)
+            .map(|segment| segment.ident.to_string())
+            .eq(self.from.iter().cloned());
+        if !matches {
+            return;
         }
+        let mut segments: syn::punctuated::Punctuated<syn::PathSegment, Token![::]> = self
+            .to
+            .iter()
+            .map(|segment| syn::PathSegment::from(syn::Ident::new(segment, Span::call_site())))
+            .collect();
+        segments.extend(path.segments.iter().skip(prefix).cloned());
+        path.segments = segments;
     }
 }
 
+/// Moves the item `item_name` (with impl blocks on it) from one module of a
+/// `combine_as_modules` layout to another. `crate::` paths and imports that
+/// named the item are redirected, the old module imports it if it still
+/// refers to it, and the moved code imports the names it used from the old
+/// module. Modules are given as paths such as `util::list` or `crate`.
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn count_unsafe_tokens(code: &str) -> PyResult<(usize, usize)> {
-    let mut ast = parse_src(code)?;
-    let mut counter = TokenCounter {
-        total_tokens: 0,
-        unsafe_tokens: 0,
+fn move_item_to_module(
+    code_map: HashMap<String, String>,
+    item_name: &str,
+    from_module: &str,
+    to_module: &str,
+) -> PyResult<HashMap<String, String>> {
+    let from = parse_module_path(from_module);
+    let to = parse_module_path(to_module);
+    let mut files: BTreeMap<String, File> = BTreeMap::new();
+    for (path, code) in &code_map {
+        files.insert(path.clone(), parse_src(code)?);
+    }
+    let find_file = |module: &[String], label: &str| {
+        files
+            .keys()
+            .find(|path| module_of_file(path) == module)
+            .cloned()
+            .ok_or_else(|| {
+                pyo3::exceptions::PyValueError::new_err(format!("Module not found: {}", label))
+            })
     };
-    counter.visit_file_mut(&mut ast);
-    Ok((counter.total_tokens, counter.unsafe_tokens))
-}
-
-pub struct ParsedAttribute(pub Attribute);
-
-impl Parse for ParsedAttribute {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let pound_token: token::Pound = input.parse()?;
-
-        // Determine the style of the attribute (Outer or Inner)
-        let style = if input.peek(token::Bracket) {
-            AttrStyle::Outer
-        } else if input.peek(Token![!]) {
-            input.parse::<Token![!]>()?;
-            AttrStyle::Inner(Token![!](input.span()))
-        } else {
-            return Err(input.error("Expected attribute brackets"));
-        };
-
-        // Parse the bracketed contents
-        let content;
-        let bracket_token = syn::bracketed!(content in input);
-        let meta: Meta = content.parse()?;
-
-        let attribute = Attribute {
-            pound_token,
-            style,
-            bracket_token,
-            meta,
-        };
+    let from_file = find_file(&from, from_module)?;
+    let to_file = find_file(&to, to_module)?;
+    if from_file == to_file {
+        return Ok(code_map);
+    }
+    let old_path = crate_path(&from, item_name);
+    let new_path = crate_path(&to, item_name);
 
-        Ok(ParsedAttribute(attribute))
+    let is_moved = |item: &syn::Item| match item {
+        syn::Item::Impl(imp) => type_last_ident(&imp.self_ty).is_some_and(|name| name == item_name),
+        _ => crate::item_name(item).is_some_and(|name| name == item_name),
+    };
+    let source = files.get_mut(&from_file).expect("file was found above");
+    let (mut moved, kept): (Vec<syn::Item>, Vec<syn::Item>) = mem::take(&mut source.items)
+        .into_iter()
+        .partition(|item| is_moved(item));
+    source.items = kept;
+    if !moved.iter().any(|item| crate::item_name(item).is_some()) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Item {} not found in module {}",
+            item_name, from_module
+        )));
+    }
+    if files[&to_file]
+        .items
+        .iter()
+        .filter_map(crate::item_name)
+        .any(|name| name == item_name)
+    {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Module {} already defines {}",
+            to_module, item_name
+        )));
     }
-}
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn add_attr_to_function(code: &str, function_name: &str, attr: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-    for item in ast.items.iter_mut() {
-        if let syn::Item::Fn(f) = item {
-            if f.sig.ident == function_name {
-                let parsed = parse_str::<ParsedAttribute>(attr).map_err(|e| {
-                    pyo3::exceptions::PySyntaxError::new_err(format!(
-                        "Parse error: {}\n source code: {}",
-                        e, attr
-                    ))
-                })?;
-                let attr = parsed.0;
-                // check if the attribute is already present
-                for existing_attr in f.attrs.iter() {
-                    if existing_attr.to_token_stream().to_string()
-                        == attr.to_token_stream().to_string()
-                    {
-                        return Ok(prettyplease::unparse(&ast));
-                    }
+    // names the moved code used from its old module: local items and imports
+    let mut used = HashSet::new();
+    for item in &moved {
+        collect_token_idents(item.to_token_stream(), &mut used);
+    }
+    let source = &files[&from_file];
+    let mut carried: Vec<syn::Item> = Vec::new();
+    let mut exported: HashSet<String> = HashSet::new();
+    for item in &source.items {
+        match item {
+            syn::Item::Use(item_use) => {
+                let mut names = HashSet::new();
+                collect_use_idents(&item_use.tree, &mut names);
+                if names.iter().any(|name| used.contains(name)) {
+                    carried.push(item.clone());
+                }
+            }
+            _ => {
+                if let Some(name) = crate::item_name(item).filter(|name| used.contains(name)) {
+                    carried.push(use_item_for(&crate_path(&from, &name), None));
+                    exported.insert(name);
                 }
-
-                f.attrs.push(attr);
             }
         }
     }
-    Ok(prettyplease::unparse(&ast))
-}
-
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn add_attr_to_struct_union(code: &str, struct_union_name: &str, attr: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-
-    fn add_attribute(attrs: &mut Vec<syn::Attribute>, attr: &str) -> PyResult<()> {
-        let parsed = parse_str::<ParsedAttribute>(attr).map_err(|e| {
-            pyo3::exceptions::PySyntaxError::new_err(format!(
-                "Parse error: {}\n source code: {}",
-                e, attr
-            ))
-        })?;
-        let attr = parsed.0;
-
-        // Check if the attribute is already present
-        if attrs.iter().any(|existing| {
-            existing.to_token_stream().to_string() == attr.to_token_stream().to_string()
-        }) {
-            return Ok(());
+    let source = files.get_mut(&from_file).expect("file was found above");
+    for item in source.items.iter_mut() {
+        if crate::item_name(item).is_some_and(|name| exported.contains(&name)) {
+            make_item_pub(item);
         }
-
-        attrs.push(attr);
-        Ok(())
+    }
+    for item in moved.iter_mut() {
+        make_item_pub(item);
     }
 
-    for item in ast.items.iter_mut() {
-        if let syn::Item::Struct(s) = item {
-            if s.ident == struct_union_name {
-                add_attribute(&mut s.attrs, attr)?;
+    let mut rewriter = ModulePathRewriter {
+        from: &old_path[..old_path.len() - 1],
+        to: &new_path[..new_path.len() - 1],
+    };
+    for (path, ast) in files.iter_mut() {
+        let mut aliases = Vec::new();
+        ast.items.retain_mut(|item| {
+            let syn::Item::Use(item_use) = item else {
+                return true;
+            };
+            let (found, empty) = extract_use_leaves(&mut item_use.tree, &old_path);
+            aliases.extend(found);
+            !empty
+        });
+        let is_target = *path == to_file;
+        let glob_import = ast.items.iter().any(
+            |item| matches!(item, syn::Item::Use(u) if has_glob_import(&u.tree, &old_path[..old_path.len() - 1])),
+        );
+        if !is_target && (*path == from_file || glob_import) {
+            let mut referenced = HashSet::new();
+            for item in ast
+                .items
+                .iter()
+                .filter(|item| !matches!(item, syn::Item::Use(_)))
+            {
+                collect_token_idents(item.to_token_stream(), &mut referenced);
             }
-        } else if let syn::Item::Union(u) = item {
-            if u.ident == struct_union_name {
-                add_attribute(&mut u.attrs, attr)?;
+            if referenced.contains(item_name) && !aliases.contains(&None) {
+                aliases.push(None);
             }
         }
+        rewriter.visit_file_mut(ast);
+        if is_target {
+            continue;
+        }
+        let mut seen = HashSet::new();
+        let imports: Vec<syn::Item> = aliases
+            .iter()
+            .filter(|alias| seen.insert(alias.as_ref().map(|ident| ident.to_string())))
+            .map(|alias| use_item_for(&new_path, alias.as_ref()))
+            .collect();
+        let insert_at = leading_use_count(&ast.items);
+        ast.items.splice(insert_at..insert_at, imports);
     }
 
-    Ok(prettyplease::unparse(&ast))
+    let target = files.get_mut(&to_file).expect("file was found above");
+    let existing_uses = target
+        .items
+        .iter()
+        .filter(|item| matches!(item, syn::Item::Use(_)))
+        .map(|item| item.to_token_stream().to_string())
+        .collect::<HashSet<_>>();
+    carried.retain(|item| !existing_uses.contains(&item.to_token_stream().to_string()));
+    let insert_at = leading_use_count(&target.items);
+    for item in moved.iter_mut() {
+        rewriter.visit_item_mut(item);
+    }
+    target
+        .items
+        .splice(insert_at..insert_at, carried.into_iter().chain(moved));
+
+    Ok(files
+        .into_iter()
+        .map(|(path, ast)| (path, unparse(&ast)))
+        .collect())
 }
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn add_derive_to_struct_union(
-    code: &str,
-    struct_union_name: &str,
-    derive: &str,
-) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-
-    fn add_derive(
-        attrs: &mut Vec<syn::Attribute>,
-        derive: &str,
-        span: proc_macro2::Span,
-    ) -> PyResult<()> {
-        let mut existing_derive = None;
-
-        // Check for existing derive attribute
-        for attr in attrs.iter_mut() {
-            if let syn::Meta::List(list) = &mut attr.meta {
-                if list.path.is_ident("derive") {
-                    existing_derive = Some(list);
-                    break;
-                }
-            }
-        }
+/// Token string of one definition and the indices of the files holding it.
+type DefinitionVariant = (String, Vec<usize>);
+
+fn collision_kind(item: &syn::Item) -> Option<(&'static str, &syn::Ident)> {
+    match item {
+        syn::Item::Fn(f) => Some(("function", &f.sig.ident)),
+        syn::Item::Static(s) => Some(("static", &s.ident)),
+        syn::Item::Struct(s) => Some(("type", &s.ident)),
+        syn::Item::Enum(e) => Some(("type", &e.ident)),
+        syn::Item::Union(u) => Some(("type", &u.ident)),
+        syn::Item::Type(t) => Some(("type", &t.ident)),
+        _ => None,
+    }
+}
 
-        if let Some(existing_derive) = existing_derive {
-            // Check if derive is already present
-            let mut found = false;
-            existing_derive
-                .parse_nested_meta(|meta| {
-                    if meta.path.is_ident(derive) {
-                        found = true;
-                    }
-                    Ok(())
-                })
-                .map_err(|e| {
-                    pyo3::exceptions::PySyntaxError::new_err(format!(
-                        "Parse error: {}\n source code: {}",
-                        e, derive
-                    ))
-                })?;
+fn rename_definition(item: &mut syn::Item, old_name: &str, new_name: &str) {
+    let ident = match item {
+        syn::Item::Fn(f) => &mut f.sig.ident,
+        syn::Item::Static(s) => &mut s.ident,
+        syn::Item::Struct(s) => &mut s.ident,
+        syn::Item::Enum(e) => &mut e.ident,
+        syn::Item::Union(u) => &mut u.ident,
+        syn::Item::Type(t) => &mut t.ident,
+        _ => return,
+    };
+    if ident == old_name {
+        *ident = syn::Ident::new(new_name, ident.span());
+    }
+}
 
-            if !found {
-                let current_derive_tokens = existing_derive.tokens.clone();
-                let ident = syn::Ident::new(derive, span);
-                existing_derive.tokens = quote! { #current_derive_tokens, #ident };
+/// Reports functions, statics and types that several translated files define
+/// differently, e.g. C `static` helpers sharing a name. Identical definitions
+/// (such as a struct from a shared header) are not collisions. `files` maps
+/// C paths to Rust code; the result is `{"collisions": [{"name", "kind",
+/// "files", "renamed"}], "files": {path: code}}`. With `rename`, the definition
+/// in the first file (by path) keeps its name, the others get a `_<module>`
+/// suffix (`helper` in `util/list.c` -> `helper_util_list`) and references in
+/// their files, macro arguments included, are updated.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (files, rename=false))]
+fn detect_symbol_collisions(
+    py: Python<'_>,
+    files: HashMap<String, String>,
+    rename: bool,
+) -> PyResult<PyObject> {
+    let mut files = parse_files(files)?;
+
+    // (kind, name) -> distinct definitions with the files defining each
+    let mut definitions: BTreeMap<(&'static str, String), Vec<DefinitionVariant>> = BTreeMap::new();
+    for (idx, (_, ast)) in files.iter().enumerate() {
+        for item in &ast.items {
+            let Some((kind, ident)) = collision_kind(item) else {
+                continue;
+            };
+            let tokens = item.to_token_stream().to_string();
+            let variants = definitions.entry((kind, ident.to_string())).or_default();
+            match variants
+                .iter_mut()
+                .find(|(existing, _)| *existing == tokens)
+            {
+                Some((_, owners)) if !owners.contains(&idx) => owners.push(idx),
+                Some(_) => {}
+                None => variants.push((tokens, vec![idx])),
             }
-        } else {
-            // Add new derive attribute
-            let ident = syn::Ident::new(derive, span);
-            attrs.push(parse_quote!(#[derive(#ident)]));
         }
-
-        Ok(())
     }
 
-    for item in ast.items.iter_mut() {
-        match item {
-            syn::Item::Struct(s) if s.ident == struct_union_name => {
-                let span = s.span();
-                add_derive(&mut s.attrs, derive, span)?;
-            }
-            syn::Item::Union(u) if u.ident == struct_union_name => {
-                let span = u.span();
-                add_derive(&mut u.attrs, derive, span)?;
+    let collisions = PyList::empty(py);
+    for ((kind, name), variants) in definitions {
+        if variants.len() < 2 {
+            continue;
+        }
+        let mut owners: Vec<usize> = variants
+            .iter()
+            .flat_map(|(_, owners)| owners.clone())
+            .collect();
+        owners.sort();
+        let renamed = PyDict::new(py);
+        if rename {
+            for (_, variant_owners) in variants.iter().filter(|(_, o)| !o.contains(&owners[0])) {
+                for idx in variant_owners {
+                    let (path, ast) = &mut files[*idx];
+                    let new_name = format!("{}_{}", name, c_path_to_module(path).join("_"));
+                    for item in ast.items.iter_mut() {
+                        rename_definition(item, &name, &new_name);
+                    }
+                    RenameVisitor {
+                        old_name: name.clone(),
+                        new_name: new_name.clone(),
+                        modifer: RenameModifier::Function,
+                    }
+                    .visit_file_mut(ast);
+                    renamed.set_item(path.as_str(), new_name)?;
+                }
             }
-            _ => {}
         }
+        let entry = PyDict::new(py);
+        entry.set_item("name", &name)?;
+        entry.set_item("kind", kind)?;
+        let paths: Vec<&str> = owners.iter().map(|idx| files[*idx].0.as_str()).collect();
+        entry.set_item("files", paths)?;
+        entry.set_item("renamed", renamed)?;
+        collisions.append(entry)?;
     }
 
-    Ok(prettyplease::unparse(&ast))
+    let code = PyDict::new(py);
+    for (path, ast) in &files {
+        code.set_item(path.as_str(), unparse(ast))?;
+    }
+    let result = PyDict::new(py);
+    result.set_item("collisions", collisions)?;
+    result.set_item("files", code)?;
+    Ok(result.into())
 }
 
-/// A visitor that traverses the AST and replaces libc scalar types with Rust primitives.
-struct LibcTypeVisitor;
-
-impl VisitMut for LibcTypeVisitor {
-    /// This method is called for every type path in the source code.
-    fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
-        // We are looking for paths with exactly two segments, like `libc::c_int`.
-        if type_path.qself.is_none() && type_path.path.segments.len() == 2 {
-            let first_segment = &type_path.path.segments[0];
-
-            // Check if the first segment is `libc`.
-            if first_segment.ident == "libc" && first_segment.arguments.is_none() {
-                let second_segment = &type_path.path.segments[1];
-                let libc_type_name = second_segment.ident.to_string();
-
-                // If the second segment is a libc type we can replace...
-                if let Some(rust_type_str) = map_libc_scalar(&libc_type_name) {
-                    // Create a new identifier for the Rust type.
-                    let new_rust_type_ident =
-                        syn::Ident::new(rust_type_str, second_segment.ident.span());
-
-                    // Create a new path from this single identifier.
-                    let new_path: syn::Path = new_rust_type_ident.into();
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn get_union_definition(source_code: &str, union_name: &str) -> PyResult<String> {
+    let ast = parse_src(source_code)?;
+    let mut prefix_items: Vec<syn::Item> = Vec::new();
 
-                    // Replace the old path (`libc::c_int`) with the new one (`i32`).
-                    type_path.path = new_path;
-                }
+    for item in ast.items.iter() {
+        if let syn::Item::Union(s) = item {
+            if s.ident == union_name {
+                let mut items = prefix_items;
+                items.push(syn::Item::Union(s.clone()));
+                let file = syn::File {
+                    shebang: None,
+                    attrs: vec![],
+                    items,
+                };
+                return Ok(unparse(&file));
             }
         }
 
-        // Continue traversing the rest of the AST to find other types.
-        syn::visit_mut::visit_type_path_mut(self, type_path);
+        match item {
+            syn::Item::Use(_) | syn::Item::Type(_) | syn::Item::Const(_) => {
+                prefix_items.push(item.clone())
+            }
+            _ => {}
+        }
     }
+
+    Err(pyo3::exceptions::PyValueError::new_err(format!(
+        "Union '{}' not found",
+        union_name
+    )))
 }
 
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn replace_libc_numeric_types_to_rust_primitive_types(code: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-    let mut visitor = LibcTypeVisitor;
-    visitor.visit_file_mut(&mut ast);
+fn get_uses_code(code: &str) -> PyResult<Vec<String>> {
+    let ast = parse_src(code)?;
+    let mut uses = vec![];
+    for item in ast.items.iter() {
+        if let syn::Item::Use(u) = item {
+            uses.push(quote!(#u).to_string());
+        }
+    }
 
-    // Convert the modified syntax tree back into formatted code.
-    let transformed_code = prettyplease::unparse(&ast);
-    Ok(transformed_code)
+    Ok(uses)
 }
 
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn unidiomatic_function_cleanup(code: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
-
-    for item in ast.items.iter_mut() {
+fn get_code_other_than_uses(code: &str) -> PyResult<String> {
+    let ast = parse_src(code)?;
+    let mut code_other_than_uses = String::new();
+    for item in ast.items.iter() {
+        if let syn::Item::Use(_) = item {
+            continue;
+        }
+        code_other_than_uses.push_str(&quote!(#item).to_string());
+    }
+
+    Ok(code_other_than_uses)
+}
+
+fn collect_paths(
+    tree: &syn::UseTree,
+    current_path: &mut Vec<String>,
+    all_paths: &mut Vec<Vec<String>>,
+) -> PyResult<()> {
+    match tree {
+        syn::UseTree::Path(path) => {
+            current_path.push(path.ident.to_string());
+            collect_paths(&path.tree, current_path, all_paths)?;
+            current_path.pop();
+        }
+        syn::UseTree::Name(name) => {
+            if name.ident != "self" {
+                current_path.push(name.ident.to_string());
+                all_paths.push(current_path.clone());
+                current_path.pop();
+            } else {
+                // For self, add the current path without pushing 'self'
+                all_paths.push(current_path.clone());
+            }
+        }
+        syn::UseTree::Rename(_) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Use statements with 'as' are not supported",
+            ));
+        }
+        syn::UseTree::Glob(_) => {
+            // Handle glob imports
+            current_path.push("*".to_string());
+            all_paths.push(current_path.clone());
+            current_path.pop();
+        }
+        syn::UseTree::Group(group) => {
+            // Handle nested groups like a::{b, c}
+            for tree in &group.items {
+                collect_paths(tree, current_path, all_paths)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct UseAliasExpander {
+    aliases: std::collections::HashMap<String, syn::Path>,
+}
+
+impl UseAliasExpander {
+    fn new() -> Self {
+        Self {
+            aliases: std::collections::HashMap::new(),
+        }
+    }
+
+    fn collect_aliases(&mut self, file: &syn::File) {
+        for item in &file.items {
+            if let syn::Item::Use(use_item) = item {
+                self.collect_use_tree_aliases(&use_item.tree, &mut Vec::new());
+            }
+        }
+    }
+
+    fn collect_use_tree_aliases(&mut self, tree: &syn::UseTree, current_path: &mut Vec<String>) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                current_path.push(path.ident.to_string());
+                self.collect_use_tree_aliases(&path.tree, current_path);
+                current_path.pop();
+            }
+            syn::UseTree::Rename(rename) => {
+                // Handle self aliases specially
+                if rename.ident == "self" {
+                    // E.g. for `self as collections`, map `collections` to the current path
+                    let full_path = syn::Path {
+                        leading_colon: None,
+                        segments: current_path
+                            .iter()
+                            .map(|s| syn::PathSegment {
+                                ident: syn::Ident::new(s, proc_macro2::Span::call_site()),
+                                arguments: syn::PathArguments::None,
+                            })
+                            .collect(),
+                    };
+                    self.aliases.insert(rename.rename.to_string(), full_path);
+                } else {
+                    current_path.push(rename.ident.to_string());
+
+                    // Create the full path
+                    let full_path = syn::Path {
+                        leading_colon: None,
+                        segments: current_path
+                            .iter()
+                            .map(|s| syn::PathSegment {
+                                ident: syn::Ident::new(s, proc_macro2::Span::call_site()),
+                                arguments: syn::PathArguments::None,
+                            })
+                            .collect(),
+                    };
+
+                    // Store the alias mapping
+                    self.aliases.insert(rename.rename.to_string(), full_path);
+                    current_path.pop();
+                }
+            }
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.collect_use_tree_aliases(tree, current_path);
+                }
+            }
+            _ => {} // Handle other cases as needed
+        }
+    }
+}
+
+impl syn::visit_mut::VisitMut for UseAliasExpander {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if let Some(first_segment) = path.segments.first() {
+            let first_ident = first_segment.ident.to_string();
+            if let Some(expanded_path) = self.aliases.get(&first_ident) {
+                // Create new segments starting with the expanded path
+                let mut new_segments = expanded_path.segments.clone();
+
+                // Preserve generic arguments from the original first segment
+                if !first_segment.arguments.is_empty() {
+                    if let Some(last_segment) = new_segments.last_mut() {
+                        last_segment.arguments = first_segment.arguments.clone();
+                    }
+                }
+
+                // If there are remaining segments after the alias, append them
+                if path.segments.len() > 1 {
+                    let remaining_segments: Vec<_> =
+                        path.segments.iter().skip(1).cloned().collect();
+                    new_segments.extend(remaining_segments);
+                }
+
+                path.segments = new_segments;
+            }
+        }
+
+        // Continue visiting nested paths
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_use_tree_mut(&mut self, tree: &mut syn::UseTree) {
+        // Remove alias use statements and convert them to regular use statements
+        if let syn::UseTree::Rename(rename) = tree {
+            // Replace rename with regular name
+            *tree = syn::UseTree::Name(syn::UseName {
+                ident: rename.ident.clone(),
+            });
+        }
+
+        // Continue visiting
+        syn::visit_mut::visit_use_tree_mut(self, tree);
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn expand_use_aliases(code: &str) -> PyResult<String> {
+    use std::panic;
+    let res = panic::catch_unwind(|| {
+        let mut ast: File = parse_src(code)?;
+        let mut expander = UseAliasExpander::new();
+        // First pass: collect all aliases
+        expander.collect_aliases(&ast);
+
+        // Second pass: expand all usages
+        expander.visit_file_mut(&mut ast);
+
+        Ok(unparse(&ast))
+    });
+    match res {
+        Ok(inner_res) => inner_res,
+        Err(e) => if let Some(msg) = e.downcast_ref::<&str>() {
+            Err(format!("Error when expand_use_aliases: {}", msg))
+        } else if let Some(msg) = e.downcast_ref::<String>() {
+            Err(format!("Error when expand_use_aliases: {}", msg))
+        } else {
+            Err("Error when expand_use_aliases.".to_string())
+        }
+        .map_err(|msg| pyo3::exceptions::PySyntaxError::new_err(msg)),
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn get_standalone_uses_code_paths(code: &str) -> PyResult<Vec<Vec<String>>> {
+    let ast = parse_src(code)?;
+    let mut all_paths = Vec::new();
+
+    for item in ast.items.iter() {
+        if let syn::Item::Use(use_item) = item {
+            collect_paths(&use_item.tree, &mut Vec::new(), &mut all_paths)?;
+        }
+    }
+
+    Ok(all_paths)
+}
+
+enum RenameModifier {
+    Function,
+    StructUnion,
+}
+
+struct RenameVisitor {
+    old_name: String,
+    new_name: String,
+    modifer: RenameModifier,
+}
+
+impl syn::visit_mut::VisitMut for RenameVisitor {
+    fn visit_item_fn_mut(&mut self, item_fn: &mut syn::ItemFn) {
+        if let RenameModifier::Function = self.modifer {
+            // rename definition
+            if item_fn.sig.ident == self.old_name {
+                item_fn.sig.ident = syn::Ident::new(&self.new_name, item_fn.sig.ident.span());
+            }
+        }
+        syn::visit_mut::visit_item_fn_mut(self, item_fn);
+    }
+
+    fn visit_item_struct_mut(&mut self, item_struct: &mut syn::ItemStruct) {
+        if let RenameModifier::StructUnion = self.modifer {
+            // rename definition
+            if item_struct.ident == self.old_name {
+                item_struct.ident = syn::Ident::new(&self.new_name, item_struct.ident.span());
+            }
+        }
+
+        syn::visit_mut::visit_item_struct_mut(self, item_struct);
+    }
+
+    fn visit_item_union_mut(&mut self, item_union: &mut syn::ItemUnion) {
+        if let RenameModifier::StructUnion = self.modifer {
+            // rename definition
+            if item_union.ident == self.old_name {
+                item_union.ident = syn::Ident::new(&self.new_name, item_union.ident.span());
+            }
+        }
+
+        syn::visit_mut::visit_item_union_mut(self, item_union);
+    }
+
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if let Some(ident) = path.get_ident() {
+            if ident == self.old_name.as_str() {
+                path.segments.last_mut().unwrap().ident =
+                    syn::Ident::new(&self.new_name, ident.span());
+            }
+        }
+
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        mac.tokens = rename_token_idents(mac.tokens.clone(), &self.old_name, &self.new_name);
+        syn::visit_mut::visit_macro_mut(self, mac);
+    }
+}
+
+/// Renames the bare identifier `old_name` in macro tokens, as `RenameVisitor`
+/// does for single-segment paths; fields, methods and path segments such as
+/// `x.old_name` or `m::old_name` are left alone.
+fn rename_token_idents(
+    tokens: proc_macro2::TokenStream,
+    old_name: &str,
+    new_name: &str,
+) -> proc_macro2::TokenStream {
+    let trees: Vec<proc_macro2::TokenTree> = tokens.into_iter().collect();
+    let is_punct = |idx: Option<usize>, ch: char| {
+        idx.and_then(|idx| trees.get(idx)).is_some_and(
+            |tree| matches!(tree, proc_macro2::TokenTree::Punct(p) if p.as_char() == ch),
+        )
+    };
+    trees
+        .iter()
+        .enumerate()
+        .map(|(idx, tree)| match tree {
+            proc_macro2::TokenTree::Ident(ident)
+                if ident == old_name
+                    && !is_punct(idx.checked_sub(1), '.')
+                    && !is_punct(idx.checked_sub(1), ':')
+                    && !is_punct(Some(idx + 1), ':') =>
+            {
+                proc_macro2::TokenTree::Ident(syn::Ident::new(new_name, ident.span()))
+            }
+            proc_macro2::TokenTree::Group(group) => {
+                let mut renamed = proc_macro2::Group::new(
+                    group.delimiter(),
+                    rename_token_idents(group.stream(), old_name, new_name),
+                );
+                renamed.set_span(group.span());
+                proc_macro2::TokenTree::Group(renamed)
+            }
+            other => other.clone(),
+        })
+        .collect()
+}
+
+// Need to rename both function definition and function calls
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn rename_function(code: &str, old_name: &str, new_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    // Create and run our visitor
+    let mut visitor = RenameVisitor {
+        old_name: old_name.to_string(),
+        new_name: new_name.to_string(),
+        modifer: RenameModifier::Function,
+    };
+    visitor.visit_file_mut(&mut ast);
+
+    // Return the modified source code
+    Ok(unparse(&ast))
+}
+//
+// Need to rename both function definition and function calls
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn rename_struct_union(code: &str, old_name: &str, new_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    // Create and run our visitor
+    let mut visitor = RenameVisitor {
+        old_name: old_name.to_string(),
+        new_name: new_name.to_string(),
+        modifer: RenameModifier::StructUnion,
+    };
+    visitor.visit_file_mut(&mut ast);
+
+    // Return the modified source code
+    Ok(unparse(&ast))
+}
+
+struct TokenCounter {
+    total_tokens: usize,
+    unsafe_tokens: usize,
+}
+
+fn count_tokens(tokens: proc_macro2::TokenStream) -> usize {
+    let mut total = 0;
+    for token in tokens.into_iter() {
+        match &token {
+            proc_macro2::TokenTree::Group(group) => {
+                total += count_tokens(group.stream());
+            }
+            _ => {
+                total += 1;
+            }
+        }
+    }
+    total
+}
+
+impl syn::visit_mut::VisitMut for TokenCounter {
+    fn visit_item_fn_mut(&mut self, func: &mut syn::ItemFn) {
+        if func.sig.unsafety.is_some() {
+            let unsafe_tokens = count_tokens(func.block.to_token_stream());
+            self.unsafe_tokens += unsafe_tokens;
+            self.total_tokens += unsafe_tokens;
+        } else {
+            self.total_tokens += count_tokens(func.block.to_token_stream());
+            syn::visit_mut::visit_item_fn_mut(self, func);
+        }
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Unsafe(unsafe_expr) = expr {
+            let unsafe_tokens = count_tokens(unsafe_expr.block.to_token_stream());
+            self.unsafe_tokens += unsafe_tokens;
+        } else {
+            syn::visit_mut::visit_expr_mut(self, expr);
+        }
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn count_unsafe_tokens(code: &str) -> PyResult<(usize, usize)> {
+    let mut ast = parse_src(code)?;
+    let mut counter = TokenCounter {
+        total_tokens: 0,
+        unsafe_tokens: 0,
+    };
+    counter.visit_file_mut(&mut ast);
+    Ok((counter.total_tokens, counter.unsafe_tokens))
+}
+
+#[derive(Default)]
+struct MetricsVisitor {
+    complexity: usize,
+    statements: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl MetricsVisitor {
+    fn nested(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        visit(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for MetricsVisitor {
+    fn visit_stmt(&mut self, stmt: &'ast syn::Stmt) {
+        self.statements += 1;
+        visit::visit_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::If(expr_if) => {
+                self.complexity += 1;
+                self.visit_expr(&expr_if.cond);
+                self.nested(|v| v.visit_block(&expr_if.then_branch));
+                match expr_if.else_branch.as_ref().map(|(_, e)| &**e) {
+                    // `else if` chains stay at the same depth
+                    Some(chained @ syn::Expr::If(_)) => self.visit_expr(chained),
+                    Some(other) => self.nested(|v| v.visit_expr(other)),
+                    None => {}
+                }
+            }
+            syn::Expr::While(_) | syn::Expr::ForLoop(_) => {
+                self.complexity += 1;
+                self.nested(|v| visit::visit_expr(v, expr));
+            }
+            syn::Expr::Match(expr_match) => {
+                self.complexity += expr_match.arms.len().saturating_sub(1);
+                self.complexity += expr_match
+                    .arms
+                    .iter()
+                    .filter(|arm| arm.guard.is_some())
+                    .count();
+                self.nested(|v| visit::visit_expr(v, expr));
+            }
+            syn::Expr::Loop(_) | syn::Expr::Closure(_) => {
+                self.nested(|v| visit::visit_expr(v, expr));
+            }
+            syn::Expr::Binary(binary)
+                if matches!(binary.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) =>
+            {
+                self.complexity += 1;
+                visit::visit_expr(self, expr);
+            }
+            syn::Expr::Try(_) => {
+                self.complexity += 1;
+                visit::visit_expr(self, expr);
+            }
+            _ => visit::visit_expr(self, expr),
+        }
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Size and complexity of every function and method: `cyclomatic_complexity`
+/// (1 plus one per `if`, `while`, `for`, extra `match` arm, arm guard, `&&`,
+/// `||` and `?`), `statements` (all nested statements), `max_nesting` (depth of
+/// nested control flow and closures) and `tokens` in the body. Methods are
+/// named `Type::method`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn code_metrics(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let mut functions: Vec<(String, &syn::Block)> = Vec::new();
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Fn(f) => functions.push((f.sig.ident.to_string(), &f.block)),
+            syn::Item::Impl(imp) => {
+                let owner = type_last_ident(&imp.self_ty).unwrap_or_default();
+                for impl_item in imp.items.iter() {
+                    if let syn::ImplItem::Fn(method) = impl_item {
+                        functions.push((format!("{}::{}", owner, method.sig.ident), &method.block));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let result = PyList::empty(py);
+    for (name, block) in functions {
+        let mut metrics = MetricsVisitor::default();
+        metrics.visit_block(block);
+        let entry = PyDict::new(py);
+        entry.set_item("name", name)?;
+        entry.set_item("cyclomatic_complexity", metrics.complexity + 1)?;
+        entry.set_item("statements", metrics.statements)?;
+        entry.set_item("max_nesting", metrics.max_depth)?;
+        entry.set_item("tokens", count_tokens(block.to_token_stream()))?;
+        result.append(entry)?;
+    }
+    Ok(result.into())
+}
+
+pub struct ParsedAttribute(pub Attribute);
+
+impl Parse for ParsedAttribute {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let pound_token: token::Pound = input.parse()?;
+
+        // Determine the style of the attribute (Outer or Inner)
+        let style = if input.peek(token::Bracket) {
+            AttrStyle::Outer
+        } else if input.peek(Token![!]) {
+            input.parse::<Token![!]>()?;
+            AttrStyle::Inner(Token![!](input.span()))
+        } else {
+            return Err(input.error("Expected attribute brackets"));
+        };
+
+        // Parse the bracketed contents
+        let content;
+        let bracket_token = syn::bracketed!(content in input);
+        let meta: Meta = content.parse()?;
+
+        let attribute = Attribute {
+            pound_token,
+            style,
+            bracket_token,
+            meta,
+        };
+
+        Ok(ParsedAttribute(attribute))
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn add_attr_to_function(code: &str, function_name: &str, attr: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    for item in ast.items.iter_mut() {
+        if let syn::Item::Fn(f) = item {
+            if f.sig.ident == function_name {
+                let parsed = parse_str::<ParsedAttribute>(attr).map_err(|e| {
+                    pyo3::exceptions::PySyntaxError::new_err(format!(
+                        "Parse error: {}\n source code: {}",
+                        e, attr
+                    ))
+                })?;
+                let attr = parsed.0;
+                // check if the attribute is already present
+                for existing_attr in f.attrs.iter() {
+                    if existing_attr.to_token_stream().to_string()
+                        == attr.to_token_stream().to_string()
+                    {
+                        return Ok(unparse(&ast));
+                    }
+                }
+
+                f.attrs.push(attr);
+            }
+        }
+    }
+    Ok(unparse(&ast))
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn add_attr_to_struct_union(code: &str, struct_union_name: &str, attr: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    fn add_attribute(attrs: &mut Vec<syn::Attribute>, attr: &str) -> PyResult<()> {
+        let parsed = parse_str::<ParsedAttribute>(attr).map_err(|e| {
+            pyo3::exceptions::PySyntaxError::new_err(format!(
+                "Parse error: {}\n source code: {}",
+                e, attr
+            ))
+        })?;
+        let attr = parsed.0;
+
+        // Check if the attribute is already present
+        if attrs.iter().any(|existing| {
+            existing.to_token_stream().to_string() == attr.to_token_stream().to_string()
+        }) {
+            return Ok(());
+        }
+
+        attrs.push(attr);
+        Ok(())
+    }
+
+    for item in ast.items.iter_mut() {
+        if let syn::Item::Struct(s) = item {
+            if s.ident == struct_union_name {
+                add_attribute(&mut s.attrs, attr)?;
+            }
+        } else if let syn::Item::Union(u) = item {
+            if u.ident == struct_union_name {
+                add_attribute(&mut u.attrs, attr)?;
+            }
+        }
+    }
+
+    Ok(unparse(&ast))
+}
+
+/// Adds `derive` to the item's `#[derive(...)]`, creating it if missing.
+fn add_derive(
+    attrs: &mut Vec<syn::Attribute>,
+    derive: &str,
+    span: proc_macro2::Span,
+) -> PyResult<()> {
+    let mut existing_derive = None;
+
+    // Check for existing derive attribute
+    for attr in attrs.iter_mut() {
+        if let syn::Meta::List(list) = &mut attr.meta {
+            if list.path.is_ident("derive") {
+                existing_derive = Some(list);
+                break;
+            }
+        }
+    }
+
+    if let Some(existing_derive) = existing_derive {
+        // Check if derive is already present
+        let mut found = false;
+        existing_derive
+            .parse_nested_meta(|meta| {
+                if meta.path.is_ident(derive) {
+                    found = true;
+                }
+                Ok(())
+            })
+            .map_err(|e| {
+                pyo3::exceptions::PySyntaxError::new_err(format!(
+                    "Parse error: {}\n source code: {}",
+                    e, derive
+                ))
+            })?;
+
+        if !found {
+            let current_derive_tokens = existing_derive.tokens.clone();
+            let ident = syn::Ident::new(derive, span);
+            existing_derive.tokens = quote! { #current_derive_tokens, #ident };
+        }
+    } else {
+        // Add new derive attribute
+        let ident = syn::Ident::new(derive, span);
+        attrs.push(parse_quote!(#[derive(#ident)]));
+    }
+
+    Ok(())
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn add_derive_to_struct_union(
+    code: &str,
+    struct_union_name: &str,
+    derive: &str,
+) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    for item in ast.items.iter_mut() {
+        match item {
+            syn::Item::Struct(s) if s.ident == struct_union_name => {
+                let span = s.span();
+                add_derive(&mut s.attrs, derive, span)?;
+            }
+            syn::Item::Union(u) if u.ident == struct_union_name => {
+                let span = u.span();
+                add_derive(&mut u.attrs, derive, span)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(unparse(&ast))
+}
+
+/// Last path segments of every trait listed in the item's `#[derive(...)]`s.
+fn derived_traits(attrs: &[Attribute]) -> Vec<String> {
+    let mut traits = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("derive")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if let Some(last) = meta.path.segments.last() {
+                traits.push(last.ident.to_string());
+            }
+            Ok(())
+        });
+    }
+    traits
+}
+
+/// Collects the struct, enum or union named `type_name` and the traits of
+/// every `impl Trait for type_name`, looking into inline modules as well.
+fn find_type_and_impls<'a>(
+    items: &'a [syn::Item],
+    type_name: &str,
+    found: &mut Option<&'a [Attribute]>,
+    impls: &mut Vec<String>,
+) {
+    for item in items {
+        match item {
+            syn::Item::Struct(s) if s.ident == type_name => *found = Some(&s.attrs),
+            syn::Item::Enum(e) if e.ident == type_name => *found = Some(&e.attrs),
+            syn::Item::Union(u) if u.ident == type_name => *found = Some(&u.attrs),
+            syn::Item::Impl(imp) if type_last_ident(&imp.self_ty).as_deref() == Some(type_name) => {
+                if let Some((_, trait_path, _)) = &imp.trait_ {
+                    if let Some(last) = trait_path.segments.last() {
+                        impls.push(last.ident.to_string());
+                    }
+                }
+            }
+            syn::Item::Mod(m) => {
+                if let Some((_, content)) = &m.content {
+                    find_type_and_impls(content, type_name, found, impls);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Derived traits and trait impls of `type_name`; errors if the type is not
+/// defined in `code`.
+fn type_traits_of(code: &str, type_name: &str) -> PyResult<(Vec<String>, Vec<String>)> {
+    let ast = parse_src(code)?;
+    let mut found = None;
+    let mut impls = Vec::new();
+    find_type_and_impls(&ast.items, type_name, &mut found, &mut impls);
+    let attrs = found.ok_or_else(|| {
+        pyo3::exceptions::PyValueError::new_err(format!("Type '{}' not found", type_name))
+    })?;
+    Ok((derived_traits(attrs), impls))
+}
+
+/// Last segment of a trait path such as `std::fmt::Debug`.
+fn trait_ident(trait_name: &str) -> &str {
+    trait_name.rsplit("::").next().unwrap_or(trait_name).trim()
+}
+
+/// Whether `type_name` has `trait_name` in one of its `#[derive(...)]`s.
+/// Paths are compared by their last segment, so `std::fmt::Debug` matches
+/// `#[derive(Debug)]`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn has_derive(code: &str, type_name: &str, trait_name: &str) -> PyResult<bool> {
+    let (derives, _) = type_traits_of(code, type_name)?;
+    Ok(derives.iter().any(|name| name == trait_ident(trait_name)))
+}
+
+/// Whether `type_name` implements `trait_name`, either through a derive or a
+/// hand-written `impl Trait for Type` block anywhere in `code`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn implements_trait(code: &str, type_name: &str, trait_name: &str) -> PyResult<bool> {
+    let (derives, impls) = type_traits_of(code, type_name)?;
+    let trait_name = trait_ident(trait_name);
+    Ok(derives
+        .iter()
+        .chain(impls.iter())
+        .any(|name| name == trait_name))
+}
+
+/// Traits `infer_derives` considers, in the order they are added.
+const INFERABLE_DERIVES: &[&str] = &["Clone", "Copy", "Debug", "PartialEq", "Default"];
+
+/// Last segments of the type paths a derive on the containing type depends
+/// on; pointees of raw and function pointers are skipped.
+struct TypePathIdents(HashSet<String>);
+
+impl<'ast> Visit<'ast> for TypePathIdents {
+    fn visit_type_path(&mut self, type_path: &'ast syn::TypePath) {
+        if let Some(last) = type_path.path.segments.last() {
+            self.0.insert(last.ident.to_string());
+        }
+        visit::visit_type_path(self, type_path);
+    }
+
+    fn visit_type_ptr(&mut self, _node: &'ast syn::TypePtr) {}
+
+    fn visit_type_bare_fn(&mut self, _node: &'ast syn::TypeBareFn) {}
+}
+
+/// A struct, enum or union of the file as seen by `infer_derives`.
+struct DeriveTarget<'a> {
+    kind: DeriveKind,
+    field_types: Vec<&'a syn::Type>,
+    generics: HashSet<String>,
+    has_default_variant: bool,
+    derived: Vec<String>,
+    /// Derived traits plus those of hand-written `impl` blocks.
+    implemented: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DeriveKind {
+    Struct,
+    Enum,
+    Union,
+}
+
+/// Decides which of `INFERABLE_DERIVES` every local type could derive. Local
+/// types start out supporting everything their shape allows and lose traits
+/// until a fixpoint is reached, so self-referential types such as
+/// `struct Node { next: Option<Box<Node>> }` still derive `Clone`.
+struct DeriveInference<'a> {
+    targets: BTreeMap<String, DeriveTarget<'a>>,
+    aliases: HashMap<String, &'a syn::Type>,
+    supported: HashMap<String, HashSet<&'static str>>,
+}
+
+impl<'a> DeriveInference<'a> {
+    fn new(items: &'a [syn::Item]) -> Self {
+        let mut targets = BTreeMap::new();
+        let mut aliases = HashMap::new();
+        let mut impls: HashMap<String, Vec<String>> = HashMap::new();
+        for item in items {
+            let generics_of = |generics: &syn::Generics| {
+                generics
+                    .type_params()
+                    .map(|param| param.ident.to_string())
+                    .collect::<HashSet<_>>()
+            };
+            let (name, target) = match item {
+                syn::Item::Struct(s) => (
+                    s.ident.to_string(),
+                    DeriveTarget {
+                        kind: DeriveKind::Struct,
+                        field_types: s.fields.iter().map(|field| &field.ty).collect(),
+                        generics: generics_of(&s.generics),
+                        has_default_variant: false,
+                        derived: derived_traits(&s.attrs),
+                        implemented: derived_traits(&s.attrs),
+                    },
+                ),
+                syn::Item::Enum(e) => (
+                    e.ident.to_string(),
+                    DeriveTarget {
+                        kind: DeriveKind::Enum,
+                        field_types: e
+                            .variants
+                            .iter()
+                            .flat_map(|variant| variant.fields.iter().map(|field| &field.ty))
+                            .collect(),
+                        generics: generics_of(&e.generics),
+                        has_default_variant: e.variants.iter().any(|variant| {
+                            variant
+                                .attrs
+                                .iter()
+                                .any(|attr| attr.path().is_ident("default"))
+                        }),
+                        derived: derived_traits(&e.attrs),
+                        implemented: derived_traits(&e.attrs),
+                    },
+                ),
+                syn::Item::Union(u) => (
+                    u.ident.to_string(),
+                    DeriveTarget {
+                        kind: DeriveKind::Union,
+                        field_types: u.fields.named.iter().map(|field| &field.ty).collect(),
+                        generics: generics_of(&u.generics),
+                        has_default_variant: false,
+                        derived: derived_traits(&u.attrs),
+                        implemented: derived_traits(&u.attrs),
+                    },
+                ),
+                syn::Item::Type(alias) => {
+                    aliases.insert(alias.ident.to_string(), &*alias.ty);
+                    continue;
+                }
+                syn::Item::Impl(imp) => {
+                    if let (Some(self_name), Some((_, trait_path, _))) =
+                        (type_last_ident(&imp.self_ty), &imp.trait_)
+                    {
+                        if let Some(last) = trait_path.segments.last() {
+                            impls
+                                .entry(self_name)
+                                .or_default()
+                                .push(last.ident.to_string());
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            targets.insert(name, target);
+        }
+        for (name, traits) in impls {
+            if let Some(target) = targets.get_mut(&name) {
+                target.implemented.extend(traits);
+            }
+        }
+
+        let supported = targets
+            .iter()
+            .map(|(name, target)| {
+                let initial = INFERABLE_DERIVES
+                    .iter()
+                    .copied()
+                    .filter(|derive| {
+                        target.implemented.iter().any(|name| name == derive)
+                            || match (target.kind, *derive) {
+                                (DeriveKind::Union, "Clone" | "Copy") => true,
+                                (DeriveKind::Union, _) => false,
+                                (DeriveKind::Enum, "Default") => target.has_default_variant,
+                                _ => true,
+                            }
+                    })
+                    .collect();
+                (name.clone(), initial)
+            })
+            .collect();
+        let mut inference = Self {
+            targets,
+            aliases,
+            supported,
+        };
+        inference.solve();
+        inference
+    }
+
+    fn solve(&mut self) {
+        loop {
+            let mut removals = Vec::new();
+            for (name, target) in self.targets.iter() {
+                for derive in self.supported[name].iter() {
+                    if target.implemented.iter().any(|name| name == derive) {
+                        continue;
+                    }
+                    // a union's fields are only ever copied bitwise
+                    let required = if target.kind == DeriveKind::Union {
+                        "Copy"
+                    } else {
+                        derive
+                    };
+                    let fields_ok = target
+                        .field_types
+                        .iter()
+                        .all(|ty| self.type_supports(ty, required, &target.generics));
+                    // `derive(Copy)` needs `Clone` as well
+                    let clone_ok = *derive != "Copy" || self.supported[name].contains("Clone");
+                    if !fields_ok || !clone_ok {
+                        removals.push((name.clone(), *derive));
+                    }
+                }
+            }
+            if removals.is_empty() {
+                break;
+            }
+            for (name, derive) in removals {
+                if let Some(supported) = self.supported.get_mut(&name) {
+                    supported.remove(derive);
+                }
+            }
+        }
+    }
+
+    fn generic_args(segment: &syn::PathSegment) -> Vec<&syn::Type> {
+        match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn type_supports(&self, ty: &syn::Type, derive: &str, generics: &HashSet<String>) -> bool {
+        let all = |types: &[&syn::Type]| {
+            types
+                .iter()
+                .all(|ty| self.type_supports(ty, derive, generics))
+        };
+        match ty {
+            syn::Type::Paren(paren) => self.type_supports(&paren.elem, derive, generics),
+            syn::Type::Group(group) => self.type_supports(&group.elem, derive, generics),
+            syn::Type::Ptr(_) => derive != "Default",
+            // comparing function pointers is linted as unreliable
+            syn::Type::BareFn(_) => !matches!(derive, "Default" | "PartialEq"),
+            syn::Type::Never(_) => true,
+            syn::Type::Reference(reference) => match derive {
+                "Default" => false,
+                "Clone" | "Copy" => reference.mutability.is_none(),
+                _ => self.type_supports(&reference.elem, derive, generics),
+            },
+            syn::Type::Array(array) => {
+                let len_ok = derive != "Default"
+                    || matches!(&array.len, syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(len), .. })
+                        if len.base10_parse::<usize>().is_ok_and(|len| len <= 32));
+                len_ok && self.type_supports(&array.elem, derive, generics)
+            }
+            syn::Type::Tuple(tuple) => {
+                tuple.elems.len() <= 12
+                    && tuple
+                        .elems
+                        .iter()
+                        .all(|elem| self.type_supports(elem, derive, generics))
+            }
+            syn::Type::Path(type_path) => {
+                let Some(segment) = type_path.path.segments.last() else {
+                    return false;
+                };
+                let name = segment.ident.to_string();
+                let args = Self::generic_args(segment);
+                if type_path.qself.is_none() && type_path.path.segments.len() == 1 {
+                    if generics.contains(&name) {
+                        // the derive adds the matching bound to the parameter
+                        return true;
+                    }
+                    if let Some(supported) = self.supported.get(&name) {
+                        return supported.contains(derive);
+                    }
+                    if let Some(aliased) = self.aliases.get(&name) {
+                        return self.type_supports(aliased, derive, generics);
+                    }
+                }
+                if is_scalar_type(ty) || matches!(name.as_str(), "u128" | "i128" | "c_void") {
+                    return name != "c_void" || derive != "Default";
+                }
+                match name.as_str() {
+                    "Option" => derive == "Default" || all(&args),
+                    "PhantomData" => true,
+                    "String" => derive != "Copy",
+                    "Box" | "Vec" | "VecDeque" | "HashMap" | "BTreeMap" | "HashSet"
+                    | "BTreeSet" | "RefCell" => derive != "Copy" && all(&args),
+                    "Rc" | "Arc" => match derive {
+                        "Copy" => false,
+                        "Clone" => true,
+                        _ => all(&args),
+                    },
+                    "NonNull" => derive != "Default",
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Adds every derive among `Clone`, `Copy`, `Debug`, `PartialEq` and `Default`
+/// that `struct_name` can support given its field types. Field types defined
+/// in `code` are followed recursively and get the derives the outer type
+/// depends on; traits a type already derives or implements by hand are left
+/// alone. Types from outside `code` are assumed to implement nothing beyond
+/// the standard library's own impls.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn infer_derives(code: &str, struct_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    let mut plan: HashMap<String, Vec<&'static str>> = HashMap::new();
+    {
+        let inference = DeriveInference::new(&ast.items);
+        if !inference.targets.contains_key(struct_name) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Type '{}' not found",
+                struct_name
+            )));
+        }
+
+        let mut queue: Vec<(String, &'static str)> = inference.supported[struct_name]
+            .iter()
+            .map(|derive| (struct_name.to_string(), *derive))
+            .collect();
+        let mut visited = HashSet::new();
+        while let Some((name, derive)) = queue.pop() {
+            if !visited.insert((name.clone(), derive))
+                || !inference.supported[&name].contains(derive)
+            {
+                continue;
+            }
+            let target = &inference.targets[&name];
+            let derived = target.derived.iter().any(|name| name == derive);
+            if !derived {
+                if target.implemented.iter().any(|name| name == derive) {
+                    // a hand-written impl does not constrain the fields
+                    continue;
+                }
+                plan.entry(name.clone()).or_default().push(derive);
+            }
+
+            // local field types must implement whatever the derive needs
+            let mut field_names = TypePathIdents(HashSet::new());
+            for ty in target.field_types.iter() {
+                field_names.visit_type(ty);
+            }
+            let needed: &[&'static str] = if target.kind == DeriveKind::Union {
+                &["Clone", "Copy"]
+            } else {
+                std::slice::from_ref(&derive)
+            };
+            for field_name in field_names.0 {
+                if inference.targets.contains_key(&field_name) {
+                    for needed in needed {
+                        queue.push((field_name.clone(), *needed));
+                    }
+                }
+            }
+        }
+    }
+
+    for item in ast.items.iter_mut() {
+        let (ident, attrs) = match item {
+            syn::Item::Struct(s) => (&s.ident, &mut s.attrs),
+            syn::Item::Enum(e) => (&e.ident, &mut e.attrs),
+            syn::Item::Union(u) => (&u.ident, &mut u.attrs),
+            _ => continue,
+        };
+        if let Some(derives) = plan.get(&ident.to_string()) {
+            let span = ident.span();
+            for derive in INFERABLE_DERIVES
+                .iter()
+                .filter(|derive| derives.contains(derive))
+            {
+                add_derive(attrs, derive, span)?;
+            }
+        }
+    }
+
+    Ok(unparse(&ast))
+}
+
+/// A visitor that traverses the AST and replaces libc scalar types with Rust primitives.
+struct LibcTypeVisitor;
+
+impl VisitMut for LibcTypeVisitor {
+    /// This method is called for every type path in the source code.
+    fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
+        // We are looking for paths with exactly two segments, like `libc::c_int`.
+        if type_path.qself.is_none() && type_path.path.segments.len() == 2 {
+            let first_segment = &type_path.path.segments[0];
+
+            // Check if the first segment is `libc`.
+            if first_segment.ident == "libc" && first_segment.arguments.is_none() {
+                let second_segment = &type_path.path.segments[1];
+                let libc_type_name = second_segment.ident.to_string();
+
+                // If the second segment is a libc type we can replace...
+                if let Some(rust_type_str) = map_libc_scalar(&libc_type_name) {
+                    // Create a new identifier for the Rust type.
+                    let new_rust_type_ident =
+                        syn::Ident::new(rust_type_str, second_segment.ident.span());
+
+                    // Create a new path from this single identifier.
+                    let new_path: syn::Path = new_rust_type_ident.into();
+
+                    // Replace the old path (`libc::c_int`) with the new one (`i32`).
+                    type_path.path = new_path;
+                }
+            }
+        } else if type_path.qself.is_none() && type_path.path.segments.len() == 1 {
+            // bare platform typedefs (`DWORD`) only when a project registered them
+            let segment = &type_path.path.segments[0];
+            if segment.arguments.is_none() {
+                if let Some(rust_type_str) = map_registered_scalar(&segment.ident.to_string()) {
+                    type_path.path = syn::Ident::new(rust_type_str, segment.ident.span()).into();
+                }
+            }
+        }
+
+        // Continue traversing the rest of the AST to find other types.
+        syn::visit_mut::visit_type_path_mut(self, type_path);
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn replace_libc_numeric_types_to_rust_primitive_types(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    let mut visitor = LibcTypeVisitor;
+    visitor.visit_file_mut(&mut ast);
+
+    // Convert the modified syntax tree back into formatted code.
+    let transformed_code = unparse(&ast);
+    Ok(transformed_code)
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn unidiomatic_function_cleanup(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    for item in ast.items.iter_mut() {
+        if let syn::Item::Fn(f) = item {
+            // remove `extern "C"``
+            f.sig.abi = None;
+            // add `pub` before `fn`
+            f.vis = syn::Visibility::Public(Token![pub](f.span()));
+        }
+        if let syn::Item::ExternCrate(_) = item {
+            // remove `extern crate`
+            *item = syn::Item::Verbatim(Default::default());
+        }
+    }
+
+    normalize_stdint_aliases(&mut ast);
+    CCharNormalizer.visit_file_mut(&mut ast);
+
+    Ok(unparse(&ast))
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn unidiomatic_types_cleanup(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    for item in ast.items.iter_mut() {
+        if let syn::Item::ExternCrate(_) = item {
+            // remove `extern crate`
+            *item = syn::Item::Verbatim(Default::default());
+        }
+    }
+
+    normalize_stdint_aliases(&mut ast);
+
+    Ok(unparse(&ast))
+}
+
+/// Non-generic `type` aliases declared at the top level, by name.
+fn collect_type_aliases(items: &[syn::Item]) -> HashMap<String, syn::Type> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Type(alias) if alias.generics.params.is_empty() => {
+                Some((alias.ident.to_string(), (*alias.ty).clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// `name` if the type is a plain single-segment path such as `id_t`.
+fn bare_type_name(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    if type_path.qself.is_some() || type_path.path.segments.len() != 1 {
+        return None;
+    }
+    let segment = &type_path.path.segments[0];
+    segment
+        .arguments
+        .is_none()
+        .then(|| segment.ident.to_string())
+}
+
+/// Replaces alias names in a type with their targets until none are left.
+/// Names in `kept` stay as they are; `visiting` breaks alias cycles.
+struct AliasInliner<'a> {
+    aliases: &'a HashMap<String, syn::Type>,
+    kept: &'a HashSet<String>,
+    visiting: Vec<String>,
+}
+
+impl VisitMut for AliasInliner<'_> {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let Some(name) = bare_type_name(ty) {
+            if !self.kept.contains(&name) && !self.visiting.contains(&name) {
+                if let Some(target) = self.aliases.get(&name) {
+                    *ty = target.clone();
+                    self.visiting.push(name);
+                    self.visit_type_mut(ty);
+                    self.visiting.pop();
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+/// Direct targets of the top-level `type` aliases in `code`, by alias name.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn get_type_aliases(code: &str) -> PyResult<HashMap<String, String>> {
+    let ast = parse_src(code)?;
+    Ok(collect_type_aliases(&ast.items)
+        .into_iter()
+        .map(|(name, ty)| (name, ty.to_token_stream().to_string()))
+        .collect())
+}
+
+/// Rewrites every use of a top-level `type` alias to the type it finally
+/// resolves to and drops the inlined alias items. With `keep_libc_aliases`,
+/// libc/stdint names such as `uint32_t` or `size_t` are kept as the stopping
+/// point of a chain, so `id_t -> uint32_t -> __uint32_t` becomes `uint32_t`.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (code, keep_libc_aliases=true))]
+fn resolve_type_aliases(code: &str, keep_libc_aliases: bool) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    let aliases = collect_type_aliases(&ast.items);
+    let kept: HashSet<String> = if keep_libc_aliases {
+        aliases
+            .keys()
+            .filter(|name| {
+                expected_stdint_target(name).is_some() || map_libc_scalar(name).is_some()
+            })
+            .cloned()
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    ast.items.retain(|item| match item {
+        syn::Item::Type(alias) => {
+            let name = alias.ident.to_string();
+            !aliases.contains_key(&name) || kept.contains(&name)
+        }
+        _ => true,
+    });
+    let mut inliner = AliasInliner {
+        aliases: &aliases,
+        kept: &kept,
+        visiting: Vec::new(),
+    };
+    for item in ast.items.iter_mut() {
+        match item {
+            // a kept alias still resolves its own target
+            syn::Item::Type(alias) => {
+                inliner.visiting.push(alias.ident.to_string());
+                inliner.visit_type_mut(&mut alias.ty);
+                inliner.visiting.pop();
+            }
+            _ => inliner.visit_item_mut(item),
+        }
+    }
+
+    Ok(unparse(&ast))
+}
+
+const STDINT_ALIAS_TARGETS: &[(&str, &str)] = &[
+    ("int8_t", "i8"),
+    ("int16_t", "i16"),
+    ("int32_t", "i32"),
+    ("int64_t", "i64"),
+    ("uint8_t", "u8"),
+    ("uint16_t", "u16"),
+    ("uint32_t", "u32"),
+    ("uint64_t", "u64"),
+];
+
+fn expected_stdint_target(name: &str) -> Option<&'static str> {
+    STDINT_ALIAS_TARGETS
+        .iter()
+        .find(|(alias, _)| alias == &name)
+        .map(|(_, target)| *target)
+}
+
+fn should_strip_stdint_alias(item: &syn::ItemType) -> Option<String> {
+    let name = item.ident.to_string();
+    let expected = expected_stdint_target(&name)?;
+
+    if let syn::Type::Path(type_path) = item.ty.as_ref() {
+        if type_path.qself.is_none()
+            && type_path.path.segments.len() == 1
+            && type_path.path.segments[0].ident == expected
+            && type_path.path.segments[0].arguments.is_empty()
+        {
+            return Some(name);
+        }
+    }
+
+    None
+}
+
+fn normalize_stdint_aliases(ast: &mut syn::File) {
+    let mut removed_aliases: Vec<String> = Vec::new();
+    let original_items = mem::take(&mut ast.items);
+    let mut new_items = Vec::with_capacity(original_items.len());
+
+    for item in original_items {
+        if let syn::Item::Type(type_item) = &item {
+            if let Some(name) = should_strip_stdint_alias(type_item) {
+                removed_aliases.push(name);
+                continue;
+            }
+        }
+        new_items.push(item);
+    }
+
+    removed_aliases.sort();
+    removed_aliases.dedup();
+
+    let mut needed: BTreeSet<String> = removed_aliases.into_iter().collect();
+    let usages = collect_stdint_names(&new_items);
+    needed.extend(usages);
+
+    if needed.is_empty() {
+        ast.items = new_items;
+        return;
+    }
+
+    let needed_vec: Vec<String> = needed.into_iter().collect();
+    ensure_libc_imports(&mut new_items, &needed_vec);
+
+    ast.items = new_items;
+}
+
+fn ensure_libc_imports(items: &mut Vec<syn::Item>, aliases: &[String]) {
+    if aliases.is_empty() {
+        return;
+    }
+
+    let mut needed: BTreeSet<String> = aliases.iter().cloned().collect();
+
+    for item in items.iter_mut() {
+        if let syn::Item::Use(item_use) = item {
+            ensure_aliases_in_use_tree(&mut item_use.tree, &mut needed, false);
+            if needed.is_empty() {
+                break;
+            }
+        }
+    }
+
+    if needed.is_empty() {
+        return;
+    }
+
+    let group_items: Vec<syn::UseTree> = needed
+        .iter()
+        .map(|name| {
+            syn::UseTree::Name(syn::UseName {
+                ident: syn::Ident::new(name, proc_macro2::Span::call_site()),
+            })
+        })
+        .collect();
+
+    let new_use = syn::Item::Use(syn::ItemUse {
+        attrs: Vec::new(),
+        vis: syn::Visibility::Inherited,
+        use_token: Token![use](proc_macro2::Span::call_site()),
+        leading_colon: None,
+        tree: syn::UseTree::Path(syn::UsePath {
+            ident: syn::Ident::new("libc", proc_macro2::Span::call_site()),
+            colon2_token: Token![::](proc_macro2::Span::call_site()),
+            tree: Box::new(syn::UseTree::Group(syn::UseGroup {
+                brace_token: syn::token::Brace::default(),
+                items: vec_to_punctuated(group_items),
+            })),
+        }),
+        semi_token: Token![;](proc_macro2::Span::call_site()),
+    });
+
+    // Insert the new use after the last existing use declaration to keep code tidy.
+    let insertion_index = items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| match item {
+            syn::Item::Use(_) => Some(idx + 1),
+            _ => None,
+        })
+        .last()
+        .unwrap_or(0);
+
+    items.insert(insertion_index, new_use);
+    needed.clear();
+}
+
+fn collect_stdint_names(items: &[syn::Item]) -> BTreeSet<String> {
+    let mut collector = StdintUsageCollector {
+        names: BTreeSet::new(),
+    };
+
+    for item in items {
+        collector.visit_item(item);
+    }
+
+    collector.names
+}
+
+struct StdintUsageCollector {
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for StdintUsageCollector {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.leading_colon.is_none() && path.segments.len() == 1 {
+            let ident = &path.segments[0].ident;
+            let ident_str = ident.to_string();
+            if expected_stdint_target(&ident_str).is_some() {
+                self.names.insert(ident_str);
+            }
+        }
+
+        visit::visit_path(self, path);
+    }
+}
+
+fn ensure_aliases_in_use_tree(
+    tree: &mut syn::UseTree,
+    needed: &mut BTreeSet<String>,
+    in_libc: bool,
+) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            let next_in_libc = in_libc || path.ident == "libc";
+            ensure_aliases_in_use_tree(&mut path.tree, needed, next_in_libc);
+        }
+        syn::UseTree::Group(group) => {
+            if in_libc {
+                ensure_aliases_in_libc_group(group, needed);
+            } else {
+                for item in group.items.iter_mut() {
+                    ensure_aliases_in_use_tree(item, needed, false);
+                    if needed.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+        syn::UseTree::Name(name) => {
+            if !in_libc {
+                return;
+            }
+
+            let ident_str = name.ident.to_string();
+            needed.remove(&ident_str);
+
+            if needed.is_empty() {
+                return;
+            }
+
+            let mut items_vec = Vec::with_capacity(1 + needed.len());
+            items_vec.push(syn::UseTree::Name(name.clone()));
+
+            let extras: Vec<String> = needed.iter().cloned().collect();
+            for alias in extras.iter() {
+                let ident = syn::Ident::new(alias, proc_macro2::Span::call_site());
+                items_vec.push(syn::UseTree::Name(syn::UseName { ident }));
+                needed.remove(alias);
+            }
+
+            *tree = syn::UseTree::Group(syn::UseGroup {
+                brace_token: syn::token::Brace::default(),
+                items: vec_to_punctuated(items_vec),
+            });
+        }
+        syn::UseTree::Rename(rename) => {
+            if in_libc {
+                needed.remove(&rename.ident.to_string());
+            }
+        }
+        syn::UseTree::Glob(_) => {
+            if in_libc {
+                needed.clear();
+            }
+        }
+    }
+}
+
+fn ensure_aliases_in_libc_group(group: &mut syn::UseGroup, needed: &mut BTreeSet<String>) {
+    let mut existing: BTreeSet<String> = BTreeSet::new();
+
+    let mut has_glob = false;
+    for item in group.items.iter() {
+        collect_libc_names(item, true, &mut existing, &mut has_glob);
+        if has_glob {
+            needed.clear();
+            return;
+        }
+    }
+
+    let additions: Vec<String> = needed
+        .iter()
+        .filter(|alias| !existing.contains(*alias))
+        .cloned()
+        .collect();
+
+    if additions.is_empty() {
+        return;
+    }
+
+    for alias in additions.iter() {
+        let ident = syn::Ident::new(alias, proc_macro2::Span::call_site());
+        group.items.push(syn::UseTree::Name(syn::UseName { ident }));
+        needed.remove(alias);
+    }
+}
+
+fn collect_libc_names(
+    tree: &syn::UseTree,
+    in_libc: bool,
+    acc: &mut BTreeSet<String>,
+    has_glob: &mut bool,
+) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            let next_in_libc = in_libc || path.ident == "libc";
+            collect_libc_names(&path.tree, next_in_libc, acc, has_glob);
+        }
+        syn::UseTree::Group(group) => {
+            for item in group.items.iter() {
+                collect_libc_names(item, in_libc, acc, has_glob);
+                if *has_glob {
+                    break;
+                }
+            }
+        }
+        syn::UseTree::Name(name) => {
+            if in_libc {
+                acc.insert(name.ident.to_string());
+            }
+        }
+        syn::UseTree::Rename(rename) => {
+            if in_libc {
+                acc.insert(rename.ident.to_string());
+            }
+        }
+        syn::UseTree::Glob(_) => {
+            if in_libc {
+                *has_glob = true;
+            }
+        }
+    }
+}
+
+fn vec_to_punctuated(
+    trees: Vec<syn::UseTree>,
+) -> syn::punctuated::Punctuated<syn::UseTree, syn::token::Comma> {
+    let mut punctuated = syn::punctuated::Punctuated::new();
+    for (idx, tree) in trees.into_iter().enumerate() {
+        if idx > 0 {
+            punctuated.push_punct(Token![,](proc_macro2::Span::call_site()));
+        }
+        punctuated.push(tree);
+    }
+    punctuated
+}
+/// The mut-remover: walks the AST and clears `mut` when the bound ident matches `name`.
+struct RemoveMut {
+    name: String,
+}
+
+impl RemoveMut {
+    fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl VisitMut for RemoveMut {
+    fn visit_pat_ident_mut(&mut self, node: &mut PatIdent) {
+        // If this pattern is a direct identifier binding with the target name, remove `mut`.
+        if node.ident == self.name {
+            node.mutability = None;
+        }
+        // Recurse into subpatterns if any (usually none for simple PatIdent).
+        visit_mut::visit_pat_ident_mut(self, node);
+    }
+
+    fn visit_item_static_mut(&mut self, node: &mut ItemStatic) {
+        if node.ident == self.name {
+            node.mutability = syn::StaticMutability::None; // removes the `mut`
+        }
+        visit_mut::visit_item_static_mut(self, node);
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn remove_mut_from_type_specifiers(code: &str, var_name: &str) -> PyResult<String> {
+    let mut file: File = parse_src(code)?;
+    let mut remover = RemoveMut::new(var_name);
+    visit_mut::visit_file_mut(&mut remover, &mut file);
+
+    // Pretty-print. Use prettyplease for nicer formatting; otherwise use tokens.
+    let formatted = unparse(&file);
+    Ok(formatted)
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn get_value_type_name(code: &str, value: &str) -> PyResult<String> {
+    let ast = parse_src(code)?;
+
+    for item in ast.items.iter() {
+        match item {
+            // Handle static variables
+            syn::Item::Static(s) if s.ident == value => {
+                let static_without_value = syn::ItemStatic {
+                    attrs: vec![],                   // No attributes
+                    vis: syn::Visibility::Inherited, // No visibility modifier
+                    static_token: s.static_token,
+                    mutability: s.mutability.clone(),
+                    ident: s.ident.clone(),
+                    colon_token: s.colon_token,
+                    ty: s.ty.clone(),
+                    eq_token: s.eq_token,
+                    expr: Box::new(syn::Expr::Verbatim(Default::default())), // Empty expression
+                    semi_token: s.semi_token,
+                };
+
+                let static_item = syn::Item::Static(static_without_value);
+                let file = syn::File {
+                    shebang: None,
+                    attrs: vec![],
+                    items: vec![static_item],
+                };
+
+                let code_str = prettyplease::unparse(&file);
+
+                // Extract just the static declaration line (remove empty expression)
+                let lines: Vec<&str> = code_str.lines().collect();
+                for line in lines {
+                    let trimmed = line.trim();
+                    if trimmed.starts_with("static") && trimmed.ends_with("= ;") {
+                        // Remove the "= " part to get just the type declaration
+                        return Ok(trimmed.replace("= ", ""));
+                    }
+                }
+
+                return Ok(code_str.trim().to_string());
+            }
+
+            // Handle const variables
+            syn::Item::Const(c) if c.ident == value => {
+                let const_without_value = syn::ItemConst {
+                    attrs: vec![],                   // No attributes
+                    vis: syn::Visibility::Inherited, // No visibility modifier
+                    const_token: c.const_token,
+                    ident: c.ident.clone(),
+                    generics: c.generics.clone(),
+                    colon_token: c.colon_token,
+                    ty: c.ty.clone(),
+                    eq_token: c.eq_token,
+                    expr: Box::new(syn::Expr::Verbatim(Default::default())), // Empty expression
+                    semi_token: c.semi_token,
+                };
+
+                let const_item = syn::Item::Const(const_without_value);
+                let file = syn::File {
+                    shebang: None,
+                    attrs: vec![],
+                    items: vec![const_item],
+                };
+
+                let code_str = prettyplease::unparse(&file);
+
+                // Extract just the const declaration line (remove empty expression)
+                let lines: Vec<&str> = code_str.lines().collect();
+                for line in lines {
+                    let trimmed = line.trim();
+                    if trimmed.starts_with("const") && trimmed.ends_with("= ;") {
+                        // Remove the "= " part to get just the type declaration
+                        return Ok(trimmed.replace("= ", ""));
+                    }
+                }
+
+                return Ok(code_str.trim().to_string());
+            }
+
+            _ => continue,
+        }
+    }
+
+    Err(pyo3::exceptions::PyValueError::new_err(format!(
+        "Item '{}' not found",
+        value
+    )))
+}
+
+struct StringLiteral {
+    item: String,
+    kind: &'static str,
+    value: String,
+    line: usize,
+}
+
+/// Collects `"..."` and `b"..."` literals together with the item that contains them.
+/// Literals inside macro invocations (e.g. `println!("...")`) are included as well.
+struct StringLiteralCollector {
+    item_stack: Vec<String>,
+    literals: Vec<StringLiteral>,
+}
+
+impl StringLiteralCollector {
+    fn new() -> Self {
+        Self {
+            item_stack: Vec::new(),
+            literals: Vec::new(),
+        }
+    }
+
+    fn current_item(&self) -> String {
+        self.item_stack.join("::")
+    }
+
+    fn record(&mut self, lit: &syn::Lit) {
+        let (kind, value) = match lit {
+            syn::Lit::Str(s) => ("str", s.value()),
+            syn::Lit::ByteStr(b) => ("byte_str", String::from_utf8_lossy(&b.value()).into_owned()),
+            syn::Lit::CStr(c) => ("c_str", c.value().to_string_lossy().into_owned()),
+            _ => return,
+        };
+        self.literals.push(StringLiteral {
+            item: self.current_item(),
+            kind,
+            value,
+            line: lit.span().start().line,
+        });
+    }
+
+    fn record_tokens(&mut self, tokens: proc_macro2::TokenStream) {
+        for token in tokens {
+            match token {
+                proc_macro2::TokenTree::Group(group) => self.record_tokens(group.stream()),
+                proc_macro2::TokenTree::Literal(literal) => {
+                    let lit = syn::Lit::new(literal);
+                    self.record(&lit);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for StringLiteralCollector {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.item_stack.push(node.sig.ident.to_string());
+        visit::visit_item_fn(self, node);
+        self.item_stack.pop();
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        self.item_stack
+            .push(node.self_ty.to_token_stream().to_string());
+        visit::visit_item_impl(self, node);
+        self.item_stack.pop();
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.item_stack.push(node.sig.ident.to_string());
+        visit::visit_impl_item_fn(self, node);
+        self.item_stack.pop();
+    }
+
+    fn visit_item_static(&mut self, node: &'ast syn::ItemStatic) {
+        self.item_stack.push(node.ident.to_string());
+        visit::visit_item_static(self, node);
+        self.item_stack.pop();
+    }
+
+    fn visit_item_const(&mut self, node: &'ast syn::ItemConst) {
+        self.item_stack.push(node.ident.to_string());
+        visit::visit_item_const(self, node);
+        self.item_stack.pop();
+    }
+
+    fn visit_attribute(&mut self, _node: &'ast syn::Attribute) {
+        // doc comments and attribute arguments are not program literals
+    }
+
+    fn visit_lit(&mut self, node: &'ast syn::Lit) {
+        self.record(node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        self.record_tokens(node.tokens.clone());
+        visit::visit_macro(self, node);
+    }
+}
+
+fn string_literals_to_py(py: Python<'_>, literals: Vec<StringLiteral>) -> PyResult<PyObject> {
+    let result = PyList::empty(py);
+    for literal in literals {
+        let dict = PyDict::new(py);
+        dict.set_item("item", literal.item)?;
+        dict.set_item("kind", literal.kind)?;
+        dict.set_item("value", literal.value)?;
+        dict.set_item("line", literal.line)?;
+        result.append(dict)?;
+    }
+    Ok(result.into())
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn list_string_literals(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let mut collector = StringLiteralCollector::new();
+    collector.visit_file(&ast);
+    string_literals_to_py(py, collector.literals)
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn list_function_string_literals(
+    py: Python<'_>,
+    code: &str,
+    function_name: &str,
+) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+
+    for item in ast.items.iter() {
         if let syn::Item::Fn(f) = item {
-            // remove `extern "C"``
-            f.sig.abi = None;
-            // add `pub` before `fn`
-            f.vis = syn::Visibility::Public(Token![pub](f.span()));
+            if f.sig.ident == function_name {
+                let mut collector = StringLiteralCollector::new();
+                collector.visit_item_fn(f);
+                return string_literals_to_py(py, collector.literals);
+            }
+        }
+    }
+
+    Err(pyo3::exceptions::PyValueError::new_err(format!(
+        "Function '{}' not found",
+        function_name
+    )))
+}
+
+/// Checks whether a called function is `name` or `libc::name`.
+fn is_libc_call(func: &syn::Expr, name: &str) -> bool {
+    let syn::Expr::Path(path) = func else {
+        return false;
+    };
+    if path.qself.is_some() {
+        return false;
+    }
+    let segments = &path.path.segments;
+    match segments.len() {
+        1 => segments[0].ident == name,
+        2 => segments[0].ident == "libc" && segments[1].ident == name,
+        _ => false,
+    }
+}
+
+/// Extracts the text of a constant C string argument such as
+/// `b"...\0" as *const u8 as *const libc::c_char` or `b"...\0".as_ptr()`.
+fn const_c_string_arg(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Cast(cast) => const_c_string_arg(&cast.expr),
+        syn::Expr::Paren(paren) => const_c_string_arg(&paren.expr),
+        syn::Expr::Group(group) => const_c_string_arg(&group.expr),
+        syn::Expr::MethodCall(call) if call.method == "as_ptr" && call.args.is_empty() => {
+            const_c_string_arg(&call.receiver)
+        }
+        syn::Expr::Lit(lit) => {
+            let text = match &lit.lit {
+                syn::Lit::ByteStr(b) => String::from_utf8(b.value()).ok()?,
+                syn::Lit::CStr(c) => return c.value().to_str().ok().map(str::to_string),
+                _ => return None,
+            };
+            Some(match text.strip_suffix('\0') {
+                Some(stripped) => stripped.to_string(),
+                None => text,
+            })
+        }
+        _ => None,
+    }
+}
+
+enum FormatArgKind {
+    Plain,
+    /// An integer cast to the type its length modifier and conversion print it as
+    Cast(&'static str),
+    CStr,
+    Char,
+}
+
+/// The type a signed (`%d`) or unsigned (`%u`) conversion with `length` reads its
+/// argument as: `Some(None)` when the argument prints as it is, `None` for a
+/// modifier that does not apply to integers.
+fn printf_int_type(length: &str, signed: bool) -> Option<Option<&'static str>> {
+    Some(match (length, signed) {
+        ("hh", true) => Some("i8"),
+        ("h", true) => Some("i16"),
+        ("" | "l" | "ll" | "q" | "j" | "z" | "t", true) => None,
+        ("hh", false) => Some("u8"),
+        ("h", false) => Some("u16"),
+        ("", false) => Some("libc::c_uint"),
+        ("l", false) => Some("libc::c_ulong"),
+        ("ll" | "q", false) => Some("libc::c_ulonglong"),
+        ("j", false) => Some("libc::uintmax_t"),
+        ("z" | "t", false) => Some("libc::size_t"),
+        _ => return None,
+    })
+}
+
+/// Translates a printf format string into Rust format syntax.
+/// Returns `None` when a specifier has no faithful Rust equivalent.
+fn translate_printf_format(format: &str) -> Option<(String, Vec<FormatArgKind>)> {
+    let mut out = String::new();
+    let mut kinds = Vec::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => out.push_str("{{"),
+            '}' => out.push_str("}}"),
+            '%' => {
+                if chars.peek() == Some(&'%') {
+                    chars.next();
+                    out.push('%');
+                    continue;
+                }
+
+                let mut left_align = false;
+                let mut zero_pad = false;
+                let mut plus_sign = false;
+                let mut alternate = false;
+                while let Some(&flag) = chars.peek() {
+                    match flag {
+                        '-' => left_align = true,
+                        '0' => zero_pad = true,
+                        '+' => plus_sign = true,
+                        '#' => alternate = true,
+                        ' ' | '\'' => return None,
+                        _ => break,
+                    }
+                    chars.next();
+                }
+
+                let mut width = String::new();
+                while let Some(&digit) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    width.push(digit);
+                    chars.next();
+                }
+
+                let mut precision = None;
+                if chars.peek() == Some(&'.') {
+                    chars.next();
+                    let mut digits = String::new();
+                    while let Some(&digit) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                        digits.push(digit);
+                        chars.next();
+                    }
+                    precision = Some(if digits.is_empty() {
+                        "0".to_string()
+                    } else {
+                        digits
+                    });
+                }
+
+                let mut length = String::new();
+                while let Some(&modifier) = chars.peek() {
+                    if matches!(modifier, 'h' | 'l' | 'L' | 'q' | 'j' | 'z' | 't') {
+                        length.push(modifier);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                let conversion = chars.next()?;
+                let int_kind = |signed: bool| {
+                    Some(match printf_int_type(&length, signed)? {
+                        Some(ty) => FormatArgKind::Cast(ty),
+                        None => FormatArgKind::Plain,
+                    })
+                };
+                let (kind, ty, numeric) = match conversion {
+                    'd' | 'i' => (int_kind(true)?, "", true),
+                    'u' => (int_kind(false)?, "", true),
+                    'x' => (int_kind(false)?, "x", true),
+                    'X' if !alternate => (int_kind(false)?, "X", true),
+                    'o' if !alternate => (int_kind(false)?, "o", true),
+                    'f' | 'F' => {
+                        if precision.is_none() {
+                            precision = Some("6".to_string());
+                        }
+                        (FormatArgKind::Plain, "", true)
+                    }
+                    // wide `%ls`/`%lc` take `wchar_t`
+                    's' if length.is_empty() => (FormatArgKind::CStr, "", false),
+                    'c' if length.is_empty() => (FormatArgKind::Char, "", false),
+                    _ => return None,
+                };
+                if !numeric && (zero_pad || plus_sign || alternate) {
+                    return None;
+                }
+                if conversion != 's' && !matches!(conversion, 'f' | 'F') && precision.is_some() {
+                    return None;
+                }
+
+                let mut spec = String::new();
+                if !width.is_empty() {
+                    if left_align {
+                        spec.push('<');
+                    } else if !numeric {
+                        // C right-aligns strings, Rust left-aligns them by default
+                        spec.push('>');
+                    }
+                }
+                if plus_sign {
+                    spec.push('+');
+                }
+                if alternate {
+                    spec.push('#');
+                }
+                if zero_pad && !left_align && !width.is_empty() {
+                    spec.push('0');
+                }
+                spec.push_str(&width);
+                if let Some(precision) = precision {
+                    spec.push('.');
+                    spec.push_str(&precision);
+                }
+                spec.push_str(ty);
+
+                if spec.is_empty() {
+                    out.push_str("{}");
+                } else {
+                    out.push_str(&format!("{{:{spec}}}"));
+                }
+                kinds.push(kind);
+            }
+            other => out.push(other),
+        }
+    }
+
+    Some((out, kinds))
+}
+
+/// Builds a `print!`/`println!` style macro call for a printf-like format string,
+/// or `None` when the format string or the argument count cannot be translated.
+fn printf_to_macro_stmt(
+    format: &str,
+    args: &[syn::Expr],
+    macro_base: &str,
+    semi: Token![;],
+) -> Option<syn::Stmt> {
+    let (translated, kinds) = translate_printf_format(format)?;
+    if kinds.len() != args.len() {
+        return None;
+    }
+    // `char` prints as UTF-8, so only ASCII bytes print the same as `%c`
+    if kinds
+        .iter()
+        .zip(args)
+        .any(|(kind, arg)| matches!(kind, FormatArgKind::Char) && !is_ascii_int_literal(arg))
+    {
+        return None;
+    }
+
+    let (macro_name, translated) = match translated.strip_suffix('\n') {
+        Some(stripped) => (format!("{macro_base}ln"), stripped.to_string()),
+        None => (macro_base.to_string(), translated),
+    };
+    let macro_ident = syn::Ident::new(&macro_name, Span::call_site());
+    let format_lit = LitStr::new(&translated, Span::call_site());
+    let converted_args: Vec<syn::Expr> = args
+        .iter()
+        .zip(kinds.iter())
+        .map(|(arg, kind)| match kind {
+            FormatArgKind::Plain => arg.clone(),
+            FormatArgKind::Cast(ty) => {
+                let ty: syn::Type = syn::parse_str(ty).expect("valid integer type");
+                let arg = match arg {
+                    syn::Expr::Path(_)
+                    | syn::Expr::Lit(_)
+                    | syn::Expr::Call(_)
+                    | syn::Expr::MethodCall(_)
+                    | syn::Expr::Field(_)
+                    | syn::Expr::Index(_)
+                    | syn::Expr::Paren(_)
+                    | syn::Expr::Cast(_) => arg.clone(),
+                    _ => parse_quote!((#arg)),
+                };
+                parse_quote!(#arg as #ty)
+            }
+            FormatArgKind::CStr => parse_quote!(std::ffi::CStr::from_ptr(#arg).to_string_lossy()),
+            FormatArgKind::Char => parse_quote!((#arg as u8 as char)),
+        })
+        .collect();
+
+    let expr: syn::Expr = if converted_args.is_empty() {
+        parse_quote!(#macro_ident!(#format_lit))
+    } else {
+        parse_quote!(#macro_ident!(#format_lit, #(#converted_args),*))
+    };
+    Some(syn::Stmt::Expr(expr, Some(semi)))
+}
+
+/// Whether `expr` is an integer literal below 128, possibly behind casts.
+fn is_ascii_int_literal(expr: &syn::Expr) -> bool {
+    match strip_parens(expr) {
+        syn::Expr::Cast(cast) => is_ascii_int_literal(&cast.expr),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_parse::<u8>().is_ok_and(|value| value.is_ascii()),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Byte(byte),
+            ..
+        }) => byte.value().is_ascii(),
+        _ => false,
+    }
+}
+
+/// Rewrites statement-position `printf` calls with constant format strings into `print!`/`println!`.
+/// C stdio and Rust buffer stdout separately, so a file is only rewritten
+/// when no C call writing to stdout is left anywhere in it.
+struct PrintfRewriter;
+
+/// C stdio functions writing to stdout, and those writing to a stream argument.
+const C_STDOUT_WRITERS: &[&str] = &["printf", "vprintf", "puts", "putchar"];
+const C_STREAM_WRITERS: &[&str] = &["fprintf", "vfprintf", "fputs", "fputc", "putc", "fwrite"];
+
+/// Whether `file` calls a C stdio function that writes to stdout.
+fn writes_c_stdout(file: &syn::File) -> bool {
+    struct Finder(bool);
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+            if C_STDOUT_WRITERS
+                .iter()
+                .any(|name| is_libc_call(&call.func, name))
+                || C_STREAM_WRITERS
+                    .iter()
+                    .any(|name| is_libc_call(&call.func, name))
+                    && call.args.iter().any(|arg| is_libc_call(arg, "stdout"))
+            {
+                self.0 = true;
+            }
+            visit::visit_expr_call(self, call);
+        }
+    }
+    let mut finder = Finder(false);
+    finder.visit_file(file);
+    finder.0
+}
+
+impl VisitMut for PrintfRewriter {
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Expr(syn::Expr::Call(call), Some(semi)) = stmt {
+            if is_libc_call(&call.func, "printf") && !call.args.is_empty() {
+                let args: Vec<syn::Expr> = call.args.iter().cloned().collect();
+                if let Some(format) = const_c_string_arg(&args[0]) {
+                    if let Some(new_stmt) =
+                        printf_to_macro_stmt(&format, &args[1..], "print", *semi)
+                    {
+                        *stmt = new_stmt;
+                        return;
+                    }
+                }
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn rewrite_printf_to_println(code: &str) -> PyResult<String> {
+    let original = parse_src(code)?;
+    let mut ast = original.clone();
+    PrintfRewriter.visit_file_mut(&mut ast);
+    if writes_c_stdout(&ast) {
+        return Ok(unparse(&original));
+    }
+    Ok(unparse(&ast))
+}
+
+fn is_stderr_stream(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Paren(paren) => is_stderr_stream(&paren.expr),
+        syn::Expr::Group(group) => is_stderr_stream(&group.expr),
+        syn::Expr::Path(_) => is_libc_call(expr, "stderr"),
+        _ => false,
+    }
+}
+
+fn escape_format_text(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// Rewrites `fprintf(stderr, ...)` and `fputs(s, stderr)` statements into `eprint!`/`eprintln!`.
+struct StderrRewriter;
+
+impl StderrRewriter {
+    fn rewrite_call(call: &syn::ExprCall, semi: Token![;]) -> Option<syn::Stmt> {
+        let args: Vec<syn::Expr> = call.args.iter().cloned().collect();
+        if is_libc_call(&call.func, "fprintf") && args.len() >= 2 && is_stderr_stream(&args[0]) {
+            let format = const_c_string_arg(&args[1])?;
+            return printf_to_macro_stmt(&format, &args[2..], "eprint", semi);
+        }
+        if is_libc_call(&call.func, "fputs") && args.len() == 2 && is_stderr_stream(&args[1]) {
+            return match const_c_string_arg(&args[0]) {
+                Some(text) => {
+                    let (macro_name, text) = match text.strip_suffix('\n') {
+                        Some(stripped) => ("eprintln", stripped),
+                        None => ("eprint", text.as_str()),
+                    };
+                    let macro_ident = syn::Ident::new(macro_name, Span::call_site());
+                    let lit = LitStr::new(&escape_format_text(text), Span::call_site());
+                    Some(syn::Stmt::Expr(
+                        parse_quote!(#macro_ident!(#lit)),
+                        Some(semi),
+                    ))
+                }
+                None => {
+                    let arg = &args[0];
+                    Some(syn::Stmt::Expr(
+                        parse_quote!(
+                            eprint!("{}", std::ffi::CStr::from_ptr(#arg).to_string_lossy())
+                        ),
+                        Some(semi),
+                    ))
+                }
+            };
+        }
+        None
+    }
+}
+
+impl VisitMut for StderrRewriter {
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Expr(syn::Expr::Call(call), Some(semi)) = stmt {
+            if let Some(new_stmt) = Self::rewrite_call(call, *semi) {
+                *stmt = new_stmt;
+                return;
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn rewrite_fprintf_to_eprintln(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    StderrRewriter.visit_file_mut(&mut ast);
+    Ok(unparse(&ast))
+}
+
+fn is_zero_literal(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_digits() == "0",
+        syn::Expr::Paren(paren) => is_zero_literal(&paren.expr),
+        // `0 as libc::c_int as *mut T`
+        syn::Expr::Cast(cast) if !matches!(*cast.ty, syn::Type::Ptr(_)) => {
+            is_zero_literal(&cast.expr)
+        }
+        _ => false,
+    }
+}
+
+fn is_isize_type(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("isize"))
+}
+
+/// Normalizes c2rust pointer idioms:
+/// - `p.offset(i as isize)` -> `p.add(i as usize)` and `p.offset(-(i as isize))` -> `p.sub(i as usize)`
+/// - `e as *const T as *mut T` -> `e as *mut T`
+/// - `0 as *mut T` -> `std::ptr::null_mut::<T>()` (and `null` for `*const T`)
+///
+/// The offset rewrite only applies when `i` is an unsigned value or a literal,
+/// since a negative `i` would wrap to a huge `usize`; the cast collapse only
+/// when `e` is known to be a raw pointer, as a reference cannot be cast to a
+/// pointer of a different mutability.
+#[derive(Default)]
+struct PointerArithmeticNormalizer {
+    /// Declared types of the parameters and typed `let` bindings in scope.
+    var_types: HashMap<String, syn::Type>,
+}
+
+impl PointerArithmeticNormalizer {
+    fn var_type(&self, expr: &syn::Expr) -> Option<&syn::Type> {
+        path_expr_name(strip_parens(expr)).and_then(|name| self.var_types.get(&name))
+    }
+
+    /// Whether `expr` has an unsigned type or is an integer literal.
+    fn is_non_negative(&self, expr: &syn::Expr) -> bool {
+        match strip_parens(expr) {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(_),
+                ..
+            }) => true,
+            syn::Expr::Cast(cast) => is_unsigned_integer_type(&cast.ty),
+            syn::Expr::MethodCall(call) => call.method == "len" && call.args.is_empty(),
+            syn::Expr::Binary(binary) => {
+                matches!(
+                    binary.op,
+                    syn::BinOp::Add(_)
+                        | syn::BinOp::Mul(_)
+                        | syn::BinOp::Div(_)
+                        | syn::BinOp::Rem(_)
+                        | syn::BinOp::BitAnd(_)
+                        | syn::BinOp::BitOr(_)
+                        | syn::BinOp::Shr(_)
+                ) && self.is_non_negative(&binary.left)
+                    && self.is_non_negative(&binary.right)
+            }
+            other => self.var_type(other).is_some_and(is_unsigned_integer_type),
+        }
+    }
+
+    fn rewrite_offset(&self, call: &syn::ExprMethodCall) -> Option<syn::Expr> {
+        if call.method != "offset" || call.args.len() != 1 {
+            return None;
+        }
+        let receiver = &call.receiver;
+        let (method, index) = match &call.args[0] {
+            syn::Expr::Cast(cast) if is_isize_type(&cast.ty) => ("add", &cast.expr),
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            }) => match strip_parens(expr) {
+                syn::Expr::Cast(cast) if is_isize_type(&cast.ty) => ("sub", &cast.expr),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if !self.is_non_negative(index) {
+            return None;
+        }
+        let method = syn::Ident::new(method, call.method.span());
+        let index: syn::Expr = match strip_parens(index) {
+            lit @ syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) if int.suffix().is_empty() => lit.clone(),
+            syn::Expr::Cast(cast) if matches!(&*cast.ty, syn::Type::Path(p) if p.path.is_ident("usize")) => {
+                syn::Expr::Cast(cast.clone())
+            }
+            other @ (syn::Expr::Path(_)
+            | syn::Expr::Field(_)
+            | syn::Expr::MethodCall(_)
+            | syn::Expr::Call(_)) => parse_quote!(#other as usize),
+            other => parse_quote!((#other) as usize),
+        };
+        Some(parse_quote!(#receiver.#method(#index)))
+    }
+
+    fn rewrite_cast(
+        cast: &syn::ExprCast,
+        var_types: &HashMap<String, syn::Type>,
+    ) -> Option<syn::Expr> {
+        let syn::Type::Ptr(outer_ptr) = &*cast.ty else {
+            return None;
+        };
+
+        if is_zero_literal(&cast.expr) {
+            let elem = &outer_ptr.elem;
+            let func = if outer_ptr.mutability.is_some() {
+                quote!(null_mut)
+            } else {
+                quote!(null)
+            };
+            return Some(if matches!(**elem, syn::Type::Infer(_)) {
+                parse_quote!(std::ptr::#func())
+            } else {
+                parse_quote!(std::ptr::#func::<#elem>())
+            });
+        }
+
+        if let syn::Expr::Cast(inner) = &*cast.expr {
+            if let syn::Type::Ptr(inner_ptr) = &*inner.ty {
+                let same_pointee = inner_ptr.elem.to_token_stream().to_string()
+                    == outer_ptr.elem.to_token_stream().to_string();
+                // references can only be cast to a pointer with matching mutability
+                let source_is_pointer = match strip_parens(&inner.expr) {
+                    syn::Expr::Cast(source) => matches!(*source.ty, syn::Type::Ptr(_)),
+                    other => path_expr_name(other)
+                        .and_then(|name| var_types.get(&name))
+                        .is_some_and(|ty| matches!(ty, syn::Type::Ptr(_))),
+                };
+                if same_pointee && source_is_pointer {
+                    let mut collapsed = cast.clone();
+                    collapsed.expr = inner.expr.clone();
+                    return Some(syn::Expr::Cast(collapsed));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Records the names `pat` binds: with their type for `name: Type`,
+    /// otherwise as shadowing any outer binding of an unknown type.
+    fn bind_pat(&mut self, pat: &syn::Pat) {
+        struct Names(Vec<String>);
+        impl<'ast> Visit<'ast> for Names {
+            fn visit_pat_ident(&mut self, node: &'ast PatIdent) {
+                self.0.push(node.ident.to_string());
+                visit::visit_pat_ident(self, node);
+            }
+        }
+        if let syn::Pat::Type(pat_type) = pat {
+            if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                self.var_types
+                    .insert(pat_ident.ident.to_string(), (*pat_type.ty).clone());
+                return;
+            }
+        }
+        let mut names = Names(Vec::new());
+        names.visit_pat(pat);
+        for name in names.0 {
+            self.var_types.remove(&name);
+        }
+    }
+
+    fn visit_fn_mut(&mut self, sig: &syn::Signature, block: &mut syn::Block) {
+        let outer = mem::take(&mut self.var_types);
+        for input in sig.inputs.iter() {
+            if let syn::FnArg::Typed(pat_type) = input {
+                if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                    self.var_types
+                        .insert(pat_ident.ident.to_string(), (*pat_type.ty).clone());
+                }
+            }
+        }
+        self.visit_block_mut(block);
+        self.var_types = outer;
+    }
+}
+
+fn strip_parens(expr: &syn::Expr) -> &syn::Expr {
+    match expr {
+        syn::Expr::Paren(paren) => strip_parens(&paren.expr),
+        syn::Expr::Group(group) => strip_parens(&group.expr),
+        other => other,
+    }
+}
+
+impl VisitMut for PointerArithmeticNormalizer {
+    fn visit_item_fn_mut(&mut self, f: &mut syn::ItemFn) {
+        self.visit_fn_mut(&f.sig, &mut f.block);
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, f: &mut syn::ImplItemFn) {
+        self.visit_fn_mut(&f.sig, &mut f.block);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        let outer = self.var_types.clone();
+        visit_mut::visit_block_mut(self, block);
+        self.var_types = outer;
+    }
+
+    fn visit_local_mut(&mut self, local: &mut syn::Local) {
+        visit_mut::visit_local_mut(self, local);
+        self.bind_pat(&local.pat);
+    }
+
+    fn visit_expr_for_loop_mut(&mut self, node: &mut syn::ExprForLoop) {
+        self.visit_expr_mut(&mut node.expr);
+        let outer = self.var_types.clone();
+        self.bind_pat(&node.pat);
+        self.visit_block_mut(&mut node.body);
+        self.var_types = outer;
+    }
+
+    fn visit_expr_closure_mut(&mut self, node: &mut syn::ExprClosure) {
+        let outer = self.var_types.clone();
+        for input in node.inputs.iter() {
+            self.bind_pat(input);
+        }
+        self.visit_expr_mut(&mut node.body);
+        self.var_types = outer;
+    }
+
+    fn visit_arm_mut(&mut self, arm: &mut syn::Arm) {
+        let outer = self.var_types.clone();
+        self.bind_pat(&arm.pat);
+        if let Some((_, guard)) = arm.guard.as_mut() {
+            self.visit_expr_mut(guard);
+        }
+        self.visit_expr_mut(&mut arm.body);
+        self.var_types = outer;
+    }
+
+    fn visit_expr_if_mut(&mut self, node: &mut syn::ExprIf) {
+        self.visit_expr_mut(&mut node.cond);
+        let outer = self.var_types.clone();
+        if let syn::Expr::Let(expr_let) = &*node.cond {
+            self.bind_pat(&expr_let.pat);
+        }
+        self.visit_block_mut(&mut node.then_branch);
+        self.var_types = outer;
+        if let Some((_, else_branch)) = node.else_branch.as_mut() {
+            self.visit_expr_mut(else_branch);
+        }
+    }
+
+    fn visit_expr_while_mut(&mut self, node: &mut syn::ExprWhile) {
+        self.visit_expr_mut(&mut node.cond);
+        let outer = self.var_types.clone();
+        if let syn::Expr::Let(expr_let) = &*node.cond {
+            self.bind_pat(&expr_let.pat);
+        }
+        self.visit_block_mut(&mut node.body);
+        self.var_types = outer;
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        loop {
+            let rewritten = match expr {
+                syn::Expr::MethodCall(call) => self.rewrite_offset(call),
+                syn::Expr::Cast(cast) => Self::rewrite_cast(cast, &self.var_types),
+                _ => None,
+            };
+            match rewritten {
+                Some(new_expr) => *expr = new_expr,
+                None => break,
+            }
+        }
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn normalize_pointer_arithmetic(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    PointerArithmeticNormalizer::default().visit_file_mut(&mut ast);
+    Ok(unparse(&ast))
+}
+
+/// Drops c2rust's `as libc::c_int` on unsuffixed integer literals (they infer
+/// to the same `i32`) and turns `0 as *mut T` into `std::ptr::null_mut()`.
+struct C2RustExprCleaner;
+
+impl C2RustExprCleaner {
+    fn is_c_int(ty: &syn::Type) -> bool {
+        let syn::Type::Path(type_path) = ty else {
+            return false;
+        };
+        let segments: Vec<String> = type_path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        segments == ["libc", "c_int"]
+    }
+
+    fn rewrite(expr: &syn::Expr) -> Option<syn::Expr> {
+        let syn::Expr::Cast(cast) = expr else {
+            return None;
+        };
+        if matches!(*cast.ty, syn::Type::Ptr(_)) && is_zero_literal(&cast.expr) {
+            return PointerArithmeticNormalizer::rewrite_cast(cast, &HashMap::new());
+        }
+        let syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) = &*cast.expr
+        else {
+            return None;
+        };
+        let fits = int.base10_parse::<i32>().is_ok();
+        if Self::is_c_int(&cast.ty) && int.suffix().is_empty() && fits {
+            return Some((*cast.expr).clone());
+        }
+        None
+    }
+}
+
+impl VisitMut for C2RustExprCleaner {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        if let Some(new_expr) = Self::rewrite(expr) {
+            *expr = new_expr;
+        }
+    }
+
+    fn visit_expr_method_call_mut(&mut self, call: &mut syn::ExprMethodCall) {
+        // `(1 as libc::c_int).abs()` needs the cast to pick a type
+        let mut receiver = &mut *call.receiver;
+        while let syn::Expr::Paren(paren) = receiver {
+            receiver = &mut paren.expr;
+        }
+        visit_mut::visit_expr_mut(self, receiver);
+        for arg in call.args.iter_mut() {
+            self.visit_expr_mut(arg);
+        }
+    }
+}
+
+/// Rewrites `return` in the body of c2rust's `main_0` for use inside `main`:
+/// `return 0` becomes `return`, other codes exit the process.
+struct MainReturnRewriter;
+
+impl MainReturnRewriter {
+    fn exit_code(expr: &syn::Expr) -> Option<syn::Expr> {
+        match int_literal_value(expr) {
+            Some(0) => None,
+            Some(_) => Some(parse_quote!(::std::process::exit(#expr))),
+            None => Some(parse_quote!(::std::process::exit(#expr as i32))),
+        }
+    }
+}
+
+impl VisitMut for MainReturnRewriter {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        if let syn::Expr::Return(ret) = expr {
+            if let Some(value) = ret.expr.take() {
+                if let Some(exit) = Self::exit_code(&value) {
+                    *expr = exit;
+                }
+            }
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, _node: &mut syn::ExprClosure) {}
+
+    fn visit_item_mut(&mut self, _node: &mut syn::Item) {}
+}
+
+/// Folds c2rust's `main_0(argc, argv) -> c_int` and the `main` wrapper that
+/// calls it back into a single `pub fn main()`.
+fn restore_c2rust_main(items: &mut Vec<syn::Item>) {
+    let Some(main_0_idx) = items
+        .iter()
+        .position(|item| matches!(item, syn::Item::Fn(f) if f.sig.ident == "main_0"))
+    else {
+        return;
+    };
+    let Some(main_idx) = items
+        .iter()
+        .position(|item| matches!(item, syn::Item::Fn(f) if f.sig.ident == "main"))
+    else {
+        return;
+    };
+    let (syn::Item::Fn(main_0), syn::Item::Fn(main)) = (&items[main_0_idx], &items[main_idx])
+    else {
+        return;
+    };
+
+    // the wrapper ends with `unsafe { ::std::process::exit(main_0(args..) as i32) }`
+    struct Main0Call(Option<Vec<syn::Expr>>);
+    impl<'ast> Visit<'ast> for Main0Call {
+        fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+            if matches!(&*call.func, syn::Expr::Path(p) if p.path.is_ident("main_0")) {
+                self.0 = Some(call.args.iter().cloned().collect());
+            }
+            visit::visit_expr_call(self, call);
+        }
+    }
+    let Some((exit_stmt, prologue)) = main.block.stmts.split_last() else {
+        return;
+    };
+    let mut call = Main0Call(None);
+    call.visit_stmt(exit_stmt);
+    let Some(args) = call.0 else {
+        return;
+    };
+    if args.len() != main_0.sig.inputs.len() {
+        return;
+    }
+
+    let bindings = main_0
+        .sig
+        .inputs
+        .iter()
+        .zip(args.iter())
+        .filter_map(|(input, arg)| {
+            let syn::FnArg::Typed(pat_type) = input else {
+                return None;
+            };
+            let (pat, ty) = (&pat_type.pat, &pat_type.ty);
+            Some(quote!(let #pat: #ty = #arg;))
+        });
+    let mut body = (*main_0.block).clone();
+    MainReturnRewriter.visit_block_mut(&mut body);
+    if let Some(syn::Stmt::Expr(tail, None)) = body.stmts.last_mut() {
+        if let Some(exit) = MainReturnRewriter::exit_code(tail) {
+            *tail = exit;
+        } else {
+            body.stmts.pop();
+        }
+    }
+    let body_stmts = &body.stmts;
+    let attrs = &main.attrs;
+    let restored: syn::ItemFn = parse_quote! {
+        #(#attrs)*
+        pub fn main() {
+            #(#prologue)*
+            #(#bindings)*
+            unsafe {
+                #(#body_stmts)*
+            }
+        }
+    };
+
+    items[main_idx] = syn::Item::Fn(restored);
+    items.remove(main_0_idx);
+}
+
+/// Strips c2rust boilerplate that only lengthens prompts: the crate-level
+/// `#![allow(...)]` list, the `main_0` wrapper around `main`, redundant
+/// `as libc::c_int` casts on integer literals and `0 as *mut T` null pointers.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn cleanup_c2rust_artifacts(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    ast.attrs.retain(|attr| {
+        !(matches!(attr.style, AttrStyle::Inner(_)) && attr.path().is_ident("allow"))
+    });
+    restore_c2rust_main(&mut ast.items);
+    C2RustExprCleaner.visit_file_mut(&mut ast);
+    Ok(unparse(&ast))
+}
+
+/// Rewrites `*const i8` / `*mut i8` to `libc::c_char` pointers in casts and in
+/// `extern "C"` signatures. `c_char` is `u8` on aarch64, so hard-coded `i8`
+/// pointers handed to C only compile on x86.
+struct CCharNormalizer;
+
+impl CCharNormalizer {
+    /// Replaces an `i8` pointee at any pointer depth; returns whether it did.
+    fn rewrite_pointer(ty: &mut syn::Type) -> bool {
+        let syn::Type::Ptr(ptr) = ty else {
+            return false;
+        };
+        if matches!(&*ptr.elem, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("i8")) {
+            *ptr.elem = parse_quote!(libc::c_char);
+            return true;
+        }
+        Self::rewrite_pointer(&mut ptr.elem)
+    }
+
+    fn rewrite_signature(sig: &mut syn::Signature) {
+        for input in sig.inputs.iter_mut() {
+            if let syn::FnArg::Typed(pat_type) = input {
+                Self::rewrite_pointer(&mut pat_type.ty);
+            }
+        }
+        if let syn::ReturnType::Type(_, ty) = &mut sig.output {
+            Self::rewrite_pointer(ty);
+        }
+    }
+}
+
+impl VisitMut for CCharNormalizer {
+    fn visit_expr_cast_mut(&mut self, cast: &mut syn::ExprCast) {
+        visit_mut::visit_expr_cast_mut(self, cast);
+        Self::rewrite_pointer(&mut cast.ty);
+    }
+
+    fn visit_item_fn_mut(&mut self, item: &mut syn::ItemFn) {
+        if item.sig.abi.is_some() {
+            Self::rewrite_signature(&mut item.sig);
+        }
+        visit_mut::visit_item_fn_mut(self, item);
+    }
+
+    fn visit_foreign_item_fn_mut(&mut self, item: &mut syn::ForeignItemFn) {
+        Self::rewrite_signature(&mut item.sig);
+        visit_mut::visit_foreign_item_fn_mut(self, item);
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn normalize_c_char_pointers(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    CCharNormalizer.visit_file_mut(&mut ast);
+    Ok(unparse(&ast))
+}
+
+/// Free functions that are `unsafe` regardless of where they are imported from.
+const UNSAFE_FN_NAMES: &[&str] = &[
+    "from_raw_parts",
+    "from_raw_parts_mut",
+    "from_ptr",
+    "from_raw",
+    "transmute",
+    "transmute_copy",
+    "zeroed",
+    "uninitialized",
+    "copy_nonoverlapping",
+    "write_bytes",
+    "read_unaligned",
+    "read_volatile",
+    "write_unaligned",
+    "write_volatile",
+    "drop_in_place",
+    "from_utf8_unchecked",
+    "from_utf8_unchecked_mut",
+    "from_bytes_with_nul_unchecked",
+    "unreachable_unchecked",
+    "alloc",
+    "alloc_zeroed",
+    "dealloc",
+    "realloc",
+];
+
+/// Functions that are only `unsafe` in `std::ptr` (e.g. `ptr::read` vs `mem::replace`).
+const UNSAFE_PTR_FN_NAMES: &[&str] = &["read", "write", "copy", "replace", "swap"];
+
+/// Methods that are `unsafe` on raw pointers and other common receivers.
+/// Some of them have safe namesakes (`Option::as_ref`), so a match only means
+/// the call *may* need unsafe.
+const UNSAFE_METHOD_NAMES: &[&str] = &[
+    "offset",
+    "add",
+    "sub",
+    "offset_from",
+    "read",
+    "write",
+    "read_unaligned",
+    "write_unaligned",
+    "read_volatile",
+    "write_volatile",
+    "as_ref",
+    "as_mut",
+    "as_uninit_ref",
+    "copy_to",
+    "copy_from",
+    "copy_to_nonoverlapping",
+    "copy_from_nonoverlapping",
+    "write_bytes",
+    "drop_in_place",
+    "get_unchecked",
+    "get_unchecked_mut",
+    "assume_init",
+    "assume_init_read",
+    "assume_init_ref",
+    "assume_init_mut",
+    "unwrap_unchecked",
+    "set_len",
+    "as_bytes_mut",
+];
+
+/// File-level facts needed to decide whether an expression requires `unsafe`.
+#[derive(Default)]
+struct UnsafeContext {
+    unsafe_fns: HashSet<String>,
+    safe_fns: HashSet<String>,
+    unsafe_methods: HashSet<String>,
+    extern_items: HashSet<String>,
+    static_muts: HashSet<String>,
+    union_fields: HashSet<String>,
+}
+
+impl UnsafeContext {
+    fn from_file(file: &syn::File) -> Self {
+        let mut ctx = UnsafeContext::default();
+        ctx.collect_items(&file.items);
+        ctx
+    }
+
+    fn collect_items(&mut self, items: &[syn::Item]) {
+        for item in items {
+            match item {
+                syn::Item::Fn(f) => {
+                    let name = f.sig.ident.to_string();
+                    if f.sig.unsafety.is_some() {
+                        self.unsafe_fns.insert(name);
+                    } else {
+                        self.safe_fns.insert(name);
+                    }
+                }
+                syn::Item::Impl(imp) => {
+                    for impl_item in imp.items.iter() {
+                        if let syn::ImplItem::Fn(f) = impl_item {
+                            if f.sig.unsafety.is_some() {
+                                self.unsafe_methods.insert(f.sig.ident.to_string());
+                            }
+                        }
+                    }
+                }
+                syn::Item::ForeignMod(foreign) => {
+                    for foreign_item in foreign.items.iter() {
+                        match foreign_item {
+                            syn::ForeignItem::Fn(f) => {
+                                self.extern_items.insert(f.sig.ident.to_string());
+                            }
+                            syn::ForeignItem::Static(s) => {
+                                self.extern_items.insert(s.ident.to_string());
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                syn::Item::Static(s) => {
+                    if matches!(s.mutability, syn::StaticMutability::Mut(_)) {
+                        self.static_muts.insert(s.ident.to_string());
+                    }
+                }
+                syn::Item::Union(u) => {
+                    for field in u.fields.named.iter() {
+                        if let Some(ident) = &field.ident {
+                            self.union_fields.insert(ident.to_string());
+                        }
+                    }
+                }
+                syn::Item::Mod(m) => {
+                    if let Some((_, inner)) = &m.content {
+                        self.collect_items(inner);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn call_requires_unsafe(&self, path: &syn::Path) -> bool {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        let Some(last) = segments.last() else {
+            return false;
+        };
+        if UNSAFE_FN_NAMES.contains(&last.as_str()) {
+            return true;
+        }
+        if UNSAFE_PTR_FN_NAMES.contains(&last.as_str()) && segments.iter().any(|s| s == "ptr") {
+            return true;
+        }
+        if segments[0] == "libc" {
+            return true;
+        }
+        if self.unsafe_fns.contains(last) || self.extern_items.contains(last) {
+            return true;
+        }
+        if segments.len() == 1 {
+            // Tuple structs/variants and prelude helpers are safe; any other
+            // function we cannot see may be an `unsafe fn` defined elsewhere.
+            let is_constructor = last.chars().next().is_some_and(|c| c.is_uppercase());
+            let is_prelude = matches!(last.as_str(), "drop" | "panic" | "assert");
+            return !(is_constructor || is_prelude || self.safe_fns.contains(last));
+        }
+        if matches!(segments[0].as_str(), "crate" | "self" | "super") {
+            return !self.safe_fns.contains(last);
+        }
+        // `Type::f()`, where `f` is an `unsafe fn` of some impl
+        self.unsafe_methods.contains(last)
+    }
+}
+
+/// Binding names that can be dereferenced without `unsafe` in a function: those
+/// bound only with reference or `Box` types. Names bound anywhere with a raw
+/// pointer or an unknown type are excluded.
+fn collect_safe_deref_bindings(sig: &syn::Signature, block: &syn::Block) -> HashSet<String> {
+    struct BindingCollector {
+        safe: HashSet<String>,
+        unsafe_or_unknown: HashSet<String>,
+    }
+
+    fn is_safe_deref_type(ty: &syn::Type) -> bool {
+        match ty {
+            syn::Type::Reference(_) => true,
+            syn::Type::Paren(p) => is_safe_deref_type(&p.elem),
+            syn::Type::Path(p) => p
+                .path
+                .segments
+                .last()
+                .is_some_and(|s| matches!(s.ident.to_string().as_str(), "Box" | "Rc" | "Arc")),
+            _ => false,
+        }
+    }
+
+    impl BindingCollector {
+        fn bind(&mut self, pat: &syn::Pat, safe: bool) {
+            if let syn::Pat::Ident(ident) = pat {
+                let name = ident.ident.to_string();
+                if safe {
+                    self.safe.insert(name);
+                } else {
+                    self.unsafe_or_unknown.insert(name);
+                }
+            } else {
+                // destructuring patterns bind names of unknown type
+                let mut idents = PatIdentCollector { names: Vec::new() };
+                idents.visit_pat(pat);
+                self.unsafe_or_unknown.extend(idents.names);
+            }
+        }
+    }
+
+    struct PatIdentCollector {
+        names: Vec<String>,
+    }
+
+    impl<'ast> Visit<'ast> for PatIdentCollector {
+        fn visit_pat_ident(&mut self, node: &'ast PatIdent) {
+            self.names.push(node.ident.to_string());
+            visit::visit_pat_ident(self, node);
+        }
+    }
+
+    impl<'ast> Visit<'ast> for BindingCollector {
+        fn visit_local(&mut self, local: &'ast syn::Local) {
+            match &local.pat {
+                syn::Pat::Type(pat_type) => {
+                    self.bind(&pat_type.pat, is_safe_deref_type(&pat_type.ty));
+                }
+                pat => {
+                    let safe = local.init.as_ref().is_some_and(|init| {
+                        matches!(strip_parens(&init.expr), syn::Expr::Reference(_))
+                    });
+                    self.bind(pat, safe);
+                }
+            }
+            // the pattern is bound above; only the initializer can bind more names
+            if let Some(init) = &local.init {
+                self.visit_local_init(init);
+            }
+        }
+
+        fn visit_pat_ident(&mut self, node: &'ast PatIdent) {
+            // bindings introduced by closures, `for`, `match` and `if let`
+            self.unsafe_or_unknown.insert(node.ident.to_string());
+            visit::visit_pat_ident(self, node);
+        }
+    }
+
+    let mut collector = BindingCollector {
+        safe: HashSet::new(),
+        unsafe_or_unknown: HashSet::new(),
+    };
+    for input in sig.inputs.iter() {
+        if let syn::FnArg::Typed(pat_type) = input {
+            collector.bind(&pat_type.pat, is_safe_deref_type(&pat_type.ty));
+        }
+    }
+    for stmt in block.stmts.iter() {
+        match stmt {
+            syn::Stmt::Local(local) => collector.visit_local(local),
+            other => collector.visit_stmt(other),
+        }
+    }
+
+    collector
+        .safe
+        .difference(&collector.unsafe_or_unknown)
+        .cloned()
+        .collect()
+}
+
+/// Looks for operations that require an `unsafe` context. Nested `unsafe`
+/// blocks and nested items are skipped since they provide their own context.
+struct UnsafeOpFinder<'a> {
+    ctx: &'a UnsafeContext,
+    safe_derefs: &'a HashSet<String>,
+    found: bool,
+}
+
+impl<'a> UnsafeOpFinder<'a> {
+    fn new(ctx: &'a UnsafeContext, safe_derefs: &'a HashSet<String>) -> Self {
+        Self {
+            ctx,
+            safe_derefs,
+            found: false,
+        }
+    }
+
+    fn is_safe_deref(&self, expr: &syn::Expr) -> bool {
+        match strip_parens(expr) {
+            syn::Expr::Path(p) => p
+                .path
+                .get_ident()
+                .is_some_and(|ident| self.safe_derefs.contains(&ident.to_string())),
+            syn::Expr::Reference(_) => true,
+            _ => false,
+        }
+    }
+
+    fn stmt_requires_unsafe(&mut self, stmt: &syn::Stmt) -> bool {
+        self.found = false;
+        self.visit_stmt(stmt);
+        self.found
+    }
+
+    fn expr_requires_unsafe(&mut self, expr: &syn::Expr) -> bool {
+        self.found = false;
+        self.visit_expr(expr);
+        self.found
+    }
+
+    fn block_requires_unsafe(&mut self, block: &syn::Block) -> bool {
+        self.found = false;
+        self.visit_block(block);
+        self.found
+    }
+}
+
+impl<'ast> Visit<'ast> for UnsafeOpFinder<'_> {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        if self.found {
+            return;
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_expr_unsafe(&mut self, _node: &'ast syn::ExprUnsafe) {}
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+
+    fn visit_expr_unary(&mut self, node: &'ast syn::ExprUnary) {
+        if matches!(node.op, syn::UnOp::Deref(_)) && !self.is_safe_deref(&node.expr) {
+            self.found = true;
+            return;
+        }
+        visit::visit_expr_unary(self, node);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+        // any other callee, e.g. `cb.unwrap()(x)` or `(s.fp)(x)`, may be an
+        // `unsafe fn` pointer
+        let requires_unsafe = match strip_parens(&node.func) {
+            syn::Expr::Path(p) => self.ctx.call_requires_unsafe(&p.path),
+            _ => true,
+        };
+        if requires_unsafe {
+            self.found = true;
+            return;
+        }
+        visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        let method = node.method.to_string();
+        if UNSAFE_METHOD_NAMES.contains(&method.as_str())
+            || self.ctx.unsafe_methods.contains(&method)
+        {
+            self.found = true;
+            return;
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if let Some(last) = node.path.segments.last() {
+            let name = last.ident.to_string();
+            if self.ctx.static_muts.contains(&name) || self.ctx.extern_items.contains(&name) {
+                self.found = true;
+                return;
+            }
+        }
+        visit::visit_expr_path(self, node);
+    }
+
+    fn visit_expr_field(&mut self, node: &'ast syn::ExprField) {
+        if let syn::Member::Named(ident) = &node.member {
+            if self.ctx.union_fields.contains(&ident.to_string()) {
+                self.found = true;
+                return;
+            }
+        }
+        visit::visit_expr_field(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if node.path.is_ident("asm") || node.path.is_ident("global_asm") {
+            self.found = true;
+            return;
+        }
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        match syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            Ok(args) => {
+                for arg in args.iter() {
+                    self.visit_expr(arg);
+                }
+            }
+            // Unparseable macro input (e.g. `vec![0; n]`): only trust it when it
+            // contains no dereference and no names we know to be unsafe.
+            Err(_) => {
+                self.found |= macro_tokens_look_unsafe(node.tokens.clone(), self.ctx);
+            }
+        }
+    }
+}
+
+fn macro_tokens_look_unsafe(tokens: proc_macro2::TokenStream, ctx: &UnsafeContext) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Group(group) => macro_tokens_look_unsafe(group.stream(), ctx),
+        proc_macro2::TokenTree::Punct(punct) => punct.as_char() == '*',
+        proc_macro2::TokenTree::Ident(ident) => {
+            let name = ident.to_string();
+            name == "libc"
+                || ctx.unsafe_fns.contains(&name)
+                || ctx.extern_items.contains(&name)
+                || ctx.static_muts.contains(&name)
+                || ctx.unsafe_methods.contains(&name)
+                || UNSAFE_FN_NAMES.contains(&name.as_str())
+                || UNSAFE_METHOD_NAMES.contains(&name.as_str())
+        }
+        proc_macro2::TokenTree::Literal(_) => false,
+    })
+}
+
+/// Result of pushing `unsafe` into a control-flow expression.
+enum ShrinkOutcome {
+    /// `unsafe` was moved into the expression's bodies.
+    Shrunk(syn::Expr),
+    /// The expression has to be wrapped as a whole.
+    Wrap(syn::Expr),
+}
+
+/// Narrows `unsafe` regions to the statements that need them.
+struct UnsafeShrinker<'a> {
+    finder: UnsafeOpFinder<'a>,
+}
+
+impl UnsafeShrinker<'_> {
+    fn wrap_unsafe_expr(expr: syn::Expr) -> syn::Expr {
+        parse_quote!(unsafe { #expr })
+    }
+
+    fn wrap_unsafe_stmts(stmts: Vec<syn::Stmt>) -> syn::Stmt {
+        syn::Stmt::Expr(
+            syn::Expr::Unsafe(syn::ExprUnsafe {
+                attrs: Vec::new(),
+                unsafe_token: Token![unsafe](Span::call_site()),
+                block: syn::Block {
+                    brace_token: token::Brace::default(),
+                    stmts,
+                },
+            }),
+            None,
+        )
+    }
+
+    fn shrink_block(&mut self, block: &mut syn::Block) {
+        let stmts = mem::take(&mut block.stmts);
+        let last_idx = stmts.len().saturating_sub(1);
+        let mut result: Vec<syn::Stmt> = Vec::with_capacity(stmts.len());
+        // statements waiting to be emitted as one merged `unsafe { ... }`
+        let mut pending: Vec<syn::Stmt> = Vec::new();
+
+        for (idx, stmt) in stmts.into_iter().enumerate() {
+            if !self.finder.stmt_requires_unsafe(&stmt) {
+                if !pending.is_empty() {
+                    result.push(Self::wrap_unsafe_stmts(mem::take(&mut pending)));
+                }
+                result.push(stmt);
+                continue;
+            }
+
+            match stmt {
+                syn::Stmt::Expr(expr, semi) if semi.is_some() || idx != last_idx => {
+                    match self.shrink_control_flow(expr) {
+                        ShrinkOutcome::Shrunk(expr) => {
+                            if !pending.is_empty() {
+                                result.push(Self::wrap_unsafe_stmts(mem::take(&mut pending)));
+                            }
+                            result.push(syn::Stmt::Expr(expr, semi));
+                        }
+                        ShrinkOutcome::Wrap(expr) => pending.push(syn::Stmt::Expr(
+                            expr,
+                            Some(semi.unwrap_or_else(|| Token![;](Span::call_site()))),
+                        )),
+                    }
+                }
+                syn::Stmt::Macro(mac) => pending.push(syn::Stmt::Macro(mac)),
+                other => {
+                    if !pending.is_empty() {
+                        result.push(Self::wrap_unsafe_stmts(mem::take(&mut pending)));
+                    }
+                    result.push(self.shrink_single(other));
+                }
+            }
+        }
+        if !pending.is_empty() {
+            result.push(Self::wrap_unsafe_stmts(pending));
+        }
+
+        block.stmts = result;
+    }
+
+    /// Shrinks a statement that cannot be merged with its neighbours: `let`
+    /// bindings (wrapping only the initializer) and the tail expression.
+    fn shrink_single(&mut self, stmt: syn::Stmt) -> syn::Stmt {
+        match stmt {
+            syn::Stmt::Local(mut local) => {
+                if let Some(init) = local.init.as_mut() {
+                    if self.finder.expr_requires_unsafe(&init.expr) {
+                        let expr = mem::replace(&mut *init.expr, parse_quote!(()));
+                        *init.expr = Self::wrap_unsafe_expr(expr);
+                    }
+                    if let Some((_, diverge)) = init.diverge.as_mut() {
+                        if self.finder.expr_requires_unsafe(diverge) {
+                            let expr = mem::replace(&mut **diverge, parse_quote!(()));
+                            **diverge = parse_quote!({ unsafe { #expr } });
+                        }
+                    }
+                }
+                syn::Stmt::Local(local)
+            }
+            syn::Stmt::Expr(expr, None) => match self.shrink_control_flow(expr) {
+                ShrinkOutcome::Shrunk(expr) => syn::Stmt::Expr(expr, None),
+                ShrinkOutcome::Wrap(expr) => syn::Stmt::Expr(Self::wrap_unsafe_expr(expr), None),
+            },
+            other => other,
+        }
+    }
+
+    /// Pushes `unsafe` into the bodies of control-flow expressions whose heads
+    /// are safe.
+    fn shrink_control_flow(&mut self, expr: syn::Expr) -> ShrinkOutcome {
+        match expr {
+            syn::Expr::If(mut expr_if) => {
+                if self.finder.expr_requires_unsafe(&expr_if.cond) {
+                    return ShrinkOutcome::Wrap(syn::Expr::If(expr_if));
+                }
+                self.shrink_block(&mut expr_if.then_branch);
+                if let Some((else_token, else_branch)) = expr_if.else_branch.take() {
+                    let else_branch = match *else_branch {
+                        syn::Expr::Block(mut block) => {
+                            self.shrink_block(&mut block.block);
+                            syn::Expr::Block(block)
+                        }
+                        other => match self.shrink_control_flow(other) {
+                            ShrinkOutcome::Shrunk(other) => other,
+                            ShrinkOutcome::Wrap(other) => {
+                                return ShrinkOutcome::Wrap(syn::Expr::If(syn::ExprIf {
+                                    else_branch: Some((else_token, Box::new(other))),
+                                    ..expr_if
+                                }))
+                            }
+                        },
+                    };
+                    expr_if.else_branch = Some((else_token, Box::new(else_branch)));
+                }
+                ShrinkOutcome::Shrunk(syn::Expr::If(expr_if))
+            }
+            syn::Expr::While(mut expr_while) => {
+                if self.finder.expr_requires_unsafe(&expr_while.cond) {
+                    return ShrinkOutcome::Wrap(syn::Expr::While(expr_while));
+                }
+                self.shrink_block(&mut expr_while.body);
+                ShrinkOutcome::Shrunk(syn::Expr::While(expr_while))
+            }
+            syn::Expr::ForLoop(mut expr_for) => {
+                if self.finder.expr_requires_unsafe(&expr_for.expr) {
+                    return ShrinkOutcome::Wrap(syn::Expr::ForLoop(expr_for));
+                }
+                self.shrink_block(&mut expr_for.body);
+                ShrinkOutcome::Shrunk(syn::Expr::ForLoop(expr_for))
+            }
+            syn::Expr::Loop(mut expr_loop) => {
+                self.shrink_block(&mut expr_loop.body);
+                ShrinkOutcome::Shrunk(syn::Expr::Loop(expr_loop))
+            }
+            syn::Expr::Block(mut expr_block) if expr_block.label.is_none() => {
+                self.shrink_block(&mut expr_block.block);
+                ShrinkOutcome::Shrunk(syn::Expr::Block(expr_block))
+            }
+            syn::Expr::Match(mut expr_match) => {
+                if self.finder.expr_requires_unsafe(&expr_match.expr)
+                    || expr_match.arms.iter().any(|arm| {
+                        arm.guard
+                            .as_ref()
+                            .is_some_and(|(_, guard)| self.finder.expr_requires_unsafe(guard))
+                    })
+                {
+                    return ShrinkOutcome::Wrap(syn::Expr::Match(expr_match));
+                }
+                for arm in expr_match.arms.iter_mut() {
+                    if self.finder.expr_requires_unsafe(&arm.body) {
+                        let body = mem::replace(&mut *arm.body, parse_quote!(()));
+                        *arm.body = match self.shrink_control_flow(body) {
+                            ShrinkOutcome::Shrunk(body) => body,
+                            ShrinkOutcome::Wrap(body) => Self::wrap_unsafe_expr(body),
+                        };
+                        if arm.comma.is_none() && !matches!(*arm.body, syn::Expr::Block(_)) {
+                            arm.comma = Some(Token![,](Span::call_site()));
+                        }
+                    }
+                }
+                ShrinkOutcome::Shrunk(syn::Expr::Match(expr_match))
+            }
+            other => ShrinkOutcome::Wrap(other),
+        }
+    }
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn shrink_unsafe_blocks(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    let ctx = UnsafeContext::from_file(&ast);
+
+    for item in ast.items.iter_mut() {
+        let syn::Item::Fn(f) = item else {
+            continue;
+        };
+        if f.block.stmts.len() != 1 {
+            continue;
+        }
+        let (inner, semi) = match &f.block.stmts[0] {
+            syn::Stmt::Expr(syn::Expr::Unsafe(unsafe_expr), semi) => {
+                (unsafe_expr.block.clone(), *semi)
+            }
+            _ => continue,
+        };
+
+        let safe_derefs = collect_safe_deref_bindings(&f.sig, &inner);
+        let mut shrinker = UnsafeShrinker {
+            finder: UnsafeOpFinder::new(&ctx, &safe_derefs),
+        };
+        let mut body = inner;
+        shrinker.shrink_block(&mut body);
+
+        // `{ unsafe { tail }; }` discarded the tail value, keep it that way
+        if let (Some(semi), Some(syn::Stmt::Expr(_, last_semi @ None))) =
+            (semi, body.stmts.last_mut())
+        {
+            *last_semi = Some(semi);
+        }
+        f.block.stmts = body.stmts;
+    }
+
+    Ok(unparse(&ast))
+}
+
+struct UnnecessaryUnsafe {
+    kind: &'static str,
+    function: String,
+    line: usize,
+}
+
+/// Reports `unsafe {}` blocks in a function body that contain no unsafe operation.
+struct UnnecessaryUnsafeBlockFinder<'a> {
+    finder: UnsafeOpFinder<'a>,
+    function: String,
+    found: Vec<UnnecessaryUnsafe>,
+}
+
+impl<'ast> Visit<'ast> for UnnecessaryUnsafeBlockFinder<'_> {
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+
+    fn visit_expr_unsafe(&mut self, node: &'ast syn::ExprUnsafe) {
+        if !self.finder.block_requires_unsafe(&node.block) {
+            self.found.push(UnnecessaryUnsafe {
+                kind: "unsafe_block",
+                function: self.function.clone(),
+                line: node.unsafe_token.span.start().line,
+            });
+        }
+        visit::visit_expr_unsafe(self, node);
+    }
+}
+
+fn find_unnecessary_unsafe_in_fn(
+    ctx: &UnsafeContext,
+    name: String,
+    sig: &syn::Signature,
+    block: &syn::Block,
+    found: &mut Vec<UnnecessaryUnsafe>,
+) {
+    let safe_derefs = collect_safe_deref_bindings(sig, block);
+    let mut finder = UnsafeOpFinder::new(ctx, &safe_derefs);
+    if let Some(unsafety) = &sig.unsafety {
+        if !finder.block_requires_unsafe(block) {
+            found.push(UnnecessaryUnsafe {
+                kind: "unsafe_fn",
+                function: name.clone(),
+                line: unsafety.span.start().line,
+            });
+        }
+    }
+
+    let mut block_finder = UnnecessaryUnsafeBlockFinder {
+        finder,
+        function: name,
+        found: Vec::new(),
+    };
+    block_finder.visit_block(block);
+    found.extend(block_finder.found);
+}
+
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn find_unnecessary_unsafe(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let ctx = UnsafeContext::from_file(&ast);
+    let mut found = Vec::new();
+
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Fn(f) => find_unnecessary_unsafe_in_fn(
+                &ctx,
+                f.sig.ident.to_string(),
+                &f.sig,
+                &f.block,
+                &mut found,
+            ),
+            syn::Item::Impl(imp) => {
+                let self_ty = normalize_token_string(&imp.self_ty.to_token_stream().to_string());
+                for impl_item in imp.items.iter() {
+                    if let syn::ImplItem::Fn(f) = impl_item {
+                        find_unnecessary_unsafe_in_fn(
+                            &ctx,
+                            format!("{}::{}", self_ty, f.sig.ident),
+                            &f.sig,
+                            &f.block,
+                            &mut found,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let result = PyList::empty(py);
+    for entry in found {
+        let dict = PyDict::new(py);
+        dict.set_item("kind", entry.kind)?;
+        dict.set_item("function", entry.function)?;
+        dict.set_item("line", entry.line)?;
+        result.append(dict)?;
+    }
+    Ok(result.into())
+}
+struct LeakPattern {
+    kind: &'static str,
+    function: String,
+    line: usize,
+    /// Owner type of an `into_raw`/`from_raw` pair, when written as a path.
+    owner: Option<String>,
+}
+
+struct LeakFinder<'a> {
+    function: &'a str,
+    found: Vec<LeakPattern>,
+    from_raw: Vec<Option<String>>,
+}
+
+impl LeakFinder<'_> {
+    fn push(&mut self, kind: &'static str, span: Span, owner: Option<String>) {
+        self.found.push(LeakPattern {
+            kind,
+            function: self.function.to_string(),
+            line: span.start().line,
+            owner,
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for LeakFinder<'_> {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*call.func {
+            let segments: Vec<String> = func
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            let owner = segments
+                .len()
+                .checked_sub(2)
+                .map(|idx| segments[idx].clone());
+            match segments.last().map(String::as_str) {
+                Some("leak") if owner.as_deref() == Some("Box") => {
+                    self.push("box_leak", func.span(), None)
+                }
+                Some("forget") if owner.as_deref() == Some("mem") => {
+                    self.push("mem_forget", func.span(), None)
+                }
+                Some("into_raw") => self.push("into_raw", func.span(), owner),
+                Some("from_raw") => self.from_raw.push(owner),
+                _ => {}
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        match call.method.to_string().as_str() {
+            "into_raw" => self.push("into_raw", call.method.span(), None),
+            "leak" => self.push("box_leak", call.method.span(), None),
+            _ => {}
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if let Some(segment) = path.segments.iter().find(|s| s.ident == "ManuallyDrop") {
+            self.push("manually_drop", segment.ident.span(), None);
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Lists constructs that leak by design: `Box::leak`, `mem::forget`,
+/// `ManuallyDrop` and `into_raw` calls with no matching `from_raw` anywhere in
+/// the code (`Box::into_raw` needs a `Box::from_raw`; the method form
+/// `x.into_raw()` is matched by any `from_raw`). Each entry has `kind`
+/// (`box_leak`, `mem_forget`, `into_raw`, `manually_drop`), `function` and `line`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn find_leak_patterns(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let mut functions: Vec<(String, &syn::Signature, &syn::Block)> = Vec::new();
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Fn(f) => functions.push((f.sig.ident.to_string(), &f.sig, &f.block)),
+            syn::Item::Impl(imp) => {
+                let self_ty = normalize_token_string(&imp.self_ty.to_token_stream().to_string());
+                for impl_item in imp.items.iter() {
+                    if let syn::ImplItem::Fn(f) = impl_item {
+                        functions.push((format!("{}::{}", self_ty, f.sig.ident), &f.sig, &f.block));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut from_raw = Vec::new();
+    for (name, sig, block) in functions.iter() {
+        let mut finder = LeakFinder {
+            function: name,
+            found: Vec::new(),
+            from_raw: Vec::new(),
+        };
+        finder.visit_signature(sig);
+        finder.visit_block(block);
+        found.extend(finder.found);
+        from_raw.extend(finder.from_raw);
+    }
+    found.retain(|pattern| {
+        pattern.kind != "into_raw"
+            || !from_raw
+                .iter()
+                .any(|owner| pattern.owner.is_none() || owner.is_none() || *owner == pattern.owner)
+    });
+
+    let result = PyList::empty(py);
+    for entry in found {
+        let dict = PyDict::new(py);
+        dict.set_item("kind", entry.kind)?;
+        dict.set_item("function", entry.function)?;
+        dict.set_item("line", entry.line)?;
+        result.append(dict)?;
+    }
+    Ok(result.into())
+}
+/// Size in bytes of a type whose layout is fixed on the 64-bit targets
+/// translated code is built for.
+fn known_type_size(ty: &syn::Type) -> Option<usize> {
+    match ty {
+        syn::Type::Ptr(_) | syn::Type::BareFn(_) => Some(8),
+        syn::Type::Reference(r) => match &*r.elem {
+            syn::Type::Slice(_) => Some(16),
+            syn::Type::Path(p) if p.path.is_ident("str") => Some(16),
+            _ => Some(8),
+        },
+        syn::Type::Paren(p) => known_type_size(&p.elem),
+        syn::Type::Tuple(t) if t.elems.is_empty() => Some(0),
+        syn::Type::Array(array) => {
+            let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(len),
+                ..
+            }) = &array.len
+            else {
+                return None;
+            };
+            Some(known_type_size(&array.elem)? * len.base10_parse::<usize>().ok()?)
+        }
+        syn::Type::Path(p) => {
+            let name = p.path.segments.last()?.ident.to_string();
+            let name = map_libc_scalar(&name).map_or(name, str::to_string);
+            match name.as_str() {
+                "u8" | "i8" | "bool" => Some(1),
+                "u16" | "i16" => Some(2),
+                "u32" | "i32" | "f32" | "char" => Some(4),
+                "u64" | "i64" | "f64" | "usize" | "isize" => Some(8),
+                "u128" | "i128" => Some(16),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The raw pointer variable a place expression dereferences through,
+/// looking past casts and pointer arithmetic.
+fn deref_root(expr: &syn::Expr) -> Option<String> {
+    match strip_parens(expr) {
+        syn::Expr::Cast(cast) => deref_root(&cast.expr),
+        syn::Expr::MethodCall(call)
+            if POINTER_ARITH_METHODS.contains(&call.method.to_string().as_str()) =>
+        {
+            deref_root(&call.receiver)
+        }
+        other => path_expr_name(other),
+    }
+}
+
+fn is_allocation(expr: &syn::Expr) -> bool {
+    match strip_parens(expr) {
+        syn::Expr::Cast(cast) => is_allocation(&cast.expr),
+        syn::Expr::Call(call) => {
+            ["malloc", "calloc", "realloc"]
+                .iter()
+                .any(|name| is_libc_call(&call.func, name))
+                || path_ends_with(&call.func, &["null_mut"])
+                || path_ends_with(&call.func, &["null"])
+        }
+        _ => false,
+    }
+}
+
+fn is_maybe_uninit_call(expr: &syn::Expr) -> bool {
+    matches!(strip_parens(expr), syn::Expr::Call(call)
+        if path_ends_with(&call.func, &["uninit"]) || path_ends_with(&call.func, &["uninit_array"]))
+}
+
+struct UbSuspect {
+    kind: &'static str,
+    function: String,
+    line: usize,
+    detail: String,
+}
+
+struct UbFinder<'a> {
+    function: String,
+    lines: &'a [&'a str],
+    static_muts: &'a HashSet<String>,
+    var_types: HashMap<String, syn::Type>,
+    /// Raw pointers that may be null and have not been compared or checked yet.
+    unchecked: HashSet<String>,
+    uninit: HashSet<String>,
+    /// Line of the innermost `unsafe` block or fn and whether a `SAFETY:`
+    /// comment documents it.
+    unsafe_scopes: Vec<(usize, bool)>,
+    reported_statics: HashSet<(usize, String)>,
+    target_hint: Option<syn::Type>,
+    found: Vec<UbSuspect>,
+}
+
+impl UbFinder<'_> {
+    fn push(&mut self, kind: &'static str, span: Span, detail: String) {
+        self.found.push(UbSuspect {
+            kind,
+            function: self.function.clone(),
+            line: span.start().line,
+            detail,
+        });
+    }
+
+    /// A `// SAFETY:` comment on the `unsafe` line or the three lines above.
+    fn is_documented(&self, line: usize) -> bool {
+        let end = line.min(self.lines.len());
+        self.lines[end.saturating_sub(4)..end]
+            .iter()
+            .any(|text| text.contains("SAFETY"))
+    }
+
+    fn expr_type(&self, expr: &syn::Expr) -> Option<syn::Type> {
+        match strip_parens(expr) {
+            syn::Expr::Cast(cast) => Some((*cast.ty).clone()),
+            other => path_expr_name(other).and_then(|name| self.var_types.get(&name).cloned()),
+        }
+    }
+
+    fn check_transmute(&mut self, call: &syn::ExprCall) {
+        let syn::Expr::Path(func) = &*call.func else {
+            return;
+        };
+        let Some(last) = func.path.segments.last() else {
+            return;
+        };
+        if last.ident != "transmute" {
+            return;
+        }
+        let mut turbofish = Vec::new();
+        if let PathArguments::AngleBracketed(args) = &last.arguments {
+            for arg in args.args.iter() {
+                if let GenericArgument::Type(ty) = arg {
+                    turbofish
+                        .push(Some(ty.clone()).filter(|ty| !matches!(ty, syn::Type::Infer(_))));
+                }
+            }
+        }
+        let from = turbofish
+            .first()
+            .cloned()
+            .flatten()
+            .or_else(|| call.args.first().and_then(|arg| self.expr_type(arg)));
+        let to = turbofish
+            .get(1)
+            .cloned()
+            .flatten()
+            .or(self.target_hint.clone());
+        let (Some(from), Some(to)) = (from, to) else {
+            return;
+        };
+        if let (Some(from_size), Some(to_size)) = (known_type_size(&from), known_type_size(&to)) {
+            if from_size != to_size {
+                let detail = format!(
+                    "{} ({} bytes) -> {} ({} bytes)",
+                    normalize_token_string(&from.to_token_stream().to_string()),
+                    from_size,
+                    normalize_token_string(&to.to_token_stream().to_string()),
+                    to_size
+                );
+                self.push("transmute_size_mismatch", func.span(), detail);
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UbFinder<'_> {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let (pat, ty) = match &local.pat {
+            syn::Pat::Type(pat_type) => (&*pat_type.pat, Some(&*pat_type.ty)),
+            other => (other, None),
+        };
+        let name = match pat {
+            syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+            _ => None,
+        };
+        if let Some(init) = &local.init {
+            self.target_hint = ty.cloned();
+            self.visit_expr(&init.expr);
+            self.target_hint = None;
+            if let Some((_, diverge)) = &init.diverge {
+                self.visit_expr(diverge);
+            }
+        }
+        let Some(name) = name else {
+            return;
+        };
+        match ty {
+            Some(ty) => self.var_types.insert(name.clone(), ty.clone()),
+            None => self.var_types.remove(&name),
+        };
+        let init = local.init.as_ref().map(|init| &*init.expr);
+        if init.is_some_and(is_allocation) {
+            self.unchecked.insert(name.clone());
+        } else {
+            self.unchecked.remove(&name);
+        }
+        if init.is_some_and(is_maybe_uninit_call) {
+            self.uninit.insert(name);
+        } else {
+            self.uninit.remove(&name);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Unsafe(unsafe_expr) => {
+                let line = unsafe_expr.unsafe_token.span.start().line;
+                self.unsafe_scopes.push((line, self.is_documented(line)));
+                visit::visit_expr(self, expr);
+                self.unsafe_scopes.pop();
+                return;
+            }
+            syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Deref(_)) => {
+                if let Some(name) = deref_root(&unary.expr) {
+                    if self.unchecked.remove(&name) {
+                        let detail = format!("`{}` is dereferenced without a null check", name);
+                        self.push("unchecked_null_deref", unary.span(), detail);
+                    }
+                }
+            }
+            syn::Expr::Binary(binary) => {
+                // any comparison involving the pointer counts as a check
+                for operand in [&binary.left, &binary.right] {
+                    if let Some(name) = deref_root(operand) {
+                        self.unchecked.remove(&name);
+                    }
+                }
+            }
+            syn::Expr::MethodCall(call) => {
+                let method = call.method.to_string();
+                let receiver = deref_root(&call.receiver);
+                if let Some(name) = &receiver {
+                    match method.as_str() {
+                        "is_null" | "as_ref" | "as_mut" => {
+                            self.unchecked.remove(name);
+                        }
+                        "write" | "as_mut_ptr" => {
+                            self.uninit.remove(name);
+                        }
+                        _ => {}
+                    }
+                }
+                if method.starts_with("assume_init") {
+                    let direct = is_maybe_uninit_call(&call.receiver);
+                    let local = receiver
+                        .as_ref()
+                        .is_some_and(|name| self.uninit.contains(name));
+                    if direct || local {
+                        let detail = "`assume_init` on a value that was never written".to_string();
+                        self.push("uninit_assume_init", call.method.span(), detail);
+                    }
+                }
+            }
+            syn::Expr::Call(call) => {
+                self.check_transmute(call);
+                if path_ends_with(&call.func, &["mem", "uninitialized"]) {
+                    let detail = "`mem::uninitialized` is always undefined behavior".to_string();
+                    self.push("uninit_assume_init", call.func.span(), detail);
+                }
+            }
+            syn::Expr::Reference(reference) if reference.mutability.is_some() => {
+                if let Some(name) = path_expr_name(strip_parens(&reference.expr)) {
+                    self.uninit.remove(&name);
+                }
+            }
+            syn::Expr::Assign(assign) => {
+                if let Some(name) = path_expr_name(strip_parens(&assign.left)) {
+                    self.uninit.remove(&name);
+                    if is_allocation(&assign.right) {
+                        self.unchecked.insert(name);
+                    }
+                }
+            }
+            syn::Expr::Path(path) => {
+                let name = path_expr_name(expr);
+                if let Some(name) = name.filter(|name| self.static_muts.contains(name)) {
+                    let scope = self.unsafe_scopes.last().copied();
+                    let line = scope.map_or(0, |(line, _)| line);
+                    let documented = scope.is_some_and(|(_, documented)| documented);
+                    if !documented && self.reported_statics.insert((line, name.clone())) {
+                        let detail =
+                            format!("`static mut {}` accessed without a SAFETY comment", name);
+                        self.push("static_mut_access", path.span(), detail);
+                    }
+                }
+            }
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Static pre-screen for undefined behavior in translated code. Flags
+/// `transmute` between types of different known sizes, dereferences of raw
+/// pointer parameters or allocation results before any null check, `static mut`
+/// accesses in `unsafe` code without a `// SAFETY:` comment, and `assume_init`
+/// on a `MaybeUninit` that was never written (or `mem::uninitialized`). Each
+/// entry has `kind`, `function`, `line` and `detail`; findings are suspects,
+/// not proof.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn find_ub_suspects(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let lines: Vec<&str> = code.lines().collect();
+    let mut static_muts = HashSet::new();
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Static(s) if matches!(s.mutability, syn::StaticMutability::Mut(_)) => {
+                static_muts.insert(s.ident.to_string());
+            }
+            syn::Item::ForeignMod(block) => {
+                for foreign in block.items.iter() {
+                    if let syn::ForeignItem::Static(s) = foreign {
+                        if matches!(s.mutability, syn::StaticMutability::Mut(_)) {
+                            static_muts.insert(s.ident.to_string());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut functions: Vec<(String, &syn::Signature, &syn::Block)> = Vec::new();
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Fn(f) => functions.push((f.sig.ident.to_string(), &f.sig, &f.block)),
+            syn::Item::Impl(imp) => {
+                let self_ty = normalize_token_string(&imp.self_ty.to_token_stream().to_string());
+                for impl_item in imp.items.iter() {
+                    if let syn::ImplItem::Fn(f) = impl_item {
+                        functions.push((format!("{}::{}", self_ty, f.sig.ident), &f.sig, &f.block));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    for (name, sig, block) in functions {
+        let mut finder = UbFinder {
+            function: name,
+            lines: &lines,
+            static_muts: &static_muts,
+            var_types: HashMap::new(),
+            unchecked: HashSet::new(),
+            uninit: HashSet::new(),
+            unsafe_scopes: Vec::new(),
+            reported_statics: HashSet::new(),
+            target_hint: None,
+            found: Vec::new(),
+        };
+        for input in sig.inputs.iter() {
+            let syn::FnArg::Typed(pat_type) = input else {
+                continue;
+            };
+            let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+                continue;
+            };
+            let param = pat_ident.ident.to_string();
+            if matches!(&*pat_type.ty, syn::Type::Ptr(_)) {
+                finder.unchecked.insert(param.clone());
+            }
+            finder.var_types.insert(param, (*pat_type.ty).clone());
+        }
+        if let Some(unsafety) = &sig.unsafety {
+            let line = unsafety.span.start().line;
+            finder
+                .unsafe_scopes
+                .push((line, finder.is_documented(line)));
+        }
+        finder.visit_block(block);
+        found.extend(finder.found);
+    }
+
+    let result = PyList::empty(py);
+    for entry in found {
+        let dict = PyDict::new(py);
+        dict.set_item("kind", entry.kind)?;
+        dict.set_item("function", entry.function)?;
+        dict.set_item("line", entry.line)?;
+        dict.set_item("detail", entry.detail)?;
+        result.append(dict)?;
+    }
+    Ok(result.into())
+}
+const GLOBAL_WRITE_METHODS: &[&str] = &[
+    "store",
+    "swap",
+    "compare_exchange",
+    "compare_exchange_weak",
+    "lock",
+    "borrow_mut",
+    "set",
+    "replace",
+    "take",
+];
+
+struct LocalNames(HashSet<String>);
+
+impl<'ast> Visit<'ast> for LocalNames {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.0.insert(pat.ident.to_string());
+        visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Records which globals a function body reads and writes.
+struct GlobalUsageVisitor<'a> {
+    globals: &'a HashSet<String>,
+    locals: HashSet<String>,
+    reads: BTreeSet<String>,
+    writes: BTreeSet<String>,
+}
+
+impl GlobalUsageVisitor<'_> {
+    fn global_root(&self, expr: &syn::Expr) -> Option<String> {
+        assigned_root(expr)
+            .filter(|name| self.globals.contains(name) && !self.locals.contains(name))
+    }
+
+    /// Visits the operands of a written place without reading its root.
+    fn visit_place(&mut self, expr: &syn::Expr) {
+        match expr {
+            syn::Expr::Field(field) => self.visit_place(&field.base),
+            syn::Expr::Index(index) => {
+                self.visit_place(&index.expr);
+                self.visit_expr(&index.index);
+            }
+            syn::Expr::Paren(paren) => self.visit_place(&paren.expr),
+            syn::Expr::Path(_) => {}
+            other => self.visit_expr(other),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for GlobalUsageVisitor<'_> {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Assign(assign) => {
+                if let Some(name) = self.global_root(&assign.left) {
+                    self.writes.insert(name);
+                }
+                self.visit_place(&assign.left);
+                self.visit_expr(&assign.right);
+                return;
+            }
+            syn::Expr::Binary(binary) if is_compound_assign(&binary.op) => {
+                if let Some(name) = self.global_root(&binary.left) {
+                    self.reads.insert(name.clone());
+                    self.writes.insert(name);
+                }
+                self.visit_place(&binary.left);
+                self.visit_expr(&binary.right);
+                return;
+            }
+            syn::Expr::Reference(reference) if reference.mutability.is_some() => {
+                if let Some(name) = self.global_root(&reference.expr) {
+                    self.writes.insert(name);
+                }
+            }
+            syn::Expr::MethodCall(call) => {
+                let method = call.method.to_string();
+                let mutating = MUTATING_METHODS.contains(&method.as_str())
+                    || GLOBAL_WRITE_METHODS.contains(&method.as_str())
+                    || method.starts_with("fetch_");
+                if mutating {
+                    if let Some(name) = self.global_root(&call.receiver) {
+                        self.writes.insert(name);
+                    }
+                }
+            }
+            syn::Expr::Path(_) => {
+                if let Some(name) = self.global_root(expr) {
+                    self.reads.insert(name);
+                }
+            }
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if node.path.is_ident("addr_of_mut") {
+            if let Ok(expr) = node.parse_body::<syn::Expr>() {
+                if let Some(name) = self.global_root(&expr) {
+                    self.writes.insert(name);
+                }
+            }
+            return;
+        }
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Maps each function (methods as `Type::method`) to the top-level statics
+/// and consts, including `extern` statics, it `reads` and `writes`. Compound
+/// assignments count as both; `&mut`, `addr_of_mut!` and mutating or atomic
+/// methods count as writes. Names shadowed by a local binding anywhere in the
+/// function are ignored.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn global_usage(code: &str) -> PyResult<HashMap<String, HashMap<String, Vec<String>>>> {
+    let ast = parse_src(code)?;
+    let mut globals = HashSet::new();
+    let mut functions: Vec<(String, &syn::Signature, &syn::Block)> = Vec::new();
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Static(s) => {
+                globals.insert(s.ident.to_string());
+            }
+            syn::Item::Const(c) if c.ident != "_" => {
+                globals.insert(c.ident.to_string());
+            }
+            syn::Item::ForeignMod(block) => {
+                for foreign in block.items.iter() {
+                    if let syn::ForeignItem::Static(s) = foreign {
+                        globals.insert(s.ident.to_string());
+                    }
+                }
+            }
+            syn::Item::Fn(f) => functions.push((f.sig.ident.to_string(), &f.sig, &f.block)),
+            syn::Item::Impl(imp) => {
+                let self_ty = normalize_token_string(&imp.self_ty.to_token_stream().to_string());
+                for impl_item in imp.items.iter() {
+                    if let syn::ImplItem::Fn(f) = impl_item {
+                        functions.push((format!("{}::{}", self_ty, f.sig.ident), &f.sig, &f.block));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut result = HashMap::new();
+    for (name, sig, block) in functions {
+        let mut locals = LocalNames(HashSet::new());
+        locals.visit_signature(sig);
+        locals.visit_block(block);
+        let mut visitor = GlobalUsageVisitor {
+            globals: &globals,
+            locals: locals.0,
+            reads: BTreeSet::new(),
+            writes: BTreeSet::new(),
+        };
+        visitor.visit_block(block);
+        result.insert(
+            name,
+            HashMap::from([
+                ("reads".to_string(), visitor.reads.into_iter().collect()),
+                ("writes".to_string(), visitor.writes.into_iter().collect()),
+            ]),
+        );
+    }
+    Ok(result)
+}
+/// Scalar name of a path type with libc aliases resolved (`c_int` -> `i32`).
+fn shim_scalar(traits: &TypeTraits) -> Option<String> {
+    let ident = traits.path_ident.as_ref()?;
+    Some(map_libc_scalar(ident).unwrap_or(ident).to_string())
+}
+
+fn shim_same_type(a: &TypeTraits, b: &TypeTraits) -> bool {
+    a.normalized == b.normalized || (shim_scalar(a).is_some() && shim_scalar(a) == shim_scalar(b))
+}
+
+fn shim_is_numeric(traits: &TypeTraits) -> bool {
+    shim_scalar(traits).is_some_and(|name| is_numeric_primitive(&name) || name == "bool")
+}
+
+fn shim_type(traits: &TypeTraits) -> PyResult<syn::Type> {
+    syn::parse_str(&traits.raw).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Failed to parse type '{}': {}",
+            traits.raw, e
+        ))
+    })
+}
+
+/// Statements run before and after the idiomatic call for one parameter, and
+/// the expression passed in its place.
+struct ShimArg {
+    pre: Vec<syn::Stmt>,
+    arg: syn::Expr,
+    post: Vec<syn::Stmt>,
+}
+
+fn shim_arg(
+    name: &str,
+    i_traits: &TypeTraits,
+    c_ident: &syn::Ident,
+    c_traits: &TypeTraits,
+    len: Option<syn::Expr>,
+) -> PyResult<ShimArg> {
+    let local = syn::Ident::new(&format!("__{}", name), Span::call_site());
+    let unsupported = || {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Cannot convert C parameter '{}: {}' to '{}: {}'",
+            c_ident, c_traits.normalized, name, i_traits.normalized
+        ))
+    };
+    let simple = |arg: syn::Expr| ShimArg {
+        pre: Vec::new(),
+        arg,
+        post: Vec::new(),
+    };
+    let c_pointee = c_traits.pointer_inner.as_deref();
+
+    if i_traits.is_option {
+        let inner = i_traits.option_inner.as_deref().ok_or_else(unsupported)?;
+        if !c_traits.is_pointer {
+            return Err(unsupported());
+        }
+        if inner.is_str && inner.is_reference {
+            return Ok(ShimArg {
+                pre: parse_quote! {
+                    let #local = (!#c_ident.is_null())
+                        .then(|| unsafe { std::ffi::CStr::from_ptr(#c_ident) }.to_string_lossy());
+                },
+                arg: parse_quote!(#local.as_deref()),
+                post: Vec::new(),
+            });
+        }
+        if inner.is_string {
+            return Ok(simple(parse_quote! {
+                (!#c_ident.is_null())
+                    .then(|| unsafe { std::ffi::CStr::from_ptr(#c_ident) }.to_string_lossy().into_owned())
+            }));
+        }
+        if inner.is_reference && !inner.is_slice && !inner.is_str {
+            return Ok(simple(if inner.is_mut_reference {
+                parse_quote!(unsafe { #c_ident.as_mut() })
+            } else {
+                parse_quote!(unsafe { #c_ident.as_ref() })
+            }));
+        }
+        return Err(unsupported());
+    }
+
+    if i_traits.is_str && i_traits.is_reference {
+        if !c_traits.is_pointer {
+            return Err(unsupported());
+        }
+        return Ok(ShimArg {
+            pre: parse_quote! {
+                let #local = if #c_ident.is_null() {
+                    std::borrow::Cow::Borrowed("")
+                } else {
+                    unsafe { std::ffi::CStr::from_ptr(#c_ident) }.to_string_lossy()
+                };
+            },
+            arg: parse_quote!(&#local),
+            post: Vec::new(),
+        });
+    }
+
+    if i_traits.is_string && !i_traits.is_reference {
+        if !c_traits.is_pointer {
+            return Err(unsupported());
+        }
+        return Ok(simple(parse_quote! {
+            if #c_ident.is_null() {
+                String::new()
+            } else {
+                unsafe { std::ffi::CStr::from_ptr(#c_ident) }.to_string_lossy().into_owned()
+            }
+        }));
+    }
+
+    if i_traits.is_slice && i_traits.is_reference {
+        if !c_traits.is_pointer {
+            return Err(unsupported());
+        }
+        let len = len.ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!(
+                "Slice parameter '{}' needs len_from or len_const in its mapping",
+                name
+            ))
+        })?;
+        let elem: syn::Type = syn::parse_str(i_traits.slice_elem.as_deref().unwrap_or("u8"))
+            .map_err(|_| unsupported())?;
+        let len_local = syn::Ident::new(&format!("__{}_len", name), Span::call_site());
+        let mut pre: Vec<syn::Stmt> = vec![parse_quote!(let #len_local: usize = #len;)];
+        pre.push(if i_traits.is_mut_reference {
+            parse_quote! {
+                let #local: &mut [#elem] = if #c_ident.is_null() || #len_local == 0 {
+                    &mut []
+                } else {
+                    unsafe { std::slice::from_raw_parts_mut(#c_ident as *mut #elem, #len_local) }
+                };
+            }
+        } else {
+            parse_quote! {
+                let #local: &[#elem] = if #c_ident.is_null() || #len_local == 0 {
+                    &[]
+                } else {
+                    unsafe { std::slice::from_raw_parts(#c_ident as *const #elem, #len_local) }
+                };
+            }
+        });
+        return Ok(ShimArg {
+            pre,
+            arg: parse_quote!(#local),
+            post: Vec::new(),
+        });
+    }
+
+    if i_traits.is_reference {
+        let inner = i_traits
+            .reference_inner
+            .as_deref()
+            .ok_or_else(unsupported)?;
+        let c_pointee = c_pointee
+            .filter(|_| c_traits.is_pointer)
+            .ok_or_else(unsupported)?;
+        if shim_same_type(inner, c_pointee) {
+            return Ok(simple(if i_traits.is_mut_reference {
+                parse_quote!(unsafe { &mut *#c_ident })
+            } else {
+                parse_quote!(unsafe { &*#c_ident })
+            }));
+        }
+        if !shim_is_numeric(inner) || !shim_is_numeric(c_pointee) {
+            return Err(unsupported());
+        }
+        // Differing scalars go through a local that is written back afterwards.
+        let i_ty = shim_type(inner)?;
+        let c_ty = shim_type(c_pointee)?;
+        if i_traits.is_mut_reference {
+            return Ok(ShimArg {
+                pre: parse_quote! {
+                    let mut #local = unsafe { *#c_ident } as #i_ty;
+                },
+                arg: parse_quote!(&mut #local),
+                post: parse_quote! {
+                    unsafe { *#c_ident = #local as #c_ty };
+                },
+            });
+        }
+        return Ok(ShimArg {
+            pre: parse_quote! {
+                let #local = unsafe { *#c_ident } as #i_ty;
+            },
+            arg: parse_quote!(&#local),
+            post: Vec::new(),
+        });
+    }
+
+    if c_traits.is_pointer {
+        return Err(unsupported());
+    }
+    if shim_same_type(i_traits, c_traits) {
+        return Ok(simple(parse_quote!(#c_ident)));
+    }
+    if shim_scalar(i_traits).as_deref() == Some("bool") && shim_is_numeric(c_traits) {
+        return Ok(simple(parse_quote!(#c_ident != 0)));
+    }
+    if shim_is_numeric(i_traits) && shim_is_numeric(c_traits) {
+        let i_ty = shim_type(i_traits)?;
+        return Ok(simple(parse_quote!(#c_ident as #i_ty)));
+    }
+    Err(unsupported())
+}
+
+/// Converts the idiomatic return value `__ret` to the C return type.
+fn shim_return(i_ret: &TypeTraits, c_ret: &TypeTraits) -> PyResult<syn::Expr> {
+    let c_ty = shim_type(c_ret)?;
+    if c_ret.is_pointer {
+        if i_ret.is_string && !i_ret.is_reference {
+            return Ok(parse_quote! {
+                std::ffi::CString::new(__ret).unwrap_or_default().into_raw() as #c_ty
+            });
+        }
+        if i_ret.is_option && i_ret.is_box {
+            return Ok(parse_quote!(__ret.map_or(std::ptr::null_mut(), Box::into_raw) as #c_ty));
+        }
+        if i_ret.is_box {
+            return Ok(parse_quote!(Box::into_raw(__ret) as #c_ty));
+        }
+    }
+    if shim_same_type(i_ret, c_ret) {
+        return Ok(parse_quote!(__ret));
+    }
+    if shim_is_numeric(i_ret) && shim_is_numeric(c_ret) {
+        return Ok(parse_quote!(__ret as #c_ty));
+    }
+    Err(pyo3::exceptions::PyValueError::new_err(format!(
+        "Cannot convert return type '{}' to '{}'",
+        i_ret.normalized, c_ret.normalized
+    )))
+}
+
+/// Generates the `extern "C"` wrapper with `c_signature` that forwards to the
+/// idiomatic function. `param_mapping_json` uses the function spec format
+/// (`{"fields": [{"u_field": ..., "i_field": ...}]}`); parameters it does not
+/// mention take the C parameter of the same name, or else of the same position.
+/// C strings are read through `CStr`, slice parameters are built with
+/// `from_raw_parts` using their `len_from`/`len_const` (a negative length
+/// aborts), and `&mut` scalars of a different width are written back through
+/// the out-pointer after the call. NULL strings and slices read as empty. A
+/// returned `String` comes with a `free_<name>_result` function to release it.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_ffi_shim(
+    c_signature: &str,
+    idiomatic_signature: &str,
+    param_mapping_json: &str,
+) -> PyResult<String> {
+    let mut shim = parse_signature_item(c_signature)?;
+    let mut idiomatic = parse_signature_item(idiomatic_signature)?;
+    if let Some(param) = signature_type_params(&idiomatic.sig).first() {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Generic parameter '{}' of '{}' has no concrete type for a C shim",
+            param, idiomatic.sig.ident
+        )));
+    }
+    erase_signature_lifetimes(&mut idiomatic.sig);
+    if idiomatic.sig.ident == shim.sig.ident {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Idiomatic function '{}' must not share the C function's name",
+            shim.sig.ident
+        )));
+    }
+    let mapping: HashMap<String, FieldMapping> = if param_mapping_json.trim().is_empty() {
+        HashMap::new()
+    } else {
+        parse_field_mappings(param_mapping_json)?
+            .into_iter()
+            .map(|field| (field.i_name.clone(), field))
+            .collect()
+    };
+
+    let c_params: Vec<(syn::Ident, TypeTraits)> = shim
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(ident) => Some((ident.ident.clone(), analyze_type(&pat_type.ty))),
+                _ => None,
+            },
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let find_c_param = |name: &str| {
+        c_params
+            .iter()
+            .find(|(ident, _)| ident == name)
+            .ok_or_else(|| {
+                pyo3::exceptions::PyValueError::new_err(format!(
+                    "C signature has no parameter '{}'",
+                    name
+                ))
+            })
+    };
+
+    let mut pre: Vec<syn::Stmt> = Vec::new();
+    let mut args: Vec<syn::Expr> = Vec::new();
+    let mut post: Vec<syn::Stmt> = Vec::new();
+    for (index, input) in idiomatic.sig.inputs.iter().enumerate() {
+        let syn::FnArg::Typed(pat_type) = input else {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Idiomatic signature cannot take self",
+            ));
+        };
+        let name = match &*pat_type.pat {
+            syn::Pat::Ident(ident) => ident.ident.to_string(),
+            other => quote!(#other).to_string(),
+        };
+        let field = mapping.get(&name);
+        let (c_ident, c_traits) = match field {
+            Some(field) => find_c_param(&field.u_name)?,
+            None => find_c_param(&name).or_else(|err| c_params.get(index).ok_or(err))?,
+        };
+        let len: Option<syn::Expr> = match field.and_then(|field| field.ptr.as_ref()) {
+            Some(PtrShape {
+                len_from: Some(len_from),
+                ..
+            }) => {
+                let (len_ident, len_traits) = find_c_param(len_from)?;
+                // a negative C length must not wrap around to a huge slice
+                Some(
+                    if shim_scalar(len_traits).is_some_and(|ty| ty.starts_with('i')) {
+                        let message = format!("negative length '{}' for '{}'", len_ident, name);
+                        parse_quote!(usize::try_from(#len_ident).expect(#message))
+                    } else {
+                        parse_quote!(#len_ident as usize)
+                    },
+                )
+            }
+            Some(PtrShape {
+                len_const: Some(len_const),
+                ..
+            }) => Some(parse_quote!(#len_const)),
+            _ => None,
+        };
+        let arg = shim_arg(&name, &analyze_type(&pat_type.ty), c_ident, c_traits, len)?;
+        pre.extend(arg.pre);
+        args.push(arg.arg);
+        post.extend(arg.post);
+    }
+
+    let callee = &idiomatic.sig.ident;
+    let mut call: syn::Expr = parse_quote!(#callee(#(#args),*));
+    if idiomatic.sig.unsafety.is_some() {
+        call = parse_quote!(unsafe { #call });
+    }
+    let i_ret = match &idiomatic.sig.output {
+        syn::ReturnType::Type(_, ty) => Some(analyze_type(ty)).filter(|t| t.normalized != "()"),
+        syn::ReturnType::Default => None,
+    };
+    let c_ret = match &shim.sig.output {
+        syn::ReturnType::Type(_, ty) => Some(analyze_type(ty)).filter(|t| t.normalized != "()"),
+        syn::ReturnType::Default => None,
+    };
+    let mut stmts = pre;
+    match (&i_ret, &c_ret) {
+        (_, None) => {
+            stmts.push(parse_quote!(#call;));
+            stmts.extend(post);
+        }
+        (Some(i_ret), Some(c_ret)) => {
+            let ret = shim_return(i_ret, c_ret)?;
+            if post.is_empty() && ret == parse_quote!(__ret) {
+                stmts.push(syn::Stmt::Expr(call, None));
+            } else {
+                stmts.push(parse_quote!(let __ret = #call;));
+                stmts.extend(post);
+                stmts.push(syn::Stmt::Expr(ret, None));
+            }
+        }
+        (None, Some(c_ret)) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Idiomatic function returns nothing but the C function returns '{}'",
+                c_ret.normalized
+            )))
+        }
+    }
+
+    shim.block.stmts = stmts;
+    shim.vis = syn::Visibility::Public(Token![pub](Span::call_site()));
+    // the shim trusts its pointer arguments
+    shim.sig.unsafety = Some(Token![unsafe](Span::call_site()));
+    if shim.sig.abi.is_none() {
+        shim.sig.abi = Some(parse_quote!(extern "C"));
+    }
+    if !shim
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("no_mangle"))
+    {
+        shim.attrs.push(parse_quote!(#[no_mangle]));
+    }
+    let name = shim.sig.ident.clone();
+    let mut items = vec![syn::Item::Fn(shim)];
+    // a returned `String` is owned by Rust's allocator, so C must hand it back
+    if let (Some(i_ret), Some(c_ret)) = (&i_ret, &c_ret) {
+        if c_ret.is_pointer && i_ret.is_string && !i_ret.is_reference {
+            let free = syn::Ident::new(&format!("free_{}_result", name), Span::call_site());
+            let c_ty = shim_type(c_ret)?;
+            items.push(parse_quote! {
+                #[no_mangle]
+                pub unsafe extern "C" fn #free(ptr: #c_ty) {
+                    if !ptr.is_null() {
+                        drop(std::ffi::CString::from_raw(ptr as *mut std::ffi::c_char));
+                    }
+                }
+            });
+        }
+    }
+    let file = syn::File {
+        shebang: None,
+        attrs: vec![],
+        items,
+    };
+    Ok(unparse(&file))
+}
+
+const IO_MACROS: &[&str] = &[
+    "print", "println", "eprint", "eprintln", "write", "writeln", "dbg",
+];
+/// Macros a `const fn` may use; everything else (`format!`, `vec!`, ...) blocks it.
+const CONST_MACROS: &[&str] = &[
+    "panic",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "unreachable",
+    "matches",
+];
+const IO_MODULES: &[&str] = &["io", "fs", "env", "process", "thread", "net", "time"];
+const INTERIOR_MUTABILITY_TYPES: &[&str] = &[
+    "Cell",
+    "RefCell",
+    "UnsafeCell",
+    "OnceCell",
+    "Mutex",
+    "RwLock",
+];
+
+/// Collects what keeps a function body from being pure or `const fn`-able.
+struct PurityScanner<'a> {
+    statics: &'a HashSet<String>,
+    externs: &'a HashSet<String>,
+    functions: &'a HashSet<String>,
+    reasons: BTreeSet<&'static str>,
+    calls: BTreeSet<String>,
+    const_blocked: bool,
+}
+
+impl PurityScanner<'_> {
+    fn is_local_path(path: &syn::Path) -> bool {
+        path.segments.len() == 1
+            || path.segments.len() == 2
+                && matches!(
+                    path.segments[0].ident.to_string().as_str(),
+                    "crate" | "self" | "super"
+                )
+    }
+
+    fn check_call(&mut self, path: &syn::Path) {
+        let Some(last) = path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+        else {
+            return;
+        };
+        let first = path.segments[0].ident.to_string();
+        if Self::is_local_path(path) && self.functions.contains(&last) {
+            self.calls.insert(last);
+            return;
+        }
+        // tuple struct and variant constructors such as `Some(x)`
+        if last.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return;
+        }
+        self.const_blocked = true;
+        if first == "libc" || Self::is_local_path(path) && self.externs.contains(&last) {
+            self.reasons.insert("ffi");
+        } else if path
+            .segments
+            .iter()
+            .any(|segment| IO_MODULES.contains(&segment.ident.to_string().as_str()))
+        {
+            self.reasons.insert("io");
+        } else if path.segments.len() == 1 {
+            // a free function defined outside this code
+            self.reasons.insert("unknown_call");
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PurityScanner<'_> {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Call(call) => {
+                if let syn::Expr::Path(func) = &*call.func {
+                    self.check_call(&func.path);
+                    for arg in call.args.iter() {
+                        self.visit_expr(arg);
+                    }
+                    return;
+                }
+                self.const_blocked = true;
+                self.reasons.insert("unknown_call");
+            }
+            syn::Expr::Path(path) => {
+                let last = path.path.segments.last().map(|s| s.ident.to_string());
+                if Self::is_local_path(&path.path)
+                    && last.is_some_and(|name| self.statics.contains(&name))
+                {
+                    self.reasons.insert("global");
+                }
+            }
+            syn::Expr::Unsafe(_) => {
+                self.reasons.insert("unsafe");
+            }
+            syn::Expr::MethodCall(_) | syn::Expr::ForLoop(_) | syn::Expr::Closure(_) => {
+                self.const_blocked = true;
+            }
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let name = node
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default();
+        if IO_MACROS.contains(&name.as_str()) {
+            self.reasons.insert("io");
+        }
+        if !CONST_MACROS.contains(&name.as_str()) {
+            self.const_blocked = true;
+        }
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_path_segment(&mut self, segment: &'ast syn::PathSegment) {
+        let name = segment.ident.to_string();
+        if INTERIOR_MUTABILITY_TYPES.contains(&name.as_str()) || name.starts_with("Atomic") {
+            self.reasons.insert("interior_mutability");
+        }
+        visit::visit_path_segment(self, segment);
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Flags functions with no I/O, FFI, global or unsafe access, interior
+/// mutability or mutable/raw pointer parameters, and that only call other
+/// such functions, as pure (safe to memoize). Pure functions that also avoid
+/// method calls, non-local calls, `for` loops, closures and allocating macros
+/// are `const fn` candidates. Returns one entry per top-level function:
+/// `name`, `pure`, `const_candidate`, `is_const` and the `reasons` it is impure.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn find_pure_functions(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let mut statics = HashSet::new();
+    let mut externs = HashSet::new();
+    let mut functions = HashSet::new();
+    for item in ast.items.iter() {
+        match item {
+            syn::Item::Static(s) => {
+                statics.insert(s.ident.to_string());
+            }
+            syn::Item::Fn(f) => {
+                functions.insert(f.sig.ident.to_string());
+            }
+            syn::Item::ForeignMod(block) => {
+                for foreign in block.items.iter() {
+                    match foreign {
+                        syn::ForeignItem::Static(s) => {
+                            statics.insert(s.ident.to_string());
+                        }
+                        other => externs.extend(foreign_item_name(other)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    struct Summary {
+        name: String,
+        is_const: bool,
+        reasons: BTreeSet<&'static str>,
+        calls: BTreeSet<String>,
+        const_blocked: bool,
+    }
+    let mut summaries = Vec::new();
+    for item in ast.items.iter() {
+        let syn::Item::Fn(f) = item else {
+            continue;
+        };
+        let mut scanner = PurityScanner {
+            statics: &statics,
+            externs: &externs,
+            functions: &functions,
+            reasons: BTreeSet::new(),
+            calls: BTreeSet::new(),
+            const_blocked: false,
+        };
+        for input in f.sig.inputs.iter() {
+            let syn::FnArg::Typed(pat_type) = input else {
+                scanner.const_blocked = true;
+                continue;
+            };
+            match param_kind(&pat_type.ty) {
+                ParamKind::MutRef => {
+                    scanner.reasons.insert("mutable_param");
+                }
+                ParamKind::Pointer => {
+                    scanner.reasons.insert("raw_pointer");
+                }
+                _ => {}
+            }
+            scanner.visit_type(&pat_type.ty);
+        }
+        if f.sig.asyncness.is_some() || f.sig.abi.is_some() {
+            scanner.const_blocked = true;
+        }
+        scanner.visit_block(&f.block);
+        summaries.push(Summary {
+            name: f.sig.ident.to_string(),
+            is_const: f.sig.constness.is_some(),
+            reasons: scanner.reasons,
+            calls: scanner.calls,
+            const_blocked: scanner.const_blocked,
+        });
+    }
+
+    // start optimistic so (mutually) recursive pure functions stay pure
+    let mut pure: HashMap<String, bool> = summaries
+        .iter()
+        .map(|s| (s.name.clone(), s.reasons.is_empty()))
+        .collect();
+    let mut constant: HashMap<String, bool> = summaries
+        .iter()
+        .map(|s| (s.name.clone(), s.reasons.is_empty() && !s.const_blocked))
+        .collect();
+    loop {
+        let mut changed = false;
+        for summary in summaries.iter() {
+            let callees_pure = summary.calls.iter().all(|callee| pure[callee]);
+            let callees_const = summary.calls.iter().all(|callee| constant[callee]);
+            if pure[&summary.name] && !callees_pure {
+                pure.insert(summary.name.clone(), false);
+                changed = true;
+            }
+            if constant[&summary.name] && !(callees_const && pure[&summary.name]) {
+                constant.insert(summary.name.clone(), false);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let result = PyList::empty(py);
+    for summary in summaries {
+        let mut reasons: Vec<String> = summary.reasons.iter().map(|r| r.to_string()).collect();
+        if summary.reasons.is_empty() && !pure[&summary.name] {
+            reasons.push("impure_call".to_string());
+        }
+        let entry = PyDict::new(py);
+        entry.set_item("name", &summary.name)?;
+        entry.set_item("pure", pure[&summary.name])?;
+        entry.set_item("const_candidate", constant[&summary.name])?;
+        entry.set_item("is_const", summary.is_const)?;
+        entry.set_item("reasons", reasons)?;
+        result.append(entry)?;
+    }
+    Ok(result.into())
+}
+
+const ATOMIC_SCALARS: &[(&str, &str)] = &[
+    ("bool", "AtomicBool"),
+    ("i8", "AtomicI8"),
+    ("u8", "AtomicU8"),
+    ("i16", "AtomicI16"),
+    ("u16", "AtomicU16"),
+    ("i32", "AtomicI32"),
+    ("u32", "AtomicU32"),
+    ("i64", "AtomicI64"),
+    ("u64", "AtomicU64"),
+    ("isize", "AtomicIsize"),
+    ("usize", "AtomicUsize"),
+];
+
+/// `libc` types the scalar map widens to pointer-sized integers, with the
+/// primitives `libc` defines them as on LP64 targets. The atomic must match
+/// the latter, as the use sites keep passing the `libc` type.
+const LIBC_ATOMIC_SCALARS: &[(&str, &str)] = &[("c_long", "i64"), ("c_ulong", "u64")];
+
+fn atomic_type_for(ty: &syn::Type) -> Option<&'static str> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let last = type_path.path.segments.last()?.ident.to_string();
+    let primitive = LIBC_ATOMIC_SCALARS
+        .iter()
+        .find(|(name, _)| *name == last)
+        .map(|(_, primitive)| *primitive)
+        .or_else(|| map_libc_scalar(&last))
+        .or_else(|| expected_stdint_target(&last))
+        .unwrap_or(last.as_str());
+    ATOMIC_SCALARS
+        .iter()
+        .find(|(scalar, _)| *scalar == primitive)
+        .map(|(_, atomic)| *atomic)
+}
+
+fn type_contains_pointer(ty: &syn::Type) -> bool {
+    struct PointerFinder(bool);
+    impl<'ast> Visit<'ast> for PointerFinder {
+        fn visit_type_ptr(&mut self, _node: &'ast syn::TypePtr) {
+            self.0 = true;
+        }
+    }
+    let mut finder = PointerFinder(false);
+    finder.visit_type(ty);
+    finder.0
+}
+
+fn path_expr_name(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Path(p) if p.qself.is_none() => p.path.get_ident().map(|i| i.to_string()),
+        _ => None,
+    }
+}
+
+/// Runs `f` on the comma-separated expressions of a macro invocation such as
+/// `println!("{}", X)`, writing the tokens back. Other macro shapes are left alone.
+fn visit_macro_exprs_mut(mac: &mut syn::Macro, f: &mut dyn FnMut(&mut syn::Expr)) {
+    let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+    if let Ok(mut args) = syn::parse::Parser::parse2(parser, mac.tokens.clone()) {
+        for arg in args.iter_mut() {
+            f(arg);
+        }
+        mac.tokens = args.to_token_stream();
+    }
+}
+
+/// How each `static mut` is accessed, used to pick its safe replacement.
+#[derive(Default)]
+struct StaticMutAccess {
+    written: HashSet<String>,
+    address_taken: HashSet<String>,
+    /// Referenced or turned into a raw pointer, which a lock guard could not
+    /// outlive.
+    escaped: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for StaticMutAccess {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Assign(assign) => {
+                if let Some(name) = assigned_root(&assign.left) {
+                    self.written.insert(name);
+                }
+            }
+            syn::Expr::Binary(binary) if is_compound_assign(&binary.op) => {
+                if let Some(name) = assigned_root(&binary.left) {
+                    self.written.insert(name);
+                }
+            }
+            syn::Expr::Reference(reference) => {
+                if let Some(name) = assigned_root(&reference.expr) {
+                    self.address_taken.insert(name.clone());
+                    self.escaped.insert(name.clone());
+                    if reference.mutability.is_some() {
+                        self.written.insert(name);
+                    }
+                }
+            }
+            syn::Expr::MethodCall(call) => {
+                // `X.as_mut_ptr()` or a `&mut self` method on an aggregate
+                if let Some(name) = assigned_root(&call.receiver) {
+                    if call.method == "as_ptr" || call.method == "as_mut_ptr" {
+                        self.escaped.insert(name.clone());
+                    }
+                    self.address_taken.insert(name.clone());
+                    self.written.insert(name);
+                }
+            }
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if node.path.is_ident("addr_of") || node.path.is_ident("addr_of_mut") {
+            if let Ok(expr) = node.parse_body::<syn::Expr>() {
+                if let Some(name) = assigned_root(&expr) {
+                    self.address_taken.insert(name.clone());
+                    self.escaped.insert(name.clone());
+                    self.written.insert(name);
+                }
+            }
+            return;
+        }
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
+
+/// The variable a place expression like `X.a[i].b` is rooted at.
+fn assigned_root(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Field(field) => assigned_root(&field.base),
+        syn::Expr::Index(index) => assigned_root(&index.expr),
+        syn::Expr::Paren(paren) => assigned_root(&paren.expr),
+        other => path_expr_name(other),
+    }
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    matches!(
+        op,
+        syn::BinOp::AddAssign(_)
+            | syn::BinOp::SubAssign(_)
+            | syn::BinOp::MulAssign(_)
+            | syn::BinOp::DivAssign(_)
+            | syn::BinOp::RemAssign(_)
+            | syn::BinOp::BitXorAssign(_)
+            | syn::BinOp::BitAndAssign(_)
+            | syn::BinOp::BitOrAssign(_)
+            | syn::BinOp::ShlAssign(_)
+            | syn::BinOp::ShrAssign(_)
+    )
+}
+
+/// Rewrites reads and writes of atomic-backed statics to `load`/`store`/`fetch_*`.
+struct AtomicAccessRewriter<'a> {
+    atomics: &'a HashSet<String>,
+}
+
+impl AtomicAccessRewriter<'_> {
+    fn is_atomic(&self, expr: &syn::Expr) -> Option<syn::Ident> {
+        let syn::Expr::Path(p) = expr else {
+            return None;
+        };
+        let ident = p.path.get_ident()?;
+        self.atomics
+            .contains(&ident.to_string())
+            .then(|| ident.clone())
+    }
+
+    /// Rewrites an assignment to an atomic; the result evaluates to `()` only
+    /// for plain stores, so `fetch_*` calls are returned separately.
+    fn rewrite_assignment(&mut self, expr: &mut syn::Expr) -> Option<(syn::Expr, bool)> {
+        let ordering = quote!(std::sync::atomic::Ordering::SeqCst);
+        match expr {
+            syn::Expr::Assign(assign) => {
+                let name = self.is_atomic(&assign.left)?;
+                self.visit_expr_mut(&mut assign.right);
+                let value = &assign.right;
+                Some((parse_quote!(#name.store(#value, #ordering)), false))
+            }
+            syn::Expr::Binary(binary) if is_compound_assign(&binary.op) => {
+                let name = self.is_atomic(&binary.left)?;
+                self.visit_expr_mut(&mut binary.right);
+                let value = &binary.right;
+                let fetch = match binary.op {
+                    syn::BinOp::AddAssign(_) => Some("fetch_add"),
+                    syn::BinOp::SubAssign(_) => Some("fetch_sub"),
+                    syn::BinOp::BitAndAssign(_) => Some("fetch_and"),
+                    syn::BinOp::BitOrAssign(_) => Some("fetch_or"),
+                    syn::BinOp::BitXorAssign(_) => Some("fetch_xor"),
+                    _ => None,
+                };
+                Some(match fetch {
+                    Some(method) => {
+                        let method = syn::Ident::new(method, Span::call_site());
+                        (parse_quote!(#name.#method(#value, #ordering)), true)
+                    }
+                    None => {
+                        let op = match binary.op {
+                            syn::BinOp::MulAssign(_) => quote!(*),
+                            syn::BinOp::DivAssign(_) => quote!(/),
+                            syn::BinOp::RemAssign(_) => quote!(%),
+                            syn::BinOp::ShlAssign(_) => quote!(<<),
+                            _ => quote!(>>),
+                        };
+                        let value: syn::Expr = match **value {
+                            syn::Expr::Lit(_) | syn::Expr::Path(_) => (**value).clone(),
+                            _ => parse_quote!((#value)),
+                        };
+                        (
+                            parse_quote!(#name.store(#name.load(#ordering) #op #value, #ordering)),
+                            false,
+                        )
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl VisitMut for AtomicAccessRewriter<'_> {
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Expr(expr, Some(_)) = stmt {
+            if let Some((new_expr, _)) = self.rewrite_assignment(expr) {
+                *expr = new_expr;
+                return;
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let Some((new_expr, returns_value)) = self.rewrite_assignment(expr) {
+            // keep the `()` type of the original compound assignment
+            *expr = if returns_value {
+                parse_quote!({ #new_expr; })
+            } else {
+                new_expr
+            };
+            return;
+        }
+        if let Some(name) = self.is_atomic(expr) {
+            *expr = parse_quote!(#name.load(std::sync::atomic::Ordering::SeqCst));
+            return;
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_exprs_mut(mac, &mut |expr| self.visit_expr_mut(expr));
+    }
+}
+
+/// Replaces uses of mutex-backed statics with lock guards. A statement that
+/// mentions the same static more than once gets a single guard so that it
+/// does not try to lock the mutex twice, unless it calls other code, which may
+/// lock the mutex itself; then every access locks and unlocks on its own.
+struct MutexAccessRewriter<'a> {
+    mutexes: &'a HashSet<String>,
+}
+
+/// Replaces occurrences of the given statics with `replacement(name)`, placing a
+/// deref in front when the static is used as a value rather than as a receiver.
+struct MutexOccurrenceReplacer<'a> {
+    names: &'a HashSet<String>,
+    guarded: &'a HashMap<String, syn::Ident>,
+    /// Reads and method calls copy their value out of a guard dropped right
+    /// away, with the operands evaluated before the lock is taken.
+    scoped: bool,
+}
+
+impl MutexOccurrenceReplacer<'_> {
+    fn receiver_for(&self, name: &str) -> syn::Expr {
+        match self.guarded.get(name) {
+            Some(guard) => parse_quote!(#guard),
+            None => {
+                let ident = syn::Ident::new(name, Span::call_site());
+                parse_quote!(#ident.lock().unwrap())
+            }
+        }
+    }
+
+    fn replace_receiver(&mut self, expr: &mut syn::Expr) -> bool {
+        match path_expr_name(expr) {
+            Some(name) if self.names.contains(&name) => {
+                *expr = self.receiver_for(&name);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The static a chain of fields, indexes and method calls like
+    /// `X.a[i].len()` starts from, if it is one of `names`.
+    fn access_root(&self, expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Field(field) => self.access_root(&field.base),
+            syn::Expr::Index(index) => self.access_root(&index.expr),
+            syn::Expr::MethodCall(call) => self.access_root(&call.receiver),
+            other => path_expr_name(other).filter(|name| self.names.contains(name)),
+        }
+    }
+
+    /// Replaces the static at the root of the place `expr` with a temporary
+    /// guard, moving index operands that lock or call into `hoisted`.
+    fn replace_place(&mut self, expr: &mut syn::Expr, hoisted: &mut Vec<syn::Stmt>) {
+        match expr {
+            syn::Expr::Field(field) => {
+                if !self.replace_receiver(&mut field.base) {
+                    self.replace_place(&mut field.base, hoisted);
+                }
+            }
+            syn::Expr::Index(index) => {
+                if !self.replace_receiver(&mut index.expr) {
+                    self.replace_place(&mut index.expr, hoisted);
+                }
+                self.hoist_operand(&mut index.index, hoisted);
+            }
+            syn::Expr::MethodCall(call) => {
+                if !self.replace_receiver(&mut call.receiver) {
+                    self.replace_place(&mut call.receiver, hoisted);
+                }
+                for arg in call.args.iter_mut() {
+                    self.hoist_operand(arg, hoisted);
+                }
+            }
+            _ => {
+                let name = path_expr_name(expr).unwrap_or_default();
+                let receiver = self.receiver_for(&name);
+                *expr = parse_quote!(*#receiver);
+            }
+        }
+    }
+
+    /// Evaluates an operand of an access before the lock is taken when it may
+    /// lock a mutex itself.
+    fn hoist_operand(&mut self, operand: &mut syn::Expr, hoisted: &mut Vec<syn::Stmt>) {
+        let mut counter = MutexOccurrenceCounter {
+            names: self.names,
+            counts: HashMap::new(),
+        };
+        counter.visit_expr(operand);
+        if counter.counts.is_empty() && !calls_other_code(operand, self.names) {
+            return;
+        }
+        self.visit_expr_mut(operand);
+        let ident = syn::Ident::new(&format!("operand{}", hoisted.len()), Span::call_site());
+        hoisted.push(parse_quote!(let #ident = #operand;));
+        *operand = parse_quote!(#ident);
+    }
+}
+
+impl VisitMut for MutexOccurrenceReplacer<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if self.scoped {
+            match expr {
+                // the value is evaluated before the place is locked
+                syn::Expr::Assign(assign) if self.access_root(&assign.left).is_some() => {
+                    self.visit_expr_mut(&mut assign.right);
+                    let mut hoisted = Vec::new();
+                    self.replace_place(&mut assign.left, &mut hoisted);
+                    if !hoisted.is_empty() {
+                        *expr = parse_quote!({ #(#hoisted)* #expr });
+                    }
+                }
+                syn::Expr::Binary(binary)
+                    if is_compound_assign(&binary.op)
+                        && self.access_root(&binary.left).is_some() =>
+                {
+                    self.visit_expr_mut(&mut binary.right);
+                    let mut hoisted = Vec::new();
+                    self.replace_place(&mut binary.left, &mut hoisted);
+                    if !hoisted.is_empty() {
+                        *expr = parse_quote!({ #(#hoisted)* #expr });
+                    }
+                }
+                _ if self.access_root(expr).is_some() => {
+                    let mut hoisted = Vec::new();
+                    self.replace_place(expr, &mut hoisted);
+                    *expr = parse_quote!({
+                        #(#hoisted)*
+                        let value = #expr;
+                        value
+                    });
+                }
+                _ => visit_mut::visit_expr_mut(self, expr),
+            }
+            return;
+        }
+        match expr {
+            syn::Expr::Field(field) => {
+                if !self.replace_receiver(&mut field.base) {
+                    self.visit_expr_mut(&mut field.base);
+                }
+            }
+            syn::Expr::Index(index) => {
+                if !self.replace_receiver(&mut index.expr) {
+                    self.visit_expr_mut(&mut index.expr);
+                }
+                self.visit_expr_mut(&mut index.index);
+            }
+            syn::Expr::MethodCall(call) => {
+                if !self.replace_receiver(&mut call.receiver) {
+                    self.visit_expr_mut(&mut call.receiver);
+                }
+                for arg in call.args.iter_mut() {
+                    self.visit_expr_mut(arg);
+                }
+            }
+            _ => match path_expr_name(expr) {
+                Some(name) if self.names.contains(&name) => {
+                    let receiver = self.receiver_for(&name);
+                    *expr = parse_quote!(*#receiver);
+                }
+                _ => visit_mut::visit_expr_mut(self, expr),
+            },
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_exprs_mut(mac, &mut |expr| self.visit_expr_mut(expr));
+    }
+}
+
+/// Whether `expr` calls a function, or a method on something other than one of
+/// the statics `names`; such code may lock their mutexes.
+fn calls_other_code(expr: &syn::Expr, names: &HashSet<String>) -> bool {
+    struct CallFinder<'a> {
+        names: &'a HashSet<String>,
+        found: bool,
+    }
+    impl<'ast> Visit<'ast> for CallFinder<'_> {
+        fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+            self.found = true;
+            visit::visit_expr_call(self, node);
+        }
+
+        fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+            if !assigned_root(&node.receiver).is_some_and(|name| self.names.contains(&name)) {
+                self.found = true;
+            }
+            visit::visit_expr_method_call(self, node);
+        }
+
+        fn visit_macro(&mut self, node: &'ast syn::Macro) {
+            let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+            if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+                for arg in args.iter() {
+                    self.visit_expr(arg);
+                }
+            }
+        }
+    }
+    let mut finder = CallFinder {
+        names,
+        found: false,
+    };
+    finder.visit_expr(expr);
+    finder.found
+}
+
+struct MutexOccurrenceCounter<'a> {
+    names: &'a HashSet<String>,
+    counts: HashMap<String, usize>,
+}
+
+impl<'ast> Visit<'ast> for MutexOccurrenceCounter<'_> {
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if let Some(ident) = node.path.get_ident() {
+            let name = ident.to_string();
+            if self.names.contains(&name) {
+                *self.counts.entry(name).or_default() += 1;
+            }
+        }
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
+
+impl MutexAccessRewriter<'_> {
+    /// Rewrites one full expression. When any static occurs more than once the
+    /// expression is moved into a block holding the guards; `discard_value` is
+    /// set for expression statements whose value is unused.
+    fn rewrite_full_expr(&self, expr: &mut syn::Expr, discard_value: bool) {
+        let mut counter = MutexOccurrenceCounter {
+            names: self.mutexes,
+            counts: HashMap::new(),
+        };
+        counter.visit_expr(expr);
+        if counter.counts.is_empty() {
+            return;
+        }
+        if calls_other_code(expr, self.mutexes) {
+            let mut replacer = MutexOccurrenceReplacer {
+                names: self.mutexes,
+                guarded: &HashMap::new(),
+                scoped: true,
+            };
+            replacer.visit_expr_mut(expr);
+            return;
+        }
+
+        let mut repeated: Vec<String> = counter
+            .counts
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(name, _)| name.clone())
+            .collect();
+        repeated.sort();
+        let guarded: HashMap<String, syn::Ident> = repeated
+            .iter()
+            .map(|name| {
+                let guard = format!("{}_guard", name.to_lowercase());
+                (name.clone(), syn::Ident::new(&guard, Span::call_site()))
+            })
+            .collect();
+
+        let mut replacer = MutexOccurrenceReplacer {
+            names: self.mutexes,
+            guarded: &guarded,
+            scoped: false,
+        };
+        replacer.visit_expr_mut(expr);
+
+        if repeated.is_empty() {
+            return;
+        }
+        let guard_stmts: Vec<syn::Stmt> = repeated
+            .iter()
+            .map(|name| {
+                let guard = &guarded[name];
+                let ident = syn::Ident::new(name, Span::call_site());
+                parse_quote!(let mut #guard = #ident.lock().unwrap();)
+            })
+            .collect();
+        let inner = mem::replace(expr, parse_quote!(()));
+        *expr = if discard_value {
+            parse_quote!({
+                #(#guard_stmts)*
+                #inner;
+            })
+        } else {
+            parse_quote!({
+                #(#guard_stmts)*
+                let value = #inner;
+                value
+            })
+        };
+    }
+
+    fn rewrite_block(&self, block: &mut syn::Block) {
+        for stmt in block.stmts.iter_mut() {
+            match stmt {
+                syn::Stmt::Local(local) => {
+                    if let Some(init) = local.init.as_mut() {
+                        self.rewrite_full_expr(&mut init.expr, false);
+                        if let Some((_, diverge)) = init.diverge.as_mut() {
+                            self.rewrite_statement_expr(diverge, false);
+                        }
+                    }
+                }
+                syn::Stmt::Expr(expr, semi) => self.rewrite_statement_expr(expr, semi.is_some()),
+                syn::Stmt::Macro(stmt_macro) => {
+                    let mut expr = syn::Expr::Macro(syn::ExprMacro {
+                        attrs: Vec::new(),
+                        mac: stmt_macro.mac.clone(),
+                    });
+                    self.rewrite_full_expr(&mut expr, stmt_macro.semi_token.is_some());
+                    match expr {
+                        syn::Expr::Macro(expr_macro) => stmt_macro.mac = expr_macro.mac,
+                        other => {
+                            *stmt = syn::Stmt::Expr(other, stmt_macro.semi_token);
+                        }
+                    }
+                }
+                syn::Stmt::Item(_) => {}
+            }
+        }
+    }
+
+    /// Control-flow statements keep their bodies as separate statements so that
+    /// a guard never spans a loop or branch body.
+    fn rewrite_statement_expr(&self, expr: &mut syn::Expr, discard_value: bool) {
+        match expr {
+            syn::Expr::If(expr_if) => {
+                self.rewrite_full_expr(&mut expr_if.cond, false);
+                self.rewrite_block(&mut expr_if.then_branch);
+                if let Some((_, else_branch)) = expr_if.else_branch.as_mut() {
+                    self.rewrite_statement_expr(else_branch, discard_value);
+                }
+            }
+            syn::Expr::While(expr_while) => {
+                self.rewrite_full_expr(&mut expr_while.cond, false);
+                self.rewrite_block(&mut expr_while.body);
+            }
+            syn::Expr::ForLoop(expr_for) => {
+                self.rewrite_full_expr(&mut expr_for.expr, false);
+                self.rewrite_block(&mut expr_for.body);
+            }
+            syn::Expr::Loop(expr_loop) => self.rewrite_block(&mut expr_loop.body),
+            syn::Expr::Block(expr_block) => self.rewrite_block(&mut expr_block.block),
+            syn::Expr::Unsafe(expr_unsafe) => self.rewrite_block(&mut expr_unsafe.block),
+            syn::Expr::Match(expr_match) => {
+                // scrutinee temporaries live for the whole match, so copy the value out
+                let mut counter = MutexOccurrenceCounter {
+                    names: self.mutexes,
+                    counts: HashMap::new(),
+                };
+                counter.visit_expr(&expr_match.expr);
+                if !counter.counts.is_empty() {
+                    self.rewrite_full_expr(&mut expr_match.expr, false);
+                    if !matches!(*expr_match.expr, syn::Expr::Block(_)) {
+                        let scrutinee = &expr_match.expr;
+                        *expr_match.expr = parse_quote!({
+                            let value = #scrutinee;
+                            value
+                        });
+                    }
+                }
+                for arm in expr_match.arms.iter_mut() {
+                    if let Some((_, guard)) = arm.guard.as_mut() {
+                        self.rewrite_full_expr(guard, false);
+                    }
+                    self.rewrite_statement_expr(&mut arm.body, false);
+                }
+            }
+            other => self.rewrite_full_expr(other, discard_value),
+        }
+    }
+}
+
+/// Rewrites `static mut` items into safe globals: atomics for integer and bool
+/// scalars, plain `static` when the value is never written, and `Mutex` otherwise.
+/// Statics holding raw pointers or whose address escapes are left untouched.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn rewrite_static_mut_globals(code: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    let mut access = StaticMutAccess::default();
+    access.visit_file(&ast);
+
+    let mut atomics: HashSet<String> = HashSet::new();
+    let mut mutexes: HashSet<String> = HashSet::new();
+
+    for item in ast.items.iter_mut() {
+        let syn::Item::Static(s) = item else {
+            continue;
+        };
+        if !matches!(s.mutability, syn::StaticMutability::Mut(_)) || type_contains_pointer(&s.ty) {
+            continue;
+        }
+        let name = s.ident.to_string();
+        if access.address_taken.contains(&name) && atomic_type_for(&s.ty).is_some() {
+            continue;
+        }
+
+        if !access.written.contains(&name) {
+            s.mutability = syn::StaticMutability::None;
+        } else if let Some(atomic) = atomic_type_for(&s.ty) {
+            let atomic = syn::Ident::new(atomic, Span::call_site());
+            let init = s.expr.clone();
+            *s.ty = parse_quote!(std::sync::atomic::#atomic);
+            *s.expr = parse_quote!(std::sync::atomic::#atomic::new(#init));
+            s.mutability = syn::StaticMutability::None;
+            atomics.insert(name);
+        } else if !access.escaped.contains(&name) {
+            let ty = &s.ty;
+            let init = s.expr.clone();
+            *s.ty = parse_quote!(std::sync::Mutex<#ty>);
+            *s.expr = parse_quote!(std::sync::Mutex::new(#init));
+            s.mutability = syn::StaticMutability::None;
+            mutexes.insert(name);
+        }
+    }
+
+    if !atomics.is_empty() {
+        let mut rewriter = AtomicAccessRewriter { atomics: &atomics };
+        for item in ast.items.iter_mut() {
+            if !matches!(item, syn::Item::Static(_)) {
+                rewriter.visit_item_mut(item);
+            }
+        }
+    }
+
+    if !mutexes.is_empty() {
+        let rewriter = MutexAccessRewriter { mutexes: &mutexes };
+        struct BlockVisitor<'a>(&'a MutexAccessRewriter<'a>);
+        impl VisitMut for BlockVisitor<'_> {
+            fn visit_item_fn_mut(&mut self, f: &mut syn::ItemFn) {
+                self.0.rewrite_block(&mut f.block);
+            }
+            fn visit_impl_item_fn_mut(&mut self, f: &mut syn::ImplItemFn) {
+                self.0.rewrite_block(&mut f.block);
+            }
+        }
+        BlockVisitor(&rewriter).visit_file_mut(&mut ast);
+    }
+
+    Ok(unparse(&ast))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ParamKind {
+    Value,
+    Pointer,
+    SharedRef,
+    MutRef,
+}
+
+#[derive(Default)]
+struct ParamAccess {
+    read: bool,
+    written: bool,
+    /// A whole-value write on every path has happened, so later reads see
+    /// the function's own value rather than the caller's.
+    definitely_written: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Access {
+    Read,
+    Write { whole: bool },
+    ReadWrite,
+}
+
+const POINTER_ARITH_METHODS: &[&str] = &[
+    "add",
+    "sub",
+    "offset",
+    "wrapping_add",
+    "wrapping_sub",
+    "wrapping_offset",
+    "cast",
+    "cast_mut",
+    "cast_const",
+    "is_null",
+];
+const WHOLE_WRITE_METHODS: &[&str] = &["write", "fill", "copy_from_slice", "clone_from_slice"];
+const MUTATING_METHODS: &[&str] = &[
+    "push",
+    "push_str",
+    "insert",
+    "remove",
+    "pop",
+    "clear",
+    "truncate",
+    "extend",
+    "swap",
+    "sort",
+    "sort_unstable",
+    "reverse",
+    "as_mut",
+    "as_mut_ptr",
+    "get_mut",
+    "iter_mut",
+    "write_bytes",
+];
+
+/// Walks a function body in evaluation order and records how each parameter
+/// (or, for pointers and references, its pointee) is accessed.
+struct ParamUsageVisitor {
+    kinds: HashMap<String, ParamKind>,
+    /// `*const` pointer parameters, which callees can only read through.
+    const_pointers: HashSet<String>,
+    access: HashMap<String, ParamAccess>,
+    /// Nesting depth of conditional/loop bodies; writes there are not definite.
+    depth: usize,
+}
+
+/// Finds the parameter a place expression is rooted at, whether the place goes
+/// through the pointee (`*p`, `p[i]`, `(*p).f`) and whether it names the whole value.
+fn param_place_root(expr: &syn::Expr) -> Option<(String, bool, bool)> {
+    match strip_parens(expr) {
+        syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Deref(_)) => {
+            let inner = strip_parens(&unary.expr);
+            if let Some(name) = path_expr_name(inner) {
+                return Some((name, true, true));
+            }
+            if let syn::Expr::MethodCall(call) = inner {
+                if POINTER_ARITH_METHODS.contains(&call.method.to_string().as_str()) {
+                    if let Some(name) = path_expr_name(strip_parens(&call.receiver)) {
+                        return Some((name, true, false));
+                    }
+                }
+            }
+            param_place_root(inner).map(|(name, _, _)| (name, true, false))
+        }
+        syn::Expr::Field(field) => param_place_root(&field.base).map(|(n, d, _)| (n, d, false)),
+        syn::Expr::Index(index) => {
+            if let Some(name) = path_expr_name(strip_parens(&index.expr)) {
+                return Some((name, true, false));
+            }
+            param_place_root(&index.expr).map(|(name, _, _)| (name, true, false))
+        }
+        other => path_expr_name(other).map(|name| (name, false, true)),
+    }
+}
+
+impl ParamUsageVisitor {
+    fn record(&mut self, name: &str, access: Access) {
+        let depth = self.depth;
+        let Some(state) = self.access.get_mut(name) else {
+            return;
+        };
+        match access {
+            Access::Read => {
+                if !state.definitely_written {
+                    state.read = true;
+                }
+            }
+            Access::Write { whole } => {
+                state.written = true;
+                if whole && depth == 0 {
+                    state.definitely_written = true;
+                }
+            }
+            Access::ReadWrite => {
+                if !state.definitely_written {
+                    state.read = true;
+                }
+                state.written = true;
+            }
+        }
+    }
+
+    /// Records an access to a place; only pointee accesses count for pointers
+    /// and references, only direct accesses for value parameters.
+    fn record_place(&mut self, place: &syn::Expr, access: Access) -> bool {
+        let Some((name, through_pointee, whole)) = param_place_root(place) else {
+            return false;
+        };
+        let Some(kind) = self.kinds.get(&name).copied() else {
+            return false;
+        };
+        let counts = match kind {
+            ParamKind::Value => true,
+            _ => through_pointee,
+        };
+        if counts {
+            let access = match access {
+                Access::Write { .. } => Access::Write { whole },
+                other => other,
+            };
+            self.record(&name, access);
+        }
+        true
+    }
+
+    /// Visits the index and offset expressions inside a place without treating
+    /// the place itself as a read.
+    fn visit_place_operands(&mut self, place: &syn::Expr) {
+        match strip_parens(place) {
+            syn::Expr::Unary(unary) => match strip_parens(&unary.expr) {
+                syn::Expr::MethodCall(call) => {
+                    for arg in call.args.iter() {
+                        self.visit_expr(arg);
+                    }
+                }
+                inner => self.visit_place_operands(inner),
+            },
+            syn::Expr::Field(field) => self.visit_place_operands(&field.base),
+            syn::Expr::Index(index) => {
+                self.visit_place_operands(&index.expr);
+                self.visit_expr(&index.index);
+            }
+            syn::Expr::Path(_) => {}
+            other => self.visit_expr(other),
+        }
+    }
+
+    /// A pointer or mutable reference handed to code we cannot see may be
+    /// read and written.
+    fn record_escape(&mut self, expr: &syn::Expr) -> bool {
+        let expr = strip_parens(expr);
+        let root = match expr {
+            syn::Expr::MethodCall(call)
+                if POINTER_ARITH_METHODS.contains(&call.method.to_string().as_str()) =>
+            {
+                path_expr_name(strip_parens(&call.receiver))
+            }
+            syn::Expr::Cast(cast) => path_expr_name(strip_parens(&cast.expr)),
+            other => path_expr_name(other),
+        };
+        let root = root.filter(|name| self.kinds.contains_key(name));
+        let read_only = root
+            .as_ref()
+            .is_some_and(|name| self.const_pointers.contains(name));
+        match root.as_ref().and_then(|name| self.kinds.get(name)) {
+            Some(ParamKind::Pointer) | Some(ParamKind::MutRef) if !read_only => {
+                self.record(root.as_ref().unwrap(), Access::ReadWrite);
+                if let syn::Expr::MethodCall(call) = expr {
+                    for arg in call.args.iter() {
+                        self.visit_expr(arg);
+                    }
+                }
+                true
+            }
+            Some(ParamKind::Pointer) | Some(ParamKind::SharedRef) => {
+                self.record(root.as_ref().unwrap(), Access::Read);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn visit_nested_block(&mut self, block: &syn::Block) {
+        self.depth += 1;
+        self.visit_block(block);
+        self.depth -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for ParamUsageVisitor {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Assign(assign) => {
+                self.visit_expr(&assign.right);
+                if self.record_place(&assign.left, Access::Write { whole: true }) {
+                    self.visit_place_operands(&assign.left);
+                } else {
+                    self.visit_expr(&assign.left);
+                }
+            }
+            syn::Expr::Binary(binary) if is_compound_assign(&binary.op) => {
+                self.visit_expr(&binary.right);
+                if self.record_place(&binary.left, Access::ReadWrite) {
+                    self.visit_place_operands(&binary.left);
+                } else {
+                    self.visit_expr(&binary.left);
+                }
+            }
+            syn::Expr::Binary(binary) => {
+                // comparing a pointer against null does not touch the pointee
+                for operand in [&binary.left, &binary.right] {
+                    let is_pointer = path_expr_name(strip_parens(operand))
+                        .and_then(|name| self.kinds.get(&name).copied())
+                        == Some(ParamKind::Pointer);
+                    if !is_pointer {
+                        self.visit_expr(operand);
+                    }
+                }
+            }
+            syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Deref(_)) => {
+                if self.record_place(expr, Access::Read) {
+                    self.visit_place_operands(expr);
+                } else {
+                    self.visit_expr(&unary.expr);
+                }
+            }
+            syn::Expr::Field(_) | syn::Expr::Index(_) => {
+                if self.record_place(expr, Access::Read) {
+                    self.visit_place_operands(expr);
+                } else {
+                    visit::visit_expr(self, expr);
+                }
+            }
+            syn::Expr::Reference(reference) => {
+                let access = if reference.mutability.is_some() {
+                    Access::ReadWrite
+                } else {
+                    Access::Read
+                };
+                if self.record_place(&reference.expr, access) {
+                    self.visit_place_operands(&reference.expr);
+                } else if !self.record_escape(&reference.expr) {
+                    self.visit_expr(&reference.expr);
+                }
+            }
+            syn::Expr::MethodCall(call) => {
+                let method = call.method.to_string();
+                let receiver = strip_parens(&call.receiver);
+                let receiver_kind =
+                    path_expr_name(receiver).and_then(|name| self.kinds.get(&name).copied());
+                if POINTER_ARITH_METHODS.contains(&method.as_str())
+                    && receiver_kind == Some(ParamKind::Pointer)
+                {
+                    // pointer arithmetic only; a dereference around it is the access
+                } else {
+                    let access = if method == "read" {
+                        Access::Read
+                    } else if WHOLE_WRITE_METHODS.contains(&method.as_str()) {
+                        Access::Write { whole: true }
+                    } else if MUTATING_METHODS.contains(&method.as_str()) {
+                        Access::ReadWrite
+                    } else {
+                        Access::Read
+                    };
+                    match path_expr_name(receiver) {
+                        Some(name) if receiver_kind.is_some() => self.record(&name, access),
+                        _ => {
+                            if self.record_place(receiver, access) {
+                                self.visit_place_operands(receiver);
+                            } else {
+                                self.visit_expr(&call.receiver);
+                            }
+                        }
+                    }
+                }
+                for arg in call.args.iter() {
+                    if !self.record_escape(arg) {
+                        self.visit_expr(arg);
+                    }
+                }
+            }
+            syn::Expr::Call(call) => {
+                let write_target = if is_libc_call(&call.func, "write")
+                    || path_ends_with(&call.func, &["ptr", "write"])
+                {
+                    call.args.first()
+                } else {
+                    None
+                };
+                for (index, arg) in call.args.iter().enumerate() {
+                    if index == 0 && write_target.is_some() {
+                        if let Some(name) = path_expr_name(strip_parens(arg)) {
+                            if self.kinds.get(&name) == Some(&ParamKind::Pointer) {
+                                self.record(&name, Access::Write { whole: true });
+                                continue;
+                            }
+                        }
+                    }
+                    if path_ends_with(&call.func, &["ptr", "read"])
+                        || path_ends_with(&call.func, &["from_raw_parts"])
+                    {
+                        if let Some(name) = path_expr_name(strip_parens(arg)) {
+                            if self.kinds.contains_key(&name) {
+                                self.record(&name, Access::Read);
+                                continue;
+                            }
+                        }
+                    }
+                    if !self.record_escape(arg) {
+                        self.visit_expr(arg);
+                    }
+                }
+                self.visit_expr(&call.func);
+            }
+            syn::Expr::If(expr_if) => {
+                self.visit_expr(&expr_if.cond);
+                self.visit_nested_block(&expr_if.then_branch);
+                if let Some((_, else_branch)) = &expr_if.else_branch {
+                    self.depth += 1;
+                    self.visit_expr(else_branch);
+                    self.depth -= 1;
+                }
+            }
+            syn::Expr::While(expr_while) => {
+                self.depth += 1;
+                self.visit_expr(&expr_while.cond);
+                self.visit_block(&expr_while.body);
+                self.depth -= 1;
+            }
+            syn::Expr::ForLoop(expr_for) => {
+                self.visit_expr(&expr_for.expr);
+                self.visit_nested_block(&expr_for.body);
+            }
+            syn::Expr::Loop(expr_loop) => self.visit_nested_block(&expr_loop.body),
+            syn::Expr::Match(expr_match) => {
+                self.visit_expr(&expr_match.expr);
+                self.depth += 1;
+                for arm in expr_match.arms.iter() {
+                    if let Some((_, guard)) = &arm.guard {
+                        self.visit_expr(guard);
+                    }
+                    self.visit_expr(&arm.body);
+                }
+                self.depth -= 1;
+            }
+            syn::Expr::Closure(closure) => {
+                self.depth += 1;
+                self.visit_expr(&closure.body);
+                self.depth -= 1;
+            }
+            syn::Expr::Path(_) => {
+                if let Some(name) = path_expr_name(expr) {
+                    match self.kinds.get(&name) {
+                        Some(ParamKind::Value) => self.record(&name, Access::Read),
+                        Some(_) => {
+                            self.record_escape(expr);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            _ => visit::visit_expr(self, expr),
+        }
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let Some(init) = &local.init {
+            if !self.record_escape(&init.expr) {
+                self.visit_expr(&init.expr);
+            }
+            if let Some((_, diverge)) = &init.diverge {
+                self.visit_expr(diverge);
+            }
+        }
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Checks whether a path expression ends with the given segments.
+fn path_ends_with(expr: &syn::Expr, segments: &[&str]) -> bool {
+    let syn::Expr::Path(p) = strip_parens(expr) else {
+        return false;
+    };
+    let names: Vec<String> = p
+        .path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect();
+    names.len() >= segments.len()
+        && names[names.len() - segments.len()..]
+            .iter()
+            .zip(segments)
+            .all(|(a, b)| a == b)
+}
+
+fn param_kind(ty: &syn::Type) -> ParamKind {
+    match ty {
+        syn::Type::Ptr(_) => ParamKind::Pointer,
+        syn::Type::Reference(r) if r.mutability.is_some() => ParamKind::MutRef,
+        syn::Type::Reference(_) => ParamKind::SharedRef,
+        syn::Type::Paren(p) => param_kind(&p.elem),
+        _ => ParamKind::Value,
+    }
+}
+
+/// Runs `ParamUsageVisitor` over a function, returning its identifier
+/// parameters in order with their types and recorded accesses.
+fn collect_param_usage(
+    f: &syn::ItemFn,
+) -> (Vec<(String, &syn::Type)>, HashMap<String, ParamAccess>) {
+    let mut params = Vec::new();
+    let mut visitor = ParamUsageVisitor {
+        kinds: HashMap::new(),
+        const_pointers: HashSet::new(),
+        access: HashMap::new(),
+        depth: 0,
+    };
+    for input in f.sig.inputs.iter() {
+        let syn::FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+            continue;
+        };
+        let name = pat_ident.ident.to_string();
+        visitor.kinds.insert(name.clone(), param_kind(&pat_type.ty));
+        if let syn::Type::Ptr(ptr) = &*pat_type.ty {
+            if ptr.const_token.is_some() {
+                visitor.const_pointers.insert(name.clone());
+            }
+        }
+        visitor.access.insert(name.clone(), ParamAccess::default());
+        params.push((name, &*pat_type.ty));
+    }
+    visitor.visit_block(&f.block);
+    (params, visitor.access)
+}
+
+/// Classifies each parameter of a function as `read`, `write` (an output
+/// parameter whose incoming value is never used), `read_write` or `unused`.
+/// For pointers and references the classification refers to the pointee.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn analyze_parameter_usage(py: Python<'_>, code: &str, function_name: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+
+    for item in ast.items.iter() {
+        let syn::Item::Fn(f) = item else {
+            continue;
+        };
+        if f.sig.ident != function_name {
+            continue;
+        }
+
+        let (params, access) = collect_param_usage(f);
+        let result = PyList::empty(py);
+        for (name, ty) in params {
+            let ty = analyze_type(ty).raw;
+            let state = &access[&name];
+            let usage = match (state.read, state.written) {
+                (true, true) => "read_write",
+                (true, false) => "read",
+                (false, true) => "write",
+                (false, false) => "unused",
+            };
+            let entry = PyDict::new(py);
+            entry.set_item("name", name)?;
+            entry.set_item("type", ty)?;
+            entry.set_item("usage", usage)?;
+            result.append(entry)?;
+        }
+        return Ok(result.into());
+    }
+
+    Err(pyo3::exceptions::PyValueError::new_err(format!(
+        "Function '{}' not found",
+        function_name
+    )))
+}
+
+/// Lists `&mut T` and `*mut T` parameters whose pointee is never written
+/// (directly, through mutating methods, or by handing the pointer to other
+/// code), with the `&T`/`*const T` type they could take instead.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn suggest_immutable_params(py: Python<'_>, code: &str, function_name: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let Some(f) = ast.items.iter().find_map(|item| match item {
+        syn::Item::Fn(f) if f.sig.ident == function_name => Some(f),
+        _ => None,
+    }) else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' not found",
+            function_name
+        )));
+    };
+
+    let (params, access) = collect_param_usage(f);
+    let result = PyList::empty(py);
+    for (name, ty) in params {
+        if access[&name].written {
+            continue;
+        }
+        let suggested: syn::Type = match ty {
+            syn::Type::Reference(r) if r.mutability.is_some() => {
+                let mut shared = r.clone();
+                shared.mutability = None;
+                syn::Type::Reference(shared)
+            }
+            syn::Type::Ptr(p) if p.mutability.is_some() => {
+                let elem = &p.elem;
+                parse_quote!(*const #elem)
+            }
+            _ => continue,
+        };
+        let entry = PyDict::new(py);
+        entry.set_item("name", name)?;
+        entry.set_item("type", analyze_type(ty).raw)?;
+        entry.set_item("suggested_type", analyze_type(&suggested).raw)?;
+        result.append(entry)?;
+    }
+    Ok(result.into())
+}
+
+/// What `analyze_pointer_ownership` learns about one raw pointer variable.
+#[derive(Default)]
+struct PointerFacts {
+    /// The allocator or conversion the pointer was last assigned from.
+    allocated: Option<String>,
+    /// The functions the pointee was released with.
+    freed: Vec<String>,
+    /// The pointer goes through pointer arithmetic, so it points into an array.
+    array: bool,
+    nullable: bool,
+    returned: bool,
+    /// Places the pointer is stored into, with the position of the store.
+    stores: Vec<(String, usize)>,
+    copies: Vec<String>,
+    /// Calls receiving the pointer in two arguments.
+    passed_twice: Vec<String>,
+}
+
+/// Walks a function body in evaluation order, numbering variable uses, and
+/// records the allocations, frees, stores and copies of its raw pointers.
+struct PointerOwnershipVisitor {
+    facts: HashMap<String, PointerFacts>,
+    /// Types of the tracked pointers, in declaration order.
+    pointers: Vec<(String, syn::Type, &'static str)>,
+    /// Position of the last use of each variable.
+    last_use: HashMap<String, usize>,
+    position: usize,
+}
+
+const POINTER_ALLOCATORS: &[&str] = &["malloc", "calloc", "realloc", "strdup", "strndup"];
+const POINTER_RELEASERS: &[&str] = &["free", "realloc"];
+
+/// The allocator an expression returns the result of: a libc allocator or an
+/// `into_raw` conversion of an owned value.
+fn pointer_allocator(expr: &syn::Expr) -> Option<String> {
+    match strip_parens(expr) {
+        syn::Expr::Cast(cast) => pointer_allocator(&cast.expr),
+        syn::Expr::Call(call) => {
+            if let Some(name) = POINTER_ALLOCATORS
+                .iter()
+                .find(|name| is_libc_call(&call.func, name))
+            {
+                return Some(name.to_string());
+            }
+            let syn::Expr::Path(path) = strip_parens(&call.func) else {
+                return None;
+            };
+            (path.path.segments.last()?.ident == "into_raw")
+                .then(|| normalize_token_string(&path.to_token_stream().to_string()))
+        }
+        syn::Expr::MethodCall(call) if call.method == "into_raw" => Some("into_raw".to_string()),
+        syn::Expr::MethodCall(call)
+            if matches!(
+                call.method.to_string().as_str(),
+                "cast" | "cast_mut" | "cast_const"
+            ) =>
+        {
+            pointer_allocator(&call.receiver)
+        }
+        _ => None,
+    }
+}
+
+/// The pointer type a `let` binding declares or casts its initializer to.
+fn local_pointer_type(local: &syn::Local) -> Option<syn::Type> {
+    if let syn::Pat::Type(pat_type) = &local.pat {
+        return matches!(&*pat_type.ty, syn::Type::Ptr(_)).then(|| (*pat_type.ty).clone());
+    }
+    let init = &local.init.as_ref()?.expr;
+    pointer_allocator(init)?;
+    match strip_parens(init) {
+        syn::Expr::Cast(cast) if matches!(&*cast.ty, syn::Type::Ptr(_)) => Some((*cast.ty).clone()),
+        _ => Some(parse_quote!(*mut ::core::ffi::c_void)),
+    }
+}
+
+/// A place expression as written, e.g. `(*list).head`.
+fn place_text(expr: &syn::Expr) -> String {
+    normalize_token_string(&expr.to_token_stream().to_string())
+        .replace(" . ", ".")
+        .replace("(* ", "(*")
+        .replace(" [", "[")
+        .replace("[ ", "[")
+        .replace(" ]", "]")
+}
+
+fn is_null_pointer(expr: &syn::Expr) -> bool {
+    match strip_parens(expr) {
+        syn::Expr::Cast(cast) => is_null_pointer(&cast.expr),
+        syn::Expr::Call(call) => {
+            path_ends_with(&call.func, &["null_mut"]) || path_ends_with(&call.func, &["null"])
+        }
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => int.base10_digits() == "0",
+        _ => false,
+    }
+}
+
+impl PointerOwnershipVisitor {
+    fn tracked(&self, expr: &syn::Expr) -> Option<String> {
+        peeled_var_name(expr).filter(|name| self.facts.contains_key(name))
+    }
+
+    fn store(&mut self, value: &syn::Expr, place: String) {
+        if let Some(name) = self.tracked(value) {
+            let position = self.position;
+            self.facts
+                .get_mut(&name)
+                .unwrap()
+                .stores
+                .push((place, position));
+        }
+    }
+
+    fn mark_returned(&mut self, expr: &syn::Expr) {
+        if let Some(name) = self.tracked(expr) {
+            self.facts.get_mut(&name).unwrap().returned = true;
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PointerOwnershipVisitor {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Path(_) => {
+                if let Some(name) = path_expr_name(expr) {
+                    self.position += 1;
+                    self.last_use.insert(name, self.position);
+                }
+            }
+            syn::Expr::Assign(assign) => {
+                self.visit_expr(&assign.right);
+                // overwriting a variable is not a use of its old value
+                let target = path_expr_name(strip_parens(&assign.left));
+                if target.is_none() {
+                    self.visit_expr(&assign.left);
+                }
+                let place = place_text(&assign.left);
+                match target {
+                    Some(target) if self.facts.contains_key(&target) => {
+                        if let Some(allocator) = pointer_allocator(&assign.right) {
+                            self.facts.get_mut(&target).unwrap().allocated = Some(allocator);
+                        } else if let Some(name) = self.tracked(&assign.right) {
+                            self.facts.get_mut(&name).unwrap().copies.push(target);
+                        }
+                    }
+                    Some(target) => {
+                        if let Some(name) = self.tracked(&assign.right) {
+                            self.facts.get_mut(&name).unwrap().copies.push(target);
+                        }
+                    }
+                    None => self.store(&assign.right, place),
+                }
+            }
+            syn::Expr::Binary(binary)
+                if matches!(binary.op, syn::BinOp::Eq(_) | syn::BinOp::Ne(_)) =>
+            {
+                visit::visit_expr(self, expr);
+                for (operand, other) in
+                    [(&binary.left, &binary.right), (&binary.right, &binary.left)]
+                {
+                    if is_null_pointer(other) {
+                        if let Some(name) = self.tracked(operand) {
+                            self.facts.get_mut(&name).unwrap().nullable = true;
+                        }
+                    }
+                }
+            }
+            syn::Expr::MethodCall(call) => {
+                visit::visit_expr(self, expr);
+                if let Some(name) = path_expr_name(strip_parens(&call.receiver)) {
+                    if let Some(facts) = self.facts.get_mut(&name) {
+                        match call.method.to_string().as_str() {
+                            "is_null" => facts.nullable = true,
+                            "add" | "sub" | "offset" | "wrapping_add" | "wrapping_sub"
+                            | "wrapping_offset" | "offset_from" => facts.array = true,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            syn::Expr::Call(call) => {
+                visit::visit_expr(self, expr);
+                let callee = normalize_token_string(&call.func.to_token_stream().to_string());
+                let releaser = POINTER_RELEASERS
+                    .iter()
+                    .find(|name| is_libc_call(&call.func, name))
+                    .map(|name| name.to_string())
+                    .or_else(|| path_ends_with(&call.func, &["from_raw"]).then(|| callee.clone()));
+                if let Some(releaser) = releaser {
+                    if let Some(name) = call.args.first().and_then(|arg| self.tracked(arg)) {
+                        self.facts.get_mut(&name).unwrap().freed.push(releaser);
+                    }
+                }
+                let mut seen = HashSet::new();
+                for arg in call.args.iter() {
+                    if let Some(name) = self.tracked(arg) {
+                        if !seen.insert(name.clone()) {
+                            self.facts
+                                .get_mut(&name)
+                                .unwrap()
+                                .passed_twice
+                                .push(callee.clone());
+                        }
+                    }
+                }
+            }
+            syn::Expr::Struct(expr_struct) => {
+                visit::visit_expr(self, expr);
+                let ty = normalize_token_string(&expr_struct.path.to_token_stream().to_string());
+                for field in expr_struct.fields.iter() {
+                    let member =
+                        normalize_token_string(&field.member.to_token_stream().to_string());
+                    self.store(&field.expr, format!("{}.{}", ty, member));
+                }
+            }
+            syn::Expr::Return(ret) => {
+                visit::visit_expr(self, expr);
+                if let Some(value) = &ret.expr {
+                    self.mark_returned(value);
+                }
+            }
+            _ => visit::visit_expr(self, expr),
+        }
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let Some(init) = &local.init else {
+            visit::visit_local(self, local);
+            return;
+        };
+        self.visit_expr(&init.expr);
+        if let Some((_, diverge)) = &init.diverge {
+            self.visit_expr(diverge);
+        }
+        let name = match &local.pat {
+            syn::Pat::Type(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(ident) => Some(ident.ident.to_string()),
+                _ => None,
+            },
+            syn::Pat::Ident(ident) => Some(ident.ident.to_string()),
+            _ => None,
+        };
+        let Some(name) = name else {
+            return;
+        };
+        if let Some(source) = self.tracked(&init.expr) {
+            self.facts
+                .get_mut(&source)
+                .unwrap()
+                .copies
+                .push(name.clone());
+        }
+        if let Some(ty) = local_pointer_type(local) {
+            let facts = PointerFacts {
+                allocated: pointer_allocator(&init.expr),
+                ..Default::default()
+            };
+            self.facts.insert(name.clone(), facts);
+            self.pointers.push((name, ty, "local"));
+        }
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// The idiomatic type for a pointer to `pointee` with the given ownership, or
+/// `None` for `void` and nested pointers, whose pointee is unknown.
+fn suggested_pointer_type(
+    pointee: &syn::Type,
+    ownership: &str,
+    mutable: bool,
+    array: bool,
+    nullable: bool,
+) -> Option<String> {
+    let syn::Type::Path(path) = pointee else {
+        return None;
+    };
+    let name = path.path.segments.last()?.ident.to_string();
+    if name == "c_void" {
+        return None;
+    }
+    let suggested = if name == "c_char" {
+        match (ownership, mutable) {
+            ("owning", _) => "String".to_string(),
+            ("aliased", _) => "Rc<RefCell<String>>".to_string(),
+            (_, true) => "&mut [u8]".to_string(),
+            (_, false) => "&str".to_string(),
+        }
+    } else {
+        let elem = map_libc_scalar(&name).map_or_else(
+            || normalize_token_string(&pointee.to_token_stream().to_string()),
+            str::to_string,
+        );
+        let owned = if array {
+            format!("Vec<{}>", elem)
+        } else {
+            format!("Box<{}>", elem)
+        };
+        match (ownership, mutable, array) {
+            ("owning", _, _) => owned,
+            ("aliased", _, false) => format!("Rc<RefCell<{}>>", elem),
+            ("aliased", _, true) => format!("Rc<RefCell<Vec<{}>>>", elem),
+            (_, true, true) => format!("&mut [{}]", elem),
+            (_, false, true) => format!("&[{}]", elem),
+            (_, true, false) => format!("&mut {}", elem),
+            (_, false, false) => format!("&{}", elem),
+        }
+    };
+    Some(if nullable {
+        format!("Option<{}>", suggested)
+    } else {
+        suggested
+    })
+}
+
+/// Classifies the raw pointer parameters and locals of a function by the
+/// ownership of their pointee: `owning` when the function allocates or frees
+/// it, or moves the pointer into a data structure it no longer uses
+/// afterwards; `aliased` when the pointer is stored somewhere and still used,
+/// or passed twice to one call; `borrowed` otherwise. Each entry has `name`,
+/// `type`, `kind` (`param` or `local`), `ownership`, `mutable`, `array`,
+/// `nullable`, `evidence` and the idiomatic `suggested_type` (`None` for
+/// `void` pointers).
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn analyze_pointer_ownership(
+    py: Python<'_>,
+    code: &str,
+    function_name: &str,
+) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let Some(f) = ast.items.iter().find_map(|item| match item {
+        syn::Item::Fn(f) if f.sig.ident == function_name => Some(f),
+        _ => None,
+    }) else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' not found",
+            function_name
+        )));
+    };
+
+    let mut visitor = PointerOwnershipVisitor {
+        facts: HashMap::new(),
+        pointers: Vec::new(),
+        last_use: HashMap::new(),
+        position: 0,
+    };
+    for input in f.sig.inputs.iter() {
+        let syn::FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        let (syn::Pat::Ident(pat_ident), syn::Type::Ptr(_)) = (&*pat_type.pat, &*pat_type.ty)
+        else {
+            continue;
+        };
+        let name = pat_ident.ident.to_string();
+        visitor.facts.insert(name.clone(), PointerFacts::default());
+        visitor
+            .pointers
+            .push((name, (*pat_type.ty).clone(), "param"));
+    }
+    visitor.visit_block(&f.block);
+    if let Some(syn::Stmt::Expr(tail, None)) = f.block.stmts.last() {
+        visitor.mark_returned(tail);
+    }
+
+    // the pointees written through each pointer, parameters and locals alike
+    let mut usage = ParamUsageVisitor {
+        kinds: HashMap::new(),
+        const_pointers: HashSet::new(),
+        access: HashMap::new(),
+        depth: 0,
+    };
+    for (name, ty, _) in visitor.pointers.iter() {
+        usage.kinds.insert(name.clone(), ParamKind::Pointer);
+        usage.access.insert(name.clone(), ParamAccess::default());
+        if matches!(ty, syn::Type::Ptr(ptr) if ptr.const_token.is_some()) {
+            usage.const_pointers.insert(name.clone());
+        }
+    }
+    usage.visit_block(&f.block);
+
+    let result = PyList::empty(py);
+    for (name, ty, kind) in visitor.pointers.iter() {
+        let facts = &visitor.facts[name];
+        let last_use = visitor.last_use.get(name).copied().unwrap_or(0);
+        let mut evidence = Vec::new();
+        let mut aliased = false;
+        if let Some(allocator) = &facts.allocated {
+            evidence.push(format!("allocated with `{}`", allocator));
+        }
+        for releaser in facts.freed.iter() {
+            evidence.push(format!("released with `{}`", releaser));
+        }
+        for (place, position) in facts.stores.iter() {
+            if last_use > *position {
+                aliased = true;
+                evidence.push(format!("stored in `{}` and used afterwards", place));
+            } else {
+                evidence.push(format!("moved into `{}`", place));
+            }
+        }
+        for callee in facts.passed_twice.iter() {
+            aliased = true;
+            evidence.push(format!("passed twice to `{}`", callee));
+        }
+        for copy in facts.copies.iter() {
+            evidence.push(format!("copied to `{}`", copy));
+        }
+        if facts.returned {
+            evidence.push("returned".to_string());
+        }
+        let owning =
+            facts.allocated.is_some() || !facts.freed.is_empty() || !facts.stores.is_empty();
+        let ownership = if aliased {
+            "aliased"
+        } else if owning {
+            "owning"
+        } else {
+            "borrowed"
+        };
+        let mutable = usage.access[name].written;
+        let syn::Type::Ptr(ptr) = ty else {
+            continue;
+        };
+        let suggested =
+            suggested_pointer_type(&ptr.elem, ownership, mutable, facts.array, facts.nullable);
+
+        let entry = PyDict::new(py);
+        entry.set_item("name", name)?;
+        entry.set_item("type", analyze_type(ty).raw)?;
+        entry.set_item("kind", *kind)?;
+        entry.set_item("ownership", ownership)?;
+        entry.set_item("mutable", mutable)?;
+        entry.set_item("array", facts.array)?;
+        entry.set_item("nullable", facts.nullable)?;
+        entry.set_item("evidence", evidence)?;
+        entry.set_item("suggested_type", suggested)?;
+        result.append(entry)?;
+    }
+    Ok(result.into())
+}
+
+/// Peels casts, parentheses and `.cast()` calls off an expression and returns
+/// the variable underneath, e.g. `len as usize` or `(buf as *mut u8)`.
+fn peeled_var_name(expr: &syn::Expr) -> Option<String> {
+    match strip_parens(expr) {
+        syn::Expr::Cast(cast) => peeled_var_name(&cast.expr),
+        syn::Expr::MethodCall(call)
+            if call.args.is_empty()
+                && matches!(
+                    call.method.to_string().as_str(),
+                    "cast" | "cast_mut" | "cast_const" | "try_into" | "unwrap"
+                ) =>
+        {
+            peeled_var_name(&call.receiver)
+        }
+        other => path_expr_name(other),
+    }
+}
+
+/// Collects `(pointer, length)` parameter pairs together with the evidence
+/// that ties them together.
+struct SliceParamVisitor {
+    pointers: HashSet<String>,
+    lengths: HashSet<String>,
+    /// Loop variables currently bounded by a length parameter.
+    bounds: Vec<(String, String)>,
+    /// Slices built from a pointer parameter, mapped to that parameter.
+    slice_aliases: HashMap<String, String>,
+    pairs: Vec<(String, String, Vec<String>)>,
+}
+
+impl SliceParamVisitor {
+    fn add_pair(&mut self, pointer: String, length: String, evidence: &str) {
+        if let Some((_, _, kinds)) = self
+            .pairs
+            .iter_mut()
+            .find(|(p, l, _)| *p == pointer && *l == length)
+        {
+            if !kinds.iter().any(|k| k == evidence) {
+                kinds.push(evidence.to_string());
+            }
+            return;
+        }
+        self.pairs
+            .push((pointer, length, vec![evidence.to_string()]));
+    }
+
+    /// Extracts `(index, length)` from loop conditions such as `i < n`,
+    /// `(i as usize) < n as usize` or `n > i`.
+    fn loop_bound(&self, cond: &syn::Expr) -> Option<(String, String)> {
+        let syn::Expr::Binary(binary) = strip_parens(cond) else {
+            return None;
+        };
+        let (index, bound) = match binary.op {
+            syn::BinOp::Lt(_) | syn::BinOp::Le(_) | syn::BinOp::Ne(_) => {
+                (&binary.left, &binary.right)
+            }
+            syn::BinOp::Gt(_) | syn::BinOp::Ge(_) => (&binary.right, &binary.left),
+            syn::BinOp::And(_) => {
+                return self
+                    .loop_bound(&binary.left)
+                    .or_else(|| self.loop_bound(&binary.right));
+            }
+            _ => return None,
+        };
+        let index = peeled_var_name(index)?;
+        let bound = match strip_parens(bound) {
+            // `i <= n - 1`
+            syn::Expr::Binary(inner) if matches!(inner.op, syn::BinOp::Sub(_)) => {
+                peeled_var_name(&inner.left)?
+            }
+            other => peeled_var_name(other)?,
+        };
+        self.lengths.contains(&bound).then_some((index, bound))
+    }
+
+    fn bound_of(&self, index: &syn::Expr) -> Option<String> {
+        let index = peeled_var_name(index)?;
+        self.bounds
+            .iter()
+            .rev()
+            .find(|(var, _)| *var == index)
+            .map(|(_, bound)| bound.clone())
+    }
+}
+
+impl<'ast> Visit<'ast> for SliceParamVisitor {
+    fn visit_expr_while(&mut self, node: &'ast syn::ExprWhile) {
+        self.visit_expr(&node.cond);
+        let bound = self.loop_bound(&node.cond);
+        let pushed = bound.is_some();
+        if let Some(bound) = bound {
+            self.bounds.push(bound);
+        }
+        self.visit_block(&node.body);
+        if pushed {
+            self.bounds.pop();
+        }
+    }
+
+    fn visit_expr_for_loop(&mut self, node: &'ast syn::ExprForLoop) {
+        self.visit_expr(&node.expr);
+        let mut pushed = false;
+        if let (syn::Pat::Ident(var), syn::Expr::Range(range)) =
+            (&*node.pat, strip_parens(&node.expr))
+        {
+            if let Some(end) = range.end.as_deref().and_then(peeled_var_name) {
+                if self.lengths.contains(&end) {
+                    self.bounds.push((var.ident.to_string(), end));
+                    pushed = true;
+                }
+            }
+        }
+        self.visit_block(&node.body);
+        if pushed {
+            self.bounds.pop();
+        }
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+        if (path_ends_with(&node.func, &["from_raw_parts"])
+            || path_ends_with(&node.func, &["from_raw_parts_mut"]))
+            && node.args.len() == 2
+        {
+            let pointer = peeled_var_name(&node.args[0]);
+            let length = peeled_var_name(&node.args[1]);
+            if let (Some(pointer), Some(length)) = (pointer, length) {
+                if self.pointers.contains(&pointer) && self.lengths.contains(&length) {
+                    self.add_pair(pointer, length, "from_raw_parts");
+                }
+            }
+        }
+        visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        let method = node.method.to_string();
+        if matches!(
+            method.as_str(),
+            "add" | "offset" | "wrapping_add" | "wrapping_offset"
+        ) && node.args.len() == 1
+        {
+            if let Some(pointer) = peeled_var_name(&node.receiver) {
+                if self.pointers.contains(&pointer) {
+                    if let Some(length) = self.bound_of(&node.args[0]) {
+                        self.add_pair(pointer, length, "offset_loop");
+                    }
+                }
+            }
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_local(&mut self, node: &'ast syn::Local) {
+        if let (syn::Pat::Ident(var), Some(init)) = (&node.pat, &node.init) {
+            if let syn::Expr::Call(call) = strip_parens(&init.expr) {
+                if path_ends_with(&call.func, &["from_raw_parts"])
+                    || path_ends_with(&call.func, &["from_raw_parts_mut"])
+                {
+                    if let Some(pointer) = call.args.first().and_then(peeled_var_name) {
+                        if self.pointers.contains(&pointer) {
+                            self.slice_aliases.insert(var.ident.to_string(), pointer);
+                        }
+                    }
+                }
+            }
+        }
+        visit::visit_local(self, node);
+    }
+
+    fn visit_expr_index(&mut self, node: &'ast syn::ExprIndex) {
+        if let Some(base) = peeled_var_name(&node.expr) {
+            if let Some(pointer) = self.slice_aliases.get(&base).cloned() {
+                if let Some(length) = self.bound_of(&node.index) {
+                    self.add_pair(pointer, length, "index_loop");
+                }
+            }
+        }
+        visit::visit_expr_index(self, node);
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Reports `(pointer, length)` parameter pairs of a function that are used
+/// together via `from_raw_parts`, `.add(i)`/`.offset(i)` inside a loop bounded
+/// by the length, or indexing of a slice built from the pointer bounded by the length.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn infer_slice_params(py: Python<'_>, code: &str, function_name: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+
+    for item in ast.items.iter() {
+        let syn::Item::Fn(f) = item else {
+            continue;
+        };
+        if f.sig.ident != function_name {
+            continue;
+        }
+
+        let mut visitor = SliceParamVisitor {
+            pointers: HashSet::new(),
+            lengths: HashSet::new(),
+            bounds: Vec::new(),
+            slice_aliases: HashMap::new(),
+            pairs: Vec::new(),
+        };
+        let mut mutable_pointers = HashSet::new();
+        for input in f.sig.inputs.iter() {
+            let syn::FnArg::Typed(pat_type) = input else {
+                continue;
+            };
+            let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+                continue;
+            };
+            let name = pat_ident.ident.to_string();
+            match &*pat_type.ty {
+                syn::Type::Ptr(ptr) => {
+                    if ptr.mutability.is_some() {
+                        mutable_pointers.insert(name.clone());
+                    }
+                    visitor.pointers.insert(name);
+                }
+                syn::Type::Path(_) => {
+                    visitor.lengths.insert(name);
+                }
+                _ => {}
+            }
+        }
+        visitor.visit_block(&f.block);
+
+        let result = PyList::empty(py);
+        for (pointer, length, evidence) in visitor.pairs {
+            let entry = PyDict::new(py);
+            entry.set_item("mutable", mutable_pointers.contains(&pointer))?;
+            entry.set_item("pointer", pointer)?;
+            entry.set_item("length", length)?;
+            entry.set_item("evidence", evidence)?;
+            result.append(entry)?;
+        }
+        return Ok(result.into());
+    }
+
+    Err(pyo3::exceptions::PyValueError::new_err(format!(
+        "Function '{}' not found",
+        function_name
+    )))
+}
+
+/// How the expression around a field read uses the value.
+#[derive(Clone)]
+enum FieldUse {
+    Other,
+    /// Bounds a pointer field (`None` when it is not known which), with how.
+    Bound(Option<String>, &'static str),
+    /// Computes the new value of the field, as in `(*s).len = (*s).len + 1`.
+    Update(String),
+}
+
+/// Records which functions read and write the fields of one struct, and the
+/// length fields that are only read to bound a pointer field.
+struct FieldUsageVisitor<'a> {
+    target: &'a str,
+    /// Named fields of every struct in the code.
+    structs: &'a HashMap<String, Vec<(String, syn::Type)>>,
+    pointer_fields: HashSet<String>,
+    length_fields: HashSet<String>,
+    function: String,
+    /// Struct each variable of the current function points to or holds.
+    var_structs: HashMap<String, String>,
+    context: Vec<FieldUse>,
+    /// Length fields read as bounds, in order, to find loop bounds.
+    bound_reads: Vec<String>,
+    /// Length fields bounding the loops being visited.
+    loop_bounds: Vec<String>,
+    read_by: HashMap<String, Vec<String>>,
+    written_by: HashMap<String, Vec<String>>,
+    /// Length fields also read for something other than a bound.
+    other_reads: HashSet<String>,
+    pairs: Vec<(String, String, Vec<String>)>,
+}
+
+/// The struct a type refers to through pointers, references and arrays.
+fn struct_of_type(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Ptr(p) => struct_of_type(&p.elem),
+        syn::Type::Reference(r) => struct_of_type(&r.elem),
+        syn::Type::Array(a) => struct_of_type(&a.elem),
+        syn::Type::Slice(s) => struct_of_type(&s.elem),
+        syn::Type::Paren(p) => struct_of_type(&p.elem),
+        other => type_last_ident(other),
+    }
+}
+
+impl FieldUsageVisitor<'_> {
+    /// The struct an expression evaluates to or points to.
+    fn expr_struct(&self, expr: &syn::Expr) -> Option<String> {
+        match strip_parens(expr) {
+            syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Deref(_)) => {
+                self.expr_struct(&unary.expr)
+            }
+            syn::Expr::Reference(reference) => self.expr_struct(&reference.expr),
+            syn::Expr::Cast(cast) => struct_of_type(&cast.ty)
+                .filter(|name| self.structs.contains_key(name))
+                .or_else(|| self.expr_struct(&cast.expr)),
+            syn::Expr::MethodCall(call)
+                if POINTER_ARITH_METHODS.contains(&call.method.to_string().as_str()) =>
+            {
+                self.expr_struct(&call.receiver)
+            }
+            syn::Expr::Index(index) => self.expr_struct(&index.expr),
+            syn::Expr::Field(field) => {
+                let syn::Member::Named(member) = &field.member else {
+                    return None;
+                };
+                let owner = self.expr_struct(&field.base)?;
+                let (_, ty) = self
+                    .structs
+                    .get(&owner)?
+                    .iter()
+                    .find(|(name, _)| member == name)?;
+                struct_of_type(ty).filter(|name| self.structs.contains_key(name))
+            }
+            other => path_expr_name(other).and_then(|name| self.var_structs.get(&name).cloned()),
+        }
+    }
+
+    /// The field of the analyzed struct an expression accesses.
+    fn target_field(&self, expr: &syn::Expr) -> Option<String> {
+        let syn::Expr::Field(field) = strip_parens(expr) else {
+            return None;
+        };
+        let syn::Member::Named(member) = &field.member else {
+            return None;
+        };
+        (self.expr_struct(&field.base).as_deref() == Some(self.target)).then(|| member.to_string())
+    }
+
+    /// The pointer field of the analyzed struct an expression reads, looking
+    /// past casts and `.cast()`.
+    fn pointer_field(&self, expr: &syn::Expr) -> Option<String> {
+        let expr = match strip_parens(expr) {
+            syn::Expr::Cast(cast) => &cast.expr,
+            syn::Expr::MethodCall(call)
+                if matches!(
+                    call.method.to_string().as_str(),
+                    "cast" | "cast_mut" | "cast_const"
+                ) =>
+            {
+                &call.receiver
+            }
+            other => other,
+        };
+        self.target_field(expr)
+            .filter(|name| self.pointer_fields.contains(name))
+    }
+
+    fn visit_in(&mut self, expr: &syn::Expr, context: FieldUse) {
+        self.context.push(context);
+        self.visit_expr(expr);
+        self.context.pop();
+    }
+
+    fn note(list: &mut HashMap<String, Vec<String>>, field: &str, function: &str) {
+        let functions = list.entry(field.to_string()).or_default();
+        if !functions.iter().any(|f| f == function) {
+            functions.push(function.to_string());
+        }
+    }
+
+    fn pair(&mut self, length: &str, pointer: &str, evidence: String) {
+        match self
+            .pairs
+            .iter_mut()
+            .find(|(l, p, _)| l == length && p == pointer)
+        {
+            Some((_, _, found)) => {
+                if !found.contains(&evidence) {
+                    found.push(evidence);
+                }
+            }
+            None => self
+                .pairs
+                .push((length.to_string(), pointer.to_string(), vec![evidence])),
+        }
+    }
+
+    fn read_field(&mut self, field: &str) {
+        Self::note(&mut self.read_by, field, &self.function);
+        if self.pointer_fields.contains(field) {
+            for length in self.loop_bounds.clone() {
+                let evidence = format!("bounds a loop over `{}` in `{}`", field, self.function);
+                self.pair(&length, field, evidence);
+            }
+            return;
+        }
+        if !self.length_fields.contains(field) {
+            return;
+        }
+        match self.context.last().cloned().unwrap_or(FieldUse::Other) {
+            FieldUse::Bound(pointer, how) => {
+                self.bound_reads.push(field.to_string());
+                if let Some(pointer) = pointer {
+                    let evidence = format!("{} `{}` in `{}`", how, pointer, self.function);
+                    self.pair(field, &pointer, evidence);
+                }
+            }
+            FieldUse::Update(updated) if updated == field => {}
+            _ => {
+                self.other_reads.insert(field.to_string());
+            }
+        }
+    }
+
+    /// Visits a loop body with the length fields read in its condition as bounds.
+    fn visit_bounded_body(&mut self, first_bound: usize, body: &syn::Block) {
+        let bounds: Vec<String> = self.bound_reads.drain(first_bound..).collect();
+        let pushed = bounds.len();
+        self.loop_bounds.extend(bounds);
+        self.visit_block(body);
+        self.loop_bounds.truncate(self.loop_bounds.len() - pushed);
+    }
+}
+
+impl<'ast> Visit<'ast> for FieldUsageVisitor<'_> {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        match expr {
+            syn::Expr::Field(field) => {
+                if let Some(name) = self.target_field(expr) {
+                    self.read_field(&name);
+                }
+                self.visit_in(&field.base, FieldUse::Other);
+            }
+            syn::Expr::Assign(assign) => {
+                let target = self.target_field(&assign.left);
+                let context = match &target {
+                    Some(name) if self.pointer_fields.contains(name) => {
+                        if pointer_allocator(&assign.right).is_some() {
+                            FieldUse::Bound(Some(name.clone()), "sizes the allocation of")
+                        } else {
+                            FieldUse::Other
+                        }
+                    }
+                    Some(name) => FieldUse::Update(name.clone()),
+                    None => FieldUse::Other,
+                };
+                self.visit_in(&assign.right, context);
+                match (&target, strip_parens(&assign.left)) {
+                    (Some(name), syn::Expr::Field(field)) => {
+                        Self::note(&mut self.written_by, name, &self.function);
+                        self.visit_in(&field.base, FieldUse::Other);
+                    }
+                    _ => self.visit_in(&assign.left, FieldUse::Other),
+                }
+            }
+            syn::Expr::Binary(binary) if is_compound_assign(&binary.op) => {
+                match (self.target_field(&binary.left), strip_parens(&binary.left)) {
+                    (Some(name), syn::Expr::Field(field)) => {
+                        // an update in place reads the field only to write it back
+                        Self::note(&mut self.read_by, &name, &self.function);
+                        Self::note(&mut self.written_by, &name, &self.function);
+                        self.visit_in(&binary.right, FieldUse::Update(name));
+                        self.visit_in(&field.base, FieldUse::Other);
+                    }
+                    _ => {
+                        self.visit_in(&binary.right, FieldUse::Other);
+                        self.visit_in(&binary.left, FieldUse::Other);
+                    }
+                }
+            }
+            syn::Expr::Binary(binary)
+                if matches!(
+                    binary.op,
+                    syn::BinOp::Lt(_)
+                        | syn::BinOp::Le(_)
+                        | syn::BinOp::Gt(_)
+                        | syn::BinOp::Ge(_)
+                        | syn::BinOp::Eq(_)
+                        | syn::BinOp::Ne(_)
+                ) =>
+            {
+                self.visit_in(&binary.left, FieldUse::Bound(None, "bounds"));
+                self.visit_in(&binary.right, FieldUse::Bound(None, "bounds"));
+            }
+            syn::Expr::Range(range) => {
+                for bound in [&range.start, &range.end].into_iter().flatten() {
+                    self.visit_in(bound, FieldUse::Bound(None, "bounds"));
+                }
+            }
+            syn::Expr::While(expr_while) => {
+                let first_bound = self.bound_reads.len();
+                self.visit_in(&expr_while.cond, FieldUse::Other);
+                self.visit_bounded_body(first_bound, &expr_while.body);
+            }
+            syn::Expr::ForLoop(expr_for) => {
+                let first_bound = self.bound_reads.len();
+                self.visit_in(&expr_for.expr, FieldUse::Other);
+                self.visit_bounded_body(first_bound, &expr_for.body);
+            }
+            syn::Expr::Call(call) => {
+                let allocator = pointer_allocator(expr).is_some();
+                let slice = path_ends_with(&call.func, &["from_raw_parts"])
+                    || path_ends_with(&call.func, &["from_raw_parts_mut"]);
+                let realloc = is_libc_call(&call.func, "realloc");
+                let pointer = call.args.first().and_then(|arg| self.pointer_field(arg));
+                for (index, arg) in call.args.iter().enumerate() {
+                    let context = if index == 0 && (slice || realloc) {
+                        FieldUse::Other
+                    } else if slice {
+                        FieldUse::Bound(pointer.clone(), "is the slice length of")
+                    } else if realloc {
+                        FieldUse::Bound(pointer.clone(), "sizes the allocation of")
+                    } else if allocator {
+                        match self.context.last() {
+                            Some(FieldUse::Bound(Some(_), _)) => {
+                                self.context.last().cloned().unwrap()
+                            }
+                            _ => FieldUse::Bound(None, "sizes the allocation of"),
+                        }
+                    } else {
+                        FieldUse::Other
+                    };
+                    self.visit_in(arg, context);
+                }
+                self.visit_in(&call.func, FieldUse::Other);
+            }
+            syn::Expr::MethodCall(call) => {
+                let method = call.method.to_string();
+                let pointer = if POINTER_ARITH_METHODS.contains(&method.as_str()) {
+                    self.pointer_field(&call.receiver)
+                } else {
+                    None
+                };
+                let context = match pointer {
+                    Some(pointer) => FieldUse::Bound(Some(pointer), "indexes"),
+                    None => FieldUse::Other,
+                };
+                self.visit_in(&call.receiver, FieldUse::Other);
+                for arg in call.args.iter() {
+                    self.visit_in(arg, context.clone());
+                }
+            }
+            syn::Expr::Index(index) => {
+                let context = match self.target_field(&index.expr) {
+                    Some(array) => FieldUse::Bound(Some(array), "indexes"),
+                    None => FieldUse::Other,
+                };
+                self.visit_in(&index.expr, FieldUse::Other);
+                self.visit_in(&index.index, context);
+            }
+            syn::Expr::Struct(expr_struct)
+                if expr_struct
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == self.target) =>
+            {
+                for field in expr_struct.fields.iter() {
+                    if let syn::Member::Named(member) = &field.member {
+                        Self::note(&mut self.written_by, &member.to_string(), &self.function);
+                    }
+                    self.visit_in(&field.expr, FieldUse::Other);
+                }
+                if let Some(rest) = &expr_struct.rest {
+                    self.visit_in(rest, FieldUse::Other);
+                }
+            }
+            syn::Expr::Paren(_) | syn::Expr::Group(_) | syn::Expr::Cast(_) => {
+                visit::visit_expr(self, expr)
+            }
+            syn::Expr::Binary(_) | syn::Expr::Unary(_) => visit::visit_expr(self, expr),
+            syn::Expr::Path(_) | syn::Expr::Lit(_) => {}
+            _ => {
+                self.context.push(FieldUse::Other);
+                visit::visit_expr(self, expr);
+                self.context.pop();
+            }
+        }
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let Some(init) = &local.init {
+            self.visit_in(&init.expr, FieldUse::Other);
+            if let Some((_, diverge)) = &init.diverge {
+                self.visit_in(diverge, FieldUse::Other);
+            }
+        }
+        let (name, ty) = match &local.pat {
+            syn::Pat::Type(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(ident) => (ident.ident.to_string(), Some(&*pat_type.ty)),
+                _ => return,
+            },
+            syn::Pat::Ident(ident) => (ident.ident.to_string(), None),
+            _ => return,
+        };
+        let found = match ty {
+            Some(ty) => struct_of_type(ty).filter(|name| self.structs.contains_key(name)),
+            None => local
+                .init
+                .as_ref()
+                .and_then(|init| self.expr_struct(&init.expr)),
+        };
+        match found {
+            Some(found) => {
+                self.var_structs.insert(name, found);
+            }
+            None => {
+                self.var_structs.remove(&name);
+            }
+        }
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_in(arg, FieldUse::Other);
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+/// Reports how the functions in `code` use the fields of struct `struct_name`
+/// (defined in `code`): `fields` lists each field with its `type` and the
+/// functions it is `read_by` and `written_by`; `length_fields` lists the
+/// integer fields only ever read to bound a pointer or array field (loop
+/// bounds, allocation sizes, indices, slice lengths), each with the `field`,
+/// the `pointer` it bounds, the pointer's `element_type` and the `evidence`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn analyze_struct_field_usage(py: Python<'_>, code: &str, struct_name: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let mut structs = HashMap::new();
+    for item in ast.items.iter() {
+        if let syn::Item::Struct(s) = item {
+            let fields: Vec<(String, syn::Type)> = s
+                .fields
+                .iter()
+                .filter_map(|f| Some((f.ident.as_ref()?.to_string(), f.ty.clone())))
+                .collect();
+            structs.insert(s.ident.to_string(), fields);
+        }
+    }
+    let Some(fields) = structs.get(struct_name) else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Struct '{}' not found",
+            struct_name
+        )));
+    };
+
+    let mut visitor = FieldUsageVisitor {
+        target: struct_name,
+        structs: &structs,
+        pointer_fields: fields
+            .iter()
+            .filter(|(_, ty)| matches!(ty, syn::Type::Ptr(_) | syn::Type::Array(_)))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        length_fields: fields
+            .iter()
+            .filter(|(_, ty)| is_integer_type(ty))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        function: String::new(),
+        var_structs: HashMap::new(),
+        context: Vec::new(),
+        bound_reads: Vec::new(),
+        loop_bounds: Vec::new(),
+        read_by: HashMap::new(),
+        written_by: HashMap::new(),
+        other_reads: HashSet::new(),
+        pairs: Vec::new(),
+    };
+    for item in ast.items.iter() {
+        let syn::Item::Fn(f) = item else {
+            continue;
+        };
+        visitor.function = f.sig.ident.to_string();
+        visitor.var_structs.clear();
+        for input in f.sig.inputs.iter() {
+            let syn::FnArg::Typed(pat_type) = input else {
+                continue;
+            };
+            let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+                continue;
+            };
+            if let Some(found) =
+                struct_of_type(&pat_type.ty).filter(|name| structs.contains_key(name))
+            {
+                visitor
+                    .var_structs
+                    .insert(pat_ident.ident.to_string(), found);
+            }
+        }
+        visitor.visit_block(&f.block);
+    }
+
+    let result = PyDict::new(py);
+    let field_list = PyList::empty(py);
+    for (name, ty) in fields.iter() {
+        let entry = PyDict::new(py);
+        entry.set_item("name", name)?;
+        entry.set_item("type", analyze_type(ty).normalized)?;
+        entry.set_item("read_by", visitor.read_by.remove(name).unwrap_or_default())?;
+        entry.set_item(
+            "written_by",
+            visitor.written_by.remove(name).unwrap_or_default(),
+        )?;
+        field_list.append(entry)?;
+    }
+    result.set_item("fields", field_list)?;
+
+    let length_list = PyList::empty(py);
+    for (length, pointer, evidence) in visitor.pairs {
+        if visitor.other_reads.contains(&length) {
+            continue;
+        }
+        let element = fields
+            .iter()
+            .find(|(name, _)| *name == pointer)
+            .and_then(|(_, ty)| match ty {
+                syn::Type::Ptr(ptr) => Some(&*ptr.elem),
+                syn::Type::Array(array) => Some(&*array.elem),
+                _ => None,
+            })
+            .map(|elem| {
+                type_last_ident(elem)
+                    .and_then(|name| map_libc_scalar(&name))
+                    .map_or_else(
+                        || normalize_token_string(&elem.to_token_stream().to_string()),
+                        str::to_string,
+                    )
+            });
+        let entry = PyDict::new(py);
+        entry.set_item("field", length)?;
+        entry.set_item("pointer", pointer)?;
+        entry.set_item("element_type", element)?;
+        entry.set_item("evidence", evidence)?;
+        length_list.append(entry)?;
+    }
+    result.set_item("length_fields", length_list)?;
+    Ok(result.into())
+}
+
+/// Reads an integer literal such as `0`, `-1` or `-1 as libc::c_int`.
+fn int_literal_value(expr: &syn::Expr) -> Option<i64> {
+    match strip_parens(expr) {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(lit),
+            ..
+        }) => lit.base10_parse::<i64>().ok(),
+        syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Neg(_)) => {
+            int_literal_value(&unary.expr).map(|v| -v)
+        }
+        syn::Expr::Cast(cast) => int_literal_value(&cast.expr),
+        _ => None,
+    }
+}
+
+fn is_int_return_type(output: &syn::ReturnType) -> bool {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return false;
+    };
+    let syn::Type::Path(type_path) = &**ty else {
+        return false;
+    };
+    let Some(last) = type_path.path.segments.last() else {
+        return false;
+    };
+    let name = last.ident.to_string();
+    let primitive = map_libc_scalar(&name)
+        .or_else(|| expected_stdint_target(&name))
+        .unwrap_or(name.as_str());
+    matches!(primitive, "i8" | "i16" | "i32" | "i64" | "isize")
+}
+
+/// Collects every value a function returns, or `None` when some return value
+/// is not an integer literal.
+struct ReturnLiteralCollector {
+    values: Vec<i64>,
+    all_literal: bool,
+}
+
+impl<'ast> Visit<'ast> for ReturnLiteralCollector {
+    fn visit_expr_return(&mut self, node: &'ast syn::ExprReturn) {
+        match node.expr.as_deref().and_then(int_literal_value) {
+            Some(value) => self.values.push(value),
+            None => self.all_literal = false,
+        }
+    }
+
+    fn visit_expr_closure(&mut self, _node: &'ast syn::ExprClosure) {}
+
+    fn visit_item(&mut self, _node: &'ast syn::Item) {}
+}
+
+fn returned_int_literals(f: &syn::ItemFn) -> Option<Vec<i64>> {
+    let mut collector = ReturnLiteralCollector {
+        values: Vec::new(),
+        all_literal: true,
+    };
+    collector.visit_block(&f.block);
+    // a body ending in a statement must leave through `return`
+    if let Some(syn::Stmt::Expr(expr, None)) = f.block.stmts.last() {
+        collector.values.push(int_literal_value(expr)?);
+    }
+    (collector.all_literal && !collector.values.is_empty()).then_some(collector.values)
+}
+
+fn is_call_to(expr: &syn::Expr, function_name: &str) -> bool {
+    let syn::Expr::Call(call) = strip_parens(expr) else {
+        return false;
+    };
+    let syn::Expr::Path(p) = &*call.func else {
+        return false;
+    };
+    p.path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == function_name)
+}
+
+/// Evaluates `value <op> literal` for the comparison operators.
+fn compare_int(op: &syn::BinOp, value: i64, literal: i64) -> Option<bool> {
+    Some(match op {
+        syn::BinOp::Eq(_) => value == literal,
+        syn::BinOp::Ne(_) => value != literal,
+        syn::BinOp::Lt(_) => value < literal,
+        syn::BinOp::Le(_) => value <= literal,
+        syn::BinOp::Gt(_) => value > literal,
+        syn::BinOp::Ge(_) => value >= literal,
+        _ => return None,
+    })
+}
+
+/// For a comparison between a call to the function and a literal, tells
+/// whether the comparison is true exactly on failure (`Some(true)`) or
+/// exactly on success (`Some(false)`).
+fn classify_status_check(
+    binary: &syn::ExprBinary,
+    function_name: &str,
+    failure_values: &[i64],
+) -> Option<bool> {
+    let (literal, flipped) = if is_call_to(&binary.left, function_name) {
+        (int_literal_value(&binary.right)?, false)
+    } else if is_call_to(&binary.right, function_name) {
+        (int_literal_value(&binary.left)?, true)
+    } else {
+        return None;
+    };
+    let holds = |value: i64| {
+        if flipped {
+            compare_int(&binary.op, literal, value)
+        } else {
+            compare_int(&binary.op, value, literal)
+        }
+    };
+    let on_success = holds(0)?;
+    let on_failure: Vec<bool> = failure_values
+        .iter()
+        .map(|v| holds(*v))
+        .collect::<Option<_>>()?;
+    if !on_success && on_failure.iter().all(|b| *b) {
+        Some(true)
+    } else if on_success && on_failure.iter().all(|b| !*b) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+struct StatusCheckCounter<'a> {
+    function_name: &'a str,
+    failure_values: &'a [i64],
+    calls: usize,
+    checked: usize,
+}
+
+impl<'ast> Visit<'ast> for StatusCheckCounter<'_> {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        if is_call_to(expr, self.function_name) {
+            self.calls += 1;
+        }
+        if let syn::Expr::Binary(binary) = expr {
+            if classify_status_check(binary, self.function_name, self.failure_values).is_some() {
+                self.checked += 1;
+            }
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+            for arg in args.iter() {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
+
+/// Whether a macro in `file` whose input does not parse as expressions names
+/// `function_name`, so that its calls there cannot be rewritten.
+fn unparsed_macro_mentions(file: &syn::File, function_name: &str) -> bool {
+    fn mentions(tokens: proc_macro2::TokenStream, name: &str) -> bool {
+        tokens.into_iter().any(|tree| match tree {
+            proc_macro2::TokenTree::Ident(ident) => ident == name,
+            proc_macro2::TokenTree::Group(group) => mentions(group.stream(), name),
+            _ => false,
+        })
+    }
+    struct Finder<'a> {
+        name: &'a str,
+        found: bool,
+    }
+    impl<'ast> Visit<'ast> for Finder<'_> {
+        fn visit_macro(&mut self, node: &'ast syn::Macro) {
+            let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+            match syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+                Ok(args) => args.iter().for_each(|arg| self.visit_expr(arg)),
+                Err(_) => self.found |= mentions(node.tokens.clone(), self.name),
+            }
+        }
+    }
+    let mut finder = Finder {
+        name: function_name,
+        found: false,
+    };
+    finder.visit_file(file);
+    finder.found
+}
+
+/// Lists functions returning a signed C integer used as a status code: every
+/// return value is an integer literal, `0` among them, with at least one
+/// non-zero failure value. Each entry also reports how many call sites compare
+/// the result against a literal.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn find_error_code_functions(py: Python<'_>, code: &str) -> PyResult<PyObject> {
+    let ast = parse_src(code)?;
+    let result = PyList::empty(py);
+
+    for item in ast.items.iter() {
+        let syn::Item::Fn(f) = item else {
+            continue;
+        };
+        if !is_int_return_type(&f.sig.output) {
+            continue;
+        }
+        let Some(values) = returned_int_literals(f) else {
+            continue;
+        };
+        if !values.contains(&0) {
+            continue;
+        }
+        let mut failure_values: Vec<i64> = values.into_iter().filter(|v| *v != 0).collect();
+        failure_values.sort();
+        failure_values.dedup();
+        if failure_values.is_empty() {
+            continue;
+        }
+
+        let name = f.sig.ident.to_string();
+        let mut counter = StatusCheckCounter {
+            function_name: &name,
+            failure_values: &failure_values,
+            calls: 0,
+            checked: 0,
+        };
+        counter.visit_file(&ast);
+        let (calls, checked) = (counter.calls, counter.checked);
+
+        let entry = PyDict::new(py);
+        entry.set_item("function", name)?;
+        entry.set_item("failure_values", failure_values)?;
+        entry.set_item("call_sites", calls)?;
+        entry.set_item("checked_call_sites", checked)?;
+        result.append(entry)?;
+    }
+
+    Ok(result.into())
+}
+
+/// Turns literal status returns into `Ok(())`/`Err(code)`.
+struct StatusReturnRewriter;
+
+impl StatusReturnRewriter {
+    fn to_result(expr: &syn::Expr) -> syn::Expr {
+        match int_literal_value(expr) {
+            Some(0) => parse_quote!(Ok(())),
+            _ => parse_quote!(Err(#expr)),
+        }
+    }
+}
+
+impl VisitMut for StatusReturnRewriter {
+    fn visit_expr_return_mut(&mut self, node: &mut syn::ExprReturn) {
+        if let Some(expr) = node.expr.as_mut() {
+            **expr = Self::to_result(expr);
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, _node: &mut syn::ExprClosure) {}
+
+    fn visit_item_mut(&mut self, _node: &mut syn::Item) {}
+}
+
+/// Rewrites callers of a converted function so that status checks use
+/// `is_err()`/`is_ok()` and other uses recover the original integer.
+struct StatusCallRewriter<'a> {
+    function_name: &'a str,
+    failure_values: &'a [i64],
+}
+
+impl VisitMut for StatusCallRewriter<'_> {
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Expr(expr, Some(_)) = stmt {
+            if is_call_to(expr, self.function_name) {
+                visit_mut::visit_expr_mut(self, expr);
+                let call = expr.clone();
+                *stmt = parse_quote!(let _ = #call;);
+                return;
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Binary(binary) = expr {
+            if let Some(on_failure) =
+                classify_status_check(binary, self.function_name, self.failure_values)
+            {
+                let mut call = if is_call_to(&binary.left, self.function_name) {
+                    (*binary.left).clone()
+                } else {
+                    (*binary.right).clone()
+                };
+                visit_mut::visit_expr_mut(self, &mut call);
+                *expr = if on_failure {
+                    parse_quote!(#call.is_err())
+                } else {
+                    parse_quote!(#call.is_ok())
+                };
+                return;
+            }
+        }
+        if is_call_to(expr, self.function_name) {
+            visit_mut::visit_expr_mut(self, expr);
+            let call = expr.clone();
+            *expr = parse_quote!(#call.err().unwrap_or(0));
+            return;
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_exprs_mut(mac, &mut |expr| self.visit_expr_mut(expr));
+    }
+}
+
+/// Changes a status-code function (see `find_error_code_functions`) to return
+/// `Result<(), T>` where `T` is its former integer type, and updates callers.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn convert_error_code_to_result(code: &str, function_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    let Some(index) = ast
+        .items
+        .iter()
+        .position(|item| matches!(item, syn::Item::Fn(f) if f.sig.ident == function_name))
+    else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' not found",
+            function_name
+        )));
+    };
+
+    let failure_values = {
+        let syn::Item::Fn(f) = &ast.items[index] else {
+            unreachable!();
+        };
+        if f.sig.abi.is_some() || f.attrs.iter().any(|attr| attr.path().is_ident("no_mangle")) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Function '{}' is exported to C; changing its return type would break the ABI",
+                function_name
+            )));
+        }
+        let values = returned_int_literals(f)
+            .filter(|values| {
+                is_int_return_type(&f.sig.output)
+                    && values.contains(&0)
+                    && values.iter().any(|v| *v != 0)
+            })
+            .ok_or_else(|| {
+                pyo3::exceptions::PyValueError::new_err(format!(
+                    "Function '{}' does not return a status code",
+                    function_name
+                ))
+            })?;
+        let mut failure_values: Vec<i64> = values.into_iter().filter(|v| *v != 0).collect();
+        failure_values.sort();
+        failure_values.dedup();
+        failure_values
+    };
+    if unparsed_macro_mentions(&ast, function_name) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' is called inside a macro whose arguments cannot be rewritten",
+            function_name
+        )));
+    }
+
+    let mut call_rewriter = StatusCallRewriter {
+        function_name,
+        failure_values: &failure_values,
+    };
+    call_rewriter.visit_file_mut(&mut ast);
+
+    let syn::Item::Fn(f) = &mut ast.items[index] else {
+        unreachable!();
+    };
+    if let syn::ReturnType::Type(_, ty) = &mut f.sig.output {
+        let code_ty = ty.clone();
+        **ty = parse_quote!(Result<(), #code_ty>);
+    }
+    StatusReturnRewriter.visit_block_mut(&mut f.block);
+    if let Some(syn::Stmt::Expr(tail, None)) = f.block.stmts.last_mut() {
+        *tail = StatusReturnRewriter::to_result(tail);
+    }
+
+    Ok(unparse(&ast))
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ReceiverKind {
+    Ref,
+    MutRef,
+    Pointer,
+}
+
+fn receiver_kind_for(ty: &syn::Type, struct_name: &str) -> Option<ReceiverKind> {
+    let (elem, kind) = match ty {
+        syn::Type::Reference(r) if r.mutability.is_some() => (&r.elem, ReceiverKind::MutRef),
+        syn::Type::Reference(r) => (&r.elem, ReceiverKind::Ref),
+        syn::Type::Ptr(p) => (&p.elem, ReceiverKind::Pointer),
+        _ => return None,
+    };
+    let syn::Type::Path(type_path) = &**elem else {
+        return None;
+    };
+    let last = type_path.path.segments.last()?;
+    (last.ident == struct_name).then_some(kind)
+}
+
+/// The first use of the pointer `param` in `block` that a reference cannot
+/// stand in for: a method call on it such as `p.is_null()` or `p.offset(1)`,
+/// a comparison, a cast, or an assignment to it.
+fn raw_pointer_use(block: &syn::Block, param: &str) -> Option<String> {
+    struct Finder<'a> {
+        param: &'a str,
+        found: Option<String>,
+    }
+    impl Finder<'_> {
+        fn is_param(&self, expr: &syn::Expr) -> bool {
+            path_expr_name(strip_parens(expr)).is_some_and(|name| name == self.param)
+        }
+    }
+    impl<'ast> Visit<'ast> for Finder<'_> {
+        fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+            if self.found.is_some() {
+                return;
+            }
+            let raw = match expr {
+                syn::Expr::MethodCall(call) => self.is_param(&call.receiver),
+                syn::Expr::Binary(binary) => {
+                    self.is_param(&binary.left) || self.is_param(&binary.right)
+                }
+                syn::Expr::Cast(cast) => self.is_param(&cast.expr),
+                syn::Expr::Assign(assign) => self.is_param(&assign.left),
+                _ => false,
+            };
+            if raw {
+                self.found = Some(normalize_token_string(&expr.to_token_stream().to_string()));
+                return;
+            }
+            visit::visit_expr(self, expr);
+        }
+
+        fn visit_macro(&mut self, node: &'ast syn::Macro) {
+            let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+            if let Ok(args) = syn::parse::Parser::parse2(parser, node.tokens.clone()) {
+                for arg in args.iter() {
+                    self.visit_expr(arg);
+                }
+            }
+        }
+
+        fn visit_item(&mut self, _node: &'ast syn::Item) {}
+    }
+    let mut finder = Finder { param, found: None };
+    finder.visit_block(block);
+    finder.found
+}
+
+/// Replaces the receiver parameter with `self` inside the new method body and
+/// tidies `(*self).field` into `self.field`.
+struct SelfRewriter<'a> {
+    param: &'a str,
+}
+
+impl VisitMut for SelfRewriter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if path_expr_name(expr).is_some_and(|name| name == self.param) {
+            *expr = parse_quote!(self);
+            return;
+        }
+        visit_mut::visit_expr_mut(self, expr);
+        if let syn::Expr::Field(field) = expr {
+            if let syn::Expr::Paren(paren) = &*field.base {
+                if let syn::Expr::Unary(unary) = &*paren.expr {
+                    if matches!(unary.op, syn::UnOp::Deref(_))
+                        && path_expr_name(&unary.expr).is_some_and(|name| name == "self")
+                    {
+                        *field.base = parse_quote!(self);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_exprs_mut(mac, &mut |expr| self.visit_expr_mut(expr));
+    }
+
+    fn visit_item_mut(&mut self, _node: &mut syn::Item) {}
+}
+
+/// Rewrites `function(recv, args...)` into `recv.method(args...)` and bare
+/// references to the function into `Struct::method`.
+struct MethodCallRewriter<'a> {
+    function_name: &'a str,
+    struct_ident: &'a syn::Ident,
+    method_ident: &'a syn::Ident,
+}
+
+impl MethodCallRewriter<'_> {
+    fn names_function(&self, expr: &syn::Expr) -> bool {
+        matches!(expr, syn::Expr::Path(p) if p.qself.is_none() && p.path.is_ident(self.function_name))
+    }
+
+    /// Turns the first argument into a method receiver: `&mut x` and
+    /// `&mut x as *mut S` become `x`, other pointers are dereferenced.
+    fn receiver(expr: &syn::Expr) -> syn::Expr {
+        match strip_parens(expr) {
+            syn::Expr::Reference(reference) => (*reference.expr).clone(),
+            syn::Expr::Cast(cast) => match strip_parens(&cast.expr) {
+                syn::Expr::Reference(reference) => (*reference.expr).clone(),
+                inner => parse_quote!((*#inner)),
+            },
+            syn::Expr::Path(p) => parse_quote!((*#p)),
+            other => parse_quote!((*(#other))),
+        }
+    }
+}
+
+impl VisitMut for MethodCallRewriter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        match expr {
+            syn::Expr::Call(call) if self.names_function(&call.func) && !call.args.is_empty() => {
+                for arg in call.args.iter_mut() {
+                    self.visit_expr_mut(arg);
+                }
+                let mut args: Vec<syn::Expr> = call.args.iter().cloned().collect();
+                let receiver = Self::receiver(&args.remove(0));
+                let method = self.method_ident;
+                *expr = parse_quote!(#receiver.#method(#(#args),*));
+            }
+            syn::Expr::Path(_) if self.names_function(expr) => {
+                let (struct_ident, method) = (self.struct_ident, self.method_ident);
+                *expr = parse_quote!(#struct_ident::#method);
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_exprs_mut(mac, &mut |expr| self.visit_expr_mut(expr));
+    }
+}
+
+/// Moves a free function whose first parameter is `&Struct`, `&mut Struct` or
+/// `*mut Struct` into an inherent `impl Struct` block as a method, dropping a
+/// `struct_` prefix from its name, and rewrites all call sites to method calls.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn convert_to_method(code: &str, function_name: &str, struct_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    let Some(index) = ast
+        .items
+        .iter()
+        .position(|item| matches!(item, syn::Item::Fn(f) if f.sig.ident == function_name))
+    else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' not found",
+            function_name
+        )));
+    };
+    let syn::Item::Fn(mut f) = ast.items.remove(index) else {
+        unreachable!();
+    };
+    if f.sig.abi.is_some() || f.attrs.iter().any(|attr| attr.path().is_ident("no_mangle")) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' is exported to C; turning it into a method would break the ABI",
+            function_name
+        )));
+    }
+
+    let first = match f.sig.inputs.first() {
+        Some(syn::FnArg::Typed(pat_type)) => Some(pat_type.clone()),
+        _ => None,
+    };
+    let receiver = first.as_ref().and_then(|pat_type| {
+        let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+            return None;
+        };
+        let kind = receiver_kind_for(&pat_type.ty, struct_name)?;
+        Some((pat_ident.ident.to_string(), kind))
+    });
+    let Some((param, kind)) = receiver else {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "The first parameter of '{}' is not a reference or pointer to '{}'",
+            function_name, struct_name
+        )));
+    };
+
+    if kind == ReceiverKind::Pointer {
+        if let Some(usage) = raw_pointer_use(&f.block, &param) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "'{}' uses its receiver '{}' as a raw pointer (`{}`), which `&mut self` cannot replace",
+                function_name, param, usage
+            )));
+        }
+    }
+
+    let prefix = format!("{}_", to_snake_case(struct_name));
+    let method_name = function_name
+        .strip_prefix(&prefix)
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(function_name);
+    let method_ident = syn::Ident::new(method_name, Span::call_site());
+    let struct_ident = syn::Ident::new(struct_name, Span::call_site());
+
+    let self_arg: syn::FnArg = match kind {
+        ReceiverKind::Ref => parse_quote!(&self),
+        ReceiverKind::MutRef | ReceiverKind::Pointer => parse_quote!(&mut self),
+    };
+    let rest: Vec<syn::FnArg> = f.sig.inputs.iter().skip(1).cloned().collect();
+    f.sig.inputs = std::iter::once(self_arg).chain(rest).collect();
+    f.sig.ident = method_ident.clone();
+    f.sig.abi = None;
+    f.attrs.retain(|attr| !attr.path().is_ident("no_mangle"));
+    SelfRewriter { param: &param }.visit_block_mut(&mut f.block);
+
+    let method = syn::ImplItem::Fn(syn::ImplItemFn {
+        attrs: f.attrs,
+        vis: f.vis,
+        defaultness: None,
+        sig: f.sig,
+        block: *f.block,
+    });
+
+    let existing_impl = ast.items.iter_mut().find_map(|item| match item {
+        syn::Item::Impl(item_impl) if item_impl.trait_.is_none() => match &*item_impl.self_ty {
+            syn::Type::Path(p) if p.path.is_ident(struct_name) => Some(item_impl),
+            _ => None,
+        },
+        _ => None,
+    });
+    match existing_impl {
+        Some(item_impl) => item_impl.items.push(method),
+        None => {
+            let item_impl: syn::ItemImpl = parse_quote! {
+                impl #struct_ident {
+                    #method
+                }
+            };
+            ast.items.insert(index, syn::Item::Impl(item_impl));
+        }
+    }
+
+    let mut rewriter = MethodCallRewriter {
+        function_name,
+        struct_ident: &struct_ident,
+        method_ident: &method_ident,
+    };
+    rewriter.visit_file_mut(&mut ast);
+
+    Ok(unparse(&ast))
+}
+
+fn parse_json(text: &str, what: &str) -> PyResult<serde_json::Value> {
+    serde_json::from_str(text).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!("Failed to parse {}: {}", what, e))
+    })
+}
+
+/// Converts a JSON test value into a Rust expression. Strings hold Rust source
+/// (so a string literal is written as `"\"abc\""`), numbers and booleans are
+/// emitted as literals.
+fn json_to_rust_expr(value: &serde_json::Value) -> PyResult<syn::Expr> {
+    let source = match value {
+        serde_json::Value::String(source) => source.clone(),
+        serde_json::Value::Number(number) => number.to_string(),
+        serde_json::Value::Bool(flag) => flag.to_string(),
+        other => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Unsupported test value: {}",
+                other
+            )))
+        }
+    };
+    parse_str::<syn::Expr>(&source).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Failed to parse test value '{}': {:?}",
+            source, e
+        ))
+    })
+}
+
+/// Like `json_to_rust_expr`, but writes a JSON number as a literal of `ty`, so
+/// `3` is `3.0` for an `f64`.
+fn json_to_typed_expr(value: &serde_json::Value, ty: Option<&syn::Type>) -> PyResult<syn::Expr> {
+    match ty {
+        Some(ty) if value.is_number() => kani_bound(value, ty),
+        _ => json_to_rust_expr(value),
+    }
+}
+
+fn is_float_type(ty: &syn::Type) -> bool {
+    let syn::Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path.path.segments.last().is_some_and(|segment| {
+        let name = segment.ident.to_string();
+        matches!(
+            map_libc_scalar(&name).unwrap_or(name.as_str()),
+            "f32" | "f64"
+        )
+    })
+}
+
+/// Appends a `#[cfg(test)] mod tests` with one `#[test]` per case to the code.
+/// `cases_json` is a list of objects `{"args": [...], "expected": ..., "name": ...}`;
+/// `expected` and `name` are optional. Cases are added to an existing `tests`
+/// module when there is one.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_test_module(code: &str, function_name: &str, cases_json: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    let sig = ast
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Fn(f) if f.sig.ident == function_name => Some(f.sig.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!(
+                "Function '{}' not found",
+                function_name
+            ))
+        })?;
+    let return_type = match &sig.output {
+        syn::ReturnType::Type(_, ty) => Some(&**ty),
+        syn::ReturnType::Default => None,
+    };
+    let float_result = return_type.is_some_and(is_float_type);
+    let param_types: Vec<Option<&syn::Type>> = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            syn::FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+
+    let cases = parse_json(cases_json, "test cases")?;
+    let cases = cases
+        .as_array()
+        .ok_or_else(|| pyo3::exceptions::PyValueError::new_err("Test cases must be a JSON list"))?;
+
+    let function_ident = &sig.ident;
+    let mut tests: Vec<syn::Item> = Vec::new();
+    for (i, case) in cases.iter().enumerate() {
+        let args = match case.get("args") {
+            Some(serde_json::Value::Array(args)) => args
+                .iter()
+                .enumerate()
+                .map(|(j, arg)| json_to_typed_expr(arg, param_types.get(j).copied().flatten()))
+                .collect::<PyResult<Vec<_>>>()?,
+            None => Vec::new(),
+            Some(_) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Test case {} has non-list 'args'",
+                    i
+                )))
+            }
+        };
+        if args.len() != sig.inputs.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Test case {} passes {} arguments, '{}' takes {}",
+                i,
+                args.len(),
+                function_name,
+                sig.inputs.len()
+            )));
+        }
+        let test_name = match case.get("name").and_then(|n| n.as_str()) {
+            Some(name) => name.to_string(),
+            None => format!("test_{}_{}", function_name, i + 1),
+        };
+        let test_ident = parse_str::<syn::Ident>(&test_name).map_err(|_| {
+            pyo3::exceptions::PyValueError::new_err(format!(
+                "Test case {} is named '{}', which is not a valid Rust identifier",
+                i, test_name
+            ))
+        })?;
+
+        let mut call: syn::Expr = parse_quote!(#function_ident(#(#args),*));
+        if sig.unsafety.is_some() {
+            call = parse_quote!(unsafe { #call });
+        }
+        let body: Vec<syn::Stmt> = match case.get("expected") {
+            None | Some(serde_json::Value::Null) => vec![parse_quote!(#call;)],
+            Some(expected) => {
+                let expected = json_to_typed_expr(expected, return_type)?;
+                if float_result {
+                    parse_quote! {
+                        let result = #call;
+                        assert!((result - #expected).abs() < 1e-6, "{} != {}", result, #expected);
+                    }
+                } else {
+                    parse_quote! {
+                        let result = #call;
+                        assert_eq!(result, #expected);
+                    }
+                }
+            }
+        };
+        tests.push(parse_quote! {
+            #[test]
+            fn #test_ident() {
+                #(#body)*
+            }
+        });
+    }
+
+    let existing = ast.items.iter_mut().find_map(|item| match item {
+        syn::Item::Mod(item_mod) if item_mod.ident == "tests" => item_mod.content.as_mut(),
+        _ => None,
+    });
+    match existing {
+        Some((_, items)) => items.extend(tests),
+        None => ast.items.push(parse_quote! {
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #(#tests)*
+            }
+        }),
+    }
+
+    Ok(unparse(&ast))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PtrKind {
+    Slice,
+    CString,
+    Ref,
+}
+
+/// The `shape.ptr` part of a spec field (see `sactor/verifier/spec/SPEC.md`).
+#[derive(Clone)]
+struct PtrShape {
+    kind: PtrKind,
+    len_from: Option<String>,
+    len_const: Option<usize>,
+    /// Row length of a `T**` slice converted to `Vec<Vec<T>>`.
+    inner_len_from: Option<String>,
+    inner_len_const: Option<usize>,
+}
+
+/// One `u_field` -> `i_field` entry of a struct spec.
+#[derive(Clone)]
+struct FieldMapping {
+    u_name: String,
+    i_name: String,
+    ptr: Option<PtrShape>,
+}
+
+fn parse_field_mapping(value: &serde_json::Value) -> PyResult<FieldMapping> {
+    let name_of = |side: &str| {
+        value
+            .get(side)
+            .and_then(|field| field.get("name"))
+            .and_then(|name| name.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                pyo3::exceptions::PyValueError::new_err(format!(
+                    "Field mapping is missing {}.name",
+                    side
+                ))
+            })
+    };
+    let u_name = name_of("u_field")?;
+    let i_name = name_of("i_field")?;
+
+    let ptr = match value["u_field"].get("shape").and_then(|s| s.get("ptr")) {
+        None => None,
+        Some(ptr) => {
+            let kind = match ptr.get("kind").and_then(|k| k.as_str()) {
+                Some("slice") => PtrKind::Slice,
+                Some("cstring") => PtrKind::CString,
+                Some("ref") => PtrKind::Ref,
+                other => {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "Unsupported pointer kind {:?} for field '{}'",
+                        other, u_name
+                    )))
+                }
+            };
+            Some(PtrShape {
+                kind,
+                len_from: ptr
+                    .get("len_from")
+                    .and_then(|l| l.as_str())
+                    .map(str::to_string),
+                len_const: ptr
+                    .get("len_const")
+                    .and_then(|l| l.as_u64())
+                    .map(|l| l as usize),
+                inner_len_from: ptr
+                    .get("inner_len_from")
+                    .and_then(|l| l.as_str())
+                    .map(str::to_string),
+                inner_len_const: ptr
+                    .get("inner_len_const")
+                    .and_then(|l| l.as_u64())
+                    .map(|l| l as usize),
+            })
+        }
+    };
+
+    Ok(FieldMapping {
+        u_name,
+        i_name,
+        ptr,
+    })
+}
+
+/// Accepts either a struct spec object with a `fields` list or the bare list.
+fn parse_field_mappings(json: &str) -> PyResult<Vec<FieldMapping>> {
+    let value = parse_json(json, "field mapping")?;
+    let fields = match &value {
+        serde_json::Value::Array(fields) => fields.as_slice(),
+        serde_json::Value::Object(_) => match value.get("fields") {
+            Some(serde_json::Value::Array(fields)) => fields.as_slice(),
+            Some(_) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "Field mapping 'fields' must be a list",
+                ))
+            }
+            // enum specs may describe everything through `variants`
+            None => &[],
+        },
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Field mapping must be a JSON object or list",
+            ))
+        }
+    };
+    fields.iter().map(parse_field_mapping).collect()
+}
+
+/// One `variants` entry of an enum spec: the tag value selecting the variant
+/// and the mapping of active fields into its payload.
+struct VariantMapping {
+    name: String,
+    tag: String,
+    equals: syn::Expr,
+    payload: Vec<FieldMapping>,
+}
+
+fn parse_variant_mappings(json: &str) -> PyResult<Vec<VariantMapping>> {
+    let value = parse_json(json, "field mapping")?;
+    let Some(variants) = value.get("variants").and_then(|v| v.as_array()) else {
+        return Ok(Vec::new());
+    };
+    variants
+        .iter()
+        .map(|variant| {
+            let name = variant.get("name").and_then(|n| n.as_str());
+            let tag = variant.pointer("/when/tag").and_then(|t| t.as_str());
+            let equals = variant.pointer("/when/equals");
+            let (Some(name), Some(tag), Some(equals)) = (name, tag, equals) else {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "Enum variant mapping needs 'name', 'when.tag' and 'when.equals'",
+                ));
+            };
+            let payload = match variant.get("payload").and_then(|p| p.as_array()) {
+                Some(fields) => fields
+                    .iter()
+                    .map(parse_field_mapping)
+                    .collect::<PyResult<Vec<_>>>()?,
+                None => Vec::new(),
+            };
+            Ok(VariantMapping {
+                name: name.to_string(),
+                tag: tag.to_string(),
+                equals: json_to_rust_expr(equals)?,
+                payload,
+            })
+        })
+        .collect()
+}
+fn generic_arg_of<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+fn type_last_ident(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn is_scalar_type(ty: &syn::Type) -> bool {
+    type_last_ident(ty).is_some_and(|name| {
+        is_numeric_primitive(&name)
+            || matches!(name.as_str(), "bool" | "char")
+            || map_libc_scalar(&name).is_some()
+            || expected_stdint_target(&name).is_some()
+    })
+}
+
+fn same_type(a: &syn::Type, b: &syn::Type) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+fn pointer_elem(ty: &syn::Type) -> Option<&syn::Type> {
+    match ty {
+        syn::Type::Ptr(ptr) => Some(&ptr.elem),
+        _ => None,
+    }
+}
+
+/// Name of the generated converter between two struct types, e.g. `CStudent_to_Student_mut`.
+fn converter_ident(from: &syn::Type, to: &syn::Type) -> Option<syn::Ident> {
+    let name = format!("{}_to_{}_mut", type_last_ident(from)?, type_last_ident(to)?);
+    Some(syn::Ident::new(&name, Span::call_site()))
+}
+
+/// `free_CStudent` / `free_CStudent_fields` for the C struct `CStudent`.
+fn free_ident(ty: &syn::Type, suffix: &str) -> Option<syn::Ident> {
+    let name = format!("free_{}{}", type_last_ident(ty)?, suffix);
+    Some(syn::Ident::new(&name, Span::call_site()))
+}
+
+fn scalar_cast(
+    value: proc_macro2::TokenStream,
+    from: &syn::Type,
+    to: &syn::Type,
+) -> proc_macro2::TokenStream {
+    let is_bool = |ty: &syn::Type| type_last_ident(ty).is_some_and(|name| name == "bool");
+    if same_type(from, to) {
+        value
+    } else if is_bool(to) && !is_bool(from) {
+        quote!((#value != 0))
+    } else {
+        quote!((#value as #to))
+    }
+}
+
+fn null_pointer_of(ty: &syn::Type) -> proc_macro2::TokenStream {
+    match ty {
+        syn::Type::Ptr(ptr) if ptr.const_token.is_some() => quote!(core::ptr::null()),
+        _ => quote!(core::ptr::null_mut()),
+    }
+}
+
+fn is_void_pointer(ty: &syn::Type) -> bool {
+    pointer_elem(ty).is_some_and(|elem| type_last_ident(elem).is_some_and(|name| name == "c_void"))
+}
+
+fn is_boxed_any(ty: &syn::Type) -> bool {
+    let Some(syn::Type::TraitObject(object)) = generic_arg_of(ty, "Box") else {
+        return false;
+    };
+    object.bounds.iter().any(|bound| {
+        matches!(bound, syn::TypeParamBound::Trait(t)
+            if t.path.segments.last().is_some_and(|segment| segment.ident == "Any"))
+    })
+}
+
+/// The `fn` type of a function-pointer field and whether it is wrapped in
+/// `Option`, as c2rust emits nullable callbacks.
+fn bare_fn_of(ty: &syn::Type) -> Option<(&syn::TypeBareFn, bool)> {
+    match ty {
+        syn::Type::BareFn(bare_fn) => Some((bare_fn, false)),
+        _ => match generic_arg_of(ty, "Option")? {
+            syn::Type::BareFn(bare_fn) => Some((bare_fn, true)),
+            _ => None,
+        },
+    }
+}
+
+/// Argument and return types of a `Box<dyn Fn(..) -> ..>` closure type.
+fn boxed_closure_sig(ty: &syn::Type) -> Option<(Vec<syn::Type>, syn::ReturnType)> {
+    let syn::Type::TraitObject(object) = generic_arg_of(ty, "Box")? else {
+        return None;
+    };
+    object.bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(trait_bound) = bound else {
+            return None;
+        };
+        let segment = trait_bound.path.segments.last()?;
+        let PathArguments::Parenthesized(args) = &segment.arguments else {
+            return None;
+        };
+        (segment.ident == "Fn")
+            .then(|| (args.inputs.iter().cloned().collect(), args.output.clone()))
+    })
+}
+
+/// Passes a callback argument across the boundary, or `None` if it needs more
+/// than a scalar cast.
+fn callback_cast(
+    value: proc_macro2::TokenStream,
+    from: &syn::Type,
+    to: &syn::Type,
+) -> Option<proc_macro2::TokenStream> {
+    if same_type(from, to) || (is_scalar_type(from) && is_scalar_type(to)) {
+        Some(scalar_cast(value, from, to))
+    } else {
+        None
+    }
+}
+
+fn return_cast(
+    value: proc_macro2::TokenStream,
+    from: &syn::ReturnType,
+    to: &syn::ReturnType,
+) -> Option<proc_macro2::TokenStream> {
+    match (from, to) {
+        (syn::ReturnType::Default, syn::ReturnType::Default) => Some(value),
+        (syn::ReturnType::Type(_, from), syn::ReturnType::Type(_, to)) => {
+            callback_cast(value, from, to)
+        }
+        _ => None,
+    }
+}
+
+/// Converts a C value stored inline (scalar, nested struct or fixed-size
+/// array) at `value` into the idiomatic type `to`. `char[N]` arrays become
+/// `String`s; arrays map onto arrays or `Vec`s element by element.
+fn c_to_i_inline_value(
+    value: proc_macro2::TokenStream,
+    from: &syn::Type,
+    to: &syn::Type,
+    depth: usize,
+) -> Option<proc_macro2::TokenStream> {
+    if same_type(from, to) {
+        return Some(value);
+    }
+    if is_scalar_type(from) && is_scalar_type(to) {
+        return Some(scalar_cast(value, from, to));
+    }
+    if let syn::Type::Array(array) = from {
+        let elem = &*array.elem;
+        let len = &array.len;
+        if type_last_ident(to).as_deref() == Some("String") {
+            if type_last_ident(elem).as_deref() != Some("c_char") {
+                return None;
+            }
+            return Some(quote!({
+                let bytes: Vec<u8> = #value
+                    .iter()
+                    .take_while(|c| **c != 0)
+                    .map(|c| *c as u8)
+                    .collect();
+                String::from_utf8_lossy(&bytes).into_owned()
+            }));
+        }
+        let index = syn::Ident::new(&format!("i{}", depth), Span::call_site());
+        if let syn::Type::Array(to_array) = to {
+            let item =
+                c_to_i_inline_value(quote!(#value[#index]), elem, &to_array.elem, depth + 1)?;
+            return Some(quote!(core::array::from_fn(|#index| #item)));
+        }
+        let to_elem = generic_arg_of(to, "Vec")?;
+        let item = c_to_i_inline_value(quote!(#value[#index]), elem, to_elem, depth + 1)?;
+        return Some(quote!((0..#len).map(|#index| #item).collect::<Vec<#to_elem>>()));
+    }
+    if is_scalar_type(from) || is_scalar_type(to) {
+        return None;
+    }
+    let conv = converter_ident(from, to)?;
+    Some(quote!({
+        let mut tmp = #value;
+        *Box::from_raw(#conv(&mut tmp) as *mut #to)
+    }))
+}
+
+/// Inverse of `c_to_i_inline_value`: fills the inline C value of type `to`
+/// from the idiomatic place `value`. Strings are truncated to leave room for
+/// the NUL terminator and short `Vec`s are zero-padded.
+fn i_to_c_inline_value(
+    value: proc_macro2::TokenStream,
+    from: &syn::Type,
+    to: &syn::Type,
+    depth: usize,
+) -> Option<proc_macro2::TokenStream> {
+    if same_type(from, to) {
+        return Some(value);
+    }
+    if is_scalar_type(from) && is_scalar_type(to) {
+        return Some(scalar_cast(value, from, to));
+    }
+    if let syn::Type::Array(array) = to {
+        let elem = &*array.elem;
+        let len = &array.len;
+        if type_last_ident(from).as_deref() == Some("String") {
+            if type_last_ident(elem).as_deref() != Some("c_char") {
+                return None;
+            }
+            return Some(quote!({
+                let mut out = [0 as #elem; #len];
+                for (dst, byte) in out.iter_mut().zip(#value.bytes().take(#len - 1)) {
+                    *dst = byte as #elem;
+                }
+                out
+            }));
+        }
+        let index = syn::Ident::new(&format!("i{}", depth), Span::call_site());
+        if let syn::Type::Array(from_array) = from {
+            let item =
+                i_to_c_inline_value(quote!(#value[#index]), &from_array.elem, elem, depth + 1)?;
+            return Some(quote!(core::array::from_fn(|#index| #item)));
+        }
+        let from_elem = generic_arg_of(from, "Vec")?;
+        let item = i_to_c_inline_value(quote!(#value[#index]), from_elem, elem, depth + 1)?;
+        return Some(quote!(core::array::from_fn(|#index| {
+            if #index < #value.len() {
+                #item
+            } else {
+                core::mem::zeroed()
+            }
+        })));
+    }
+    if is_scalar_type(from) || is_scalar_type(to) {
+        return None;
+    }
+    let conv = converter_ident(from, to)?;
+    Some(quote!(*Box::from_raw(#conv(&mut #value))))
+}
+
+/// Releases what `i_to_c_inline_value` allocated inside the inline C place
+/// `place`: nested structs own their pointer fields, arrays recurse.
+fn release_inline_value(
+    place: proc_macro2::TokenStream,
+    u_ty: &syn::Type,
+    i_ty: &syn::Type,
+    depth: usize,
+) -> Option<proc_macro2::TokenStream> {
+    if same_type(u_ty, i_ty) || is_scalar_type(u_ty) || is_scalar_type(i_ty) {
+        return None;
+    }
+    if let syn::Type::Array(array) = u_ty {
+        let i_elem = match i_ty {
+            syn::Type::Array(i_array) => &*i_array.elem,
+            _ => generic_arg_of(i_ty, "Vec")?,
+        };
+        let item = syn::Ident::new(&format!("x{}", depth), Span::call_site());
+        let release = release_inline_value(quote!((*#item)), &array.elem, i_elem, depth + 1)?;
+        return Some(quote!(for #item in #place.iter_mut() {
+            #release
+        }));
+    }
+    let free = free_ident(u_ty, "_fields")?;
+    Some(quote!(#free(&mut #place);))
+}
+
+/// Converts one element `x` of a `Vec<String>` / `Vec<Vec<T>>` field into the
+/// `*mut T` stored in the C pointer array.
+fn i_to_c_row(pointee: &syn::Type, elem: &syn::Type) -> Option<proc_macro2::TokenStream> {
+    let inner = pointer_elem(pointee)?;
+    if type_last_ident(elem).as_deref() == Some("String") {
+        return Some(quote!(std::ffi::CString::new(x.clone())
+            .unwrap_or_else(|_| std::ffi::CString::new("").unwrap())
+            .into_raw() as #pointee));
+    }
+    let row_elem = generic_arg_of(elem, "Vec")?;
+    let row = if same_type(inner, row_elem) {
+        quote!(x.clone())
+    } else if is_scalar_type(inner) && is_scalar_type(row_elem) {
+        let cast = scalar_cast(quote!(*y), row_elem, inner);
+        quote!(x.iter().map(|y| #cast).collect::<Vec<#inner>>())
+    } else {
+        return None;
+    };
+    let null = null_pointer_of(pointee);
+    Some(quote!(if x.is_empty() {
+        #null
+    } else {
+        let mut b = #row.into_boxed_slice();
+        let p = b.as_mut_ptr();
+        core::mem::forget(b);
+        p as #pointee
+    }))
+}
+
+fn todo_marker(message: String) -> proc_macro2::TokenStream {
+    let message = format!("TODO: {}", message);
+    quote!(todo!(#message))
+}
+
+fn named_struct_fields(item: &syn::ItemStruct) -> Vec<(syn::Ident, syn::Type)> {
+    item.fields
+        .iter()
+        .filter_map(|field| Some((field.ident.clone()?, field.ty.clone())))
+        .collect()
+}
+
+/// Field lists of every struct and union in a piece of code, by type name.
+fn collect_record_fields(items: &[syn::Item]) -> HashMap<String, Vec<(syn::Ident, syn::Type)>> {
+    let mut records = HashMap::new();
+    for item in items {
+        let (ident, fields) = match item {
+            syn::Item::Struct(item_struct) => {
+                (&item_struct.ident, named_struct_fields(item_struct))
+            }
+            syn::Item::Union(item_union) => (
+                &item_union.ident,
+                item_union
+                    .fields
+                    .named
+                    .iter()
+                    .filter_map(|field| Some((field.ident.clone()?, field.ty.clone())))
+                    .collect(),
+            ),
+            _ => continue,
+        };
+        records.insert(ident.to_string(), fields);
+    }
+    records
+}
+
+/// `(payload key, binding, type)` for each field of an enum variant; tuple
+/// fields are keyed `0`, `1`, ... and bound to `v0`, `v1`, ...
+fn payload_bindings(variant: &syn::Variant) -> Vec<(String, syn::Ident, syn::Type)> {
+    match &variant.fields {
+        syn::Fields::Unit => Vec::new(),
+        syn::Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let binding = syn::Ident::new(&format!("v{}", i), Span::call_site());
+                (i.to_string(), binding, field.ty.clone())
+            })
+            .collect(),
+        syn::Fields::Named(fields) => fields
+            .named
+            .iter()
+            .filter_map(|field| {
+                let ident = field.ident.clone()?;
+                Some((ident.to_string(), ident, field.ty.clone()))
+            })
+            .collect(),
+    }
+}
+
+/// Idiomatic representation of a pointer field linking a struct to its own type.
+enum SelfLink {
+    /// `Rc<RefCell<T>>`, for links shared between nodes.
+    Rc,
+    /// `usize` index into an arena of nodes.
+    Arena,
+}
+
+/// Builds field conversion expressions between a `#[repr(C)]` struct and its
+/// idiomatic counterpart from a spec field mapping.
+struct ConverterGen<'a> {
+    c_fields: Vec<(syn::Ident, syn::Type)>,
+    /// Structs and unions defined alongside the C struct, for dotted paths like `u.i`.
+    c_records: HashMap<String, Vec<(syn::Ident, syn::Type)>>,
+    i_fields: Vec<(syn::Ident, syn::Type)>,
+    mappings: &'a [FieldMapping],
+    /// Name of the C struct, used to name per-field helper items.
+    c_name: String,
+    /// Name of the idiomatic type, for helpers converting whole linked structures.
+    i_name: String,
+    /// Items the conversions depend on (callback trampolines), by name.
+    items: RefCell<Vec<(String, proc_macro2::TokenStream)>>,
+}
+
+impl ConverterGen<'_> {
+    fn c_field_type(&self, path: &str) -> Option<&syn::Type> {
+        let mut fields = &self.c_fields;
+        let mut found: Option<&syn::Type> = None;
+        for segment in path.split('.') {
+            if let Some(parent) = found {
+                fields = self.c_records.get(&type_last_ident(parent)?)?;
+            }
+            found = Some(
+                fields
+                    .iter()
+                    .find(|(ident, _)| ident == segment)
+                    .map(|(_, ty)| ty)?,
+            );
+        }
+        found
+    }
+
+    /// `c_struct.a.b` for the C field path `a.b`.
+    fn c_src(&self, path: &str) -> proc_macro2::TokenStream {
+        let segments = path
+            .split('.')
+            .map(|segment| syn::Ident::new(segment, Span::call_site()));
+        quote!(c_struct #(.#segments)*)
+    }
+
+    fn i_field_type(&self, name: &str) -> Option<&syn::Type> {
+        self.i_fields
+            .iter()
+            .find(|(ident, _)| ident == name)
+            .map(|(_, ty)| ty)
+    }
+
+    /// The spec shape, or one inferred from the C type when the spec omits it.
+    fn shape_of(&self, mapping: &FieldMapping, u_ty: &syn::Type) -> Option<PtrShape> {
+        if mapping.ptr.is_some() {
+            return mapping.ptr.clone();
+        }
+        let elem = pointer_elem(u_ty)?;
+        let kind = if type_last_ident(elem).is_some_and(|name| name == "c_char") {
+            PtrKind::CString
+        } else {
+            PtrKind::Ref
+        };
+        Some(PtrShape {
+            kind,
+            len_from: None,
+            len_const: None,
+            inner_len_from: None,
+            inner_len_const: None,
+        })
+    }
+
+    /// Expression producing the idiomatic field value from `c_struct`.
+    fn c_to_i_value(&self, mapping: &FieldMapping, i_ty: &syn::Type) -> proc_macro2::TokenStream {
+        let Some(u_ty) = self.c_field_type(&mapping.u_name) else {
+            return todo_marker(format!("unknown C field '{}'", mapping.u_name));
+        };
+        let src = self.c_src(&mapping.u_name);
+        let (optional, target) = match generic_arg_of(i_ty, "Option") {
+            Some(inner) => (true, inner),
+            None => (false, i_ty),
+        };
+        let wrap = |value: proc_macro2::TokenStream| {
+            if optional {
+                quote!(Some(#value))
+            } else {
+                value
+            }
+        };
+        if let Some(value) = self.c_to_i_callback(mapping, u_ty, i_ty, &src) {
+            return value;
+        }
+        if is_void_pointer(u_ty) {
+            return self.c_to_i_void(mapping, u_ty, i_ty, &src);
+        }
+        if let Some(value) = self.c_to_i_link(u_ty, i_ty, &src) {
+            return value;
+        }
+        let unsupported = || {
+            todo_marker(format!(
+                "convert C field '{}' ({}) to '{}' ({})",
+                mapping.u_name,
+                u_ty.to_token_stream(),
+                mapping.i_name,
+                i_ty.to_token_stream()
+            ))
+        };
+
+        let Some(shape) = self.shape_of(mapping, u_ty) else {
+            if matches!(u_ty, syn::Type::Array(_)) || same_type(u_ty, target) {
+                return match c_to_i_inline_value(src, u_ty, target, 0) {
+                    Some(value) => wrap(value),
+                    None => unsupported(),
+                };
+            }
+            if is_scalar_type(u_ty) && is_scalar_type(target) {
+                return wrap(scalar_cast(src, u_ty, target));
+            }
+            if !optional && !is_scalar_type(u_ty) && !is_scalar_type(target) {
+                if let Some(conv) = converter_ident(u_ty, target) {
+                    return quote!({
+                        let mut tmp = #src;
+                        *Box::from_raw(#conv(&mut tmp) as *mut #target)
+                    });
+                }
+            }
+            return unsupported();
+        };
+        let Some(pointee) = pointer_elem(u_ty) else {
+            return unsupported();
+        };
+
+        match shape.kind {
+            PtrKind::CString => {
+                if type_last_ident(target).as_deref() != Some("String") {
+                    return unsupported();
+                }
+                let value = wrap(quote!(std::ffi::CStr::from_ptr(#src)
+                    .to_string_lossy()
+                    .into_owned()));
+                let null = if optional {
+                    quote!(None)
+                } else {
+                    quote!(String::new())
+                };
+                quote!(if !#src.is_null() { #value } else { #null })
+            }
+            PtrKind::Slice => {
+                let Some(elem) = generic_arg_of(target, "Vec") else {
+                    return unsupported();
+                };
+                let len = match (&shape.len_from, shape.len_const) {
+                    (Some(len_from), _) => {
+                        let len_src = self.c_src(len_from);
+                        quote!((#len_src as usize))
+                    }
+                    (None, Some(len)) => {
+                        let len = syn::LitInt::new(&len.to_string(), Span::call_site());
+                        quote!(#len)
+                    }
+                    (None, None) => {
+                        return todo_marker(format!(
+                            "slice field '{}' has no len_from/len_const",
+                            mapping.u_name
+                        ))
+                    }
+                };
+                let slice = quote!(std::slice::from_raw_parts(#src as *const #pointee, #len));
+                let vec = if same_type(pointee, elem) {
+                    quote!(#slice.to_vec())
+                } else if is_scalar_type(pointee) && is_scalar_type(elem) {
+                    let cast = scalar_cast(quote!(*v), pointee, elem);
+                    quote!(#slice.iter().map(|v| #cast).collect::<Vec<#elem>>())
+                } else if let Some(conv) = converter_ident(pointee, elem) {
+                    quote!(#slice
+                        .iter()
+                        .map(|v| {
+                            let mut tmp = *v;
+                            *Box::from_raw(#conv(&mut tmp) as *mut #elem)
+                        })
+                        .collect::<Vec<#elem>>())
+                } else if let Some(row) = self.c_to_i_row(&shape, pointee, elem) {
+                    quote!(#slice.iter().map(|p| #row).collect::<Vec<#elem>>())
+                } else {
+                    return unsupported();
+                };
+                let value = wrap(vec);
+                let null = if optional {
+                    quote!(None)
+                } else {
+                    quote!(Vec::<#elem>::new())
+                };
+                quote!(if !#src.is_null() && #len > 0 { #value } else { #null })
+            }
+            PtrKind::Ref => {
+                let boxed = generic_arg_of(target, "Box");
+                let inner = boxed.unwrap_or(target);
+                let mut value = if is_scalar_type(pointee) && is_scalar_type(inner) {
+                    scalar_cast(quote!(*#src), pointee, inner)
+                } else if let Some(conv) = converter_ident(pointee, inner) {
+                    quote!(*Box::from_raw(#conv(#src as *mut #pointee) as *mut #inner))
+                } else {
+                    return unsupported();
+                };
+                if boxed.is_some() {
+                    value = quote!(Box::new(#value));
+                }
+                if optional {
+                    quote!(if !#src.is_null() { Some(#value) } else { None })
+                } else {
+                    quote!({
+                        assert!(!#src.is_null());
+                        #value
+                    })
+                }
+            }
+        }
+    }
+
+    /// Expression for the length of an idiomatic collection field, as `u_ty`.
+    fn i_len_value(&self, i_name: &str, u_ty: &syn::Type) -> proc_macro2::TokenStream {
+        let i_ident = syn::Ident::new(i_name, Span::call_site());
+        match self.i_field_type(i_name) {
+            Some(ty) if generic_arg_of(ty, "Option").is_some() => {
+                quote!((idiom_struct.#i_ident.as_ref().map_or(0, |v| v.len()) as #u_ty))
+            }
+            Some(_) => quote!((idiom_struct.#i_ident.len() as #u_ty)),
+            None => todo_marker(format!("unknown idiomatic field '{}'", i_name)),
+        }
+    }
+
+    /// Row length of a `T**` slice, from `inner_len_from` or `inner_len_const`.
+    fn row_len(&self, shape: &PtrShape) -> Option<proc_macro2::TokenStream> {
+        match (&shape.inner_len_from, shape.inner_len_const) {
+            (Some(len_from), _) => {
+                let len_src = self.c_src(len_from);
+                Some(quote!((#len_src as usize)))
+            }
+            (None, Some(len)) => {
+                let len = syn::LitInt::new(&len.to_string(), Span::call_site());
+                Some(quote!(#len))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Converts one element `p: &*mut T` of a `T**` slice into a `String`
+    /// (for `char**`) or a `Vec` row.
+    fn c_to_i_row(
+        &self,
+        shape: &PtrShape,
+        pointee: &syn::Type,
+        elem: &syn::Type,
+    ) -> Option<proc_macro2::TokenStream> {
+        let inner = pointer_elem(pointee)?;
+        if type_last_ident(elem).as_deref() == Some("String") {
+            if type_last_ident(inner).as_deref() != Some("c_char") {
+                return None;
+            }
+            return Some(quote!(if p.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(*p).to_string_lossy().into_owned()
+            }));
+        }
+        let row_elem = generic_arg_of(elem, "Vec")?;
+        let row = quote!(std::slice::from_raw_parts(*p as *const #inner, len));
+        let row = if same_type(inner, row_elem) {
+            quote!(#row.to_vec())
+        } else if is_scalar_type(inner) && is_scalar_type(row_elem) {
+            let cast = scalar_cast(quote!(*v), inner, row_elem);
+            quote!(#row.iter().map(|v| #cast).collect::<Vec<#row_elem>>())
+        } else {
+            return None;
+        };
+        let Some(len) = self.row_len(shape) else {
+            return Some(todo_marker(
+                "`Vec<Vec<_>>` field needs inner_len_from/inner_len_const".to_string(),
+            ));
+        };
+        Some(quote!({
+            let len = #len;
+            if p.is_null() || len == 0 {
+                Vec::new()
+            } else {
+                #row
+            }
+        }))
+    }
+
+    /// Releases one element `p: &*mut T` allocated by `i_to_c_row`.
+    fn release_row(
+        &self,
+        shape: &PtrShape,
+        pointee: &syn::Type,
+        elem: &syn::Type,
+    ) -> Option<proc_macro2::TokenStream> {
+        let inner = pointer_elem(pointee)?;
+        if type_last_ident(elem).as_deref() == Some("String") {
+            return Some(quote!(if !p.is_null() {
+                drop(std::ffi::CString::from_raw(*p as *mut std::ffi::c_char));
+            }));
+        }
+        generic_arg_of(elem, "Vec")?;
+        let len = self.row_len(shape)?;
+        Some(quote!(if !p.is_null() && #len > 0 {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(*p as *mut #inner, #len)));
+        }))
+    }
+
+    /// Row length written to an `inner_len_from` field, taken from the first row.
+    fn i_row_len_value(&self, i_name: &str, u_ty: &syn::Type) -> proc_macro2::TokenStream {
+        let i_ident = syn::Ident::new(i_name, Span::call_site());
+        match self.i_field_type(i_name) {
+            Some(ty) if generic_arg_of(ty, "Option").is_some() => quote!((idiom_struct
+                .#i_ident
+                .as_ref()
+                .and_then(|rows| rows.first())
+                .map_or(0, |row| row.len()) as #u_ty)),
+            Some(_) => {
+                quote!((idiom_struct.#i_ident.first().map_or(0, |row| row.len()) as #u_ty))
+            }
+            None => todo_marker(format!("unknown idiomatic field '{}'", i_name)),
+        }
+    }
+
+    /// Expression producing the C field value (of type `u_ty`) from `idiom_struct`.
+    fn i_to_c_value(&self, mapping: &FieldMapping, u_ty: &syn::Type) -> proc_macro2::TokenStream {
+        if let Some(base) = mapping.i_name.strip_suffix(".len") {
+            return self.i_len_value(base, u_ty);
+        }
+        let Some(i_ty) = self.i_field_type(&mapping.i_name) else {
+            return todo_marker(format!("unknown idiomatic field '{}'", mapping.i_name));
+        };
+        let i_ident = syn::Ident::new(&mapping.i_name, Span::call_site());
+        self.i_to_c_convert(mapping, u_ty, i_ty, quote!(idiom_struct.#i_ident))
+    }
+
+    /// Converts the idiomatic place `src` of type `i_ty` into a C value of type `u_ty`.
+    fn i_to_c_convert(
+        &self,
+        mapping: &FieldMapping,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        if let Some(value) = self.i_to_c_callback(mapping, u_ty, i_ty, &src) {
+            return value;
+        }
+        if is_void_pointer(u_ty) {
+            return self.i_to_c_void(mapping, u_ty, i_ty, &src);
+        }
+        if let Some(value) = self.i_to_c_link(u_ty, i_ty, &src) {
+            return value;
+        }
+        let (optional, target) = match generic_arg_of(i_ty, "Option") {
+            Some(inner) => (true, inner),
+            None => (false, i_ty),
+        };
+        let unsupported = || {
+            todo_marker(format!(
+                "convert idiomatic field '{}' ({}) to '{}' ({})",
+                mapping.i_name,
+                i_ty.to_token_stream(),
+                mapping.u_name,
+                u_ty.to_token_stream()
+            ))
+        };
+        let null = null_pointer_of(u_ty);
+        // runs `body` with `v` bound to `&mut target`, mapping `None` to NULL
+        let with_value = |body: proc_macro2::TokenStream, unbox: bool| {
+            let unbox = if unbox {
+                quote!(let v = &mut **v;)
+            } else {
+                quote!()
+            };
+            if optional {
+                quote!(match #src.as_mut() {
+                    Some(v) => {
+                        #unbox
+                        #body
+                    }
+                    None => #null,
+                })
+            } else {
+                quote!({
+                    let v = &mut #src;
+                    #unbox
+                    #body
+                })
+            }
+        };
+
+        let Some(shape) = self.shape_of(mapping, u_ty) else {
+            if !optional && (matches!(u_ty, syn::Type::Array(_)) || same_type(u_ty, target)) {
+                return i_to_c_inline_value(src, target, u_ty, 0).unwrap_or_else(unsupported);
+            }
+            if is_scalar_type(u_ty) && is_scalar_type(target) {
+                let value = if optional {
+                    quote!(#src.unwrap_or_default())
+                } else {
+                    src.clone()
+                };
+                return scalar_cast(value, target, u_ty);
+            }
+            if !optional && !is_scalar_type(u_ty) && !is_scalar_type(target) {
+                if let Some(conv) = converter_ident(target, u_ty) {
+                    return quote!(*Box::from_raw(#conv(&mut #src)));
+                }
+            }
+            return unsupported();
+        };
+        let Some(pointee) = pointer_elem(u_ty) else {
+            return unsupported();
+        };
+
+        match shape.kind {
+            PtrKind::CString => {
+                if type_last_ident(target).as_deref() != Some("String") {
+                    return unsupported();
+                }
+                with_value(
+                    quote!(std::ffi::CString::new(v.clone())
+                        .unwrap_or_else(|_| std::ffi::CString::new("").unwrap())
+                        .into_raw() as #u_ty),
+                    false,
+                )
+            }
+            PtrKind::Slice => {
+                let Some(elem) = generic_arg_of(target, "Vec") else {
+                    return unsupported();
+                };
+                let elements = if same_type(pointee, elem) {
+                    quote!(v.clone())
+                } else if is_scalar_type(pointee) && is_scalar_type(elem) {
+                    let cast = scalar_cast(quote!(*x), elem, pointee);
+                    quote!(v.iter().map(|x| #cast).collect::<Vec<#pointee>>())
+                } else if let Some(conv) = converter_ident(elem, pointee) {
+                    quote!(v
+                        .iter_mut()
+                        .map(|x| *Box::from_raw(#conv(x)))
+                        .collect::<Vec<#pointee>>())
+                } else if let Some(row) = i_to_c_row(pointee, elem) {
+                    quote!(v.iter().map(|x| #row).collect::<Vec<#pointee>>())
+                } else {
+                    return unsupported();
+                };
+                with_value(
+                    quote!(if v.is_empty() {
+                        #null
+                    } else {
+                        let mut b = #elements.into_boxed_slice();
+                        let p = b.as_mut_ptr();
+                        core::mem::forget(b);
+                        p as #u_ty
+                    }),
+                    false,
+                )
+            }
+            PtrKind::Ref => {
+                let boxed = generic_arg_of(target, "Box");
+                let inner = boxed.unwrap_or(target);
+                let body = if is_scalar_type(pointee) && is_scalar_type(inner) {
+                    let cast = scalar_cast(quote!(*v), inner, pointee);
+                    quote!(Box::into_raw(Box::new(#cast)) as #u_ty)
+                } else if let Some(conv) = converter_ident(inner, pointee) {
+                    quote!(#conv(v) as #u_ty)
+                } else {
+                    return unsupported();
+                };
+                with_value(body, boxed.is_some())
+            }
+        }
+    }
+
+    fn c_to_i_inits(&self) -> Vec<proc_macro2::TokenStream> {
+        self.i_fields
+            .iter()
+            .map(|(i_ident, i_ty)| {
+                let value = match self.mappings.iter().find(|m| i_ident == &m.i_name) {
+                    Some(mapping) => self.c_to_i_value(mapping, i_ty),
+                    None => todo_marker(format!("no mapping for idiomatic field '{}'", i_ident)),
+                };
+                quote!(#i_ident: #value)
+            })
+            .collect()
+    }
+
+    fn i_to_c_lets(&self) -> Vec<proc_macro2::TokenStream> {
+        self.c_fields
+            .iter()
+            .map(|(u_ident, u_ty)| {
+                let binding = syn::Ident::new(&format!("_{}", u_ident), Span::call_site());
+                let explicit = self
+                    .mappings
+                    .iter()
+                    .find(|m| u_ident == &m.u_name && !m.i_name.ends_with(".len"));
+                let length_of = |inner: bool| {
+                    self.mappings.iter().find(|m| {
+                        m.ptr.as_ref().is_some_and(|p| {
+                            let len_from = if inner {
+                                &p.inner_len_from
+                            } else {
+                                &p.len_from
+                            };
+                            len_from.as_deref() == Some(&u_ident.to_string())
+                        })
+                    })
+                };
+                let value = match (explicit, length_of(false), length_of(true)) {
+                    (Some(mapping), _, _) => self.i_to_c_value(mapping, u_ty),
+                    (None, Some(slice), _) => self.i_len_value(&slice.i_name, u_ty),
+                    (None, None, Some(rows)) => self.i_row_len_value(&rows.i_name, u_ty),
+                    (None, None, None) => {
+                        match self.mappings.iter().find(|m| u_ident == &m.u_name) {
+                            Some(derived) => self.i_to_c_value(derived, u_ty),
+                            None => quote!(core::mem::zeroed()),
+                        }
+                    }
+                };
+                quote!(let #binding: #u_ty = #value;)
+            })
+            .collect()
+    }
+
+    /// Wraps a C function-pointer field in a boxed closure that calls it, or
+    /// `None` when `u_ty` is not a function pointer.
+    fn c_to_i_callback(
+        &self,
+        mapping: &FieldMapping,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let (c_fn, c_optional) = bare_fn_of(u_ty)?;
+        if same_type(u_ty, i_ty) {
+            return Some(src.clone());
+        }
+        let (optional, target) = match generic_arg_of(i_ty, "Option") {
+            Some(inner) => (true, inner),
+            None => (false, i_ty),
+        };
+        let unsupported = || todo_marker(format!("convert callback field '{}'", mapping.u_name));
+        let Some((i_args, i_output)) = boxed_closure_sig(target) else {
+            return Some(unsupported());
+        };
+        if c_fn.variadic.is_some() || i_args.len() != c_fn.inputs.len() {
+            return Some(unsupported());
+        }
+
+        let params: Vec<syn::Ident> = (0..i_args.len())
+            .map(|i| syn::Ident::new(&format!("a{}", i), Span::call_site()))
+            .collect();
+        let mut args = Vec::new();
+        for ((param, i_arg), c_arg) in params.iter().zip(&i_args).zip(&c_fn.inputs) {
+            let Some(arg) = callback_cast(quote!(#param), i_arg, &c_arg.ty) else {
+                return Some(unsupported());
+            };
+            args.push(arg);
+        }
+        let Some(call) = return_cast(quote!(f(#(#args),*)), &c_fn.output, &i_output) else {
+            return Some(unsupported());
+        };
+        let call = if c_fn.unsafety.is_some() {
+            quote!(unsafe { #call })
+        } else {
+            call
+        };
+        let closure = quote!(Box::new(move |#(#params: #i_args),*| #call) as #target);
+        Some(match (c_optional, optional) {
+            (true, true) => quote!(#src.map(|f| #closure)),
+            (true, false) => quote!({
+                let f = #src.expect("NULL function pointer");
+                #closure
+            }),
+            (false, true) => quote!({
+                let f = #src;
+                Some(#closure)
+            }),
+            (false, false) => quote!({
+                let f = #src;
+                #closure
+            }),
+        })
+    }
+
+    /// The thread-local slot holding the closure behind a callback field, and
+    /// the `extern "C"` trampoline that dispatches to it.
+    fn callback_idents(&self, mapping: &FieldMapping) -> (String, syn::Ident, syn::Ident) {
+        let base = format!("{}_{}", self.c_name, mapping.u_name.replace('.', "_"));
+        let slot = syn::Ident::new(
+            &format!("{}_CLOSURE", base.to_uppercase()),
+            Span::call_site(),
+        );
+        let trampoline = syn::Ident::new(&format!("{}_trampoline", base), Span::call_site());
+        (base, slot, trampoline)
+    }
+
+    /// Points a C function-pointer field at an `extern "C"` trampoline that
+    /// dispatches to the closure in `src`, or `None` when `u_ty` is not a
+    /// function pointer. The trampoline cannot tell which C struct it was
+    /// called through, so a single C struct may hold it at a time: converting
+    /// another idiomatic value before `free_*` has released the previous C
+    /// struct panics.
+    fn i_to_c_callback(
+        &self,
+        mapping: &FieldMapping,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let (c_fn, c_optional) = bare_fn_of(u_ty)?;
+        if same_type(u_ty, i_ty) {
+            return Some(src.clone());
+        }
+        let (optional, target) = match generic_arg_of(i_ty, "Option") {
+            Some(inner) => (true, inner),
+            None => (false, i_ty),
+        };
+        let unsupported = || todo_marker(format!("convert callback field '{}'", mapping.i_name));
+        let (Some((i_args, i_output)), Some(dyn_ty)) =
+            (boxed_closure_sig(target), generic_arg_of(target, "Box"))
+        else {
+            return Some(unsupported());
+        };
+        if (optional && !c_optional) || c_fn.variadic.is_some() || i_args.len() != c_fn.inputs.len()
+        {
+            return Some(unsupported());
+        }
+
+        let params: Vec<syn::Ident> = (0..i_args.len())
+            .map(|i| syn::Ident::new(&format!("a{}", i), Span::call_site()))
+            .collect();
+        let c_args: Vec<&syn::Type> = c_fn.inputs.iter().map(|arg| &arg.ty).collect();
+        let mut args = Vec::new();
+        for ((param, c_arg), i_arg) in params.iter().zip(&c_args).zip(&i_args) {
+            let Some(arg) = callback_cast(quote!(#param), c_arg, i_arg) else {
+                return Some(unsupported());
+            };
+            args.push(arg);
+        }
+        let Some(call) = return_cast(quote!((*f)(#(#args),*)), &i_output, &c_fn.output) else {
+            return Some(unsupported());
+        };
+        let body = if c_fn.unsafety.is_some() {
+            call
+        } else {
+            quote!(unsafe { #call })
+        };
+
+        let (base, slot, trampoline) = self.callback_idents(mapping);
+        let mut items = self.items.borrow_mut();
+        if !items.iter().any(|(name, _)| *name == base) {
+            let unsafety = &c_fn.unsafety;
+            let abi = &c_fn.abi;
+            let output = &c_fn.output;
+            items.push((
+                base,
+                quote! {
+                    thread_local! {
+                        // the owning `Box` field's address, and the closure it holds
+                        static #slot: std::cell::Cell<Option<(usize, *const #dyn_ty)>> =
+                            const { std::cell::Cell::new(None) };
+                    }
+
+                    #unsafety #abi fn #trampoline(#(#params: #c_args),*) #output {
+                        let (_, f) = #slot
+                            .with(|slot| slot.get())
+                            .expect("callback closure is not set");
+                        #body
+                    }
+                },
+            ));
+        }
+
+        let pointer = if c_optional {
+            quote!(Some(#trampoline as #c_fn))
+        } else {
+            quote!(#trampoline as #c_fn)
+        };
+        let message = format!(
+            "another {} still holds the '{}' callback; free it first",
+            self.c_name, mapping.u_name
+        );
+        let install = quote! {
+            #slot.with(|slot| {
+                let owner = v as *const _ as usize;
+                if slot.get().is_some_and(|(held, _)| held != owner) {
+                    panic!(#message);
+                }
+                slot.set(Some((owner, &**v as *const #dyn_ty)));
+            });
+            #pointer
+        };
+        Some(if optional {
+            quote!(match #src.as_ref() {
+                Some(v) => {
+                    #install
+                }
+                None => None,
+            })
+        } else {
+            quote!({
+                let v = &#src;
+                #install
+            })
+        })
+    }
+
+    /// How a self-referential pointer field is represented on the idiomatic
+    /// side; `Box` links go through the ordinary `kind: "ref"` conversion.
+    fn self_link(&self, u_ty: &syn::Type, i_ty: &syn::Type) -> Option<SelfLink> {
+        let pointee = pointer_elem(u_ty)?;
+        if type_last_ident(pointee)? != self.c_name {
+            return None;
+        }
+        let target = generic_arg_of(i_ty, "Option").unwrap_or(i_ty);
+        if generic_arg_of(target, "Rc")
+            .is_some_and(|cell| generic_arg_of(cell, "RefCell").is_some())
+        {
+            Some(SelfLink::Rc)
+        } else if type_last_ident(target).is_some_and(|name| name == "usize") {
+            Some(SelfLink::Arena)
+        } else {
+            None
+        }
+    }
+
+    fn link_ident(&self, suffix: &str) -> syn::Ident {
+        syn::Ident::new(&format!("{}{}", self.c_name, suffix), Span::call_site())
+    }
+
+    fn link_static(&self, suffix: &str) -> syn::Ident {
+        syn::Ident::new(
+            &format!("{}{}", self.c_name.to_uppercase(), suffix),
+            Span::call_site(),
+        )
+    }
+
+    /// Scope guard clearing the `Rc` link tables once the outermost
+    /// conversion returns, so shared nodes map to a single allocation per call.
+    fn rc_link_items(&self) {
+        let mut items = self.items.borrow_mut();
+        if items.iter().any(|(name, _)| name == "rc_links") {
+            return;
+        }
+        let c_ident = self.link_ident("");
+        let i_ident = syn::Ident::new(&self.i_name, Span::call_site());
+        let depth = self.link_static("_LINK_DEPTH");
+        let to_rc = self.link_static("_TO_RC");
+        let from_rc = self.link_static("_FROM_RC");
+        let freed = self.link_static("_FREED");
+        let scope = self.link_ident("LinkScope");
+        let c_to_rc = syn::Ident::new(
+            &format!("{}_to_{}_rc", self.c_name, self.i_name),
+            Span::call_site(),
+        );
+        let rc_to_c = syn::Ident::new(
+            &format!("{}_rc_to_{}", self.i_name, self.c_name),
+            Span::call_site(),
+        );
+        let c_to_i = syn::Ident::new(
+            &format!("{}_to_{}_mut", self.c_name, self.i_name),
+            Span::call_site(),
+        );
+        let i_to_c = syn::Ident::new(
+            &format!("{}_to_{}_mut", self.i_name, self.c_name),
+            Span::call_site(),
+        );
+        let free_link = self.link_ident("_free_link");
+        let free_c = syn::Ident::new(&format!("free_{}", self.c_name), Span::call_site());
+        items.push((
+            "rc_links".to_string(),
+            quote! {
+                thread_local! {
+                    static #depth: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+                    static #to_rc: std::cell::RefCell<
+                        std::collections::HashMap<usize, Option<std::rc::Rc<std::cell::RefCell<#i_ident>>>>,
+                    > = std::cell::RefCell::new(std::collections::HashMap::new());
+                    static #from_rc: std::cell::RefCell<std::collections::HashMap<usize, *mut #c_ident>> =
+                        std::cell::RefCell::new(std::collections::HashMap::new());
+                    static #freed: std::cell::RefCell<std::collections::HashSet<usize>> =
+                        std::cell::RefCell::new(std::collections::HashSet::new());
+                }
+
+                struct #scope;
+
+                impl #scope {
+                    fn enter() -> Self {
+                        #depth.with(|depth| depth.set(depth.get() + 1));
+                        Self
+                    }
+                }
+
+                impl Drop for #scope {
+                    fn drop(&mut self) {
+                        if #depth.with(|depth| {
+                            depth.set(depth.get() - 1);
+                            depth.get() == 0
+                        }) {
+                            #to_rc.with(|links| links.borrow_mut().clear());
+                            #from_rc.with(|links| links.borrow_mut().clear());
+                            #freed.with(|links| links.borrow_mut().clear());
+                        }
+                    }
+                }
+
+                unsafe fn #c_to_rc(ptr: *mut #c_ident) -> std::rc::Rc<std::cell::RefCell<#i_ident>> {
+                    let key = ptr as usize;
+                    if let Some(seen) = #to_rc.with(|links| links.borrow().get(&key).cloned()) {
+                        return seen.expect("cyclic links cannot be converted to Rc; use an arena");
+                    }
+                    #to_rc.with(|links| links.borrow_mut().insert(key, None));
+                    let node = std::rc::Rc::new(std::cell::RefCell::new(*Box::from_raw(
+                        #c_to_i(ptr) as *mut #i_ident,
+                    )));
+                    #to_rc.with(|links| links.borrow_mut().insert(key, Some(node.clone())));
+                    node
+                }
+
+                unsafe fn #rc_to_c(node: &std::rc::Rc<std::cell::RefCell<#i_ident>>) -> *mut #c_ident {
+                    let key = std::rc::Rc::as_ptr(node) as usize;
+                    if let Some(seen) = #from_rc.with(|links| links.borrow().get(&key).copied()) {
+                        return seen;
+                    }
+                    // reserve the node first so cycles point back at it
+                    let out = Box::into_raw(Box::new(core::mem::MaybeUninit::<#c_ident>::uninit()))
+                        as *mut #c_ident;
+                    #from_rc.with(|links| links.borrow_mut().insert(key, out));
+                    let converted = #i_to_c(&mut node.borrow_mut());
+                    core::ptr::write(out, *Box::from_raw(converted));
+                    out
+                }
+
+                unsafe fn #free_link(ptr: *mut #c_ident) {
+                    if !ptr.is_null() && #freed.with(|links| links.borrow_mut().insert(ptr as usize)) {
+                        #free_c(ptr);
+                    }
+                }
+            },
+        ));
+    }
+
+    /// Arena helpers: nodes reachable from a head pointer become a `Vec` and
+    /// links become indices into it, in both directions.
+    fn arena_link_items(&self) {
+        let mut items = self.items.borrow_mut();
+        if items.iter().any(|(name, _)| name == "arena_links") {
+            return;
+        }
+        let links: Vec<&syn::Ident> = self
+            .c_fields
+            .iter()
+            .filter(|(u_ident, u_ty)| {
+                self.mappings
+                    .iter()
+                    .find(|m| u_ident == &m.u_name)
+                    .and_then(|m| self.i_field_type(&m.i_name))
+                    .is_some_and(|i_ty| matches!(self.self_link(u_ty, i_ty), Some(SelfLink::Arena)))
+            })
+            .map(|(u_ident, _)| u_ident)
+            .collect();
+        let c_ident = self.link_ident("");
+        let i_ident = syn::Ident::new(&self.i_name, Span::call_site());
+        let index = self.link_static("_ARENA_INDEX");
+        let ptrs = self.link_static("_ARENA_PTRS");
+        let reachable = self.link_ident("_arena_nodes");
+        let c_to_arena = syn::Ident::new(
+            &format!("{}_to_{}_arena", self.c_name, self.i_name),
+            Span::call_site(),
+        );
+        let arena_to_c = syn::Ident::new(
+            &format!("{}_arena_to_{}", self.i_name, self.c_name),
+            Span::call_site(),
+        );
+        let c_to_i = syn::Ident::new(
+            &format!("{}_to_{}_mut", self.c_name, self.i_name),
+            Span::call_site(),
+        );
+        let i_to_c = syn::Ident::new(
+            &format!("{}_to_{}_mut", self.i_name, self.c_name),
+            Span::call_site(),
+        );
+        let free_c = syn::Ident::new(&format!("free_{}", self.c_name), Span::call_site());
+        let free_arena = syn::Ident::new(&format!("free_{}_arena", self.c_name), Span::call_site());
+        items.push((
+            "arena_links".to_string(),
+            quote! {
+                thread_local! {
+                    static #index: std::cell::RefCell<std::collections::HashMap<usize, usize>> =
+                        std::cell::RefCell::new(std::collections::HashMap::new());
+                    static #ptrs: std::cell::RefCell<Vec<*mut #c_ident>> = const { std::cell::RefCell::new(Vec::new()) };
+                }
+
+                /// Nodes reachable from `head`, `head` first.
+                unsafe fn #reachable(head: *mut #c_ident) -> Vec<*mut #c_ident> {
+                    let mut nodes = Vec::new();
+                    let mut seen = std::collections::HashSet::new();
+                    let mut stack = vec![head];
+                    while let Some(ptr) = stack.pop() {
+                        if ptr.is_null() || !seen.insert(ptr as usize) {
+                            continue;
+                        }
+                        nodes.push(ptr);
+                        #(stack.push((*ptr).#links as *mut #c_ident);)*
+                    }
+                    nodes
+                }
+
+                unsafe fn #c_to_arena(head: *mut #c_ident) -> (Vec<#i_ident>, Option<usize>) {
+                    let nodes = #reachable(head);
+                    #index.with(|index| {
+                        *index.borrow_mut() = nodes
+                            .iter()
+                            .enumerate()
+                            .map(|(i, ptr)| (*ptr as usize, i))
+                            .collect()
+                    });
+                    let arena = nodes
+                        .iter()
+                        .map(|ptr| *Box::from_raw(#c_to_i(*ptr) as *mut #i_ident))
+                        .collect();
+                    #index.with(|index| index.borrow_mut().clear());
+                    (arena, if head.is_null() { None } else { Some(0) })
+                }
+
+                unsafe fn #arena_to_c(arena: &mut [#i_ident], head: Option<usize>) -> *mut #c_ident {
+                    // reserve every node first so links can point forward
+                    let nodes: Vec<*mut #c_ident> = (0..arena.len())
+                        .map(|_| {
+                            Box::into_raw(Box::new(core::mem::MaybeUninit::<#c_ident>::uninit()))
+                                as *mut #c_ident
+                        })
+                        .collect();
+                    #ptrs.with(|ptrs| *ptrs.borrow_mut() = nodes.clone());
+                    for (node, ptr) in arena.iter_mut().zip(&nodes) {
+                        core::ptr::write(*ptr, *Box::from_raw(#i_to_c(node)));
+                    }
+                    #ptrs.with(|ptrs| ptrs.borrow_mut().clear());
+                    head.map_or(core::ptr::null_mut(), |i| nodes[i])
+                }
+
+                unsafe fn #free_arena(head: *mut #c_ident) {
+                    for ptr in #reachable(head) {
+                        #free_c(ptr);
+                    }
+                }
+            },
+        ));
+    }
+
+    fn c_to_i_link(
+        &self,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let optional = generic_arg_of(i_ty, "Option").is_some();
+        let value = match self.self_link(u_ty, i_ty)? {
+            SelfLink::Rc => {
+                self.rc_link_items();
+                let c_to_rc = syn::Ident::new(
+                    &format!("{}_to_{}_rc", self.c_name, self.i_name),
+                    Span::call_site(),
+                );
+                let value = quote!(#c_to_rc(#src as *mut _));
+                if optional {
+                    quote!(if !#src.is_null() { Some(#value) } else { None })
+                } else {
+                    quote!({
+                        assert!(!#src.is_null());
+                        #value
+                    })
+                }
+            }
+            SelfLink::Arena => {
+                self.arena_link_items();
+                let index = self.link_static("_ARENA_INDEX");
+                let value =
+                    quote!(#index.with(|index| index.borrow().get(&(#src as usize)).copied()));
+                if optional {
+                    value
+                } else {
+                    quote!(#value.expect("link outside the arena"))
+                }
+            }
+        };
+        Some(value)
+    }
+
+    fn i_to_c_link(
+        &self,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: &proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let optional = generic_arg_of(i_ty, "Option").is_some();
+        let (convert, by_ref) = match self.self_link(u_ty, i_ty)? {
+            SelfLink::Rc => {
+                self.rc_link_items();
+                let rc_to_c = syn::Ident::new(
+                    &format!("{}_rc_to_{}", self.i_name, self.c_name),
+                    Span::call_site(),
+                );
+                (quote!(#rc_to_c), true)
+            }
+            SelfLink::Arena => {
+                self.arena_link_items();
+                let ptrs = self.link_static("_ARENA_PTRS");
+                (
+                    quote!((|i: usize| #ptrs.with(|ptrs| ptrs.borrow()[i]))),
+                    false,
+                )
+            }
+        };
+        let null = null_pointer_of(u_ty);
+        Some(match (optional, by_ref) {
+            (true, true) => quote!(#src.as_ref().map_or(#null, |v| #convert(v) as #u_ty)),
+            (true, false) => quote!(#src.map_or(#null, |v| #convert(v) as #u_ty)),
+            (false, true) => quote!(#convert(&#src) as #u_ty),
+            (false, false) => quote!(#convert(#src) as #u_ty),
+        })
+    }
+
+    /// Statement run at the start of every converter when `Rc` links are in play.
+    fn link_scope(&self) -> proc_macro2::TokenStream {
+        if !self
+            .items
+            .borrow()
+            .iter()
+            .any(|(name, _)| name == "rc_links")
+        {
+            return quote!();
+        }
+        let scope = self.link_ident("LinkScope");
+        quote!(let _links = #scope::enter();)
+    }
+
+    /// Registry functions backing `Box<dyn Any>` fields mapped from `void *`:
+    /// `register` parks the box and returns its address as the C pointer,
+    /// `take` hands it back (or boxes the raw address if it was never parked).
+    fn void_registry(&self) -> (syn::Ident, syn::Ident) {
+        let registry = syn::Ident::new(
+            &format!("{}_VOID_PTRS", self.c_name.to_uppercase()),
+            Span::call_site(),
+        );
+        let register = syn::Ident::new(
+            &format!("{}_register_void_ptr", self.c_name),
+            Span::call_site(),
+        );
+        let take = syn::Ident::new(&format!("{}_take_void_ptr", self.c_name), Span::call_site());
+        let mut items = self.items.borrow_mut();
+        if !items.iter().any(|(name, _)| name == "void_ptr_registry") {
+            items.push((
+                "void_ptr_registry".to_string(),
+                quote! {
+                    thread_local! {
+                        static #registry: std::cell::RefCell<
+                            std::collections::HashMap<usize, Box<dyn std::any::Any>>,
+                        > = std::cell::RefCell::new(std::collections::HashMap::new());
+                    }
+
+                    fn #register(value: Box<dyn std::any::Any>) -> *mut core::ffi::c_void {
+                        let handle = &*value as *const dyn std::any::Any as *const () as usize;
+                        #registry.with(|registry| registry.borrow_mut().insert(handle, value));
+                        handle as *mut core::ffi::c_void
+                    }
+
+                    fn #take(ptr: *const core::ffi::c_void) -> Box<dyn std::any::Any> {
+                        #registry
+                            .with(|registry| registry.borrow_mut().remove(&(ptr as usize)))
+                            .unwrap_or_else(|| Box::new(ptr as usize))
+                    }
+                },
+            ));
+        }
+        (register, take)
+    }
+
+    /// `void *` to the idiomatic representation the translation chose: a raw
+    /// pointer kept as is, a `usize` address, or a registered `Box<dyn Any>`.
+    fn c_to_i_void(
+        &self,
+        mapping: &FieldMapping,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let (optional, target) = match generic_arg_of(i_ty, "Option") {
+            Some(inner) => (true, inner),
+            None => (false, i_ty),
+        };
+        let value = if is_boxed_any(target) {
+            let (_, take) = self.void_registry();
+            quote!(#take(#src as *const core::ffi::c_void))
+        } else if matches!(target, syn::Type::Ptr(_))
+            || type_last_ident(target).is_some_and(|name| name == "usize")
+        {
+            quote!(#src as #target)
+        } else {
+            return todo_marker(format!(
+                "convert void pointer field '{}' ({}) to '{}' ({})",
+                mapping.u_name,
+                u_ty.to_token_stream(),
+                mapping.i_name,
+                i_ty.to_token_stream()
+            ));
+        };
+        if optional {
+            quote!(if #src.is_null() { None } else { Some(#value) })
+        } else {
+            value
+        }
+    }
+
+    fn i_to_c_void(
+        &self,
+        mapping: &FieldMapping,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        src: &proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let (optional, target) = match generic_arg_of(i_ty, "Option") {
+            Some(inner) => (true, inner),
+            None => (false, i_ty),
+        };
+        let null = null_pointer_of(u_ty);
+        if is_boxed_any(target) {
+            // the box moves into the registry until the C value is converted back
+            let (register, _) = self.void_registry();
+            return if optional {
+                quote!(match #src.take() {
+                    Some(v) => #register(v) as #u_ty,
+                    None => #null,
+                })
+            } else {
+                quote!(#register(core::mem::replace(&mut #src, Box::new(()))) as #u_ty)
+            };
+        }
+        if !matches!(target, syn::Type::Ptr(_))
+            && !type_last_ident(target).is_some_and(|name| name == "usize")
+        {
+            return todo_marker(format!(
+                "convert idiomatic field '{}' ({}) to void pointer '{}' ({})",
+                mapping.i_name,
+                i_ty.to_token_stream(),
+                mapping.u_name,
+                u_ty.to_token_stream()
+            ));
+        }
+        if optional {
+            quote!(#src.map_or(#null, |v| v as #u_ty))
+        } else {
+            quote!(#src as #u_ty)
+        }
+    }
+
+    /// `c_struct.tag == (value as TagTy)` selecting a variant.
+    fn tag_condition(&self, mapping: &VariantMapping) -> proc_macro2::TokenStream {
+        let tag_src = self.c_src(&mapping.tag);
+        let equals = &mapping.equals;
+        match self.c_field_type(&mapping.tag) {
+            Some(tag_ty) => quote!(#tag_src == (#equals as #tag_ty)),
+            None => quote!(#tag_src == #equals),
+        }
+    }
+
+    /// Statement releasing what `i_to_c_convert` allocated for the C place
+    /// `place`, or `None` when the converted value owns no memory.
+    fn release_value(
+        &self,
+        mapping: &FieldMapping,
+        u_ty: &syn::Type,
+        i_ty: &syn::Type,
+        place: proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        let target = generic_arg_of(i_ty, "Option").unwrap_or(i_ty);
+        if is_void_pointer(u_ty) {
+            if !is_boxed_any(target) {
+                return None;
+            }
+            let (_, take) = self.void_registry();
+            return Some(quote!(drop(#take(#place));));
+        }
+        match self.self_link(u_ty, i_ty) {
+            Some(SelfLink::Rc) => {
+                let free_link = self.link_ident("_free_link");
+                return Some(quote!(#free_link(#place as *mut _);));
+            }
+            // the arena owner frees every node
+            Some(SelfLink::Arena) => return None,
+            None => {}
+        }
+        let Some(shape) = self.shape_of(mapping, u_ty) else {
+            // the closure stays owned by the idiomatic value; only the slot is released
+            if let Some((_, c_optional)) = bare_fn_of(u_ty) {
+                let (base, slot, trampoline) = self.callback_idents(mapping);
+                if !self.items.borrow().iter().any(|(name, _)| *name == base) {
+                    return None;
+                }
+                let installed = if c_optional {
+                    quote!(#place.is_some_and(|f| f as usize == #trampoline as usize))
+                } else {
+                    quote!(#place as usize == #trampoline as usize)
+                };
+                return Some(quote!(if #installed {
+                    #slot.with(|slot| slot.set(None));
+                }));
+            }
+            return release_inline_value(place, u_ty, target, 0);
+        };
+        let pointee = pointer_elem(u_ty)?;
+
+        match shape.kind {
+            PtrKind::CString => {
+                if type_last_ident(target).as_deref() != Some("String") {
+                    return None;
+                }
+                Some(quote!(if !#place.is_null() {
+                    drop(std::ffi::CString::from_raw(#place as *mut std::ffi::c_char));
+                }))
+            }
+            PtrKind::Slice => {
+                let elem = generic_arg_of(target, "Vec")?;
+                let len = match (&shape.len_from, shape.len_const) {
+                    (Some(len_from), _) => {
+                        let len_src = self.c_src(len_from);
+                        quote!((#len_src as usize))
+                    }
+                    (None, Some(len)) => {
+                        let len = syn::LitInt::new(&len.to_string(), Span::call_site());
+                        quote!(#len)
+                    }
+                    (None, None) => return None,
+                };
+                let slice =
+                    quote!(std::ptr::slice_from_raw_parts_mut(#place as *mut #pointee, #len));
+                let release = if same_type(pointee, elem)
+                    || (is_scalar_type(pointee) && is_scalar_type(elem))
+                {
+                    quote!(drop(Box::from_raw(#slice));)
+                } else if pointer_elem(pointee).is_some() {
+                    match self.release_row(&shape, pointee, elem) {
+                        Some(row) => quote! {
+                            let elements = Box::from_raw(#slice);
+                            for p in elements.iter() {
+                                #row
+                            }
+                        },
+                        // rows of unknown length can only be leaked
+                        None => quote!(drop(Box::from_raw(#slice));),
+                    }
+                } else {
+                    let free = free_ident(pointee, "_fields")?;
+                    quote! {
+                        let mut elements = Box::from_raw(#slice);
+                        for x in elements.iter_mut() {
+                            #free(x);
+                        }
+                    }
+                };
+                Some(quote!(if !#place.is_null() && #len > 0 {
+                    #release
+                }))
+            }
+            PtrKind::Ref => {
+                let inner = generic_arg_of(target, "Box").unwrap_or(target);
+                if is_scalar_type(pointee) && is_scalar_type(inner) {
+                    Some(quote!(if !#place.is_null() {
+                        drop(Box::from_raw(#place as *mut #pointee));
+                    }))
+                } else {
+                    let free = free_ident(pointee, "")?;
+                    Some(quote!(#free(#place as *mut #pointee);))
+                }
+            }
+        }
+    }
+
+    fn release_fields(&self) -> Vec<proc_macro2::TokenStream> {
+        self.c_fields
+            .iter()
+            .filter_map(|(u_ident, u_ty)| {
+                let mapping = self
+                    .mappings
+                    .iter()
+                    .find(|m| u_ident == &m.u_name && !m.i_name.ends_with(".len"))?;
+                let i_ty = self.i_field_type(&mapping.i_name)?;
+                self.release_value(mapping, u_ty, i_ty, quote!(c_struct.#u_ident))
+            })
+            .collect()
+    }
+
+    /// Releases the payload of whichever variant the tag selects.
+    fn release_enum(
+        &self,
+        i_enum: &syn::ItemEnum,
+        variants: &[VariantMapping],
+    ) -> Vec<proc_macro2::TokenStream> {
+        let mut conditions = Vec::new();
+        let mut bodies = Vec::new();
+        for mapping in variants {
+            let Some(variant) = i_enum.variants.iter().find(|v| v.ident == mapping.name) else {
+                continue;
+            };
+            let bindings = payload_bindings(variant);
+            let releases: Vec<proc_macro2::TokenStream> = mapping
+                .payload
+                .iter()
+                .filter_map(|field| {
+                    let (_, _, i_ty) = bindings.iter().find(|(key, _, _)| *key == field.i_name)?;
+                    let u_ty = self.c_field_type(&field.u_name)?;
+                    self.release_value(field, u_ty, i_ty, self.c_src(&field.u_name))
+                })
+                .collect();
+            if !releases.is_empty() {
+                conditions.push(self.tag_condition(mapping));
+                bodies.push(quote!(#(#releases)*));
+            }
+        }
+        if conditions.is_empty() {
+            return Vec::new();
+        }
+        vec![quote!(#(if #conditions { #bodies })else*)]
+    }
+
+    /// `if tag == A { Variant(..) } else if ... else { panic!() }` building the
+    /// idiomatic enum from the active union member.
+    fn c_to_i_enum(
+        &self,
+        i_enum: &syn::ItemEnum,
+        variants: &[VariantMapping],
+    ) -> proc_macro2::TokenStream {
+        let enum_ident = &i_enum.ident;
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        for mapping in variants {
+            let Some(variant) = i_enum.variants.iter().find(|v| v.ident == mapping.name) else {
+                continue;
+            };
+            conditions.push(self.tag_condition(mapping));
+
+            let payload_value = |key: &str, ty: &syn::Type| match mapping
+                .payload
+                .iter()
+                .find(|m| m.i_name == key)
+            {
+                Some(field) => self.c_to_i_value(field, ty),
+                None => todo_marker(format!(
+                    "no payload mapping for '{}' of variant '{}'",
+                    key, mapping.name
+                )),
+            };
+            let variant_ident = &variant.ident;
+            values.push(match &variant.fields {
+                syn::Fields::Unit => quote!(#enum_ident::#variant_ident),
+                syn::Fields::Unnamed(fields) => {
+                    let args = fields
+                        .unnamed
+                        .iter()
+                        .enumerate()
+                        .map(|(i, field)| payload_value(&i.to_string(), &field.ty));
+                    quote!(#enum_ident::#variant_ident(#(#args),*))
+                }
+                syn::Fields::Named(fields) => {
+                    let inits = fields.named.iter().filter_map(|field| {
+                        let ident = field.ident.as_ref()?;
+                        let value = payload_value(&ident.to_string(), &field.ty);
+                        Some(quote!(#ident: #value))
+                    });
+                    quote!(#enum_ident::#variant_ident { #(#inits),* })
+                }
+            });
+        }
+        quote! {
+            #(if #conditions { #values } else)* {
+                panic!("unsupported tag value")
+            }
+        }
+    }
+
+    /// `match idiom_struct { Variant(v0) => CStruct { tag, union member, .. } }`,
+    /// zeroing the C fields that the active variant does not use.
+    fn i_to_c_enum(
+        &self,
+        i_enum: &syn::ItemEnum,
+        c_ident: &syn::Ident,
+        variants: &[VariantMapping],
+    ) -> proc_macro2::TokenStream {
+        let enum_ident = &i_enum.ident;
+        let mut arms = Vec::new();
+        for mapping in variants {
+            let Some(variant) = i_enum.variants.iter().find(|v| v.ident == mapping.name) else {
+                continue;
+            };
+            let bindings = payload_bindings(variant);
+            let variant_ident = &variant.ident;
+            let binding_idents = bindings.iter().map(|(_, binding, _)| binding);
+            let pattern = match &variant.fields {
+                syn::Fields::Unit => quote!(#enum_ident::#variant_ident),
+                syn::Fields::Unnamed(_) => {
+                    quote!(#enum_ident::#variant_ident(#(#binding_idents),*))
+                }
+                syn::Fields::Named(_) => {
+                    quote!(#enum_ident::#variant_ident { #(#binding_idents),* })
+                }
+            };
+            let payload_src = |field: &FieldMapping| {
+                bindings
+                    .iter()
+                    .find(|(key, _, _)| *key == field.i_name)
+                    .map(|(_, binding, ty)| (quote!((*#binding)), ty))
+            };
+
+            let mut lets = Vec::new();
+            for (u_ident, u_ty) in self.c_fields.iter() {
+                let binding = syn::Ident::new(&format!("_{}", u_ident), Span::call_site());
+                let u_name = u_ident.to_string();
+                let member_prefix = format!("{}.", u_name);
+                let value = if mapping.tag == u_name {
+                    let equals = &mapping.equals;
+                    quote!(#equals as #u_ty)
+                } else if let Some(field) = mapping.payload.iter().find(|m| m.u_name == u_name) {
+                    match payload_src(field) {
+                        Some((src, i_ty)) => self.i_to_c_convert(field, u_ty, i_ty, src),
+                        None => todo_marker(format!("unknown payload field '{}'", field.i_name)),
+                    }
+                } else {
+                    let members: Vec<proc_macro2::TokenStream> = mapping
+                        .payload
+                        .iter()
+                        .filter_map(|field| {
+                            let member = field.u_name.strip_prefix(&member_prefix)?;
+                            let member_ty = self.c_field_type(&field.u_name);
+                            let value = match (payload_src(field), member_ty) {
+                                (Some((src, i_ty)), Some(member_ty)) => {
+                                    self.i_to_c_convert(field, member_ty, i_ty, src)
+                                }
+                                _ => {
+                                    todo_marker(format!("unknown payload field '{}'", field.u_name))
+                                }
+                            };
+                            let path = member
+                                .split('.')
+                                .map(|segment| syn::Ident::new(segment, Span::call_site()));
+                            let value = unparenthesized(value);
+                            Some(quote!(tmp #(.#path)* = #value;))
+                        })
+                        .collect();
+                    if members.is_empty() {
+                        quote!(core::mem::zeroed())
+                    } else {
+                        quote!({
+                            let mut tmp: #u_ty = core::mem::zeroed();
+                            #(#members)*
+                            tmp
+                        })
+                    }
+                };
+                let value = unparenthesized(value);
+                lets.push(quote!(let #binding: #u_ty = #value;));
+            }
+            let c_inits = self.c_fields.iter().map(|(u_ident, _)| {
+                let binding = syn::Ident::new(&format!("_{}", u_ident), Span::call_site());
+                quote!(#u_ident: #binding)
+            });
+            arms.push(quote! {
+                #pattern => {
+                    #(#lets)*
+                    #c_ident { #(#c_inits),* }
+                }
+            });
+        }
+        let covered = variants
+            .iter()
+            .filter(|m| i_enum.variants.iter().any(|v| v.ident == m.name))
+            .count();
+        if covered < i_enum.variants.len() {
+            arms.push(quote!(_ => panic!("unsupported variant")));
+        }
+        quote!(match idiom_struct { #(#arms)* })
+    }
+}
+
+/// Drops the outer parentheses left by substituting `(*binding)` into a
+/// plain copy, which would otherwise trip `unused_parens`.
+fn unparenthesized(value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match syn::parse2::<syn::Expr>(value.clone()) {
+        Ok(expr) => {
+            let inner = strip_parens(&expr);
+            quote!(#inner)
+        }
+        Err(_) => value,
+    }
+}
+
+/// Generates the `C{S}_to_{S}_mut` / `{S}_to_C{S}_mut` converter pair between a
+/// `#[repr(C)]` struct and its idiomatic counterpart. `field_mapping_json` uses
+/// the struct spec format (`{"fields": [{"u_field": ..., "i_field": ...}]}`).
+/// When the idiomatic definition is an enum, the spec's `variants` map tag
+/// values of a C tagged union to enum variants; unions or structs used by
+/// dotted payload paths such as `data.code` are read from `c_struct_def`.
+/// Shapes without an automatic rule become `todo!("TODO: ...")` markers.
+/// Alongside the converters it emits `free_C{S}` (and `free_C{S}_fields`) to
+/// release what `{S}_to_C{S}_mut` allocated, `free_{S}` for the leaked
+/// idiomatic value, and a `C{S}Guard` that frees the C struct on drop.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_struct_converters(
+    c_struct_def: &str,
+    idiomatic_struct_def: &str,
+    field_mapping_json: &str,
+) -> PyResult<String> {
+    let c_ast = parse_src(c_struct_def)?;
+    let c_struct = c_ast
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Struct(item_struct) => Some(item_struct.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err("No struct found in the C struct definition")
+        })?;
+    let i_item = parse_src(idiomatic_struct_def)?
+        .items
+        .into_iter()
+        .find(|item| matches!(item, syn::Item::Struct(_) | syn::Item::Enum(_)))
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(
+                "No struct or enum found in the idiomatic definition",
+            )
+        })?;
+    let mappings = parse_field_mappings(field_mapping_json)?;
+
+    let i_ident = match &i_item {
+        syn::Item::Struct(i_struct) => &i_struct.ident,
+        syn::Item::Enum(i_enum) => &i_enum.ident,
+        _ => unreachable!(),
+    };
+    let generator = ConverterGen {
+        c_fields: named_struct_fields(&c_struct),
+        c_records: collect_record_fields(&c_ast.items),
+        i_fields: match &i_item {
+            syn::Item::Struct(i_struct) => named_struct_fields(i_struct),
+            _ => Vec::new(),
+        },
+        mappings: &mappings,
+        c_name: c_struct.ident.to_string(),
+        i_name: i_ident.to_string(),
+        items: RefCell::new(Vec::new()),
+    };
+    let c_ident = &c_struct.ident;
+    let c_to_i = syn::Ident::new(
+        &format!("{}_to_{}_mut", c_ident, i_ident),
+        Span::call_site(),
+    );
+    let i_to_c = syn::Ident::new(
+        &format!("{}_to_{}_mut", i_ident, c_ident),
+        Span::call_site(),
+    );
+
+    let (c_to_i_body, i_to_c_body, releases) = match &i_item {
+        syn::Item::Enum(i_enum) => {
+            let variants = parse_variant_mappings(field_mapping_json)?;
+            if variants.is_empty() {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Mapping to enum '{}' needs a 'variants' list",
+                    i_ident
+                )));
+            }
+            let c_to_i_value = generator.c_to_i_enum(i_enum, &variants);
+            let i_to_c_value = generator.i_to_c_enum(i_enum, c_ident, &variants);
+            (
+                quote!(let idiom_struct = #c_to_i_value;),
+                quote!(let c_struct = #i_to_c_value;),
+                generator.release_enum(i_enum, &variants),
+            )
+        }
+        _ => {
+            let i_inits = generator.c_to_i_inits();
+            let c_lets = generator.i_to_c_lets();
+            let c_inits = generator.c_fields.iter().map(|(u_ident, _)| {
+                let binding = syn::Ident::new(&format!("_{}", u_ident), Span::call_site());
+                quote!(#u_ident: #binding)
+            });
+            (
+                quote! {
+                    let idiom_struct = #i_ident {
+                        #(#i_inits),*
+                    };
+                },
+                quote! {
+                    #(#c_lets)*
+                    let c_struct = #c_ident {
+                        #(#c_inits),*
+                    };
+                },
+                generator.release_fields(),
+            )
+        }
+    };
+
+    let free_c = syn::Ident::new(&format!("free_{}", c_ident), Span::call_site());
+    let free_c_fields = syn::Ident::new(&format!("free_{}_fields", c_ident), Span::call_site());
+    let free_i = syn::Ident::new(&format!("free_{}", i_ident), Span::call_site());
+    let guard = syn::Ident::new(&format!("{}Guard", c_ident), Span::call_site());
+    let fields_param = if releases.is_empty() {
+        quote!(_c_struct)
+    } else {
+        quote!(c_struct)
+    };
+
+    let link_scope = generator.link_scope();
+    // a cycle back to the outermost node must not free it twice
+    let mark_freed = if link_scope.is_empty() {
+        quote!()
+    } else {
+        let freed = generator.link_static("_FREED");
+        quote!(#freed.with(|links| links.borrow_mut().insert(input as usize));)
+    };
+    let items = generator.items.borrow();
+    let items = items.iter().map(|(_, item)| item);
+
+    let tokens = quote! {
+        #(#items)*
+
+        unsafe fn #c_to_i(input: *mut #c_ident) -> &'static mut #i_ident {
+            assert!(!input.is_null());
+            #link_scope
+            let c_struct = &*input;
+            #c_to_i_body
+            Box::leak(Box::new(idiom_struct))
+        }
+
+        unsafe fn #i_to_c(idiom_struct: &mut #i_ident) -> *mut #c_ident {
+            #link_scope
+            #i_to_c_body
+            Box::into_raw(Box::new(c_struct))
+        }
+
+        unsafe fn #free_c_fields(#fields_param: &mut #c_ident) {
+            #(#releases)*
+        }
+
+        unsafe fn #free_c(input: *mut #c_ident) {
+            if input.is_null() {
+                return;
+            }
+            #link_scope
+            #mark_freed
+            let mut c_struct = Box::from_raw(input);
+            #free_c_fields(&mut c_struct);
+        }
+
+        unsafe fn #free_i(idiom_struct: &'static mut #i_ident) {
+            drop(Box::from_raw(idiom_struct as *mut #i_ident));
+        }
+
+        struct #guard(*mut #c_ident);
+
+        impl #guard {
+            unsafe fn new(idiom_struct: &mut #i_ident) -> Self {
+                Self(#i_to_c(idiom_struct))
+            }
+
+            fn as_ptr(&self) -> *mut #c_ident {
+                self.0
+            }
+        }
+
+        impl Drop for #guard {
+            fn drop(&mut self) {
+                unsafe { #free_c(self.0) }
+            }
+        }
+    };
+    let file: syn::File = syn::parse2(tokens).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!("Failed to build converters: {:?}", e))
+    })?;
+    Ok(unparse(&file))
+}
+
+/// Compile-time checks that `#[repr(C)]` mirrors match the size and alignment
+/// clang computed for the C types. `layouts_json` maps each type name to
+/// `{"size": N, "align": M}` in bytes.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_layout_asserts(
+    code: &str,
+    struct_names: Vec<String>,
+    layouts_json: &str,
+) -> PyResult<String> {
+    let ast = parse_src(code)?;
+    let layouts = parse_json(layouts_json, "layouts")?;
+    let mut defined = Vec::new();
+    collect_struct_enum_union(&ast.items, &mut defined);
+
+    // prettyplease leaves macro arguments unformatted, so write the items directly
+    let mut asserts = String::new();
+    for name in &struct_names {
+        if !defined
+            .iter()
+            .any(|(defined_name, kind)| defined_name == name && kind != "enum")
+        {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Struct or union '{}' not found",
+                name
+            )));
+        }
+        let layout_field = |key: &str| {
+            layouts
+                .get(name)
+                .and_then(|layout| layout.get(key))
+                .and_then(|value| value.as_u64())
+                .ok_or_else(|| {
+                    pyo3::exceptions::PyValueError::new_err(format!(
+                        "Missing '{}' in the layout of '{}'",
+                        key, name
+                    ))
+                })
+        };
+        asserts.push_str(&format!(
+            "const _: () = assert!(\n    core::mem::size_of::<{0}>() == {1} && core::mem::align_of::<{0}>() == {2}\n);\n",
+            name,
+            layout_field("size")?,
+            layout_field("align")?
+        ));
+    }
+
+    Ok(asserts)
+}
+/// Builds C-style zero values (`memset(0)`) for the types of one file.
+struct ZeroInit<'a> {
+    items: HashMap<String, &'a syn::Item>,
+    aliases: HashMap<String, &'a syn::Type>,
+    defaults: HashSet<String>,
+    generics: HashSet<String>,
+    visiting: Vec<String>,
+}
+
+impl<'a> ZeroInit<'a> {
+    fn new(file: &'a syn::File) -> Self {
+        let mut items = HashMap::new();
+        let mut aliases = HashMap::new();
+        let mut defaults = HashSet::new();
+        for item in file.items.iter() {
+            match item {
+                syn::Item::Struct(s) => {
+                    if derived_traits(&s.attrs)
+                        .iter()
+                        .any(|name| name == "Default")
+                    {
+                        defaults.insert(s.ident.to_string());
+                    }
+                    items.insert(s.ident.to_string(), item);
+                }
+                syn::Item::Enum(e) => {
+                    if derived_traits(&e.attrs)
+                        .iter()
+                        .any(|name| name == "Default")
+                    {
+                        defaults.insert(e.ident.to_string());
+                    }
+                    items.insert(e.ident.to_string(), item);
+                }
+                syn::Item::Union(u) => {
+                    items.insert(u.ident.to_string(), item);
+                }
+                syn::Item::Type(alias) => {
+                    aliases.insert(alias.ident.to_string(), &*alias.ty);
+                }
+                syn::Item::Impl(imp) => {
+                    let is_default = imp.trait_.as_ref().is_some_and(|(_, path, _)| {
+                        path.segments
+                            .last()
+                            .is_some_and(|last| last.ident == "Default")
+                    });
+                    if let (true, Some(name)) = (is_default, type_last_ident(&imp.self_ty)) {
+                        defaults.insert(name);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            items,
+            aliases,
+            defaults,
+            generics: HashSet::new(),
+            visiting: Vec::new(),
+        }
+    }
+
+    fn unsupported(ty: &syn::Type) -> PyErr {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "No zero value for type '{}'",
+            normalize_token_string(&ty.to_token_stream().to_string())
+        ))
+    }
+
+    /// Whether `[value; N]` is allowed, i.e. the element type is `Copy`.
+    fn is_copy_zero(&self, ty: &syn::Type) -> bool {
+        match ty {
+            syn::Type::Ptr(_) => true,
+            syn::Type::Array(array) => self.is_copy_zero(&array.elem),
+            syn::Type::Path(type_path) if type_path.path.segments.len() == 1 => {
+                let name = type_path.path.segments[0].ident.to_string();
+                match self.aliases.get(&name) {
+                    Some(aliased) => self.is_copy_zero(aliased),
+                    None => is_scalar_type(ty),
+                }
+            }
+            _ => is_scalar_type(ty),
+        }
+    }
+
+    fn zero_expr(&mut self, ty: &syn::Type) -> PyResult<syn::Expr> {
+        match ty {
+            syn::Type::Paren(paren) => self.zero_expr(&paren.elem),
+            syn::Type::Group(group) => self.zero_expr(&group.elem),
+            syn::Type::Ptr(ptr) => Ok(if ptr.mutability.is_some() {
+                parse_quote!(std::ptr::null_mut())
+            } else {
+                parse_quote!(std::ptr::null())
+            }),
+            syn::Type::Array(array) => {
+                let elem = self.zero_expr(&array.elem)?;
+                let len = &array.len;
+                Ok(if self.is_copy_zero(&array.elem) {
+                    parse_quote!([#elem; #len])
+                } else {
+                    parse_quote!(std::array::from_fn(|_| #elem))
+                })
+            }
+            syn::Type::Tuple(tuple) => {
+                let elems = tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.zero_expr(elem))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(parse_quote!((#(#elems,)*)))
+            }
+            syn::Type::Path(type_path) => {
+                let Some(segment) = type_path.path.segments.last() else {
+                    return Err(Self::unsupported(ty));
+                };
+                let name = segment.ident.to_string();
+                if type_path.qself.is_none() && type_path.path.segments.len() == 1 {
+                    if self.generics.contains(&name) || self.defaults.contains(&name) {
+                        return Ok(parse_quote!(Default::default()));
+                    }
+                    if let Some(aliased) = self.aliases.get(&name).copied() {
+                        return self.zero_expr(aliased);
+                    }
+                    if let Some(item) = self.items.get(&name).copied() {
+                        return self.zero_item(item);
+                    }
+                }
+                let scalar = map_libc_scalar(&name)
+                    .map(str::to_string)
+                    .or_else(|| is_scalar_type(ty).then(|| name.clone()));
+                if let Some(scalar) = scalar {
+                    return Ok(match scalar.as_str() {
+                        "f32" | "f64" => parse_quote!(0.0),
+                        "bool" => parse_quote!(false),
+                        "char" => parse_quote!('\0'),
+                        _ => parse_quote!(0),
+                    });
+                }
+                match name.as_str() {
+                    "Option" => Ok(parse_quote!(None)),
+                    "String" => Ok(parse_quote!(String::new())),
+                    "Vec" => Ok(parse_quote!(Vec::new())),
+                    "PhantomData" => Ok(parse_quote!(std::marker::PhantomData)),
+                    "Box" => match DeriveInference::generic_args(segment).first() {
+                        Some(inner @ syn::Type::Path(_)) => {
+                            let inner = self.zero_expr(inner)?;
+                            Ok(parse_quote!(Box::new(#inner)))
+                        }
+                        _ => Ok(parse_quote!(Default::default())),
+                    },
+                    // std types and anything foreign are trusted to be `Default`
+                    _ => Ok(parse_quote!(Default::default())),
+                }
+            }
+            _ => Err(Self::unsupported(ty)),
+        }
+    }
+
+    /// Struct literal (or first variant / first union field) with every field
+    /// zeroed.
+    fn zero_item(&mut self, item: &'a syn::Item) -> PyResult<syn::Expr> {
+        let name = crate::item_name(item).unwrap_or_default();
+        if self.visiting.contains(&name) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "'{}' contains itself without a nullable indirection",
+                name
+            )));
+        }
+        self.visiting.push(name.clone());
+        let result = match item {
+            syn::Item::Struct(s) => {
+                let ident = &s.ident;
+                self.zero_fields(parse_quote!(#ident), &s.fields)
+            }
+            syn::Item::Union(u) => {
+                let ident = &u.ident;
+                let field = u.fields.named.first().ok_or_else(|| {
+                    pyo3::exceptions::PyValueError::new_err(format!(
+                        "Union '{}' has no fields",
+                        ident
+                    ))
+                })?;
+                let field_ident = &field.ident;
+                let value = self.zero_expr(&field.ty)?;
+                Ok(parse_quote!(#ident { #field_ident: #value }))
+            }
+            syn::Item::Enum(e) => {
+                let ident = &e.ident;
+                let variant = e
+                    .variants
+                    .iter()
+                    .find(|variant| {
+                        variant.discriminant.as_ref().is_some_and(|(_, expr)| {
+                            matches!(expr, syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. })
+                                if lit.base10_digits() == "0")
+                        })
+                    })
+                    .or_else(|| e.variants.first())
+                    .ok_or_else(|| {
+                        pyo3::exceptions::PyValueError::new_err(format!("Enum '{}' has no variants", ident))
+                    })?;
+                let variant_ident = &variant.ident;
+                self.zero_fields(parse_quote!(#ident::#variant_ident), &variant.fields)
+            }
+            _ => Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "'{}' is not a struct, enum or union",
+                name
+            ))),
+        };
+        self.visiting.pop();
+        result
+    }
+
+    fn zero_fields(&mut self, path: syn::Path, fields: &syn::Fields) -> PyResult<syn::Expr> {
+        match fields {
+            syn::Fields::Named(named) => {
+                let mut values: Vec<syn::FieldValue> = Vec::new();
+                for field in named.named.iter() {
+                    let ident = &field.ident;
+                    let value = self.zero_expr(&field.ty)?;
+                    values.push(parse_quote!(#ident: #value));
+                }
+                Ok(parse_quote!(#path { #(#values),* }))
+            }
+            syn::Fields::Unnamed(unnamed) => {
+                let values = unnamed
+                    .unnamed
+                    .iter()
+                    .map(|field| self.zero_expr(&field.ty))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(parse_quote!(#path(#(#values),*)))
+            }
+            syn::Fields::Unit => Ok(parse_quote!(#path)),
+        }
+    }
+}
+
+/// Emits `impl Default for struct_name` whose value matches a zeroed C
+/// struct: numbers are `0`, pointers null, `Option`s `None`, strings and
+/// vectors empty. Nested structs defined in `code` are zeroed field by field
+/// unless they already implement `Default`; an enum takes its variant with
+/// discriminant `0` or else its first variant.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_default_impl(code: &str, struct_name: &str) -> PyResult<String> {
+    let ast = parse_src(code)?;
+    let mut zero = ZeroInit::new(&ast);
+    let item = zero
+        .items
+        .get(struct_name)
+        .copied()
+        .filter(|item| !matches!(item, syn::Item::Enum(_)))
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!(
+                "Struct or union '{}' not found",
+                struct_name
+            ))
+        })?;
+    let mut generics = match item {
+        syn::Item::Struct(s) => s.generics.clone(),
+        syn::Item::Union(u) => u.generics.clone(),
+        _ => syn::Generics::default(),
+    };
+    zero.generics = generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect();
+    zero.defaults.remove(struct_name);
+    let value = zero.zero_item(item)?;
+
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(Default));
+    }
+    let ident = syn::Ident::new(struct_name, Span::call_site());
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let file: syn::File = parse_quote! {
+        impl #impl_generics Default for #ident #ty_generics #where_clause {
+            fn default() -> Self {
+                #value
+            }
+        }
+    };
+    Ok(unparse(&file))
+}
+
+/// Whether `ty` is an integer usable as an element count.
+fn is_integer_type(ty: &syn::Type) -> bool {
+    type_last_ident(ty).is_some_and(|name| {
+        let primitive = map_libc_scalar(&name)
+            .map(str::to_string)
+            .or_else(|| expected_stdint_target(&name).map(str::to_string))
+            .unwrap_or(name);
+        is_numeric_primitive(&primitive) && !primitive.starts_with('f')
+    })
+}
+
+/// Whether `ty` is an unsigned integer, e.g. `usize` or `libc::c_uint`.
+fn is_unsigned_integer_type(ty: &syn::Type) -> bool {
+    type_last_ident(ty).is_some_and(|name| {
+        let primitive = map_libc_scalar(&name)
+            .or_else(|| expected_stdint_target(&name))
+            .map(str::to_string)
+            .unwrap_or(name);
+        primitive.starts_with('u') && is_numeric_primitive(&primitive)
+    })
+}
+
+fn is_c_char_pointer(ty: &syn::Type) -> bool {
+    pointer_elem(ty).is_some_and(|elem| type_last_ident(elem).is_some_and(|name| name == "c_char"))
+}
+
+/// Index of the sequence field whose element count `len_name` holds, e.g.
+/// `items_len`, `n_items`, `item_count`, or a bare `len` when the struct has
+/// a single sequence.
+fn length_field_target(len_name: &str, sequences: &[(usize, String)]) -> Option<usize> {
+    let found = sequences.iter().find(|(_, seq)| {
+        let singular = seq.strip_suffix('s').unwrap_or(seq);
+        [seq.as_str(), singular].iter().any(|base| {
+            [
+                format!("{base}_len"),
+                format!("{base}_length"),
+                format!("{base}_count"),
+                format!("{base}_size"),
+                format!("{base}_num"),
+                format!("n_{base}"),
+                format!("num_{base}"),
+                format!("n{base}"),
+            ]
+            .iter()
+            .any(|candidate| candidate == len_name)
+        })
+    });
+    match (found, sequences) {
+        (Some((index, _)), _) => Some(*index),
+        (None, [(index, _)])
+            if matches!(len_name, "len" | "length" | "count" | "size" | "n" | "num") =>
+        {
+            Some(*index)
+        }
+        _ => None,
+    }
+}
+
+/// Expression drawing a value of `ty` from `u` when the plain
+/// `u.arbitrary()?` would break a C invariant: strings never contain NUL and
+/// C strings are never null. `None` means `u.arbitrary()?` is fine.
+fn arbitrary_expr(ty: &syn::Type, u: &syn::Ident) -> PyResult<Option<syn::Expr>> {
+    let unsupported = |reason: &str| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Type '{}' {}",
+            normalize_token_string(&ty.to_token_stream().to_string()),
+            reason
+        ))
+    };
+    match ty {
+        syn::Type::Paren(paren) => arbitrary_expr(&paren.elem, u),
+        syn::Type::Group(group) => arbitrary_expr(&group.elem, u),
+        syn::Type::Reference(_) => Err(unsupported("borrows data and cannot be generated")),
+        syn::Type::BareFn(_) => Err(unsupported("is a function pointer and cannot be generated")),
+        syn::Type::Ptr(ptr) => Ok(Some(if is_c_char_pointer(ty) {
+            let string = arbitrary_string(u);
+            let cast = ptr.const_token.map(|_| quote!(as *const _));
+            parse_quote!(std::ffi::CString::new(#string).unwrap_or_default().into_raw() #cast)
+        } else if ptr.mutability.is_some() {
+            parse_quote!(std::ptr::null_mut())
+        } else {
+            parse_quote!(std::ptr::null())
+        })),
+        syn::Type::Tuple(tuple) => {
+            let elems = tuple
+                .elems
+                .iter()
+                .map(|elem| arbitrary_expr(elem, u))
+                .collect::<PyResult<Vec<_>>>()?;
+            if elems.iter().all(Option::is_none) {
+                return Ok(None);
+            }
+            let elems = elems
+                .into_iter()
+                .map(|elem| elem.unwrap_or_else(|| parse_quote!(#u.arbitrary()?)));
+            Ok(Some(parse_quote!((#(#elems,)*))))
+        }
+        syn::Type::Path(type_path) => {
+            let Some(segment) = type_path.path.segments.last() else {
+                return Ok(None);
+            };
+            let inner = DeriveInference::generic_args(segment).first().copied();
+            match (segment.ident.to_string().as_str(), inner) {
+                ("String", _) => Ok(Some(arbitrary_string(u))),
+                ("Option", Some(syn::Type::BareFn(_))) => Ok(Some(parse_quote!(None))),
+                ("Option", Some(inner)) => Ok(arbitrary_expr(inner, u)?.map(
+                    |value| parse_quote!(if #u.arbitrary::<bool>()? { Some(#value) } else { None }),
+                )),
+                ("Box", Some(inner)) => {
+                    Ok(arbitrary_expr(inner, u)?.map(|value| parse_quote!(Box::new(#value))))
+                }
+                ("Vec", Some(inner)) => Ok(arbitrary_expr(inner, u)?.map(|value| {
+                    parse_quote! {
+                        (0..#u.arbitrary_len::<u8>()?)
+                            .map(|_| Ok(#value))
+                            .collect::<arbitrary::Result<Vec<_>>>()?
+                    }
+                })),
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+fn arbitrary_string(u: &syn::Ident) -> syn::Expr {
+    parse_quote!(#u.arbitrary::<String>()?.replace('\0', ""))
+}
+
+/// Emits `impl arbitrary::Arbitrary for struct_name` for property-based and
+/// fuzz testing. Strings never contain NUL, C string pointers are never null,
+/// and an integer field named after a `Vec`, `String` or pointer field (e.g.
+/// `items_len`, `n_items`, or `len` beside a single sequence) always holds
+/// that sequence's length; such a pointer points to a leaked buffer of that
+/// length. Other raw pointers are null.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_arbitrary_impl(code: &str, struct_name: &str) -> PyResult<String> {
+    let ast = parse_src(code)?;
+    let item = ast
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Struct(s) if s.ident == struct_name => Some(s),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!("Struct '{}' not found", struct_name))
+        })?;
+
+    let names: Vec<syn::Ident> =
+        item.fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                field.ident.clone().unwrap_or_else(|| {
+                    syn::Ident::new(&format!("field{}", index), Span::call_site())
+                })
+            })
+            .collect();
+    let u_name = if names.iter().any(|name| name == "u") {
+        "unstructured"
+    } else {
+        "u"
+    };
+    let u = syn::Ident::new(u_name, Span::call_site());
+
+    let fields: Vec<&syn::Field> = item.fields.iter().collect();
+    // `void *` and pointer-to-pointer fields have no element type to fill
+    let is_opaque_pointer = |ty: &syn::Type| {
+        is_void_pointer(ty)
+            || pointer_elem(ty).is_some_and(|elem| matches!(elem, syn::Type::Ptr(_)))
+    };
+    let sequences: Vec<(usize, String)> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| {
+            generic_arg_of(&field.ty, "Vec").is_some()
+                || type_last_ident(&field.ty).is_some_and(|name| name == "String")
+                || (pointer_elem(&field.ty).is_some() && !is_opaque_pointer(&field.ty))
+        })
+        .map(|(index, _)| (index, names[index].to_string()))
+        .collect();
+    let lengths: HashMap<usize, usize> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| is_integer_type(&field.ty))
+        .filter_map(|(index, _)| {
+            length_field_target(&names[index].to_string(), &sequences).map(|target| (index, target))
+        })
+        .collect();
+    let measured: HashSet<usize> = lengths.values().copied().collect();
+
+    let mut draws: Vec<syn::Stmt> = Vec::new();
+    let mut measures: Vec<syn::Stmt> = Vec::new();
+    let mut conversions: Vec<syn::Stmt> = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let name = &names[index];
+        let ty = &field.ty;
+        if let Some(target) = lengths.get(&index) {
+            let sequence = &names[*target];
+            measures.push(parse_quote!(let #name = #sequence.len() as #ty;));
+            continue;
         }
-        if let syn::Item::ExternCrate(_) = item {
-            // remove `extern crate`
-            *item = syn::Item::Verbatim(Default::default());
+        match (pointer_elem(ty), measured.contains(&index)) {
+            (Some(_), true) if is_c_char_pointer(ty) => {
+                let string = arbitrary_string(&u);
+                let cast = matches!(ty, syn::Type::Ptr(ptr) if ptr.const_token.is_some())
+                    .then(|| quote!(as *const _));
+                draws.push(parse_quote!(let #name: String = #string;));
+                conversions.push(parse_quote! {
+                    let #name = std::ffi::CString::new(#name).unwrap_or_default().into_raw() #cast;
+                });
+            }
+            (Some(elem), true) => {
+                let buffer = match arbitrary_expr(elem, &u)? {
+                    Some(value) => parse_quote! {
+                        (0..#u.arbitrary_len::<u8>()?)
+                            .map(|_| Ok(#value))
+                            .collect::<arbitrary::Result<Vec<_>>>()?
+                    },
+                    None => quote!(#u.arbitrary()?),
+                };
+                draws.push(parse_quote!(let #name: Vec<#elem> = #buffer;));
+                let leak = if matches!(ty, syn::Type::Ptr(ptr) if ptr.const_token.is_some()) {
+                    quote!(as_ptr)
+                } else {
+                    quote!(as_mut_ptr)
+                };
+                conversions.push(parse_quote! {
+                    let #name = Box::leak(#name.into_boxed_slice()).#leak();
+                });
+            }
+            _ => {
+                let value =
+                    arbitrary_expr(ty, &u)?.unwrap_or_else(|| parse_quote!(#u.arbitrary()?));
+                draws.push(parse_quote!(let #name: #ty = #value;));
+            }
         }
     }
+    let construct: syn::Expr = match &item.fields {
+        syn::Fields::Named(_) => parse_quote!(Self { #(#names),* }),
+        syn::Fields::Unnamed(_) => parse_quote!(Self(#(#names),*)),
+        syn::Fields::Unit => parse_quote!(Self),
+    };
 
-    normalize_stdint_aliases(&mut ast);
+    let mut generics = item.generics.clone();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(arbitrary::Arbitrary<'arbitrary>));
+    }
+    let ty_generics = item.generics.split_for_impl().1;
+    generics.params.insert(0, parse_quote!('arbitrary));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let ident = &item.ident;
+    let file: syn::File = parse_quote! {
+        impl #impl_generics arbitrary::Arbitrary<'arbitrary> for #ident #ty_generics #where_clause {
+            fn arbitrary(#u: &mut arbitrary::Unstructured<'arbitrary>) -> arbitrary::Result<Self> {
+                #(#draws)*
+                #(#measures)*
+                #(#conversions)*
+                Ok(#construct)
+            }
+        }
+    };
+    Ok(unparse(&file))
+}
 
-    Ok(prettyplease::unparse(&ast))
+/// How one parameter of the fuzzed function is drawn from `FuzzInput`,
+/// passed to the C reference and to the translation, and compared afterwards.
+#[derive(Default)]
+struct FuzzParam {
+    input: Option<syn::Type>,
+    setup: Vec<syn::Stmt>,
+    c_arg: Option<syn::Expr>,
+    rust_arg: Option<syn::Expr>,
+    checks: Vec<syn::Stmt>,
 }
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn unidiomatic_types_cleanup(code: &str) -> PyResult<String> {
-    let mut ast = parse_src(code)?;
+/// Fields of a local struct when all of them are scalars, which the fuzz
+/// target can fill from the input and compare one by one.
+fn scalar_struct_fields<'a>(
+    structs: &HashMap<String, &'a syn::ItemStruct>,
+    ty: &syn::Type,
+) -> Option<Vec<(&'a syn::Ident, &'a syn::Type)>> {
+    let item = structs.get(&type_last_ident(ty)?)?;
+    let fields: Vec<_> = item
+        .fields
+        .iter()
+        .map(|field| Some((field.ident.as_ref()?, &field.ty)))
+        .collect::<Option<_>>()?;
+    (!fields.is_empty() && fields.iter().all(|(_, ty)| is_scalar_type(ty))).then_some(fields)
+}
 
-    for item in ast.items.iter_mut() {
-        if let syn::Item::ExternCrate(_) = item {
-            // remove `extern crate`
-            *item = syn::Item::Verbatim(Default::default());
+/// `let <local>: Struct = zeroed(); <local>.f = input.<name>.0; ...`
+fn fuzz_struct_setup(
+    local: &syn::Ident,
+    ty: &syn::Type,
+    name: &syn::Ident,
+    fields: &[(&syn::Ident, &syn::Type)],
+) -> Vec<syn::Stmt> {
+    let mut stmts: Vec<syn::Stmt> =
+        vec![parse_quote!(let mut #local: #ty = unsafe { std::mem::zeroed() };)];
+    for (index, (field, _)) in fields.iter().enumerate() {
+        let index = syn::Index::from(index);
+        stmts.push(parse_quote!(#local.#field = input.#name.#index;));
+    }
+    stmts
+}
+
+fn fuzz_struct_checks(
+    what: &str,
+    c_value: &syn::Expr,
+    rust_value: &syn::Expr,
+    fields: &[(&syn::Ident, &syn::Type)],
+) -> Vec<syn::Stmt> {
+    fields
+        .iter()
+        .map(|(field, _)| {
+            let message = syn::LitStr::new(
+                &format!("{}.{} differs: C {{:?}}, Rust {{:?}}", what, field),
+                Span::call_site(),
+            );
+            parse_quote! {
+                assert!(
+                    fuzz_same(&#c_value.#field, &#rust_value.#field),
+                    #message, #c_value.#field, #rust_value.#field
+                );
+            }
+        })
+        .collect()
+}
+
+/// `<name>_c` / `<name>_rust`, the copies handed to each side.
+fn fuzz_local(name: &syn::Ident, suffix: &str) -> syn::Ident {
+    let base = name.to_string();
+    syn::Ident::new(
+        &format!("{}_{}", base.trim_end_matches('_'), suffix),
+        Span::call_site(),
+    )
+}
+
+fn fuzz_param(
+    name: &syn::Ident,
+    ty: &syn::Type,
+    structs: &HashMap<String, &syn::ItemStruct>,
+    function_name: &str,
+) -> PyResult<FuzzParam> {
+    let traits = analyze_type(ty);
+    let c_local = fuzz_local(name, "c");
+    let rust_local = fuzz_local(name, "rust");
+    let c_value: syn::Expr = parse_quote!(#c_local);
+    let rust_value: syn::Expr = parse_quote!(#rust_local);
+    if is_scalar_type(ty) {
+        return Ok(FuzzParam {
+            input: Some(ty.clone()),
+            c_arg: Some(parse_quote!(input.#name)),
+            rust_arg: Some(parse_quote!(input.#name)),
+            ..Default::default()
+        });
+    }
+    if let Some(fields) = scalar_struct_fields(structs, ty) {
+        let types = fields.iter().map(|(_, ty)| ty);
+        let mut setup = fuzz_struct_setup(&c_local, ty, name, &fields);
+        setup.extend(fuzz_struct_setup(&rust_local, ty, name, &fields));
+        return Ok(FuzzParam {
+            input: Some(parse_quote!((#(#types,)*))),
+            setup,
+            c_arg: Some(c_value),
+            rust_arg: Some(rust_value),
+            ..Default::default()
+        });
+    }
+    let unsupported = || {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Parameter '{}' of '{}' has type '{}', which the fuzz target cannot generate",
+            name, function_name, traits.normalized
+        ))
+    };
+    let elem = match pointer_elem(ty) {
+        Some(elem) if traits.pointer_depth == 1 && !is_void_pointer(ty) => elem,
+        _ => return Err(unsupported()),
+    };
+    let (ptr, cast) = if traits.pointer_is_mut {
+        (quote!(as_mut_ptr), quote!(as *mut _))
+    } else {
+        (quote!(as_ptr), quote!(as *const _))
+    };
+    let mut param = FuzzParam {
+        c_arg: Some(parse_quote!(#c_local.#ptr() #cast)),
+        rust_arg: Some(parse_quote!(#rust_local.#ptr() #cast)),
+        ..Default::default()
+    };
+    let mutability = traits.pointer_is_mut.then(|| quote!(mut));
+    if is_c_char_pointer(ty) {
+        param.input = Some(parse_quote!(String));
+        param.setup = vec![
+            parse_quote!(let mut #c_local: Vec<u8> = input.#name.replace('\0', "").into_bytes();),
+            parse_quote!(#c_local.push(0);),
+        ];
+    } else if is_scalar_type(elem) {
+        param.input = Some(parse_quote!(Vec<#elem>));
+        param.setup = vec![
+            parse_quote! {
+                if input.#name.is_empty() {
+                    return;
+                }
+            },
+            parse_quote!(let #mutability #c_local = input.#name.clone();),
+        ];
+    } else if let Some(fields) = scalar_struct_fields(structs, elem) {
+        let types = fields.iter().map(|(_, ty)| ty);
+        param.input = Some(parse_quote!((#(#types,)*)));
+        param.setup = fuzz_struct_setup(&c_local, elem, name, &fields);
+        param
+            .setup
+            .extend(fuzz_struct_setup(&rust_local, elem, name, &fields));
+        param.c_arg = Some(parse_quote!(&mut #c_local as *mut _));
+        param.rust_arg = Some(parse_quote!(&mut #rust_local as *mut _));
+        if traits.pointer_is_mut {
+            param.checks = fuzz_struct_checks(&name.to_string(), &c_value, &rust_value, &fields);
         }
+        return Ok(param);
+    } else {
+        return Err(unsupported());
+    }
+    param
+        .setup
+        .push(parse_quote!(let #mutability #rust_local = #c_local.clone();));
+    if traits.pointer_is_mut {
+        let message = syn::LitStr::new(
+            &format!("{} differs: C {{:?}}, Rust {{:?}}", name),
+            Span::call_site(),
+        );
+        param.checks.push(parse_quote! {
+            assert!(fuzz_same(&#c_local, &#rust_local), #message, #c_local, #rust_local);
+        });
     }
+    Ok(param)
+}
 
-    normalize_stdint_aliases(&mut ast);
+/// Emits a `cargo-fuzz` target for the exposed function `function_name` of
+/// `code`. The target holds the whole translation, declares the C reference
+/// as `<c_prefix><function_name>`, decodes the fuzz input into arguments
+/// (scalars, C strings, scalar buffers, and structs of scalars by value or
+/// pointer), calls both, and asserts equal results and equal buffers and
+/// structs behind mutable pointers. An integer parameter named after a pointer
+/// parameter (`items_len`, `n_items`, or `len` beside a single pointer) gets
+/// its length. Returns `{"code": ..., "renamed": {c_name: c_ref_name}}`, where
+/// `renamed` lists every C function the translation defines, which the C
+/// reference must be compiled under the prefixed name to avoid clashing.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_fuzz_target(
+    py: Python<'_>,
+    code: &str,
+    function_name: &str,
+    c_prefix: &str,
+) -> PyResult<PyObject> {
+    let (mut file, renamed) = differential_target(
+        code,
+        function_name,
+        c_prefix,
+        parse_quote!(#[derive(Debug, arbitrary::Arbitrary)]),
+    )?;
+    file.attrs.retain(|attr| !attr.path().is_ident("no_main"));
+    file.attrs.insert(0, parse_quote!(#![no_main]));
+    file.items
+        .push(parse_quote!(libfuzzer_sys::fuzz_target!(|input: FuzzInput| fuzz_one(input));));
 
-    Ok(prettyplease::unparse(&ast))
+    let result = PyDict::new(py);
+    result.set_item("code", unparse(&file))?;
+    result.set_item("renamed", renamed)?;
+    Ok(result.into())
 }
 
-const STDINT_ALIAS_TARGETS: &[(&str, &str)] = &[
-    ("int8_t", "i8"),
-    ("int16_t", "i16"),
-    ("int32_t", "i32"),
-    ("int64_t", "i64"),
-    ("uint8_t", "u8"),
-    ("uint16_t", "u16"),
-    ("uint32_t", "u32"),
-    ("uint64_t", "u64"),
-];
+/// Emits a standalone differential driver for the exposed function
+/// `function_name` of `code`, comparing it with the C reference
+/// `<c_prefix><function_name>` exactly like `generate_fuzz_target` does, but
+/// on fixed inputs: `main` reads a JSON list of argument objects keyed by
+/// parameter name (the embedded `samples.json` next to the crate manifest, or
+/// the file given as its first argument), runs every sample, reports each
+/// divergence with its index, and prints `{"total": .., "diverged": [..]}`.
+/// Returns the same `{"code", "renamed"}` dict as `generate_fuzz_target`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_differential_driver(
+    py: Python<'_>,
+    code: &str,
+    function_name: &str,
+    c_prefix: &str,
+) -> PyResult<PyObject> {
+    let (mut file, renamed) = differential_target(
+        code,
+        function_name,
+        c_prefix,
+        parse_quote!(#[derive(Debug, serde::Deserialize)]),
+    )?;
+    file.attrs.retain(|attr| !attr.path().is_ident("no_main"));
+    file.items.retain(|item| match item {
+        syn::Item::Fn(f) => f.sig.ident != "main",
+        _ => true,
+    });
+    file.items.push(parse_quote! {
+        fn main() {
+            let samples = match std::env::args().nth(1) {
+                Some(path) => std::fs::read_to_string(&path)
+                    .unwrap_or_else(|err| panic!("cannot read {}: {}", path, err)),
+                None => include_str!("../samples.json").to_string(),
+            };
+            let samples: Vec<FuzzInput> =
+                serde_json::from_str(&samples).expect("malformed samples");
+            let total = samples.len();
+            let mut diverged = Vec::new();
+            for (index, input) in samples.into_iter().enumerate() {
+                let shown = format!("{:?}", input);
+                if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| fuzz_one(input))).is_err() {
+                    eprintln!("sample {} diverged: {}", index, shown);
+                    diverged.push(index);
+                }
+            }
+            println!("{{\"total\": {}, \"diverged\": {:?}}}", total, diverged);
+            if !diverged.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    });
 
-fn expected_stdint_target(name: &str) -> Option<&'static str> {
-    STDINT_ALIAS_TARGETS
+    let result = PyDict::new(py);
+    result.set_item("code", unparse(&file))?;
+    result.set_item("renamed", renamed)?;
+    Ok(result.into())
+}
+
+/// The translation with the C reference declared, the input struct (derived
+/// with `input_derive`), and `fuzz_one` calling both sides and asserting they
+/// agree, shared by the fuzz target and the differential driver. Also returns
+/// the C functions the reference must be compiled under prefixed names.
+fn differential_target(
+    code: &str,
+    function_name: &str,
+    c_prefix: &str,
+    input_derive: syn::Attribute,
+) -> PyResult<(syn::File, BTreeMap<String, String>)> {
+    let ast = parse_src(code)?;
+    let func = ast
+        .items
         .iter()
-        .find(|(alias, _)| alias == &name)
-        .map(|(_, target)| *target)
+        .find_map(|item| match item {
+            syn::Item::Fn(f) if f.sig.ident == function_name && is_exposed_item(item) => Some(f),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            pyo3::exceptions::PyValueError::new_err(format!(
+                "Exposed function '{}' not found",
+                function_name
+            ))
+        })?;
+    let structs: HashMap<String, &syn::ItemStruct> = ast
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Struct(s) => Some((s.ident.to_string(), s)),
+            _ => None,
+        })
+        .collect();
+
+    let params: Vec<(syn::Ident, &syn::Type)> = func
+        .sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(ident) => Ok((ident.ident.clone(), &*pat_type.ty)),
+                _ => Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Parameters of '{}' must be plain identifiers",
+                    function_name
+                ))),
+            },
+            syn::FnArg::Receiver(_) => Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "'{}' cannot take self",
+                function_name
+            ))),
+        })
+        .collect::<PyResult<_>>()?;
+    let buffers: Vec<(usize, String)> = params
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, ty))| pointer_elem(ty).is_some() && !is_void_pointer(ty))
+        .map(|(index, (name, _))| (index, name.to_string()))
+        .collect();
+
+    let mut fuzz_params: Vec<FuzzParam> = Vec::new();
+    for (name, ty) in params.iter() {
+        let length_of = is_integer_type(ty)
+            .then(|| length_field_target(&name.to_string(), &buffers))
+            .flatten();
+        fuzz_params.push(match length_of {
+            Some(target) => {
+                let buffer = &params[target].0;
+                let length = |suffix: &str| -> syn::Expr {
+                    let local = fuzz_local(buffer, suffix);
+                    // C strings are measured without their terminator
+                    if is_c_char_pointer(params[target].1) {
+                        parse_quote!((#local.len() - 1) as #ty)
+                    } else {
+                        parse_quote!(#local.len() as #ty)
+                    }
+                };
+                FuzzParam {
+                    c_arg: Some(length("c")),
+                    rust_arg: Some(length("rust")),
+                    ..Default::default()
+                }
+            }
+            None => fuzz_param(name, ty, &structs, function_name)?,
+        });
+    }
+
+    let c_ident = syn::Ident::new(&format!("{}{}", c_prefix, function_name), Span::call_site());
+    let rust_ident = &func.sig.ident;
+    // foreign declarations take no `mut` bindings
+    let mut inputs = func.sig.inputs.clone();
+    for input in inputs.iter_mut() {
+        if let syn::FnArg::Typed(pat_type) = input {
+            if let syn::Pat::Ident(ident) = &mut *pat_type.pat {
+                ident.mutability = None;
+            }
+        }
+    }
+    let output = &func.sig.output;
+    let input_fields = params
+        .iter()
+        .zip(fuzz_params.iter())
+        .filter_map(|((name, _), param)| {
+            let ty = param.input.as_ref()?;
+            Some(quote!(#name: #ty))
+        });
+    let setup = fuzz_params.iter().flat_map(|param| param.setup.iter());
+    let c_args = fuzz_params.iter().filter_map(|param| param.c_arg.as_ref());
+    let rust_args = fuzz_params
+        .iter()
+        .filter_map(|param| param.rust_arg.as_ref());
+    let checks = fuzz_params.iter().flat_map(|param| param.checks.iter());
+    let mut rust_call: syn::Expr = parse_quote!(#rust_ident(#(#rust_args),*));
+    if func.sig.unsafety.is_some() {
+        rust_call = parse_quote!(unsafe { #rust_call });
+    }
+
+    let c_result: syn::Expr = parse_quote!(c_result);
+    let rust_result: syn::Expr = parse_quote!(rust_result);
+    let mut uses_c_string = false;
+    let result_checks: Vec<syn::Stmt> = match output {
+        syn::ReturnType::Type(_, ty) if is_scalar_type(ty) => vec![parse_quote! {
+            assert!(
+                fuzz_same(&c_result, &rust_result),
+                "result differs: C {:?}, Rust {:?}", c_result, rust_result
+            );
+        }],
+        syn::ReturnType::Type(_, ty) if is_c_char_pointer(ty) => {
+            uses_c_string = true;
+            vec![parse_quote! {
+                assert_eq!(
+                    unsafe { fuzz_c_string(c_result) },
+                    unsafe { fuzz_c_string(rust_result) },
+                    "result differs"
+                );
+            }]
+        }
+        syn::ReturnType::Type(_, ty) => match scalar_struct_fields(&structs, ty) {
+            Some(fields) => fuzz_struct_checks("result", &c_result, &rust_result, &fields),
+            // other pointers point to different allocations on each side
+            None => Vec::new(),
+        },
+        syn::ReturnType::Default => Vec::new(),
+    };
+    let c_call: syn::Expr = parse_quote!(unsafe { #c_ident(#(#c_args),*) });
+    let calls: Vec<syn::Stmt> = if result_checks.is_empty() {
+        vec![parse_quote!(#c_call;), parse_quote!(#rust_call;)]
+    } else {
+        vec![
+            parse_quote!(let c_result = #c_call;),
+            parse_quote!(let rust_result = #rust_call;),
+        ]
+    };
+
+    let mut file = ast.clone();
+    file.items.push(parse_quote! {
+        extern "C" {
+            fn #c_ident(#inputs) #output;
+        }
+    });
+    file.items.push(parse_quote! {
+        #input_derive
+        struct FuzzInput {
+            #(#input_fields,)*
+        }
+    });
+    file.items.push(parse_quote! {
+        /// Equal, or both NaN.
+        #[allow(clippy::eq_op)]
+        fn fuzz_same<T: PartialEq>(a: &T, b: &T) -> bool {
+            a == b || (a != a && b != b)
+        }
+    });
+    if uses_c_string {
+        file.items.push(parse_quote! {
+            unsafe fn fuzz_c_string(ptr: *const libc::c_char) -> Option<Vec<u8>> {
+                (!ptr.is_null()).then(|| std::ffi::CStr::from_ptr(ptr).to_bytes().to_vec())
+            }
+        });
+    }
+    file.items.push(parse_quote! {
+        fn fuzz_one(input: FuzzInput) {
+            #(#setup)*
+            #(#calls)*
+            #(#result_checks)*
+            #(#checks)*
+        }
+    });
+    let renamed: BTreeMap<String, String> = ast
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Fn(f) if f.sig.ident != "main" && is_exposed_item(item) => {
+                let name = f.sig.ident.to_string();
+                Some((name.clone(), format!("{}{}", c_prefix, name)))
+            }
+            _ => None,
+        })
+        .collect();
+    Ok((file, renamed))
 }
 
-fn should_strip_stdint_alias(item: &syn::ItemType) -> Option<String> {
-    let name = item.ident.to_string();
-    let expected = expected_stdint_target(&name)?;
+/// Constraints `generate_kani_harness` accepts for one parameter.
+#[derive(Default)]
+struct KaniPrecondition {
+    min: Option<serde_json::Value>,
+    max: Option<serde_json::Value>,
+    non_null: Option<bool>,
+    max_len: Option<usize>,
+    len_from: Option<String>,
+}
 
-    if let syn::Type::Path(type_path) = item.ty.as_ref() {
-        if type_path.qself.is_none()
-            && type_path.path.segments.len() == 1
-            && type_path.path.segments[0].ident == expected
-            && type_path.path.segments[0].arguments.is_empty()
-        {
-            return Some(name);
+fn parse_kani_preconditions(
+    preconditions_json: &str,
+) -> PyResult<(HashMap<String, KaniPrecondition>, Option<u64>)> {
+    if preconditions_json.trim().is_empty() {
+        return Ok((HashMap::new(), None));
+    }
+    let value = parse_json(preconditions_json, "preconditions")?;
+    let invalid = |message: String| pyo3::exceptions::PyValueError::new_err(message);
+    let object = value
+        .as_object()
+        .ok_or_else(|| invalid("Preconditions must be a JSON object".to_string()))?;
+    let unwind = match object.get("unwind") {
+        None => None,
+        Some(unwind) => Some(
+            unwind
+                .as_u64()
+                .ok_or_else(|| invalid(format!("Invalid unwind bound: {}", unwind)))?,
+        ),
+    };
+    let mut preconditions = HashMap::new();
+    if let Some(params) = object.get("params") {
+        let params = params
+            .as_object()
+            .ok_or_else(|| invalid("'params' must map parameter names to objects".to_string()))?;
+        for (name, spec) in params {
+            let spec = spec
+                .as_object()
+                .ok_or_else(|| invalid(format!("Preconditions of '{}' must be an object", name)))?;
+            let mut precondition = KaniPrecondition::default();
+            for (key, value) in spec {
+                let bad_value = || invalid(format!("Invalid '{}' for '{}': {}", key, name, value));
+                match key.as_str() {
+                    "min" | "max" if value.is_number() => {
+                        if key == "min" {
+                            precondition.min = Some(value.clone());
+                        } else {
+                            precondition.max = Some(value.clone());
+                        }
+                    }
+                    "non_null" => {
+                        precondition.non_null = Some(value.as_bool().ok_or_else(bad_value)?)
+                    }
+                    "max_len" => {
+                        precondition.max_len = Some(value.as_u64().ok_or_else(bad_value)? as usize)
+                    }
+                    "len_from" => {
+                        precondition.len_from =
+                            Some(value.as_str().ok_or_else(bad_value)?.to_string())
+                    }
+                    "min" | "max" => return Err(bad_value()),
+                    _ => {
+                        return Err(invalid(format!(
+                            "Unknown precondition '{}' for '{}'",
+                            key, name
+                        )))
+                    }
+                }
+            }
+            preconditions.insert(name.clone(), precondition);
         }
     }
+    Ok((preconditions, unwind))
+}
 
-    None
+/// A JSON number as a literal of `ty`, so `0` bounds an `f64` as `0.0`.
+fn kani_bound(value: &serde_json::Value, ty: &syn::Type) -> PyResult<syn::Expr> {
+    let mut literal = value.to_string();
+    if is_float_type(ty) && !literal.contains(['.', 'e', 'E']) {
+        literal.push_str(".0");
+    }
+    json_to_rust_expr(&serde_json::Value::String(literal))
 }
 
-fn normalize_stdint_aliases(ast: &mut syn::File) {
-    let mut removed_aliases: Vec<String> = Vec::new();
-    let original_items = mem::take(&mut ast.items);
-    let mut new_items = Vec::with_capacity(original_items.len());
+/// `c == rust`, or both NaN for floats.
+fn kani_same(c_value: &syn::Expr, rust_value: &syn::Expr, ty: &syn::Type) -> syn::Expr {
+    if is_float_type(ty) {
+        parse_quote!(#c_value == #rust_value || (#c_value.is_nan() && #rust_value.is_nan()))
+    } else {
+        parse_quote!(#c_value == #rust_value)
+    }
+}
 
-    for item in original_items {
-        if let syn::Item::Type(type_item) = &item {
-            if let Some(name) = should_strip_stdint_alias(type_item) {
-                removed_aliases.push(name);
-                continue;
+/// Assertions comparing a struct returned by value field by field, recursing
+/// into nested structs. Pointer fields are skipped since each side points to
+/// its own allocations; arrays of scalars are compared element-wise.
+fn kani_struct_checks(
+    c_value: &syn::Expr,
+    rust_value: &syn::Expr,
+    ty: &syn::Type,
+    structs: &HashMap<String, &syn::ItemStruct>,
+    what: &str,
+) -> PyResult<Vec<syn::Stmt>> {
+    let unsupported = |ty: &syn::Type| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "{} has type '{}', which the Kani harness cannot compare",
+            what,
+            analyze_type(ty).normalized
+        ))
+    };
+    let item = type_last_ident(ty)
+        .and_then(|name| structs.get(&name))
+        .ok_or_else(|| unsupported(ty))?;
+    let mut checks = Vec::new();
+    for (index, field) in item.fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(index)),
+        };
+        let c_field: syn::Expr = parse_quote!(#c_value.#member);
+        let rust_field: syn::Expr = parse_quote!(#rust_value.#member);
+        let field_what = format!("{}.{}", what, member.to_token_stream());
+        match &field.ty {
+            ty if is_scalar_type(ty) => {
+                let same = kani_same(&c_field, &rust_field, ty);
+                checks.push(parse_quote!(assert!(#same);));
+            }
+            syn::Type::Ptr(_) => {}
+            syn::Type::Array(array) if is_scalar_type(&array.elem) => {
+                let (c, rust) = (parse_quote!(c), parse_quote!(rust));
+                let same = kani_same(&c, &rust, &array.elem);
+                checks.push(parse_quote! {
+                    assert!(#c_field.iter().zip(#rust_field.iter()).all(|(c, rust)| #same));
+                });
             }
+            ty => checks.extend(kani_struct_checks(
+                &c_field,
+                &rust_field,
+                ty,
+                structs,
+                &field_what,
+            )?),
         }
-        new_items.push(item);
     }
+    Ok(checks)
+}
 
-    removed_aliases.sort();
-    removed_aliases.dedup();
+/// Emits a `#[cfg(kani)]` module with a `#[kani::proof]` harness checking that
+/// the exposed translation with `signature` returns what the C function
+/// declared as `<c_prefix><name>` returns, for every argument allowed by
+/// `preconditions_json`: `{"params": {name: {...}}, "unwind": n}` where a
+/// parameter may set `min`/`max` (inclusive bounds), `non_null` (pointers,
+/// default true), `max_len` (pointers become a buffer of up to that many
+/// elements; C strings are NUL-terminated within it) and `len_from` (the
+/// integer parameter receiving the length, otherwise found by name as in
+/// `items_len`/`n_items`). Mutable buffers are compared after the call. Structs
+/// returned by value are compared field by field, which needs their
+/// `#[repr(C)]` definitions (and those of nested structs) in `struct_code`. The
+/// C side must be linked into the proof, e.g. with Kani's `--c-lib`.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (signature, preconditions_json, c_prefix="sactor_c_", struct_code=""))]
+fn generate_kani_harness(
+    signature: &str,
+    preconditions_json: &str,
+    c_prefix: &str,
+    struct_code: &str,
+) -> PyResult<String> {
+    let item = parse_signature_item(signature)?;
+    let sig = &item.sig;
+    let (preconditions, unwind) = parse_kani_preconditions(preconditions_json)?;
+    let struct_ast = parse_src(struct_code)?;
+    let structs: HashMap<String, &syn::ItemStruct> = struct_ast
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Struct(s) => Some((s.ident.to_string(), s)),
+            _ => None,
+        })
+        .collect();
+    let params: Vec<(syn::Ident, &syn::Type)> = sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(ident) => Ok((ident.ident.clone(), &*pat_type.ty)),
+                _ => Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Parameters of '{}' must be plain identifiers",
+                    sig.ident
+                ))),
+            },
+            syn::FnArg::Receiver(_) => Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "'{}' cannot take self",
+                sig.ident
+            ))),
+        })
+        .collect::<PyResult<_>>()?;
+    if let Some(name) = preconditions
+        .keys()
+        .find(|name| !params.iter().any(|(ident, _)| ident == name.as_str()))
+    {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Precondition for unknown parameter '{}'",
+            name
+        )));
+    }
+    let no_precondition = KaniPrecondition::default();
+    let precondition_of = |name: &syn::Ident| {
+        preconditions
+            .get(&name.to_string())
+            .unwrap_or(&no_precondition)
+    };
 
-    let mut needed: BTreeSet<String> = removed_aliases.into_iter().collect();
-    let usages = collect_stdint_names(&new_items);
-    needed.extend(usages);
+    // the integer parameter carrying each buffer's length
+    let buffers: Vec<(usize, String)> = params
+        .iter()
+        .enumerate()
+        .filter(|(_, (name, _))| precondition_of(name).max_len.is_some())
+        .map(|(index, (name, _))| (index, name.to_string()))
+        .collect();
+    let mut length_params: HashMap<String, usize> = HashMap::new();
+    for (index, name) in buffers.iter() {
+        if let Some(len_from) = &precondition_of(&params[*index].0).len_from {
+            if !params.iter().any(|(ident, _)| ident == len_from.as_str()) {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "'len_from' of '{}' names unknown parameter '{}'",
+                    name, len_from
+                )));
+            }
+            length_params.insert(len_from.clone(), *index);
+        }
+    }
+    for (name, ty) in params.iter() {
+        let name = name.to_string();
+        if !length_params.contains_key(&name) && is_integer_type(ty) {
+            if let Some(target) = length_field_target(&name, &buffers) {
+                length_params.insert(name, target);
+            }
+        }
+    }
 
-    if needed.is_empty() {
-        ast.items = new_items;
-        return;
+    let mut stmts: Vec<syn::Stmt> = Vec::new();
+    let mut c_args: Vec<syn::Expr> = Vec::new();
+    let mut rust_args: Vec<syn::Expr> = Vec::new();
+    let mut checks: Vec<syn::Stmt> = Vec::new();
+    let mut max_unwind = 0;
+    for (name, ty) in params.iter() {
+        let precondition = precondition_of(name);
+        if let Some(target) = length_params.get(&name.to_string()) {
+            let len = fuzz_local(&params[*target].0, "len");
+            c_args.push(parse_quote!(#len as #ty));
+            rust_args.push(parse_quote!(#len as #ty));
+            continue;
+        }
+        if is_scalar_type(ty) {
+            stmts.push(parse_quote!(let #name: #ty = kani::any();));
+            if let Some(min) = &precondition.min {
+                let min = kani_bound(min, ty)?;
+                stmts.push(parse_quote!(kani::assume(#name >= #min);));
+            }
+            if let Some(max) = &precondition.max {
+                let max = kani_bound(max, ty)?;
+                stmts.push(parse_quote!(kani::assume(#name <= #max);));
+            }
+            c_args.push(parse_quote!(#name));
+            rust_args.push(parse_quote!(#name));
+            continue;
+        }
+        let traits = analyze_type(ty);
+        let elem = match pointer_elem(ty) {
+            Some(elem) if traits.pointer_depth == 1 && is_scalar_type(elem) => elem,
+            _ => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Parameter '{}' of '{}' has type '{}', which the Kani harness cannot generate",
+                    name, sig.ident, traits.normalized
+                )))
+            }
+        };
+        let c_local = fuzz_local(name, "c");
+        let rust_local = fuzz_local(name, "rust");
+        let len = fuzz_local(name, "len");
+        let mutability = traits.pointer_is_mut.then(|| quote!(mut));
+        match precondition.max_len {
+            Some(max_len) => {
+                // C strings need room for the terminator
+                let capacity = if is_c_char_pointer(ty) {
+                    max_len + 1
+                } else {
+                    max_len
+                };
+                max_unwind = max_unwind.max(capacity as u64 + 1);
+                let (max_len, capacity) = (
+                    proc_macro2::Literal::usize_unsuffixed(max_len),
+                    proc_macro2::Literal::usize_unsuffixed(capacity),
+                );
+                stmts.push(parse_quote!(let #len: usize = kani::any();));
+                stmts.push(parse_quote!(kani::assume(#len <= #max_len);));
+                if is_c_char_pointer(ty) {
+                    stmts.push(parse_quote!(let mut #c_local: [#elem; #capacity] = kani::any();));
+                    stmts.push(parse_quote!(#c_local[#len] = 0;));
+                } else {
+                    stmts.push(
+                        parse_quote!(let #mutability #c_local: [#elem; #capacity] = kani::any();),
+                    );
+                }
+            }
+            None => {
+                stmts.push(parse_quote!(let #mutability #c_local: [#elem; 1] = kani::any();));
+            }
+        }
+        stmts.push(parse_quote!(let #mutability #rust_local = #c_local;));
+        let (as_ptr, null) = if traits.pointer_is_mut {
+            (quote!(as_mut_ptr), quote!(core::ptr::null_mut()))
+        } else {
+            (quote!(as_ptr), quote!(core::ptr::null()))
+        };
+        if precondition.non_null.unwrap_or(true) {
+            c_args.push(parse_quote!(#c_local.#as_ptr()));
+            rust_args.push(parse_quote!(#rust_local.#as_ptr()));
+        } else {
+            let is_null = fuzz_local(name, "is_null");
+            stmts.push(parse_quote!(let #is_null: bool = kani::any();));
+            c_args.push(parse_quote!(if #is_null { #null } else { #c_local.#as_ptr() }));
+            rust_args.push(parse_quote!(if #is_null { #null } else { #rust_local.#as_ptr() }));
+        }
+        if traits.pointer_is_mut {
+            checks.push(parse_quote!(assert!(#c_local == #rust_local);));
+        }
     }
 
-    let needed_vec: Vec<String> = needed.into_iter().collect();
-    ensure_libc_imports(&mut new_items, &needed_vec);
+    let name = &sig.ident;
+    let c_ident = syn::Ident::new(&format!("{}{}", c_prefix, name), Span::call_site());
+    let proof_ident = syn::Ident::new(&format!("{}_matches_c", name), Span::call_site());
+    let module_ident = syn::Ident::new(&format!("kani_{}", name), Span::call_site());
+    let mut inputs = sig.inputs.clone();
+    for input in inputs.iter_mut() {
+        if let syn::FnArg::Typed(pat_type) = input {
+            if let syn::Pat::Ident(ident) = &mut *pat_type.pat {
+                ident.mutability = None;
+            }
+        }
+    }
+    let output = &sig.output;
+    let mut rust_call: syn::Expr = parse_quote!(#name(#(#rust_args),*));
+    if sig.unsafety.is_some() {
+        rust_call = parse_quote!(unsafe { #rust_call });
+    }
+    let c_call: syn::Expr = parse_quote!(unsafe { #c_ident(#(#c_args),*) });
+    match output {
+        syn::ReturnType::Type(_, ty) if is_scalar_type(ty) => {
+            stmts.push(parse_quote!(let c_result = #c_call;));
+            stmts.push(parse_quote!(let rust_result = #rust_call;));
+            stmts.push(if is_float_type(ty) {
+                parse_quote! {
+                    assert!(c_result == rust_result || (c_result.is_nan() && rust_result.is_nan()));
+                }
+            } else {
+                parse_quote!(assert_eq!(c_result, rust_result);)
+            });
+        }
+        syn::ReturnType::Type(_, ty) if !matches!(**ty, syn::Type::Ptr(_)) => {
+            let result_checks = kani_struct_checks(
+                &parse_quote!(c_result),
+                &parse_quote!(rust_result),
+                ty,
+                &structs,
+                &format!("Return value of '{}'", name),
+            )?;
+            stmts.push(parse_quote!(let c_result = #c_call;));
+            stmts.push(parse_quote!(let rust_result = #rust_call;));
+            stmts.extend(result_checks);
+        }
+        // pointers returned by each side point to different memory
+        _ => {
+            stmts.push(parse_quote!(#c_call;));
+            stmts.push(parse_quote!(#rust_call;));
+        }
+    }
+    stmts.extend(checks);
+
+    let unwind_attr: Option<syn::Attribute> = unwind
+        .or((max_unwind > 0).then_some(max_unwind))
+        .map(|unwind| {
+            let unwind = proc_macro2::Literal::u64_unsuffixed(unwind);
+            parse_quote!(#[kani::unwind(#unwind)])
+        });
+    let file: syn::File = parse_quote! {
+        #[cfg(kani)]
+        mod #module_ident {
+            use super::*;
+
+            extern "C" {
+                fn #c_ident(#inputs) #output;
+            }
 
-    ast.items = new_items;
+            #[kani::proof]
+            #unwind_attr
+            fn #proof_ident() {
+                #(#stmts)*
+            }
+        }
+    };
+    Ok(unparse(&file))
 }
 
-fn ensure_libc_imports(items: &mut Vec<syn::Item>, aliases: &[String]) {
-    if aliases.is_empty() {
-        return;
+/// Summarizes the errors in rustc `--error-format=json` (or cargo
+/// `--message-format=json`) output. Each entry has `kind` (`missing_import`,
+/// `unresolved_name`, `type_mismatch`, `borrowck`, `lifetime` or `other`),
+/// `code`, `message`, `label`, `notes` (help/note children, with suggested
+/// replacements) and the `line`/`column` of the primary span. When `code` is the
+/// compiled source, `item` names the function, type or impl method containing
+/// the error; unparsable code just leaves `item` unset.
+#[gen_stub_pyfunction]
+#[pyfunction(signature = (output, code=""))]
+fn summarize_rustc_diagnostics(py: Python<'_>, output: &str, code: &str) -> PyResult<PyObject> {
+    let source = if code.is_empty() {
+        None
+    } else {
+        parse_src(code).ok()
+    };
+    let result = PyList::empty(py);
+    for diagnostic in diagnostics::parse_diagnostics(output, source.as_ref()) {
+        let dict = PyDict::new(py);
+        dict.set_item("kind", diagnostic.kind)?;
+        dict.set_item("code", diagnostic.code)?;
+        dict.set_item("message", diagnostic.message)?;
+        dict.set_item("item", diagnostic.item)?;
+        dict.set_item("line", diagnostic.line)?;
+        dict.set_item("column", diagnostic.column)?;
+        dict.set_item("label", diagnostic.label)?;
+        dict.set_item("notes", diagnostic.notes)?;
+        result.append(dict)?;
     }
+    Ok(result.into())
+}
 
-    let mut needed: BTreeSet<String> = aliases.iter().cloned().collect();
-
-    for item in items.iter_mut() {
-        if let syn::Item::Use(item_use) = item {
-            ensure_aliases_in_use_tree(&mut item_use.tree, &mut needed, false);
-            if needed.is_empty() {
-                break;
+/// Turns the translation of a C-variadic function, written as
+/// `<function_name>_va(..., args: VaList)`, into the C entry point
+/// `<function_name>(..., mut args: ...)`. The entry point nests the `_va`
+/// function and forwards `args.as_va_list()` to it, so the translation stays a
+/// single function. Other items of `code` are kept.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_variadic_wrapper(code: &str, function_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    let impl_name = format!("{}_va", function_name);
+    let not_found = || {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Function '{}' taking a trailing `VaList` not found",
+            impl_name
+        ))
+    };
+    let index = ast
+        .items
+        .iter()
+        .position(|item| matches!(item, syn::Item::Fn(f) if f.sig.ident == impl_name))
+        .ok_or_else(not_found)?;
+    let syn::Item::Fn(mut inner) = ast.items[index].clone() else {
+        unreachable!()
+    };
+    let va_name = match inner.sig.inputs.last() {
+        Some(syn::FnArg::Typed(pat_type))
+            if type_last_ident(&pat_type.ty).is_some_and(|name| name == "VaList") =>
+        {
+            match &*pat_type.pat {
+                syn::Pat::Ident(ident) => ident.ident.clone(),
+                _ => return Err(not_found()),
             }
         }
-    }
+        _ => return Err(not_found()),
+    };
 
-    if needed.is_empty() {
-        return;
+    let mut inputs = inner.sig.inputs.clone();
+    inputs.pop();
+    let mut call_args: Vec<syn::Expr> = Vec::new();
+    for input in inputs.iter_mut() {
+        let syn::FnArg::Typed(pat_type) = input else {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "'{}' cannot take self",
+                impl_name
+            )));
+        };
+        let syn::Pat::Ident(ident) = &mut *pat_type.pat else {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "Parameters of '{}' must be plain identifiers",
+                impl_name
+            )));
+        };
+        ident.mutability = None;
+        let name = &ident.ident;
+        call_args.push(parse_quote!(#name));
     }
+    call_args.push(parse_quote!(#va_name.as_va_list()));
+
+    inner.vis = syn::Visibility::Inherited;
+    inner.sig.abi = None;
+    inner.sig.unsafety = Some(Token![unsafe](Span::call_site()));
+    inner
+        .attrs
+        .retain(|attr| !attr.path().is_ident("no_mangle"));
+    let inner_name = &inner.sig.ident;
+    let entry_name = syn::Ident::new(function_name, Span::call_site());
+    let output = &inner.sig.output;
+    let separator = (!inputs.is_empty() && !inputs.trailing_punct()).then(|| quote!(,));
+    ast.items[index] = parse_quote! {
+        pub unsafe extern "C" fn #entry_name(#inputs #separator mut #va_name: ...) #output {
+            #inner
+            #inner_name(#(#call_args),*)
+        }
+    };
+    Ok(unparse(&ast))
+}
 
-    let group_items: Vec<syn::UseTree> = needed
-        .iter()
-        .map(|name| {
-            syn::UseTree::Name(syn::UseName {
-                ident: syn::Ident::new(name, proc_macro2::Span::call_site()),
-            })
-        })
-        .collect();
+struct CVariadicFinder {
+    found: bool,
+}
 
-    let new_use = syn::Item::Use(syn::ItemUse {
-        attrs: Vec::new(),
-        vis: syn::Visibility::Inherited,
-        use_token: Token![use](proc_macro2::Span::call_site()),
-        leading_colon: None,
-        tree: syn::UseTree::Path(syn::UsePath {
-            ident: syn::Ident::new("libc", proc_macro2::Span::call_site()),
-            colon2_token: Token![::](proc_macro2::Span::call_site()),
-            tree: Box::new(syn::UseTree::Group(syn::UseGroup {
-                brace_token: syn::token::Brace::default(),
-                items: vec_to_punctuated(group_items),
-            })),
-        }),
-        semi_token: Token![;](proc_macro2::Span::call_site()),
-    });
+impl<'ast> Visit<'ast> for CVariadicFinder {
+    fn visit_signature(&mut self, sig: &'ast syn::Signature) {
+        self.found |= sig.variadic.is_some();
+    }
 
-    // Insert the new use after the last existing use declaration to keep code tidy.
-    let insertion_index = items
-        .iter()
-        .enumerate()
-        .filter_map(|(idx, item)| match item {
-            syn::Item::Use(_) => Some(idx + 1),
-            _ => None,
-        })
-        .last()
-        .unwrap_or(0);
+    // foreign declarations like `fn printf(fmt: *const c_char, ...)` need no gate
+    fn visit_foreign_item_fn(&mut self, _: &'ast syn::ForeignItemFn) {}
+}
 
-    items.insert(insertion_index, new_use);
-    needed.clear();
+/// Whether `code` defines a C-variadic function (`fn f(x: c_int, mut args: ...)`),
+/// which needs `#![feature(c_variadic)]` at the crate root.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn defines_c_variadic(code: &str) -> PyResult<bool> {
+    let ast = parse_src(code)?;
+    let mut finder = CVariadicFinder { found: false };
+    finder.visit_file(&ast);
+    Ok(finder.found)
 }
 
-fn collect_stdint_names(items: &[syn::Item]) -> BTreeSet<String> {
-    let mut collector = StdintUsageCollector {
-        names: BTreeSet::new(),
-    };
+/// `core::ffi` spellings of the C scalar types, which `libc` paths map to.
+const CORE_FFI_TYPES: &[&str] = &[
+    "c_char",
+    "c_schar",
+    "c_uchar",
+    "c_short",
+    "c_ushort",
+    "c_int",
+    "c_uint",
+    "c_long",
+    "c_ulong",
+    "c_longlong",
+    "c_ulonglong",
+    "c_float",
+    "c_double",
+    "c_void",
+];
 
-    for item in items {
-        collector.visit_item(item);
-    }
+/// `std` modules that are re-exports of `core`.
+const CORE_MODULES: &[&str] = &[
+    "any",
+    "array",
+    "ascii",
+    "cell",
+    "char",
+    "clone",
+    "cmp",
+    "convert",
+    "default",
+    "error",
+    "f32",
+    "f64",
+    "ffi",
+    "fmt",
+    "future",
+    "hash",
+    "hint",
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "i128",
+    "isize",
+    "iter",
+    "marker",
+    "mem",
+    "num",
+    "ops",
+    "option",
+    "panic",
+    "pin",
+    "primitive",
+    "ptr",
+    "result",
+    "slice",
+    "str",
+    "task",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "u128",
+    "usize",
+];
 
-    collector.names
+/// `std` modules that are re-exports of `alloc`.
+const ALLOC_MODULES: &[&str] = &[
+    "alloc",
+    "borrow",
+    "boxed",
+    "collections",
+    "rc",
+    "string",
+    "vec",
+];
+
+/// Prelude names `no_std` code has to import from `alloc`, with their paths.
+const ALLOC_PRELUDE: &[(&str, &str)] = &[
+    ("Box", "alloc::boxed::Box"),
+    ("String", "alloc::string::String"),
+    ("ToOwned", "alloc::borrow::ToOwned"),
+    ("ToString", "alloc::string::ToString"),
+    ("Vec", "alloc::vec::Vec"),
+];
+
+/// Float methods implemented in `std` on top of the platform's libm, which
+/// `core` does not provide.
+const STD_FLOAT_METHODS: &[&str] = &[
+    "acos",
+    "acosh",
+    "asin",
+    "asinh",
+    "atan",
+    "atan2",
+    "atanh",
+    "cbrt",
+    "ceil",
+    "cos",
+    "cosh",
+    "div_euclid",
+    "exp",
+    "exp2",
+    "exp_m1",
+    "floor",
+    "fract",
+    "hypot",
+    "ln",
+    "ln_1p",
+    "log",
+    "log10",
+    "log2",
+    "mul_add",
+    "powf",
+    "powi",
+    "rem_euclid",
+    "round",
+    "round_ties_even",
+    "sin",
+    "sin_cos",
+    "sinh",
+    "sqrt",
+    "tan",
+    "tanh",
+    "trunc",
+];
+
+/// The crate (`core` or `alloc`) a `std` path, given without its leading
+/// `std`, lives in, or `None` when it needs an operating system.
+fn no_std_crate(rest: &[String]) -> Option<&'static str> {
+    let module = rest.first().map(String::as_str).unwrap_or_default();
+    let item = rest.get(1).map(String::as_str).unwrap_or_default();
+    match (module, item) {
+        ("ffi", "CString" | "NulError" | "IntoStringError" | "FromVecWithNulError") => {
+            Some("alloc")
+        }
+        ("ffi", "OsStr" | "OsString") => None,
+        ("collections", "HashMap" | "HashSet" | "hash_map" | "hash_set") => None,
+        ("fmt", "format") => Some("alloc"),
+        ("sync", "Arc" | "Weak") => Some("alloc"),
+        ("sync", "atomic") => Some("core"),
+        ("time", "Duration") => Some("core"),
+        _ if ALLOC_MODULES.contains(&module) => Some("alloc"),
+        _ if CORE_MODULES.contains(&module) => Some("core"),
+        _ => None,
+    }
 }
 
-struct StdintUsageCollector {
-    names: BTreeSet<String>,
+/// Rewrites code for `#![no_std]` + `alloc`; see `convert_to_no_std`.
+struct NoStdRewriter {
+    logger: Option<syn::Path>,
+    errors: BTreeSet<String>,
+    used_prelude: BTreeSet<&'static str>,
 }
 
-impl<'ast> Visit<'ast> for StdintUsageCollector {
-    fn visit_path(&mut self, path: &'ast syn::Path) {
-        if path.leading_colon.is_none() && path.segments.len() == 1 {
-            let ident = &path.segments[0].ident;
-            let ident_str = ident.to_string();
-            if expected_stdint_target(&ident_str).is_some() {
-                self.names.insert(ident_str);
+impl NoStdRewriter {
+    fn rewrite_segments(&mut self, segments: &[String]) -> Option<Vec<String>> {
+        match segments.first().map(String::as_str) {
+            Some("std") if segments.len() > 1 => match no_std_crate(&segments[1..]) {
+                Some(krate) => {
+                    let mut rewritten = vec![krate.to_string()];
+                    rewritten.extend_from_slice(&segments[1..]);
+                    Some(rewritten)
+                }
+                None => {
+                    self.errors.insert(format!(
+                        "`{}` needs the standard library",
+                        segments.join("::")
+                    ));
+                    None
+                }
+            },
+            Some("libc") if segments.len() == 2 => {
+                let name = segments[1].as_str();
+                if CORE_FFI_TYPES.contains(&name) {
+                    Some(vec!["core".into(), "ffi".into(), name.into()])
+                } else if let Some(primitive) = map_libc_scalar(name) {
+                    Some(vec![primitive.to_string()])
+                } else {
+                    self.errors.insert(format!(
+                        "`libc::{name}` has no binding on bare-metal targets"
+                    ));
+                    None
+                }
             }
+            _ => None,
         }
+    }
 
-        visit::visit_path(self, path);
+    /// Visits the comma-separated expressions of a format-like macro.
+    fn visit_macro_args(&mut self, mac: &mut syn::Macro) {
+        let parser = syn::punctuated::Punctuated::<syn::Expr, Token![,]>::parse_terminated;
+        if let Ok(mut args) = mac.parse_body_with(parser) {
+            for arg in args.iter_mut() {
+                self.visit_expr_mut(arg);
+            }
+            mac.tokens = args.to_token_stream();
+        }
+    }
+
+    /// The replacement for a `print!` family macro, writing to the logger.
+    fn print_replacement(&mut self, mac: &mut syn::Macro) -> Option<syn::Expr> {
+        let name = mac.path.segments.last()?.ident.to_string();
+        if !matches!(name.as_str(), "print" | "println" | "eprint" | "eprintln") {
+            return None;
+        }
+        let Some(logger) = self.logger.clone() else {
+            self.errors.insert(format!(
+                "`{name}!` needs the standard library; pass a logger to route output through `core::fmt::Write`"
+            ));
+            return None;
+        };
+        self.visit_macro_args(mac);
+        let tokens = &mac.tokens;
+        let args: syn::Expr = if !name.ends_with("ln") {
+            parse_quote!(format_args!(#tokens))
+        } else if tokens.is_empty() {
+            parse_quote!(format_args!("\n"))
+        } else {
+            parse_quote!(format_args!("{}\n", format_args!(#tokens)))
+        };
+        Some(parse_quote!({
+            let _ = core::fmt::Write::write_fmt(&mut #logger(), #args);
+        }))
     }
 }
 
-fn ensure_aliases_in_use_tree(
-    tree: &mut syn::UseTree,
-    needed: &mut BTreeSet<String>,
-    in_libc: bool,
-) {
-    match tree {
-        syn::UseTree::Path(path) => {
-            let next_in_libc = in_libc || path.ident == "libc";
-            ensure_aliases_in_use_tree(&mut path.tree, needed, next_in_libc);
+impl VisitMut for NoStdRewriter {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        if let [.., float, method] = segments.as_slice() {
+            if (float == "f32" || float == "f64") && STD_FLOAT_METHODS.contains(&method.as_str()) {
+                self.errors
+                    .insert(format!("`{float}::{method}` needs the standard library"));
+            }
         }
-        syn::UseTree::Group(group) => {
-            if in_libc {
-                ensure_aliases_in_libc_group(group, needed);
-            } else {
-                for item in group.items.iter_mut() {
-                    ensure_aliases_in_use_tree(item, needed, false);
-                    if needed.is_empty() {
-                        break;
+        if segments.len() == 1 {
+            if let Some((name, _)) = ALLOC_PRELUDE.iter().find(|(name, _)| *name == segments[0]) {
+                self.used_prelude.insert(name);
+            }
+        }
+        if let Some(rewritten) = self.rewrite_segments(&segments) {
+            let arguments: Vec<PathArguments> =
+                path.segments.iter().map(|s| s.arguments.clone()).collect();
+            // `libc::size_t` -> `usize` drops a segment; the arguments stay on the last one
+            let offset = rewritten.len() as isize - segments.len() as isize;
+            path.leading_colon = None;
+            path.segments = rewritten
+                .iter()
+                .enumerate()
+                .map(|(index, ident)| {
+                    let source = index as isize - offset;
+                    syn::PathSegment {
+                        ident: syn::Ident::new(ident, Span::call_site()),
+                        arguments: if source >= 0 {
+                            arguments[source as usize].clone()
+                        } else {
+                            PathArguments::None
+                        },
                     }
+                })
+                .collect();
+        }
+        visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_item_use_mut(&mut self, item: &mut syn::ItemUse) {
+        let root = match &item.tree {
+            syn::UseTree::Path(path) => path.ident.to_string(),
+            _ => return,
+        };
+        if root != "std" && root != "libc" {
+            return;
+        }
+        // `use std::{ffi::CString, ptr}` may split between `alloc` and `core`
+        let mut leaves = Vec::new();
+        flatten_use_tree(&item.tree, &mut Vec::new(), &mut leaves);
+        let mut rewritten_leaves: Vec<syn::UseTree> = Vec::new();
+        for (mut segments, leaf) in leaves {
+            let alias = match &leaf {
+                syn::UseTree::Name(name) => {
+                    segments.push(name.ident.to_string());
+                    None
+                }
+                syn::UseTree::Rename(rename) => {
+                    segments.push(rename.ident.to_string());
+                    Some(rename.rename.to_string())
                 }
+                _ => None,
+            };
+            let is_glob = matches!(leaf, syn::UseTree::Glob(_));
+            if segments.last().is_some_and(|last| last == "self") {
+                segments.pop();
             }
-        }
-        syn::UseTree::Name(name) => {
-            if !in_libc {
+            let Some(mut rewritten) = self.rewrite_segments(&segments) else {
                 return;
+            };
+            let alias = alias.or_else(|| (rewritten.len() == 1).then(|| segments[1].clone()));
+            if rewritten.len() == 1 {
+                // `use libc::size_t;` -> `use core::primitive::usize as size_t;`
+                rewritten.splice(0..0, ["core".to_string(), "primitive".to_string()]);
             }
+            let mut text = rewritten.join("::");
+            if is_glob {
+                text.push_str("::*");
+            } else if let Some(alias) = alias {
+                text.push_str(&format!(" as {alias}"));
+            }
+            rewritten_leaves.push(parse_str(&text).unwrap());
+        }
+        item.leading_colon = None;
+        item.tree = if rewritten_leaves.len() == 1 {
+            rewritten_leaves.pop().unwrap()
+        } else {
+            syn::UseTree::Group(syn::UseGroup {
+                brace_token: Default::default(),
+                items: rewritten_leaves.into_iter().collect(),
+            })
+        };
+    }
 
-            let ident_str = name.ident.to_string();
-            needed.remove(&ident_str);
-
-            if needed.is_empty() {
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Macro(stmt_macro) = stmt {
+            if let Some(expr) = self.print_replacement(&mut stmt_macro.mac) {
+                *stmt = syn::Stmt::Expr(expr, stmt_macro.semi_token);
                 return;
             }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
 
-            let mut items_vec = Vec::with_capacity(1 + needed.len());
-            items_vec.push(syn::UseTree::Name(name.clone()));
-
-            let extras: Vec<String> = needed.iter().cloned().collect();
-            for alias in extras.iter() {
-                let ident = syn::Ident::new(alias, proc_macro2::Span::call_site());
-                items_vec.push(syn::UseTree::Name(syn::UseName { ident }));
-                needed.remove(alias);
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Macro(expr_macro) = expr {
+            if let Some(replacement) = self.print_replacement(&mut expr_macro.mac) {
+                *expr = replacement;
+                return;
             }
-
-            *tree = syn::UseTree::Group(syn::UseGroup {
-                brace_token: syn::token::Brace::default(),
-                items: vec_to_punctuated(items_vec),
-            });
         }
-        syn::UseTree::Rename(rename) => {
-            if in_libc {
-                needed.remove(&rename.ident.to_string());
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_expr_method_call_mut(&mut self, call: &mut syn::ExprMethodCall) {
+        match call.method.to_string().as_str() {
+            "to_string" => {
+                self.used_prelude.insert("ToString");
             }
-        }
-        syn::UseTree::Glob(_) => {
-            if in_libc {
-                needed.clear();
+            "to_owned" => {
+                self.used_prelude.insert("ToOwned");
+            }
+            method if STD_FLOAT_METHODS.contains(&method) => {
+                self.errors.insert(format!(
+                    "`.{method}()` on floats needs the standard library"
+                ));
             }
+            _ => {}
         }
+        visit_mut::visit_expr_method_call_mut(self, call);
     }
-}
 
-fn ensure_aliases_in_libc_group(group: &mut syn::UseGroup, needed: &mut BTreeSet<String>) {
-    let mut existing: BTreeSet<String> = BTreeSet::new();
-
-    let mut has_glob = false;
-    for item in group.items.iter() {
-        collect_libc_names(item, true, &mut existing, &mut has_glob);
-        if has_glob {
-            needed.clear();
-            return;
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        let segments: Vec<String> = mac
+            .path
+            .segments
+            .iter()
+            .map(|s| s.ident.to_string())
+            .collect();
+        let name = segments.last().cloned().unwrap_or_default();
+        match name.as_str() {
+            "format" | "vec" => {
+                mac.path = parse_str(&format!("alloc::{name}")).unwrap();
+            }
+            "dbg" => {
+                self.errors
+                    .insert("`dbg!` needs the standard library".to_string());
+            }
+            _ => {
+                if segments.len() > 1 && segments[0] == "std" {
+                    mac.path = parse_str(&format!("core::{name}")).unwrap();
+                }
+            }
         }
-    }
-
-    let additions: Vec<String> = needed
-        .iter()
-        .filter(|alias| !existing.contains(*alias))
-        .cloned()
-        .collect();
-
-    if additions.is_empty() {
-        return;
-    }
-
-    for alias in additions.iter() {
-        let ident = syn::Ident::new(alias, proc_macro2::Span::call_site());
-        group.items.push(syn::UseTree::Name(syn::UseName { ident }));
-        needed.remove(alias);
+        self.visit_macro_args(mac);
     }
 }
 
-fn collect_libc_names(
+/// Splits a use tree into its leaves (names, renames and globs), each with
+/// the path leading to it.
+fn flatten_use_tree(
     tree: &syn::UseTree,
-    in_libc: bool,
-    acc: &mut BTreeSet<String>,
-    has_glob: &mut bool,
+    prefix: &mut Vec<String>,
+    out: &mut Vec<(Vec<String>, syn::UseTree)>,
 ) {
     match tree {
-        syn::UseTree::Path(path) => {
-            let next_in_libc = in_libc || path.ident == "libc";
-            collect_libc_names(&path.tree, next_in_libc, acc, has_glob);
-        }
-        syn::UseTree::Group(group) => {
-            for item in group.items.iter() {
-                collect_libc_names(item, in_libc, acc, has_glob);
-                if *has_glob {
-                    break;
-                }
-            }
-        }
-        syn::UseTree::Name(name) => {
-            if in_libc {
-                acc.insert(name.ident.to_string());
-            }
-        }
-        syn::UseTree::Rename(rename) => {
-            if in_libc {
-                acc.insert(rename.ident.to_string());
-            }
+        syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            flatten_use_tree(&path.tree, prefix, out);
+            prefix.pop();
         }
-        syn::UseTree::Glob(_) => {
-            if in_libc {
-                *has_glob = true;
+        syn::UseTree::Group(group) => {
+            for item in group.items.iter() {
+                flatten_use_tree(item, prefix, out);
             }
         }
+        leaf => out.push((prefix.clone(), leaf.clone())),
     }
 }
 
-fn vec_to_punctuated(
-    trees: Vec<syn::UseTree>,
-) -> syn::punctuated::Punctuated<syn::UseTree, syn::token::Comma> {
-    let mut punctuated = syn::punctuated::Punctuated::new();
-    for (idx, tree) in trees.into_iter().enumerate() {
-        if idx > 0 {
-            punctuated.push_punct(Token![,](proc_macro2::Span::call_site()));
+/// Rewrites code for a `#![no_std]` crate with `alloc`: `std` paths move to
+/// `core` or `alloc`, `libc` scalar types to `core::ffi` (or the mapped
+/// primitive), `format!`/`vec!` to their `alloc` macros, and the `alloc`
+/// prelude names the code uses (`Vec`, `String`, `Box`, `ToString`,
+/// `ToOwned`) are imported. `print!`-style macros write to what `logger`, the
+/// path of a function such as `crate::logger`, returns (a `core::fmt::Write`
+/// value); a top-level `fn main` is dropped, since bare-metal entry points are
+/// board-specific. The crate attributes are left to the caller.
+///
+/// Fails listing every construct that needs the standard library (files,
+/// processes, `HashMap`, float math such as `sqrt`, printing without a
+/// logger, other `libc` items).
+#[gen_stub_pyfunction]
+#[pyfunction]
+#[pyo3(signature = (code, logger=None))]
+fn convert_to_no_std(code: &str, logger: Option<&str>) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+    let logger = logger
+        .map(|logger| {
+            parse_str::<syn::Path>(logger).map_err(|e| {
+                pyo3::exceptions::PyValueError::new_err(format!(
+                    "Invalid logger path '{logger}': {e}"
+                ))
+            })
+        })
+        .transpose()?;
+    let mut rewriter = NoStdRewriter {
+        logger,
+        errors: BTreeSet::new(),
+        used_prelude: BTreeSet::new(),
+    };
+    ast.items
+        .retain(|item| !matches!(item, syn::Item::Fn(f) if f.sig.ident == "main"));
+    rewriter.visit_file_mut(&mut ast);
+    if !rewriter.errors.is_empty() {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "Not available without std: {}",
+            rewriter.errors.into_iter().collect::<Vec<_>>().join("; ")
+        )));
+    }
+
+    let mut declared = HashSet::new();
+    for item in &ast.items {
+        match item {
+            syn::Item::Use(item_use) => collect_use_idents(&item_use.tree, &mut declared),
+            syn::Item::Struct(s) => {
+                declared.insert(s.ident.to_string());
+            }
+            syn::Item::Enum(e) => {
+                declared.insert(e.ident.to_string());
+            }
+            syn::Item::Trait(t) => {
+                declared.insert(t.ident.to_string());
+            }
+            syn::Item::Type(t) => {
+                declared.insert(t.ident.to_string());
+            }
+            _ => {}
         }
-        punctuated.push(tree);
     }
-    punctuated
+    let imports: Vec<syn::Item> = ALLOC_PRELUDE
+        .iter()
+        .filter(|(name, _)| rewriter.used_prelude.contains(name) && !declared.contains(*name))
+        .map(|(_, path)| {
+            let path: syn::Path = parse_str(path).unwrap();
+            parse_quote!(use #path;)
+        })
+        .collect();
+    ast.items.splice(0..0, imports);
+    Ok(unparse(&ast))
 }
-/// The mut-remover: walks the AST and clears `mut` when the bound ident matches `name`.
-struct RemoveMut {
-    name: String,
+
+struct NightlyFinder {
+    found: Vec<(&'static str, String, usize)>,
 }
 
-impl RemoveMut {
-    fn new(name: impl Into<String>) -> Self {
-        Self { name: name.into() }
+impl<'ast> Visit<'ast> for NightlyFinder {
+    fn visit_file(&mut self, file: &'ast syn::File) {
+        for attr in file
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("feature"))
+        {
+            let features = match &attr.meta {
+                Meta::List(list) => list.tokens.to_string(),
+                _ => String::new(),
+            };
+            self.found
+                .push(("feature", features, attr.span().start().line));
+        }
+        visit::visit_file(self, file);
     }
-}
 
-impl VisitMut for RemoveMut {
-    fn visit_pat_ident_mut(&mut self, node: &mut PatIdent) {
-        // If this pattern is a direct identifier binding with the target name, remove `mut`.
-        if node.ident == self.name {
-            node.mutability = None;
+    fn visit_attribute(&mut self, attr: &'ast Attribute) {
+        let path = attr.path();
+        if path.is_ident("thread_local")
+            || path.is_ident("linkage")
+            || path
+                .get_ident()
+                .is_some_and(|ident| ident.to_string().starts_with("rustc_"))
+        {
+            self.found.push((
+                "unstable_attribute",
+                path.to_token_stream().to_string(),
+                attr.span().start().line,
+            ));
         }
-        // Recurse into subpatterns if any (usually none for simple PatIdent).
-        visit_mut::visit_pat_ident_mut(self, node);
     }
 
-    fn visit_item_static_mut(&mut self, node: &mut ItemStatic) {
-        if node.ident == self.name {
-            node.mutability = syn::StaticMutability::None; // removes the `mut`
+    fn visit_signature(&mut self, sig: &'ast syn::Signature) {
+        if sig.variadic.is_some() {
+            self.found
+                .push(("c_variadic", sig.ident.to_string(), sig.span().start().line));
         }
-        visit_mut::visit_item_static_mut(self, node);
+        visit::visit_signature(self, sig);
     }
-}
 
-#[gen_stub_pyfunction]
-#[pyfunction]
-fn remove_mut_from_type_specifiers(code: &str, var_name: &str) -> PyResult<String> {
-    let mut file: File = parse_src(code)?;
-    let mut remover = RemoveMut::new(var_name);
-    visit_mut::visit_file_mut(&mut remover, &mut file);
+    // variadic foreign declarations such as `printf` are stable
+    fn visit_foreign_item_fn(&mut self, _: &'ast syn::ForeignItemFn) {}
 
-    // Pretty-print. Use prettyplease for nicer formatting; otherwise use tokens.
-    let formatted = prettyplease::unparse(&file);
-    Ok(formatted)
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        let intrinsics = segments
+            .windows(2)
+            .any(|pair| matches!(pair[0].as_str(), "core" | "std") && pair[1] == "intrinsics");
+        let va_list = segments
+            .last()
+            .is_some_and(|last| last == "VaList" || last == "VaListImpl");
+        if intrinsics || va_list {
+            self.found.push((
+                if intrinsics { "intrinsics" } else { "va_list" },
+                segments.join("::"),
+                path.span().start().line,
+            ));
+        }
+        visit::visit_path(self, path);
+    }
 }
 
+/// Lists constructs that only build on a nightly toolchain: crate-level
+/// `#![feature(..)]` gates, C-variadic function definitions, `VaList`,
+/// `core`/`std::intrinsics` paths, and unstable attributes (`#[thread_local]`,
+/// `#[linkage]`, `#[rustc_*]`). Each entry has `kind` (`feature`, `c_variadic`,
+/// `va_list`, `intrinsics`, `unstable_attribute`), `detail` and `line`.
 #[gen_stub_pyfunction]
 #[pyfunction]
-fn get_value_type_name(code: &str, value: &str) -> PyResult<String> {
+fn find_nightly_constructs(py: Python<'_>, code: &str) -> PyResult<PyObject> {
     let ast = parse_src(code)?;
+    let mut finder = NightlyFinder { found: Vec::new() };
+    finder.visit_file(&ast);
 
-    for item in ast.items.iter() {
-        match item {
-            // Handle static variables
-            syn::Item::Static(s) if s.ident == value => {
-                let static_without_value = syn::ItemStatic {
-                    attrs: vec![],                   // No attributes
-                    vis: syn::Visibility::Inherited, // No visibility modifier
-                    static_token: s.static_token,
-                    mutability: s.mutability.clone(),
-                    ident: s.ident.clone(),
-                    colon_token: s.colon_token,
-                    ty: s.ty.clone(),
-                    eq_token: s.eq_token,
-                    expr: Box::new(syn::Expr::Verbatim(Default::default())), // Empty expression
-                    semi_token: s.semi_token,
-                };
+    let result = PyList::empty(py);
+    for (kind, detail, line) in finder.found {
+        let dict = PyDict::new(py);
+        dict.set_item("kind", kind)?;
+        dict.set_item("detail", detail)?;
+        dict.set_item("line", line)?;
+        result.append(dict)?;
+    }
+    Ok(result.into())
+}
 
-                let static_item = syn::Item::Static(static_without_value);
-                let file = syn::File {
-                    shebang: None,
-                    attrs: vec![],
-                    items: vec![static_item],
-                };
+/// C spelling of a scalar type name (libc alias, stdint alias, or Rust
+/// primitive), or `None` when it has no direct C equivalent.
+fn c_scalar_name(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "c_char" => "char",
+        "c_schar" => "signed char",
+        "c_uchar" => "unsigned char",
+        "c_short" => "short",
+        "c_ushort" => "unsigned short",
+        "c_int" => "int",
+        "c_uint" => "unsigned int",
+        "c_long" => "long",
+        "c_ulong" => "unsigned long",
+        "c_longlong" => "long long",
+        "c_ulonglong" => "unsigned long long",
+        "c_float" | "f32" => "float",
+        "c_double" | "f64" => "double",
+        "c_void" => "void",
+        "size_t" | "usize" => "size_t",
+        "ssize_t" | "isize" | "ptrdiff_t" => "ptrdiff_t",
+        "bool" => "bool",
+        "i8" | "int8_t" => "int8_t",
+        "i16" | "int16_t" => "int16_t",
+        "i32" | "int32_t" => "int32_t",
+        "i64" | "int64_t" => "int64_t",
+        "u8" | "uint8_t" => "uint8_t",
+        "u16" | "uint16_t" => "uint16_t",
+        "u32" | "uint32_t" => "uint32_t",
+        "u64" | "uint64_t" => "uint64_t",
+        "intptr_t" => "intptr_t",
+        "uintptr_t" => "uintptr_t",
+        "FILE" => "FILE",
+        _ => return None,
+    })
+}
 
-                let code_str = prettyplease::unparse(&file);
+fn has_repr_c(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("repr")
+            && attr
+                .parse_args_with(
+                    syn::punctuated::Punctuated::<syn::Path, Token![,]>::parse_terminated,
+                )
+                .is_ok_and(|reprs| reprs.iter().any(|repr| repr.is_ident("C")))
+    })
+}
 
-                // Extract just the static declaration line (remove empty expression)
-                let lines: Vec<&str> = code_str.lines().collect();
-                for line in lines {
-                    let trimmed = line.trim();
-                    if trimmed.starts_with("static") && trimmed.ends_with("= ;") {
-                        // Remove the "= " part to get just the type declaration
-                        return Ok(trimmed.replace("= ", ""));
+/// Renders Rust types as C declarations against the local `#[repr(C)]`
+/// structs, unions and enums and the type aliases of one file.
+struct CHeaderTypes {
+    /// `struct`, `union` or `enum` for each local tagged type
+    tags: HashMap<String, &'static str>,
+    aliases: HashSet<String>,
+}
+
+impl CHeaderTypes {
+    /// The declaration of `declarator` with type `ty`, e.g. `const char *name`;
+    /// `const_object` makes the declared object itself `const`.
+    fn declare(&self, ty: &syn::Type, declarator: &str, const_object: bool) -> Result<String> {
+        let join = |base: String| {
+            let base = if const_object {
+                format!("const {}", base)
+            } else {
+                base
+            };
+            if declarator.is_empty() {
+                base
+            } else {
+                format!("{} {}", base, declarator)
+            }
+        };
+        match ty {
+            syn::Type::Paren(paren) => self.declare(&paren.elem, declarator, const_object),
+            syn::Type::Group(group) => self.declare(&group.elem, declarator, const_object),
+            syn::Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(join("void".to_string())),
+            syn::Type::Ptr(ptr) => {
+                let mut inner = if const_object {
+                    format!("*const {}", declarator)
+                } else {
+                    format!("*{}", declarator)
+                };
+                inner = inner.trim_end().to_string();
+                if matches!(&*ptr.elem, syn::Type::Array(_) | syn::Type::BareFn(_)) {
+                    inner = format!("({})", inner);
+                }
+                self.declare(&ptr.elem, &inner, ptr.const_token.is_some())
+            }
+            syn::Type::Array(array) => {
+                let len = array.len.to_token_stream().to_string();
+                self.declare(
+                    &array.elem,
+                    &format!("{}[{}]", declarator, len),
+                    const_object,
+                )
+            }
+            syn::Type::BareFn(bare_fn) => {
+                let params = bare_fn
+                    .inputs
+                    .iter()
+                    .map(|arg| self.declare(&arg.ty, "", false))
+                    .collect::<Result<Vec<_>>>()?;
+                let params = if params.is_empty() {
+                    "void".to_string()
+                } else {
+                    params.join(", ")
+                };
+                let declarator = format!("(*{})({})", declarator, params);
+                self.declare_return(&bare_fn.output, &declarator)
+            }
+            syn::Type::Path(_) => {
+                if let Some(inner) = generic_arg_of(ty, "Option") {
+                    // `Option<extern "C" fn(..)>` is a nullable function pointer
+                    if matches!(inner, syn::Type::BareFn(_)) {
+                        return self.declare(inner, declarator, const_object);
                     }
                 }
-
-                return Ok(code_str.trim().to_string());
+                let name = type_last_ident(ty).unwrap_or_default();
+                if let Some(tag) = self.tags.get(&name) {
+                    Ok(join(format!("{} {}", tag, name)))
+                } else if self.aliases.contains(&name) {
+                    Ok(join(name))
+                } else if let Some(scalar) = c_scalar_name(&name) {
+                    Ok(join(scalar.to_string()))
+                } else {
+                    Err(syn::Error::new(
+                        ty.span(),
+                        format!("'{}' has no C equivalent", ty.to_token_stream()),
+                    ))
+                }
             }
+            _ => Err(syn::Error::new(
+                ty.span(),
+                format!("'{}' has no C equivalent", ty.to_token_stream()),
+            )),
+        }
+    }
 
-            // Handle const variables
-            syn::Item::Const(c) if c.ident == value => {
-                let const_without_value = syn::ItemConst {
-                    attrs: vec![],                   // No attributes
-                    vis: syn::Visibility::Inherited, // No visibility modifier
-                    const_token: c.const_token,
-                    ident: c.ident.clone(),
-                    generics: c.generics.clone(),
-                    colon_token: c.colon_token,
-                    ty: c.ty.clone(),
-                    eq_token: c.eq_token,
-                    expr: Box::new(syn::Expr::Verbatim(Default::default())), // Empty expression
-                    semi_token: c.semi_token,
+    fn declare_return(&self, output: &syn::ReturnType, declarator: &str) -> Result<String> {
+        match output {
+            syn::ReturnType::Default => Ok(format!("void {}", declarator)),
+            syn::ReturnType::Type(_, ty) => self.declare(ty, declarator, false),
+        }
+    }
+
+    fn fields(&self, fields: &syn::FieldsNamed) -> Result<Vec<String>> {
+        fields
+            .named
+            .iter()
+            .map(|field| {
+                let name = field.ident.as_ref().map(|ident| ident.to_string());
+                self.declare(&field.ty, &name.unwrap_or_default(), false)
+                    .map(|decl| format!("    {};", decl))
+            })
+            .collect()
+    }
+}
+
+/// Emits a C header for the exposed items of `code`: a prototype for every
+/// `#[no_mangle]`/`extern "C"` function (other than `main`), an `extern`
+/// declaration for every `#[no_mangle]` static, and the `#[repr(C)]` structs,
+/// unions and fieldless enums and the type aliases they use, ordered so that
+/// types used by value are defined first. Raw pointers keep their const-ness
+/// (`*const c_char` is `const char *`) and `Option<extern "C" fn>` becomes a
+/// function pointer. The header is wrapped in an include guard `guard`.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn generate_c_header(code: &str, guard: &str) -> PyResult<String> {
+    let ast = parse_src(code)?;
+    let mut types = CHeaderTypes {
+        tags: HashMap::new(),
+        aliases: HashSet::new(),
+    };
+    for item in &ast.items {
+        match item {
+            syn::Item::Struct(s) if has_repr_c(&s.attrs) => {
+                types.tags.insert(s.ident.to_string(), "struct");
+            }
+            syn::Item::Union(u) if has_repr_c(&u.attrs) => {
+                types.tags.insert(u.ident.to_string(), "union");
+            }
+            syn::Item::Enum(e) if e.variants.iter().all(|v| v.fields.is_empty()) => {
+                types.tags.insert(e.ident.to_string(), "enum");
+            }
+            syn::Item::Type(alias) => {
+                types.aliases.insert(alias.ident.to_string());
+            }
+            _ => {}
+        }
+    }
+    let error = |err: syn::Error| pyo3::exceptions::PyValueError::new_err(err.to_string());
+
+    // definitions, keyed by name, with the local types they use by value
+    let mut definitions: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    let by_value = |ty: &syn::Type| -> Vec<String> {
+        let mut ty = ty;
+        while let syn::Type::Array(array) = ty {
+            ty = &array.elem;
+        }
+        type_last_ident(ty).into_iter().collect()
+    };
+    for item in &ast.items {
+        match item {
+            syn::Item::Struct(s) if has_repr_c(&s.attrs) => {
+                let syn::Fields::Named(fields) = &s.fields else {
+                    continue;
                 };
+                let body = types.fields(fields).map_err(error)?;
+                let uses = fields.named.iter().flat_map(|f| by_value(&f.ty)).collect();
+                definitions.insert(
+                    s.ident.to_string(),
+                    (
+                        format!("struct {} {{\n{}\n}};", s.ident, body.join("\n")),
+                        uses,
+                    ),
+                );
+            }
+            syn::Item::Union(u) if has_repr_c(&u.attrs) => {
+                let body = types.fields(&u.fields).map_err(error)?;
+                let uses = u
+                    .fields
+                    .named
+                    .iter()
+                    .flat_map(|f| by_value(&f.ty))
+                    .collect();
+                definitions.insert(
+                    u.ident.to_string(),
+                    (
+                        format!("union {} {{\n{}\n}};", u.ident, body.join("\n")),
+                        uses,
+                    ),
+                );
+            }
+            syn::Item::Enum(e) if types.tags.contains_key(&e.ident.to_string()) => {
+                let variants: Vec<String> = e
+                    .variants
+                    .iter()
+                    .map(|v| match &v.discriminant {
+                        Some((_, value)) => {
+                            format!("    {} = {},", v.ident, value.to_token_stream())
+                        }
+                        None => format!("    {},", v.ident),
+                    })
+                    .collect();
+                definitions.insert(
+                    e.ident.to_string(),
+                    (
+                        format!("enum {} {{\n{}\n}};", e.ident, variants.join("\n")),
+                        Vec::new(),
+                    ),
+                );
+            }
+            syn::Item::Type(alias) => {
+                let decl = types
+                    .declare(&alias.ty, &alias.ident.to_string(), false)
+                    .map_err(error)?;
+                definitions.insert(
+                    alias.ident.to_string(),
+                    (format!("typedef {};", decl), by_value(&alias.ty)),
+                );
+            }
+            _ => {}
+        }
+    }
 
-                let const_item = syn::Item::Const(const_without_value);
-                let file = syn::File {
-                    shebang: None,
-                    attrs: vec![],
-                    items: vec![const_item],
+    let mut declarations = Vec::new();
+    for item in &ast.items {
+        if !is_exposed_item(item) {
+            continue;
+        }
+        match item {
+            syn::Item::Fn(f) if f.sig.ident != "main" => {
+                let params = f
+                    .sig
+                    .inputs
+                    .iter()
+                    .map(|input| match input {
+                        syn::FnArg::Typed(pat_type) => {
+                            let name = match &*pat_type.pat {
+                                syn::Pat::Ident(ident) => ident.ident.to_string(),
+                                _ => String::new(),
+                            };
+                            types.declare(&pat_type.ty, &name, false)
+                        }
+                        syn::FnArg::Receiver(receiver) => Err(syn::Error::new(
+                            receiver.span(),
+                            format!("'{}' cannot take self", f.sig.ident),
+                        )),
+                    })
+                    .collect::<Result<Vec<_>>>()
+                    .map_err(error)?;
+                let params = if params.is_empty() {
+                    "void".to_string()
+                } else {
+                    params.join(", ")
                 };
+                let declarator = format!("{}({})", f.sig.ident, params);
+                declarations.push(format!(
+                    "{};",
+                    types
+                        .declare_return(&f.sig.output, &declarator)
+                        .map_err(error)?
+                ));
+            }
+            syn::Item::Static(s) => {
+                let is_mut = matches!(s.mutability, syn::StaticMutability::Mut(_));
+                let decl = types
+                    .declare(&s.ty, &s.ident.to_string(), !is_mut)
+                    .map_err(error)?;
+                declarations.push(format!("extern {};", decl));
+            }
+            _ => {}
+        }
+    }
 
-                let code_str = prettyplease::unparse(&file);
+    // depth-first, so a type used by value is defined before its users
+    fn emit(
+        name: &str,
+        definitions: &BTreeMap<String, (String, Vec<String>)>,
+        done: &mut HashSet<String>,
+        out: &mut Vec<String>,
+    ) {
+        let Some((definition, uses)) = definitions.get(name) else {
+            return;
+        };
+        if !done.insert(name.to_string()) {
+            return;
+        }
+        for used in uses {
+            emit(used, definitions, done, out);
+        }
+        out.push(definition.clone());
+    }
+    let mut done = HashSet::new();
+    let mut ordered = Vec::new();
+    for item in &ast.items {
+        if let Some(name) = item_name(item) {
+            emit(&name, &definitions, &mut done, &mut ordered);
+        }
+    }
 
-                // Extract just the const declaration line (remove empty expression)
-                let lines: Vec<&str> = code_str.lines().collect();
-                for line in lines {
-                    let trimmed = line.trim();
-                    if trimmed.starts_with("const") && trimmed.ends_with("= ;") {
-                        // Remove the "= " part to get just the type declaration
-                        return Ok(trimmed.replace("= ", ""));
-                    }
-                }
+    let mut lines = vec![
+        format!("#ifndef {}", guard),
+        format!("#define {}", guard),
+        String::new(),
+        "#include <stdbool.h>".to_string(),
+        "#include <stddef.h>".to_string(),
+        "#include <stdint.h>".to_string(),
+    ];
+    let body = ordered
+        .iter()
+        .chain(declarations.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    if body.iter().any(|line| line.contains("FILE")) {
+        lines.push("#include <stdio.h>".to_string());
+    }
+    lines.push(String::new());
+    // pointers to tagged types need no complete definition
+    let forward: Vec<String> = types
+        .tags
+        .iter()
+        .filter(|(_, tag)| **tag != "enum")
+        .map(|(name, tag)| format!("{} {};", tag, name))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if !forward.is_empty() {
+        lines.extend(forward);
+        lines.push(String::new());
+    }
+    for definition in ordered {
+        lines.push(definition);
+        lines.push(String::new());
+    }
+    lines.extend(declarations);
+    lines.push(String::new());
+    lines.push(format!("#endif /* {} */", guard));
+    Ok(lines.join("\n") + "\n")
+}
 
-                return Ok(code_str.trim().to_string());
+/// Finds a raw or function pointer, which serde cannot serialize.
+struct UnserializableType(bool);
+
+impl<'ast> Visit<'ast> for UnserializableType {
+    fn visit_type_ptr(&mut self, _node: &'ast syn::TypePtr) {
+        self.0 = true;
+    }
+
+    fn visit_type_bare_fn(&mut self, _node: &'ast syn::TypeBareFn) {
+        self.0 = true;
+    }
+}
+
+/// Adds `#[derive(Serialize, Deserialize)]` to `struct_name` and to the types
+/// defined in `code` that its fields contain, imports them from `serde`, and
+/// gives `struct_name` a `to_capture_json()` method returning its JSON
+/// encoding, so differential tests can compare struct values. Fields holding
+/// raw or function pointers, and unions, are rejected.
+#[gen_stub_pyfunction]
+#[pyfunction]
+fn add_serde_capture(code: &str, struct_name: &str) -> PyResult<String> {
+    let mut ast = parse_src(code)?;
+
+    let mut pending = vec![struct_name.to_string()];
+    let mut targets: HashSet<String> = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if !targets.insert(name.clone()) {
+            continue;
+        }
+        let fields: Vec<&syn::Field> = match ast.items.iter().find(|item| {
+            matches!(
+                item,
+                syn::Item::Struct(_) | syn::Item::Enum(_) | syn::Item::Union(_)
+            ) && crate::item_name(item).as_deref() == Some(name.as_str())
+        }) {
+            Some(syn::Item::Struct(s)) => s.fields.iter().collect(),
+            Some(syn::Item::Enum(e)) => e.variants.iter().flat_map(|v| v.fields.iter()).collect(),
+            Some(_) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Union '{}' cannot derive Serialize",
+                    name
+                )))
+            }
+            None if name == struct_name => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Type '{}' not found",
+                    name
+                )))
+            }
+            None => continue,
+        };
+        for (index, field) in fields.iter().enumerate() {
+            let mut unserializable = UnserializableType(false);
+            unserializable.visit_type(&field.ty);
+            if unserializable.0 {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Field '{}' of '{}' has type '{}', which cannot be serialized",
+                    field
+                        .ident
+                        .as_ref()
+                        .map_or_else(|| index.to_string(), |ident| ident.to_string()),
+                    name,
+                    normalize_token_string(&field.ty.to_token_stream().to_string())
+                )));
             }
+            let mut idents = TypePathIdents(HashSet::new());
+            idents.visit_type(&field.ty);
+            pending.extend(idents.0);
+        }
+    }
 
+    let mut has_method = false;
+    for item in ast.items.iter_mut() {
+        let (ident, attrs) = match item {
+            syn::Item::Struct(s) => (&s.ident, &mut s.attrs),
+            syn::Item::Enum(e) => (&e.ident, &mut e.attrs),
+            syn::Item::Impl(imp) => {
+                if type_last_ident(&imp.self_ty).as_deref() == Some(struct_name)
+                    && imp.items.iter().any(|item| {
+                        matches!(item, syn::ImplItem::Fn(f) if f.sig.ident == "to_capture_json")
+                    })
+                {
+                    has_method = true;
+                }
+                continue;
+            }
             _ => continue,
+        };
+        if targets.contains(&ident.to_string()) {
+            let span = ident.span();
+            add_derive(attrs, "Serialize", span)?;
+            add_derive(attrs, "Deserialize", span)?;
         }
     }
 
-    Err(pyo3::exceptions::PyValueError::new_err(format!(
-        "Item '{}' not found",
-        value
-    )))
+    let imports_serde = ast.items.iter().any(|item| match item {
+        syn::Item::Use(item_use) => {
+            let mut leaves = HashSet::new();
+            collect_use_idents(&item_use.tree, &mut leaves);
+            leaves.contains("Serialize")
+        }
+        _ => false,
+    });
+    if !imports_serde {
+        let position = leading_use_count(&ast.items);
+        ast.items.insert(
+            position,
+            parse_quote!(
+                use serde::{Deserialize, Serialize};
+            ),
+        );
+    }
+
+    if !has_method {
+        let mut generics = ast
+            .items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Struct(s) if s.ident == struct_name => Some(s.generics.clone()),
+                syn::Item::Enum(e) if e.ident == struct_name => Some(e.generics.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        for param in generics.type_params_mut() {
+            param.bounds.push(parse_quote!(Serialize));
+        }
+        let ident = syn::Ident::new(struct_name, Span::call_site());
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        ast.items.push(parse_quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                pub fn to_capture_json(&self) -> String {
+                    serde_json::to_string(self).unwrap_or_default()
+                }
+            }
+        });
+    }
+
+    Ok(unparse(&ast))
 }
 
 #[pymodule]
@@ -2208,6 +15809,64 @@ fn rust_ast_parser(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(remove_mut_from_type_specifiers, m)?)?;
     #[allow(clippy::unsafe_removed_from_name)]
     m.add_function(wrap_pyfunction!(count_unsafe_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(list_string_literals, m)?)?;
+    m.add_function(wrap_pyfunction!(list_function_string_literals, m)?)?;
+    m.add_function(wrap_pyfunction!(rewrite_printf_to_println, m)?)?;
+    m.add_function(wrap_pyfunction!(rewrite_fprintf_to_eprintln, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_pointer_arithmetic, m)?)?;
+    #[allow(clippy::unsafe_removed_from_name)]
+    m.add_function(wrap_pyfunction!(shrink_unsafe_blocks, m)?)?;
+    #[allow(clippy::unsafe_removed_from_name)]
+    m.add_function(wrap_pyfunction!(find_unnecessary_unsafe, m)?)?;
+    m.add_function(wrap_pyfunction!(rewrite_static_mut_globals, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_parameter_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(infer_slice_params, m)?)?;
+    m.add_function(wrap_pyfunction!(find_error_code_functions, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_error_code_to_result, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_to_method, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_test_module, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_struct_converters, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_layout_asserts, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_c_char_pointers, m)?)?;
+    m.add_function(wrap_pyfunction!(register_scalar_mapping, m)?)?;
+    m.add_function(wrap_pyfunction!(load_scalar_map, m)?)?;
+    m.add_function(wrap_pyfunction!(get_type_aliases, m)?)?;
+    m.add_function(wrap_pyfunction!(resolve_type_aliases, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_extern_declarations, m)?)?;
+    m.add_function(wrap_pyfunction!(strip_extern_blocks, m)?)?;
+    m.add_function(wrap_pyfunction!(cleanup_c2rust_artifacts, m)?)?;
+    m.add_function(wrap_pyfunction!(remove_unreachable_items, m)?)?;
+    m.add_function(wrap_pyfunction!(combine_as_modules, m)?)?;
+    m.add_function(wrap_pyfunction!(move_item_to_module, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_symbol_collisions, m)?)?;
+    m.add_function(wrap_pyfunction!(get_macro_definition, m)?)?;
+    m.add_function(wrap_pyfunction!(get_function_dependencies, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest_immutable_params, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_pointer_ownership, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze_struct_field_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(find_pure_functions, m)?)?;
+    m.add_function(wrap_pyfunction!(code_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(find_leak_patterns, m)?)?;
+    m.add_function(wrap_pyfunction!(find_ub_suspects, m)?)?;
+    m.add_function(wrap_pyfunction!(global_usage, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_ffi_shim, m)?)?;
+    m.add_function(wrap_pyfunction!(has_derive, m)?)?;
+    m.add_function(wrap_pyfunction!(implements_trait, m)?)?;
+    m.add_function(wrap_pyfunction!(infer_derives, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_default_impl, m)?)?;
+    m.add_function(wrap_pyfunction!(add_serde_capture, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_arbitrary_impl, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_fuzz_target, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_kani_harness, m)?)?;
+    m.add_function(wrap_pyfunction!(summarize_rustc_diagnostics, m)?)?;
+    m.add_function(wrap_pyfunction!(set_rustfmt_config, m)?)?;
+    m.add_function(wrap_pyfunction!(format_with_rustfmt, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_variadic_wrapper, m)?)?;
+    m.add_function(wrap_pyfunction!(defines_c_variadic, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_differential_driver, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_c_header, m)?)?;
+    m.add_function(wrap_pyfunction!(find_nightly_constructs, m)?)?;
+    m.add_function(wrap_pyfunction!(convert_to_no_std, m)?)?;
     Ok(())
 }
 
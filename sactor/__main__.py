@@ -1,14 +1,25 @@
 import argparse
+import json
 import os
+import re
+import shlex
 import sys
 
 from sactor import Sactor
 from sactor import logging as sactor_logging
-from sactor import utils
+from sactor import rust_ast_parser, utils
+from sactor.config_check import check_config, validate_config
+from sactor.planner import Planner, format_plan
+from sactor.unsafe_metrics import collect_metrics, format_metrics, metrics_csv
 
 logger = sactor_logging.get_logger(__name__)
-from sactor.test_generator import ExecutableTestGenerator, TestGeneratorResult
+from sactor.test_generator import (BenchmarkGenerator,
+                                   DifferentialDriverGenerator,
+                                   ExecutableTestGenerator,
+                                   FuzzTargetGenerator, TestGeneratorResult)
+from sactor.llm import BudgetExceededError, ReplayDivergenceError
 from sactor.test_runner import ExecutableTestRunner, TestRunnerResult
+from sactor.translator import TranslateResult
 
 
 def add_logging_arguments(parser: argparse.ArgumentParser) -> None:
@@ -17,6 +28,29 @@ def add_logging_arguments(parser: argparse.ArgumentParser) -> None:
         dest='log_dir',
         help='Override log directory (default: {result_dir}/logs).'
     )
+    parser.add_argument(
+        '-v', '--verbose',
+        action='count',
+        default=0,
+        help='Show more on the console, one level below logging.console_level per repetition.'
+    )
+    parser.add_argument(
+        '-q', '--quiet',
+        action='count',
+        default=0,
+        help='Show less on the console, one level above logging.console_level per repetition.'
+    )
+    parser.add_argument(
+        '--log-jsonl',
+        dest='log_jsonl',
+        action=argparse.BooleanOptionalAction,
+        default=None,
+        help='Write (or do not write) the JSON lines log alongside the text log, overriding logging.jsonl.'
+    )
+
+
+def _verbosity_from_args(args) -> int:
+    return getattr(args, 'verbose', 0) - getattr(args, 'quiet', 0)
 
 
 def _configure_logging_from_args(config, args, *, result_dir: str | None = None):
@@ -24,6 +58,8 @@ def _configure_logging_from_args(config, args, *, result_dir: str | None = None)
         config,
         result_dir=result_dir,
         log_dir_override=getattr(args, 'log_dir', None),
+        enable_jsonl_override=getattr(args, 'log_jsonl', None),
+        verbosity=_verbosity_from_args(args),
     )
 
 
@@ -33,7 +69,7 @@ def parse_translate(parser):
         type=str,
         nargs='?',
         default=None,
-        help='The input C file to translate to Rust. Omit to translate all C files found in compile_commands.json'
+        help='The input C file to translate to Rust. Omit to translate all C files found in compile_commands.json or the --make-project'
     )
 
     parser.add_argument(
@@ -115,6 +151,38 @@ def parse_translate(parser):
         help='Only translate C code into idiomatic Rust code, skipping the unidiomatic translation'
     )
 
+    parser.add_argument(
+        '--c2rust-translation',
+        type=str,
+        default=None,
+        help=('An existing c2rust translation of the input file (the output of `c2rust transpile`) to use as '
+              'the unidiomatic translation instead of translating with the LLM')
+    )
+
+    parser.add_argument(
+        '--resume',
+        action='store_true',
+        help=('Continue an interrupted translation from the checkpoint in the result directory, keeping the '
+              'accepted translations and attempt counts instead of translating everything again')
+    )
+
+    parser.add_argument(
+        '--record-replay',
+        type=str,
+        default=None,
+        metavar='BUNDLE',
+        help='Record every LLM interaction of the translation into the replay bundle directory BUNDLE'
+    )
+
+    parser.add_argument(
+        '--replay',
+        type=str,
+        default=None,
+        metavar='BUNDLE',
+        help=('Translate again answering every LLM query from the replay bundle BUNDLE recorded with '
+              '--record-replay, without network access, to reproduce the recorded run')
+    )
+
     parser.add_argument(
         '--continue-run-when-incomplete',
         action='store_true',
@@ -152,6 +220,225 @@ def parse_translate(parser):
         help='The compile commands json file for the project, generated by tools like "scan-build" or "bear"'
     )
 
+    parser.add_argument(
+        '--make-project',
+        type=str,
+        default=None,
+        help='Directory of a plain Makefile project; `make` is run to capture compile_commands.json (instead of --compile-commands-file)'
+    )
+
+    parser.add_argument(
+        '--make-args',
+        type=str,
+        default="",
+        help='Extra arguments passed to `make` when capturing a --make-project (e.g. "-j4 all")'
+    )
+
+
+def parse_retranslate(parser):
+    parser.add_argument(
+        'function',
+        type=str,
+        help='The C function to translate again'
+    )
+
+    parser.add_argument(
+        'input_file',
+        type=str,
+        help='The input C file of the earlier translation'
+    )
+
+    parser.add_argument(
+        'test_command_path',
+        type=str,
+        help='The path to the json file containing the test commands, need to follow the format specified in the README'
+    )
+
+    parser.add_argument(
+        '--type',
+        choices=['bin', 'lib'],
+        required=True,
+        help='Whether the target is a binary program or a library'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+    parser.add_argument(
+        '--build-dir',
+        '-b',
+        type=str,
+        help='The directory to use for the build process'
+    )
+
+    parser.add_argument(
+        '--result-dir',
+        '-r',
+        type=str,
+        help='The result directory of the earlier translation'
+    )
+
+    parser.add_argument(
+        '--llm-stat',
+        '-l',
+        type=str,
+        help=(
+            'The base path for LLM statistics json files; the function name and stage are added. '
+            'Default to {result_dir}/llm_stat.json'
+        )
+    )
+
+    parser.add_argument(
+        '--no-verify',
+        action='store_true',
+        help='Do not verify the generated Rust code'
+    )
+
+    parser.add_argument(
+        '--unidiomatic-only',
+        action='store_true',
+        help='Only translate the function again into unidiomatic Rust code'
+    )
+
+    parser.add_argument(
+        '--idiomatic-only',
+        action='store_true',
+        help='Only translate the function again into idiomatic Rust code, from its current unidiomatic translation'
+    )
+
+    parser.add_argument(
+        '--extra-compile-command',
+        type=str,
+        help='The extra compile command to use to compile the C code',
+    )
+
+    parser.add_argument(
+        '--executable-object',
+        '-e',
+        type=str,
+        action='append',
+        default=None,
+        help='Path to an executable object file or link argument; may be specified multiple times for library targets'
+    )
+
+    parser.add_argument(
+        '--link-args',
+        type=str,
+        default="",
+        help='Additional linker flags to apply when building intermediates (e.g. "-lm -lz").'
+    )
+
+
+def parse_watch(parser):
+    parser.add_argument(
+        'input_file',
+        type=str,
+        help='The input C file to translate and watch'
+    )
+
+    parser.add_argument(
+        'test_command_path',
+        type=str,
+        help='The path to the json file containing the test commands, need to follow the format specified in the README'
+    )
+
+    parser.add_argument(
+        '--type',
+        choices=['bin', 'lib'],
+        required=True,
+        help='Whether the target is a binary program or a library'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+    parser.add_argument(
+        '--build-dir',
+        '-b',
+        type=str,
+        help='The directory to use for the build process'
+    )
+
+    parser.add_argument(
+        '--result-dir',
+        '-r',
+        type=str,
+        help='The directory to save the translation in, default to sactor_result in the current directory'
+    )
+
+    parser.add_argument(
+        '--llm-stat',
+        '-l',
+        type=str,
+        help=(
+            'The base path for LLM statistics json files; the stage, and the function name of retranslations, are added. '
+            'Default to {result_dir}/llm_stat.json'
+        )
+    )
+
+    parser.add_argument(
+        '--no-verify',
+        action='store_true',
+        help='Do not verify the generated Rust code'
+    )
+
+    parser.add_argument(
+        '--unidiomatic-only',
+        action='store_true',
+        help='Only keep the unidiomatic Rust translation in sync'
+    )
+
+    parser.add_argument(
+        '--idiomatic-only',
+        action='store_true',
+        help='Only keep the idiomatic Rust translation in sync, translating from the current unidiomatic one'
+    )
+
+    parser.add_argument(
+        '--extra-compile-command',
+        type=str,
+        help='The extra compile command to use to compile the C code',
+    )
+
+    parser.add_argument(
+        '--executable-object',
+        '-e',
+        type=str,
+        action='append',
+        default=None,
+        help='Path to an executable object file or link argument; may be specified multiple times for library targets'
+    )
+
+    parser.add_argument(
+        '--link-args',
+        type=str,
+        default="",
+        help='Additional linker flags to apply when building intermediates (e.g. "-lm -lz").'
+    )
+
+    parser.add_argument(
+        '--interval',
+        type=float,
+        default=2.0,
+        help='Seconds between checks of the C file for changes (default: 2)'
+    )
+
+    parser.add_argument(
+        '--once',
+        action='store_true',
+        help='Translate what changed since the last sync once and exit, instead of watching'
+    )
+
 
 def parse_run_tests(parser):
     parser.add_argument(
@@ -207,6 +494,36 @@ def parse_run_tests(parser):
         help='The path to save the output json of the test run and the expected output, if the test fails. If not set, the output will not be saved.'
     )
 
+    parser.add_argument(
+        '--valgrind',
+        action='store_true',
+        help='Only avaliable for binary targets. Run the test sample under valgrind memcheck and attach the leak/invalid-access summary to the saved output json.'
+    )
+
+    parser.add_argument(
+        '--valgrind-suppressions',
+        type=str,
+        help='The valgrind suppression file to use with --valgrind. Defaults to `test_runner.valgrind_suppressions` in the config.'
+    )
+
+    parser.add_argument(
+        '--abs-tolerance',
+        type=float,
+        help='Only avaliable for binary targets. Accept floating-point numbers in the output that differ from the expected ones by at most this much. Defaults to `test_runner.float_abs_tolerance` in the config.'
+    )
+
+    parser.add_argument(
+        '--rel-tolerance',
+        type=float,
+        help='Only avaliable for binary targets. Accept floating-point numbers in the output that differ from the expected ones by at most this fraction of the larger value. Defaults to `test_runner.float_rel_tolerance` in the config.'
+    )
+
+    parser.add_argument(
+        '--minimize-with',
+        type=str,
+        help='Only avaliable for binary targets. The original C executable; if the test fails, its input is shrunk while the target still disagrees with this executable, and the minimal reproducer is written to the --save json.'
+    )
+
 
 def parse_generate_tests(parser):
     parser.add_argument(
@@ -294,21 +611,311 @@ def parse_generate_tests(parser):
         help='Only avaliable for binary targets. If set, the test samples will be fed to the target program via stdin.'
     )
 
+    parser.add_argument(
+        "--coverage-guided",
+        action='store_true',
+        help='Only avaliable for binary targets. After the LLM samples are generated, build the C program with gcc coverage instrumentation and add mutated samples that cover new branches until coverage stops improving.'
+    )
 
-def translate(parser, args):
-    if getattr(args, "test_command_override", None):
-        args.test_command_path = args.test_command_override
 
-    if not args.test_command_path:
-        parser.error('test_command_path is required')
+def parse_generate_fuzz(parser):
+    parser.add_argument(
+        'input_file',
+        type=str,
+        help='The original C file, used as the reference implementation'
+    )
 
-    try:
-        exec_obj = utils._normalize_executable_object_arg(args.executable_object)
-        result = Sactor.translate(
-            target_type=args.type,
-            test_cmd_path=args.test_command_path,
-            input_file=args.input_file,
-            compile_commands_file=args.compile_commands_file,
+    parser.add_argument(
+        'rust_file',
+        type=str,
+        help='The translated Rust file containing the function'
+    )
+
+    parser.add_argument(
+        'function',
+        type=str,
+        help='The name of the function to fuzz'
+    )
+
+    parser.add_argument(
+        '--out-dir',
+        '-o',
+        type=str,
+        default='fuzz',
+        help='The directory to write the fuzz crate to, default to `$PWD/fuzz`; the crate is placed in a subdirectory named after the function'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+    parser.add_argument(
+        '--compile-commands-file',
+        '-C',
+        type=str,
+        default="",
+        help='The compile commands json file for the project, used to compile the C reference'
+    )
+
+    parser.add_argument(
+        '--extra-compile-command',
+        type=str,
+        help='The extra compile command to use to compile the C reference'
+    )
+
+    parser.add_argument(
+        '--link-args',
+        type=str,
+        default="",
+        help='Additional linker flags for the C reference (e.g. "-lm -lz").'
+    )
+
+
+def parse_generate_diff(parser):
+    parser.add_argument(
+        'input_file',
+        type=str,
+        help='The original C file, used as the reference implementation'
+    )
+
+    parser.add_argument(
+        'rust_file',
+        type=str,
+        help='The translated Rust file containing the functions'
+    )
+
+    parser.add_argument(
+        'samples_file',
+        type=str,
+        help='A json file mapping each function name to a list of argument objects keyed by parameter name'
+    )
+
+    parser.add_argument(
+        '--out-dir',
+        '-o',
+        type=str,
+        default='diff',
+        help='The directory to write the driver crates to, default to `$PWD/diff`; each crate is placed in a subdirectory named after its function'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+    parser.add_argument(
+        '--compile-commands-file',
+        '-C',
+        type=str,
+        default="",
+        help='The compile commands json file for the project, used to compile the C reference'
+    )
+
+    parser.add_argument(
+        '--extra-compile-command',
+        type=str,
+        help='The extra compile command to use to compile the C reference'
+    )
+
+    parser.add_argument(
+        '--link-args',
+        type=str,
+        default="",
+        help='Additional linker flags for the C reference (e.g. "-lm -lz").'
+    )
+
+    parser.add_argument(
+        '--run',
+        action='store_true',
+        help='Build and run every driver and report the functions whose samples diverge'
+    )
+
+
+def parse_generate_header(parser):
+    parser.add_argument(
+        'rust_file',
+        type=str,
+        help='The translated Rust file whose exposed functions and #[repr(C)] types are declared'
+    )
+
+    parser.add_argument(
+        '--output',
+        '-o',
+        type=str,
+        help='The header file to write, default to the Rust file name with a `.h` extension'
+    )
+
+    parser.add_argument(
+        '--guard',
+        type=str,
+        help='The include guard macro, default to the upper-cased header file name, e.g. `MYLIB_H`'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+
+def parse_generate_bench(parser):
+    parser.add_argument(
+        'test_samples_path',
+        type=str,
+        help='The test samples json file whose inputs are benchmarked'
+    )
+
+    parser.add_argument(
+        'c_executable',
+        type=str,
+        help='The executable of the original C program'
+    )
+
+    parser.add_argument(
+        'rust_executable',
+        type=str,
+        help='The executable of the translated Rust program'
+    )
+
+    parser.add_argument(
+        '--out-dir',
+        '-o',
+        type=str,
+        default='bench',
+        help='The directory to write the benchmark crate to, default to `$PWD/bench`'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+    parser.add_argument(
+        "--feed-as-stdin",
+        action='store_true',
+        help='Feed the test samples to the programs via stdin instead of as arguments.'
+    )
+
+    parser.add_argument(
+        '--run',
+        action='store_true',
+        help='Run `cargo bench` on the generated crate and write report.json and report.md next to it'
+    )
+
+
+def parse_check_config(parser):
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to check, default to the one a translation would use'
+    )
+
+
+def parse_plan(parser):
+    parser.add_argument(
+        'input_file',
+        type=str,
+        nargs='?',
+        default=None,
+        help='The input C file to plan the translation of. Omit to plan all C files found in compile_commands.json'
+    )
+
+    parser.add_argument(
+        '--compile-commands-file',
+        '-C',
+        type=str,
+        default="",
+        help='The compile commands json file for the project, generated by tools like "scan-build" or "bear"'
+    )
+
+    parser.add_argument(
+        '--config',
+        '-c',
+        type=str,
+        dest='config_file',
+        help='The configuration file to use'
+    )
+
+    parser.add_argument(
+        '--unidiomatic-only',
+        action='store_true',
+        help='Plan only the unidiomatic translation'
+    )
+
+    parser.add_argument(
+        '--idiomatic-only',
+        action='store_true',
+        help='Plan only the idiomatic translation'
+    )
+
+    parser.add_argument(
+        '--json',
+        type=str,
+        default=None,
+        metavar='PATH',
+        help='Also write the plan, with the expected spend by model, stage and item, as JSON to PATH'
+    )
+
+
+def parse_metrics(parser):
+    parser.add_argument(
+        'path',
+        type=str,
+        help='A Rust file, or a crate or translation result directory whose Rust files are measured'
+    )
+
+    parser.add_argument(
+        '--format',
+        choices=['table', 'json', 'csv'],
+        default='table',
+        help='The output format, default to tables of the files and functions with the aggregate unsafe ratio'
+    )
+
+    parser.add_argument(
+        '--output',
+        '-o',
+        type=str,
+        default=None,
+        help='The file to write the metrics to, default to stdout'
+    )
+
+    parser.add_argument(
+        '--no-functions',
+        action='store_true',
+        help='Leave out the table of the functions of each file (table format only)'
+    )
+
+
+def translate(parser, args):
+    if getattr(args, "test_command_override", None):
+        args.test_command_path = args.test_command_override
+
+    if not args.test_command_path:
+        parser.error('test_command_path is required')
+
+    try:
+        exec_obj = utils._normalize_executable_object_arg(args.executable_object)
+        result = Sactor.translate(
+            target_type=args.type,
+            test_cmd_path=args.test_command_path,
+            input_file=args.input_file,
+            compile_commands_file=args.compile_commands_file,
+            make_project=args.make_project,
+            make_args=args.make_args,
             entry_tu_file=args.entry_tu_file,
             result_dir=args.result_dir,
             build_dir=args.build_dir,
@@ -322,14 +929,90 @@ def translate(parser, args):
             link_args=args.link_args,
             llm_stat=args.llm_stat,
             log_dir_override=getattr(args, 'log_dir', None),
+            log_jsonl=getattr(args, 'log_jsonl', None),
+            log_verbosity=_verbosity_from_args(args),
+            c2rust_translation_file=args.c2rust_translation,
+            resume=args.resume,
+            record_replay=args.record_replay,
+            replay=args.replay,
         )
     except (FileNotFoundError, ValueError) as exc:
         parser.error(str(exc))
+    except BudgetExceededError as exc:
+        logger.error('❌ %s, raise it in the [cost] config and continue with --resume', exc, extra={"plain": True})
+        sys.exit(1)
+    except ReplayDivergenceError as exc:
+        logger.error('❌ %s', exc, extra={"plain": True})
+        sys.exit(1)
 
     if result.any_failed:
         sys.exit(1)
 
 
+def retranslate(parser, args):
+    try:
+        result = Sactor.retranslate(
+            function_name=args.function,
+            target_type=args.type,
+            test_cmd_path=args.test_command_path,
+            input_file=args.input_file,
+            result_dir=args.result_dir,
+            build_dir=args.build_dir,
+            config_file=args.config_file,
+            no_verify=args.no_verify,
+            unidiomatic_only=args.unidiomatic_only,
+            idiomatic_only=args.idiomatic_only,
+            extra_compile_command=args.extra_compile_command,
+            executable_object=args.executable_object,
+            link_args=args.link_args,
+            llm_stat=args.llm_stat,
+            log_dir_override=getattr(args, 'log_dir', None),
+            log_jsonl=getattr(args, 'log_jsonl', None),
+            log_verbosity=_verbosity_from_args(args),
+        )
+    except (FileNotFoundError, ValueError) as exc:
+        parser.error(str(exc))
+    except BudgetExceededError as exc:
+        logger.error('❌ %s', exc, extra={"plain": True})
+        sys.exit(1)
+
+    if result != TranslateResult.SUCCESS:
+        logger.error('❌ Failed to retranslate %s: %s', args.function, result, extra={"plain": True})
+        sys.exit(1)
+
+
+def watch(parser, args):
+    try:
+        ok = Sactor.watch(
+            target_type=args.type,
+            test_cmd_path=args.test_command_path,
+            input_file=args.input_file,
+            result_dir=args.result_dir,
+            build_dir=args.build_dir,
+            config_file=args.config_file,
+            no_verify=args.no_verify,
+            unidiomatic_only=args.unidiomatic_only,
+            idiomatic_only=args.idiomatic_only,
+            extra_compile_command=args.extra_compile_command,
+            executable_object=args.executable_object,
+            link_args=args.link_args,
+            llm_stat=args.llm_stat,
+            log_dir_override=getattr(args, 'log_dir', None),
+            log_jsonl=getattr(args, 'log_jsonl', None),
+            log_verbosity=_verbosity_from_args(args),
+            interval=args.interval,
+            once=args.once,
+        )
+    except (FileNotFoundError, ValueError) as exc:
+        parser.error(str(exc))
+    except BudgetExceededError as exc:
+        logger.error('❌ %s', exc, extra={"plain": True})
+        sys.exit(1)
+
+    if not ok:
+        sys.exit(1)
+
+
 def run_tests(parser, args):
     config = utils.try_load_config(args.config_file)
     _configure_logging_from_args(config, args)
@@ -340,6 +1023,15 @@ def run_tests(parser, args):
         if args.feed_as_stdin is not None:
             parser.error(
                 '--feed-as-stdin is only avaliable for binary targets')
+        if args.valgrind:
+            parser.error('--valgrind is only avaliable for binary targets')
+        if args.abs_tolerance is not None or args.rel_tolerance is not None:
+            parser.error('--abs-tolerance and --rel-tolerance are only avaliable for binary targets')
+        if args.minimize_with:
+            parser.error('--minimize-with is only avaliable for binary targets')
+
+    if args.valgrind_suppressions and not args.valgrind:
+        parser.error('--valgrind-suppressions requires --valgrind')
 
     if args.type == 'bin':
         if args.feed_as_args is None and args.feed_as_stdin is None:
@@ -360,9 +1052,16 @@ def run_tests(parser, args):
             args.test_samples_path,
             target,
             config_path=args.config_file,
-            feed_as_arguments=feed_as_args
+            feed_as_arguments=feed_as_args,
+            valgrind=args.valgrind,
+            valgrind_suppressions=args.valgrind_suppressions,
+            abs_tolerance=args.abs_tolerance,
+            rel_tolerance=args.rel_tolerance,
+            minimize_reference=os.path.abspath(args.minimize_with) if args.minimize_with else None,
         )
         result = test_runner.run_test(args.test_sample_number, args.save)
+        if test_runner.valgrind_summary is not None:
+            logger.info('Valgrind summary: %s', test_runner.valgrind_summary, extra={"plain": True})
         if result[0] == TestRunnerResult.PASSED:
             logger.info('✅ Test %d passed successfully!', args.test_sample_number, extra={"plain": True})
             sys.exit(0)
@@ -371,6 +1070,8 @@ def run_tests(parser, args):
             logger.error('Diff (-actual +expected):', extra={"plain": True})
             if result[1]:
                 logger.error('%s', result[1], extra={"plain": True})
+            if test_runner.minimized is not None:
+                logger.error('Minimal failing input: %s', test_runner.minimized['input'], extra={"plain": True})
             sys.exit(1)
 
     elif args.type == 'lib':
@@ -390,6 +1091,8 @@ def generate_tests(parser, args):
         if args.feed_as_stdin is not None:
             parser.error(
                 '--feed-as-stdin is only avaliable for binary targets')
+        if args.coverage_guided:
+            parser.error('--coverage-guided is only avaliable for binary targets')
 
     if args.type == 'bin':
         if args.feed_as_args is None and args.feed_as_stdin is None:
@@ -423,6 +1126,9 @@ def generate_tests(parser, args):
 
         result = test_generator.generate_tests(args.count)
         if result == TestGeneratorResult.SUCCESS:
+            if args.coverage_guided:
+                added = test_generator.extend_by_coverage()
+                logger.info('Added %d coverage-guided test samples', added)
             logger.info('✅ Tests generated successfully!', extra={"plain": True})
             test_generator.create_test_task(
                 args.out_test_task_path,
@@ -439,6 +1145,194 @@ def generate_tests(parser, args):
         raise ValueError(f'Invalid type: {args.type}')
 
 
+def generate_fuzz(parser, args):
+    config = utils.try_load_config(args.config_file)
+    _configure_logging_from_args(config, args)
+
+    if not os.path.isfile(args.input_file):
+        parser.error(f'C file not found: {args.input_file}')
+    if not os.path.isfile(args.rust_file):
+        parser.error(f'Rust file not found: {args.rust_file}')
+
+    generator = FuzzTargetGenerator(
+        c_file=args.input_file,
+        rust_code=utils.read_file(args.rust_file),
+        out_dir=args.out_dir,
+        compile_commands_file=args.compile_commands_file,
+        extra_compile_command=args.extra_compile_command,
+        link_args=shlex.split(args.link_args),
+    )
+    try:
+        crate_dir = generator.generate(args.function)
+    except (ValueError, RuntimeError) as exc:
+        logger.error('❌ Failed to generate the fuzz target: %s', exc, extra={"plain": True})
+        sys.exit(1)
+    logger.info('✅ Fuzz target generated in %s', crate_dir, extra={"plain": True})
+
+
+def generate_diff(parser, args):
+    config = utils.try_load_config(args.config_file)
+    _configure_logging_from_args(config, args)
+
+    for path in (args.input_file, args.rust_file, args.samples_file):
+        if not os.path.isfile(path):
+            parser.error(f'File not found: {path}')
+    samples = json.loads(utils.read_file(args.samples_file))
+    if not isinstance(samples, dict):
+        parser.error('The samples file must map function names to lists of argument objects')
+
+    generator = DifferentialDriverGenerator(
+        c_file=args.input_file,
+        rust_code=utils.read_file(args.rust_file),
+        out_dir=args.out_dir,
+        compile_commands_file=args.compile_commands_file,
+        extra_compile_command=args.extra_compile_command,
+        link_args=shlex.split(args.link_args),
+    )
+    diverged_functions = []
+    for function_name, function_samples in samples.items():
+        try:
+            crate_dir = generator.generate(function_name, function_samples)
+        except (ValueError, RuntimeError) as exc:
+            logger.error('❌ Failed to generate the driver for %s: %s',
+                         function_name, exc, extra={"plain": True})
+            sys.exit(1)
+        logger.info('✅ Differential driver for %s generated in %s',
+                    function_name, crate_dir, extra={"plain": True})
+        if not args.run:
+            continue
+        try:
+            summary = generator.run(crate_dir)
+        except RuntimeError as exc:
+            logger.error('❌ %s', exc, extra={"plain": True})
+            sys.exit(1)
+        if summary['diverged']:
+            diverged_functions.append(function_name)
+            logger.error('❌ %s diverged on %d/%d samples: %s\n%s',
+                         function_name, len(summary['diverged']), summary['total'],
+                         summary['diverged'], summary['log'], extra={"plain": True})
+        else:
+            logger.info('✅ %s matched the C original on all %d samples',
+                        function_name, summary['total'], extra={"plain": True})
+    if diverged_functions:
+        sys.exit(1)
+
+
+def generate_header(parser, args):
+    config = utils.try_load_config(args.config_file)
+    _configure_logging_from_args(config, args)
+
+    if not os.path.isfile(args.rust_file):
+        parser.error(f'Rust file not found: {args.rust_file}')
+    output = args.output or os.path.splitext(args.rust_file)[0] + '.h'
+    guard = args.guard or re.sub(r'[^0-9A-Za-z]', '_', os.path.basename(output)).upper()
+
+    try:
+        header = rust_ast_parser.generate_c_header(utils.read_file(args.rust_file), guard)
+    except ValueError as exc:
+        logger.error('❌ Failed to generate the header: %s', exc, extra={"plain": True})
+        sys.exit(1)
+    with open(output, 'w', encoding='utf-8') as f:
+        f.write(header)
+    logger.info('✅ Header written to %s', output, extra={"plain": True})
+
+
+def generate_bench(parser, args):
+    config = utils.try_load_config(args.config_file)
+    _configure_logging_from_args(config, args)
+
+    for path in (args.test_samples_path, args.c_executable, args.rust_executable):
+        if not os.path.isfile(path):
+            parser.error(f'File not found: {path}')
+
+    generator = BenchmarkGenerator(
+        test_samples_path=args.test_samples_path,
+        c_executable=args.c_executable,
+        rust_executable=args.rust_executable,
+        out_dir=args.out_dir,
+        feed_as_arguments=not args.feed_as_stdin,
+    )
+    crate_dir = generator.generate()
+    logger.info('✅ Benchmark crate generated in %s', crate_dir, extra={"plain": True})
+    if args.run:
+        try:
+            report = generator.run()
+        except RuntimeError as exc:
+            logger.error('❌ Failed to run the benchmarks: %s', exc, extra={"plain": True})
+            sys.exit(1)
+        logger.info('Geometric mean of Rust / C time: %.2fx (details in %s)',
+                    report['geomean_ratio'] or float('nan'),
+                    os.path.join(crate_dir, 'report.md'), extra={"plain": True})
+
+
+def plan(parser, args):
+    if not args.input_file and not args.compile_commands_file:
+        parser.error('input_file is required unless --compile-commands-file is provided')
+    if args.unidiomatic_only and args.idiomatic_only:
+        parser.error('Only one of --unidiomatic-only and --idiomatic-only can be set')
+
+    try:
+        config = utils.try_load_config(args.config_file)
+        _configure_logging_from_args(config, args)
+        validate_config(config)
+        planner = Planner(config, unidiomatic_only=args.unidiomatic_only, idiomatic_only=args.idiomatic_only)
+        translation_plan = planner.plan(args.input_file, args.compile_commands_file)
+    except (FileNotFoundError, ValueError) as exc:
+        parser.error(str(exc))
+
+    logger.info('%s', format_plan(translation_plan), extra={"plain": True})
+    if args.json:
+        with open(args.json, 'w', encoding='utf-8') as f:
+            json.dump(translation_plan.to_dict(), f, indent=4)
+        logger.info('Plan written to %s', args.json, extra={"plain": True})
+
+
+def metrics(parser, args):
+    try:
+        report = collect_metrics(args.path)
+    except FileNotFoundError as exc:
+        parser.error(str(exc))
+    if not report.files:
+        parser.error(f'No Rust files found in {args.path}')
+
+    match args.format:
+        case 'json':
+            output = json.dumps(report.to_dict(), indent=4) + '\n'
+        case 'csv':
+            output = metrics_csv(report)
+        case _:
+            output = format_metrics(report, functions=not args.no_functions) + '\n'
+    if args.output:
+        with open(args.output, 'w', encoding='utf-8') as f:
+            f.write(output)
+    else:
+        sys.stdout.write(output)
+
+
+def check_config_command(parser, args):
+    try:
+        config = utils.try_load_config(args.config_file)
+    except FileNotFoundError as exc:
+        parser.error(str(exc))
+    except (ValueError, TypeError) as exc:
+        # TOML syntax errors, and tables given where the defaults have values or vice versa
+        logger.error('❌ Failed to load the configuration: %s', exc, extra={"plain": True})
+        sys.exit(1)
+    _configure_logging_from_args(config, args)
+
+    issues = check_config(config)
+    for issue in issues:
+        if issue.severity == "error":
+            logger.error('❌ %s', issue, extra={"plain": True})
+        else:
+            logger.warning('warning: %s', issue, extra={"plain": True})
+    errors = sum(issue.severity == "error" for issue in issues)
+    if errors:
+        logger.error('%d error(s), %d warning(s)', errors, len(issues) - errors, extra={"plain": True})
+        sys.exit(1)
+    logger.info('✅ The configuration is valid (%d warning(s))', len(issues), extra={"plain": True})
+
+
 def main():
     logging_parent = argparse.ArgumentParser(add_help=False)
     add_logging_arguments(logging_parent)
@@ -461,6 +1355,36 @@ def main():
         parents=[logging_parent]
     )
 
+    retranslate_parser = subparsers.add_parser(
+        'retranslate',
+        help='Translate one function of an earlier translation again',
+        parents=[logging_parent]
+    )
+
+    watch_parser = subparsers.add_parser(
+        'watch',
+        help='Keep the translation of a C file in sync while the file is edited',
+        parents=[logging_parent]
+    )
+
+    plan_parser = subparsers.add_parser(
+        'plan',
+        help='Show the translation order, difficulty and expected LLM cost without querying a model',
+        parents=[logging_parent]
+    )
+
+    metrics_parser = subparsers.add_parser(
+        'metrics',
+        help='Measure the unsafe tokens of the Rust files of a crate or translation result, by file and function',
+        parents=[logging_parent]
+    )
+
+    check_config_parser = subparsers.add_parser(
+        'check-config',
+        help='Check the configuration for invalid values, unknown keys and unusable models',
+        parents=[logging_parent]
+    )
+
     test_runner_parser = subparsers.add_parser(
         'run-tests',
         help='Run tests on the target program or library',
@@ -473,19 +1397,70 @@ def main():
         parents=[logging_parent]
     )
 
+    generate_fuzz_parser = subparsers.add_parser(
+        'generate-fuzz',
+        help='Generate a cargo-fuzz target comparing a translated function with the C original',
+        parents=[logging_parent]
+    )
+
+    generate_diff_parser = subparsers.add_parser(
+        'generate-diff',
+        help='Generate drivers comparing translated library functions with the C originals on fixed arguments',
+        parents=[logging_parent]
+    )
+
+    generate_header_parser = subparsers.add_parser(
+        'generate-header',
+        help='Generate a C header declaring the exposed functions and #[repr(C)] types of a translation',
+        parents=[logging_parent]
+    )
+
+    generate_bench_parser = subparsers.add_parser(
+        'generate-bench',
+        help='Generate a criterion benchmark comparing the C program with its Rust translation',
+        parents=[logging_parent]
+    )
+
     parse_translate(translate_parser)
+    parse_retranslate(retranslate_parser)
+    parse_watch(watch_parser)
+    parse_plan(plan_parser)
+    parse_metrics(metrics_parser)
+    parse_check_config(check_config_parser)
     parse_run_tests(test_runner_parser)
     parse_generate_tests(generate_tests_parser)
+    parse_generate_fuzz(generate_fuzz_parser)
+    parse_generate_diff(generate_diff_parser)
+    parse_generate_header(generate_header_parser)
+    parse_generate_bench(generate_bench_parser)
 
     args = parser.parse_args()
 
     match args.subcommand:
         case 'translate':
             translate(parser, args)
+        case 'retranslate':
+            retranslate(parser, args)
+        case 'watch':
+            watch(parser, args)
+        case 'plan':
+            plan(parser, args)
+        case 'metrics':
+            metrics(parser, args)
+        case 'check-config':
+            check_config_command(parser, args)
         case 'run-tests':
             run_tests(parser, args)
         case 'generate-tests':
             generate_tests(parser, args)
+        case 'generate-fuzz':
+            generate_fuzz(parser, args)
+        case 'generate-diff':
+            generate_diff(parser, args)
+        case 'generate-header':
+            generate_header(parser, args)
+        case 'generate-bench':
+            generate_bench(parser, args)
         case _:
             parser.print_help()
 
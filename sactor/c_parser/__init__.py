@@ -3,6 +3,8 @@ from .enum_info import EnumValueInfo, EnumInfo
 from .function_info import FunctionInfo
 from .struct_info import StructInfo
 from .global_var_info import GlobalVarInfo
+from .macro_constant import MacroConstant
+from .macro_function import MacroFunction
 from .refs import (
     SymbolRef,
     FunctionDependencyRef,
@@ -18,6 +20,8 @@ __all__ = [
     'StructInfo',
     'FunctionInfo',
     'GlobalVarInfo',
+    'MacroConstant',
+    'MacroFunction',
     'SymbolRef',
     'FunctionDependencyRef',
     'StructRef',
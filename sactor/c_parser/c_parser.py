@@ -9,9 +9,18 @@ import tempfile
 from sactor import logging as sactor_logging, utils
 from sactor.utils import read_file, read_file_lines
 
+from .concurrency import is_pthread_call
+from .cxx_classes import (CONSTRUCTOR, DESTRUCTOR, METHOD, OPERATOR_TRAITS, CxxClass, CxxMethod,
+                          is_cxx_source, parse_args, unsupported_type)
 from .enum_info import EnumInfo, EnumValueInfo
+from .errno_api import ERRNO_ACCESSORS, ERRNO_FUNCTIONS, ERRNO_READERS
 from .function_info import FunctionInfo
 from .global_var_info import GlobalVarInfo
+from .goto_plan import GotoLabel, classify_goto_label
+from .macro_constant import MacroConstant, infer_macro_constant
+from .macro_function import MacroFunction, translate_macro_function
+from .signals import SignalRegistration, is_signal_registrar
+from .stdio_api import is_stdio_call
 from .struct_info import StructInfo
 from clang.cindex import CursorKind
 from .refs import FunctionDependencyRef, StructRef, EnumRef, GlobalVarRef, SymbolRef
@@ -22,6 +31,10 @@ standard_io = [
     "stderr",
 ]
 
+# `setjmp` is a macro over one of these in the C libraries
+setjmp_functions = {"setjmp", "_setjmp", "sigsetjmp", "__sigsetjmp"}
+longjmp_functions = {"longjmp", "_longjmp", "siglongjmp", "__longjmp_chk"}
+
 
 logger = sactor_logging.get_logger(__name__)
 
@@ -30,11 +43,13 @@ class CParser:
     def __init__(self, filename, extra_args=None, omit_error=False, raw_filename=None):
         self.filename = filename
         self.raw_filename = raw_filename if raw_filename else filename
+        # C++ sources are parsed with clang's C++ front end, see `cxx_classes`
+        self.is_cxx = is_cxx_source(self.raw_filename)
 
         # Parse the C file
         index = cindex.Index.create()
         self.compiler_include_paths = utils.get_compiler_include_paths()
-        args = ['-x', 'c', '-std=c99'] + (extra_args or [])
+        args = parse_args(self.raw_filename) + (extra_args or [])
         args.extend([f"-I{path}" for path in self.compiler_include_paths])
         self.translation_unit = index.parse(
             self.filename, args=args, options=cindex.TranslationUnit.PARSE_DETAILED_PROCESSING_RECORD)
@@ -61,13 +76,21 @@ class CParser:
         self._macro_def_cursors: list[cindex.Cursor] = []
         self._macro_expand_cursors: list[cindex.Cursor] = []
         self._macro_def_map: dict[str, cindex.Cursor] = {}
-        self._macro_defs_for_function: dict[str, list[str]] = {}
+        self._macro_closure_cache: dict[str, list[tuple[str, cindex.Cursor | None]]] = {}
+        self._macro_constants: dict[str, MacroConstant] | None = None
+        self._macro_functions: dict[str, MacroFunction] | None = None
         self._raw_file_cache: dict[str, str] = {}
         self._skipped_ranges_cache: dict[str, list[tuple[int, int]]] = {}
         self._manual_skip_cache: dict[str, list[tuple[int, int]]] = {}
+        self._cxx_classes: dict[str, CxxClass] = {}
+        # the class definition followed by its out-of-line member function definitions
+        self._cxx_class_nodes: dict[str, list[cindex.Cursor]] = {}
+        self._cxx_unsupported: list[str] = []
         
         self._intrinsic_alias = _discover_intrinsic_aliases()
         self._type_alias: dict[str, str] = self._extract_type_alias()
+        if self.is_cxx:
+            self._extract_cxx_classes()
         self._extract_structs_unions()
         self._update_structs_unions()
 
@@ -485,6 +508,18 @@ class CParser:
         return list(self._global_vars.values())
     
 
+    def get_cxx_class(self, class_name) -> CxxClass:
+        if class_name not in self._cxx_classes:
+            raise ValueError(f"Class {class_name} not found")
+        return self._cxx_classes[class_name]
+
+    def get_cxx_classes(self) -> list[CxxClass]:
+        return list(self._cxx_classes.values())
+
+    def get_unsupported_cxx_constructs(self) -> list[str]:
+        """The constructs of a C++ input outside the supported subset, as `file:line: description`."""
+        return list(self._cxx_unsupported)
+
     def get_enum_info(self, enum_name):
         """
         Raises ValueError if the enum is not found.
@@ -655,6 +690,11 @@ class CParser:
         """
         for function in self._functions.values():
             self._update_function_dependencies(function)
+        for function in self._functions.values():
+            for registration in function.signal_registrations:
+                handler = self._functions.get(registration.handler or "")
+                if handler is not None and registration.signal not in handler.handled_signals:
+                    handler.handled_signals.append(registration.signal)
 
     def _extract_functions(self):
         """
@@ -668,13 +708,130 @@ class CParser:
             if node.location and not self._is_in_system_header(node):
                 name = node.spelling
                 # ignore unnamed structs TODO: is this good?
-                if name.find("unnamed at") == -1:
+                if name.find("unnamed at") == -1 and name not in self._cxx_classes:
                     dependencies = []
                     struct_info = StructInfo(node, name, dependencies)
                     self._structs_unions[name] = struct_info
         for child in node.get_children():
             self._collect_structs_and_unions(child)
 
+    def _extract_cxx_classes(self):
+        """
+        Collects the classes of a C++ translation unit (class definitions, and
+        structs with member functions or bases), and the constructs outside
+        the supported subset.
+        """
+        def report(node, description):
+            self._cxx_unsupported.append(f"{node.location.file}:{node.location.line}: {description}")
+
+        unsupported_kinds = {
+            CursorKind.CLASS_TEMPLATE: "template",
+            CursorKind.CLASS_TEMPLATE_PARTIAL_SPECIALIZATION: "template",
+            CursorKind.FUNCTION_TEMPLATE: "template",
+            CursorKind.CXX_THROW_EXPR: "exception",
+            CursorKind.CXX_TRY_STMT: "exception",
+        }
+        out_of_line: list[cindex.Cursor] = []
+        for top_level in self.translation_unit.cursor.get_children():
+            if not top_level.location.file or self._is_in_system_header(top_level):
+                continue
+            for node in top_level.walk_preorder():
+                kind = node.kind
+                if kind in unsupported_kinds:
+                    what = f"{unsupported_kinds[kind]} `{node.spelling}`" if node.spelling else unsupported_kinds[kind]
+                    report(node, f"{what} is not supported")
+                elif kind in (CursorKind.CLASS_DECL, CursorKind.STRUCT_DECL) and node.is_definition():
+                    members = {child.kind for child in node.get_children()}
+                    if kind == CursorKind.CLASS_DECL or members & {
+                        CursorKind.CXX_METHOD, CursorKind.CONSTRUCTOR, CursorKind.DESTRUCTOR,
+                        CursorKind.CXX_BASE_SPECIFIER,
+                    }:
+                        self._cxx_classes[node.spelling] = self._cxx_class_info(node, report)
+                        self._cxx_class_nodes[node.spelling] = [node]
+                elif kind in (CursorKind.CXX_METHOD, CursorKind.CONSTRUCTOR, CursorKind.DESTRUCTOR) \
+                        and node.is_definition() and node.semantic_parent != node.lexical_parent:
+                    out_of_line.append(node)
+        for node in out_of_line:
+            class_nodes = self._cxx_class_nodes.get(node.semantic_parent.spelling)
+            if class_nodes is not None:
+                class_nodes.append(node)
+
+        class_names = set(self._cxx_classes)
+        for cls in self._cxx_classes.values():
+            node = self._cxx_class_nodes[cls.name][0]
+            typed = [(f"field `{cls.name}::{name}`", spelling) for name, spelling in cls.fields]
+            for method in cls.methods:
+                if method.name.startswith("operator") and method.name not in OPERATOR_TRAITS:
+                    report(node, f"operator `{cls.name}::{method.name}` is not supported")
+                typed += [(f"parameter `{arg}` of `{cls.name}::{method.name}`", spelling)
+                          for arg, spelling in method.arguments]
+                if method.kind == METHOD:
+                    typed.append((f"the result of `{cls.name}::{method.name}`", method.return_type))
+            for what, spelling in typed:
+                if unsupported_type(spelling, class_names):
+                    report(node, f"{what} has type `{spelling}`, outside the supported subset "
+                                 "(primitives, classes, `std::string` and `std::vector`)")
+
+    @staticmethod
+    def _cxx_class_info(node, report) -> CxxClass:
+        """The fields, member functions and base of a class definition; `report` takes unsupported constructs."""
+        cls = CxxClass(node.spelling, f"{node.location.file}:{node.location.line}")
+        method_kinds = {
+            CursorKind.CXX_METHOD: METHOD,
+            CursorKind.CONSTRUCTOR: CONSTRUCTOR,
+            CursorKind.DESTRUCTOR: DESTRUCTOR,
+        }
+        bases = []
+        for child in node.get_children():
+            public = child.access_specifier == cindex.AccessSpecifier.PUBLIC
+            if child.kind == CursorKind.CXX_BASE_SPECIFIER:
+                if any(token.spelling == "virtual" for token in utils.cursor_get_tokens(child)):
+                    report(child, f"virtual inheritance in class `{cls.name}` is not supported")
+                bases.append(child.referenced.spelling if child.referenced else child.type.spelling)
+            elif child.kind == CursorKind.FIELD_DECL:
+                cls.fields.append((child.spelling, child.type.spelling))
+                if public:
+                    cls.public_fields.add(child.spelling)
+            elif child.kind in method_kinds:
+                cls.methods.append(CxxMethod(
+                    child.spelling,
+                    method_kinds[child.kind],
+                    child.result_type.spelling,
+                    [(arg.spelling, arg.type.spelling) for arg in child.get_arguments()],
+                    is_const=child.is_const_method(),
+                    is_static=child.is_static_method(),
+                    is_virtual=child.is_virtual_method(),
+                    is_public=public,
+                ))
+        if len(bases) > 1:
+            report(node, f"multiple inheritance in class `{cls.name}` is not supported")
+        elif bases:
+            cls.base = bases[0]
+        return cls
+
+    def _collect_cxx_class_refs(self, node) -> list[str]:
+        """The classes a function uses, with the classes they depend on listed before them."""
+        ordered: list[str] = []
+        visited: set[str] = set()
+
+        def visit(name: str):
+            if name in visited:
+                return
+            visited.add(name)
+            cls = self._cxx_classes[name]
+            dependencies = [cls.base] if cls.base else []
+            dependencies += [word for _, spelling in cls.fields for word in re.findall(r"\w+", spelling)]
+            for dependency in dependencies:
+                if dependency in self._cxx_classes:
+                    visit(dependency)
+            ordered.append(name)
+
+        for cursor in node.walk_preorder():
+            if cursor.kind == CursorKind.TYPE_REF and cursor.referenced is not None \
+                    and cursor.referenced.spelling in self._cxx_classes:
+                visit(cursor.referenced.spelling)
+        return ordered
+
     def _get_all_used_structs(self) -> dict[str, StructInfo]:
         used = {}
         queue = deque()
@@ -756,6 +913,27 @@ class CParser:
                         function_info.usr = node.get_usr()
                     except Exception:
                         function_info.usr = ""
+                    try:
+                        function_info.is_variadic = node.type.is_function_variadic()
+                    except Exception:
+                        function_info.is_variadic = False
+                    function_info.goto_labels = self._collect_goto_labels(node)
+                    called = {
+                        cursor.spelling for cursor in node.walk_preorder()
+                        if cursor.kind == CursorKind.CALL_EXPR
+                    }
+                    function_info.uses_setjmp = bool(called & setjmp_functions)
+                    function_info.uses_longjmp = bool(called & longjmp_functions)
+                    function_info.pthread_calls = sorted(filter(is_pthread_call, called))
+                    function_info.errno_calls = sorted(called & ERRNO_FUNCTIONS.keys())
+                    function_info.uses_errno = bool(called & (ERRNO_ACCESSORS | ERRNO_READERS))
+                    function_info.sets_errno = self._assigns_errno(node)
+                    function_info.stdio_calls = sorted(filter(is_stdio_call, called))
+                    function_info.fopen_modes = self._collect_fopen_modes(node)
+                    if any(map(is_signal_registrar, called)):
+                        function_info.signal_registrations = self._collect_signal_registrations(node)
+                    if self._cxx_classes:
+                        function_info.cxx_classes = self._collect_cxx_class_refs(node)
                     # Populate new reference lists (struct/enum/global) for this function
                     function_info.struct_dependency_refs = self._collect_struct_refs(node)
                     function_info.enum_dependency_refs = self._collect_enum_refs(node)
@@ -766,6 +944,114 @@ class CParser:
         for child in node.get_children():
             self._extract_function_info(child)
 
+    @staticmethod
+    def _collect_signal_registrations(node) -> list[SignalRegistration]:
+        """
+        The `signal(SIG, handler)` and `sigaction(SIG, &action, ...)` calls of a
+        function; a `sigaction` takes the handler last assigned to `sa_handler`
+        or `sa_sigaction` before it.
+        """
+        def handler_name(expr) -> str | None:
+            for cursor in expr.walk_preorder():
+                if cursor.kind == CursorKind.DECL_REF_EXPR and cursor.referenced is not None \
+                        and cursor.referenced.kind == CursorKind.FUNCTION_DECL:
+                    return cursor.spelling
+            tokens = [t.spelling for t in expr.get_tokens()]
+            return next((t for t in tokens if t in ("SIG_IGN", "SIG_DFL")), None)
+
+        registrations: list[SignalRegistration] = []
+        action_handler: str | None = None
+        for cursor in node.walk_preorder():
+            if cursor.kind == CursorKind.BINARY_OPERATOR:
+                children = list(cursor.get_children())
+                if (len(children) == 2 and children[0].kind == CursorKind.MEMBER_REF_EXPR
+                        and children[0].spelling in ("sa_handler", "sa_sigaction")):
+                    action_handler = handler_name(children[1])
+                continue
+            if cursor.kind != CursorKind.CALL_EXPR or not is_signal_registrar(cursor.spelling):
+                continue
+            args = list(cursor.get_arguments())
+            if len(args) < 2:
+                continue
+            signal = "".join(t.spelling for t in args[0].get_tokens())
+            handler = action_handler if cursor.spelling == "sigaction" else handler_name(args[1])
+            registration = SignalRegistration(signal, handler)
+            if registration not in registrations:
+                registrations.append(registration)
+        return registrations
+
+    @staticmethod
+    def _collect_fopen_modes(node) -> list[str]:
+        """The string literal modes passed to `fopen`/`fdopen`, in order of first use."""
+        modes: list[str] = []
+        for cursor in node.walk_preorder():
+            if cursor.kind != CursorKind.CALL_EXPR or cursor.spelling not in ("fopen", "fdopen"):
+                continue
+            args = list(cursor.get_arguments())
+            if len(args) != 2:
+                continue
+            literals = [t.spelling for t in args[1].get_tokens() if t.spelling.startswith('"')]
+            if len(literals) == 1 and literals[0][1:-1] not in modes:
+                modes.append(literals[0][1:-1])
+        return modes
+
+    @staticmethod
+    def _assigns_errno(node) -> bool:
+        """Whether the function body assigns to `errno` (which expands to a call)."""
+        for cursor in node.walk_preorder():
+            if cursor.kind not in (CursorKind.BINARY_OPERATOR, CursorKind.COMPOUND_ASSIGNMENT_OPERATOR):
+                continue
+            children = list(cursor.get_children())
+            if len(children) != 2 or not any(
+                c.kind == CursorKind.CALL_EXPR and c.spelling in ERRNO_ACCESSORS
+                for c in children[0].walk_preorder()
+            ):
+                continue
+            lhs_end = children[0].extent.end.offset
+            operator = next(
+                (t.spelling for t in cursor.get_tokens() if t.extent.start.offset >= lhs_end), None)
+            if operator is not None and operator.endswith("=") and operator not in ("==", "!=", "<=", ">="):
+                return True
+        return False
+
+    @staticmethod
+    def _collect_goto_labels(node) -> list[GotoLabel]:
+        """Labels the function jumps to with `goto`, classified for restructuring."""
+        body = next((c for c in node.get_children() if c.kind == CursorKind.COMPOUND_STMT), None)
+        if body is None:
+            return []
+        statements = list(body.get_children())
+        top_level: dict[str, int] = {}
+        for index, stmt in enumerate(statements):
+            # `a: b: stmt` nests one label in the other
+            while stmt is not None and stmt.kind == CursorKind.LABEL_STMT:
+                top_level[stmt.spelling] = index
+                stmt = next(iter(stmt.get_children()), None)
+
+        label_lines: dict[str, int] = {}
+        goto_lines: dict[str, list[int]] = {}
+        for cursor in body.walk_preorder():
+            if cursor.kind == CursorKind.LABEL_STMT:
+                label_lines[cursor.spelling] = cursor.location.line
+            elif cursor.kind == CursorKind.GOTO_STMT:
+                ref = next((c for c in cursor.get_children() if c.kind == CursorKind.LABEL_REF), None)
+                if ref is not None:
+                    goto_lines.setdefault(ref.spelling, []).append(cursor.location.line)
+
+        loops = {CursorKind.FOR_STMT, CursorKind.WHILE_STMT, CursorKind.DO_STMT}
+        labels: list[GotoLabel] = []
+        for name, line in sorted(label_lines.items(), key=lambda item: item[1]):
+            if name not in goto_lines:
+                continue
+            index = top_level.get(name)
+            loop_after = index is None or any(
+                cursor.kind in loops
+                for stmt in statements[index:]
+                for cursor in stmt.walk_preorder()
+            )
+            labels.append(classify_goto_label(name, line, goto_lines[name], index is not None, loop_after))
+        return labels
+
     def _collect_function_dependency_refs(self, node) -> list[FunctionDependencyRef]:
         """
         Recursively collect function dependency references with USR when available.
@@ -1050,6 +1336,23 @@ class CParser:
         end_line = struct_union_node.extent.end.line
         return "".join(lines[start_line:end_line])
 
+    def extract_cxx_class_code(self, class_name):
+        """
+        Extracts the code of the class definition with the given name, followed
+        by its member functions defined outside the class.
+
+        Raises ValueError if the class is not found
+        """
+        if class_name not in self._cxx_class_nodes:
+            raise ValueError(f"Class {class_name} not found")
+        lines = read_file_lines(self.filename)
+        parts = []
+        for node in self._cxx_class_nodes[class_name]:
+            start_line = node.extent.start.line - 1
+            end_line = node.extent.end.line
+            parts.append("".join(lines[start_line:end_line]))
+        return "\n".join(parts)
+
     def extract_enum_definition_code(self, enum_name):
         """
         Extracts the code of the enum definition with the given name from the file.
@@ -1080,17 +1383,156 @@ class CParser:
         end_line = global_var_node.extent.end.line
         return "".join(lines[start_line:end_line])
 
-    def get_macro_definitions_for_function(self, function_name: str) -> list[str]:
+    def get_macro_definitions_for_function(
+        self, function_name: str, exclude_translated: bool = False
+    ) -> list[str]:
         """
         Collect macro definitions used in the given function, including nested macro
         dependencies (macro bodies referencing other macros). Builtins/__* are skipped.
+        With `exclude_translated`, macros translated as Rust constants or
+        `macro_rules!` (see `get_macro_constants`, `get_macro_functions`) are left out.
+        """
+        closure = self._macro_closure_for_function(function_name)
+        translated: set[str] = set()
+        if exclude_translated:
+            translated = set(self.get_macro_constants()) | set(self.get_macro_functions())
+        macro_defs: list[str] = []
+        for name, cursor in closure:
+            if name in translated:
+                continue
+            rendered = self._render_macro_definition(cursor)
+            if not rendered:
+                rendered = f"#define {name} /* definition unavailable */"
+            macro_defs.append(rendered)
+        return macro_defs
+
+    def get_macro_constants_for_function(self, function_name: str) -> list[MacroConstant]:
+        """
+        Macro constants (see `get_macro_constants`) the given function uses,
+        directly or through other macros, in dependency order.
         """
-        if function_name in self._macro_defs_for_function:
-            return self._macro_defs_for_function[function_name]
+        constants = self.get_macro_constants()
+        used = {name for name, _ in self._macro_closure_for_function(function_name)}
+        return [constant for name, constant in constants.items() if name in used]
+
+    def get_macro_constants(self) -> dict[str, MacroConstant]:
+        """
+        Object-like `#define`s outside system headers whose bodies are constant
+        expressions, typed and rendered as Rust `const` items. Constants are
+        ordered so each one follows the constants it refers to.
+        """
+        if self._macro_constants is not None:
+            return self._macro_constants
+        self._build_macro_index()
+        constants: dict[str, MacroConstant] = {}
+        resolving: set[str] = set()
+
+        def resolve(name: str) -> None:
+            if name in constants or name in resolving:
+                return
+            cursor = self._macro_def_map.get(name)
+            body = self._object_macro_body(cursor) if cursor is not None else None
+            if not body:
+                return
+            resolving.add(name)
+            for token in body:
+                if token in self._macro_def_map and token != name:
+                    resolve(token)
+            resolving.discard(name)
+            constant = infer_macro_constant(name, body, constants)
+            if constant is not None:
+                constants[name] = constant
+
+        for cursor in self._macro_def_cursors:
+            resolve(cursor.spelling)
+        self._macro_constants = constants
+        return constants
+
+    def get_macro_functions_for_function(self, function_name: str) -> list[MacroFunction]:
+        """
+        Function-like macros (see `get_macro_functions`) the given function
+        uses, directly or through other macros, in dependency order.
+        """
+        macros = self.get_macro_functions()
+        used = {name for name, _ in self._macro_closure_for_function(function_name)}
+        return [macro for name, macro in macros.items() if name in used]
+
+    def get_macro_functions(self) -> dict[str, MacroFunction]:
+        """
+        Function-like `#define`s outside system headers whose expansions are
+        pure expressions over their parameters, rendered as `macro_rules!`
+        macros. Macros are ordered so each one follows the macros it expands.
+        """
+        if self._macro_functions is not None:
+            return self._macro_functions
+        constants = self.get_macro_constants()
+        macros: dict[str, MacroFunction] = {}
+        resolving: set[str] = set()
+
+        def resolve(name: str) -> None:
+            if name in macros or name in resolving:
+                return
+            cursor = self._macro_def_map.get(name)
+            parts = self._macro_parts(cursor) if cursor is not None else None
+            if parts is None or parts[0] is None:
+                return
+            params, body = parts
+            resolving.add(name)
+            for token in body:
+                if token in self._macro_def_map and token != name:
+                    resolve(token)
+            resolving.discard(name)
+            macro = translate_macro_function(name, params, body, constants, macros)
+            if macro is not None:
+                macros[name] = macro
+
+        for cursor in self._macro_def_cursors:
+            resolve(cursor.spelling)
+        self._macro_functions = macros
+        return macros
+
+    def _object_macro_body(self, cursor: cindex.Cursor) -> list[str] | None:
+        """Body token spellings of an object-like macro defined outside system headers."""
+        parts = self._macro_parts(cursor)
+        if parts is None or parts[0] is not None:
+            return None
+        return parts[1]
+
+    def _macro_parts(self, cursor: cindex.Cursor) -> tuple[list[str] | None, list[str]] | None:
+        """
+        Parameters (None for object-like macros) and body token spellings of a
+        macro defined outside system headers.
+        """
+        location = cursor.location
+        if location is None or location.file is None or getattr(location, "is_in_system_header", False):
+            return None
+        try:
+            tokens = [tok for tok in cursor.get_tokens() if tok.spelling not in ("#", "define")]
+        except Exception:
+            return None
+        if not tokens or tokens[0].spelling != cursor.spelling:
+            return None
+        if len(tokens) > 1 and tokens[1].spelling == "(" \
+                and tokens[1].extent.start.offset == tokens[0].extent.end.offset:
+            # function-like macro: `NAME(` without whitespace
+            params: list[str] = []
+            idx = 2
+            while idx < len(tokens) and tokens[idx].spelling != ")":
+                if tokens[idx].spelling != ",":
+                    params.append(tokens[idx].spelling)
+                idx += 1
+            if idx >= len(tokens):
+                return None
+            return params, [tok.spelling for tok in tokens[idx + 1:]]
+        return None, [tok.spelling for tok in tokens[1:]]
+
+    def _macro_closure_for_function(self, function_name: str) -> list[tuple[str, cindex.Cursor | None]]:
+        if function_name in self._macro_closure_cache:
+            return self._macro_closure_cache[function_name]
 
         raw_cursor = self._get_raw_function_cursor(function_name)
         if raw_cursor is None:
-            self._macro_defs_for_function[function_name] = []
+            self._macro_closure_cache[function_name] = []
             return []
 
         self._build_macro_index()
@@ -1123,15 +1565,8 @@ class CParser:
                     if dep not in seen:
                         queue.append(dep)
 
-        macro_defs: list[str] = []
-        for name, cursor in closure:
-            rendered = self._render_macro_definition(cursor)
-            if not rendered:
-                rendered = f"#define {name} /* definition unavailable */"
-            macro_defs.append(rendered)
-
-        self._macro_defs_for_function[function_name] = macro_defs
-        return macro_defs
+        self._macro_closure_cache[function_name] = closure
+        return closure
 
     def _is_in_system_header(self, node):
         """
@@ -11,6 +11,7 @@ from sactor import logging as sactor_logging, utils
 from sactor.utils import get_temp_dir, read_file, read_file_lines
 
 from .c_parser import CParser
+from .cxx_classes import parse_args
 
 
 logger = sactor_logging.get_logger(__name__)
@@ -98,7 +99,7 @@ def expand_all_macros(input_file, commands: list[list[str]] | None=None):
         """
         index = cindex.Index.create()
         compiler_include_paths = utils.get_compiler_include_paths()
-        args = ['-x', 'c', '-std=c99'] + (flags or [])
+        args = parse_args(input_file) + (flags or [])
         args.extend([f"-I{path}" for path in compiler_include_paths])
         translation_unit = index.parse(
                 tmp_file_path, args=args, options=cindex.TranslationUnit.PARSE_DETAILED_PROCESSING_RECORD)
@@ -176,7 +177,7 @@ def expand_all_macros(input_file, commands: list[list[str]] | None=None):
     # expand macros
     # #ifdef __cplusplus will be automatically removed if there is no __cplusplus flag
     result = utils.run_command(
-        ['cpp', '-C', '-P', '-xc', '-std=c99', tmp_file_path, *compile_flags],
+        ['cpp', '-C', '-P', *parse_args(input_file), tmp_file_path, *compile_flags],
         check=True,
     )
 
@@ -0,0 +1,68 @@
+from typing import Optional
+
+# pthread API families, by call prefix, and the std equivalents the idiomatic
+# stage maps them to
+_PTHREAD_FAMILIES: list[tuple[tuple[str, ...], str]] = [
+    (("pthread_create", "pthread_join", "pthread_detach", "pthread_exit", "pthread_self", "pthread_attr_"),
+     "Threads: `pthread_create` becomes `std::thread::spawn(move || ...)` (or `std::thread::scope` when the "
+     "thread borrows local data) and `pthread_join` becomes `handle.join()`; the thread's result is the "
+     "closure's return value instead of a `void *`, and `pthread_exit` becomes a return from the closure."),
+    (("pthread_mutex_",),
+     "Mutexes: a `pthread_mutex_t` becomes a `std::sync::Mutex<T>` owning the data it protects. "
+     "`pthread_mutex_lock` becomes `lock().unwrap()`, whose guard unlocks when dropped, so end the guard's "
+     "scope where the C code calls `pthread_mutex_unlock`; `pthread_mutex_trylock` becomes `try_lock()`. "
+     "There is no init or destroy."),
+    (("pthread_cond_",),
+     "Condition variables: a `pthread_cond_t` becomes a `std::sync::Condvar` used with the mutex guard: "
+     "`pthread_cond_wait` in a `while` loop becomes `cvar.wait_while(guard, |state| ...)`, and "
+     "`pthread_cond_signal`/`pthread_cond_broadcast` become `notify_one()`/`notify_all()`."),
+    (("pthread_rwlock_",),
+     "Read-write locks: a `pthread_rwlock_t` becomes a `std::sync::RwLock<T>`, locked with `read()`/`write()`."),
+    (("pthread_once",),
+     "One-time initialization: `pthread_once` becomes `std::sync::Once::call_once` (or a `OnceLock` holding "
+     "the initialized value)."),
+    (("pthread_key_", "pthread_getspecific", "pthread_setspecific"),
+     "Thread-specific data: `pthread_key_t` values become `thread_local!` statics."),
+]
+
+# Synchronization globals, declared as std statics without the LLM
+_SYNC_GLOBALS = {
+    "pthread_mutex_t": ("std::sync::Mutex<()>", "std::sync::Mutex::new(())"),
+    "pthread_cond_t": ("std::sync::Condvar", "std::sync::Condvar::new()"),
+    "pthread_rwlock_t": ("std::sync::RwLock<()>", "std::sync::RwLock::new(())"),
+    "pthread_once_t": ("std::sync::Once", "std::sync::Once::new()"),
+}
+
+
+def is_pthread_call(name: str) -> bool:
+    return any(name.startswith(prefixes) for prefixes, _ in _PTHREAD_FAMILIES)
+
+
+def sync_global_code(name: str, c_type: str) -> Optional[str]:
+    """The idiomatic `static` for a global mutex, condition variable, rwlock or once flag."""
+    c_type = c_type.replace("volatile", "").strip()
+    if c_type not in _SYNC_GLOBALS:
+        return None
+    rust_type, initializer = _SYNC_GLOBALS[c_type]
+    return f"pub static {name}: {rust_type} = {initializer};\n"
+
+
+def concurrency_plan(pthread_calls: list[str], sync_globals: dict[str, str]) -> str:
+    """
+    Guidance for translating a function's pthread calls to `std::thread` and
+    `std::sync`; `sync_globals` maps the pthread globals it uses to their
+    already declared Rust statics.
+    """
+    steps = [
+        guidance for prefixes, guidance in _PTHREAD_FAMILIES
+        if any(call.startswith(prefixes) for call in pthread_calls)
+    ]
+    plan = "\n".join(f"- {step}" for step in steps)
+    text = f"The function uses POSIX threads. Translate them to the standard library:\n{plan}\n"
+    if sync_globals:
+        statics = "".join(sync_globals.values())
+        text += f'''The global synchronization objects it uses are already declared as follows (do **NOT** define them). Their `()` payload only provides the locking; move the data they protect into the function's own state where you can:
+```rust
+{statics}```
+'''
+    return text
@@ -0,0 +1,267 @@
+import os
+import re
+from dataclasses import dataclass, field
+from typing import Optional
+
+# Source extensions parsed as C++ instead of C
+CXX_EXTENSIONS = {".cc", ".cp", ".cpp", ".cxx", ".c++", ".C", ".hh", ".hpp", ".hxx"}
+
+# Standard library types of the supported subset besides the primitives
+_STRING_TYPES = {"std::string", "string", "std::__cxx11::string", "std::basic_string<char>",
+                 "std::__cxx11::basic_string<char>"}
+_VECTOR_TYPE = re.compile(r"^(?:std::)?vector<(.+?)(?:,\s*std::allocator<.*>)?>$")
+
+_PRIMITIVE_TYPES: dict[str, str] = {
+    "void": "()",
+    "bool": "bool",
+    "char": "u8",
+    "signed char": "i8",
+    "unsigned char": "u8",
+    "short": "i16",
+    "unsigned short": "u16",
+    "int": "i32",
+    "unsigned int": "u32",
+    "unsigned": "u32",
+    "long": "i64",
+    "unsigned long": "u64",
+    "long long": "i64",
+    "unsigned long long": "u64",
+    "size_t": "usize",
+    "std::size_t": "usize",
+    "float": "f32",
+    "double": "f64",
+    "int8_t": "i8",
+    "int16_t": "i16",
+    "int32_t": "i32",
+    "int64_t": "i64",
+    "uint8_t": "u8",
+    "uint16_t": "u16",
+    "uint32_t": "u32",
+    "uint64_t": "u64",
+}
+
+# Overloaded operators and the Rust traits implementing them
+OPERATOR_TRAITS: dict[str, str] = {
+    "operator=": "Clone",
+    "operator==": "PartialEq",
+    "operator!=": "PartialEq",
+    "operator<": "PartialOrd",
+    "operator<=": "PartialOrd",
+    "operator>": "PartialOrd",
+    "operator>=": "PartialOrd",
+    "operator+": "std::ops::Add",
+    "operator-": "std::ops::Sub",
+    "operator*": "std::ops::Mul",
+    "operator/": "std::ops::Div",
+    "operator+=": "std::ops::AddAssign",
+    "operator-=": "std::ops::SubAssign",
+    "operator[]": "std::ops::Index/IndexMut",
+    "operator<<": "std::fmt::Display",
+}
+
+METHOD = "method"
+CONSTRUCTOR = "constructor"
+DESTRUCTOR = "destructor"
+
+
+@dataclass
+class CxxMethod:
+    name: str
+    kind: str
+    return_type: str
+    arguments: list[tuple[str, str]] = field(default_factory=list)
+    is_const: bool = False
+    is_static: bool = False
+    is_virtual: bool = False
+    is_public: bool = True
+
+
+@dataclass
+class CxxClass:
+    name: str
+    location: str
+    fields: list[tuple[str, str]] = field(default_factory=list)
+    methods: list[CxxMethod] = field(default_factory=list)
+    # the single, non-virtual base class
+    base: Optional[str] = None
+    # fields declared `public`
+    public_fields: set[str] = field(default_factory=set)
+
+
+def is_cxx_source(path: str) -> bool:
+    return os.path.splitext(path)[1] in CXX_EXTENSIONS
+
+
+def parse_args(path: str) -> list[str]:
+    """The clang language arguments for parsing `path`."""
+    if is_cxx_source(path):
+        return ['-x', 'c++', '-std=c++17']
+    return ['-x', 'c', '-std=c99']
+
+
+def _strip_qualifiers(spelling: str) -> str:
+    spelling = re.sub(r"\b(const|volatile|class|struct)\b", "", spelling)
+    return re.sub(r"\s+", " ", spelling).strip()
+
+
+def rust_type_of_cxx(spelling: str, class_names: set[str], param: bool = False) -> Optional[str]:
+    """
+    The idiomatic Rust type of the C++ type `spelling`, None when it is not
+    part of the supported subset. `param`: the type of a parameter, where
+    references to strings and vectors become `&str` and slices.
+    """
+    spelling = spelling.strip()
+    if spelling.endswith("&"):
+        inner = spelling[:-1].strip()
+        mutable = not re.search(r"\bconst\b", inner)
+        target = rust_type_of_cxx(inner, class_names)
+        if target is None:
+            return None
+        if param and not mutable:
+            if target == "String":
+                return "&str"
+            if target.startswith("Vec<"):
+                return f"&[{target[4:-1]}]"
+        return f"&mut {target}" if mutable else f"&{target}"
+    if spelling.endswith("*"):
+        inner = spelling[:-1].strip()
+        if _strip_qualifiers(inner) == "char":
+            return "&str" if param else "String"
+        target = rust_type_of_cxx(inner, class_names)
+        if target is None:
+            return None
+        if not param:
+            return f"Option<Box<{target}>>"
+        return f"Option<&{target}>" if re.search(r"\bconst\b", inner) else f"Option<&mut {target}>"
+    base = _strip_qualifiers(spelling)
+    if base in _STRING_TYPES:
+        return "String"
+    vector = _VECTOR_TYPE.match(base)
+    if vector:
+        element = rust_type_of_cxx(vector.group(1), class_names)
+        return f"Vec<{element}>" if element is not None else None
+    if base in _PRIMITIVE_TYPES:
+        return _PRIMITIVE_TYPES[base]
+    if base in class_names:
+        return base
+    return None
+
+
+def unsupported_type(spelling: str, class_names: set[str]) -> bool:
+    """Whether the C++ type falls outside the subset: other std types, templates or class pointers."""
+    return rust_type_of_cxx(spelling, class_names, param=True) is None
+
+
+def _rust_method_names(cls: CxxClass) -> dict[int, str]:
+    """Rust names for the methods of `cls`, by index, numbering overloads apart."""
+    names: dict[int, str] = {}
+    seen: dict[str, int] = {}
+    for i, method in enumerate(cls.methods):
+        if method.kind == DESTRUCTOR or method.name in OPERATOR_TRAITS or _is_copy_constructor(cls, method):
+            continue
+        name = "new" if method.kind == CONSTRUCTOR else _snake_case(method.name)
+        if name in seen:
+            seen[name] += 1
+            suffix = "_".join(_snake_case(arg) for arg, _ in method.arguments) or str(seen[name])
+            name = f"{name}_with_{suffix}" if method.kind == CONSTRUCTOR else f"{name}_{suffix}"
+        else:
+            seen[name] = 1
+        names[i] = name
+    return names
+
+
+def _snake_case(name: str) -> str:
+    return re.sub(r"(?<=[a-z0-9])([A-Z])", r"_\1", name).lower()
+
+
+def _is_copy_constructor(cls: CxxClass, method: CxxMethod) -> bool:
+    return (method.kind == CONSTRUCTOR and len(method.arguments) == 1
+            and _strip_qualifiers(method.arguments[0][1].rstrip("&").strip()) == cls.name)
+
+
+def class_rust_skeleton(cls: CxxClass, class_names: set[str]) -> str:
+    """The Rust struct and impl blocks `cls` maps to, with `todo!()` bodies."""
+    def rust(spelling: str, param: bool = False) -> str:
+        return rust_type_of_cxx(spelling, class_names, param) or f"/* {spelling} */"
+
+    lines = [f"pub struct {cls.name} {{"]
+    if cls.base:
+        lines.append(f"    pub base: {cls.base},")
+    for name, spelling in cls.fields:
+        visibility = "pub " if name in cls.public_fields else ""
+        lines.append(f"    {visibility}{name}: {rust(spelling)},")
+    lines.append("}\n")
+
+    methods = []
+    for i, name in _rust_method_names(cls).items():
+        method = cls.methods[i]
+        params = [] if method.is_static or method.kind == CONSTRUCTOR else \
+            ["&self" if method.is_const else "&mut self"]
+        params += [f"{arg}: {rust(spelling, param=True)}" for arg, spelling in method.arguments]
+        if method.kind == CONSTRUCTOR:
+            ret = " -> Self"
+        else:
+            # a returned reference borrows from `self`, like a parameter
+            ret_type = rust(method.return_type, param=method.return_type.endswith(("&", "*")))
+            ret = "" if ret_type == "()" else f" -> {ret_type}"
+        visibility = "pub " if method.is_public else ""
+        methods.append(f"    {visibility}fn {name}({', '.join(params)}){ret} {{ todo!() }}")
+    if methods:
+        lines.append(f"impl {cls.name} {{")
+        lines.extend(methods)
+        lines.append("}\n")
+    if any(_is_copy_constructor(cls, m) or m.name == "operator=" for m in cls.methods):
+        lines.append(f"impl Clone for {cls.name} {{\n    fn clone(&self) -> Self {{ todo!() }}\n}}\n")
+    if any(m.kind == DESTRUCTOR for m in cls.methods):
+        lines.append(f"impl Drop for {cls.name} {{\n    fn drop(&mut self) {{ todo!() }}\n}}\n")
+    return "\n".join(lines)
+
+
+def cxx_class_plan(classes: list[CxxClass], class_code: str, defined: list[CxxClass], class_names: set[str]) -> str:
+    """
+    Guidance for a function using C++ classes, each becoming a Rust struct with
+    an `impl` block: `classes` (with their C++ code) are translated along with
+    the function, `defined` were translated with an earlier function.
+    """
+    text = "\nThe function uses C++ classes.\n"
+    if defined:
+        items = "\n".join(class_rust_skeleton(cls, class_names) for cls in defined)
+        text += f'''The following classes are already translated as these Rust items (you should **NOT** define them, but you can use them):
+```rust
+{items}```
+'''
+    if not classes:
+        return text
+    steps = [
+        "Each class becomes a struct holding its fields, with its member functions in an `impl` block: "
+        "constructors become associated functions returning `Self` (`new`, named apart when overloaded), "
+        "`const` member functions take `&self`, other member functions `&mut self`, and `static` ones no "
+        "receiver. A destructor becomes `impl Drop`, a copy constructor or copy assignment `impl Clone`.",
+        "`std::string` becomes `String` (`&str` when passed as `const std::string &`) and `std::vector<T>` "
+        "becomes `Vec<T>` (`&[T]` when passed as `const std::vector<T> &`); `size()` becomes `len()` and "
+        "`push_back` becomes `push`.",
+        "Objects on the stack become owned values dropped at the end of their scope, where C++ runs their "
+        "destructors; `new`/`delete` become `Box::new` and dropping the `Box`.",
+    ]
+    if any(cls.base for cls in classes):
+        steps.append("A derived class holds its base class as a `base` field, and uses of inherited members "
+                     "go through `self.base`.")
+    if any(m.is_virtual for cls in classes for m in cls.methods):
+        steps.append("Virtual member functions become a trait implemented by the class and the classes "
+                     "derived from it, and pointers or references to the base class become `&dyn`/`Box<dyn>` "
+                     "of that trait.")
+    operators = sorted({f"`{m.name}` becomes `{OPERATOR_TRAITS[m.name]}`"
+                        for cls in classes for m in cls.methods if m.name in OPERATOR_TRAITS})
+    if operators:
+        steps.append(f"Overloaded operators become trait implementations: {'; '.join(operators)}.")
+    steps.append("`std::cout << a << b` becomes `print!`/`println!`, and `std::cerr` becomes `eprint!`.")
+    plan = "\n".join(f"- {step}" for step in steps)
+    skeletons = "\n".join(class_rust_skeleton(cls, class_names) for cls in classes)
+    return text + f'''Translate the following classes along with the function, and include them in your translation:
+```cpp
+{class_code}```
+{plan}
+They map to the following Rust items, whose `todo!()` bodies you translate from the C++ member functions (keep these names and signatures, which later translations use):
+```rust
+{skeletons}```
+'''
@@ -0,0 +1,75 @@
+# libc functions reporting failures through `errno`, and the std APIs whose
+# `Result` replaces the sentinel-and-errno protocol in idiomatic code
+ERRNO_FUNCTIONS: dict[str, str] = {
+    "fopen": "`std::fs::File::open`/`File::create` (or `OpenOptions`)",
+    "open": "`std::fs::OpenOptions::open`",
+    "close": "dropping the `File` (`sync_all()` to observe write errors)",
+    "fclose": "dropping the `File` (`sync_all()` to observe write errors)",
+    "read": "`std::io::Read::read`",
+    "fread": "`std::io::Read::read`/`read_exact`",
+    "write": "`std::io::Write::write`",
+    "fwrite": "`std::io::Write::write_all`",
+    "fgets": "`std::io::BufRead::read_line`",
+    "getline": "`std::io::BufRead::read_line`",
+    "fseek": "`std::io::Seek::seek`",
+    "ftell": "`std::io::Seek::stream_position`",
+    "unlink": "`std::fs::remove_file`",
+    "remove": "`std::fs::remove_file`",
+    "rename": "`std::fs::rename`",
+    "mkdir": "`std::fs::create_dir`",
+    "rmdir": "`std::fs::remove_dir`",
+    "stat": "`std::fs::metadata`",
+    "lstat": "`std::fs::symlink_metadata`",
+    "opendir": "`std::fs::read_dir`",
+    "getcwd": "`std::env::current_dir`",
+    "chdir": "`std::env::set_current_dir`",
+    "strtol": "`str::parse::<i64>()`",
+    "strtoll": "`str::parse::<i64>()`",
+    "strtoul": "`str::parse::<u64>()`",
+    "strtoull": "`str::parse::<u64>()`",
+    "strtod": "`str::parse::<f64>()`",
+    "strtof": "`str::parse::<f32>()`",
+}
+
+# What the `errno` macro expands to in the C libraries, plus readers of it
+ERRNO_ACCESSORS = {"__errno_location", "__errno", "___errno", "__error"}
+ERRNO_READERS = {"perror", "strerror"}
+
+
+def errno_guidance(
+    errno_calls: list[str],
+    sets_errno: bool,
+    idiomatic: bool,
+    result_callees: list[str] | None = None,
+) -> str:
+    """
+    Prompt text for functions that check or set `errno`: raw `errno` access
+    through `std::io::Error` in the unidiomatic stage, `Result` values in the
+    idiomatic one. `result_callees` are dependencies already returning `Result`
+    in place of the errno protocol.
+    """
+    if not idiomatic:
+        return '''
+The function checks or sets `errno`. Keep calling the libc functions, and read `errno` right after the failing call (before any other call can overwrite it) with `std::io::Error::last_os_error()`: `.raw_os_error()` is the `errno` value, to compare with constants such as `libc::ENOENT`. Set `errno` with `*libc::__errno_location() = value`. Keep `libc::perror`/`libc::strerror` for messages, because the `Display` of `std::io::Error` appends ` (os error N)` to the C text.
+'''
+    text = '''
+The C function uses the `errno` protocol (a sentinel return value such as `-1` or `NULL`, with the cause in `errno`). Replace it with `Result` values:
+'''
+    mapped = [f"`{name}` becomes {ERRNO_FUNCTIONS[name]}" for name in errno_calls if name in ERRNO_FUNCTIONS]
+    if mapped:
+        text += f"- Call the std APIs returning `Result` instead of the libc functions: {'; '.join(mapped)}.\n"
+    text += ("- Rewrite each check of a sentinel and `errno` as a `match` on the `Result`, e.g. "
+             "`Err(e) if e.kind() == std::io::ErrorKind::NotFound => ...` for `errno == ENOENT` "
+             "(`e.raw_os_error() == Some(libc::ENOENT)` only where no `ErrorKind` fits).\n"
+             "- Messages built with `strerror`/`perror` print the C text without the ` (os error N)` "
+             "suffix of `std::io::Error`'s `Display`.\n")
+    if result_callees:
+        names = ", ".join(f"`{name}`" for name in result_callees)
+        text += ("- The following functions already return `Result` in place of the sentinel and `errno`, so "
+                 f"`match` on their `Result` (or use `?`) where you call them instead of checking `errno`: {names}.\n")
+    if sets_errno:
+        text += ("- The function sets `errno` itself: return `Result<T, std::io::Error>` instead, with "
+                 "`std::io::Error::from_raw_os_error(libc::EINVAL)` (or an `ErrorKind`) as the error. In the "
+                 "spec, add `\"on_error\"` to the `ret` field with the C return value for failures (e.g. "
+                 "`\"on_error\": \"-1\"`), so the test harness turns an `Err` back into that value and `errno`.\n")
+    return text
@@ -6,6 +6,8 @@ from sactor import logging as sactor_logging, utils
 from .enum_info import EnumInfo, EnumValueInfo
 from .struct_info import StructInfo
 from .global_var_info import GlobalVarInfo
+from .goto_plan import GotoLabel
+from .signals import SignalRegistration
 from .refs import FunctionDependencyRef, StructRef, EnumRef, GlobalVarRef
 
 
@@ -47,6 +49,28 @@ class FunctionInfo:
         self.struct_dependency_refs: list[StructRef] = []
         self.enum_dependency_refs: list[EnumRef] = []
         self.global_dependency_refs: list[GlobalVarRef] = []
+        # Declared with a trailing `...`
+        self.is_variadic: bool = False
+        # Labels targeted by `goto`, see `goto_plan`
+        self.goto_labels: list[GotoLabel] = []
+        # Calls `setjmp`/`longjmp`, see `utils.SETJMP_SCAFFOLD_CODE`
+        self.uses_setjmp: bool = False
+        self.uses_longjmp: bool = False
+        # `pthread_*` functions called, see `concurrency`
+        self.pthread_calls: list[str] = []
+        # libc functions reporting failures through `errno`, see `errno_api`
+        self.errno_calls: list[str] = []
+        # Reads or writes `errno` (directly or with `perror`/`strerror`)
+        self.uses_errno: bool = False
+        self.sets_errno: bool = False
+        # `FILE *` functions called and the literal `fopen` modes, see `stdio_api`
+        self.stdio_calls: list[str] = []
+        self.fopen_modes: list[str] = []
+        # Signal handlers it installs, and the signals it handles itself, see `signals`
+        self.signal_registrations: list[SignalRegistration] = []
+        self.handled_signals: list[str] = []
+        # C++ classes it uses, dependencies first, see `cxx_classes`
+        self.cxx_classes: list[str] = []
 
         self.stdio_list = []
 
@@ -0,0 +1,70 @@
+from dataclasses import dataclass, field
+
+# How a label is reached, which decides how it is restructured in Rust
+GOTO_CLEANUP = "cleanup"  # forward jumps to an exit path at the end of the function
+GOTO_LOOP = "loop"  # backward jumps that re-run earlier code
+GOTO_FORWARD = "forward"  # other forward jumps, e.g. out of nested loops
+
+
+@dataclass
+class GotoLabel:
+    name: str
+    line: int
+    kind: str
+    goto_lines: list[int] = field(default_factory=list)
+
+
+def classify_goto_label(
+    name: str,
+    line: int,
+    goto_lines: list[int],
+    top_level: bool,
+    loop_after: bool,
+) -> GotoLabel:
+    """
+    `top_level`: the label is a statement of the function body itself (not
+    nested in a block); `loop_after`: a loop follows the label in the body.
+    """
+    if any(goto_line > line for goto_line in goto_lines):
+        kind = GOTO_LOOP
+    elif top_level and not loop_after:
+        kind = GOTO_CLEANUP
+    else:
+        kind = GOTO_FORWARD
+    return GotoLabel(name, line, kind, sorted(goto_lines))
+
+
+def goto_plan(labels: list[GotoLabel], idiomatic: bool = False) -> str:
+    """Restructuring guidance for a function using `goto`, one entry per label."""
+    steps: list[str] = []
+    cleanup = [label.name for label in labels if label.kind == GOTO_CLEANUP]
+    if cleanup:
+        names = ", ".join(f"`{name}`" for name in cleanup)
+        if idiomatic:
+            steps.append(
+                f"Labels {names} form an error-cleanup ladder. Release resources through ownership "
+                "instead: let owned values (`Box`, `Vec`, `String`, `File`) or a guard type implementing "
+                "`Drop` free them on every exit, and replace each `goto` with an early `return` "
+                "(or `?` on a `Result`). Keep the cleanup order of the C code.")
+        else:
+            steps.append(
+                f"Labels {names} form an error-cleanup ladder. Nest one labeled block per label, "
+                "outermost for the last label: `'label: { ... }` followed by that label's cleanup code, "
+                "and replace `goto label;` with `break 'label;`, so falling into a label runs the "
+                "cleanup code of every later label, as in C.")
+    for label in labels:
+        if label.kind == GOTO_LOOP:
+            steps.append(
+                f"`goto {label.name}` jumps backwards (a retry loop). Wrap the code from `{label.name}:` "
+                f"up to the last jump in `'{label.name}: loop {{ ... break; }}` and replace the jumps "
+                f"with `continue '{label.name};`.")
+        elif label.kind == GOTO_FORWARD:
+            steps.append(
+                f"`goto {label.name}` skips ahead (e.g. out of nested loops). Put the code it skips in "
+                f"a labeled block `'{label.name}: {{ ... }}` ending right before `{label.name}:`, or label "
+                f"the outer loop, and replace the jumps with `break '{label.name};`.")
+    plan = "\n".join(f"{i}. {step}" for i, step in enumerate(steps, 1))
+    return (
+        "The function uses `goto`, which Rust does not have. Restructure its control flow as follows, "
+        "keeping every path's behavior:\n" + plan
+    )
@@ -0,0 +1,429 @@
+import re
+from dataclasses import dataclass
+from typing import Optional
+
+# C integer types by conversion rank, with their width and signedness (LP64)
+_INT_TYPES = {
+    "signed char": ("i8", 8, True, 1),
+    "char": ("i8", 8, True, 1),
+    "unsigned char": ("u8", 8, False, 1),
+    "short": ("i16", 16, True, 2),
+    "unsigned short": ("u16", 16, False, 2),
+    "int": ("i32", 32, True, 3),
+    "unsigned int": ("u32", 32, False, 3),
+    "long": ("i64", 64, True, 4),
+    "unsigned long": ("u64", 64, False, 4),
+    "long long": ("i64", 64, True, 5),
+    "unsigned long long": ("u64", 64, False, 5),
+}
+_FLOAT_TYPES = {"float": "f32", "double": "f64", "long double": "f64"}
+_CSTR = "char *"
+
+# Type names a cast inside a constant may use, beyond the builtin spellings
+_FIXED_WIDTH_TYPES = {
+    "int8_t": "signed char", "uint8_t": "unsigned char",
+    "int16_t": "short", "uint16_t": "unsigned short",
+    "int32_t": "int", "uint32_t": "unsigned int",
+    "int64_t": "long", "uint64_t": "unsigned long",
+    "size_t": "unsigned long", "ssize_t": "long",
+    "intptr_t": "long", "uintptr_t": "unsigned long",
+}
+_TYPE_WORDS = {"signed", "unsigned", "char", "short", "int", "long", "float", "double"}
+
+# Identifiers C allows as macro names that are keywords in Rust
+_RUST_KEYWORDS = {
+    "as", "async", "await", "box", "crate", "dyn", "false", "fn", "impl", "in",
+    "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "self", "super",
+    "trait", "true", "type", "unsafe", "use", "where", "yield",
+}
+
+_BINARY_PRECEDENCE = {
+    "||": 1, "&&": 2, "|": 3, "^": 4, "&": 5,
+    "==": 6, "!=": 6, "<": 7, ">": 7, "<=": 7, ">=": 7,
+    "<<": 8, ">>": 8, "+": 9, "-": 9, "*": 10, "/": 10, "%": 10,
+}
+
+_SIMPLE_ESCAPES = {
+    "n": 10, "t": 9, "r": 13, "0": 0, "\\": 92, "'": 39, '"': 34,
+    "a": 7, "b": 8, "f": 12, "v": 11, "?": 63,
+}
+
+
+@dataclass
+class MacroConstant:
+    """An object-like `#define` whose body is a constant expression, as a Rust `const`."""
+    name: str
+    body: str
+    c_type: str
+    rust_type: str
+    rust_value: str
+
+    def rust_code(self) -> str:
+        return f"pub const {self.name}: {self.rust_type} = {self.rust_value};\n"
+
+
+@dataclass
+class _Value:
+    c_type: str
+    rust: str
+    # integer literal digits (without suffix) that can be retyped instead of cast
+    literal: Optional[str] = None
+
+
+def rust_type_of(c_type: str) -> str:
+    if c_type in _INT_TYPES:
+        return _INT_TYPES[c_type][0]
+    if c_type in _FLOAT_TYPES:
+        return _FLOAT_TYPES[c_type]
+    return "&core::ffi::CStr"
+
+
+def infer_macro_constant(
+    name: str,
+    body_tokens: list[str],
+    known: Optional[dict[str, "MacroConstant"]] = None,
+) -> Optional[MacroConstant]:
+    """
+    Infers the C type of an object-like macro body (C's literal typing and
+    usual arithmetic conversions, LP64) and renders it as a Rust constant
+    expression. `known` holds constants the body may refer to. Returns None
+    for bodies that are not constant expressions, e.g. ones calling functions
+    or using other identifiers, `sizeof` or the ternary operator.
+    """
+    if not body_tokens or name in _RUST_KEYWORDS or name.startswith("__"):
+        return None
+    parser = _ExprParser(body_tokens, known or {})
+    try:
+        value = parser.parse_expression(0)
+        if parser.pos != len(body_tokens):
+            return None
+    except _Unsupported:
+        return None
+    rust = value.rust
+    if rust.startswith("(") and _balanced(rust[1:-1]):
+        rust = rust[1:-1]
+    return MacroConstant(
+        name=name,
+        body=" ".join(body_tokens),
+        c_type=value.c_type,
+        rust_type=rust_type_of(value.c_type),
+        rust_value=rust,
+    )
+
+
+class _Unsupported(Exception):
+    pass
+
+
+def _balanced(text: str) -> bool:
+    depth = 0
+    for ch in text:
+        if ch == "(":
+            depth += 1
+        elif ch == ")":
+            depth -= 1
+            if depth < 0:
+                return False
+    return depth == 0
+
+
+def _promote(c_type: str) -> str:
+    if c_type in _INT_TYPES and _INT_TYPES[c_type][3] < 3:
+        return "int"
+    return c_type
+
+
+def _common_type(a: str, b: str) -> str:
+    """C's usual arithmetic conversions."""
+    if a == _CSTR or b == _CSTR:
+        raise _Unsupported()
+    if a in _FLOAT_TYPES or b in _FLOAT_TYPES:
+        floats = [t for t in (a, b) if t in _FLOAT_TYPES]
+        return "double" if "double" in floats or "long double" in floats else "float"
+    a, b = _promote(a), _promote(b)
+    if a == b:
+        return a
+    _, a_bits, a_signed, a_rank = _INT_TYPES[a]
+    _, b_bits, b_signed, b_rank = _INT_TYPES[b]
+    if a_signed == b_signed:
+        return a if a_rank > b_rank else b
+    unsigned, signed = (a, b) if not a_signed else (b, a)
+    if _INT_TYPES[unsigned][3] >= _INT_TYPES[signed][3]:
+        return unsigned
+    if _INT_TYPES[signed][1] > _INT_TYPES[unsigned][1]:
+        return signed
+    return "unsigned " + signed
+
+
+def _int_literal(token: str) -> _Value:
+    match = re.fullmatch(r"(0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)([uUlL]*)", token)
+    if not match:
+        raise _Unsupported()
+    digits, suffix = match.group(1), match.group(2).lower()
+    if digits.startswith(("0x", "0X")):
+        value, decimal = int(digits, 16), False
+    elif digits.startswith(("0b", "0B")):
+        value, decimal = int(digits[2:], 2), False
+    elif len(digits) > 1 and digits.startswith("0"):
+        value, decimal = int(digits, 8), False
+        digits = "0o" + digits[1:]
+    else:
+        value, decimal = int(digits), True
+
+    unsigned = "u" in suffix
+    longs = suffix.count("l")
+    candidates = ["int", "long", "long long"][min(longs, 2):]
+    for signed_type in candidates:
+        bits = _INT_TYPES[signed_type][1]
+        if not unsigned and value < 2 ** (bits - 1):
+            return _Value(signed_type, digits, digits)
+        if (unsigned or not decimal) and value < 2 ** bits:
+            c_type = "unsigned " + signed_type
+            return _Value(c_type, digits, digits)
+    raise _Unsupported()
+
+
+def _float_literal(token: str) -> _Value:
+    match = re.fullmatch(r"((?:[0-9]+\.[0-9]*|\.[0-9]+)(?:[eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+)([fFlL]?)", token)
+    if not match:
+        raise _Unsupported()
+    digits, suffix = match.group(1), match.group(2).lower()
+    if digits.startswith("."):
+        digits = "0" + digits
+    mantissa, _, exponent = digits.partition("e") if "e" in digits else digits.partition("E")
+    if mantissa.endswith("."):
+        mantissa += "0"
+    if "." not in mantissa:
+        mantissa += ".0"
+    digits = mantissa + (f"e{exponent}" if exponent else "")
+    return _Value("float" if suffix == "f" else "double", digits)
+
+
+def _unescape(body: str) -> list[int]:
+    """Byte values of a C character/string literal body."""
+    values: list[int] = []
+    i = 0
+    while i < len(body):
+        ch = body[i]
+        if ch != "\\":
+            values.extend(ch.encode("utf-8"))
+            i += 1
+            continue
+        i += 1
+        if i >= len(body):
+            raise _Unsupported()
+        esc = body[i]
+        if esc == "x":
+            match = re.match(r"[0-9a-fA-F]+", body[i + 1:])
+            if not match:
+                raise _Unsupported()
+            values.append(int(match.group(0), 16) & 0xFF)
+            i += 1 + len(match.group(0))
+        elif esc in "01234567":
+            match = re.match(r"[0-7]{1,3}", body[i:])
+            values.append(int(match.group(0), 8) & 0xFF)
+            i += len(match.group(0))
+        elif esc in _SIMPLE_ESCAPES:
+            values.append(_SIMPLE_ESCAPES[esc])
+            i += 1
+        else:
+            raise _Unsupported()
+    return values
+
+
+def _char_literal(token: str) -> _Value:
+    values = _unescape(token[1:-1])
+    if len(values) != 1:
+        raise _Unsupported()
+    ch = values[0]
+    if 0x20 <= ch < 0x7F and chr(ch) not in "'\\":
+        return _Value("int", f"(b'{chr(ch)}' as i32)")
+    # plain `char` is signed, so bytes above 0x7f read as negative values
+    value = ch - 256 if ch >= 0x80 else ch
+    return _Value("int", str(value), str(value) if value >= 0 else None)
+
+
+def _string_literal(tokens: list[str]) -> _Value:
+    values: list[int] = []
+    for token in tokens:
+        values.extend(_unescape(token[1:-1]))
+    if 0 in values:
+        raise _Unsupported()
+    text = ""
+    for value in values:
+        ch = chr(value)
+        if value == 10:
+            text += "\\n"
+        elif value == 9:
+            text += "\\t"
+        elif ch in '"\\':
+            text += "\\" + ch
+        elif 0x20 <= value < 0x7F:
+            text += ch
+        else:
+            text += f"\\x{value:02x}"
+    return _Value(_CSTR, f'c"{text}"')
+
+
+def _cast(value: _Value, c_type: str) -> _Value:
+    if value.c_type == c_type:
+        return value
+    if value.c_type == _CSTR or c_type == _CSTR:
+        raise _Unsupported()
+    if value.literal is not None:
+        # a literal that fits is retyped rather than cast
+        number = int(value.literal, 0)
+        if c_type in _FLOAT_TYPES:
+            return _Value(c_type, f"{number}.0")
+        _, bits, signed, _ = _INT_TYPES[c_type]
+        low, high = (-(2 ** (bits - 1)), 2 ** (bits - 1)) if signed else (0, 2 ** bits)
+        if low <= number < high:
+            return _Value(c_type, value.literal, value.literal)
+    source = value.rust
+    if value.literal is not None and not source.startswith("("):
+        source += rust_type_of(value.c_type)
+    return _Value(c_type, f"({source} as {rust_type_of(c_type)})")
+
+
+def _truthy(value: _Value) -> str:
+    zero = "0.0" if value.c_type in _FLOAT_TYPES else "0"
+    return f"({value.rust} != {zero})"
+
+
+class _ExprParser:
+    def __init__(self, tokens: list[str], known: dict[str, MacroConstant]):
+        self.tokens = tokens
+        self.known = known
+        self.pos = 0
+
+    def _peek(self, offset: int = 0) -> Optional[str]:
+        index = self.pos + offset
+        return self.tokens[index] if index < len(self.tokens) else None
+
+    def _next(self) -> str:
+        token = self._peek()
+        if token is None:
+            raise _Unsupported()
+        self.pos += 1
+        return token
+
+    def parse_expression(self, min_precedence: int) -> _Value:
+        left = self.parse_unary()
+        while True:
+            op = self._peek()
+            precedence = _BINARY_PRECEDENCE.get(op or "")
+            if precedence is None or precedence <= min_precedence:
+                return left
+            self.pos += 1
+            right = self.parse_expression(precedence)
+            left = self._binary(op, left, right)
+
+    def _binary(self, op: str, left: _Value, right: _Value) -> _Value:
+        if op in ("&&", "||"):
+            return _Value("int", f"(({_truthy(left)} {op} {_truthy(right)}) as i32)")
+        if op in ("<<", ">>"):
+            if left.c_type in _FLOAT_TYPES or right.c_type in _FLOAT_TYPES:
+                raise _Unsupported()
+            left = _cast(left, _promote(left.c_type))
+            return _Value(left.c_type, f"({left.rust} {op} {right.rust})")
+        if op in ("%", "&", "|", "^") and (left.c_type in _FLOAT_TYPES or right.c_type in _FLOAT_TYPES):
+            raise _Unsupported()
+        common = _common_type(left.c_type, right.c_type)
+        left, right = _cast(left, common), _cast(right, common)
+        if _BINARY_PRECEDENCE[op] in (6, 7):
+            return _Value("int", f"(({left.rust} {op} {right.rust}) as i32)")
+        return _Value(common, f"({left.rust} {op} {right.rust})")
+
+    def parse_unary(self) -> _Value:
+        token = self._next()
+        if token in ("-", "+", "~", "!"):
+            operand = self.parse_unary()
+            if operand.c_type == _CSTR:
+                raise _Unsupported()
+            if token == "!":
+                return _Value("int", f"((!{_truthy(operand)}) as i32)")
+            if token == "~":
+                if operand.c_type in _FLOAT_TYPES:
+                    raise _Unsupported()
+                operand = _cast(operand, _promote(operand.c_type))
+                return _Value(operand.c_type, f"(!{operand.rust})")
+            operand = _cast(operand, _promote(operand.c_type))
+            if token == "+":
+                return operand
+            if operand.c_type in _INT_TYPES and not _INT_TYPES[operand.c_type][2]:
+                # negating an unsigned value wraps around in C
+                return _Value(operand.c_type, f"{operand.rust}{rust_type_of(operand.c_type)}.wrapping_neg()"
+                              if operand.literal is not None else f"({operand.rust}).wrapping_neg()")
+            literal = f"-{operand.literal}" if operand.literal is not None else None
+            return _Value(operand.c_type, f"(-{operand.rust})", literal)
+        if token == "(":
+            cast_type = self._cast_type()
+            if cast_type is not None:
+                return _cast(self.parse_unary(), cast_type)
+            inner = self.parse_expression(0)
+            if self._next() != ")":
+                raise _Unsupported()
+            return inner
+        return self._primary(token)
+
+    def _cast_type(self) -> Optional[str]:
+        """The C type of a `(type)` cast starting at the current token, consuming it."""
+        end = self.pos
+        while end < len(self.tokens) and self.tokens[end] != ")":
+            end += 1
+        words = self.tokens[self.pos:end]
+        if not words or end >= len(self.tokens):
+            return None
+        if len(words) == 1 and words[0] in _FIXED_WIDTH_TYPES:
+            c_type = _FIXED_WIDTH_TYPES[words[0]]
+        elif all(word in _TYPE_WORDS for word in words):
+            c_type = _normalize_type(words)
+        else:
+            return None
+        self.pos = end + 1
+        return c_type
+
+    def _primary(self, token: str) -> _Value:
+        if token.startswith('"'):
+            tokens = [token]
+            while (self._peek() or "").startswith('"'):
+                tokens.append(self._next())
+            return _string_literal(tokens)
+        if token.startswith("'"):
+            return _char_literal(token)
+        if token[0].isdigit() or token.startswith("."):
+            if re.fullmatch(r"(0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)[uUlL]*", token):
+                return _int_literal(token)
+            return _float_literal(token)
+        constant = self.known.get(token)
+        if constant is None:
+            raise _Unsupported()
+        return _Value(constant.c_type, constant.name)
+
+
+def _normalize_type(words: list[str]) -> str:
+    unsigned = "unsigned" in words
+    signed = "signed" in words
+    words = [word for word in words if word not in ("signed", "unsigned")]
+    if words in (["float"], ["double"], ["long", "double"]):
+        if unsigned or signed:
+            raise _Unsupported()
+        return " ".join(words)
+    core = [word for word in words if word != "int"]
+    if core == ["char"]:
+        base = "char"
+    elif core == ["short"]:
+        base = "short"
+    elif core == []:
+        base = "int"
+    elif core == ["long"]:
+        base = "long"
+    elif core == ["long", "long"]:
+        base = "long long"
+    else:
+        raise _Unsupported()
+    if unsigned:
+        return "unsigned " + base
+    if signed and base == "char":
+        return "signed char"
+    return base
@@ -0,0 +1,222 @@
+import re
+from dataclasses import dataclass
+from typing import Optional
+
+from .macro_constant import (_BINARY_PRECEDENCE, _FIXED_WIDTH_TYPES,
+                             _RUST_KEYWORDS, _TYPE_WORDS, MacroConstant,
+                             _balanced, _char_literal, _float_literal, _int_literal,
+                             _normalize_type, _Unsupported, rust_type_of)
+
+_COMPARISONS = {"==", "!=", "<", ">", "<=", ">="}
+
+
+@dataclass
+class MacroFunction:
+    """
+    A function-like `#define` whose expansion is a pure expression over its
+    parameters, as a `macro_rules!` macro. A macro rather than an `#[inline] fn`
+    keeps C's untyped semantics: `MAX(a, b)` works for any operand type.
+    """
+    name: str
+    params: list[str]
+    body: str
+    rust_body: str
+
+    def rust_code(self) -> str:
+        matchers = ", ".join(f"${param}:expr" for param in self.params)
+        return (
+            f"macro_rules! {self.name} {{\n"
+            f"    ({matchers}) => {{\n"
+            f"        {self.rust_body}\n"
+            f"    }};\n"
+            f"}}\n"
+        )
+
+
+@dataclass
+class _Expr:
+    rust: str
+    # comparisons and logical operators yield `bool` in Rust, `int` in C
+    is_bool: bool = False
+
+
+def translate_macro_function(
+    name: str,
+    params: list[str],
+    body_tokens: list[str],
+    constants: Optional[dict[str, MacroConstant]] = None,
+    macros: Optional[dict[str, "MacroFunction"]] = None,
+) -> Optional[MacroFunction]:
+    """
+    Translates the body of a function-like macro into a Rust expression over
+    `$param` fragments. Returns None when the expansion is not a pure
+    expression (assignments, increments, the comma operator, `#`/`##`,
+    `sizeof`, or calls to functions rather than translated macros); such
+    macros are left for the LLM to expand at each call site.
+    """
+    if not body_tokens or name in _RUST_KEYWORDS or name.startswith("__"):
+        return None
+    if any(param == "..." or param in _RUST_KEYWORDS for param in params):
+        return None
+    translator = _MacroExprTranslator(body_tokens, params, constants or {}, macros or {})
+    try:
+        expr = translator.parse_conditional()
+        if translator.pos != len(body_tokens):
+            return None
+    except _Unsupported:
+        return None
+    return MacroFunction(
+        name=name,
+        params=list(params),
+        body=" ".join(body_tokens),
+        rust_body=_strip_parens(expr.rust),
+    )
+
+
+def _strip_parens(rust: str) -> str:
+    if rust.startswith("(") and rust.endswith(")") and _balanced(rust[1:-1]):
+        return rust[1:-1]
+    return rust
+
+
+def _as_bool(expr: _Expr) -> str:
+    return expr.rust if expr.is_bool else f"({expr.rust} != 0)"
+
+
+def _as_value(expr: _Expr) -> str:
+    return f"({expr.rust} as i32)" if expr.is_bool else expr.rust
+
+
+class _MacroExprTranslator:
+    def __init__(
+        self,
+        tokens: list[str],
+        params: list[str],
+        constants: dict[str, MacroConstant],
+        macros: dict[str, MacroFunction],
+    ):
+        self.tokens = tokens
+        self.params = set(params)
+        self.constants = constants
+        self.macros = macros
+        self.pos = 0
+
+    def _peek(self, offset: int = 0) -> Optional[str]:
+        index = self.pos + offset
+        return self.tokens[index] if index < len(self.tokens) else None
+
+    def _next(self) -> str:
+        token = self._peek()
+        if token is None:
+            raise _Unsupported()
+        self.pos += 1
+        return token
+
+    def _expect(self, token: str) -> None:
+        if self._next() != token:
+            raise _Unsupported()
+
+    def parse_conditional(self) -> _Expr:
+        condition = self.parse_binary(0)
+        if self._peek() != "?":
+            return condition
+        self.pos += 1
+        then = self.parse_conditional()
+        self._expect(":")
+        otherwise = self.parse_conditional()
+        if then.is_bool != otherwise.is_bool:
+            then, otherwise = _Expr(_as_value(then)), _Expr(_as_value(otherwise))
+        return _Expr(
+            f"(if {_strip_parens(_as_bool(condition))} {{ {then.rust} }} else {{ {otherwise.rust} }})",
+            then.is_bool,
+        )
+
+    def parse_binary(self, min_precedence: int) -> _Expr:
+        left = self.parse_unary()
+        while True:
+            op = self._peek()
+            precedence = _BINARY_PRECEDENCE.get(op or "")
+            if precedence is None or precedence <= min_precedence:
+                return left
+            self.pos += 1
+            right = self.parse_binary(precedence)
+            if op in ("&&", "||"):
+                left = _Expr(f"({_as_bool(left)} {op} {_as_bool(right)})", True)
+            elif op in _COMPARISONS:
+                left = _Expr(f"({_as_value(left)} {op} {_as_value(right)})", True)
+            else:
+                left = _Expr(f"({_as_value(left)} {op} {_as_value(right)})")
+
+    def parse_unary(self) -> _Expr:
+        token = self._next()
+        if token in ("++", "--", "&", "*", "sizeof"):
+            # side effects and pointer operations are left to the LLM
+            raise _Unsupported()
+        if token == "!":
+            operand = self.parse_unary()
+            return _Expr(f"(!{_as_bool(operand)})", True)
+        if token == "~":
+            return _Expr(f"(!{_as_value(self.parse_unary())})")
+        if token == "-":
+            return _Expr(f"(-{_as_value(self.parse_unary())})")
+        if token == "+":
+            return _Expr(_as_value(self.parse_unary()))
+        if token == "(":
+            cast_type = self._cast_type()
+            if cast_type is not None:
+                operand = self.parse_unary()
+                return _Expr(f"({_as_value(operand)} as {rust_type_of(cast_type)})")
+            inner = self.parse_conditional()
+            self._expect(")")
+            return inner
+        return self._primary(token)
+
+    def _cast_type(self) -> Optional[str]:
+        end = self.pos
+        while end < len(self.tokens) and self.tokens[end] != ")":
+            end += 1
+        words = self.tokens[self.pos:end]
+        if not words or end >= len(self.tokens) or any(word in self.params for word in words):
+            return None
+        if len(words) == 1 and words[0] in _FIXED_WIDTH_TYPES:
+            c_type = _FIXED_WIDTH_TYPES[words[0]]
+        elif all(word in _TYPE_WORDS for word in words):
+            c_type = _normalize_type(words)
+        else:
+            return None
+        self.pos = end + 1
+        return c_type
+
+    def _primary(self, token: str) -> _Expr:
+        if token in self.params:
+            return _Expr(f"${token}")
+        if token.startswith("'"):
+            return _Expr(_char_literal(token).rust)
+        if token[0].isdigit() or token.startswith("."):
+            return _Expr(self._number(token))
+        if token in self.constants:
+            return _Expr(token)
+        if token in self.macros and self._peek() == "(":
+            self.pos += 1
+            args: list[str] = []
+            if self._peek() != ")":
+                while True:
+                    args.append(_as_value(self.parse_conditional()))
+                    if self._peek() != ",":
+                        break
+                    self.pos += 1
+            self._expect(")")
+            if len(args) != len(self.macros[token].params):
+                raise _Unsupported()
+            return _Expr(f"{token}!({', '.join(args)})")
+        raise _Unsupported()
+
+    @staticmethod
+    def _number(token: str) -> str:
+        """A literal left untyped (so it takes the operands' type) unless C gives it a suffix."""
+        match = re.fullmatch(r"(0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)([uUlL]*)", token)
+        if match:
+            value = _int_literal(token)
+            return value.rust + (rust_type_of(value.c_type) if match.group(2) else "")
+        value = _float_literal(token)
+        return value.rust + ("f32" if value.c_type == "float" else "")
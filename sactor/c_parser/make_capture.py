@@ -0,0 +1,148 @@
+import json
+import os
+import shlex
+import shutil
+import stat
+import subprocess
+import sys
+import tempfile
+from typing import Optional, Sequence
+
+from sactor import logging as sactor_logging
+
+logger = sactor_logging.get_logger(__name__)
+
+# Compiler names Makefiles commonly invoke directly instead of through $(CC)
+_COMPILER_NAMES = ("cc", "gcc", "clang", "c99")
+
+_WRAPPER_TEMPLATE = '''#!{python}
+import json, os, sys
+with open({log!r}, "a", encoding="utf-8") as fh:
+    fh.write(json.dumps({{"directory": os.getcwd(), "arguments": [{compiler!r}] + sys.argv[1:]}}) + "\\n")
+os.execv({compiler!r}, [{compiler!r}] + sys.argv[1:])
+'''
+
+
+def capture_make_compile_commands(
+    project_dir: str,
+    make_args: Sequence[str] = (),
+    output_path: Optional[str] = None,
+    use_bear: Optional[bool] = None,
+) -> str:
+    """
+    Runs `make -B` in `project_dir` and records every C compiler invocation as
+    a compile_commands.json (default: `<project_dir>/compile_commands.json`),
+    so plain Makefile projects go through the same per-translation-unit
+    pipeline as CMake ones. Uses `bear` when it is on PATH (or `use_bear` is
+    set), otherwise compiler wrappers placed ahead of the real compilers.
+    Returns the path of the written file.
+    """
+    project_dir = os.path.realpath(project_dir)
+    if not os.path.isdir(project_dir):
+        raise FileNotFoundError(f"Makefile project not found: {project_dir}")
+    if output_path is None:
+        output_path = os.path.join(project_dir, "compile_commands.json")
+    output_path = os.path.realpath(output_path)
+    if use_bear is None:
+        use_bear = shutil.which("bear") is not None
+
+    if use_bear:
+        _run_make(["bear", "--output", output_path, "--", "make", "-B", *make_args], project_dir)
+        entries = _c_entries(_load_json(output_path))
+    else:
+        entries = _capture_with_wrappers(project_dir, make_args)
+
+    if not entries:
+        raise ValueError(f"No C compiler invocations captured from make in {project_dir}")
+    with open(output_path, "w", encoding="utf-8") as fh:
+        json.dump(entries, fh, indent=2)
+    logger.info("Captured %d translation unit(s) from make into %s", len(entries), output_path)
+    return output_path
+
+
+def _capture_with_wrappers(project_dir: str, make_args: Sequence[str]) -> list[dict]:
+    with tempfile.TemporaryDirectory(prefix="sactor_make_") as tmpdir:
+        wrapper_dir = os.path.join(tmpdir, "bin")
+        os.makedirs(wrapper_dir)
+        log_path = os.path.join(tmpdir, "invocations.jsonl")
+        wrappers = []
+        for name in _COMPILER_NAMES:
+            compiler = shutil.which(name)
+            if compiler is None:
+                continue
+            wrapper = os.path.join(wrapper_dir, name)
+            with open(wrapper, "w", encoding="utf-8") as fh:
+                fh.write(_WRAPPER_TEMPLATE.format(
+                    python=sys.executable, log=log_path, compiler=compiler))
+            os.chmod(wrapper, os.stat(wrapper).st_mode | stat.S_IXUSR)
+            wrappers.append(wrapper)
+        if not wrappers:
+            raise ValueError("No C compiler found on PATH to capture make invocations with")
+
+        env = dict(os.environ)
+        env["PATH"] = wrapper_dir + os.pathsep + env.get("PATH", "")
+        # A command-line CC overrides assignments in the Makefile itself
+        _run_make(["make", "-B", f"CC={wrappers[0]}", *make_args], project_dir, env)
+
+        if not os.path.exists(log_path):
+            return []
+        with open(log_path, encoding="utf-8") as fh:
+            return _c_entries([json.loads(line) for line in fh])
+
+
+def _run_make(cmd: list[str], cwd: str, env: Optional[dict] = None):
+    logger.info("Capturing compiler invocations: %s", shlex.join(cmd))
+    try:
+        result = subprocess.run(cmd, cwd=cwd, env=env, capture_output=True, text=True)
+    except FileNotFoundError as exc:
+        raise ValueError(f"Failed to run {cmd[0]}: {exc}") from exc
+    if result.returncode != 0:
+        raise ValueError(
+            f"make failed in {cwd} (exit code {result.returncode}):\n{result.stderr[-2000:]}")
+
+
+def _load_json(path: str) -> list[dict]:
+    if not os.path.exists(path):
+        return []
+    with open(path, encoding="utf-8") as fh:
+        return json.load(fh)
+
+
+def _c_entries(invocations: list[dict]) -> list[dict]:
+    """
+    One compile-only entry per C source: link steps are dropped, and a
+    command compiling several sources (or compiling and linking at once) is
+    split per source with `-c` and without its `-o`.
+    """
+    entries: dict[str, dict] = {}
+    for invocation in invocations:
+        directory = invocation["directory"]
+        args = invocation.get("arguments") or shlex.split(invocation.get("command", ""))
+        if not args:
+            continue
+        sources = [arg for arg in args[1:] if arg.endswith(".c")]
+        if not sources:
+            continue
+        compile_only = "-c" in args
+        for source in sources:
+            arguments = [args[0]]
+            skip_next = False
+            for arg in args[1:]:
+                if skip_next:
+                    skip_next = False
+                    continue
+                if arg.endswith(".c") and arg != source:
+                    continue
+                if not compile_only and arg == "-o":
+                    skip_next = True
+                    continue
+                arguments.append(arg)
+            if not compile_only:
+                arguments.append("-c")
+            file_path = os.path.realpath(os.path.join(directory, source))
+            entries[file_path] = {
+                "directory": directory,
+                "arguments": arguments,
+                "file": file_path,
+            }
+    return list(entries.values())
@@ -0,0 +1,68 @@
+from dataclasses import dataclass
+from typing import Optional
+
+# Functions installing a signal disposition
+SIGNAL_REGISTRARS = {"signal", "sigaction", "sigset", "bsd_signal", "sysv_signal"}
+
+# Synchronous fault signals: their handlers catch memory errors and arithmetic
+# faults, which safe Rust reports as panics instead, so they are not translated
+FAULT_SIGNALS = {"SIGSEGV", "SIGBUS", "SIGFPE", "SIGILL", "SIGSYS", "SIGTRAP"}
+
+
+@dataclass(frozen=True)
+class SignalRegistration:
+    signal: str
+    # the handler function, `SIG_IGN`/`SIG_DFL`, or None when it is not a name
+    handler: Optional[str]
+
+
+def is_signal_registrar(name: str) -> bool:
+    return name in SIGNAL_REGISTRARS
+
+
+def signal_flag_global_code(name: str, c_type: str, initial: str = "0") -> Optional[str]:
+    """The idiomatic `static` for a `volatile sig_atomic_t` global set by signal handlers."""
+    c_type = c_type.replace("volatile", "").strip()
+    if c_type != "sig_atomic_t":
+        return None
+    return f"pub static {name}: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new({initial});\n"
+
+
+def signal_plan(registrations: list[SignalRegistration], handled_signals: list[str], idiomatic: bool) -> str:
+    """
+    Guidance for a function registering signal handlers (`registrations`)
+    and/or registered itself as the handler of `handled_signals`.
+    """
+    if not idiomatic:
+        return '''
+The function uses signal handlers. Keep registering them with `libc::signal`/`libc::sigaction`, passing the handler as `handler as *const () as libc::sighandler_t` where the handler is an `extern "C" fn(sig: i32)`. Handlers may only touch `volatile sig_atomic_t` globals (read and write them with `std::ptr::read_volatile`/`write_volatile`) and call async-signal-safe functions such as `libc::write` and `libc::_exit`.
+'''
+    steps: list[str] = []
+    faults = sorted({r.signal for r in registrations if r.signal in FAULT_SIGNALS})
+    handlers = {r.handler for r in registrations if r.signal not in FAULT_SIGNALS}
+    if handlers & {"SIG_IGN", "SIG_DFL"}:
+        steps.append("`signal(sig, SIG_IGN)` becomes `sactor_ignore_signal(sig)` and `signal(sig, SIG_DFL)` "
+                     "becomes `sactor_default_signal(sig)`.")
+    if handlers - {"SIG_IGN", "SIG_DFL"}:
+        steps.append("A handler that only sets a `sig_atomic_t` flag is registered with "
+                     "`sactor_signal_flag(libc::SIGINT, &FLAG)` instead, where `FLAG` is the `AtomicI32` the flag "
+                     "global becomes; the code polling the flag reads it with `FLAG.load(Ordering::SeqCst) != 0` "
+                     "(or `swap(0, ..)` to consume it), and any other work of the handler moves to that polling site.")
+        steps.append("Only where the handler must run at the moment of the signal, register it with "
+                     "`unsafe { sactor_signal(sig, handler) }` and keep the handler a plain `fn(i32)` that only "
+                     "uses atomics.")
+    if faults:
+        names = ", ".join(f"`{name}`" for name in faults)
+        steps.append(f"Do not register handlers for {names}: safe Rust reports out-of-bounds accesses, overflow "
+                     "and division by zero as panics instead of faults. If the C handler prints a diagnostic or "
+                     "exits with a specific status, install the same behavior with `std::panic::set_hook` "
+                     "(calling `std::process::exit` with the C status). Keep a raw `libc::sigaction` handler "
+                     "only if `unsafe` code can still fault.")
+    if handled_signals:
+        names = ", ".join(f"`{name}`" for name in handled_signals)
+        steps.append(f"The function is the handler for {names}: keep the signature `fn(sig: i32)` and translate "
+                     "its `sig_atomic_t` globals as the `AtomicI32` statics declared for them, setting them with "
+                     "`store(.., Ordering::SeqCst)`; do not allocate, lock or print in it.")
+    plan = "\n".join(f"- {step}" for step in steps)
+    return ("The function uses signal handlers. Translate them with the `sactor_*` signal functions, "
+            f"which work like the `signal_hook` crate:\n{plan}\n")
@@ -0,0 +1,98 @@
+from typing import Optional
+
+# `FILE *` functions and the `std::fs`/`std::io` operations the idiomatic
+# stage maps them to
+STDIO_FUNCTIONS: dict[str, str] = {
+    "fopen": "`std::fs::File::open`/`File::create`/`OpenOptions`, by mode (see below)",
+    "fdopen": "`std::fs::File::from(OwnedFd)`",
+    "fclose": "dropping the value (after `flush()` for a `BufWriter`, to observe write errors)",
+    "fread": "`Read::read_exact`, or `Read::read` in a loop where a short count is expected",
+    "fwrite": "`Write::write_all`",
+    "fgets": "`BufRead::read_line` (keeping the `\\n`, like `fgets`)",
+    "getline": "`BufRead::read_line`",
+    "fgetc": "`Read::read` of one byte (or `bytes()`), with `None` for `EOF`",
+    "getc": "`Read::read` of one byte (or `bytes()`), with `None` for `EOF`",
+    "ungetc": "`BufRead::fill_buf` to peek before `consume`",
+    "fputs": "`Write::write_all(s.as_bytes())`",
+    "fputc": "`Write::write_all(&[c])`",
+    "putc": "`Write::write_all(&[c])`",
+    "fprintf": "`write!`/`writeln!`",
+    "fscanf": "`BufRead::read_line` followed by `str::split_whitespace` and `parse`",
+    "fseek": "`Seek::seek` with `SeekFrom::Start`/`Current`/`End` for `SEEK_SET`/`SEEK_CUR`/`SEEK_END`",
+    "ftell": "`Seek::stream_position`",
+    "rewind": "`Seek::rewind`",
+    "fflush": "`Write::flush`",
+    "feof": "a read returning `Ok(0)` (there is no end-of-file flag)",
+    "ferror": "the `Err` of the failing operation (there is no error flag)",
+    "setvbuf": "choosing `BufReader`/`BufWriter::with_capacity`, or no wrapper for unbuffered streams",
+    "tmpfile": "a `File` created in `std::env::temp_dir()` and removed when done",
+}
+
+# calls also common on the standard streams alone, e.g. `fprintf(stderr, ...)`
+_STREAM_WRITERS = {"fprintf", "fputs", "fputc", "putc", "fflush"}
+
+# `fopen` modes and the `std::fs` call opening a file the same way
+_FOPEN_MODES: dict[str, str] = {
+    "r": "File::open(path)",
+    "w": "File::create(path)",
+    "a": "OpenOptions::new().append(true).create(true).open(path)",
+    "r+": "OpenOptions::new().read(true).write(true).open(path)",
+    "w+": "OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)",
+    "a+": "OpenOptions::new().read(true).append(true).create(true).open(path)",
+}
+
+
+def is_stdio_call(name: str) -> bool:
+    return name in STDIO_FUNCTIONS
+
+
+def uses_file_streams(stdio_calls: list[str], std_streams: list[str]) -> bool:
+    """Whether the function works on `FILE *` streams beyond writing to `stdout`/`stderr`."""
+    return bool(stdio_calls) and (not std_streams or not set(stdio_calls) <= _STREAM_WRITERS)
+
+
+def fopen_open_call(mode: str) -> Optional[str]:
+    """The `std::fs` call opening a file like `fopen` with `mode`; None for unknown modes."""
+    # `b` is a no-op on POSIX, and glibc's `x` (exclusive create) maps to `create_new`
+    exclusive = "x" in mode
+    mode = mode.replace("b", "").replace("x", "")
+    call = _FOPEN_MODES.get(mode)
+    if call is None or (exclusive and not mode.startswith("w")):
+        return None
+    if exclusive:
+        call = call.replace("File::create(path)", "OpenOptions::new().write(true).create_new(true).open(path)")
+        call = call.replace(".create(true).truncate(true)", ".create_new(true)")
+    return call
+
+
+def stdio_plan(stdio_calls: list[str], fopen_modes: list[str], std_streams: list[str]) -> str:
+    """
+    Guidance for translating a function's `FILE *` streams to `std::fs::File`
+    wrapped in `BufReader`/`BufWriter`, with how C's stream buffering differs.
+    `std_streams` are the standard streams (`stdin`, ...) it names.
+    """
+    mapped = [f"`{name}` becomes {STDIO_FUNCTIONS[name]}" for name in stdio_calls if name in STDIO_FUNCTIONS]
+    text = "The function uses `FILE *` streams. Translate them to `std::fs::File`:\n"
+    text += ("- A stream read from becomes a `std::io::BufReader<File>`, and a stream written to a "
+             "`std::io::BufWriter<File>`; a parameter taking a stream becomes `&mut BufReader<File>`/"
+             "`&mut BufWriter<File>` (or `&mut File` when it seeks and reads or writes little).\n")
+    if mapped:
+        text += f"- {'; '.join(mapped)}.\n"
+    opens = []
+    for mode in fopen_modes:
+        call = fopen_open_call(mode)
+        if call is not None:
+            opens.append(f"`fopen(path, \"{mode}\")` is `{call}`")
+    if opens:
+        text += f"- Open files with the same semantics: {'; '.join(opens)}.\n"
+    text += ("- Buffering compatibility: like a `FILE *`, a `BufWriter` holds written data until it is full, "
+             "flushed or dropped. Call `flush()` wherever the C code calls `fflush` or `fclose` and before the "
+             "file is read back or reopened, and before `std::process::exit`, which skips destructors (C's `exit` "
+             "flushes every stream). `BufWriter` reports write errors on `flush()` only; its `Drop` ignores "
+             "them. A `BufReader` reads ahead, so seek through the `BufReader` itself, never through "
+             "`get_mut()`.\n")
+    if std_streams:
+        text += ("- `stdin`/`stdout`/`stderr` become `std::io::stdin().lock()`/`stdout().lock()`/`stderr()`. "
+                 "Rust's stdout is line buffered even when redirected, where C fully buffers it, so "
+                 "write whole lines and flush stdout before writing to stderr where their order matters.\n")
+    return text
@@ -27,6 +27,15 @@ class StructInfo:
         else:
             self.data_type = DataType.UNION
 
+    @property
+    def layout(self) -> dict[str, int] | None:
+        """Size and alignment in bytes as computed by clang, or None if incomplete."""
+        size = self.node.type.get_size()
+        align = self.node.type.get_align()
+        if size < 0 or align < 0:
+            return None
+        return {"size": size, "align": align}
+
     def __hash__(self):
         return hash(self.name) + hash(self.location)
 
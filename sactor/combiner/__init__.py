@@ -1,5 +1,6 @@
 from .combiner import Combiner, merge_uses
 from .combiner_types import CombineResult
+from .crate_emitter import CrateEmitter, CrateUnit
 from .program_combiner import ProgramCombiner
 from .project_combiner import ProjectCombiner, TuArtifact
 from .rust_code import RustCode
@@ -10,6 +11,8 @@ __all__ = [
     'ProjectCombiner',
     'TuArtifact',
     'CombineResult',
+    'CrateEmitter',
+    'CrateUnit',
     'RustCode',
     'merge_uses'
 ]
@@ -7,3 +7,4 @@ class CombineResult(Enum):
     RUSTFIX_FAILED = auto()
     COMPILE_FAILED = auto()
     TEST_FAILED = auto()
+    LEAK_DETECTED = auto()
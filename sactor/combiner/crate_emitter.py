@@ -0,0 +1,179 @@
+import os
+import re
+import shutil
+from dataclasses import dataclass
+from typing import Optional, Sequence
+
+from sactor import logging as sactor_logging
+from sactor import utils
+
+logger = sactor_logging.get_logger(__name__)
+
+LIBC_DEPENDENCY = 'libc = "0.2.159"'
+C_OBJECTS_LIB = "sactor_c"
+
+
+def uses_libc(rust_code: str) -> bool:
+    """Whether the code still refers to the `libc` crate."""
+    return re.search(r"\blibc::|\buse\s+libc\b|\bextern\s+crate\s+libc\b", rust_code) is not None
+
+
+def cargo_manifest(
+    crate_name: str,
+    rust_code: str,
+    edition: str = "2021",
+    lib_crate_types: Optional[Sequence[str]] = None,
+    bins: Sequence[tuple[str, str]] = (),
+    build_script: bool = False,
+    rust_version: str = "",
+) -> str:
+    """
+    A `Cargo.toml` for `rust_code` (all of the crate's sources) depending only
+    on the crates the code uses. `lib_crate_types` adds a `[lib]` target,
+    `bins` one `[[bin]]` per `(name, path)` and `rust_version` the MSRV.
+    """
+    manifest = [
+        "[package]",
+        f'name = "{crate_name}"',
+        'version = "0.1.0"',
+        f'edition = "{edition}"',
+    ]
+    if rust_version:
+        manifest.append(f'rust-version = "{rust_version}"')
+    if build_script:
+        manifest.append('build = "build.rs"')
+    manifest += ["", "[dependencies]"]
+    if uses_libc(rust_code):
+        manifest.append(LIBC_DEPENDENCY)
+    manifest += utils.optional_dependencies(rust_code)
+    if lib_crate_types is not None:
+        crate_types = ", ".join(f'"{crate_type}"' for crate_type in lib_crate_types)
+        manifest += ["", "[lib]", f'name = "{crate_name}"', f"crate-type = [{crate_types}]"]
+    for name, path in bins:
+        manifest += ["", "[[bin]]", f'name = "{name}"', f'path = "{path}"']
+    manifest += ["", "[workspace]"]
+    return "\n".join(manifest) + "\n"
+
+
+@dataclass
+class CrateUnit:
+    """One translated unit: `kind` is `bin` (has a `main`) or `lib`."""
+    name: str
+    code: str
+    kind: str = "bin"
+
+
+class CrateEmitter:
+    """
+    Writes a complete, buildable crate around translated code: the manifest,
+    one target per unit (`src/lib.rs` for the library unit, `src/main.rs` for
+    a single binary or `src/bin/<name>.rs` for several), and a `build.rs`
+    linking any C objects that were not translated plus extra link arguments.
+
+    With `general.stable_output` the code is checked for nightly-only
+    constructs and the manifest records `general.msrv` as `rust-version`.
+    Under the `no_std` profile the library is rewritten for `#![no_std]` +
+    `alloc` and `.cargo/config.toml` builds it for `no_std.target`.
+    """
+
+    def __init__(self, config: dict):
+        self.edition = config['general'].get('rust_edition', '2021')
+        self.stable_output = config['general'].get('stable_output', False)
+        self.msrv = config['general'].get('msrv', '') if self.stable_output else ''
+        self.no_std = utils.no_std_profile(config)
+        self.no_std_config = config.get('no_std', {})
+
+    def emit(
+        self,
+        out_dir: str,
+        crate_name: str,
+        units: Sequence[CrateUnit],
+        lib_crate_types: Sequence[str] = ("rlib",),
+        c_objects: Sequence[str] = (),
+        link_args: Sequence[str] = (),
+    ) -> str:
+        """Writes the crate to `out_dir`, replacing what is there, and returns it."""
+        libs = [unit for unit in units if unit.kind == "lib"]
+        bins = [unit for unit in units if unit.kind == "bin"]
+        if len(libs) > 1:
+            raise ValueError(
+                f"A crate has at most one library target, got {', '.join(u.name for u in libs)}")
+        if not units:
+            raise ValueError("No translated units to emit")
+        if self.no_std:
+            if bins:
+                raise ValueError(
+                    f"The no_std profile emits a library, got binaries {', '.join(u.name for u in bins)}")
+            libs = [CrateUnit(unit.name, utils.no_std_crate(
+                unit.code, self.no_std_config.get('logger', '')), unit.kind) for unit in libs]
+            units = libs
+        if self.stable_output:
+            for unit in units:
+                constructs = utils.nightly_constructs(unit.code)
+                if constructs:
+                    raise ValueError(
+                        f"{unit.name}: {utils.describe_nightly_constructs(constructs)}")
+
+        if os.path.exists(out_dir):
+            shutil.rmtree(out_dir)
+        os.makedirs(os.path.join(out_dir, "src"))
+        bin_targets = []
+        for unit in bins:
+            path = "src/main.rs" if len(bins) == 1 else f"src/bin/{unit.name}.rs"
+            bin_targets.append((unit.name, path))
+            utils.save_code(os.path.join(out_dir, path), utils.enable_c_variadic(unit.code))
+        if libs:
+            utils.save_code(
+                os.path.join(out_dir, "src", "lib.rs"), utils.enable_c_variadic(libs[0].code))
+
+        build_script = bool(c_objects or link_args)
+        if build_script:
+            self._write_build_script(out_dir, c_objects, link_args)
+        if self.no_std:
+            target = self.no_std_config.get('target', 'thumbv7em-none-eabihf')
+            os.makedirs(os.path.join(out_dir, ".cargo"))
+            with open(os.path.join(out_dir, ".cargo", "config.toml"), "w", encoding="utf-8") as fh:
+                fh.write(f'[build]\ntarget = "{target}"\n')
+        manifest = cargo_manifest(
+            crate_name,
+            "\n".join(unit.code for unit in units),
+            edition=self.edition,
+            lib_crate_types=lib_crate_types if libs else None,
+            bins=bin_targets,
+            build_script=build_script,
+            rust_version=self.msrv,
+        )
+        with open(os.path.join(out_dir, "Cargo.toml"), "w", encoding="utf-8") as fh:
+            fh.write(manifest)
+        logger.info("Crate %s written to %s", crate_name, out_dir)
+        return out_dir
+
+    def _write_build_script(
+        self, out_dir: str, c_objects: Sequence[str], link_args: Sequence[str]
+    ) -> None:
+        lines = ["fn main() {"]
+        if c_objects:
+            # the objects are archived into the crate so it builds on its own
+            objects_dir = os.path.join(out_dir, "c_objects")
+            os.makedirs(objects_dir, exist_ok=True)
+            copied = []
+            for obj in c_objects:
+                target = os.path.join(objects_dir, os.path.basename(obj))
+                shutil.copyfile(obj, target)
+                copied.append(target)
+            utils.run_command(
+                ["ar", "rcs", os.path.join(objects_dir, f"lib{C_OBJECTS_LIB}.a"), *copied],
+                check=True)
+            lines += [
+                '    println!("cargo:rustc-link-search=native={}/c_objects", env!("CARGO_MANIFEST_DIR"));',
+                f'    println!("cargo:rustc-link-lib=static={C_OBJECTS_LIB}");',
+            ]
+        for arg in link_args:
+            if arg.startswith("-l"):
+                lines.append(f'    println!("cargo:rustc-link-lib={arg[2:]}");')
+            elif arg.startswith("-L"):
+                lines.append(f'    println!("cargo:rustc-link-search={arg[2:]}");')
+            else:
+                lines.append(f'    println!("cargo:rustc-link-arg={arg}");')
+        lines.append("}")
+        utils.save_code(os.path.join(out_dir, "build.rs"), "\n".join(lines) + "\n")
@@ -0,0 +1,67 @@
+import os
+import re
+
+from sactor import utils
+from sactor.c_parser import CParser
+
+from .crate_emitter import CrateEmitter, CrateUnit
+
+# headers covering the types C declarations commonly use
+HEADER_INCLUDES = ("stdbool.h", "stddef.h", "stdint.h", "stdio.h")
+
+
+def library_crate_name(c_file: str) -> str:
+    """A crate and library name derived from the C file, e.g. `libfoo.c` -> `libfoo`."""
+    stem = os.path.splitext(os.path.basename(c_file))[0]
+    name = re.sub(r"[^0-9A-Za-z_]", "_", stem).strip("_") or "library"
+    if name[0].isdigit():
+        name = f"lib_{name}"
+    return name
+
+
+def c_header(c_parser: CParser, guard: str) -> str:
+    """
+    Declarations of the library's structs, enums and functions taken from the
+    original C source, so C programs built against the original library can
+    include it unchanged when linking against the translation.
+    """
+    lines = [f"#ifndef {guard}", f"#define {guard}", ""]
+    lines.extend(f"#include <{header}>" for header in HEADER_INCLUDES)
+    lines.append("")
+    declarations = []
+    for enum in c_parser.get_enums():
+        declarations.append(_declaration(enum.node))
+    for struct in c_parser.get_structs():
+        declarations.append(_declaration(struct.node))
+    for function in c_parser.get_functions():
+        if function.name == "main":
+            continue
+        declarations.append(f"{function.get_signature()};")
+    lines.extend(declaration for declaration in declarations if declaration)
+    lines.extend(["", f"#endif /* {guard} */"])
+    return "\n".join(lines) + "\n"
+
+
+def _declaration(node) -> str:
+    # unnamed definitions only appear through the typedefs that were unfolded
+    if not node.spelling or "unnamed" in node.spelling:
+        return ""
+    return " ".join(token.spelling for token in utils.cursor_get_tokens(node)) + ";"
+
+
+def write_library_crate(out_dir: str, c_parser: CParser, rust_code: str, config: dict) -> str:
+    """
+    Writes a `cdylib` + `staticlib` crate for a translated C library to
+    `out_dir`: `src/lib.rs` holds the translation with every function exported
+    under its original C symbol, and `include/<name>.h` declares them.
+    Returns the crate name.
+    """
+    name = library_crate_name(c_parser.raw_filename)
+    CrateEmitter(config).emit(
+        out_dir, name, [CrateUnit(name, rust_code, kind="lib")],
+        lib_crate_types=("cdylib", "staticlib"))
+    include_dir = os.path.join(out_dir, "include")
+    os.makedirs(include_dir, exist_ok=True)
+    with open(os.path.join(include_dir, f"{name}.h"), "w", encoding="utf-8") as f:
+        f.write(c_header(c_parser, f"{name.upper()}_H"))
+    return name
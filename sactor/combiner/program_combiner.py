@@ -11,6 +11,7 @@ from sactor.verifier import E2EVerifier, VerifyResult
 
 from .combiner import Combiner
 from .combiner_types import CombineResult
+from .library_crate import write_library_crate
 from .rust_code import RustCode
 
 
@@ -88,6 +89,26 @@ class ProgramCombiner(Combiner):
                 e_code = f.read()
                 data_type_code[enum_name] = RustCode(e_code)
 
+        # `#define` constants and macros the translated functions refer to
+        for function in self.functions:
+            for constant in self.c_parser.get_macro_constants_for_function(function.name):
+                data_type_code[constant.name] = RustCode(constant.rust_code())
+            for macro in self.c_parser.get_macro_functions_for_function(function.name):
+                data_type_code[macro.name] = RustCode(macro.rust_code())
+
+        if is_idiomatic and any(getattr(f, 'is_variadic', False) for f in self.functions):
+            # variadic functions take `args: &[VarArg]`
+            data_type_code["VarArg"] = RustCode(utils.VARARG_ENUM_CODE)
+
+        if any(utils.uses_setjmp_scaffold(code.code)
+               for code in [*function_code.values(), *data_type_code.values()]):
+            # `setjmp`/`longjmp` are translated with `sactor_setjmp`/`sactor_longjmp`
+            data_type_code["JmpBuf"] = RustCode(utils.SETJMP_SCAFFOLD_CODE)
+
+        if any(utils.uses_signal_scaffold(code.code) for code in function_code.values()):
+            # idiomatic signal registration goes through `sactor_signal_flag`/`sactor_signal`
+            data_type_code["SactorSignals"] = RustCode(utils.SIGNAL_SCAFFOLD_CODE)
+
         if not is_idiomatic:
             # add stdio uses to data type code
             stdio_uses = set()
@@ -131,7 +152,8 @@ class ProgramCombiner(Combiner):
 
         if not skip_test:
             result = self.verifier.e2e_verify(
-                e2e_code)
+                e2e_code,
+                threaded=any(getattr(f, 'pthread_calls', None) for f in self.functions))
             if result[0] != VerifyResult.SUCCESS:
                 logger.error("Failed to verify the combined code: %s", result[1])
                 match result[0]:
@@ -161,9 +183,23 @@ class ProgramCombiner(Combiner):
 
         # save the warning stat
         self._stat_unsafe_blocks(combined_for_stat)
+        self._stat_pure_functions(combined_for_stat)
+        leak_check = self.config['general'].get('leak_check', 'warn')
+        leaks = []
+        if is_idiomatic and leak_check != 'off':
+            leaks = self._check_leak_patterns(combined_for_stat)
         with open(os.path.join(result_dir_with_type, "clippy_stat.json"), "w") as f:
             json.dump(self.clippy_stat, f, indent=4)
 
+        if leaks and leak_check == 'error':
+            return CombineResult.LEAK_DETECTED, None
+
+        if not self.is_executable and not is_idiomatic \
+                and not getattr(self.verifier, "processed_compile_commands", None):
+            # the exported code is what the C test programs were linked against
+            write_library_crate(
+                os.path.join(result_dir_with_type, "library"), self.c_parser, e2e_code,
+                self.config)
         return CombineResult.SUCCESS, output_code
 
     def _stat_unsafe_blocks(self, code: str) -> None:
@@ -181,6 +217,45 @@ class ProgramCombiner(Combiner):
         self.clippy_stat["unsafe_tokens"] = unsafe_tokens
         self.clippy_stat["unsafe_fraction"] = unsafe_fraction
 
+    def _stat_pure_functions(self, code: str) -> None:
+        """
+        Count functions free of side effects in the combined Rust code.
+
+        - pure_functions: functions with no I/O, FFI, global or unsafe access.
+        - const_fn_candidates: pure functions that could also be `const fn`.
+        - pure_fraction: pure_functions / total_functions (0 when there are none).
+        """
+        try:
+            functions = rust_ast_parser.find_pure_functions(code)
+        except Exception as e:
+            logger.warning("Failed to analyze function purity: %s", e)
+            return
+        pure_functions = sum(1 for f in functions if f["pure"])
+
+        self.clippy_stat["total_functions"] = len(functions)
+        self.clippy_stat["pure_functions"] = pure_functions
+        self.clippy_stat["const_fn_candidates"] = sum(
+            1 for f in functions if f["const_candidate"])
+        self.clippy_stat["pure_fraction"] = (
+            pure_functions / len(functions)) if functions else 0.0
+
+    def _check_leak_patterns(self, code: str) -> list[dict]:
+        """
+        Warn about constructs that leak by design (Box::leak, mem::forget,
+        ManuallyDrop, into_raw without from_raw) and record their count.
+        """
+        try:
+            leaks = rust_ast_parser.find_leak_patterns(code)
+        except Exception as e:
+            logger.warning("Failed to scan for leak patterns: %s", e)
+            return []
+        for leak in leaks:
+            logger.warning(
+                "Leak pattern %s in %s (line %d)",
+                leak["kind"], leak["function"], leak["line"])
+        self.clippy_stat["leak_patterns"] = len(leaks)
+        return leaks
+
     def _get_warning_error_count(self, compiler_output: str, has_error: bool) -> tuple[int, int]:
         warnings_count = 0
         compiler_output_lines = compiler_output.split("\n")
@@ -2,12 +2,16 @@ import os
 import json
 import shlex
 import shutil
+import subprocess
 from dataclasses import dataclass
 from typing import Optional
 
 from sactor import logging as sactor_logging
 from sactor import utils, rust_ast_parser
 from sactor.c_parser import CParser
+from sactor.verifier.task_fixtures import TaskFixtures
+
+from .crate_emitter import cargo_manifest
 
 logger = sactor_logging.get_logger(__name__)
 
@@ -235,59 +239,63 @@ class ProjectCombiner:
                         func_owner_by_name.setdefault(ref_name, owner)
         return cross_deps, func_owner_by_name
 
-    def _write_manifest(self, crate_dir: str, with_bin: bool, crate_name: str) -> None:
-        manifest = [
-            "[package]",
-            f"name = \"{crate_name}\"",
-            "version = \"0.1.0\"",
-            "edition = \"2021\"",
-            "",
-            "[dependencies]",
-            "libc = \"0.2.159\"",
-        ]
-        if not with_bin:
-            manifest += [
-                "",
-                "[lib]",
-                f"name = \"{crate_name}\"",
-                "crate-type = [\"rlib\"]",
-            ]
-        else:
-            manifest += [
-                "",
-                "[[bin]]",
-                f"name = \"{crate_name}\"",
-                "path = \"src/main.rs\"",
-            ]
-        manifest += [
-            "",
-            "[workspace]",
-        ]
+    def _write_manifest(
+        self,
+        crate_dir: str,
+        with_bin: bool,
+        crate_name: str,
+        rust_code: str,
+    ) -> None:
+        manifest = cargo_manifest(
+            crate_name,
+            rust_code,
+            edition=self.config['general'].get('rust_edition', '2021'),
+            lib_crate_types=None if with_bin else ["rlib"],
+            bins=[(crate_name, "src/main.rs")] if with_bin else [],
+        )
         with open(os.path.join(crate_dir, "Cargo.toml"), "w", encoding="utf-8") as fh:
-            fh.write("\n".join(manifest) + "\n")
+            fh.write(manifest)
 
-    def _load_test_cmd(self) -> list[list[str]]:
+    def _load_test_cmd(self) -> list[tuple[list[str], dict]]:
         raw = utils.read_file(self.test_cmd_path).strip()
         arr = json.loads(raw)
-        out: list[list[str]] = []
+        out: list[tuple[list[str], dict]] = []
         for item in arr:
             cmd = item.get("command")
             if isinstance(cmd, str):
-                out.append(cmd.split())
+                out.append((cmd.split(), item))
             elif isinstance(cmd, list):
-                out.append([str(x) for x in cmd])
+                out.append(([str(x) for x in cmd], item))
         return out
 
     def _run_project_tests(self, bin_path: str) -> tuple[bool, Optional[str]]:
         test_cmds = self._load_test_cmd()
         env = os.environ.copy()
         cwd = os.path.dirname(os.path.abspath(self.test_cmd_path))
-        for cmd in test_cmds:
-            expanded = [(bin_path if tok == "%t" else tok) for tok in cmd]
+        placeholders = {"%t": bin_path, "%d": cwd}
+        for cmd, task in test_cmds:
+            expanded = [placeholders.get(tok, tok) for tok in cmd]
             logger.debug("Project test: %s", expanded)
-            res = utils.run_command(expanded, cwd=cwd)
-            if res.returncode != 0:
-                return False, (res.stderr or res.stdout)
+            fixtures = TaskFixtures(task, cwd)
+            options = utils.TaskRunOptions.from_task(task)
+            try:
+                res = utils.run_command(
+                    expanded,
+                    cwd=options.resolve_cwd(fixtures.setup()),
+                    timeout=options.timeout,
+                    env=options.merge_env(env),
+                    memory_limit_mb=options.memory_limit_mb,
+                    signals=options.signals,
+                )
+                if res.returncode != 0:
+                    return False, (res.stderr or res.stdout)
+                fixture_error = fixtures.check()
+            except (subprocess.TimeoutExpired, TimeoutError) as e:
+                return False, f"Test timed out: {e}"
+            finally:
+                fixtures.teardown()
+            if fixture_error:
+                return False, fixture_error
         return True, None
 
     # --------------- main entry ---------------
@@ -309,12 +317,16 @@ class ProjectCombiner:
 
         # Prepare module declarations for non-entry TUs and write module files
         module_decls: list[str] = []
+        crate_code: list[str] = []
+        c_variadic = False
         for tu_path, result_dir in tu_map.items():
             rs_rel_path, mod_name = self._rel_c_to_rs_path(tu_path, src_root)
             out_path = os.path.join(src_dir, rs_rel_path)
             os.makedirs(os.path.dirname(out_path), exist_ok=True)
 
             code = self._collect_rs_code_for_tu(result_dir)
+            c_variadic |= utils.defines_c_variadic(code)
+            crate_code.append(code)
 
             # Inject cross-TU imports needed by this TU
             needed = sorted(cross_deps.get(os.path.realpath(tu_path), set()))
@@ -336,9 +348,15 @@ class ProjectCombiner:
 
         # Write manifest (bin if entry exists; otherwise lib)
         with_bin = bool(entry_tu)
-        self._write_manifest(crate_dir, with_bin=with_bin, crate_name=crate_name)
+        self._write_manifest(
+            crate_dir, with_bin=with_bin, crate_name=crate_name, rust_code="\n".join(crate_code)
+        )
 
         # Compose crate root
+        root_attrs = ["#![allow(unused_imports, unused_variables, dead_code)]"]
+        if c_variadic:
+            # feature gates only take effect at the crate root
+            root_attrs.insert(0, utils.C_VARIADIC_FEATURE)
         if with_bin:
             # Main TU code goes into src/main.rs
             assert entry_tu is not None
@@ -346,7 +364,7 @@ class ProjectCombiner:
             # Gather code for entry
             entry_code = self._collect_rs_code_for_tu(tu_map[entry_tu])
             # In main.rs, declare all other modules
-            root = ["#![allow(unused_imports, unused_variables, dead_code)]"]
+            root = list(root_attrs)
             root.extend(module_decls)
 
             # Bring cross-TU deps used in entry into scope
@@ -364,7 +382,7 @@ class ProjectCombiner:
             utils.save_code(os.path.join(src_dir, "main.rs"), main_rs)
         else:
             # No entry: build a library root that declares all modules
-            root = ["#![allow(unused_imports, unused_variables, dead_code)]"]
+            root = list(root_attrs)
             root.extend(module_decls)
             lib_rs = "\n".join(root) + "\n"
             utils.save_code(os.path.join(src_dir, "lib.rs"), lib_rs)
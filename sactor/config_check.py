@@ -0,0 +1,216 @@
+import difflib
+import json
+import os
+from dataclasses import dataclass
+from pathlib import Path
+from typing import Any, Iterable, Mapping, Optional
+
+from jsonschema import Draft202012Validator
+
+from sactor import logging as sactor_logging
+from sactor import utils
+
+logger = sactor_logging.get_logger(__name__)
+
+CONFIG_SCHEMA_FILE = "config.schema.json"
+# environment variables litellm reads for `"os.environ/NAME"` values
+ENVIRON_PREFIX = "os.environ/"
+
+
+@dataclass(frozen=True)
+class ConfigIssue:
+    # dotted key of the value, e.g. "litellm.model_list[1].model_name"; empty for the whole config
+    path: str
+    message: str
+    # error: the run would fail or misbehave; warning: the value is ignored or may be unusable
+    severity: str = "error"
+
+    def __str__(self) -> str:
+        return f"{self.path}: {self.message}" if self.path else self.message
+
+
+class ConfigError(ValueError):
+    def __init__(self, issues: list[ConfigIssue]):
+        self.issues = issues
+        super().__init__("Invalid configuration:\n" + "\n".join(f"  - {issue}" for issue in issues))
+
+
+def load_config_schema() -> dict:
+    with open(Path(__file__).resolve().parent / "_resources" / CONFIG_SCHEMA_FILE, encoding="utf-8") as f:
+        return json.load(f)
+
+
+def _path(parts: Iterable[Any]) -> str:
+    path = ""
+    for part in parts:
+        path += f"[{part}]" if isinstance(part, int) else f".{part}" if path else str(part)
+    return path
+
+
+def _default(defaults: dict, parts: list[Any]) -> Optional[Any]:
+    """The scalar default value at `parts`, None when there is none."""
+    value: Any = defaults
+    for part in parts:
+        if not isinstance(value, dict) or part not in value:
+            return None
+        value = value[part]
+    return None if isinstance(value, (dict, list)) else value
+
+
+def _describe(instance: Any) -> str:
+    return json.dumps(instance) if isinstance(instance, (str, int, float, bool)) else type(instance).__name__
+
+
+def _schema_message(error) -> str:
+    schema, value = error.schema, error.validator_value
+    match error.validator:
+        case "type":
+            expected = " or ".join(value) if isinstance(value, list) else value
+            message = f"expected {expected}, got {_describe(error.instance)}"
+        case "enum":
+            message = f"expected one of {', '.join(json.dumps(v) for v in value)}, got {_describe(error.instance)}"
+        case "minimum":
+            message = f"must be at least {value}, got {error.instance}"
+        case "exclusiveMinimum":
+            message = f"must be greater than {value}, got {error.instance}"
+        case "minLength":
+            message = "must not be empty"
+        case "pattern" | "oneOf":
+            message = f"{_describe(error.instance)} is not valid"
+        case _:
+            message = error.message
+    if "description" in schema and error.validator in ("pattern", "oneOf"):
+        message += f", expected {schema['description']}"
+    return message
+
+
+def _unknown_keys(error, parts: list[Any]) -> list[ConfigIssue]:
+    known = sorted(error.schema.get("properties", {}))
+    issues = []
+    for key in sorted(set(error.instance) - set(known)):
+        path = _path(parts + [key])
+        suggestion = difflib.get_close_matches(key, known, n=1, cutoff=0.75)
+        if suggestion:
+            issues.append(ConfigIssue(path, f"unknown key, did you mean `{suggestion[0]}`?"))
+        else:
+            # plugins may read sections and keys of their own
+            issues.append(ConfigIssue(path, "unknown key, ignored", "warning"))
+    return issues
+
+
+def _schema_issues(config: dict) -> list[ConfigIssue]:
+    defaults = utils.load_default_config()
+    validator = Draft202012Validator(load_config_schema())
+    issues = []
+    for error in sorted(validator.iter_errors(config), key=lambda e: _path(e.absolute_path)):
+        parts = list(error.absolute_path)
+        if error.validator == "additionalProperties":
+            issues.extend(_unknown_keys(error, parts))
+            continue
+        if error.validator == "required":
+            missing = [key for key in error.validator_value if key not in error.instance]
+            issues.extend(ConfigIssue(_path(parts + [key]), "missing required key") for key in missing)
+            continue
+        message = _schema_message(error)
+        default = _default(defaults, parts)
+        if default is not None:
+            message += f" (default: {json.dumps(default)})"
+        issues.append(ConfigIssue(_path(parts), message))
+    return issues
+
+
+def _section(config: dict, name: str) -> dict:
+    section = config.get(name, {})
+    return section if isinstance(section, dict) else {}
+
+
+def _used_models(config: dict) -> list[tuple[str, str]]:
+    """The (path, model name) of every model the config makes sactor query."""
+    models = []
+    model = _section(config, "general").get("model")
+    if isinstance(model, str) and model:
+        models.append(("general.model", model))
+    ensemble = _section(config, "ensemble")
+    for stage in ("unidiomatic", "idiomatic"):
+        names = ensemble.get(stage, [])
+        if isinstance(names, list):
+            models.extend((f"ensemble.{stage}[{i}]", name) for i, name in enumerate(names) if isinstance(name, str))
+    cost = _section(config, "cost")
+    downgrade_model = cost.get("downgrade_model")
+    if cost.get("on_budget_exceeded") == "downgrade" and isinstance(downgrade_model, str) and downgrade_model:
+        models.append(("cost.downgrade_model", downgrade_model))
+    return models
+
+
+def _semantic_issues(config: dict, environ: Mapping[str, str]) -> list[ConfigIssue]:
+    """Constraints between values that the schema cannot express."""
+    issues = []
+    general = _section(config, "general")
+    cost = _section(config, "cost")
+    local = _section(config, "local")
+
+    if cost.get("on_budget_exceeded") == "downgrade" and not cost.get("downgrade_model"):
+        issues.append(ConfigIssue("cost.downgrade_model", 'required with on_budget_exceeded = "downgrade"'))
+    if general.get("stable_output") is True:
+        if general.get("sanitizers") is True:
+            issues.append(ConfigIssue("general.sanitizers", "needs a nightly toolchain, which general.stable_output rules out"))
+        if general.get("miri_check", "off") != "off":
+            issues.append(ConfigIssue("general.miri_check", "needs a nightly toolchain, which general.stable_output rules out"))
+
+    if isinstance(local.get("api_base"), str) and local["api_base"]:
+        # every query goes to the local server, which knows its own models
+        window, reserved = local.get("context_window"), local.get("max_output_tokens")
+        if isinstance(window, int) and isinstance(reserved, int) and reserved >= window:
+            issues.append(ConfigIssue(
+                "local.max_output_tokens",
+                f"must be less than local.context_window ({window}), which also holds the prompt"))
+        return issues
+
+    model_list = _section(config, "litellm").get("model_list", [])
+    entries = {}
+    if isinstance(model_list, list):
+        for i, entry in enumerate(model_list):
+            if isinstance(entry, dict) and isinstance(entry.get("model_name"), str):
+                entries.setdefault(entry["model_name"], (i, entry))
+    checked = set()
+    for path, name in _used_models(config):
+        if name not in entries:
+            message = f"model `{name}` is not a model_name of litellm.model_list"
+            suggestion = difflib.get_close_matches(name, list(entries), n=1)
+            message += f", did you mean `{suggestion[0]}`?" if suggestion else f" ({', '.join(sorted(entries)) or 'empty'})"
+            issues.append(ConfigIssue(path, message))
+            continue
+        if name in checked:
+            continue
+        checked.add(name)
+        i, entry = entries[name]
+        params = entry.get("litellm_params", {})
+        if not isinstance(params, dict):
+            continue
+        for key, value in params.items():
+            if isinstance(value, str) and value.startswith(ENVIRON_PREFIX) and value[len(ENVIRON_PREFIX):] not in environ:
+                issues.append(ConfigIssue(
+                    f"litellm.model_list[{i}].litellm_params.{key}",
+                    f"environment variable {value[len(ENVIRON_PREFIX):]} is not set, model `{name}` cannot be queried",
+                    "warning"))
+    return issues
+
+
+def check_config(config: dict, environ: Optional[Mapping[str, str]] = None) -> list[ConfigIssue]:
+    """
+    The issues of `config`, a configuration merged with the defaults: the
+    values that do not match `config.schema.json`, the unknown keys, and the
+    models and settings that cannot work together.
+    """
+    return _schema_issues(config) + _semantic_issues(config, os.environ if environ is None else environ)
+
+
+def validate_config(config: dict) -> None:
+    """Logs the warnings of `check_config`, and raises ConfigError listing its errors."""
+    issues = check_config(config)
+    for issue in issues:
+        if issue.severity == "warning":
+            logger.warning("Config %s", issue)
+    errors = [issue for issue in issues if issue.severity == "error"]
+    if errors:
+        raise ConfigError(errors)
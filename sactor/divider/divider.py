@@ -1,3 +1,4 @@
+from sactor import rust_ast_parser
 from sactor.c_parser import CParser, StructInfo, FunctionInfo
 
 
@@ -32,6 +33,15 @@ class Divider():
     def get_function_order(self) -> list[list[FunctionInfo]]:
         return self.function_order
 
+    def get_function_globals(self, rust_code: str) -> dict[str, list[str]]:
+        # Globals each function touches, so they can be translated together
+        # instead of being discovered through compile errors.
+        usage = rust_ast_parser.global_usage(rust_code)
+        return {
+            name: sorted(set(access["reads"]) | set(access["writes"]))
+            for name, access in usage.items()
+        }
+
     def _extract_order(self, lst: list, dependencies_accessor) -> list[list]:
         dependencies_table = {}
         for item in lst:
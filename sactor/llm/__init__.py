@@ -1,10 +1,28 @@
+from .cache import ResponseCache
+from .cost import BudgetExceededError, CostTracker
 from .llm import LLM
+from .local_llm import LocalLLM
+from .replay import ReplayBundle, ReplayDivergenceError, ReplayLLM
 
 __all__ = [
+    'BudgetExceededError',
+    'CostTracker',
     'LLM',
+    'LocalLLM',
+    'ReplayBundle',
+    'ReplayDivergenceError',
+    'ReplayLLM',
+    'ResponseCache',
 ]
 
 
-def llm_factory(config: dict, encoding=None, system_message=None) -> LLM:
-    # litellm handles all providers through unified interface
-    return LLM(config, encoding=encoding, system_msg=system_message)
+def llm_factory(config: dict, encoding=None, system_message=None, replay: ReplayBundle | None = None) -> LLM:
+    if replay is not None and replay.replaying:
+        return ReplayLLM(config, replay, encoding=encoding, system_msg=system_message)
+    if config.get('local', {}).get('api_base'):
+        llm = LocalLLM(config, encoding=encoding, system_msg=system_message)
+    else:
+        # litellm handles all providers through unified interface
+        llm = LLM(config, encoding=encoding, system_msg=system_message)
+    llm.replay = replay
+    return llm
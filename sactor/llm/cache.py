@@ -0,0 +1,74 @@
+import hashlib
+import json
+import os
+from collections import defaultdict
+from typing import Optional
+
+from sactor import logging as sactor_logging
+
+logger = sactor_logging.get_logger(__name__)
+
+CACHE_VERSION = 1
+
+
+def default_cache_dir() -> str:
+    cache_home = os.environ.get("XDG_CACHE_HOME") or os.path.join(os.path.expanduser("~"), ".cache")
+    return os.path.join(cache_home, "sactor", "llm")
+
+
+class ResponseCache:
+    """
+    Persistent cache of LLM responses keyed by a hash of the model, the stage,
+    the system message and the prompt, one JSON file per entry under
+    `cache.path`. A prompt asked again within a run, e.g. by a retry, maps to
+    the next response cached for it rather than the same one, so a re-run
+    replays the queries of the run that filled the cache in order.
+    """
+
+    def __init__(self, config: dict):
+        cache_config = config.get('cache', {})
+        self.enabled = bool(cache_config.get('enabled', False))
+        self.path = cache_config.get('path') or default_cache_dir()
+        self.hits = 0
+        self.misses = 0
+        # times each key was asked in this run
+        self._asked: dict[str, int] = defaultdict(int)
+
+    @staticmethod
+    def key(model: str, stage: Optional[str], system_msg: Optional[str], prompt: str) -> str:
+        content = json.dumps([CACHE_VERSION, model, stage, system_msg, prompt])
+        return hashlib.sha256(content.encode()).hexdigest()
+
+    def _entry_path(self, key: str, occurrence: int) -> str:
+        return os.path.join(self.path, key[:2], f"{key}-{occurrence}.json")
+
+    def get(self, model: str, stage: Optional[str], system_msg: Optional[str], prompt: str) -> tuple[Optional[str], str]:
+        """The cached response to the query, None on a miss, and the entry to `put` its response to."""
+        key = self.key(model, stage, system_msg, prompt)
+        entry = self._entry_path(key, self._asked[key])
+        self._asked[key] += 1
+        if not self.enabled:
+            return None, entry
+        try:
+            with open(entry) as f:
+                response = json.load(f)["response"]
+        except FileNotFoundError:
+            self.misses += 1
+            return None, entry
+        except (OSError, ValueError, KeyError) as e:
+            logger.warning("Ignoring unreadable LLM cache entry %s: %s", entry, e)
+            self.misses += 1
+            return None, entry
+        self.hits += 1
+        logger.debug("LLM cache hit: %s", entry)
+        return response, entry
+
+    def put(self, entry: str, model: str, stage: Optional[str], response: str) -> None:
+        if not self.enabled:
+            return
+        os.makedirs(os.path.dirname(entry), exist_ok=True)
+        # written aside and renamed so that concurrent runs never read a partial entry
+        tmp_path = f"{entry}.{os.getpid()}.tmp"
+        with open(tmp_path, "w") as f:
+            json.dump({"model": model, "stage": stage, "response": response}, f)
+        os.replace(tmp_path, entry)
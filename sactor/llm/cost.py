@@ -0,0 +1,155 @@
+import json
+import os
+from collections import defaultdict
+from typing import Optional
+
+import litellm
+
+from sactor import logging as sactor_logging
+
+logger = sactor_logging.get_logger(__name__)
+
+COST_REPORT_FILE = "cost_report.json"
+BUDGET_ACTIONS = ("abort", "downgrade")
+
+
+class BudgetExceededError(RuntimeError):
+    pass
+
+
+def _no_spend() -> dict:
+    return {"queries": 0, "input_tokens": 0, "output_tokens": 0, "cost": 0.0}
+
+
+class CostTracker:
+    """
+    Records the tokens and the cost of every LLM query by model, stage,
+    translated item and translation unit, and enforces the `[cost]` budget:
+    once it is spent, queries either raise `BudgetExceededError` or go to
+    `cost.downgrade_model`.
+    """
+
+    def __init__(self, config: dict):
+        cost_config = config.get('cost', {})
+        self.max_cost = float(cost_config.get('max_cost', 0.0))
+        self.max_tokens = int(cost_config.get('max_tokens', 0))
+        self.on_budget_exceeded = cost_config.get('on_budget_exceeded', 'abort')
+        if self.on_budget_exceeded not in BUDGET_ACTIONS:
+            raise ValueError(
+                f"Invalid cost.on_budget_exceeded: {self.on_budget_exceeded}, expected one of {BUDGET_ACTIONS}")
+        self.downgrade_model = cost_config.get('downgrade_model', '')
+        if self.on_budget_exceeded == 'downgrade' and not self.downgrade_model:
+            raise ValueError("cost.downgrade_model must be set when cost.on_budget_exceeded is \"downgrade\"")
+        # USD per million tokens, by model name
+        self.prices: dict[str, dict] = cost_config.get('prices', {})
+        # router model names -> the provider models litellm prices
+        self._provider_models = {
+            entry.get('model_name'): entry.get('litellm_params', {}).get('model')
+            for entry in config.get('litellm', {}).get('model_list', [])
+        }
+        # what the queries are made for, set by the pipeline
+        self.unit: Optional[str] = None
+        self.stage: Optional[str] = None
+        self.item: Optional[str] = None
+        self.records: list[dict] = []
+        self.downgraded_to: Optional[str] = None
+        self.unpriced_models: set[str] = set()
+
+    @property
+    def total_tokens(self) -> int:
+        return sum(r["input_tokens"] + r["output_tokens"] for r in self.records)
+
+    @property
+    def total_cost(self) -> float:
+        return sum(r["cost"] for r in self.records)
+
+    def exceeded(self) -> bool:
+        return (self.max_cost > 0 and self.total_cost >= self.max_cost) or \
+            (self.max_tokens > 0 and self.total_tokens >= self.max_tokens)
+
+    def model_for(self, model: str) -> str:
+        """The model to query instead of `model`, which is `model` itself until the budget is spent."""
+        if not self.exceeded():
+            return model
+        if self.on_budget_exceeded == 'downgrade':
+            if self.downgraded_to is None:
+                logger.warning("LLM budget exceeded (%s), continuing with %s", self._spent(), self.downgrade_model)
+                self.downgraded_to = self.downgrade_model
+            return self.downgrade_model
+        raise BudgetExceededError(f"LLM budget exceeded: {self._spent()}")
+
+    def _spent(self) -> str:
+        spent = [f"${self.total_cost:.4f}" + (f" of ${self.max_cost:.2f}" if self.max_cost > 0 else "")]
+        spent.append(f"{self.total_tokens} tokens" + (f" of {self.max_tokens}" if self.max_tokens > 0 else ""))
+        return ", ".join(spent)
+
+    def _price_per_token(self, model: str) -> Optional[tuple[float, float]]:
+        if model in self.prices:
+            price = self.prices[model]
+            return float(price.get('input', 0.0)) / 1e6, float(price.get('output', 0.0)) / 1e6
+        model_cost = getattr(litellm, 'model_cost', None)
+        if isinstance(model_cost, dict):
+            for name in (self._provider_models.get(model), model):
+                if not name:
+                    continue
+                # litellm lists most models without their provider prefix
+                for key in (name, name.split('/', 1)[-1]):
+                    entry = model_cost.get(key)
+                    if entry:
+                        return (float(entry.get('input_cost_per_token') or 0.0),
+                                float(entry.get('output_cost_per_token') or 0.0))
+        return None
+
+    def record(self, model: str, input_tokens: int, output_tokens: int) -> None:
+        price = self._price_per_token(model)
+        if price is None:
+            if model not in self.unpriced_models:
+                logger.warning("No price known for model %s, set it in cost.prices; its queries are counted as free",
+                               model)
+                self.unpriced_models.add(model)
+            price = (0.0, 0.0)
+        self.records.append({
+            "unit": self.unit,
+            "stage": self.stage,
+            "item": self.item,
+            "model": model,
+            "input_tokens": input_tokens,
+            "output_tokens": output_tokens,
+            "cost": input_tokens * price[0] + output_tokens * price[1],
+        })
+
+    def report(self, unit: Optional[str] = None) -> dict:
+        """The spend in total and by model, stage and item, of the queries for `unit` only if given."""
+        records = [r for r in self.records if unit is None or r["unit"] == unit]
+        groups = {"by_model": "model", "by_stage": "stage", "by_item": "item"}
+        if unit is None and any(r["unit"] for r in records):
+            groups["by_unit"] = "unit"
+        report = {"currency": "USD", "total": _no_spend()}
+        totals = {group: defaultdict(_no_spend) for group in groups}
+        for record in records:
+            targets = [report["total"]]
+            # queries made outside of a stage or item, e.g. by the test generator
+            targets += [totals[group][record[key] or "other"] for group, key in groups.items()]
+            for target in targets:
+                target["queries"] += 1
+                target["input_tokens"] += record["input_tokens"]
+                target["output_tokens"] += record["output_tokens"]
+                target["cost"] += record["cost"]
+        for group in groups:
+            report[group] = dict(totals[group])
+        report["budget"] = {
+            "max_cost": self.max_cost,
+            "max_tokens": self.max_tokens,
+            "on_budget_exceeded": self.on_budget_exceeded,
+            "exceeded": self.exceeded(),
+            "downgraded_to": self.downgraded_to,
+        }
+        report["unpriced_models"] = sorted(self.unpriced_models)
+        return report
+
+    def write_report(self, path: str, unit: Optional[str] = None) -> dict:
+        report = self.report(unit)
+        os.makedirs(os.path.dirname(path) or ".", exist_ok=True)
+        with open(path, "w") as f:
+            json.dump(report, f, indent=4)
+        return report
@@ -9,6 +9,9 @@ from litellm import Router
 from sactor import logging as sactor_logging
 from sactor import utils
 
+from .cache import ResponseCache
+from .cost import CostTracker
+
 logger = sactor_logging.get_logger(__name__)
 
 class LLM:
@@ -29,6 +32,11 @@ class LLM:
         self.costed_input_tokens = []
         self.costed_output_tokens = []
         self.costed_time = []
+        self.cached_queries = 0
+        self.cost = CostTracker(config)
+        self.cache = ResponseCache(config)
+        # replay bundle the interactions are recorded to, or answered from
+        self.replay = None
 
         # Initialize litellm router with config
         self.default_model = config['general']['model']
@@ -51,19 +59,21 @@ class LLM:
             **litellm_config.get('router_settings', {})
         )
 
-    def _query_impl(self, prompt, model=None) -> str:
-        if model is None:
-            model = self.default_model
-
+    def _messages(self, prompt) -> list[dict]:
         messages = []
         if self.system_msg is not None:
             messages.append({"role": "system", "content": self.system_msg})
         messages.append({"role": "user", "content": prompt})
+        return messages
+
+    def _query_impl(self, prompt, model=None) -> str:
+        if model is None:
+            model = self.default_model
 
         try:
             response = self.router.completion(
                 model=model,
-                messages=messages
+                messages=self._messages(prompt)
             )
             content = response.choices[0].message.content
 
@@ -84,22 +94,33 @@ class LLM:
                 self.max_input_tokens,
             )
             prompt = self.enc.decode(input_tokens[: self.max_input_tokens - 2]) + " ..."
+        # raises BudgetExceededError, or picks the cheaper model, once the budget is spent
+        model = self.cost.model_for(model if model is not None else self.default_model)
         sactor_logging.log_llm_prompt(prompt)
         old_system_msg = None
         if override_system_message is not None:
             old_system_msg = self.system_msg
             self.system_msg = override_system_message
 
-        start_time = time.time()
-        response = self._query_impl(prompt, model)
-        end_time = time.time()
-        last_costed_time = end_time - start_time
-        self.costed_time.append(last_costed_time)
-
-        output_tokens = self.enc.encode(response)
-
-        self.costed_input_tokens.append(len(input_tokens))
-        self.costed_output_tokens.append(len(output_tokens))
+        # identical queries of earlier runs are answered from the cache, at no cost
+        response, cache_entry = self.cache.get(model, self.cost.stage, self.system_msg, prompt)
+        if response is not None:
+            self.cached_queries += 1
+        else:
+            start_time = time.time()
+            response = self._query_impl(prompt, model)
+            end_time = time.time()
+            last_costed_time = end_time - start_time
+            self.costed_time.append(last_costed_time)
+
+            output_tokens = self.enc.encode(response)
+
+            self.costed_input_tokens.append(len(input_tokens))
+            self.costed_output_tokens.append(len(output_tokens))
+            self.cost.record(model, len(input_tokens), len(output_tokens))
+            self.cache.put(cache_entry, model, self.cost.stage, response)
+        if self.replay is not None and self.replay.recording:
+            self.replay.append(self.cost, model, self.system_msg, prompt, response)
 
         sactor_logging.log_llm_response(response)
 
@@ -113,6 +134,7 @@ class LLM:
         self.costed_input_tokens = []
         self.costed_output_tokens = []
         self.costed_time = []
+        self.cached_queries = 0
 
     def statistic(self, path: str) -> None:
         if os.path.isdir(path):
@@ -126,6 +148,7 @@ class LLM:
             "total_costed_input_tokens": total_costed_input_tokens,
             "total_costed_output_tokens": total_costed_output_tokens,
             "total_costed_time": total_costed_time,
+            "total_cached_queries": self.cached_queries,
             "costed_input_tokens": self.costed_input_tokens,
             "costed_output_tokens": self.costed_output_tokens,
             "costed_time": self.costed_time,
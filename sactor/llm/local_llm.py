@@ -0,0 +1,92 @@
+import time
+
+import openai
+
+from sactor import logging as sactor_logging
+
+from .llm import LLM
+
+logger = sactor_logging.get_logger(__name__)
+
+# connection failures, timeouts, 429 and 5xx; other 4xx are the request's fault
+_RETRYABLE_ERRORS = (
+    openai.APIConnectionError,
+    openai.RateLimitError,
+    openai.InternalServerError,
+)
+
+
+class LocalLLM(LLM):
+    """
+    LLM served by a local OpenAI-compatible endpoint (Ollama, vLLM,
+    llama.cpp server), queried directly rather than through the litellm
+    router so that no prompt leaves the machine. Prompts are truncated to
+    fit the model's context window next to the system message and the
+    reserved response tokens, and failed requests are retried with
+    exponential backoff.
+    """
+
+    def __init__(self, config, encoding=None, system_msg=None):
+        super().__init__(config, encoding=encoding, system_msg=system_msg)
+        local_config = config['local']
+        self.api_base = local_config['api_base'].rstrip('/')
+        self.default_model = local_config.get('model') or self.default_model
+        self.context_window = int(local_config.get('context_window', 8192))
+        self.max_output_tokens = int(local_config.get('max_output_tokens', 2048))
+        self.max_retries = int(local_config.get('max_retries', 3))
+        self.retry_backoff = float(local_config.get('retry_backoff_seconds', 2.0))
+
+        system_tokens = len(self.enc.encode(self.system_msg)) if self.system_msg else 0
+        available = self.context_window - self.max_output_tokens - system_tokens
+        if available <= 0:
+            raise ValueError(
+                f"local.context_window ({self.context_window}) leaves no room for a prompt next to "
+                f"local.max_output_tokens ({self.max_output_tokens}) and the system message ({system_tokens} tokens)")
+        self.max_input_tokens = min(self.max_input_tokens, available)
+
+        self.client = openai.OpenAI(
+            base_url=self.api_base,
+            # most local servers ignore the key, but the client requires one
+            api_key=local_config.get('api_key') or "local",
+            timeout=float(local_config.get('timeout_seconds', 600)),
+            max_retries=0,
+        )
+        # queries of locally served models cost nothing
+        ensemble_config = config.get('ensemble', {})
+        for model in [self.default_model, *ensemble_config.get('unidiomatic', []),
+                      *ensemble_config.get('idiomatic', [])]:
+            self.cost.prices.setdefault(model, {"input": 0.0, "output": 0.0})
+        logger.debug("Local model '%s' at %s, %d prompt tokens", self.default_model, self.api_base,
+                     self.max_input_tokens)
+
+    def _query_impl(self, prompt, model=None) -> str:
+        if model is None:
+            model = self.default_model
+
+        for attempt in range(self.max_retries + 1):
+            try:
+                response = self.client.chat.completions.create(
+                    model=model,
+                    messages=self._messages(prompt),
+                    max_tokens=self.max_output_tokens,
+                )
+                break
+            except _RETRYABLE_ERRORS as e:
+                if attempt == self.max_retries:
+                    raise Exception(
+                        f"Local model query failed for {model} at {self.api_base} after {attempt + 1} tries: {str(e)}")
+                delay = self.retry_backoff * 2 ** attempt
+                logger.warning("Local model query failed for %s (%s), retrying in %.1fs (%d/%d)",
+                               model, e, delay, attempt + 1, self.max_retries)
+                time.sleep(delay)
+            except openai.OpenAIError as e:
+                raise Exception(f"Local model query failed for {model} at {self.api_base}: {str(e)}")
+
+        choice = response.choices[0]
+        content = choice.message.content
+        if content is None:
+            raise Exception(f"Failed to generate response: {response}")
+        if choice.finish_reason == "length":
+            logger.warning("Response of %s was cut off at %d tokens, raise local.max_output_tokens",
+                           model, self.max_output_tokens)
+        return content
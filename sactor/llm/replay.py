@@ -0,0 +1,152 @@
+import hashlib
+import json
+import os
+import platform
+import subprocess
+from typing import Optional
+
+from sactor import logging as sactor_logging
+from sactor import utils
+
+from .llm import LLM
+
+logger = sactor_logging.get_logger(__name__)
+
+REPLAY_VERSION = 1
+MANIFEST_FILE = "manifest.json"
+INTERACTIONS_FILE = "interactions.jsonl"
+
+
+class ReplayDivergenceError(RuntimeError):
+    pass
+
+
+def _digest(text: Optional[str]) -> Optional[str]:
+    return None if text is None else hashlib.sha256(text.encode()).hexdigest()
+
+
+def _toolchain() -> dict:
+    """Versions of the tools the verification runs, which the replayed run should match."""
+    versions = {"python": platform.python_version()}
+    for tool in ("rustc", "cargo", "c2rust"):
+        try:
+            versions[tool] = subprocess.run(
+                [tool, "--version"], capture_output=True, text=True, check=True, timeout=30).stdout.strip()
+        except (OSError, subprocess.SubprocessError):
+            versions[tool] = None
+    return versions
+
+
+class ReplayBundle:
+    """
+    Directory holding every LLM interaction of a translation run in order,
+    with the prompt, the response, the model, the stage and the item it was
+    made for, next to a manifest of the configuration and tool versions.
+    A run recording the bundle appends each interaction as it happens; a run
+    replaying it answers its queries from the bundle in the same order and
+    stops with `ReplayDivergenceError` once a query differs from the recorded
+    one, so that it never reaches a model.
+    """
+
+    def __init__(self, path: str, replaying: bool):
+        self.path = path
+        self.replaying = replaying
+        self.interactions: list[dict] = []
+        self.position = 0
+
+    @property
+    def recording(self) -> bool:
+        return not self.replaying
+
+    @classmethod
+    def record(cls, path: str, config: dict) -> "ReplayBundle":
+        utils.try_backup_file(path)
+        os.makedirs(path, exist_ok=True)
+        bundle = cls(path, replaying=False)
+        with open(os.path.join(path, MANIFEST_FILE), "w") as f:
+            json.dump({
+                "version": REPLAY_VERSION,
+                "config": utils.sanitize_config(config),
+                "toolchain": _toolchain(),
+            }, f, indent=4)
+        open(os.path.join(path, INTERACTIONS_FILE), "w").close()
+        logger.info("Recording LLM interactions to replay bundle %s", path)
+        return bundle
+
+    @classmethod
+    def load(cls, path: str, config: dict) -> "ReplayBundle":
+        manifest_path = os.path.join(path, MANIFEST_FILE)
+        if not os.path.isfile(manifest_path):
+            raise FileNotFoundError(f"Replay bundle not found: {path}")
+        with open(manifest_path) as f:
+            manifest = json.load(f)
+        if manifest.get("version") != REPLAY_VERSION:
+            raise ValueError(f"Unsupported replay bundle version in {path}: {manifest.get('version')}")
+        if manifest.get("config") != utils.sanitize_config(config):
+            logger.warning("The configuration differs from the one recorded in %s, the replay may diverge", path)
+        toolchain = _toolchain()
+        for tool, version in manifest.get("toolchain", {}).items():
+            if toolchain.get(tool) != version:
+                logger.warning("%s is %s but the bundle was recorded with %s, results may differ",
+                               tool, toolchain.get(tool), version)
+        bundle = cls(path, replaying=True)
+        with open(os.path.join(path, INTERACTIONS_FILE)) as f:
+            bundle.interactions = [json.loads(line) for line in f if line.strip()]
+        logger.info("Replaying %d LLM interactions from %s", len(bundle.interactions), path)
+        return bundle
+
+    def append(self, cost, model: str, system_msg: Optional[str], prompt: str, response: str) -> None:
+        """Records an interaction, with the unit, stage and item of the `cost` tracker."""
+        interaction = {
+            "index": self.position,
+            "unit": cost.unit,
+            "stage": cost.stage,
+            "item": cost.item,
+            "model": model,
+            "system_message": system_msg,
+            "prompt": prompt,
+            "response": response,
+        }
+        self.position += 1
+        with open(os.path.join(self.path, INTERACTIONS_FILE), "a") as f:
+            f.write(json.dumps(interaction) + "\n")
+
+    def next_response(self, cost, model: str, system_msg: Optional[str], prompt: str) -> str:
+        """The recorded response to the next query, which must be the recorded one."""
+        if self.position >= len(self.interactions):
+            raise ReplayDivergenceError(
+                f"The run makes more LLM queries than the {len(self.interactions)} recorded in {self.path}")
+        recorded = self.interactions[self.position]
+        # not the unit, whose path depends on the working directory
+        query = {"stage": cost.stage, "item": cost.item, "model": model}
+        differences = [key for key, value in query.items() if recorded.get(key) != value]
+        if _digest(recorded.get("system_message")) != _digest(system_msg):
+            differences.append("system_message")
+        if _digest(recorded.get("prompt")) != _digest(prompt):
+            differences.append("prompt")
+        if differences:
+            raise ReplayDivergenceError(
+                f"LLM query {self.position} diverges from {self.path} in {', '.join(differences)} "
+                f"(recorded for {recorded.get('item') or recorded.get('stage') or 'other'})")
+        self.position += 1
+        return recorded["response"]
+
+    def finish(self) -> None:
+        if self.replaying and self.position < len(self.interactions):
+            logger.warning("The replayed run made %d of the %d LLM queries recorded in %s",
+                           self.position, len(self.interactions), self.path)
+
+
+class ReplayLLM(LLM):
+    """LLM answering every query from a replay bundle, without network access."""
+
+    def __init__(self, config, bundle: ReplayBundle, encoding=None, system_msg=None):
+        super().__init__(config, encoding=encoding, system_msg=system_msg)
+        self.replay = bundle
+        # the bundle alone decides the responses
+        self.cache.enabled = False
+
+    def _query_impl(self, prompt, model=None) -> str:
+        if model is None:
+            model = self.default_model
+        return self.replay.next_response(self.cost, model, self.system_msg, prompt)
@@ -2,9 +2,13 @@ import datetime as _dt
 import json
 import logging as _logging
 import os
+import re
 import sys
-from dataclasses import dataclass
-from typing import Any, Dict, Optional
+import time
+from contextlib import contextmanager
+from contextvars import ContextVar
+from dataclasses import dataclass, field
+from typing import Any, Dict, Iterator, Optional
 
 _LOGGER_NAMESPACE = "sactor"
 _LLM_LOGGER_NAME = f"{_LOGGER_NAMESPACE}.llm"
@@ -14,13 +18,23 @@ _RESPONSE_LEVEL = 16
 _logging.addLevelName(_PROMPT_LEVEL, "PROMPT")
 _logging.addLevelName(_RESPONSE_LEVEL, "RESPONSE")
 
-_DEFAULT_FORMAT = "%(asctime)s | %(levelname)-8s | %(name)s:%(lineno)d | %(message)s"
+_DEFAULT_FORMAT = "%(asctime)s | %(levelname)-8s | %(name)s:%(lineno)d%(span_label)s | %(message)s"
 _DEFAULT_DATEFMT = "%Y-%m-%d %H:%M:%S"
+# console levels stepped through by -v/-q, most verbose first
+_VERBOSITY_LEVELS = [
+    _logging.DEBUG,
+    _PROMPT_LEVEL,
+    _RESPONSE_LEVEL,
+    _logging.INFO,
+    _logging.WARNING,
+    _logging.ERROR,
+    _logging.CRITICAL,
+]
 
 def _standard_attrs() -> set[str]:
     record = _logging.makeLogRecord({})
     attrs = set(record.__dict__.keys())
-    attrs.update({"message", "asctime"})
+    attrs.update({"message", "asctime", "span_label"})
     return attrs
 
 
@@ -48,11 +62,22 @@ class LoggingState:
     file_level: int
     jsonl_enabled: bool
     prompt_trace_enabled: bool
+    item_logs_enabled: bool = False
 
 
 _state: Optional[LoggingState] = None
 
 
+@dataclass(frozen=True)
+class _Span:
+    name: str
+    fields: Dict[str, Any] = field(default_factory=dict)
+
+
+# the spans entered by the current thread or task, outermost first
+_spans: ContextVar[tuple[_Span, ...]] = ContextVar("sactor_spans", default=())
+
+
 def _parse_level(value: Optional[str], default: int) -> int:
     if value is None:
         return default
@@ -83,6 +108,22 @@ class _ColorFormatter(_logging.Formatter):
         return message
 
 
+class _SpanFilter(_logging.Filter):
+    """Adds the fields of the enclosing spans to every record."""
+
+    def filter(self, record: _logging.LogRecord) -> bool:
+        spans = _spans.get()
+        fields = _span_fields(spans)
+        for key, value in fields.items():
+            if not hasattr(record, key):
+                setattr(record, key, value)
+        if spans and not hasattr(record, "span"):
+            record.span = "/".join(s.name for s in spans)
+        label = "/".join(str(fields[key]) for key in ("stage", "item") if fields.get(key))
+        record.span_label = f" [{label}]" if label else ""
+        return True
+
+
 class _MaxLevelFilter(_logging.Filter):
     def __init__(self, max_level: int) -> None:
         super().__init__()
@@ -140,6 +181,81 @@ def _resolve_log_dir(logging_cfg: Dict[str, Any], result_dir: Optional[str], ove
     return base
 
 
+def _span_fields(spans: tuple[_Span, ...]) -> Dict[str, Any]:
+    fields: Dict[str, Any] = {}
+    for s in spans:
+        fields.update((key, value) for key, value in s.fields.items() if value is not None)
+    return fields
+
+
+def _add_handler(logger: _logging.Logger, handler: _logging.Handler) -> None:
+    handler.addFilter(_SpanFilter())
+    logger.addHandler(handler)
+
+
+def _step_level(level: int, verbosity: int) -> int:
+    """`level` moved `verbosity` steps towards DEBUG (away when negative)."""
+    if verbosity == 0:
+        return level
+    # levels in between the named ones step from the next named one
+    index = next((i for i, named in enumerate(_VERBOSITY_LEVELS) if named >= level), len(_VERBOSITY_LEVELS) - 1)
+    index = min(max(index - verbosity, 0), len(_VERBOSITY_LEVELS) - 1)
+    return _VERBOSITY_LEVELS[index]
+
+
+def _item_log_path(log_dir: str, fields: Dict[str, Any]) -> str:
+    parts = []
+    if fields.get("unit"):
+        parts.append(os.path.basename(str(fields["unit"])))
+    parts.append(str(fields.get("stage") or "translation"))
+    parts.append(str(fields["item"]))
+    parts = [re.sub(r"[^A-Za-z0-9_.-]", "_", part) for part in parts]
+    return os.path.join(log_dir, "items", *parts[:-1], f"{parts[-1]}.log")
+
+
+@contextmanager
+def span(name: str, *, item_log: bool = False, **fields: Any) -> Iterator[None]:
+    """
+    Tags every record logged within it with `fields`, e.g. the stage and the
+    item being translated, on top of the fields of the enclosing spans, and
+    logs its end with its duration and whether it raised.
+
+    With `item_log` and `logging.item_logs`, the records are also written to
+    `items/[<unit>/]<stage>/<item>.log` in the log directory, so the attempts
+    at one item can be read without the rest of the run.
+    """
+    logger = get_logger("span")
+    spans = _spans.get() + (_Span(name, fields),)
+    token = _spans.set(spans)
+    item_handler = None
+    state = _state
+    if item_log and state and state.item_logs_enabled and state.log_dir and "item" in fields:
+        path = _item_log_path(state.log_dir, _span_fields(spans))
+        os.makedirs(os.path.dirname(path), exist_ok=True)
+        item_handler = _logging.FileHandler(path, encoding="utf-8")
+        item_handler.setLevel(state.file_level)
+        item_handler.setFormatter(_logging.Formatter(_DEFAULT_FORMAT, _DEFAULT_DATEFMT))
+        _add_handler(get_logger(), item_handler)
+
+    logger.debug("Started %s", name, extra={"event": "span_start"})
+    start = time.monotonic()
+    status = "error"
+    try:
+        yield
+        status = "ok"
+    finally:
+        duration = time.monotonic() - start
+        logger.log(
+            _logging.INFO if status == "ok" else _logging.WARNING,
+            "Finished %s in %.1fs%s", name, duration, "" if status == "ok" else " with an error",
+            extra={"event": "span_end", "duration_seconds": round(duration, 3), "span_status": status},
+        )
+        if item_handler:
+            get_logger().removeHandler(item_handler)
+            item_handler.close()
+        _spans.reset(token)
+
+
 def get_logger(name: Optional[str] = None) -> _logging.Logger:
     if name is None:
         return _logging.getLogger(_LOGGER_NAMESPACE)
@@ -170,6 +286,7 @@ def configure_logging(
     disable_color: bool = False,
     enable_jsonl_override: Optional[bool] = None,
     prompt_log_override: Optional[bool] = None,
+    verbosity: int = 0,
     force_reconfigure: bool = False,
 ) -> LoggingState:
     global _state
@@ -177,6 +294,8 @@ def configure_logging(
     logging_cfg: Dict[str, Any] = config.get("logging", {}) if config else {}
 
     console_level = _parse_level(console_level_override, _parse_level(logging_cfg.get("console_level"), _logging.INFO))
+    # -v/-q of the CLI, each one level of _VERBOSITY_LEVELS
+    console_level = _step_level(console_level, verbosity)
     file_level = _parse_level(file_level_override, _parse_level(logging_cfg.get("file_level"), _logging.DEBUG))
 
     use_color = logging_cfg.get("color", True) and not disable_color
@@ -185,6 +304,7 @@ def configure_logging(
 
     jsonl_enabled = enable_jsonl_override if enable_jsonl_override is not None else logging_cfg.get("jsonl", False)
     prompt_trace_enabled = prompt_log_override if prompt_log_override is not None else logging_cfg.get("prompt_trace", False)
+    item_logs_enabled = logging_cfg.get("item_logs", False)
 
     log_dir = _resolve_log_dir(logging_cfg, result_dir, log_dir_override)
     text_log_path = None
@@ -203,12 +323,12 @@ def configure_logging(
     stdout_handler.setLevel(console_level)
     stdout_handler.addFilter(_MaxLevelFilter(_logging.ERROR - 1))
     stdout_handler.setFormatter(_ColorFormatter(_DEFAULT_FORMAT, _DEFAULT_DATEFMT, use_color and sys.stdout.isatty()))
-    logger.addHandler(stdout_handler)
+    _add_handler(logger, stdout_handler)
 
     stderr_handler = _logging.StreamHandler(stream=sys.stderr)
     stderr_handler.setLevel(max(console_level, _logging.ERROR))
     stderr_handler.setFormatter(_ColorFormatter(_DEFAULT_FORMAT, _DEFAULT_DATEFMT, use_color and sys.stderr.isatty()))
-    logger.addHandler(stderr_handler)
+    _add_handler(logger, stderr_handler)
 
     if log_dir:
         os.makedirs(log_dir, exist_ok=True)
@@ -216,14 +336,14 @@ def configure_logging(
         file_handler = _logging.FileHandler(text_log_path, encoding="utf-8")
         file_handler.setLevel(file_level)
         file_handler.setFormatter(_logging.Formatter(_DEFAULT_FORMAT, _DEFAULT_DATEFMT))
-        logger.addHandler(file_handler)
+        _add_handler(logger, file_handler)
 
         if jsonl_enabled:
             jsonl_log_path = os.path.join(log_dir, _timestamped_filename(pattern.replace(".log", ".jsonl"), timestamp_format))
             json_handler = _logging.FileHandler(jsonl_log_path, encoding="utf-8")
             json_handler.setLevel(file_level)
             json_handler.setFormatter(_JsonLinesFormatter())
-            logger.addHandler(json_handler)
+            _add_handler(logger, json_handler)
 
         if prompt_trace_enabled:
             prompt_log_path = os.path.join(log_dir, _timestamped_filename("prompts-{timestamp}.log", timestamp_format))
@@ -231,7 +351,7 @@ def configure_logging(
             prompt_handler.setLevel(_PROMPT_LEVEL)
             prompt_handler.setFormatter(_logging.Formatter("%(asctime)s | %(levelname)s | %(message)s", _DEFAULT_DATEFMT))
             prompt_logger = get_logger(_LLM_LOGGER_NAME)
-            prompt_logger.addHandler(prompt_handler)
+            _add_handler(prompt_logger, prompt_handler)
             prompt_logger.setLevel(min(_PROMPT_LEVEL, logger.level))
             prompt_logger.propagate = True
 
@@ -248,6 +368,7 @@ def configure_logging(
         file_level=file_level,
         jsonl_enabled=jsonl_enabled,
         prompt_trace_enabled=prompt_trace_enabled,
+        item_logs_enabled=item_logs_enabled,
     )
 
     return _state
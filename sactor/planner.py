@@ -0,0 +1,321 @@
+import math
+from dataclasses import asdict, dataclass, field
+from typing import Callable, Optional
+
+import tiktoken
+from clang.cindex import CursorKind, TypeKind
+
+from sactor import logging as sactor_logging
+from sactor import utils
+from sactor.c_parser import CParser, FunctionInfo, StructInfo
+from sactor.c_parser.c_parser_utils import preprocess_source_code
+from sactor.c_parser.project_index import order_translation_units_by_dependencies
+from sactor.divider import Divider
+from sactor.llm import CostTracker
+
+logger = sactor_logging.get_logger(__name__)
+
+STAGES = ("unidiomatic", "idiomatic")
+
+# Rough calibration of the prompts of the translators, for estimates only:
+# the instructions around the C code of an item in each stage,
+PROMPT_OVERHEAD_TOKENS = {"unidiomatic": 500, "idiomatic": 900}
+# a signature of a called function or a declaration of a used global,
+DEPENDENCY_TOKENS = 40
+# the compile errors or test failures a retry prompt gives back,
+FEEDBACK_TOKENS = 300
+# and the length of a translation relative to the C code
+RUST_TOKENS_PER_C_TOKEN = 1.4
+
+# difficulty levels by the upper bound of their score, with the attempts a
+# translation of that level is expected to take per stage
+DIFFICULTY_LEVELS = (("easy", 5.0, 1), ("medium", 15.0, 2), ("hard", math.inf, 3))
+
+
+@dataclass
+class ItemMetrics:
+    lines: int
+    c_tokens: int
+    # pointer-typed parameters, variables and fields, and pointer operations (`*`, `&`, `->`)
+    pointers: int = 0
+    globals: int = 0
+    gotos: int = 0
+    # constructs the translators handle with extra guidance, e.g. "variadic", "setjmp"
+    notes: list[str] = field(default_factory=list)
+
+
+def difficulty(metrics: ItemMetrics) -> float:
+    """A score of how hard `metrics`' item is to translate, growing with its size, pointer use, globals and gotos."""
+    return round(metrics.lines / 10 + metrics.pointers / 2 + 2 * metrics.globals
+                 + 4 * metrics.gotos + 5 * len(metrics.notes), 1)
+
+
+def difficulty_level(score: float) -> tuple[str, int]:
+    """The level of a `difficulty` score and the attempts expected per stage."""
+    level, _, attempts = next(level for level in DIFFICULTY_LEVELS if score < level[1])
+    return level, attempts
+
+
+def _is_pointer(cursor) -> bool:
+    return cursor.type.get_canonical().kind == TypeKind.POINTER
+
+
+def _lines(node) -> int:
+    return node.extent.end.line - node.extent.start.line + 1
+
+
+def function_metrics(function: FunctionInfo, code: str, count_tokens: Callable[[str], int]) -> ItemMetrics:
+    pointers = gotos = 0
+    for cursor in function.node.walk_preorder():
+        if cursor.kind in (CursorKind.PARM_DECL, CursorKind.VAR_DECL) and _is_pointer(cursor):
+            pointers += 1
+        elif cursor.kind == CursorKind.UNARY_OPERATOR:
+            tokens = [t.spelling for t in cursor.get_tokens()]
+            if tokens and tokens[0] in ("*", "&"):
+                pointers += 1
+        elif cursor.kind == CursorKind.GOTO_STMT:
+            gotos += 1
+    pointers += sum(1 for t in function.node.get_tokens() if t.spelling == "->")
+
+    notes = []
+    if function.is_variadic:
+        notes.append("variadic")
+    if function.uses_setjmp or function.uses_longjmp:
+        notes.append("setjmp")
+    if function.pthread_calls:
+        notes.append("threads")
+    if function.signal_registrations or function.handled_signals:
+        notes.append("signals")
+    if function.name in function.called_function_names:
+        notes.append("recursive")
+    return ItemMetrics(
+        lines=_lines(function.node),
+        c_tokens=count_tokens(code),
+        pointers=pointers,
+        globals=len(function.global_vars_dependencies),
+        gotos=gotos,
+        notes=notes,
+    )
+
+
+def struct_metrics(struct: StructInfo, code: str, count_tokens: Callable[[str], int]) -> ItemMetrics:
+    fields = [c for c in struct.node.get_children() if c.kind == CursorKind.FIELD_DECL]
+    return ItemMetrics(
+        lines=_lines(struct.node),
+        c_tokens=count_tokens(code),
+        pointers=sum(1 for f in fields if _is_pointer(f)),
+    )
+
+
+def estimate_queries(stage: str, metrics: ItemMetrics, context_tokens: int, attempts: int,
+                     system_tokens: int = 0) -> list[tuple[int, int]]:
+    """
+    The (prompt, response) tokens of the queries translating an item in
+    `stage`, with `context_tokens` of dependencies in its prompt: a first
+    attempt, then retries that also give back the failed translation and
+    what went wrong.
+    """
+    response = round(metrics.c_tokens * RUST_TOKENS_PER_C_TOKEN)
+    prompt = system_tokens + PROMPT_OVERHEAD_TOKENS[stage] + metrics.c_tokens + context_tokens
+    if stage == "idiomatic":
+        # the prompt holds the unidiomatic translation of the item
+        prompt += response
+    return [(prompt, response)] + [(prompt + response + FEEDBACK_TOKENS, response)] * (attempts - 1)
+
+
+@dataclass
+class PlannedItem:
+    kind: str  # "struct" | "function"
+    name: str
+    metrics: ItemMetrics
+    # functions of the same file it calls, which are translated before it
+    calls: list[str]
+    difficulty: float
+    level: str
+    # expected attempts per stage
+    attempts: int
+    input_tokens: int = 0
+    output_tokens: int = 0
+    cost: float = 0.0
+
+
+@dataclass
+class UnitPlan:
+    input_file: str
+    # in translation order, structs first
+    items: list[PlannedItem]
+    # mutually dependent items, which the pipeline cannot translate yet
+    cycles: list[list[str]] = field(default_factory=list)
+
+
+@dataclass
+class TranslationPlan:
+    units: list[UnitPlan]
+    stages: list[str]
+    # model queried first in each stage
+    models: dict[str, str]
+    # spend of the expected queries, in the format of `CostTracker.report`
+    cost: dict
+
+    def to_dict(self) -> dict:
+        return asdict(self)
+
+
+class Planner:
+    """
+    Plans a translation without querying a model: the items of each file in
+    the order the pipeline translates them, how hard each is expected to be,
+    and the tokens and cost of the queries they are expected to take, priced
+    like `CostTracker` prices real queries. The estimates are rough; they
+    assume the first model of each stage, and more attempts for harder items.
+    """
+
+    def __init__(self, config: dict, *, unidiomatic_only: bool = False, idiomatic_only: bool = False,
+                 count_tokens: Optional[Callable[[str], int]] = None):
+        self.config = config
+        general = config['general']
+        if count_tokens is None:
+            encoding = tiktoken.get_encoding(general['encoding'])
+            count_tokens = lambda text: len(encoding.encode(text, disallowed_special=()))
+        self.count_tokens = count_tokens
+        self.stages = [stage for stage, skipped in zip(STAGES, (idiomatic_only, unidiomatic_only)) if not skipped]
+        self.max_attempts = int(general.get('max_translation_attempts', 6))
+        self.max_input_tokens = int(general.get('max_llm_input_tokens', 20480))
+        self.system_tokens = count_tokens(general.get('system_message', ''))
+        self.cost = CostTracker(config)
+
+        local_config = config.get('local', {})
+        default_model = general['model']
+        if local_config.get('api_base'):
+            default_model = local_config.get('model') or default_model
+        ensemble_config = config.get('ensemble', {})
+        self.models = {stage: (ensemble_config.get(stage) or [default_model])[0] for stage in self.stages}
+        if local_config.get('api_base'):
+            # queries of locally served models cost nothing, as in `LocalLLM`
+            for model in self.models.values():
+                self.cost.prices.setdefault(model, {"input": 0.0, "output": 0.0})
+
+    def _plan_item(self, unit: str, item: PlannedItem, context_tokens: int) -> None:
+        self.cost.unit = unit
+        self.cost.item = item.name
+        for stage in self.stages:
+            self.cost.stage = stage
+            spent = self.cost.total_cost
+            queries = estimate_queries(stage, item.metrics, context_tokens, item.attempts, self.system_tokens)
+            for prompt, response in queries:
+                prompt = min(prompt, self.max_input_tokens)
+                self.cost.record(self.models[stage], prompt, response)
+                item.input_tokens += prompt
+                item.output_tokens += response
+            item.cost += self.cost.total_cost - spent
+
+    def plan_unit(self, input_file: str, c_parser: CParser) -> UnitPlan:
+        """The plan of the file `c_parser` parsed."""
+        divider = Divider(c_parser)
+        cycles = [[item.name for item in group]
+                  for group in divider.get_struct_order() + divider.get_function_order() if len(group) > 1]
+        struct_tokens: dict[str, int] = {}
+        items = []
+
+        for group in divider.get_struct_order():
+            for struct in group:
+                metrics = struct_metrics(
+                    struct, c_parser.extract_struct_union_definition_code(struct.name), self.count_tokens)
+                struct_tokens[struct.name] = metrics.c_tokens
+                score = difficulty(metrics)
+                level, attempts = difficulty_level(score)
+                item = PlannedItem("struct", struct.name, metrics, [], score, level, min(attempts, self.max_attempts))
+                self._plan_item(input_file, item, sum(struct_tokens.get(d.name, 0) for d in struct.dependencies))
+                items.append(item)
+
+        local_functions = {function.name for function in c_parser.get_functions()}
+        for group in divider.get_function_order():
+            for function in group:
+                metrics = function_metrics(function, c_parser.extract_function_code(function.name), self.count_tokens)
+                calls = sorted({name for name in function.called_function_names
+                                if name in local_functions and name != function.name})
+                score = difficulty(metrics)
+                level, attempts = difficulty_level(score)
+                item = PlannedItem("function", function.name, metrics, calls, score, level,
+                                   min(attempts, self.max_attempts))
+                context_tokens = sum(struct_tokens.get(s.name, 0) for s in function.struct_dependencies) \
+                    + DEPENDENCY_TOKENS * (len(calls) + metrics.globals)
+                self._plan_item(input_file, item, context_tokens)
+                items.append(item)
+        return UnitPlan(input_file, items, cycles)
+
+    def plan_file(self, input_file: str, compile_commands_file: str = "") -> UnitPlan:
+        """Parses `input_file` as the pipeline does, and plans its translation."""
+        commands = utils.load_compile_commands_from_file(compile_commands_file, input_file) \
+            if compile_commands_file else []
+        include_flags = [flag for flag in utils.get_compile_flags_from_commands(commands) if flag.startswith("-I")]
+        c_parser = CParser(preprocess_source_code(input_file, commands), extra_args=include_flags,
+                           raw_filename=input_file)
+        return self.plan_unit(input_file, c_parser)
+
+    def plan(self, input_file: Optional[str] = None, compile_commands_file: str = "") -> TranslationPlan:
+        """
+        Plans the translation of `input_file`, or without it of every
+        translation unit of `compile_commands_file`, in dependency order.
+        """
+        if input_file:
+            files = [input_file]
+        else:
+            files = order_translation_units_by_dependencies(
+                utils.list_c_files_from_compile_commands(compile_commands_file), compile_commands_file)
+            if not files:
+                raise ValueError("No C translation units found in compile_commands.json")
+        units = []
+        for path in files:
+            logger.info("Planning %s", path)
+            units.append(self.plan_file(path, compile_commands_file))
+        return TranslationPlan(units, self.stages, self.models, self.cost.report())
+
+
+def format_plan(plan: TranslationPlan) -> str:
+    """`plan` as text: a table of the items of each file in translation order, then the expected totals."""
+    sections = []
+    for unit in plan.units:
+        rows = []
+        for i, item in enumerate(unit.items, 1):
+            metrics = item.metrics
+            rows.append([
+                i,
+                f"struct {item.name}" if item.kind == "struct" else item.name,
+                metrics.lines,
+                metrics.pointers,
+                metrics.globals,
+                metrics.gotos,
+                f"{item.difficulty:.1f}",
+                item.level,
+                item.attempts,
+                item.input_tokens + item.output_tokens,
+                f"${item.cost:.4f}",
+                ", ".join(item.calls + [f"[{note}]" for note in metrics.notes]),
+            ])
+        headers = ["#", "Item", "Lines", "Pointers", "Globals", "Gotos", "Difficulty", "Level", "Attempts",
+                   "Tokens", "Cost", "Calls"]
+        section = f"{unit.input_file}\n{utils.format_table(headers, rows)}"
+        for cycle in unit.cycles:
+            section += f"\nCircular dependency between {', '.join(cycle)}, which the translation does not support yet"
+        sections.append(section)
+
+    total = plan.cost["total"]
+    levels = {level: 0 for level, _, _ in DIFFICULTY_LEVELS}
+    for unit in plan.units:
+        for item in unit.items:
+            levels[item.level] += 1
+    summary = [
+        f"Stages: {', '.join(f'{stage} ({plan.models[stage]})' for stage in plan.stages)}",
+        f"Items: {sum(levels.values())} ({', '.join(f'{count} {level}' for level, count in levels.items())})",
+        f"Expected queries: {total['queries']}, {total['input_tokens']} prompt and "
+        f"{total['output_tokens']} response tokens, ${total['cost']:.2f}",
+    ]
+    for stage, spend in plan.cost.get("by_stage", {}).items():
+        summary.append(f"  {stage}: {spend['queries']} queries, ${spend['cost']:.2f}")
+    if plan.cost["unpriced_models"]:
+        summary.append(f"No price known for {', '.join(plan.cost['unpriced_models'])}, counted as free; "
+                       "set it in cost.prices")
+    if plan.cost["budget"]["exceeded"]:
+        summary.append("The expected spend exceeds the [cost] budget")
+    return "\n\n".join(sections + ["\n".join(summary)])
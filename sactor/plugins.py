@@ -0,0 +1,132 @@
+import importlib
+import importlib.util
+import inspect
+import os
+from dataclasses import dataclass
+from importlib import metadata
+from typing import Any, Optional
+
+from sactor import logging as sactor_logging
+
+logger = sactor_logging.get_logger(__name__)
+
+# installed packages register their plugins under this entry point group
+ENTRY_POINT_GROUP = "sactor.plugins"
+# `pre_verify` runs on every candidate translation of a function before it is
+# verified, `post_<stage>` on the one that passed verification in the stage
+HOOKS = ("pre_verify", "post_unidiomatic", "post_idiomatic")
+# hooks running after verification, which may only veto the code, as a changed
+# translation would be saved without being verified again
+VETO_ONLY_HOOKS = ("post_unidiomatic", "post_idiomatic")
+
+
+class PluginVeto(Exception):
+    """
+    Raised by a plugin hook to reject a translation. It counts as a failed
+    attempt, and the message is given to the LLM for the next one.
+    """
+
+
+@dataclass(frozen=True)
+class HookContext:
+    stage: Optional[str]
+    item_type: str
+    item: str
+    config: dict
+
+
+@dataclass
+class Plugin:
+    name: str
+    hooks: Any
+
+
+def _load_spec(spec: str) -> Any:
+    """The object `spec` names: "module", "module:attribute", or the path of a Python file with an optional ":attribute"."""
+    target, _, attribute = spec.partition(":")
+    if target.endswith(".py"):
+        if not os.path.isfile(target):
+            raise ValueError(f"Plugin file not found: {target}")
+        module_name = f"sactor_plugin_{os.path.splitext(os.path.basename(target))[0]}"
+        module_spec = importlib.util.spec_from_file_location(module_name, target)
+        assert module_spec is not None and module_spec.loader is not None
+        module = importlib.util.module_from_spec(module_spec)
+        module_spec.loader.exec_module(module)
+    else:
+        module = importlib.import_module(target)
+    if not attribute:
+        return module
+    try:
+        return getattr(module, attribute)
+    except AttributeError as e:
+        raise ValueError(f"Plugin {spec}: {target} has no attribute {attribute}") from e
+
+
+class Plugins:
+    """
+    The plugins of a run, which transform or veto translations at the `HOOKS`.
+    A plugin is a module, or an object, with a method per hook it implements:
+    `hook(code: str, context: HookContext) -> str | None` returns the new code,
+    or None to leave it as is, and raises `PluginVeto` to reject it. Only
+    `pre_verify` may change the code; the `VETO_ONLY_HOOKS` must return it
+    unchanged. A plugin class is instantiated with the configuration.
+    """
+
+    def __init__(self, plugins: list[Plugin]):
+        self.plugins = plugins
+
+    @classmethod
+    def load(cls, config: dict) -> "Plugins":
+        """Loads the plugins of the `[plugins]` config and, unless disabled there, of the installed packages."""
+        plugins_config = config.get('plugins', {})
+        disabled = set(plugins_config.get('disabled', []))
+        objects = []
+        if plugins_config.get('entry_points', True):
+            for entry_point in metadata.entry_points(group=ENTRY_POINT_GROUP):
+                if entry_point.name not in disabled:
+                    objects.append((entry_point.name, entry_point.load))
+        for spec in plugins_config.get('modules', []):
+            if spec not in disabled:
+                objects.append((spec, lambda spec=spec: _load_spec(spec)))
+
+        plugins = []
+        for name, load in objects:
+            try:
+                hooks = load()
+                if inspect.isclass(hooks):
+                    hooks = hooks(config)
+            except Exception as e:
+                raise ValueError(f"Failed to load plugin {name}: {e}") from e
+            implemented = [hook for hook in HOOKS if callable(getattr(hooks, hook, None))]
+            if not implemented:
+                logger.warning("Plugin %s implements none of the hooks %s", name, ", ".join(HOOKS))
+                continue
+            logger.info("Loaded plugin %s (%s)", name, ", ".join(implemented))
+            plugins.append(Plugin(name, hooks))
+        return cls(plugins)
+
+    def run(self, hook: str, code: str, context: HookContext) -> str:
+        """
+        `code` as transformed by the `hook` of every plugin in turn. Raises
+        `PluginVeto`, naming the plugin, when one rejects it.
+        """
+        for plugin in self.plugins:
+            function = getattr(plugin.hooks, hook, None)
+            if not callable(function):
+                continue
+            try:
+                result = function(code, context)
+            except PluginVeto as e:
+                raise PluginVeto(f"{plugin.name}: {e}") from e
+            except Exception as e:
+                raise RuntimeError(f"Plugin {plugin.name} failed in {hook}: {e}") from e
+            if result is None:
+                continue
+            if not isinstance(result, str):
+                raise RuntimeError(f"Plugin {plugin.name} returned {type(result).__name__} from {hook}, not code")
+            if hook in VETO_ONLY_HOOKS and result != code:
+                raise RuntimeError(
+                    f"Plugin {plugin.name} changed the code in {hook}, which runs after verification; "
+                    "transform the code in pre_verify instead")
+            code = result
+        return code
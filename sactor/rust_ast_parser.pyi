@@ -10,24 +10,90 @@ def add_attr_to_struct_union(code:builtins.str, struct_union_name:builtins.str,
 
 def add_derive_to_struct_union(code:builtins.str, struct_union_name:builtins.str, derive:builtins.str) -> builtins.str: ...
 
+def add_serde_capture(code:builtins.str, struct_name:builtins.str) -> builtins.str: ...
+
+def analyze_parameter_usage(code:builtins.str, function_name:builtins.str) -> typing.Any: ...
+
+def analyze_pointer_ownership(code:builtins.str, function_name:builtins.str) -> typing.Any: ...
+
+def analyze_struct_field_usage(code:builtins.str, struct_name:builtins.str) -> typing.Any: ...
+
 def append_stmt_to_function(source_code:builtins.str, function_name:builtins.str, stmt_code:builtins.str) -> builtins.str: ...
 
+def cleanup_c2rust_artifacts(code:builtins.str) -> builtins.str: ...
+
+def code_metrics(code:builtins.str) -> typing.Any: ...
+
+def combine_as_modules(files:typing.Mapping[builtins.str, builtins.str]) -> builtins.dict[builtins.str, builtins.str]: ...
+
+def convert_error_code_to_result(code:builtins.str, function_name:builtins.str) -> builtins.str: ...
+
+def convert_to_method(code:builtins.str, function_name:builtins.str, struct_name:builtins.str) -> builtins.str: ...
+
+def convert_to_no_std(code:builtins.str, logger:typing.Optional[builtins.str]=None) -> builtins.str: ...
+
 def count_unsafe_tokens(code:builtins.str) -> tuple[builtins.int, builtins.int]: ...
 
 def dedup_items(source_code:builtins.str) -> builtins.str: ...
 
+def defines_c_variadic(code:builtins.str) -> builtins.bool: ...
+
+def detect_symbol_collisions(files:typing.Mapping[builtins.str, builtins.str], rename:builtins.bool=False) -> typing.Any: ...
+
 def expand_use_aliases(code:builtins.str) -> builtins.str: ...
 
 def expose_function_to_c(source_code:builtins.str, function_name:builtins.str) -> builtins.str: ...
 
+def extract_extern_declarations(code:builtins.str) -> typing.Any: ...
+
+def find_error_code_functions(code:builtins.str) -> typing.Any: ...
+
+def find_leak_patterns(code:builtins.str) -> typing.Any: ...
+
+def find_nightly_constructs(code:builtins.str) -> typing.Any: ...
+
+def find_pure_functions(code:builtins.str) -> typing.Any: ...
+
+def find_ub_suspects(code:builtins.str) -> typing.Any: ...
+
+def find_unnecessary_unsafe(code:builtins.str) -> typing.Any: ...
+
+def format_with_rustfmt(code:builtins.str, config_path:typing.Optional[builtins.str]=None) -> builtins.str: ...
+
+def generate_arbitrary_impl(code:builtins.str, struct_name:builtins.str) -> builtins.str: ...
+
+def generate_c_header(code:builtins.str, guard:builtins.str) -> builtins.str: ...
+
+def generate_default_impl(code:builtins.str, struct_name:builtins.str) -> builtins.str: ...
+
+def generate_differential_driver(code:builtins.str, function_name:builtins.str, c_prefix:builtins.str) -> typing.Any: ...
+
+def generate_ffi_shim(c_signature:builtins.str, idiomatic_signature:builtins.str, param_mapping_json:builtins.str) -> builtins.str: ...
+
+def generate_fuzz_target(code:builtins.str, function_name:builtins.str, c_prefix:builtins.str) -> typing.Any: ...
+
+def generate_kani_harness(signature:builtins.str, preconditions_json:builtins.str, c_prefix:builtins.str='sactor_c_', struct_code:builtins.str='') -> builtins.str: ...
+
+def generate_layout_asserts(code:builtins.str, struct_names:typing.Sequence[builtins.str], layouts_json:builtins.str) -> builtins.str: ...
+
+def generate_struct_converters(c_struct_def:builtins.str, idiomatic_struct_def:builtins.str, field_mapping_json:builtins.str) -> builtins.str: ...
+
+def generate_test_module(code:builtins.str, function_name:builtins.str, cases_json:builtins.str) -> builtins.str: ...
+
+def generate_variadic_wrapper(code:builtins.str, function_name:builtins.str) -> builtins.str: ...
+
 def get_code_other_than_uses(code:builtins.str) -> builtins.str: ...
 
 def get_enum_definition(source_code:builtins.str, enum_name:builtins.str) -> builtins.str: ...
 
 def get_func_signatures(source_code:builtins.str) -> builtins.dict[builtins.str, builtins.str]: ...
 
+def get_function_dependencies(source_code:builtins.str, function_name:builtins.str) -> builtins.dict[builtins.str, builtins.list[builtins.str]]: ...
+
 def get_function_definition(source_code:builtins.str, function_name:builtins.str) -> builtins.str: ...
 
+def get_macro_definition(source_code:builtins.str, macro_name:builtins.str) -> builtins.str: ...
+
 def get_standalone_uses_code_paths(code:builtins.str) -> builtins.list[builtins.list[builtins.str]]: ...
 
 def get_static_item_definition(source_code:builtins.str, item_name:builtins.str) -> builtins.str: ...
@@ -36,18 +102,46 @@ def get_struct_definition(source_code:builtins.str, struct_name:builtins.str) ->
 
 def get_struct_field_types(source_code:builtins.str, struct_name:typing.Optional[builtins.str]=None) -> builtins.dict[builtins.str, builtins.str]: ...
 
+def get_type_aliases(code:builtins.str) -> builtins.dict[builtins.str, builtins.str]: ...
+
 def get_union_definition(source_code:builtins.str, union_name:builtins.str) -> builtins.str: ...
 
 def get_uses_code(code:builtins.str) -> builtins.list[builtins.str]: ...
 
 def get_value_type_name(code:builtins.str, value:builtins.str) -> builtins.str: ...
 
+def global_usage(code:builtins.str) -> builtins.dict[builtins.str, builtins.dict[builtins.str, builtins.list[builtins.str]]]: ...
+
+def has_derive(code:builtins.str, type_name:builtins.str, trait_name:builtins.str) -> builtins.bool: ...
+
+def implements_trait(code:builtins.str, type_name:builtins.str, trait_name:builtins.str) -> builtins.bool: ...
+
+def infer_derives(code:builtins.str, struct_name:builtins.str) -> builtins.str: ...
+
+def infer_slice_params(code:builtins.str, function_name:builtins.str) -> typing.Any: ...
+
+def list_function_string_literals(code:builtins.str, function_name:builtins.str) -> typing.Any: ...
+
+def list_string_literals(code:builtins.str) -> typing.Any: ...
+
 def list_struct_enum_union(source_code:builtins.str) -> builtins.list[tuple[builtins.str, builtins.str]]: ...
 
+def load_scalar_map(path:builtins.str) -> builtins.int: ...
+
+def move_item_to_module(code_map:typing.Mapping[builtins.str, builtins.str], item_name:builtins.str, from_module:builtins.str, to_module:builtins.str) -> builtins.dict[builtins.str, builtins.str]: ...
+
+def normalize_c_char_pointers(code:builtins.str) -> builtins.str: ...
+
+def normalize_pointer_arithmetic(code:builtins.str) -> builtins.str: ...
+
 def parse_function_signature(signature:builtins.str) -> typing.Any: ...
 
 def parse_type_traits(ty:builtins.str) -> typing.Any: ...
 
+def register_scalar_mapping(src:builtins.str, dst:builtins.str) -> None: ...
+
+def remove_unreachable_items(code:builtins.str, roots:typing.Optional[typing.Sequence[builtins.str]]=None) -> builtins.str: ...
+
 def remove_mut_from_type_specifiers(code:builtins.str, var_name:builtins.str) -> builtins.str: ...
 
 def rename_function(code:builtins.str, old_name:builtins.str, new_name:builtins.str) -> builtins.str: ...
@@ -56,8 +150,26 @@ def rename_struct_union(code:builtins.str, old_name:builtins.str, new_name:built
 
 def replace_libc_numeric_types_to_rust_primitive_types(code:builtins.str) -> builtins.str: ...
 
+def resolve_type_aliases(code:builtins.str, keep_libc_aliases:builtins.bool=True) -> builtins.str: ...
+
+def rewrite_fprintf_to_eprintln(code:builtins.str) -> builtins.str: ...
+
+def rewrite_printf_to_println(code:builtins.str) -> builtins.str: ...
+
+def rewrite_static_mut_globals(code:builtins.str) -> builtins.str: ...
+
+def set_rustfmt_config(path:typing.Optional[builtins.str]=None) -> None: ...
+
+def shrink_unsafe_blocks(code:builtins.str) -> builtins.str: ...
+
+def strip_extern_blocks(code:builtins.str, names:typing.Optional[typing.Sequence[builtins.str]]=None) -> builtins.str: ...
+
 def strip_to_struct_items(source_code:builtins.str) -> builtins.str: ...
 
+def suggest_immutable_params(code:builtins.str, function_name:builtins.str) -> typing.Any: ...
+
+def summarize_rustc_diagnostics(output:builtins.str, code:builtins.str='') -> typing.Any: ...
+
 def unidiomatic_function_cleanup(code:builtins.str) -> builtins.str: ...
 
 def unidiomatic_types_cleanup(code:builtins.str) -> builtins.str: ...
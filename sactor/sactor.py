@@ -1,21 +1,30 @@
 import json
 import os
 import shlex
+import time
 
 from sactor import logging as sactor_logging
-from sactor import thirdparty, utils
+from sactor import thirdparty, type_normalization, utils
 from sactor.c_parser import CParser
 from sactor.c_parser.c_parser_utils import preprocess_source_code
+from sactor.c_parser.make_capture import capture_make_compile_commands
 from sactor.c_parser.project_index import build_link_closure, build_nonfunc_def_maps
-from sactor.combiner import CombineResult, ProgramCombiner
+from sactor.combiner import (CombineResult, CrateEmitter, CrateUnit,
+                             ProgramCombiner)
+from sactor.combiner.library_crate import library_crate_name
+from sactor.config_check import validate_config
 from sactor.divider import Divider
-from sactor.llm import llm_factory
+from sactor.llm import CostTracker, ReplayBundle, llm_factory
+from sactor.llm.cost import COST_REPORT_FILE
 from sactor.thirdparty import C2Rust, Crown
 from sactor.translator import (IdiomaticTranslator, TranslateResult,
                                Translator, UnidiomaticTranslator)
+from sactor.translator import html_report, result_export, run_summary
+from sactor.translator.checkpoint import CHECKPOINT_FILE, Checkpoint
 from sactor.translator.batch_runner import run_translate_batch
 from sactor.translator.translator_types import TranslateBatchResult
 from sactor.verifier import Verifier
+from sactor.watcher import Watcher
 
 
 logger = sactor_logging.get_logger(__name__)
@@ -30,6 +39,8 @@ class Sactor:
         test_cmd_path: str,
         input_file: str | None = None,
         compile_commands_file: str = "",
+        make_project: str | None = None,
+        make_args: str = "",
         entry_tu_file: str | None = None,
         result_dir: str | None = None,
         build_dir: str | None = None,
@@ -43,10 +54,20 @@ class Sactor:
         link_args: str = "",
         llm_stat: str | None = None,
         log_dir_override: str | None = None,
+        log_jsonl: bool | None = None,
+        log_verbosity: int = 0,
         configure_logging: bool = True,
+        c2rust_translation_file: str | None = None,
+        resume: bool = False,
+        record_replay: str | None = None,
+        replay: str | None = None,
     ) -> TranslateBatchResult:
         if unidiomatic_only and idiomatic_only:
             raise ValueError("Only one of unidiomatic_only and idiomatic_only can be set")
+        if record_replay and replay:
+            raise ValueError("Only one of record_replay and replay can be set")
+        if c2rust_translation_file and idiomatic_only:
+            raise ValueError("Only one of c2rust_translation_file and idiomatic_only can be set")
 
         if isinstance(target_type, str):
             target_lower = target_type.lower()
@@ -60,8 +81,16 @@ class Sactor:
         if not is_executable and not normalized_executable_object:
             raise ValueError("Executable object must be provided for library targets")
 
+        if make_project:
+            if compile_commands_file:
+                raise ValueError("Only one of make_project and compile_commands_file can be set")
+            compile_commands_file = capture_make_compile_commands(
+                make_project, shlex.split(make_args))
+
         if input_file is None and not compile_commands_file:
             raise ValueError('input_file is required unless --compile-commands-file is provided')
+        if input_file is None and c2rust_translation_file:
+            raise ValueError('input_file is required to import a c2rust translation')
 
         base_result_dir = result_dir if result_dir else os.path.join(os.getcwd(), "sactor_result")
         os.makedirs(base_result_dir, exist_ok=True)
@@ -72,7 +101,17 @@ class Sactor:
                 config,
                 result_dir=base_result_dir,
                 log_dir_override=log_dir_override,
+                enable_jsonl_override=log_jsonl,
+                verbosity=log_verbosity,
             )
+            # a caller that configured logging itself, as `watch`, checked the config
+            validate_config(config)
+
+        replay_bundle = None
+        if replay:
+            replay_bundle = ReplayBundle.load(replay, config)
+        elif record_replay:
+            replay_bundle = ReplayBundle.record(record_replay, config)
 
         if input_file:
             runner = cls(
@@ -92,8 +131,13 @@ class Sactor:
                 entry_tu_file=entry_tu_file,
                 idiomatic_only=idiomatic_only,
                 continue_run_when_incomplete=continue_run_when_incomplete,
+                c2rust_translation_file=c2rust_translation_file,
+                resume=resume,
+                replay_bundle=replay_bundle,
             )
             runner.run()
+            if replay_bundle is not None:
+                replay_bundle.finish()
             entry = {
                 "input": input_file,
                 "result_dir": getattr(runner, "result_dir", base_result_dir),
@@ -108,7 +152,7 @@ class Sactor:
                 combined_dir=None,
             )
 
-        return run_translate_batch(
+        batch_result = run_translate_batch(
             runner_cls=cls,
             base_result_dir=base_result_dir,
             config=config,
@@ -126,7 +170,153 @@ class Sactor:
             executable_object=normalized_executable_object,
             link_args=link_args,
             llm_stat=llm_stat,
+            resume=resume,
+            replay_bundle=replay_bundle,
+        )
+        if replay_bundle is not None:
+            replay_bundle.finish()
+        return batch_result
+
+    @classmethod
+    def retranslate(
+        cls,
+        *,
+        function_name: str,
+        target_type: str | bool,
+        test_cmd_path: str,
+        input_file: str,
+        result_dir: str | None = None,
+        build_dir: str | None = None,
+        config_file: str | None = None,
+        no_verify: bool = False,
+        unidiomatic_only: bool = False,
+        idiomatic_only: bool = False,
+        extra_compile_command: str | None = None,
+        executable_object=None,
+        link_args: str = "",
+        llm_stat: str | None = None,
+        log_dir_override: str | None = None,
+        log_jsonl: bool | None = None,
+        log_verbosity: int = 0,
+        configure_logging: bool = True,
+    ) -> TranslateResult:
+        """
+        Translates one function of an earlier `translate` run in `result_dir`
+        again, see `retranslate_function`.
+        """
+        if unidiomatic_only and idiomatic_only:
+            raise ValueError("Only one of unidiomatic_only and idiomatic_only can be set")
+        if isinstance(target_type, str):
+            target_lower = target_type.lower()
+            if target_lower not in {"bin", "lib"}:
+                raise ValueError(f"Unsupported target type: {target_type}")
+            is_executable = target_lower == "bin"
+        else:
+            is_executable = bool(target_type)
+
+        base_result_dir = result_dir if result_dir else os.path.join(os.getcwd(), "sactor_result")
+        if not os.path.isdir(base_result_dir):
+            raise FileNotFoundError(f"No translation result to retranslate {function_name} in: {base_result_dir}")
+
+        config = utils.try_load_config(config_file)
+        if configure_logging:
+            sactor_logging.configure_logging(
+                config,
+                result_dir=base_result_dir,
+                log_dir_override=log_dir_override,
+                enable_jsonl_override=log_jsonl,
+                verbosity=log_verbosity,
+            )
+            # a caller that configured logging itself, as `watch`, checked the config
+            validate_config(config)
+
+        runner = cls(
+            input_file=input_file,
+            test_cmd_path=test_cmd_path,
+            build_dir=build_dir,
+            result_dir=base_result_dir,
+            config_file=config_file,
+            no_verify=no_verify,
+            unidiomatic_only=unidiomatic_only,
+            llm_stat=llm_stat,
+            extra_compile_command=extra_compile_command,
+            is_executable=is_executable,
+            executable_object=utils._normalize_executable_object_arg(executable_object),
+            link_args=link_args,
+            idiomatic_only=idiomatic_only,
+            # the checkpoint of the earlier run is updated, not started over
+            resume=True,
+        )
+        return runner.retranslate_function(function_name)
+
+    @classmethod
+    def watch(
+        cls,
+        *,
+        target_type: str | bool,
+        test_cmd_path: str,
+        input_file: str,
+        result_dir: str | None = None,
+        build_dir: str | None = None,
+        config_file: str | None = None,
+        no_verify: bool = False,
+        unidiomatic_only: bool = False,
+        idiomatic_only: bool = False,
+        extra_compile_command: str | None = None,
+        executable_object=None,
+        link_args: str = "",
+        llm_stat: str | None = None,
+        log_dir_override: str | None = None,
+        log_jsonl: bool | None = None,
+        log_verbosity: int = 0,
+        interval: float = 2.0,
+        once: bool = False,
+    ) -> bool:
+        """
+        Translates `input_file`, then keeps the translation in sync with it:
+        whenever the file changes, the functions whose C definition changed
+        are translated again, see `Watcher`. With `once`, syncs only once and
+        returns whether that succeeded.
+        """
+        if not os.path.isfile(input_file):
+            raise FileNotFoundError(f"No such C file to watch: {input_file}")
+        base_result_dir = result_dir if result_dir else os.path.join(os.getcwd(), "sactor_result")
+        os.makedirs(base_result_dir, exist_ok=True)
+        config = utils.try_load_config(config_file)
+        sactor_logging.configure_logging(
+            config,
+            result_dir=base_result_dir,
+            log_dir_override=log_dir_override,
+            enable_jsonl_override=log_jsonl,
+            verbosity=log_verbosity,
+        )
+        validate_config(config)
+        options = dict(
+            target_type=target_type,
+            test_cmd_path=test_cmd_path,
+            input_file=input_file,
+            result_dir=base_result_dir,
+            build_dir=build_dir,
+            config_file=config_file,
+            no_verify=no_verify,
+            unidiomatic_only=unidiomatic_only,
+            idiomatic_only=idiomatic_only,
+            extra_compile_command=extra_compile_command,
+            executable_object=executable_object,
+            link_args=link_args,
+            llm_stat=llm_stat,
+            configure_logging=False,
+        )
+        watcher = Watcher(
+            input_file,
+            base_result_dir,
+            translate=lambda: cls.translate(**options),
+            retranslate=lambda name: cls.retranslate(function_name=name, **options),
         )
+        if once:
+            return watcher.sync()
+        watcher.run(interval)
+        return True
 
     def __init__(
         self,
@@ -151,9 +341,23 @@ class Sactor:
         project_struct_usr_to_result_dir: dict[str, str] | None = None,
         project_enum_usr_to_result_dir: dict[str, str] | None = None,
         project_global_usr_to_result_dir: dict[str, str] | None = None,
+        # a c2rust translation of `input_file` imported as the unidiomatic stage
+        c2rust_translation_file: str | None = None,
+        # continue the interrupted run whose checkpoint is in `result_dir`
+        resume: bool = False,
+        # shared by the translation units of a project, whose budget covers them all
+        cost_tracker: CostTracker | None = None,
+        # replay bundle the LLM interactions are recorded to, or answered from
+        replay_bundle: ReplayBundle | None = None,
     ):
         self.config_file = config_file
         self.config = utils.try_load_config(self.config_file)
+        for scalar_map in self.config['general'].get('scalar_map_files', []):
+            type_normalization.load_scalar_map(scalar_map)
+        if self.config['general'].get('rustfmt_config'):
+            utils.set_rustfmt_config(self.config['general']['rustfmt_config'])
+        if self.config['general'].get('stable_output'):
+            utils.set_stable_output(self.config['general'].get('msrv', ''))
         self.result_dir = os.path.join(
             os.getcwd(), "sactor_result") if result_dir is None else result_dir
 
@@ -198,6 +402,15 @@ class Sactor:
         self.project_struct_usr_to_result_dir = project_struct_usr_to_result_dir or {}
         self.project_enum_usr_to_result_dir = project_enum_usr_to_result_dir or {}
         self.project_global_usr_to_result_dir = project_global_usr_to_result_dir or {}
+        self.c2rust_translation_file = c2rust_translation_file
+        if c2rust_translation_file and not os.path.isfile(c2rust_translation_file):
+            raise FileNotFoundError(f"c2rust translation not found: {c2rust_translation_file}")
+        checkpoint_path = os.path.join(self.result_dir, CHECKPOINT_FILE)
+        if resume:
+            self.checkpoint = Checkpoint.load(checkpoint_path, input_file)
+        else:
+            utils.try_backup_file(checkpoint_path)
+            self.checkpoint = Checkpoint(checkpoint_path, input_file)
             
         exec_obj_missing = executable_object is None or (
             isinstance(executable_object, list) and len(executable_object) == 0)
@@ -224,6 +437,8 @@ class Sactor:
         logger.info("Link args: %s", self.link_args)
         logger.info("Idiomatic only: %s", self.idiomatic_only)
         logger.info("Continue run when incomplete: %s", self.continue_run_when_incomplete)
+        logger.info("Imported c2rust translation: %s", self.c2rust_translation_file)
+        logger.info("Resume: %s", self.checkpoint.resumed)
         logger.info("-------------End of Configuration-------------")
         # save the config in the result dir. Sensitive info is removed from the saved config
         safe_config = utils.sanitize_config(self.config)
@@ -233,6 +448,9 @@ class Sactor:
 
         # Check necessary requirements
         missing_requirements = thirdparty.check_all_requirements()
+        if self.c2rust_translation_file:
+            # the imported translation replaces running c2rust
+            missing_requirements = [r for r in missing_requirements if r != "c2rust"]
         if missing_requirements:
             raise OSError(
                 f"Missing requirements: {', '.join(missing_requirements)}")
@@ -246,6 +464,11 @@ class Sactor:
             extra_args=include_flags,
             raw_filename=self.input_file,
         )
+        if self.c_parser.is_cxx:
+            unsupported = self.c_parser.get_unsupported_cxx_constructs()
+            if unsupported:
+                raise ValueError(
+                    "Unsupported C++ constructs in the input:\n" + "\n".join(unsupported))
 
         # Project-wide backfill for non-function refs when a compilation database is provided
         if self.compile_commands_file:
@@ -302,73 +525,295 @@ class Sactor:
         )
 
         # Initialize LLM
-        self.llm = llm_factory(self.config)
+        self.llm = llm_factory(self.config, replay=replay_bundle)
+        if cost_tracker is not None:
+            self.llm.cost = cost_tracker
+        self.llm.cost.unit = self.input_file
 
         self.c2rust_translation = None
+        if self.c2rust_translation_file:
+            self.c2rust_translation = utils.read_file(self.c2rust_translation_file)
 
     def run(self):
+        started_at = time.time()
+        self.results = []
+        error = None
+        try:
+            with sactor_logging.span("translation", unit=self.input_file):
+                self._run_stages()
+        except Exception as e:
+            error = str(e) or type(e).__name__
+            raise
+        finally:
+            self._write_cost_report(COST_REPORT_FILE)
+            self._write_summary(started_at, error)
+
+    def _run_stages(self):
         def _stage_stat_path(stage: str) -> str:
             return utils._derive_llm_stat_path(self.llm_stat, stage=stage)
 
-        if not self.idiomatic_only:
-            self.llm.reset_statistics()
-            unidiomatic_stat_path = _stage_stat_path("unidiomatic")
-            result, unidiomatic_translator = self._run_unidomatic_translation()
-            # Collect failure info
-            unidiomatic_translator.save_failure_info(unidiomatic_translator.failure_info_path)
-
-            stage_error = None
-            if result != TranslateResult.SUCCESS:
-                unidiomatic_translator.print_result_summary("Unidiomatic")
-                stage_error = f"Failed to translate unidiomatic code: {result}"
-            else:
-                combine_result, _ = self.combiner.combine(
-                    os.path.join(self.result_dir, "translated_code_unidiomatic"),
-                    is_idiomatic=False,
-                )
-                if combine_result != CombineResult.SUCCESS:
-                    stage_error = (
-                        "Failed to combine translated code for unidiomatic translation: "
-                        f"{combine_result}"
+        results: list[dict] = []
+        if not self.idiomatic_only and self.checkpoint.is_completed("unidiomatic"):
+            logger.info("Unidiomatic translation was completed by the resumed run, skipping it")
+            results.extend(result_export.stage_results("unidiomatic", self._saved_failure_info("unidiomatic")))
+            self._export_results(results)
+        elif not self.idiomatic_only:
+            with sactor_logging.span("unidiomatic translation", stage="unidiomatic"):
+                self.llm.reset_statistics()
+                self.llm.cost.stage = "unidiomatic"
+                unidiomatic_stat_path = _stage_stat_path("unidiomatic")
+                result, unidiomatic_translator = self._run_unidomatic_translation()
+                # Collect failure info
+                unidiomatic_translator.save_failure_info(unidiomatic_translator.failure_info_path)
+                self.checkpoint.record_stage("unidiomatic", unidiomatic_translator.failure_info, self.llm)
+                results.extend(result_export.stage_results("unidiomatic", unidiomatic_translator.failure_info))
+                self._export_results(results)
+
+                stage_error = None
+                if result != TranslateResult.SUCCESS:
+                    unidiomatic_translator.print_result_summary("Unidiomatic")
+                    stage_error = f"Failed to translate unidiomatic code: {result}"
+                else:
+                    combine_result, _ = self.combiner.combine(
+                        os.path.join(self.result_dir, "translated_code_unidiomatic"),
+                        is_idiomatic=False,
                     )
-
-            self.llm.statistic(unidiomatic_stat_path)
-
-            if stage_error:
-                if self.continue_run_when_incomplete:
-                    logger.error(stage_error)
+                    if combine_result != CombineResult.SUCCESS:
+                        stage_error = (
+                            "Failed to combine translated code for unidiomatic translation: "
+                            f"{combine_result}"
+                        )
+                    else:
+                        self.checkpoint.complete_stage("unidiomatic")
+                self.checkpoint.save()
+
+                self.llm.statistic(unidiomatic_stat_path)
+
+                if stage_error:
+                    if self.continue_run_when_incomplete:
+                        logger.error(stage_error)
+                    else:
+                        raise ValueError(stage_error)
+
+        if not self.unidiomatic_only and self.checkpoint.is_completed("idiomatic"):
+            logger.info("Idiomatic translation was completed by the resumed run, skipping it")
+            results.extend(result_export.stage_results("idiomatic", self._saved_failure_info("idiomatic")))
+            self._export_results(results)
+        elif not self.unidiomatic_only:
+            with sactor_logging.span("idiomatic translation", stage="idiomatic"):
+                self.llm.reset_statistics()
+                self.llm.cost.stage = "idiomatic"
+                idiomatic_stat_path = _stage_stat_path("idiomatic")
+                result, idiomatic_translator = self._run_idiomatic_translation()
+                # Collect failure info
+                idiomatic_translator.save_failure_info(idiomatic_translator.failure_info_path)
+                self.checkpoint.record_stage("idiomatic", idiomatic_translator.failure_info, self.llm)
+                results.extend(result_export.stage_results("idiomatic", idiomatic_translator.failure_info))
+                self._export_results(results)
+
+                stage_error = None
+                if result != TranslateResult.SUCCESS:
+                    idiomatic_translator.print_result_summary("Idiomatic")
+                    stage_error = f"Failed to translate idiomatic code: {result}"
                 else:
-                    raise ValueError(stage_error)
-
+                    combine_result, _ = self.combiner.combine(
+                        os.path.join(self.result_dir, "translated_code_idiomatic"),
+                        is_idiomatic=True,
+                    )
+                    if combine_result != CombineResult.SUCCESS:
+                        stage_error = (
+                            "Failed to combine translated code for idiomatic translation: "
+                            f"{combine_result}"
+                        )
+                    else:
+                        self.checkpoint.complete_stage("idiomatic")
+                self.checkpoint.save()
+
+                self.llm.statistic(idiomatic_stat_path)
+
+                if stage_error:
+                    if self.continue_run_when_incomplete:
+                        logger.error(stage_error)
+                    else:
+                        raise ValueError(stage_error)
+
+        self._emit_crate()
+
+    def retranslate_function(self, function_name: str) -> TranslateResult:
+        """
+        Translates `function_name` again in each stage of the earlier run,
+        reusing the accepted translations of all other items, and recombines
+        `combined.rs` of the stage. When the new translation fails, the
+        previous one is kept.
+        """
+        function = self.c_parser.get_function_info(function_name)
+        stages = []
+        if not self.idiomatic_only:
+            stages.append("unidiomatic")
         if not self.unidiomatic_only:
-            self.llm.reset_statistics()
-            idiomatic_stat_path = _stage_stat_path("idiomatic")
-            result, idiomatic_translator = self._run_idiomatic_translation()
-            # Collect failure info
-            idiomatic_translator.save_failure_info(idiomatic_translator.failure_info_path)
-
-            stage_error = None
-            if result != TranslateResult.SUCCESS:
-                idiomatic_translator.print_result_summary("Idiomatic")
-                stage_error = f"Failed to translate idiomatic code: {result}"
-            else:
-                combine_result, _ = self.combiner.combine(
-                    os.path.join(self.result_dir, "translated_code_idiomatic"),
-                    is_idiomatic=True,
+            stages.append("idiomatic")
+        stages = [stage for stage in stages
+                  if os.path.isdir(os.path.join(self.result_dir, f"translated_code_{stage}"))]
+        if not stages:
+            raise ValueError(f"No earlier translation in {self.result_dir} to retranslate {function_name} in")
+
+        result = TranslateResult.SUCCESS
+        results: list[dict] = []
+        try:
+            for stage in ("unidiomatic", "idiomatic"):
+                # the idiomatic translation starts from the unidiomatic one
+                if stage in stages and result == TranslateResult.SUCCESS:
+                    result = self._retranslate_in_stage(stage, function)
+                results.extend(result_export.stage_results(stage, self._saved_failure_info(stage)))
+        finally:
+            self._write_cost_report(f"cost_report_{function_name}.json")
+        self._export_results(results)
+        if result == TranslateResult.SUCCESS:
+            self._emit_crate()
+        return result
+
+    def _retranslate_in_stage(self, stage: str, function) -> TranslateResult:
+        if stage == "unidiomatic":
+            translator = self._new_unidiomatic_translator()
+        else:
+            translator = self._new_idiomatic_translator()
+        translator.use_checkpoint(self.checkpoint, stage)
+        try:
+            struct_ready, struct_blockers = translator.check_dependencies(
+                function, lambda s: s.struct_dependencies)
+            func_ready, func_blockers = translator.check_dependencies(
+                function, lambda s: s.function_dependencies)
+        except RuntimeError as exc:
+            raise ValueError(f"Cannot retranslate {function.name} in the {stage} stage: {exc}") from exc
+        if not struct_ready or not func_ready:
+            blockers = ", ".join(b["name"] for b in struct_blockers + func_blockers)
+            raise ValueError(
+                f"Cannot retranslate {function.name} in the {stage} stage, its dependencies failed: {blockers}")
+
+        stage_dir = os.path.join(self.result_dir, f"translated_code_{stage}")
+        # the previous translation, put back when the new one fails
+        previous = {}
+        for path in (os.path.join(stage_dir, "functions", f"{function.name}.rs"),
+                     os.path.join(stage_dir, "combined.rs")):
+            if os.path.isfile(path):
+                previous[path] = utils.read_file(path)
+                os.remove(path)
+        previous_status = translator.failure_info.get(function.name, {}).get("status")
+
+        self.llm.reset_statistics()
+        self.llm.cost.stage = stage
+        self.checkpoint.restart_item(stage, function.name)
+        translator.restart_item("function", function.name)
+        logger.info("Retranslating function %s in the %s stage", function.name, stage)
+        result = translator.translate_function(function)
+        stage_error = None
+        if result == TranslateResult.SUCCESS:
+            combine_result, _ = self.combiner.combine(stage_dir, is_idiomatic=stage == "idiomatic")
+            if combine_result != CombineResult.SUCCESS:
+                stage_error = (
+                    f"Failed to combine the {stage} translation with the new {function.name}: "
+                    f"{combine_result}"
                 )
-                if combine_result != CombineResult.SUCCESS:
-                    stage_error = (
-                        "Failed to combine translated code for idiomatic translation: "
-                        f"{combine_result}"
-                    )
-
-            self.llm.statistic(idiomatic_stat_path)
 
-            if stage_error:
-                if self.continue_run_when_incomplete:
-                    logger.error(stage_error)
-                else:
-                    raise ValueError(stage_error)
+        if result != TranslateResult.SUCCESS or stage_error:
+            logger.error("Retranslation of %s failed in the %s stage, keeping its previous translation",
+                         function.name, stage)
+            for path in (os.path.join(stage_dir, "functions", f"{function.name}.rs"),
+                         os.path.join(stage_dir, "combined.rs")):
+                if os.path.isfile(path):
+                    os.remove(path)
+            for path, code in previous.items():
+                with open(path, "w") as f:
+                    f.write(code)
+            if previous_status is not None:
+                translator.failure_info[function.name]['status'] = previous_status
+        translator.save_failure_info(translator.failure_info_path)
+        self.checkpoint.record_stage(stage, translator.failure_info, self.llm)
+        self.checkpoint.save()
+        self.llm.statistic(utils._derive_llm_stat_path(self.llm_stat, slug=function.name, stage=stage))
+
+        if stage_error:
+            raise ValueError(stage_error)
+        return result
+
+    def _emit_crate(self):
+        """
+        Writes a buildable crate around the most idiomatic combined translation
+        to `result_dir/crate`. Project translations get theirs from the
+        project combiner instead.
+        """
+        if self.processed_compile_commands:
+            return
+        for stage in ("idiomatic", "unidiomatic"):
+            combined_path = os.path.join(self.result_dir, f"translated_code_{stage}", "combined.rs")
+            if os.path.exists(combined_path):
+                break
+        else:
+            return
+        if stage == "unidiomatic" and utils.no_std_profile(self.config):
+            # unidiomatic code calls libc, which has no bare-metal bindings
+            logger.warning("No idiomatic translation to emit as a no_std crate")
+            return
+        name = library_crate_name(self.input_file)
+        # no_std ships a library: bare-metal entry points are board-specific
+        is_bin = self.is_executable and not utils.no_std_profile(self.config)
+        unit = CrateUnit(name, utils.read_file(combined_path), kind="bin" if is_bin else "lib")
+        CrateEmitter(self.config).emit(
+            os.path.join(self.result_dir, "crate"), name, [unit], link_args=self.link_args)
+
+    def _write_cost_report(self, file_name: str):
+        path = os.path.join(self.result_dir, file_name)
+        utils.try_backup_file(path)
+        total = self.llm.cost.write_report(path, unit=self.input_file)["total"]
+        logger.info("LLM spend: $%.4f for %d queries, %d input and %d output tokens (details in %s)",
+                    total["cost"], total["queries"], total["input_tokens"], total["output_tokens"], path)
+
+    def _write_summary(self, started_at: float, error: str | None):
+        path = os.path.join(self.result_dir, run_summary.SUMMARY_FILE)
+        stages_run = [stage for stage, skipped in (("unidiomatic", self.idiomatic_only),
+                                                   ("idiomatic", self.unidiomatic_only)) if not skipped]
+        # the items of stages this run skipped are those of the run that translated them
+        items = [item for unit in run_summary.load_units(path) for item in unit["items"]
+                 if item["stage"] not in stages_run]
+        items += run_summary.item_summaries(self.results, self.result_dir)
+        unit = run_summary.unit_summary(self.input_file, self.result_dir, items, error)
+        cost = self.llm.cost.report(unit=self.input_file)["total"]
+        run_summary.write_summary(path, run_summary.run_summary([unit], started_at, time.time(), cost))
+
+    def _saved_failure_info(self, stage: str) -> dict:
+        """The failure info a stage saved, for stages a resumed run skips."""
+        path = os.path.join(self.result_dir, f"{stage}_failure_info.json")
+        if not os.path.isfile(path):
+            return {}
+        return json.loads(utils.read_file(path))
+
+    def _export_results(self, results: list[dict]):
+        # machine-readable per-item results for CI, rewritten after every stage
+        self.results = results
+        result_export.write_json_summary(os.path.join(self.result_dir, "results.json"), results)
+        result_export.write_junit_xml(os.path.join(self.result_dir, "junit.xml"), results)
+        html_report.write_html_report(os.path.join(self.result_dir, html_report.REPORT_FILE), self.input_file,
+                                      results, self.result_dir, self._c_sources(results))
+
+    def _c_sources(self, results: list[dict]) -> dict[tuple[str, str], str]:
+        """The C code of the items in `results`, by type and name."""
+        extractors = {
+            "function": self.c_parser.extract_function_code,
+            "struct": self.c_parser.extract_struct_union_definition_code,
+            "enum": self.c_parser.extract_enum_definition_code,
+            "global_var": self.c_parser.extract_global_var_definition_code,
+        }
+        sources = {}
+        for result in results:
+            key = (result["type"], result["name"])
+            if key in sources or result["type"] not in extractors:
+                continue
+            try:
+                sources[key] = extractors[result["type"]](result["name"])
+            except (ValueError, KeyError):
+                # e.g. items the parser only knows by declaration
+                continue
+        return sources
 
     def _new_unidiomatic_translator(self):
         if self.c2rust_translation is None:
@@ -393,13 +838,16 @@ class Sactor:
             project_struct_usr_to_result_dir=self.project_struct_usr_to_result_dir,
             project_enum_usr_to_result_dir=self.project_enum_usr_to_result_dir,
             project_global_usr_to_result_dir=self.project_global_usr_to_result_dir,
+            import_c2rust=bool(self.c2rust_translation_file),
         )
         return translator
 
 
     def _run_unidomatic_translation(self) -> tuple[TranslateResult, Translator]:
         translator = self._new_unidiomatic_translator()
-        translator.prepare_failure_info_backup()
+        translator.use_checkpoint(self.checkpoint, "unidiomatic")
+        if not self.checkpoint.resumed:
+            translator.prepare_failure_info_backup()
         final_result = TranslateResult.SUCCESS
         for struct_pairs in self.struct_order:
             for struct in struct_pairs:
@@ -468,7 +916,9 @@ class Sactor:
 
     def _run_idiomatic_translation(self) -> tuple[TranslateResult, Translator]:
         translator = self._new_idiomatic_translator()
-        translator.prepare_failure_info_backup()
+        translator.use_checkpoint(self.checkpoint, "idiomatic")
+        if not self.checkpoint.resumed:
+            translator.prepare_failure_info_backup()
         final_result = TranslateResult.SUCCESS
         for struct_pairs in self.struct_order:
             for struct in struct_pairs:
@@ -1,9 +1,15 @@
 from .test_generator import TestGenerator
 from .executable_test_generator import ExecutableTestGenerator
+from .fuzz_target_generator import FuzzTargetGenerator
+from .benchmark_generator import BenchmarkGenerator
+from .differential_driver_generator import DifferentialDriverGenerator
 from .test_generator_types import TestGeneratorResult
 
 __all__ = [
     'TestGenerator',
     'ExecutableTestGenerator',
+    'FuzzTargetGenerator',
+    'BenchmarkGenerator',
+    'DifferentialDriverGenerator',
     'TestGeneratorResult',
 ]
@@ -0,0 +1,183 @@
+import json
+import math
+import os
+from typing import Optional
+
+from sactor import logging as sactor_logging
+from sactor import utils
+
+logger = sactor_logging.get_logger(__name__)
+
+
+class BenchmarkGenerator:
+    """
+    Emits a criterion benchmark crate that times the original C program
+    against its Rust translation on the accepted test samples, and turns
+    criterion's estimates into a comparative report.
+
+    Both sides are timed as whole executables, one process per sample run:
+    a translated `main` reads its arguments and stdin from the process, so it
+    cannot be driven in-process the way a C `main` can through FFI.
+    """
+
+    CRATE_NAME = "sactor-bench"
+
+    def __init__(
+        self,
+        test_samples_path: str,
+        c_executable: str,
+        rust_executable: str,
+        out_dir: str,
+        feed_as_arguments: bool = True,
+    ):
+        self.test_samples: list[dict] = json.loads(utils.read_file(test_samples_path))
+        self.c_executable = os.path.abspath(c_executable)
+        self.rust_executable = os.path.abspath(rust_executable)
+        self.out_dir = os.path.abspath(out_dir)
+        self.feed_as_arguments = feed_as_arguments
+
+    def generate(self) -> str:
+        """Writes the benchmark crate and returns its directory."""
+        os.makedirs(os.path.join(self.out_dir, "benches"), exist_ok=True)
+        self._write_manifest()
+        utils.save_code(os.path.join(self.out_dir, "benches", "compare.rs"), self._bench_code())
+        logger.info(
+            "Benchmark crate written to %s; run it with `cargo bench --manifest-path %s/Cargo.toml`",
+            self.out_dir, self.out_dir)
+        return self.out_dir
+
+    def _write_manifest(self) -> None:
+        manifest = [
+            "[package]",
+            f'name = "{self.CRATE_NAME}"',
+            'version = "0.0.0"',
+            "publish = false",
+            'edition = "2021"',
+            "",
+            "[dev-dependencies]",
+            'criterion = "0.5"',
+            "",
+            "[[bench]]",
+            'name = "compare"',
+            "harness = false",
+            "",
+            "[workspace]",
+        ]
+        with open(os.path.join(self.out_dir, "Cargo.toml"), "w", encoding="utf-8") as fh:
+            fh.write("\n".join(manifest) + "\n")
+        # criterion benches need a (possibly empty) library or binary target
+        utils.save_code(os.path.join(self.out_dir, "src", "lib.rs"), "")
+
+    def _bench_code(self) -> str:
+        samples = ",\n".join(f"    {json.dumps(sample['input'])}" for sample in self.test_samples)
+        return f'''use criterion::{{criterion_group, criterion_main, Criterion}};
+use std::io::Write;
+use std::process::{{Command, Stdio}};
+
+const C_EXECUTABLE: &str = {json.dumps(self.c_executable)};
+const RUST_EXECUTABLE: &str = {json.dumps(self.rust_executable)};
+const FEED_AS_ARGUMENTS: bool = {str(self.feed_as_arguments).lower()};
+const SAMPLES: &[&str] = &[
+{samples}
+];
+
+fn run(executable: &str, input: &str) {{
+    let mut command = Command::new(executable);
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+    if FEED_AS_ARGUMENTS {{
+        command.args(input.split_whitespace()).stdin(Stdio::null());
+        command.status().expect("failed to run the benchmarked program");
+    }} else {{
+        let mut child = command.stdin(Stdio::piped()).spawn().expect("failed to run the benchmarked program");
+        let mut stdin = child.stdin.take().unwrap();
+        // the program may exit before reading all of its input
+        let _ = writeln!(stdin, "{{}}", input);
+        drop(stdin);
+        child.wait().expect("failed to wait for the benchmarked program");
+    }}
+}}
+
+fn compare(c: &mut Criterion) {{
+    for (i, input) in SAMPLES.iter().enumerate() {{
+        let mut group = c.benchmark_group(format!("sample_{{}}", i));
+        group.bench_function("c", |b| b.iter(|| run(C_EXECUTABLE, input)));
+        group.bench_function("rust", |b| b.iter(|| run(RUST_EXECUTABLE, input)));
+        group.finish();
+    }}
+}}
+
+criterion_group!(benches, compare);
+criterion_main!(benches);
+'''
+
+    def run(self) -> dict:
+        """Runs `cargo bench` on the generated crate and writes its report."""
+        res = utils.run_command(
+            ["cargo", "bench", "--manifest-path", os.path.join(self.out_dir, "Cargo.toml")])
+        if res.returncode != 0:
+            raise RuntimeError(f"cargo bench failed: {res.stderr}")
+        return self.write_report()
+
+    def write_report(self, criterion_dir: Optional[str] = None) -> dict:
+        """
+        Reads criterion's mean estimates for every sample and writes
+        `report.json` and `report.md` to the crate directory. A ratio above 1
+        means the Rust translation is slower than the C original.
+        """
+        if criterion_dir is None:
+            criterion_dir = os.path.join(self.out_dir, "target", "criterion")
+        rows = []
+        for i, sample in enumerate(self.test_samples):
+            c_ns = _mean_estimate(criterion_dir, f"sample_{i}", "c")
+            rust_ns = _mean_estimate(criterion_dir, f"sample_{i}", "rust")
+            if c_ns is None or rust_ns is None:
+                continue
+            rows.append({
+                "sample": i,
+                "input": sample["input"],
+                "c_mean_ns": c_ns,
+                "rust_mean_ns": rust_ns,
+                "ratio": rust_ns / c_ns,
+            })
+        report = {
+            "samples": rows,
+            # geometric mean, so one slow sample doesn't dominate
+            "geomean_ratio": math.exp(sum(math.log(r["ratio"]) for r in rows) / len(rows))
+            if rows else None,
+        }
+        with open(os.path.join(self.out_dir, "report.json"), "w") as f:
+            json.dump(report, f, indent=4)
+
+        lines = [
+            "| Sample | Input | C mean | Rust mean | Rust / C |",
+            "| --- | --- | --- | --- | --- |",
+        ]
+        for row in rows:
+            shown = row["input"].replace("\n", "\\n").replace("|", "\\|")
+            if len(shown) > 40:
+                shown = shown[:37] + "..."
+            lines.append(
+                f"| {row['sample']} | `{shown}` | {_format_ns(row['c_mean_ns'])} | "
+                f"{_format_ns(row['rust_mean_ns'])} | {row['ratio']:.2f}x |")
+        if report["geomean_ratio"] is not None:
+            lines.append("")
+            lines.append(f"Geometric mean of Rust / C: {report['geomean_ratio']:.2f}x")
+        with open(os.path.join(self.out_dir, "report.md"), "w") as f:
+            f.write("\n".join(lines) + "\n")
+        return report
+
+
+def _mean_estimate(criterion_dir: str, group: str, name: str) -> Optional[float]:
+    path = os.path.join(criterion_dir, group, name, "new", "estimates.json")
+    if not os.path.exists(path):
+        logger.warning("No criterion estimates at %s", path)
+        return None
+    with open(path) as f:
+        return json.load(f)["mean"]["point_estimate"]
+
+
+def _format_ns(ns: float) -> str:
+    for unit, scale in (("s", 1e9), ("ms", 1e6), ("µs", 1e3)):
+        if ns >= scale:
+            return f"{ns / scale:.2f} {unit}"
+    return f"{ns:.0f} ns"
@@ -0,0 +1,128 @@
+import json
+import os
+import shutil
+import subprocess
+from typing import Iterator
+
+from sactor import logging as sactor_logging
+from sactor import utils
+
+logger = sactor_logging.get_logger(__name__)
+
+# boundary values tried in place of every integer token
+INTEGER_MUTATIONS = (0, 1, -1, 255, 65536, 2147483647, -2147483648)
+
+
+class BranchCoverage:
+    """
+    Branch coverage of a C program per test input. The program is built with
+    gcc's `--coverage` instrumentation in a private directory, and each input
+    is run on its own so its covered branches can be read back from gcov.
+    """
+
+    def __init__(self, c_file: str, feed_as_arguments: bool, timeout_seconds: int):
+        for tool in ('gcc', 'gcov'):
+            if shutil.which(tool) is None:
+                raise OSError(f'{tool} is required for coverage-guided test generation')
+        self.feed_as_arguments = feed_as_arguments
+        self.timeout_seconds = timeout_seconds
+        self.work_dir = utils.get_temp_dir()
+        self.object_path = os.path.join(self.work_dir, 'program.o')
+        self.executable = os.path.join(self.work_dir, 'program')
+        c_file = os.path.abspath(c_file)
+        utils.run_command(
+            ['gcc', '--coverage', '-O0', '-c', c_file, '-o', self.object_path], check=True)
+        utils.run_command(
+            ['gcc', '--coverage', self.object_path, '-o', self.executable, '-lm'], check=True)
+        # gcov only lists branches once some data file exists; updated per run
+        self.total = 0
+
+    def _branches(self, covered_only: bool) -> set[tuple[int, int]]:
+        result = utils.run_command(
+            ['gcov', '--branch-probabilities', '--json-format', '--stdout',
+             os.path.basename(self.object_path)],
+            cwd=self.work_dir,
+        )
+        branches = set()
+        for line in result.stdout.splitlines():
+            if not line.startswith('{'):
+                continue
+            for source in json.loads(line).get('files', []):
+                for gcov_line in source.get('lines', []):
+                    for i, branch in enumerate(gcov_line.get('branches', [])):
+                        if branch['count'] > 0 or not covered_only:
+                            branches.add((gcov_line['line_number'], i))
+        return branches
+
+    def covered(self, sample: str) -> frozenset[tuple[int, int]]:
+        """Runs `sample` alone and returns the `(line, branch)` pairs it takes."""
+        gcda_path = os.path.splitext(self.object_path)[0] + '.gcda'
+        if os.path.exists(gcda_path):
+            os.remove(gcda_path)
+        try:
+            if self.feed_as_arguments:
+                utils.run_command(
+                    [self.executable, *sample.split()],
+                    timeout=self.timeout_seconds,
+                    cwd=self.work_dir,
+                )
+            else:
+                utils.run_command(
+                    [self.executable],
+                    timeout=self.timeout_seconds,
+                    cwd=self.work_dir,
+                    input_data=f'{sample}\n',
+                )
+        except subprocess.TimeoutExpired:
+            return frozenset()
+        if not os.path.exists(gcda_path):
+            # crashed before the coverage data was flushed
+            return frozenset()
+        self.total = max(self.total, len(self._branches(covered_only=False)))
+        return frozenset(self._branches(covered_only=True))
+
+    def cleanup(self) -> None:
+        shutil.rmtree(self.work_dir, ignore_errors=True)
+
+
+def mutate_inputs(samples: list[str], feed_as_arguments: bool) -> Iterator[str]:
+    """
+    Yields variations of `samples`: integer tokens replaced by neighbours and
+    boundary values, strings emptied, truncated or doubled, and tokens (or
+    stdin lines) dropped, duplicated or swapped.
+    """
+    for sample in samples:
+        if feed_as_arguments:
+            units = [sample.split()]
+        else:
+            units = [line.split() for line in sample.split('\n')]
+
+        def join(lines: list[list[str]]) -> str:
+            if feed_as_arguments:
+                return ' '.join(word for line in lines for word in line if word)
+            return '\n'.join(' '.join(word for word in line if word) for line in lines)
+
+        for i, line in enumerate(units):
+            for j, word in enumerate(line):
+                for replacement in _word_mutations(word):
+                    yield join(units[:i] + [line[:j] + [replacement] + line[j + 1:]] + units[i + 1:])
+            for j in range(len(line)):
+                yield join(units[:i] + [line[:j] + line[j + 1:]] + units[i + 1:])
+                yield join(units[:i] + [line[:j + 1] + line[j:]] + units[i + 1:])
+                if j + 1 < len(line):
+                    swapped = line[:j] + [line[j + 1], line[j]] + line[j + 2:]
+                    yield join(units[:i] + [swapped] + units[i + 1:])
+        if not feed_as_arguments:
+            for i in range(len(units)):
+                yield join(units[:i] + units[i + 1:])
+                yield join(units[:i + 1] + units[i:])
+
+
+def _word_mutations(word: str) -> list[str]:
+    try:
+        value = int(word)
+    except ValueError:
+        mutations = ['', word[:1], word * 2, word.upper(), word[::-1]]
+    else:
+        mutations = [str(v) for v in (value + 1, value - 1, -value, value * 2, *INTEGER_MUTATIONS)]
+    return [m for m in dict.fromkeys(mutations) if m != word]
@@ -0,0 +1,76 @@
+import json
+import os
+
+from sactor import logging as sactor_logging
+from sactor import rust_ast_parser, utils
+
+from .fuzz_target_generator import FuzzTargetGenerator
+
+logger = sactor_logging.get_logger(__name__)
+
+
+class DifferentialDriverGenerator(FuzzTargetGenerator):
+    """
+    Emits a standalone crate that calls one translated library function and
+    the original C function on the same fixed arguments, and reports which
+    samples give different results or leave different buffers and structs
+    behind mutable pointers.
+
+    Unlike whole-program tests this needs no `main`: the C reference is the
+    same renamed static library the fuzz target links against, and the
+    samples are JSON objects mapping parameter names to argument values.
+    """
+
+    def generate(self, function_name: str, samples: list[dict]) -> str:  # type: ignore[override]
+        """Writes the driver crate for `function_name` and returns its directory."""
+        rust_code = rust_ast_parser.expose_function_to_c(self.rust_code, function_name)
+        driver = rust_ast_parser.generate_differential_driver(
+            rust_code, function_name, self.C_PREFIX)
+
+        crate_dir = os.path.join(self.out_dir, function_name)
+        os.makedirs(os.path.join(crate_dir, "src"), exist_ok=True)
+        utils.save_code(os.path.join(crate_dir, "src", "main.rs"), driver["code"])
+        with open(os.path.join(crate_dir, "samples.json"), "w", encoding="utf-8") as fh:
+            json.dump(samples, fh, indent=4)
+        self._write_manifest(crate_dir, function_name, driver["code"])
+        self._write_build_script(crate_dir)
+        self._build_c_reference(crate_dir, driver["renamed"])
+
+        logger.info(
+            "Differential driver written to %s; run it with `cargo run --manifest-path %s/Cargo.toml`",
+            crate_dir, crate_dir)
+        return crate_dir
+
+    def _write_manifest(self, crate_dir: str, function_name: str, code: str) -> None:
+        manifest = [
+            "[package]",
+            f"name = \"{function_name.replace('_', '-')}-diff\"",
+            "version = \"0.0.0\"",
+            "publish = false",
+            "edition = \"2021\"",
+            "build = \"build.rs\"",
+            "",
+            "[dependencies]",
+            'libc = "0.2.159"',
+            *utils.optional_dependencies(code),
+            "",
+            "[workspace]",
+        ]
+        with open(os.path.join(crate_dir, "Cargo.toml"), "w", encoding="utf-8") as fh:
+            fh.write("\n".join(manifest) + "\n")
+
+    def run(self, crate_dir: str) -> dict:
+        """
+        Builds and runs the driver, returning `{"total", "diverged"}` with the
+        indices of the diverging samples and their assertion messages under
+        `"log"`.
+        """
+        res = utils.run_command(
+            ["cargo", "run", "--quiet", "--manifest-path", os.path.join(crate_dir, "Cargo.toml")])
+        lines = res.stdout.strip().splitlines()
+        try:
+            summary = json.loads(lines[-1])
+        except (IndexError, json.JSONDecodeError):
+            raise RuntimeError(f"The differential driver in {crate_dir} failed: {res.stderr}")
+        summary["log"] = res.stderr
+        return summary
@@ -8,6 +8,7 @@ from sactor import logging as sactor_logging
 from sactor import utils
 from sactor.llm import llm_factory
 
+from .coverage import BranchCoverage, mutate_inputs
 from .test_generator import TestGenerator
 from .test_generator_types import TestGeneratorResult
 
@@ -265,6 +266,57 @@ REMEMBER: i should start from 1.
         return TestGeneratorResult.SUCCESS
 
 
+    def extend_by_coverage(self, max_rounds=None, max_new_samples=None) -> int:
+        """
+        Adds mutated variants of the current samples that take C branches no
+        sample has taken yet, round after round until a round adds nothing.
+        Returns the number of samples added.
+        """
+        generator_config = self.config['test_generator']
+        if max_rounds is None:
+            max_rounds = generator_config.get('coverage_max_rounds', 5)
+        if max_new_samples is None:
+            max_new_samples = generator_config.get('coverage_max_new_samples', 20)
+
+        coverage = BranchCoverage(self.file_path, self.feed_as_arguments, self.timeout_seconds)
+        try:
+            covered: set = set()
+            for sample in self.test_samples:
+                covered |= coverage.covered(sample)
+            logger.info("Samples cover %d of %d branches", len(covered), coverage.total)
+
+            added = 0
+            tried = set(self.test_samples)
+            for round_number in range(max_rounds):
+                improved = False
+                for candidate in mutate_inputs(sorted(self.test_samples), self.feed_as_arguments):
+                    if added >= max_new_samples:
+                        break
+                    if not candidate.strip() or candidate in tried:
+                        continue
+                    tried.add(candidate)
+                    branches = coverage.covered(candidate)
+                    if branches <= covered:
+                        continue
+                    try:
+                        # same validity check as LLM samples: clean exit under valgrind
+                        output = self._execute_test_sample(candidate)
+                    except ValueError:
+                        continue
+                    covered |= branches
+                    self.test_samples.add(candidate)
+                    self.test_samples_output.append({"input": candidate, "output": output})
+                    added += 1
+                    improved = True
+                logger.info(
+                    "Coverage round %d: %d of %d branches covered, %d samples added",
+                    round_number + 1, len(covered), coverage.total, added)
+                if not improved or added >= max_new_samples:
+                    break
+            return added
+        finally:
+            coverage.cleanup()
+
     @override
     def generate_tests(self, count) -> TestGeneratorResult:
         return self._generate_test_impl(count)
@@ -0,0 +1,131 @@
+import os
+import shlex
+from typing import Optional, Sequence
+
+from sactor import logging as sactor_logging
+from sactor import rust_ast_parser, utils
+
+logger = sactor_logging.get_logger(__name__)
+
+
+class FuzzTargetGenerator:
+    """
+    Emits a `cargo-fuzz` crate that differentially fuzzes one translated
+    function against the original C function.
+
+    The C file is compiled into a static library with every function the
+    translation defines renamed to `C_PREFIX + name` (and `main` hidden), so
+    both versions link into the same fuzz binary.
+    """
+
+    C_PREFIX = "sactor_c_"
+
+    def __init__(
+        self,
+        c_file: str,
+        rust_code: str,
+        out_dir: str,
+        compile_commands_file: str = "",
+        extra_compile_command: Optional[str] = None,
+        link_args: Optional[Sequence[str]] = None,
+    ):
+        self.c_file = os.path.abspath(c_file)
+        self.rust_code = rust_code
+        self.out_dir = os.path.abspath(out_dir)
+        self.compile_commands_file = compile_commands_file
+        self.extra_compile_command = extra_compile_command
+        self.link_args = list(link_args or [])
+
+    def generate(self, function_name: str) -> str:
+        """Writes the fuzz crate for `function_name` and returns its directory."""
+        rust_code = rust_ast_parser.expose_function_to_c(self.rust_code, function_name)
+        target = rust_ast_parser.generate_fuzz_target(rust_code, function_name, self.C_PREFIX)
+
+        crate_dir = os.path.join(self.out_dir, function_name)
+        os.makedirs(os.path.join(crate_dir, "fuzz_targets"), exist_ok=True)
+        utils.save_code(
+            os.path.join(crate_dir, "fuzz_targets", f"{function_name}.rs"), target["code"])
+        self._write_manifest(crate_dir, function_name, target["code"])
+        self._write_build_script(crate_dir)
+        self._build_c_reference(crate_dir, target["renamed"])
+
+        logger.info(
+            "Fuzz target written to %s; run it with `cargo fuzz run --fuzz-dir %s %s`",
+            crate_dir, crate_dir, function_name)
+        return crate_dir
+
+    def _write_manifest(self, crate_dir: str, function_name: str, code: str) -> None:
+        dependencies = [
+            'libc = "0.2.159"',
+            'libfuzzer-sys = "0.4"',
+            'arbitrary = { version = "1", features = ["derive"] }',
+        ]
+        for dep in utils.optional_dependencies(code):
+            if not dep.startswith("arbitrary "):
+                dependencies.append(dep)
+        manifest = [
+            "[package]",
+            f"name = \"{function_name.replace('_', '-')}-fuzz\"",
+            "version = \"0.0.0\"",
+            "publish = false",
+            "edition = \"2021\"",
+            "build = \"build.rs\"",
+            "",
+            "[package.metadata]",
+            "cargo-fuzz = true",
+            "",
+            "[dependencies]",
+            *dependencies,
+            "",
+            "[[bin]]",
+            f"name = \"{function_name}\"",
+            f"path = \"fuzz_targets/{function_name}.rs\"",
+            "test = false",
+            "doc = false",
+            "bench = false",
+            "",
+            "[workspace]",
+        ]
+        with open(os.path.join(crate_dir, "Cargo.toml"), "w", encoding="utf-8") as fh:
+            fh.write("\n".join(manifest) + "\n")
+
+    def _write_build_script(self, crate_dir: str) -> None:
+        lines = [
+            "fn main() {",
+            '    println!("cargo:rustc-link-search=native={}/c_ref", env!("CARGO_MANIFEST_DIR"));',
+            '    println!("cargo:rustc-link-lib=static=sactor_c_ref");',
+        ]
+        for arg in self.link_args:
+            if arg.startswith("-l"):
+                lines.append(f'    println!("cargo:rustc-link-lib={arg[2:]}");')
+            elif arg.startswith("-L"):
+                lines.append(f'    println!("cargo:rustc-link-search={arg[2:]}");')
+        lines.append("}")
+        utils.save_code(os.path.join(crate_dir, "build.rs"), "\n".join(lines) + "\n")
+
+    def _build_c_reference(self, crate_dir: str, renamed: dict[str, str]) -> None:
+        c_ref_dir = os.path.join(crate_dir, "c_ref")
+        os.makedirs(c_ref_dir, exist_ok=True)
+        object_path = os.path.join(c_ref_dir, "c_ref.o")
+        defines = [f"-D{name}={new_name}" for name, new_name in sorted(renamed.items())]
+        defines.append(f"-Dmain={self.C_PREFIX}main")
+        extra = shlex.split(self.extra_compile_command) if self.extra_compile_command else []
+
+        commands = utils.load_compile_commands_from_file(
+            self.compile_commands_file, self.c_file)
+        commands = utils.process_commands_to_compile(commands, object_path, self.c_file)
+        if not commands:
+            commands = [[utils.get_compiler(), "-c", self.c_file, "-o", object_path]]
+        for command in commands:
+            if utils.is_compile_command(command):
+                command.extend([*defines, "-fPIC", *extra])
+            logger.debug("Compiling C reference: %s", command)
+            res = utils.run_command(command)
+            if utils.is_compile_command(command) and res.returncode != 0:
+                raise RuntimeError(
+                    f"Failed to compile the C reference {self.c_file}: {res.stderr}")
+
+        archive_path = os.path.join(c_ref_dir, "libsactor_c_ref.a")
+        if os.path.exists(archive_path):
+            os.remove(archive_path)
+        utils.run_command(["ar", "rcs", archive_path, object_path], check=True)
@@ -1,18 +1,40 @@
 import difflib
 import os
 import json
+import math
+import re
 import subprocess
+import tempfile
 from typing import override, Optional
 
 from sactor import logging as sactor_logging
 from sactor import utils
 
+from .minimizer import InputMinimizer
 from .test_runner import TestRunner
 from .test_runner_types import TestRunnerResult
 
 
 logger = sactor_logging.get_logger(__name__)
 
+# integers, decimals and exponent forms, plus the inf/nan spellings of printf and Rust
+NUMBER_PATTERN = re.compile(
+    r'[-+]?(?:(?:\d+\.\d*|\.\d+|\d+)(?:[eE][-+]?\d+)?|\b(?:inf(?:inity)?|nan)\b)',
+    re.IGNORECASE,
+)
+
+# named normalizers a test sample can list in `normalizers`, as (pattern, replacement) pairs
+BUILTIN_NORMALIZERS: dict[str, list[tuple[str, str]]] = {
+    'timestamps': [
+        (r'\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?', '<TIMESTAMP>'),
+        (r'\b\d{1,2}:\d{2}:\d{2}(?:\.\d+)?\b', '<TIME>'),
+    ],
+    # `%p` prints `(nil)` for NULL where Rust's `{:p}` prints `0x0`
+    'addresses': [(r'\b0x[0-9a-fA-F]+\b|\(nil\)', '<ADDR>')],
+    'temp_paths': [(r'/tmp/[^\s\'":]+', '<TMP>')],
+    'whitespace': [(r'[ \t]+', ' ')],
+}
+
 
 class ExecutableTestRunner(TestRunner):
     def __init__(
@@ -21,6 +43,11 @@ class ExecutableTestRunner(TestRunner):
         target: str,
         config_path=None,
         feed_as_arguments=True,
+        valgrind=False,
+        valgrind_suppressions: Optional[str] = None,
+        abs_tolerance: Optional[float] = None,
+        rel_tolerance: Optional[float] = None,
+        minimize_reference: Optional[str] = None,
     ):
         super().__init__(
             test_samples_path=test_samples_path,
@@ -28,10 +55,123 @@ class ExecutableTestRunner(TestRunner):
             config_path=config_path,
         )
         self.feed_as_arguments = feed_as_arguments
+        self.valgrind = valgrind
+        if valgrind_suppressions is None:
+            valgrind_suppressions = self.config['test_runner'].get('valgrind_suppressions', '')
+        self.valgrind_suppressions = valgrind_suppressions
+        self.valgrind_summary: Optional[dict] = None
+        if abs_tolerance is None:
+            abs_tolerance = self.config['test_runner'].get('float_abs_tolerance', 0.0)
+        if rel_tolerance is None:
+            rel_tolerance = self.config['test_runner'].get('float_rel_tolerance', 0.0)
+        self.abs_tolerance = float(abs_tolerance)
+        self.rel_tolerance = float(rel_tolerance)
+        self.normalizers: list = list(self.config['test_runner'].get('normalizers', []))
+        # the original C executable; when set, failing samples are minimized against it
+        self.minimize_reference = minimize_reference
+        self.minimize_max_runs = self.config['test_runner'].get('minimize_max_runs', 200)
+        self.minimized: Optional[dict] = None
+
+    def _valgrind_cmd(self, log_path: str) -> list[str]:
+        cmd = [
+            'valgrind',
+            '--tool=memcheck',
+            '--leak-check=full',
+            # keep memcheck output out of the compared program output
+            f'--log-file={log_path}',
+        ]
+        if self.valgrind_suppressions:
+            cmd.append(f'--suppressions={os.path.abspath(self.valgrind_suppressions)}')
+        cmd.append('--')
+        return cmd
+
+    @staticmethod
+    def parse_valgrind_log(log: str) -> dict:
+        """
+        Summarizes a memcheck log: the error count, invalid accesses by kind,
+        and leaked bytes by leak category.
+        """
+        def count(pattern: str) -> int:
+            return len(re.findall(pattern, log, re.MULTILINE))
+
+        def lost_bytes(kind: str) -> int:
+            match = re.search(rf'{kind} lost: ([\d,]+) bytes', log)
+            return int(match.group(1).replace(',', '')) if match else 0
+
+        error_match = re.search(r'ERROR SUMMARY: ([\d,]+) errors', log)
+        return {
+            'errors': int(error_match.group(1).replace(',', '')) if error_match else 0,
+            'invalid_reads': count(r'Invalid read of size'),
+            'invalid_writes': count(r'Invalid write of size'),
+            'invalid_frees': count(r'Invalid free\(\)|Mismatched free\(\)'),
+            'uninitialised_values': count(r'uninitialised value'),
+            'definitely_lost_bytes': lost_bytes('definitely'),
+            'indirectly_lost_bytes': lost_bytes('indirectly'),
+            'possibly_lost_bytes': lost_bytes('possibly'),
+        }
+
+    @staticmethod
+    def numeric_outputs_match(actual: str, expected: str, abs_tolerance: float, rel_tolerance: float) -> bool:
+        """
+        Compares two outputs, allowing floating-point tokens to differ by up to
+        `abs_tolerance`, or `rel_tolerance` relative to the larger magnitude.
+        The text around the numbers and integer tokens must match exactly.
+        """
+        actual_numbers = NUMBER_PATTERN.findall(actual)
+        expected_numbers = NUMBER_PATTERN.findall(expected)
+        if len(actual_numbers) != len(expected_numbers):
+            return False
+        if NUMBER_PATTERN.split(actual) != NUMBER_PATTERN.split(expected):
+            return False
+
+        for actual_token, expected_token in zip(actual_numbers, expected_numbers):
+            if actual_token == expected_token:
+                continue
+            if actual_token.lstrip('+-').isdigit() and expected_token.lstrip('+-').isdigit():
+                # integers are never approximated
+                return False
+            a, b = float(actual_token), float(expected_token)
+            if math.isnan(a) or math.isnan(b):
+                # C prints `-nan` where Rust prints `NaN`
+                if math.isnan(a) and math.isnan(b):
+                    continue
+                return False
+            if math.isinf(a) or math.isinf(b):
+                if a == b:
+                    continue
+                return False
+            if abs(a - b) > max(abs_tolerance, rel_tolerance * max(abs(a), abs(b))):
+                return False
+        return True
+
+    @staticmethod
+    def normalize_output(output: str, normalizers: list) -> str:
+        """
+        Applies output normalizers in order. Each is the name of a built-in
+        normalizer (see `BUILTIN_NORMALIZERS`) or a sed-like
+        `{"pattern": ..., "replacement": ...}` regex substitution.
+        """
+        for normalizer in normalizers:
+            if isinstance(normalizer, str):
+                if normalizer not in BUILTIN_NORMALIZERS:
+                    raise ValueError(
+                        f'Unknown output normalizer {normalizer!r}, expected one of {sorted(BUILTIN_NORMALIZERS)}')
+                substitutions = BUILTIN_NORMALIZERS[normalizer]
+            elif isinstance(normalizer, dict) and isinstance(normalizer.get('pattern'), str):
+                substitutions = [(normalizer['pattern'], normalizer.get('replacement', ''))]
+            else:
+                raise ValueError(
+                    f'Invalid output normalizer {normalizer!r}: expected a name or {{"pattern": ..., "replacement": ...}}')
+            for pattern, replacement in substitutions:
+                output = re.sub(pattern, replacement, output, flags=re.MULTILINE)
+        return output
 
     def _compare_outputs(self, actual: str, expected: str) -> tuple[TestRunnerResult, Optional[str]]:
         if actual == expected:
             return TestRunnerResult.PASSED, None
+        if (self.abs_tolerance or self.rel_tolerance) and self.numeric_outputs_match(
+                actual, expected, self.abs_tolerance, self.rel_tolerance):
+            return TestRunnerResult.PASSED, None
 
         differ = difflib.Differ()
         diff = list(differ.compare(actual.splitlines(), expected.splitlines()))
@@ -51,6 +191,10 @@ class ExecutableTestRunner(TestRunner):
         while len(current_data) <= test_sample_number:
             current_data.append({})
         current_data[test_sample_number]['input'] = self.test_samples_output[test_sample_number]['input']
+        if self.valgrind_summary is not None:
+            current_data[test_sample_number]['valgrind'] = self.valgrind_summary
+        if self.minimized is not None:
+            current_data[test_sample_number]['minimized'] = self.minimized
         current_data[test_sample_number]['expected_output'] = expected_output
         if compare_result[0] == TestRunnerResult.PASSED:
             current_data[test_sample_number]['actual_output'] = expected_output
@@ -63,6 +207,65 @@ class ExecutableTestRunner(TestRunner):
         with open(save_path, 'w') as f:
             json.dump(current_data, f, indent=4)
 
+    def _execute(self, executable: str, test_sample: dict, sample_input: str, prefix=()) -> utils.ProcessResult:
+        options = utils.TaskRunOptions.from_task(test_sample)
+        run_options = {
+            'timeout': options.timeout or self.timeout_seconds,
+            'cwd': options.resolve_cwd(self.test_samples_dir) if options.cwd else None,
+            'env': options.merge_env(None),
+            'memory_limit_mb': options.memory_limit_mb,
+        }
+        if self.feed_as_arguments:
+            feed_input_str = f'{executable} {sample_input}'
+            cmd = list(prefix) + feed_input_str.split()
+            return utils.run_command(cmd, **run_options)
+        cmd = list(prefix) + [executable]
+        return utils.run_command(
+            cmd,
+            input_data=f"{sample_input}\n",
+            **run_options,
+        )
+
+    def _normalized_output(self, test_sample: dict, output: str | utils.ProcessResult) -> str:
+        if isinstance(output, utils.ProcessResult):
+            output = utils.normalize_string(output.stdout + output.stderr)
+        normalizers = self.normalizers + test_sample.get('normalizers', [])
+        if normalizers:
+            # applied to both sides, so volatile output such as PIDs or times is comparable
+            output = self.normalize_output(output, normalizers)
+        return output
+
+    def _minimize(self, test_sample: dict) -> Optional[dict]:
+        """
+        Shrinks the input of a failing sample while the target's output still
+        differs from the reference's, and returns the minimal reproducer.
+        """
+        outputs: dict[str, tuple[str, str]] = {}
+
+        def diverges(sample_input: str) -> bool:
+            try:
+                expected = self._normalized_output(
+                    test_sample, self._execute(self.minimize_reference, test_sample, sample_input))
+                actual = self._normalized_output(
+                    test_sample, self._execute(self.target, test_sample, sample_input))
+            except subprocess.TimeoutExpired:
+                return False
+            outputs[sample_input] = (expected, actual)
+            return self._compare_outputs(actual, expected)[0] == TestRunnerResult.FAILED
+
+        minimizer = InputMinimizer(diverges, self.feed_as_arguments, self.minimize_max_runs)
+        minimized_input = minimizer.minimize(test_sample['input'])
+        if minimized_input not in outputs:
+            logger.warning('The failure does not reproduce against %s; not minimizing', self.minimize_reference)
+            return None
+        expected, actual = outputs[minimized_input]
+        return {
+            'input': minimized_input,
+            'expected_output': expected,
+            'actual_output': actual,
+            'runs': minimizer.checks,
+        }
+
     @override
     def run_test(self, test_sample_number: int, save_path=None) -> tuple[TestRunnerResult, Optional[str]]:
         len_test_samples_output = len(self.test_samples_output)
@@ -73,31 +276,38 @@ class ExecutableTestRunner(TestRunner):
         test_sample_input = test_sample['input']
         test_sample_output = test_sample['output']
 
+        prefix = []
+        log_path = None
+        if self.valgrind:
+            fd, log_path = tempfile.mkstemp(prefix='sactor_valgrind_', suffix='.log')
+            os.close(fd)
+            prefix = self._valgrind_cmd(log_path)
+
         try:
-            if self.feed_as_arguments:
-                feed_input_str = f'{self.target} {test_sample_input}'
-                cmd = feed_input_str.split()
-                result = utils.run_command(
-                    cmd,
-                    timeout=self.timeout_seconds,
-                )
-            else:
-                cmd = self.target
-                result = utils.run_command(
-                    cmd,
-                    timeout=self.timeout_seconds,
-                    input_data=f"{test_sample_input}\n",
-                )
+            result = self._execute(self.target, test_sample, test_sample_input, prefix)
         except subprocess.TimeoutExpired as e:
             logger.error('Test %d timed out: %s', test_sample_number, e)
             raise ValueError(f'Test {test_sample_number} timed out: {e}')
+        finally:
+            if log_path is not None:
+                with open(log_path, errors='replace') as f:
+                    self.valgrind_summary = self.parse_valgrind_log(f.read())
+                os.remove(log_path)
+
+        if self.valgrind_summary is not None and self.valgrind_summary['errors']:
+            logger.warning(
+                'Valgrind reported %d memory errors in test %d: %s',
+                self.valgrind_summary['errors'], test_sample_number, self.valgrind_summary)
 
 
-        target_output = utils.normalize_string(
-            result.stdout + result.stderr)
+        target_output = self._normalized_output(test_sample, result)
+        test_sample_output = self._normalized_output(test_sample, test_sample_output)
 
         # compare target output with expected output
         compare_result = self._compare_outputs(target_output, test_sample_output)
+        self.minimized = None
+        if compare_result[0] == TestRunnerResult.FAILED and self.minimize_reference:
+            self.minimized = self._minimize(test_sample)
         if save_path:
             self._save_test_outputs(
                 save_path,
@@ -0,0 +1,119 @@
+import re
+from typing import Callable
+
+from sactor import logging as sactor_logging
+
+logger = sactor_logging.get_logger(__name__)
+
+INTEGER_PATTERN = re.compile(r'[-+]?\d+')
+
+
+class InputMinimizer:
+    """
+    Delta-debugging minimizer for a failing test sample. It shrinks the input
+    while `diverges` still holds for it: first by removing arguments (or stdin
+    lines and the words in them), then by bisecting integers towards zero and
+    shortening the remaining strings. At most `max_checks` candidates are run.
+    """
+
+    def __init__(self, diverges: Callable[[str], bool], feed_as_arguments: bool, max_checks: int = 200):
+        self.diverges = diverges
+        self.feed_as_arguments = feed_as_arguments
+        self.max_checks = max_checks
+        self.checks = 0
+        self._cache: dict[str, bool] = {}
+
+    def _test(self, sample_input: str) -> bool:
+        if sample_input in self._cache:
+            return self._cache[sample_input]
+        if self.checks >= self.max_checks:
+            return False
+        self.checks += 1
+        result = self.diverges(sample_input)
+        self._cache[sample_input] = result
+        return result
+
+    def _join(self, lines: list[list[str]]) -> str:
+        # words shortened to nothing are dropped
+        if self.feed_as_arguments:
+            return ' '.join(word for line in lines for word in line if word)
+        return '\n'.join(' '.join(word for word in line if word) for line in lines)
+
+    def minimize(self, sample_input: str) -> str:
+        if self.feed_as_arguments:
+            lines = [sample_input.split()]
+        else:
+            lines = [line.split() for line in sample_input.split('\n')]
+        if not self._test(self._join(lines)):
+            # flaky or not reproducible; nothing to preserve
+            return sample_input
+
+        if not self.feed_as_arguments:
+            lines = ddmin(lines, lambda candidate: self._test(self._join(candidate)))
+        for i in range(len(lines)):
+            lines[i] = ddmin(
+                lines[i], lambda candidate: self._test(self._join(lines[:i] + [candidate] + lines[i + 1:])))
+
+        for i, line in enumerate(lines):
+            for j in range(len(line)):
+                def with_word(word: str) -> bool:
+                    candidate = [list(words) for words in lines]
+                    candidate[i][j] = word
+                    return self._test(self._join(candidate))
+
+                if INTEGER_PATTERN.fullmatch(line[j]):
+                    line[j] = shrink_integer(line[j], with_word)
+                else:
+                    line[j] = ''.join(ddmin(list(line[j]), lambda chars: with_word(''.join(chars))))
+
+        minimized = self._join(lines)
+        logger.info('Minimized failing input from %d to %d characters in %d runs',
+                    len(sample_input), len(minimized), self.checks)
+        return minimized
+
+
+def ddmin(items: list, test: Callable[[list], bool]) -> list:
+    """
+    Zeller's ddmin over `items`: returns a 1-minimal sublist for which `test`
+    holds, assuming it holds for `items` itself. Empty results are kept only
+    for lists whose elements can all be dropped.
+    """
+    granularity = 2
+    while len(items) >= 2:
+        chunk = max(1, len(items) // granularity)
+        subsets = [items[i:i + chunk] for i in range(0, len(items), chunk)]
+        reduced = False
+        for k, subset in enumerate(subsets):
+            complement = [x for s in subsets[:k] + subsets[k + 1:] for x in s]
+            if test(subset):
+                items, granularity, reduced = subset, 2, True
+                break
+            if len(subsets) > 2 and test(complement):
+                items, granularity, reduced = complement, max(granularity - 1, 2), True
+                break
+        if not reduced:
+            if granularity >= len(items):
+                break
+            granularity = min(granularity * 2, len(items))
+    if len(items) == 1 and test([]):
+        return []
+    return items
+
+
+def shrink_integer(token: str, test: Callable[[str], bool]) -> str:
+    """Bisects an integer token towards zero while `test` still holds."""
+    value = int(token)
+    if value == 0:
+        return token
+    sign = -1 if value < 0 else 1
+    if test('0'):
+        return '0'
+    # invariant: `hi` diverges, `lo` does not
+    lo, hi = 0, abs(value)
+    while hi - lo > 1:
+        mid = (lo + hi) // 2
+        if test(str(sign * mid)):
+            hi = mid
+        else:
+            lo = mid
+    return str(sign * hi)
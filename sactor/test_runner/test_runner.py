@@ -1,4 +1,5 @@
 import json
+import os
 from abc import ABC, abstractmethod
 from typing import Optional
 
@@ -12,6 +13,7 @@ class TestRunner(ABC):
     def __init__(self, test_samples_path: str, target, config_path=None):
         content = read_file(test_samples_path)
         self.test_samples_output: list[dict] = json.loads(content)
+        self.test_samples_dir = os.path.dirname(os.path.abspath(test_samples_path))
 
         self.config = utils.try_load_config(config_path)
         self.timeout_seconds = self.config['test_runner']['timeout_seconds']
@@ -1,5 +1,6 @@
 import json
 import os
+import time
 from typing import Optional
 
 from sactor import logging as sactor_logging, utils
@@ -8,6 +9,9 @@ from sactor.c_parser.project_index import (
     order_translation_units_by_dependencies,
 )
 from sactor.combiner import ProjectCombiner, TuArtifact
+from sactor.llm import CostTracker, ReplayBundle
+from sactor.llm.cost import COST_REPORT_FILE
+from sactor.translator import run_summary
 from sactor.translator.translator_types import TranslateBatchResult
 
 logger = sactor_logging.get_logger(__name__)
@@ -32,6 +36,8 @@ def run_translate_batch(
     executable_object,
     link_args: str,
     llm_stat: str | None,
+    resume: bool = False,
+    replay_bundle: ReplayBundle | None = None,
 ) -> TranslateBatchResult:
     translation_units = utils.list_c_files_from_compile_commands(compile_commands_file)
     translation_units = order_translation_units_by_dependencies(
@@ -44,7 +50,10 @@ def run_translate_batch(
     combined_root = os.path.join(base_result_dir, "combined")
     ProjectCombiner.cleanup_combined_root(combined_root, translation_units)
 
+    started_at = time.time()
     any_failed = False
+    # one budget for the whole project
+    cost_tracker = CostTracker(config)
     run_unidiomatic_phase = not idiomatic_only
     run_idiomatic_phase = not unidiomatic_only
     # Pre-create per-TU result slots
@@ -116,6 +125,9 @@ def run_translate_batch(
             project_struct_usr_to_result_dir=project_struct_usr_to_result_dir,
             project_enum_usr_to_result_dir=project_enum_usr_to_result_dir,
             project_global_usr_to_result_dir=project_global_usr_to_result_dir,
+            resume=resume,
+            cost_tracker=cost_tracker,
+            replay_bundle=replay_bundle,
         )
 
     # Detect stubbed runner in tests (e.g., tests/test_translate_batch.py)
@@ -155,6 +167,24 @@ def run_translate_batch(
             any_failed = True
         return crate_dir
 
+    def _write_summaries() -> list[dict]:
+        summary = [{k: v for k, v in meta.items() if not str(k).startswith("_")} for meta in per_tu.values()]
+        summary_path = os.path.join(base_result_dir, "batch_summary.json")
+        with open(summary_path, "w", encoding="utf-8") as handle:
+            json.dump(summary, handle, indent=2)
+        logger.info("Batch summary written to %s", summary_path)
+        cost_tracker.write_report(os.path.join(base_result_dir, COST_REPORT_FILE))
+        # the summary.json of every unit, merged into the one of the project
+        units = []
+        for meta in per_tu.values():
+            unit_units = run_summary.load_units(os.path.join(str(meta["result_dir"]), run_summary.SUMMARY_FILE))
+            units.extend(unit_units or [
+                run_summary.unit_summary(str(meta["input"]), str(meta["result_dir"]), [], meta["error"])])  # type: ignore[arg-type]
+        run_summary.write_summary(
+            os.path.join(base_result_dir, run_summary.SUMMARY_FILE),
+            run_summary.run_summary(units, started_at, time.time(), cost_tracker.report()["total"]))
+        return summary
+
     # Phase 1: unidiomatic for all TUs (unless idiomatic_only)
     if run_unidiomatic_phase:
         for tu_path in translation_units:
@@ -190,22 +220,14 @@ def run_translate_batch(
 
         # If phase 1 had failures and continue flag is not set, stop here
         if any_failed and not continue_run_when_incomplete:
-            summary = [{k: v for k, v in meta.items() if not str(k).startswith("_")} for meta in per_tu.values()]
-            summary_path = os.path.join(base_result_dir, "batch_summary.json")
-            with open(summary_path, "w", encoding="utf-8") as handle:
-                json.dump(summary, handle, indent=2)
-            logger.info("Batch summary written to %s", summary_path)
+            summary = _write_summaries()
             return TranslateBatchResult(entries=summary, any_failed=True, base_result_dir=base_result_dir, combined_dir=combined_root)
 
     # Phase 2: idiomatic (unless unidiomatic_only)
     if run_idiomatic_phase:
         if is_stub_mode and run_unidiomatic_phase:
             # In stub mode, the unidiomatic pass already created both artefacts.
-            summary = [{k: v for k, v in meta.items() if not str(k).startswith("_")} for meta in per_tu.values()]
-            summary_path = os.path.join(base_result_dir, "batch_summary.json")
-            with open(summary_path, "w", encoding="utf-8") as handle:
-                json.dump(summary, handle, indent=2)
-            logger.info("Batch summary written to %s", summary_path)
+            summary = _write_summaries()
             return TranslateBatchResult(entries=summary, any_failed=any_failed, base_result_dir=base_result_dir, combined_dir=combined_root)
 
         eligible_units = translation_units
@@ -243,11 +265,7 @@ def run_translate_batch(
             any_failed = True
             logger.error("Idiomatic ProjectCombiner failed: %s", exc, exc_info=True)
 
-    summary = [{k: v for k, v in meta.items() if not str(k).startswith("_")} for meta in per_tu.values()]
-    summary_path = os.path.join(base_result_dir, "batch_summary.json")
-    with open(summary_path, "w", encoding="utf-8") as handle:
-        json.dump(summary, handle, indent=2)
-    logger.info("Batch summary written to %s", summary_path)
+    summary = _write_summaries()
 
     return TranslateBatchResult(
         entries=summary,
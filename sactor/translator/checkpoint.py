@@ -0,0 +1,117 @@
+import json
+import os
+from typing import Optional
+
+from sactor import logging as sactor_logging
+
+logger = sactor_logging.get_logger(__name__)
+
+CHECKPOINT_FILE = "checkpoint.json"
+CHECKPOINT_VERSION = 1
+
+# result subdirectory holding the translations of each item type
+ITEM_DIRS = {
+    "function": "functions",
+    "struct": "structs",
+    "enum": "enums",
+    "global_var": "global_vars",
+}
+
+
+def _no_tokens() -> dict:
+    return {"queries": 0, "input_tokens": 0, "output_tokens": 0}
+
+
+class Checkpoint:
+    """
+    Pipeline state of a translation, saved to `checkpoint.json` in the result
+    directory after every translated item: per-stage item status and attempt
+    counts, the accepted translations, completed stages and spent tokens.
+    `sactor translate --resume` loads it to continue an interrupted run.
+    """
+
+    def __init__(self, path: str, input_file: str):
+        self.path = path
+        self.input_file = input_file
+        self.stages: dict[str, dict] = {}
+        # state of the interrupted run, unchanged while this one updates `stages`
+        self.resumed = False
+        self._resumed_stages: dict[str, dict] = {}
+
+    @classmethod
+    def load(cls, path: str, input_file: str) -> "Checkpoint":
+        """Loads the checkpoint of an interrupted run, or starts a new one when there is none."""
+        checkpoint = cls(path, input_file)
+        if not os.path.isfile(path):
+            logger.warning("No checkpoint found at %s, starting a new translation", path)
+            return checkpoint
+        with open(path) as f:
+            data = json.load(f)
+        if data.get("version") != CHECKPOINT_VERSION:
+            raise ValueError(f"Unsupported checkpoint version in {path}: {data.get('version')}")
+        if os.path.abspath(data.get("input_file", "")) != os.path.abspath(input_file):
+            raise ValueError(
+                f"Checkpoint {path} belongs to the translation of {data.get('input_file')}, not {input_file}")
+        checkpoint.stages = data.get("stages", {})
+        checkpoint.resumed = True
+        checkpoint._resumed_stages = json.loads(json.dumps(checkpoint.stages))
+        logger.info("Resuming from checkpoint %s", path)
+        return checkpoint
+
+    def _stage(self, stage: str) -> dict:
+        return self.stages.setdefault(stage, {"completed": False, "items": {}, "tokens": _no_tokens()})
+
+    def resumed_item(self, stage: str, name: str) -> Optional[dict]:
+        """The state the interrupted run left `name` in, None when it did not get to it."""
+        return self._resumed_stages.get(stage, {}).get("items", {}).get(name)
+
+    def restart_item(self, stage: str, name: str) -> None:
+        """Forgets the state of `name`, whose translation starts over."""
+        self._resumed_stages.get(stage, {}).get("items", {}).pop(name, None)
+        self.stages.get(stage, {}).get("items", {}).pop(name, None)
+
+    def is_completed(self, stage: str) -> bool:
+        return self.stages.get(stage, {}).get("completed", False)
+
+    def complete_stage(self, stage: str) -> None:
+        self._stage(stage)["completed"] = True
+
+    def record_stage(self, stage: str, failure_info: dict, llm) -> None:
+        """
+        Records the items of a translator's failure info and the tokens the
+        stage spent: those of the interrupted run plus the `llm` statistics of
+        this one, which are reset at the start of each stage.
+        """
+        state = self._stage(stage)
+        result_dir = os.path.dirname(self.path)
+        for name, info in failure_info.items():
+            item_type = info.get("type")
+            translation = None
+            if item_type in ITEM_DIRS:
+                relative = os.path.join(f"translated_code_{stage}", ITEM_DIRS[item_type], f"{name}.rs")
+                if os.path.isfile(os.path.join(result_dir, relative)):
+                    translation = relative
+            state["items"][name] = {
+                "type": item_type,
+                "status": info.get("status", "untranslated"),
+                "attempts": sum(info.get("attempts", [])),
+                "translation": translation,
+            }
+        spent = self._resumed_stages.get(stage, {}).get("tokens", _no_tokens())
+        state["tokens"] = {
+            "queries": spent["queries"] + len(llm.costed_input_tokens),
+            "input_tokens": spent["input_tokens"] + sum(llm.costed_input_tokens),
+            "output_tokens": spent["output_tokens"] + sum(llm.costed_output_tokens),
+        }
+
+    def save(self) -> None:
+        os.makedirs(os.path.dirname(self.path) or ".", exist_ok=True)
+        # written aside and renamed so a crash never leaves a truncated checkpoint
+        tmp_path = self.path + ".tmp"
+        with open(tmp_path, "w") as f:
+            json.dump({
+                "version": CHECKPOINT_VERSION,
+                "input_file": self.input_file,
+                "stages": self.stages,
+            }, f, indent=4)
+        os.replace(tmp_path, self.path)
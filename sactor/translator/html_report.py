@@ -0,0 +1,164 @@
+import difflib
+import html
+import os
+from typing import Optional
+
+from sactor import rust_ast_parser
+
+from .checkpoint import ITEM_DIRS
+
+REPORT_FILE = "report.html"
+STAGES = ("unidiomatic", "idiomatic")
+
+_STYLE = """
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+pre { margin: 0; font-size: 0.85em; white-space: pre-wrap; }
+.passed { color: #1a7f37; }
+.failed { color: #cf222e; }
+.code { display: grid; grid-template-columns: repeat(3, 1fr); gap: 1em; }
+.code > div { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+details { margin: 1em 0; border-top: 1px solid #ccc; padding-top: 0.5em; }
+summary { font-weight: bold; cursor: pointer; }
+table.diff { font-family: monospace; font-size: 0.85em; }
+.diff_add { background: #aaffaa; }
+.diff_chg { background: #ffff77; }
+.diff_sub { background: #ffaaaa; }
+"""
+
+
+def translated_code(result_dir: str, stage: str, item_type: str, name: str) -> Optional[str]:
+    """The translation of an item a stage saved in `result_dir`, None when there is none."""
+    if item_type not in ITEM_DIRS:
+        return None
+    path = os.path.join(result_dir, f"translated_code_{stage}", ITEM_DIRS[item_type], f"{name}.rs")
+    if not os.path.isfile(path):
+        return None
+    with open(path) as f:
+        return f.read()
+
+
+def unsafe_metrics(code: Optional[str]) -> Optional[dict]:
+    if code is None:
+        return None
+    try:
+        total, unsafe = rust_ast_parser.count_unsafe_tokens(code)
+    except Exception:  # pylint: disable=broad-except
+        # items that do not parse on their own, e.g. a lone global
+        return None
+    return {"tokens": total, "unsafe_tokens": unsafe, "unsafe_fraction": unsafe / total if total else 0.0}
+
+
+def _percent(metrics: Optional[dict]) -> str:
+    return "-" if metrics is None else f"{metrics['unsafe_fraction']:.1%}"
+
+
+def _status(result: Optional[dict]) -> str:
+    if result is None:
+        return "-"
+    css = "passed" if result["passed"] else "failed"
+    model = f", {html.escape(result['model'])}" if result.get("model") else ""
+    return (f"<span class=\"{css}\">{html.escape(result['status'])}</span> "
+            f"({result['attempts']} attempt(s){model})")
+
+
+def _code_block(title: str, code: Optional[str]) -> str:
+    body = html.escape(code) if code is not None else "<em>not available</em>"
+    return f"<div><strong>{html.escape(title)}</strong><pre>{body}</pre></div>"
+
+
+def _item_section(anchor: str, item_type: str, name: str, stage_results: dict, c_code: Optional[str],
+                  rust_code: dict, metrics: dict) -> str:
+    parts = [f"<details id=\"{anchor}\"><summary>{html.escape(item_type)} {html.escape(name)}</summary>"]
+    parts.append("<table><tr><th>Stage</th><th>Status</th><th>Duration</th><th>Unsafe</th><th>Last error</th></tr>")
+    for stage in STAGES:
+        result = stage_results.get(stage)
+        if result is None:
+            continue
+        duration = result.get("duration_seconds")
+        error = result.get("diff_excerpt")
+        parts.append(
+            f"<tr><td>{stage}</td><td>{_status(result)}</td>"
+            f"<td>{'-' if duration is None else f'{duration:.1f}s'}</td>"
+            f"<td>{_percent(metrics.get(stage))}</td>"
+            f"<td>{'' if error is None else f'<pre>{html.escape(error)}</pre>'}</td></tr>")
+    parts.append("</table>")
+    parts.append("<div class=\"code\">")
+    parts.append(_code_block("C", c_code))
+    parts.append(_code_block("Unidiomatic Rust", rust_code.get("unidiomatic")))
+    parts.append(_code_block("Idiomatic Rust", rust_code.get("idiomatic")))
+    parts.append("</div>")
+    if rust_code.get("unidiomatic") is not None and rust_code.get("idiomatic") is not None:
+        parts.append("<p><strong>Unidiomatic to idiomatic</strong></p>")
+        parts.append(difflib.HtmlDiff(wrapcolumn=80).make_table(
+            rust_code["unidiomatic"].splitlines(), rust_code["idiomatic"].splitlines(),
+            "unidiomatic", "idiomatic", context=False))
+    parts.append("</details>")
+    return "\n".join(parts)
+
+
+def render_html_report(title: str, results: list[dict], result_dir: str,
+                       c_sources: dict[tuple[str, str], str]) -> str:
+    """
+    Renders the results of a translation as a single HTML page: a summary
+    table with the status, attempts and unsafe share of every item per stage,
+    and for each item its C code next to the unidiomatic and idiomatic Rust,
+    with the last error and a side-by-side diff of the two translations.
+    `c_sources` maps (type, name) of the items to their C code.
+    """
+    items: dict[tuple[str, str], dict] = {}
+    for result in results:
+        items.setdefault((result["type"], result["name"]), {})[result["stage"]] = result
+
+    rows = []
+    sections = []
+    stage_totals = {stage: {"passed": 0, "total": 0, "tokens": 0, "unsafe_tokens": 0} for stage in STAGES}
+    for index, ((item_type, name), stage_results) in enumerate(items.items()):
+        anchor = f"item-{index}"
+        rust_code = {stage: translated_code(result_dir, stage, item_type, name) for stage in STAGES}
+        metrics = {stage: unsafe_metrics(code) for stage, code in rust_code.items()}
+        for stage, result in stage_results.items():
+            totals = stage_totals[stage]
+            totals["total"] += 1
+            totals["passed"] += result["passed"]
+            if metrics.get(stage) is not None:
+                totals["tokens"] += metrics[stage]["tokens"]
+                totals["unsafe_tokens"] += metrics[stage]["unsafe_tokens"]
+        cells = "".join(
+            f"<td>{_status(stage_results.get(stage))}</td><td>{_percent(metrics[stage])}</td>" for stage in STAGES)
+        rows.append(f"<tr><td><a href=\"#{anchor}\">{html.escape(name)}</a></td>"
+                    f"<td>{html.escape(item_type)}</td>{cells}</tr>")
+        sections.append(_item_section(anchor, item_type, name, stage_results,
+                                      c_sources.get((item_type, name)), rust_code, metrics))
+
+    summary = []
+    for stage in STAGES:
+        totals = stage_totals[stage]
+        if not totals["total"]:
+            continue
+        fraction = totals["unsafe_tokens"] / totals["tokens"] if totals["tokens"] else 0.0
+        summary.append(f"<li>{stage}: {totals['passed']} of {totals['total']} items passed, "
+                       f"{fraction:.1%} of the Rust tokens unsafe</li>")
+
+    header = "".join(f"<th>{stage.capitalize()}</th><th>Unsafe</th>" for stage in STAGES)
+    return "\n".join([
+        "<!DOCTYPE html>",
+        "<html><head><meta charset=\"utf-8\">",
+        f"<title>Sactor report: {html.escape(title)}</title>",
+        f"<style>{_STYLE}</style></head><body>",
+        f"<h1>Sactor report: {html.escape(title)}</h1>",
+        f"<ul>{''.join(summary)}</ul>",
+        f"<table><tr><th>Item</th><th>Type</th>{header}</tr>",
+        *rows,
+        "</table>",
+        *sections,
+        "</body></html>",
+    ])
+
+
+def write_html_report(path: str, title: str, results: list[dict], result_dir: str,
+                      c_sources: dict[tuple[str, str], str]) -> None:
+    os.makedirs(os.path.dirname(path) or ".", exist_ok=True)
+    with open(path, "w") as f:
+        f.write(render_html_report(title, results, result_dir, c_sources))
@@ -1,5 +1,6 @@
 import json
 import os
+import re
 import shutil
 from typing import Optional, override
 
@@ -8,6 +9,13 @@ import sactor.verifier as verifier
 from sactor import logging as sactor_logging, rust_ast_parser, utils
 from sactor.c_parser import (CParser, EnumInfo, EnumValueInfo, FunctionInfo,
                              GlobalVarInfo, StructInfo)
+from sactor.c_parser.concurrency import concurrency_plan, sync_global_code
+from sactor.c_parser.errno_api import errno_guidance
+from sactor.c_parser.goto_plan import goto_plan
+from sactor.c_parser.signals import signal_flag_global_code, signal_plan
+from sactor.c_parser.stdio_api import stdio_plan, uses_file_streams
+from sactor.combiner import CombineResult
+from sactor.combiner.partial_combiner import PartialCombiner
 from sactor.llm import LLM
 from sactor.thirdparty import Crown, CrownType
 from sactor.translator.idiomatic_fewshots import FUNCTION_FEWSHOTS, STRUCT_FEWSHOTS
@@ -57,6 +65,7 @@ class IdiomaticTranslator(Translator):
         )
         self.failure_info_path = os.path.join(
             self.result_path, "idiomatic_failure_info.json")
+        self.stage = "idiomatic"
         if os.path.isfile(self.failure_info_path):
             content = read_file(self.failure_info_path)
             self.failure_info = json.loads(content)
@@ -205,6 +214,210 @@ class IdiomaticTranslator(Translator):
         return None
 
     @override
+    def _void_pointer_guidance(self, code: str) -> str:
+        """Prompt text telling the model how to represent C `void *` values."""
+        if "c_void" not in code:
+            return ""
+        strategy = self.config['general'].get('void_pointer_strategy', 'keep')
+        if strategy == "usize":
+            return '''
+The code contains `void *` values. Represent each of them as an opaque `usize` address (or `Option<usize>` if it may be NULL) in the idiomatic translation.
+'''
+        if strategy == "any":
+            return '''
+The code contains `void *` values. Represent each of them as `Box<dyn std::any::Any>` (or `Option<Box<dyn std::any::Any>>` if it may be NULL) in the idiomatic translation and recover concrete data with `downcast_ref`/`downcast_mut`.
+'''
+        return '''
+The code contains `void *` values. Keep each of them as a raw `*mut core::ffi::c_void` (or `*const core::ffi::c_void`) in the idiomatic translation; do not guess what they point to.
+'''
+
+    def _pointer_ownership_guidance(self, function: FunctionInfo, unidiomatic_code: str) -> str:
+        """Prompt text classifying the raw pointers of the unidiomatic function as owning, borrowed or aliased."""
+        try:
+            pointers = rust_ast_parser.analyze_pointer_ownership(unidiomatic_code, function.name)
+        except Exception as e:
+            logger.debug("Pointer ownership analysis of %s failed: %s", function.name, e)
+            return ""
+        if not pointers:
+            return ""
+        lines = []
+        for pointer in pointers:
+            evidence = "; ".join(pointer["evidence"]) or "only accessed"
+            line = f"- `{pointer['name']}` ({pointer['kind']} `{pointer['type']}`): {pointer['ownership']}, {evidence}"
+            if pointer["suggested_type"]:
+                line += f"; use `{pointer['suggested_type']}`"
+            lines.append(line)
+        joined = "\n".join(lines)
+        return f'''
+The raw pointers of the function, classified by the ownership of what they point to: `owning` pointers allocate, free or hand over their pointee, `borrowed` ones only access it, and `aliased` ones share it with other pointers that are still used:
+{joined}
+Translate the parameters, return value and locals with the suggested types unless the code needs otherwise: an owning pointer becomes `Box<T>`, `Vec<T>` or `String`, a borrowed one a reference or slice (`Option` when it is checked for NULL), and an aliased one `Rc<RefCell<T>>`. In the SPEC, set `"ownership": "owning"` on the fields of owning pointers and `"transient"` on the others.
+'''
+
+    def _no_std_guidance(self) -> str:
+        """Prompt text for the `no_std` profile: the code must not need the standard library."""
+        if not utils.no_std_profile(self.config):
+            return ""
+        guidance = '''
+The translation targets an embedded `#![no_std]` crate with `alloc`. Use `core::` and `alloc::` paths instead of `std::` (`Vec`, `String`, `Box` and `BTreeMap` are available; `HashMap`, files, processes, threads and floating-point methods such as `sqrt` are not), and `core::ffi::CStr`/`alloc::ffi::CString` for C strings.
+'''
+        if self.config.get('no_std', {}).get('logger'):
+            guidance += "`print!`/`println!` output is routed to the board's logger.\n"
+        else:
+            guidance += "There is no console: do not print.\n"
+        return guidance
+
+    def _variadic_guidance(self, function: FunctionInfo, dependency_signatures: list[str]) -> str:
+        """Prompt text for variadic functions and their callers: `...` becomes `args: &[VarArg]`."""
+        calls_variadic = any(re.search(r'\bVarArg\b', sig) for sig in dependency_signatures)
+        if not function.is_variadic and not calls_variadic:
+            return ""
+        guidance = f'''
+Variadic arguments are passed as a slice of the following enum, which the system defines (do **NOT** define it):
+```rust
+{utils.VARARG_ENUM_CODE}```
+'''
+        if function.is_variadic:
+            guidance += f'''
+`{function.name}` is variadic: replace its `...` (and the `VaList` it is read from) with a trailing `args: &[VarArg]` parameter, and take the arguments from the slice in order instead of calling `arg::<T>()`. Integers arrive as `VarArg::Int`/`VarArg::UInt`, floating-point values as `VarArg::Double`, C strings as `VarArg::Str` and other pointers as `VarArg::Ptr`.
+'''
+        if calls_variadic:
+            guidance += '''
+To call a function taking `args: &[VarArg]`, pass its variadic arguments as a slice, e.g. `&[VarArg::Int(count as i64), VarArg::Str(name.to_string())]`.
+'''
+        return guidance
+
+    def _setjmp_guidance(self, function: FunctionInfo, unidiomatic_code: str) -> str:
+        """Prompt text for `setjmp`/`longjmp` users: error propagation, or the unwinding scaffold."""
+        if not (function.uses_setjmp or function.uses_longjmp or utils.uses_setjmp_scaffold(unidiomatic_code)):
+            return ""
+        return f'''
+The C function uses `setjmp`/`longjmp`. Where the jump is an error exit and the `setjmp` and every jump to it stay inside your translation (e.g. in helper functions you define), rewrite it as error propagation: the helpers return `Result` and the `setjmp` site handles the `Err`, using `?` in between.
+Otherwise keep using the following definitions, which the system defines (do **NOT** define them): `sactor_setjmp(env, || ...)` returns `Err(value)` when the closure jumps with `sactor_longjmp(env, value)`.
+```rust
+{utils.SETJMP_SCAFFOLD_CODE}```
+'''
+
+    def _errno_guidance(self, function: FunctionInfo, dependency_signatures: list[str]) -> str:
+        """Prompt text for `errno` users and callers of functions returning `Result` in its place."""
+        result_callees = [
+            match.group(1) for sig in dependency_signatures
+            if (match := re.search(r'\bfn\s+(\w+)[^;{]*->\s*((std::)?io::)?Result\b', sig))
+        ]
+        if not (function.uses_errno or function.sets_errno or result_callees):
+            return ""
+        return errno_guidance(function.errno_calls, function.sets_errno, idiomatic=True,
+                              result_callees=result_callees)
+
+    def _unidiomatic_global_initializer(self, name: str) -> str:
+        """The integer literal a non-const global starts with in its unidiomatic translation, or `0`."""
+        path = f"{self.unidiomatic_result_path}/translated_code_unidiomatic/global_vars/{name}.rs"
+        if not os.path.exists(path):
+            return "0"
+        match = re.search(r'=\s*(-?\d+)\s*;', read_file(path))
+        return match.group(1) if match else "0"
+
+    def _signal_guidance(self, function: FunctionInfo) -> str:
+        """Prompt text for functions installing signal handlers or registered as one."""
+        if not (function.signal_registrations or function.handled_signals):
+            return ""
+        return f'''
+{signal_plan(function.signal_registrations, function.handled_signals, idiomatic=True)}The system defines the following (do **NOT** define them):
+```rust
+{utils.SIGNAL_SCAFFOLD_CODE}```
+'''
+
+    def _stdio_guidance(self, function: FunctionInfo) -> str:
+        """Prompt text for `FILE *` users, and the spec shape of `FILE *` parameters."""
+        file_params = [name for name, arg_type in function.arguments if re.search(r'\bFILE\s*\*', arg_type)]
+        if not (file_params or uses_file_streams(function.stdio_calls, function.stdio_list)):
+            return ""
+        guidance = "\n" + stdio_plan(function.stdio_calls, function.fopen_modes, function.stdio_list)
+        if file_params:
+            names = ", ".join(f"`{name}`" for name in file_params)
+            guidance += (f"In the spec, give the `FILE *` parameters {names} the shape "
+                         '`{"ptr": {"kind": "file"}}`: the test harness then passes the `File`, '
+                         "`BufReader<File>` or `BufWriter<File>` (owned or `&mut`) your signature takes, "
+                         "sharing the C stream's file and position.\n")
+        return guidance
+
+    def _self_referential_guidance(self, struct_name: str, code: str) -> str:
+        """Prompt text for structs that point to their own type, e.g. linked lists."""
+        if not re.search(rf'\*\s*(mut|const)\s+{re.escape(struct_name)}\b', code):
+            return ""
+        overrides = self.config['general'].get('self_referential_struct_strategies', {})
+        strategy = overrides.get(
+            struct_name, self.config['general'].get('self_referential_strategy', 'box'))
+        if strategy == "rc":
+            return f'''
+`{struct_name}` links to other `{struct_name}` values, and nodes may be shared. Represent each such link as `Option<Rc<RefCell<...>>>` (use `std::rc::Rc` and `std::cell::RefCell`).
+'''
+        if strategy == "arena":
+            return f'''
+`{struct_name}` links to other `{struct_name}` values. Store the nodes in an arena (a `Vec` owned by the caller) and represent each such link as `Option<usize>`, the index of the target node in that arena.
+'''
+        return f'''
+`{struct_name}` links to other `{struct_name}` values. Each node owns the nodes it points to: represent each such link as `Option<Box<...>>`.
+'''
+
+    def _field_usage_guidance(self, struct_name: str, struct_code: str) -> str:
+        """Prompt text on how the unidiomatic functions use the struct's fields, with fixed SPEC entries for length fields."""
+        functions_path = os.path.join(
+            self.unidiomatic_result_path, "translated_code_unidiomatic/functions")
+        if not os.path.isdir(functions_path):
+            return ""
+        function_code = '\n'.join(
+            read_file(os.path.join(functions_path, name))
+            for name in sorted(os.listdir(functions_path)) if name.endswith(".rs"))
+        try:
+            usage = rust_ast_parser.analyze_struct_field_usage(
+                f"{struct_code}\n{function_code}", struct_name)
+        except Exception as e:
+            logger.debug("Field usage analysis of %s failed: %s", struct_name, e)
+            return ""
+
+        lines = []
+        for field in usage["fields"]:
+            uses = []
+            if field["read_by"]:
+                uses.append("read by " + ", ".join(f"`{f}`" for f in field["read_by"]))
+            if field["written_by"]:
+                uses.append("written by " + ", ".join(f"`{f}`" for f in field["written_by"]))
+            lines.append(f"- `{field['name']}`: {'; '.join(uses) or 'not used by any function'}")
+        joined_fields = '\n'.join(lines)
+        guidance = f'''
+The functions of the program use the fields of `{struct_name}` as follows:
+{joined_fields}
+'''
+        if not usage["length_fields"]:
+            return guidance
+
+        field_types = {field["name"]: field["type"] for field in usage["fields"]}
+        lines = []
+        entries = {}
+        for length in usage["length_fields"]:
+            lines.append(f"- `{length['field']}` holds the length of `{length['pointer']}`: "
+                         + "; ".join(length["evidence"]))
+            pointer = length["pointer"]
+            entries.setdefault(pointer, json.dumps({
+                "u_field": {"name": pointer, "type": field_types[pointer],
+                            "shape": {"ptr": {"kind": "slice", "len_from": length["field"]}}},
+                "i_field": {"name": pointer, "type": f"Vec<{length['element_type']}>"},
+            }))
+            entries.setdefault(length["field"], json.dumps({
+                "u_field": {"name": length["field"], "type": field_types[length["field"]], "shape": "scalar"},
+                "i_field": {"name": f"{pointer}.len", "type": "usize"},
+            }))
+        joined_lengths = '\n'.join(lines)
+        joined_entries = ',\n'.join(entries.values())
+        return guidance + f'''The following length fields are only used to bound a pointer. Do not keep them in the idiomatic struct: the pointer becomes a `Vec` and its `len()` replaces the length field.
+{joined_lengths}
+Use exactly these entries for them in the SPEC:
+```json
+{joined_entries}
+```
+'''
+
     def _translate_enum_impl(
         self,
         enum: EnumInfo,
@@ -453,6 +666,13 @@ The global variable is:
 ```
 If you think the global variable is already idiomatic, you can directly copy the code to the output format.
 '''
+        elif (sync_code := sync_global_code(global_var.name, global_var.type)) is not None:
+            # pthread synchronization objects map to std statics directly
+            return return_result(sync_code)
+        elif (flag_code := signal_flag_global_code(
+                global_var.name, global_var.type, self._unidiomatic_global_initializer(global_var.name))) is not None:
+            # flags set by signal handlers become atomics
+            return return_result(flag_code)
         else:
             raise NotImplementedError(
                 "Error: Only support translating const global variables for idiomatic Rust")
@@ -608,7 +828,9 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
             else:
                 raise RuntimeError(msg)
 
-        unidiomatic_struct_code = read_file(struct_path)
+        # drop the layout asserts: they describe the C mirror, not the idiomatic struct
+        unidiomatic_struct_code = rust_ast_parser.strip_to_struct_items(
+            read_file(struct_path))
 
         # Get results from crown
         crown_output = self.crown_result.query(
@@ -654,6 +876,12 @@ If the struct is designed as a cloneable struct, try to add/implement the `Clone
 {unidiomatic_struct_code}
 ```
 '''
+        prompt += self._void_pointer_guidance(unidiomatic_struct_code)
+        prompt += self._no_std_guidance()
+        prompt += self._self_referential_guidance(
+            struct_union.name, unidiomatic_struct_code)
+        prompt += self._field_usage_guidance(
+            struct_union.name, unidiomatic_struct_code)
         if len(crown_output) > 0:
             prompt += f'''
 "Crown" is a pointer analysis tool that can help to identify the ownership, mutability and fatness of pointers. Following are the possible annotations for pointers:
@@ -982,8 +1210,11 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                     candidate_order.append(cand)
             for candidate in candidate_order:
                 try:
-                    struct_result = rust_ast_parser.add_derive_to_struct_union(
-                        struct_result, candidate, "Debug")
+                    # a hand-written `impl Debug` would conflict with the derive
+                    if not rust_ast_parser.implements_trait(
+                            struct_result, candidate, "Debug"):
+                        struct_result = rust_ast_parser.add_derive_to_struct_union(
+                            struct_result, candidate, "Debug")
                     derive_applied = True
                     idiomatic_struct_name = candidate
                     break
@@ -1004,6 +1235,14 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                     attempts=attempts+1
                 )
 
+        if self.config['general'].get('serde_capture', False):
+            try:
+                struct_result = rust_ast_parser.add_serde_capture(
+                    struct_result, idiomatic_struct_name)
+            except Exception as e:
+                logger.warning(
+                    "Skipping serde capture for %s: %s", idiomatic_struct_name, e)
+
         all_dependency_code: dict[str, str] = {}
         all_dependency_code.update(dependencies_code)
         all_dependency_code.update(enum_dependency_code)
@@ -1111,6 +1350,44 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
         return TranslateResult.SUCCESS
 
     @override
+    def _clippy_fix_function(
+        self,
+        function: FunctionInfo,
+        function_result: str,
+        data_type_code: dict[str, str],
+        dependencies_code: dict[str, str],
+        unidiomatic_signature,
+    ) -> str:
+        """
+        Lets `cargo clippy --fix` clean up a verified translation, keeping the
+        fixes only if the function still verifies.
+        """
+        combiner = PartialCombiner(dependencies_code, data_type_code)
+        result, context_code = combiner.combine()
+        if result != CombineResult.SUCCESS or context_code is None:
+            return function_result
+        fixed = self.verifier.clippy_fix(function.name, function_result, context_code)
+        if fixed is None:
+            return function_result
+
+        try:
+            verify_result = self.verifier.verify_function(
+                function,
+                function_code=fixed,
+                data_type_code=data_type_code,
+                function_dependencies_code=dependencies_code,
+                unidiomatic_signature=unidiomatic_signature,
+                prefix=False,
+            )
+        except Exception as e:
+            verify_result = (VerifyResult.COMPILE_ERROR, str(e))
+        if verify_result[0] != VerifyResult.SUCCESS:
+            logger.warning(
+                "Discarding clippy fixes for function %s: %s", function.name, verify_result[1])
+            return function_result
+        logger.info("Applied clippy fixes to function %s", function.name)
+        return fixed
+
     def _translate_function_impl(
         self,
         function: FunctionInfo,
@@ -1142,7 +1419,17 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
         self.failure_info_set_attempts(function.name, attempts + 1)
 
         # Get macro definitions, not used in idiomatic translation for now
-        macro_definitions = self.c_parser.get_macro_definitions_for_function(function.name)
+        macro_definitions = self.c_parser.get_macro_definitions_for_function(
+            function.name, exclude_translated=True)
+        # `#define` constants are shared with the unidiomatic translation
+        code_of_macro_constants = {
+            constant.name: constant.rust_code()
+            for constant in self.c_parser.get_macro_constants_for_function(function.name)
+        }
+        code_of_macro_functions = {
+            macro.name: macro.rust_code()
+            for macro in self.c_parser.get_macro_functions_for_function(function.name)
+        }
 
         # Get used struct, unions
         structs_in_function = function.struct_dependencies
@@ -1306,6 +1593,25 @@ Your solution should only have **one** function, if you need to create help func
 {unidiomatic_function_code}
 ```
 '''
+        prompt += self._void_pointer_guidance(unidiomatic_function_code)
+        prompt += self._variadic_guidance(function, function_depedency_signatures)
+        prompt += self._setjmp_guidance(function, unidiomatic_function_code)
+        prompt += self._errno_guidance(function, function_depedency_signatures)
+        prompt += self._stdio_guidance(function)
+        prompt += self._signal_guidance(function)
+        prompt += self._cxx_class_guidance(function)
+        if function.pthread_calls:
+            sync_globals = {
+                global_var.name: code for global_var in function.global_vars_dependencies
+                if (code := sync_global_code(global_var.name, global_var.type)) is not None
+            }
+            prompt += f'''
+{concurrency_plan(function.pthread_calls, sync_globals)}'''
+        if function.goto_labels:
+            prompt += f'''
+{goto_plan(function.goto_labels, idiomatic=True)}
+'''
+        prompt += self._no_std_guidance()
         if len(crown_output) > 0:
             prompt += f'''
 "Crown" is a pointer analysis tool that can help to identify the ownership, mutability and fatness of pointers. Following are the possible annotations for pointers:
@@ -1328,6 +1634,7 @@ The following is the output of Crown for this function:
 Analyze the Crown output firstly, then translate the pointers in function arguments and return values with the help of the Crown output.
 Try to avoid using pointers in the function arguments and return values if possible.
 '''
+        prompt += self._pointer_ownership_guidance(function, unidiomatic_function_code)
 
         if len(used_global_vars) > 0:
             joint_used_global_vars_only_type_and_names = '\n'.join(used_global_vars_only_type_and_names.values())
@@ -1336,6 +1643,20 @@ The function uses the following const global variables, whose types and names ar
 ```rust
 {joint_used_global_vars_only_type_and_names}
 ```
+'''
+        if len(code_of_macro_constants) > 0:
+            joint_macro_constants = ''.join(code_of_macro_constants.values())
+            prompt += f'''
+The function uses the following constants, translated from `#define`s (you should **NOT** define them in your translation, as the system will automatically define them. Refer to them by name):
+```rust
+{joint_macro_constants}```
+'''
+        if len(code_of_macro_functions) > 0:
+            joint_macro_functions = ''.join(code_of_macro_functions.values())
+            prompt += f'''
+The function uses the following `macro_rules!` macros, translated from C function-like macros (you should **NOT** define them in your translation, as the system will automatically define them). Keep calling them as `NAME!(...)` where the operand types fit:
+```rust
+{joint_macro_functions}```
 '''
         if len(used_enum_values) > 0 or len(used_enum_defs) > 0:
             enum_definitions = set()
@@ -1448,7 +1769,7 @@ Full JSON Schema for the SPEC (do not output the schema; output only an instance
       "u_field": {{
         "name": "...",
         "type": "...",
-        "shape": "scalar" | {{"ptr": {{"kind": "slice|cstring|ref", "len_from": "?", "len_const": 1}}}}
+        "shape": "scalar" | {{"ptr": {{"kind": "slice|cstring|ref|file", "len_from": "?", "len_const": 1}}}}
       }},
       "i_field": {{
         "name": "...",
@@ -1823,7 +2144,14 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 )
             all_dependency_functions_code[dep_name] = read_file(dep_path)
 
-        data_type_code = all_dt_code | used_global_vars | code_of_enum
+        data_type_code = (all_dt_code | used_global_vars | code_of_enum
+                          | code_of_macro_constants | code_of_macro_functions)
+        if re.search(r'\bVarArg\b', function_result):
+            data_type_code["VarArg"] = utils.VARARG_ENUM_CODE
+        if any(utils.uses_setjmp_scaffold(code) for code in [function_result, *used_global_vars.values()]):
+            data_type_code["JmpBuf"] = utils.SETJMP_SCAFFOLD_CODE
+        if utils.uses_signal_scaffold(function_result):
+            data_type_code["SactorSignals"] = utils.SIGNAL_SCAFFOLD_CODE
 
         # process the function result
         function_result = rust_ast_parser.expand_use_aliases(
@@ -1876,15 +2204,19 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
             except Exception as e:
                 logger.warning("Function spec staging skipped: %s", e)
         
+        function_result, veto = self._run_plugins("pre_verify", "function", function.name, function_result)
         try:
-            result = self.verifier.verify_function(
-                function,
-                function_code=function_result,
-                data_type_code=data_type_code,
-                function_dependencies_code=all_dependency_functions_code,
-                unidiomatic_signature=undiomantic_function_signature,
-                prefix=False,  # TODO: check here
-            )
+            if veto is not None:
+                result = (VerifyResult.FEEDBACK, veto)
+            else:
+                result = self.verifier.verify_function(
+                    function,
+                    function_code=function_result,
+                    data_type_code=data_type_code,
+                    function_dependencies_code=all_dependency_functions_code,
+                    unidiomatic_signature=undiomantic_function_signature,
+                    prefix=False,  # TODO: check here
+                )
         except Exception as e:
             self.append_failure_info(
                 function.name, "COMPILE_ERROR", str(e), function_result
@@ -1897,6 +2229,10 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 error_translation=function_result,
                 attempts=attempts+1
             )
+        if result[0] == VerifyResult.SUCCESS:
+            function_result, veto = self._run_plugins("post_idiomatic", "function", function.name, function_result)
+            if veto is not None:
+                result = (VerifyResult.FEEDBACK, veto)
 
         if result[0] != VerifyResult.SUCCESS:
             # Clean up staged SPEC and mapping if verification failed
@@ -1907,7 +2243,10 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                     pass
             if spec_tmp_dir:
                 shutil.rmtree(spec_tmp_dir, ignore_errors=True)
-            if result[0] == VerifyResult.COMPILE_ERROR:
+            if veto is not None:
+                self.append_failure_info(
+                    function.name, "PLUGIN_VETO", veto, function_result)
+            elif result[0] == VerifyResult.COMPILE_ERROR:
                 self.append_failure_info(
                     function.name, "COMPILE_ERROR", result[1], function_result)
 
@@ -1934,7 +2273,16 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 error_translation=function_result,
                 attempts=attempts + 1
             )
-        
+
+        if self.config['general'].get('clippy_fix', False):
+            function_result = self._clippy_fix_function(
+                function,
+                function_result,
+                data_type_code,
+                all_dependency_functions_code,
+                undiomantic_function_signature,
+            )
+
         # Persist SPEC (if staged) and update mapping after successful verification
         if spec_json_to_save and not spec_pre_saved:
             try:
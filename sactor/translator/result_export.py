@@ -0,0 +1,119 @@
+import json
+import os
+import xml.etree.ElementTree as ET
+from collections import Counter
+from typing import Optional
+
+from .translator_types import TranslationOutcome
+
+# characters of the last error message kept as the diff excerpt of a failure
+EXCERPT_LIMIT = 2000
+
+# outcomes that count as passing; c2rust code is equivalent by construction
+PASSED_STATUSES = (
+    TranslationOutcome.SUCCESS.value,
+    TranslationOutcome.FALLBACK_C2RUST.value,
+    TranslationOutcome.IMPORTED_C2RUST.value,
+)
+
+
+def _excerpt(message: Optional[str]) -> Optional[str]:
+    if message is None:
+        return None
+    message = str(message)
+    if len(message) <= EXCERPT_LIMIT:
+        return message
+    return message[:EXCERPT_LIMIT] + f"\n... ({len(message) - EXCERPT_LIMIT} more characters)"
+
+
+def stage_results(stage: str, failure_info: dict) -> list[dict]:
+    """
+    Flattens a translator's failure info into one result per translated item:
+    its status, attempt count, duration, the last error, the error types of
+    all failed attempts and the winning ensemble model, if any.
+    """
+    results = []
+    for name, info in failure_info.items():
+        errors = info.get("errors", [])
+        last_error = errors[-1] if errors else None
+        status = info.get("status", "untranslated")
+        results.append({
+            "stage": stage,
+            "type": info.get("type"),
+            "name": name,
+            "status": status,
+            "passed": status in PASSED_STATUSES,
+            "attempts": sum(info.get("attempts", [])),
+            "duration_seconds": info.get("duration_seconds"),
+            "error_type": last_error["type"] if last_error and status not in PASSED_STATUSES else None,
+            "diff_excerpt": _excerpt(last_error["message"])
+            if last_error and status not in PASSED_STATUSES else None,
+            # failed attempts by error type
+            "failure_categories": dict(Counter(e["type"] for e in errors)),
+            "blockers": [b.get("name") for b in info.get("blockers", [])],
+            # the ensemble model whose translation was accepted
+            "model": info.get("model"),
+        })
+    return results
+
+
+def write_json_summary(path: str, results: list[dict]) -> None:
+    summary = {
+        "total": len(results),
+        "passed": sum(1 for r in results if r["passed"]),
+        "failed": sum(1 for r in results if not r["passed"]),
+        "results": results,
+    }
+    os.makedirs(os.path.dirname(path) or ".", exist_ok=True)
+    with open(path, "w") as f:
+        json.dump(summary, f, indent=4)
+
+
+def write_junit_xml(path: str, results: list[dict]) -> None:
+    """
+    Writes the results as JUnit XML: one test suite per stage, one test case
+    per item. Items blocked by a failed dependency are reported as skipped.
+    """
+    suites = ET.Element("testsuites", name="sactor")
+    for stage in dict.fromkeys(r["stage"] for r in results):
+        cases = [r for r in results if r["stage"] == stage]
+        blocked = [r for r in cases
+                   if r["status"] == TranslationOutcome.BLOCKED_FAILED.value]
+        suite = ET.SubElement(
+            suites,
+            "testsuite",
+            name=stage,
+            tests=str(len(cases)),
+            failures=str(sum(1 for r in cases if not r["passed"]) - len(blocked)),
+            skipped=str(len(blocked)),
+            time=f"{sum(r['duration_seconds'] or 0 for r in cases):.3f}",
+        )
+        for result in cases:
+            case = ET.SubElement(
+                suite,
+                "testcase",
+                classname=f"{stage}.{result['type']}",
+                name=result["name"],
+                time=f"{result['duration_seconds'] or 0:.3f}",
+            )
+            ET.SubElement(case, "properties").extend([
+                ET.Element("property", name="status", value=result["status"]),
+                ET.Element("property", name="attempts", value=str(result["attempts"])),
+            ])
+            if result["passed"]:
+                continue
+            if result["status"] == TranslationOutcome.BLOCKED_FAILED.value:
+                ET.SubElement(
+                    case, "skipped",
+                    message=f"blocked by {', '.join(result['blockers'])}")
+                continue
+            failure = ET.SubElement(
+                case,
+                "failure",
+                message=f"{result['status']} after {result['attempts']} attempt(s)",
+                type=result["error_type"] or result["status"],
+            )
+            failure.text = result["diff_excerpt"]
+    ET.indent(suites)
+    os.makedirs(os.path.dirname(path) or ".", exist_ok=True)
+    ET.ElementTree(suites).write(path, encoding="utf-8", xml_declaration=True)
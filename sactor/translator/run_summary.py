@@ -0,0 +1,147 @@
+import json
+import os
+from collections import Counter
+from datetime import datetime, timezone
+from importlib import metadata
+from typing import Optional
+
+from sactor import logging as sactor_logging
+
+from .html_report import STAGES, translated_code, unsafe_metrics
+from .translator_types import TranslationOutcome
+
+logger = sactor_logging.get_logger(__name__)
+
+SUMMARY_FILE = "summary.json"
+# bumped only by incompatible changes of sactor/_resources/summary.schema.json;
+# fields may be added within a version
+SUMMARY_SCHEMA_VERSION = 1
+
+
+def _sactor_version() -> Optional[str]:
+    try:
+        return metadata.version("sactor")
+    except metadata.PackageNotFoundError:
+        return None
+
+
+def _timestamp(seconds: float) -> str:
+    return datetime.fromtimestamp(seconds, timezone.utc).isoformat()
+
+
+def item_summaries(results: list[dict], result_dir: str) -> list[dict]:
+    """The results of `result_export.stage_results` with the unsafe token counts of each translation."""
+    items = []
+    for result in results:
+        metrics = unsafe_metrics(translated_code(result_dir, result["stage"], result["type"], result["name"]))
+        items.append({
+            "stage": result["stage"],
+            "type": result["type"],
+            "name": result["name"],
+            "status": result["status"],
+            "passed": result["passed"],
+            "attempts": result["attempts"],
+            "duration_seconds": result["duration_seconds"],
+            "model": result["model"],
+            "failure_category": result["error_type"],
+            "failure_categories": result["failure_categories"],
+            "blockers": result["blockers"],
+            "tokens": metrics["tokens"] if metrics else None,
+            "unsafe_tokens": metrics["unsafe_tokens"] if metrics else None,
+            "unsafe_ratio": metrics["unsafe_fraction"] if metrics else None,
+        })
+    return items
+
+
+def stage_totals(items: list[dict]) -> dict:
+    """Totals of the items per stage, for the stages that have any."""
+    totals = {}
+    for stage in STAGES:
+        stage_items = [i for i in items if i["stage"] == stage]
+        if not stage_items:
+            continue
+        blocked = sum(1 for i in stage_items if i["status"] == TranslationOutcome.BLOCKED_FAILED.value)
+        passed = sum(1 for i in stage_items if i["passed"])
+        tokens = sum(i["tokens"] or 0 for i in stage_items)
+        unsafe_tokens = sum(i["unsafe_tokens"] or 0 for i in stage_items)
+        categories = Counter()
+        for item in stage_items:
+            categories.update(item["failure_categories"])
+        # items blocked by a failed dependency never reach the tests
+        verified = len(stage_items) - blocked
+        totals[stage] = {
+            "items": len(stage_items),
+            "passed": passed,
+            "failed": len(stage_items) - passed - blocked,
+            "blocked": blocked,
+            "attempts": sum(i["attempts"] for i in stage_items),
+            "duration_seconds": sum(i["duration_seconds"] or 0 for i in stage_items),
+            "tokens": tokens,
+            "unsafe_tokens": unsafe_tokens,
+            "unsafe_ratio": unsafe_tokens / tokens if tokens else 0.0,
+            "test_pass_rate": passed / verified if verified else 0.0,
+            "failure_categories": dict(categories),
+        }
+    return totals
+
+
+def unit_summary(input_file: str, result_dir: str, items: list[dict], error: Optional[str]) -> dict:
+    if error is not None:
+        status = "error"
+    elif all(i["passed"] for i in items):
+        status = "success"
+    else:
+        status = "failure"
+    return {
+        "input_file": input_file,
+        "result_dir": result_dir,
+        "status": status,
+        "error": error,
+        "stages": stage_totals(items),
+        "items": items,
+    }
+
+
+def run_summary(units: list[dict], started_at: float, finished_at: float, cost: Optional[dict]) -> dict:
+    """
+    The summary of a run translating `units`, in the format described by
+    `sactor/_resources/summary.schema.json`.
+    """
+    if any(u["status"] == "error" for u in units):
+        status = "error"
+    elif any(u["status"] == "failure" for u in units):
+        status = "failure"
+    else:
+        status = "success"
+    return {
+        "schema_version": SUMMARY_SCHEMA_VERSION,
+        "sactor_version": _sactor_version(),
+        "started_at": _timestamp(started_at),
+        "finished_at": _timestamp(finished_at),
+        "duration_seconds": finished_at - started_at,
+        "status": status,
+        "stages": stage_totals([item for unit in units for item in unit["items"]]),
+        "cost": cost,
+        "units": units,
+    }
+
+
+def load_units(path: str) -> list[dict]:
+    """The units of the summary at `path`, none when there is no readable one."""
+    if not os.path.isfile(path):
+        return []
+    try:
+        with open(path) as f:
+            summary = json.load(f)
+    except (OSError, ValueError) as e:
+        logger.warning("Ignoring unreadable summary %s: %s", path, e)
+        return []
+    if summary.get("schema_version") != SUMMARY_SCHEMA_VERSION:
+        return []
+    return summary.get("units", [])
+
+
+def write_summary(path: str, summary: dict) -> None:
+    os.makedirs(os.path.dirname(path) or ".", exist_ok=True)
+    with open(path, "w") as f:
+        json.dump(summary, f, indent=4)
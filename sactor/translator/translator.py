@@ -1,5 +1,7 @@
 import json
 import os
+import re
+import time
 from abc import ABC, abstractmethod
 from collections import defaultdict
 from typing import Dict, List, Optional, Sequence, Tuple
@@ -8,6 +10,7 @@ from sactor import logging as sactor_logging
 from sactor import utils
 from sactor.c_parser import (CParser, EnumInfo, FunctionInfo, GlobalVarInfo,
                              StructInfo)
+from sactor.c_parser.cxx_classes import cxx_class_plan
 from sactor.c_parser.refs import (
     EnumRef,
     FunctionDependencyRef,
@@ -15,8 +18,10 @@ from sactor.c_parser.refs import (
     StructRef,
 )
 from sactor.llm import LLM
+from sactor.plugins import HookContext, Plugins, PluginVeto
 from sactor.verifier import VerifyResult
 
+from .checkpoint import Checkpoint
 from .translator_types import TranslateResult, TranslationOutcome
 
 
@@ -43,10 +48,31 @@ class Translator(ABC):
         self._failure_info_backup_prepared = False
         self.translation_status: Dict[str, Dict[str, TranslationOutcome]] = defaultdict(dict)
         self._dependency_cache: Dict[Tuple[str, str], bool] = {}
+        # the run's checkpoint and this translator's stage in it, see `use_checkpoint`
+        self.checkpoint: Optional[Checkpoint] = None
+        self.stage: Optional[str] = None
+        self.plugins = Plugins.load(config)
+
+    def use_checkpoint(self, checkpoint: Checkpoint, stage: str):
+        """Records every translated item in `checkpoint`, and resumes the items of an interrupted run."""
+        self.checkpoint = checkpoint
+        self.stage = stage
 
     def translate_struct(self, struct_union: StructInfo) -> TranslateResult:
-        res = self._translate_struct_impl(struct_union)
+        attempts = self._resume_attempts("struct", struct_union.name)
+        if attempts is None:
+            return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        self.llm.cost.item = struct_union.name
+        start = time.monotonic()
+        with sactor_logging.span(f"struct {struct_union.name}", item_log=True,
+                                 stage=self.stage, item=struct_union.name, item_type="struct"):
+            res = self._translate_with_ensemble(
+                "struct", struct_union.name,
+                lambda start: self._translate_struct_impl(struct_union, attempts=start), attempts)
+        self._record_duration(struct_union.name, time.monotonic() - start)
+        self.llm.cost.item = None
         self.save_failure_info(self.failure_info_path)
+        self._save_checkpoint()
         return res
 
     @abstractmethod
@@ -83,10 +109,118 @@ class Translator(ABC):
         pass
 
     def translate_function(self, function: FunctionInfo) -> TranslateResult:
-        res = self._translate_function_impl(function)
+        attempts = self._resume_attempts("function", function.name)
+        if attempts is None:
+            return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        self.llm.cost.item = function.name
+        start = time.monotonic()
+        with sactor_logging.span(f"function {function.name}", item_log=True,
+                                 stage=self.stage, item=function.name, item_type="function"):
+            res = self._translate_with_ensemble(
+                "function", function.name,
+                lambda start: self._translate_function_impl(function, attempts=start), attempts)
+        self._record_duration(function.name, time.monotonic() - start)
+        self.llm.cost.item = None
         self.save_failure_info(self.failure_info_path)
+        self._save_checkpoint()
+        return res
+
+    def _run_plugins(self, hook: str, item_type: str, item: str, code: str) -> tuple[str, Optional[str]]:
+        """
+        `code` of `item` as transformed by the `hook` of the plugins, and the
+        reason a plugin vetoed it, None when none did.
+        """
+        try:
+            return self.plugins.run(hook, code, HookContext(self.stage, item_type, item, self.config)), None
+        except PluginVeto as e:
+            logger.warning("The translation of %s %s was rejected by the plugin %s", item_type, item, e)
+            return code, f"The translation was rejected by the plugin {e}"
+
+    def _resume_attempts(self, item_type: str, item: str) -> Optional[int]:
+        """
+        The attempt the translation of `item` starts at. A resumed run redoes
+        the attempt the interrupted run was at, whose answer was lost, and
+        returns None for items that already used up their attempts, which are
+        not paid for again.
+        """
+        if self.checkpoint is None or not self.checkpoint.resumed:
+            return 0
+        state = self.checkpoint.resumed_item(self.stage, item)
+        if state is None:
+            return 0
+        models = self.ensemble_models()
+        max_attempts = self._attempts_per_model() * len(models) if models else self.max_attempts
+        if state["status"] == TranslationOutcome.FAILURE.value and state["attempts"] >= max_attempts:
+            logger.info("%s %s failed in the resumed run, not translating it again", item_type.capitalize(), item)
+            self._record_outcome(item_type, item, TranslationOutcome.FAILURE)
+            return None
+        if models:
+            # the interrupted candidate is not known, the ensemble starts over
+            return 0
+        return max(state["attempts"] - 1, 0)
+
+    def ensemble_models(self) -> list[str]:
+        """The models of this translator's stage in `[ensemble]`, empty when it uses `general.model` alone."""
+        return list(self.config.get('ensemble', {}).get(self.stage or "", []))
+
+    def _attempts_per_model(self) -> int:
+        return int(self.config.get('ensemble', {}).get('attempts_per_model', 0)) or self.max_attempts
+
+    def _translate_with_ensemble(self, item_type: str, item: str, translate, attempts: int) -> TranslateResult:
+        """
+        Runs `translate(start_attempt)` once per ensemble model, each with its
+        own attempts and attempt slot in the failure info, until a model's
+        translation passes verification, and records the candidates and the
+        winning model.
+        """
+        models = self.ensemble_models()
+        translated_path = getattr(self, f"translated_{item_type}_path", None)
+        if not models or (translated_path and os.path.exists(os.path.join(translated_path, f"{item}.rs"))):
+            return translate(attempts)
+        default_model = self.llm.default_model
+        max_attempts = self.max_attempts
+        fallback_c2rust = getattr(self, "fallback_c2rust", None)
+        candidates = []
+        res = TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        try:
+            self.max_attempts = self._attempts_per_model()
+            for i, model in enumerate(models):
+                if i > 0:
+                    self.restart_item(item_type, item)
+                if fallback_c2rust is not None:
+                    # the c2rust fallback is left to the last model
+                    self.fallback_c2rust = fallback_c2rust and i == len(models) - 1
+                self.llm.default_model = model
+                logger.info("Translating %s %s with ensemble model %s (%d/%d)",
+                            item_type, item, model, i + 1, len(models))
+                res = translate(attempts if i == 0 else 0)
+                info = self.failure_info.get(item, {})
+                candidates.append({
+                    "model": model,
+                    "status": info.get("status", "untranslated"),
+                    "attempts": info.get("attempts", [0])[-1],
+                })
+                if res == TranslateResult.SUCCESS:
+                    break
+        finally:
+            self.max_attempts = max_attempts
+            self.llm.default_model = default_model
+            if fallback_c2rust is not None:
+                self.fallback_c2rust = fallback_c2rust
+        info = self.failure_info.get(item)
+        if info is not None:
+            info['candidates'] = candidates
+            if candidates and candidates[-1]["status"] == TranslationOutcome.SUCCESS.value:
+                info['model'] = candidates[-1]["model"]
+                logger.info("%s %s translated by %s", item_type.capitalize(), item, info['model'])
         return res
 
+    def _save_checkpoint(self):
+        if self.checkpoint is None:
+            return
+        self.checkpoint.record_stage(self.stage, self.failure_info, self.llm)
+        self.checkpoint.save()
+
     @abstractmethod
     def _translate_function_impl(
         self,
@@ -137,6 +271,24 @@ class Translator(ABC):
         info['attempts'][-1] = attempts
         self.save_failure_info(self.failure_info_path)
 
+    def restart_item(self, item_type: str, item: str):
+        """
+        Forgets the outcome of `item` so it is translated again, keeping its
+        earlier errors and recording its new attempts in another slot.
+        """
+        self.translation_status[item_type].pop(item, None)
+        self._dependency_cache.pop((item_type, item), None)
+        info = self.failure_info.get(item)
+        if info is not None:
+            info['status'] = "untranslated"
+            info['attempts'].append(0)
+            info.pop('blockers', None)
+
+    def _record_duration(self, item, seconds: float):
+        # wall-clock time of the item's translation, all attempts included
+        if item in self.failure_info:
+            self.failure_info[item]['duration_seconds'] = round(seconds, 3)
+
     def save_failure_info(self, path):
         if self.failure_info == {}:
             return
@@ -223,6 +375,7 @@ class Translator(ABC):
             if status in {
                 TranslationOutcome.SUCCESS,
                 TranslationOutcome.FALLBACK_C2RUST,
+                TranslationOutcome.IMPORTED_C2RUST,
             }:
                 continue
             if status in {TranslationOutcome.FAILURE, TranslationOutcome.BLOCKED_FAILED}:
@@ -359,6 +512,32 @@ class Translator(ABC):
                     return True
         return False
 
+    def _cxx_class_guidance(self, function: FunctionInfo) -> str:
+        """
+        Prompt text for functions using C++ classes. The first translated
+        function using a class defines its struct and `impl` blocks.
+        """
+        if not function.cxx_classes:
+            return ""
+        translated_code = ""
+        base_path = getattr(self, "translated_function_path", None)
+        if base_path and os.path.isdir(base_path):
+            translated_code = "\n".join(
+                utils.read_file(os.path.join(base_path, filename))
+                for filename in sorted(os.listdir(base_path))
+                if filename.endswith(".rs") and filename != f"{function.name}.rs"
+            )
+        pending, defined = [], []
+        for name in function.cxx_classes:
+            cls = self.c_parser.get_cxx_class(name)
+            if re.search(rf'\bstruct\s+{name}\b', translated_code):
+                defined.append(cls)
+            else:
+                pending.append(cls)
+        class_code = "\n".join(self.c_parser.extract_cxx_class_code(cls.name) for cls in pending)
+        class_names = {cls.name for cls in self.c_parser.get_cxx_classes()}
+        return cxx_class_plan(pending, class_code, defined, class_names)
+
     def print_result_summary(self, title: str):
         def count_success(ctype: str) -> int:
             v = sum([1 if v['type'] == ctype and v['status'] == 'success' else 0 for v in self.failure_info.values() ])
@@ -7,6 +7,7 @@ class TranslationOutcome(Enum):
     FAILURE = "failure"
     BLOCKED_FAILED = "blocked_by_failed_dependency"
     FALLBACK_C2RUST = "fallback_c2rust"
+    IMPORTED_C2RUST = "imported_c2rust"
 
 class TranslateResult(Enum):
     SUCCESS = auto()
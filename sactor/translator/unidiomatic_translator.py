@@ -1,4 +1,4 @@
-import os, json
+import os, json, re
 from ctypes import c_buffer
 from typing import Any, Optional, override
 
@@ -8,6 +8,9 @@ from sactor import logging as sactor_logging, rust_ast_parser, utils
 from sactor.utils import read_file
 from sactor.c_parser import (CParser, EnumInfo, EnumValueInfo, FunctionInfo,
                              GlobalVarInfo, StructInfo)
+from sactor.c_parser.errno_api import errno_guidance
+from sactor.c_parser.goto_plan import goto_plan
+from sactor.c_parser.signals import signal_plan
 from sactor.combiner import RustCode
 from sactor.data_types import DataType
 from sactor.llm import LLM
@@ -40,6 +43,7 @@ class UnidiomaticTranslator(Translator):
         project_struct_usr_to_result_dir: dict[str, str] | None = None,
         project_enum_usr_to_result_dir: dict[str, str] | None = None,
         project_global_usr_to_result_dir: dict[str, str] | None = None,
+        import_c2rust: bool = False,
     ) -> None:
         super().__init__(
             llm=llm,
@@ -49,11 +53,18 @@ class UnidiomaticTranslator(Translator):
         )
         self.failure_info_path = os.path.join(
             self.result_path, "unidiomatic_failure_info.json")
+        self.stage = "unidiomatic"
         if os.path.isfile(self.failure_info_path):
             content = read_file(self.failure_info_path)
             self.failure_info = json.loads(content)
         self._failure_info_backup_prepared = False
 
+        if c2rust_translation:
+            try:
+                c2rust_translation = rust_ast_parser.cleanup_c2rust_artifacts(
+                    c2rust_translation)
+            except Exception as e:
+                logger.warning("Failed to clean up c2rust output: %s", e)
         self.c2rust_translation = c2rust_translation
         base_name = "translated_code_unidiomatic"
         self.base_name = base_name
@@ -67,6 +78,8 @@ class UnidiomaticTranslator(Translator):
             self.result_path, base_name, "functions")
         self.fallback_c2rust = config['general']['unidiomatic_fallback_c2rust']
         self.fallback_c2rust_fix_attempts = config['general']['unidiomatic_fallback_c2rust_fix_attempts']
+        # take every item from the c2rust translation instead of the LLM
+        self.import_c2rust = import_c2rust
         self.verifier = verifier.UnidiomaticVerifier(
             test_cmd_path,
             config=config,
@@ -85,6 +98,9 @@ class UnidiomaticTranslator(Translator):
         self.project_enum_usr_to_result_dir = project_enum_usr_to_result_dir or {}
         self.project_global_usr_to_result_dir = project_global_usr_to_result_dir or {}
 
+    def _c2rust_outcome(self) -> TranslationOutcome:
+        return TranslationOutcome.IMPORTED_C2RUST if self.import_c2rust else TranslationOutcome.FALLBACK_C2RUST
+
     @override
     def _translate_enum_impl(
         self,
@@ -103,17 +119,20 @@ class UnidiomaticTranslator(Translator):
             # Mark as success for this run so the new failure_info.json is populated
             self.mark_translation_success("enum", enum.name)
             return TranslateResult.SUCCESS
-        if attempts > self.max_attempts - 1:
-            logger.error(
-                "Failed to translate enum %s after %d attempts",
-                enum.name,
-                self.max_attempts,
-            )
-            if not self.fallback_c2rust:
-                return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        if self.import_c2rust or attempts > self.max_attempts - 1:
+            if self.import_c2rust:
+                logger.info("Importing the c2rust translation of enum %s", enum.name)
+            else:
+                logger.error(
+                    "Failed to translate enum %s after %d attempts",
+                    enum.name,
+                    self.max_attempts,
+                )
+                if not self.fallback_c2rust:
+                    return TranslateResult.MAX_ATTEMPTS_EXCEEDED
 
-            # fallback to c2rust
-            logger.warning("Falling back to c2rust implementation for enum %s", enum.name)
+                # fallback to c2rust
+                logger.warning("Falling back to c2rust implementation for enum %s", enum.name)
             try:
                 enum_result = rust_ast_parser.get_enum_definition(
                     self.c2rust_translation, enum.name)
@@ -194,7 +213,7 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 else:
                     break
 
-            self._record_outcome("enum", enum.name, TranslationOutcome.FALLBACK_C2RUST)
+            self._record_outcome("enum", enum.name, self._c2rust_outcome())
             utils.save_code(enum_save_path, enum_result)
             return TranslateResult.SUCCESS
 
@@ -336,6 +355,8 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 compile_code = global_var_result
                 if enum_dependency_code:
                     compile_code = f"{enum_dependency_code}\n{global_var_result}"
+                if utils.uses_setjmp_scaffold(global_var_result):
+                    compile_code = f"{utils.SETJMP_SCAFFOLD_CODE}\n{compile_code}"
                 try:
                     result = self.verifier.try_compile_rust_code(compile_code)
                 except Exception as e:
@@ -421,13 +442,16 @@ These enums are already translated to Rust as:
 Directly use these enums in your translation and do **NOT** redefine them.
 '''
 
-        if attempts > self.max_attempts - 1:
-            # fallback
-            logger.warning(
-                "Failed to translate global variable %s after %d attempts using LLM; falling back to c2rust",
-                global_var.name,
-                self.max_attempts,
-            )
+        if self.import_c2rust or attempts > self.max_attempts - 1:
+            if self.import_c2rust:
+                logger.info("Importing the c2rust translation of global variable %s", global_var.name)
+            else:
+                # fallback
+                logger.warning(
+                    "Failed to translate global variable %s after %d attempts using LLM; falling back to c2rust",
+                    global_var.name,
+                    self.max_attempts,
+                )
             result = rust_ast_parser.get_static_item_definition(self.c2rust_translation, global_var.name)
             return return_result(result, verification=False)
 
@@ -487,6 +511,11 @@ Use `extern "C"` wrap the following C global variable without defining the value
         if enum_prompt_text:
             prompt += f"\n{enum_prompt_text}\n"
 
+        if re.search(r'\b(sig)?jmp_buf\b', code_of_global_var):
+            prompt += '''
+`jmp_buf` has no Rust equivalent: use the type `JmpBuf` instead, which the system defines (do **NOT** define it).
+'''
+
         prompt += f'''
 Output the translated global variable into this format (wrap with the following tags):
 ----GLOBAL VAR----
@@ -592,10 +621,14 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 raise ValueError(
                     f"Error: Invalid data type {struct_union.data_type}")
 
-        # add Debug trait for struct/union
-        rust_s_u = rust_ast_parser.add_derive_to_struct_union(
-            rust_s_u, struct_union.name, "Debug")
+        # derive Debug and friends only where the field types allow it
+        rust_s_u = rust_ast_parser.infer_derives(rust_s_u, struct_union.name)
         rust_s_u = rust_ast_parser.unidiomatic_types_cleanup(rust_s_u)
+        layout = struct_union.layout
+        if self.config['general'].get('layout_asserts', True) and layout is not None:
+            # catch mismatched mirrors at compile time rather than as memory corruption
+            rust_s_u += "\n" + rust_ast_parser.generate_layout_asserts(
+                rust_s_u, [struct_union.name], json.dumps({struct_union.name: layout}))
 
         self.mark_translation_success("struct", struct_union.name)
         # Save the translated struct/union
@@ -607,7 +640,16 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
     def _prepare_function_context(
         self, function: FunctionInfo
     ) -> tuple[TranslateResult, Optional[dict[str, Any]]]:
-        macro_definitions = self.c_parser.get_macro_definitions_for_function(function.name)
+        macro_definitions = self.c_parser.get_macro_definitions_for_function(
+            function.name, exclude_translated=True)
+        code_of_macro_constants = {
+            constant.name: constant.rust_code()
+            for constant in self.c_parser.get_macro_constants_for_function(function.name)
+        }
+        code_of_macro_functions = {
+            macro.name: macro.rust_code()
+            for macro in self.c_parser.get_macro_functions_for_function(function.name)
+        }
         function_dependencies = function.function_dependencies
         function_name_dependencies = [f.name for f in function_dependencies]
 
@@ -752,6 +794,11 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
             used_global_vars[global_var.name] = code_of_global_var
             used_global_vars_only_type_and_names[global_var.name] = type_and_name
 
+        code_of_setjmp_scaffold: dict[str, str] = {}
+        if (function.uses_setjmp or function.uses_longjmp
+                or any(utils.uses_setjmp_scaffold(code) for code in used_global_vars.values())):
+            code_of_setjmp_scaffold["JmpBuf"] = utils.SETJMP_SCAFFOLD_CODE
+
         used_stdio = function.stdio_list
         used_stdio_code = ""
         if len(used_stdio) > 0:
@@ -784,6 +831,9 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
         context: dict[str, Any] = {
             "function_dependencies": function_dependencies,
             "macro_definitions": macro_definitions,
+            "code_of_macro_constants": code_of_macro_constants,
+            "code_of_macro_functions": code_of_macro_functions,
+            "code_of_setjmp_scaffold": code_of_setjmp_scaffold,
             "function_dependency_signatures": function_depedency_signatures,
             "function_dependency_uses": function_dependency_uses,
             "code_of_structs_full": code_of_structs_full,
@@ -817,12 +867,23 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
             self.mark_translation_success("function", function.name)
             return TranslateResult.SUCCESS
 
+        if function.is_variadic and utils.stable_output():
+            # defining a C-variadic function needs the nightly `c_variadic` feature
+            error_message = (
+                f"{function.name} is variadic; C-variadic functions cannot be defined on stable Rust")
+            logger.error("%s", error_message)
+            self.append_failure_info(function.name, "STABLE_OUTPUT_ERROR", error_message, "")
+            return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+
         prepare_status, func_ctx = self._prepare_function_context(function)
         if prepare_status != TranslateResult.SUCCESS or func_ctx is None:
             return prepare_status
 
         function_dependencies = func_ctx["function_dependencies"]
         macro_definitions: list[str] = func_ctx["macro_definitions"]
+        code_of_macro_constants: dict[str, str] = func_ctx["code_of_macro_constants"]
+        code_of_macro_functions: dict[str, str] = func_ctx["code_of_macro_functions"]
+        code_of_setjmp_scaffold: dict[str, str] = func_ctx["code_of_setjmp_scaffold"]
         function_depedency_signatures: list[str] = func_ctx["function_dependency_signatures"]
         function_dependency_uses: list[str] = func_ctx["function_dependency_uses"]
         code_of_structs_full: dict[str, str] = func_ctx["code_of_structs_full"]
@@ -834,17 +895,20 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
         code_of_enum: dict[Any, str] = func_ctx["code_of_enum"]
         used_enum_names: list[str] = func_ctx["used_enum_names"]
 
-        if attempts > self.max_attempts - 1:
-            logger.error(
-                "Failed to translate function %s after %d attempts",
-                function.name,
-                self.max_attempts,
-            )
-            if not self.fallback_c2rust:
-                return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        if self.import_c2rust or attempts > self.max_attempts - 1:
+            if self.import_c2rust:
+                logger.info("Importing the c2rust translation of function %s", function.name)
+            else:
+                logger.error(
+                    "Failed to translate function %s after %d attempts",
+                    function.name,
+                    self.max_attempts,
+                )
+                if not self.fallback_c2rust:
+                    return TranslateResult.MAX_ATTEMPTS_EXCEEDED
 
-            # fallback to c2rust
-            logger.warning("Falling back to c2rust implementation for function %s", function.name)
+                # fallback to c2rust
+                logger.warning("Falling back to c2rust implementation for function %s", function.name)
             try:
                 function_result = rust_ast_parser.get_function_definition(
                     self.c2rust_translation, function.name)
@@ -891,7 +955,12 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                             f"Error: Function signature not found in the translated code for function `{function.name}`. Got functions: {list(function_result_sigs.keys())}, check if you have the correct function name., you should **NOT** change the camel case to snake case and vice versa.")
                         return (VerifyResult.COMPILE_ERROR, error_message), processed_code
 
-                data_type_code = code_of_structs_full | used_global_vars | code_of_enum | {
+                processed_code, veto = self._run_plugins("pre_verify", "function", function.name, processed_code)
+                if veto is not None:
+                    return (VerifyResult.FEEDBACK, veto), processed_code
+
+                data_type_code = code_of_structs_full | used_global_vars | code_of_enum | code_of_macro_constants \
+                    | code_of_macro_functions | code_of_setjmp_scaffold | {
                     "stdio": used_stdio_code}
                 verification = self.verifier.verify_function(
                     function,
@@ -981,7 +1050,11 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
                 else:
                     break
 
-            self._record_outcome("function", function.name, TranslationOutcome.FALLBACK_C2RUST)
+            function_result, veto = self._run_plugins("post_unidiomatic", "function", function.name, function_result)
+            if veto is not None:
+                self.append_failure_info(function.name, "PLUGIN_VETO", veto, function_result)
+                return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+            self._record_outcome("function", function.name, self._c2rust_outcome())
             utils.save_code(function_save_path, function_result)
             return TranslateResult.SUCCESS
 
@@ -1004,6 +1077,18 @@ The function is the `main` function, which is the entry point of the program. Th
 For `return 0;`, you can directly `return;` in Rust or ignore it if it's the last statement.
 For other return values, you can use `std::process::exit()` to return the value.
 For `argc` and `argv`, you can use `std::env::args()` to get the arguments.
+'''
+
+        if function.is_variadic:
+            prompt += f'''
+The function is variadic. Translate it as `unsafe fn {function.name}_va` with the same parameters followed by a trailing `mut args: core::ffi::VaList` instead of the `...`, and read each variadic argument with `args.arg::<T>()` (default argument promotions apply: `char`/`short` arrive as `libc::c_int` and `float` as `f64`).
+To forward the arguments to a `v*` function such as `vprintf`, declare it inside your function with a `core::ffi::VaList` parameter and pass `args`.
+The system wraps your function into the C entry point `pub unsafe extern "C" fn {function.name}(..., mut args: ...)`, so do **NOT** define `{function.name}` yourself.
+'''
+
+        if function.goto_labels:
+            prompt += f'''
+{goto_plan(function.goto_labels)}
 '''
 
         if len(macro_definitions) > 0:
@@ -1015,6 +1100,38 @@ The function body above may reference the following macros. Use these definition
 ```
 '''
 
+        if len(code_of_macro_constants) > 0:
+            joint_macro_constants = ''.join(code_of_macro_constants.values())
+            prompt += f'''
+The function uses the following `#define` constants, which are already translated as (you should **NOT** define them in your translation, as the system will automatically define them. Refer to them by name instead of repeating their values):
+```rust
+{joint_macro_constants}```
+'''
+
+        if len(code_of_macro_functions) > 0:
+            joint_macro_functions = ''.join(code_of_macro_functions.values())
+            prompt += f'''
+The function uses the following function-like macros, which are already translated as `macro_rules!` macros (you should **NOT** define them in your translation, as the system will automatically define them). Translate each use such as `MAX(a, b)` as `MAX!(a, b)`; only expand a macro by hand where its operand types do not fit:
+```rust
+{joint_macro_functions}```
+'''
+
+        if code_of_setjmp_scaffold:
+            prompt += f'''
+The function uses `setjmp`/`longjmp`, which Rust cannot call. Use the following definitions instead (you should **NOT** define them in your translation, as the system will automatically define them):
+```rust
+{utils.SETJMP_SCAFFOLD_CODE}```
+Declare `jmp_buf` values as `JmpBuf` and pass them as `*mut JmpBuf` (e.g. `&raw mut ENV`). Rewrite `if (setjmp(env) == 0) {{ A }} else {{ B }}` as `match sactor_setjmp(env, || {{ A }}) {{ Ok(..) => .., Err(value) => {{ B }} }}`: the code running until the jump moves into the closure, and `value` is what `setjmp` returned for the jump. Replace `longjmp(env, value)` with `sactor_longjmp(env, value)`.
+'''
+
+        if function.uses_errno:
+            prompt += errno_guidance(function.errno_calls, function.sets_errno, idiomatic=False)
+
+        if function.signal_registrations or function.handled_signals:
+            prompt += signal_plan(function.signal_registrations, function.handled_signals, idiomatic=False)
+
+        prompt += self._cxx_class_guidance(function)
+
         if len(code_of_structs_prompt) > 0:
             joint_code_of_structs = '\n'.join(code_of_structs_prompt.values())
             prompt += f'''
@@ -1180,6 +1297,23 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
             )
         function_result = llm_result["function"]
 
+        if function.is_variadic:
+            try:
+                function_result = rust_ast_parser.generate_variadic_wrapper(
+                    function_result, function.name)
+            except Exception as e:
+                error_message = f"Error: {e}. Translate the variadic function as `{function.name}_va` taking a trailing `core::ffi::VaList`."
+                logger.error("%s", error_message)
+                self.append_failure_info(
+                    function.name, "COMPILE_ERROR", error_message, function_result
+                )
+                return self._translate_function_impl(
+                    function,
+                    verify_result=(VerifyResult.COMPILE_ERROR, error_message),
+                    error_translation=function_result,
+                    attempts=attempts+1
+                )
+
         # TODO: check function signature, must use pointers, not Box, etc.
         try:
             function_result_sigs = rust_ast_parser.get_func_signatures(
@@ -1298,7 +1432,18 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
         logger.debug("Translated function %s:", function.name)
         logger.debug("%s", function_result)
 
-        data_type_code = code_of_structs_full | used_global_vars | code_of_enum | {
+        function_result, veto = self._run_plugins("pre_verify", "function", function.name, function_result)
+        if veto is not None:
+            self.append_failure_info(function.name, "PLUGIN_VETO", veto, function_result)
+            return self._translate_function_impl(
+                function,
+                verify_result=(VerifyResult.FEEDBACK, veto),
+                error_translation=function_result,
+                attempts=attempts+1
+            )
+
+        data_type_code = code_of_structs_full | used_global_vars | code_of_enum | code_of_macro_constants \
+            | code_of_macro_functions | code_of_setjmp_scaffold | {
             "stdio": used_stdio_code}
         # add error handling because here can raise exceptions
         result = self.verifier.verify_function(
@@ -1333,6 +1478,15 @@ Error: Failed to parse the result from LLM, result is not wrapped by the tags as
             )
         function_result = rust_ast_parser.unidiomatic_function_cleanup(
             function_result)
+        function_result, veto = self._run_plugins("post_unidiomatic", "function", function.name, function_result)
+        if veto is not None:
+            self.append_failure_info(function.name, "PLUGIN_VETO", veto, function_result)
+            return self._translate_function_impl(
+                function,
+                verify_result=(VerifyResult.FEEDBACK, veto),
+                error_translation=function_result,
+                attempts=attempts+1
+            )
         self.mark_translation_success("function", function.name)
         utils.save_code(function_save_path, function_result)
         return TranslateResult.SUCCESS
@@ -11,6 +11,9 @@ from typing import Dict, Iterable, Tuple
 _RESOURCE_PACKAGE = "sactor._resources"
 _RESOURCE_NAME = "libc_scalar_map.txt"
 
+# Mappings registered at runtime; they take precedence over the resource file.
+_EXTRA_PAIRS: list[Tuple[str, str]] = []
+
 
 def _read_resource_text() -> str:
     try:
@@ -23,9 +26,7 @@ def _read_resource_text() -> str:
             return handle.read()
 
 
-@lru_cache(maxsize=1)
-def _load_libc_scalar_pairs() -> Tuple[Tuple[str, str], ...]:
-    text = _read_resource_text()
+def _parse_scalar_map(text: str, source: str) -> list[Tuple[str, str]]:
     pairs: list[Tuple[str, str]] = []
 
     for idx, raw_line in enumerate(text.splitlines(), start=1):
@@ -34,18 +35,23 @@ def _load_libc_scalar_pairs() -> Tuple[Tuple[str, str], ...]:
             continue
         if "=" not in line:
             raise ValueError(
-                f"Invalid entry in {_RESOURCE_NAME} on line {idx}: '{raw_line}'"
+                f"Invalid entry in {source} on line {idx}: '{raw_line}'"
             )
         lhs, rhs = line.split("=", 1)
         lhs = lhs.strip()
         rhs = rhs.strip()
         if not lhs or not rhs:
             raise ValueError(
-                f"Invalid entry in {_RESOURCE_NAME} on line {idx}: '{raw_line}'"
+                f"Invalid entry in {source} on line {idx}: '{raw_line}'"
             )
         pairs.append((lhs, rhs))
 
-    return tuple(pairs)
+    return pairs
+
+
+@lru_cache(maxsize=1)
+def _load_libc_scalar_pairs() -> Tuple[Tuple[str, str], ...]:
+    return tuple(_parse_scalar_map(_read_resource_text(), _RESOURCE_NAME))
 
 
 def get_libc_scalar_pairs() -> Tuple[Tuple[str, str], ...]:
@@ -57,7 +63,28 @@ def get_libc_scalar_pairs() -> Tuple[Tuple[str, str], ...]:
 def get_libc_scalar_map() -> Dict[str, str]:
     """Return a mapping of libc scalar aliases to Rust primitive types."""
 
-    return dict(_load_libc_scalar_pairs())
+    return dict(_load_libc_scalar_pairs()) | dict(_EXTRA_PAIRS)
+
+
+def register_scalar_mapping(src: str, dst: str) -> None:
+    """Map the scalar typedef ``src`` to the primitive ``dst`` here and in rust_ast_parser."""
+
+    from sactor import rust_ast_parser
+
+    rust_ast_parser.register_scalar_mapping(src, dst)
+    _EXTRA_PAIRS.append((src.strip(), dst.strip()))
+
+
+def load_scalar_map(path: str) -> int:
+    """Register every mapping in ``path`` (same format as the resource file)."""
+
+    from sactor import rust_ast_parser
+
+    with open(path, "r", encoding="utf-8") as handle:
+        pairs = _parse_scalar_map(handle.read(), path)
+    count = rust_ast_parser.load_scalar_map(path)
+    _EXTRA_PAIRS.extend(pairs)
+    return count
 
 
 def map_libc_scalar(name: str | None) -> str | None:
@@ -69,7 +96,7 @@ def map_libc_scalar(name: str | None) -> str | None:
     if not candidate:
         return None
 
-    for full, target in _load_libc_scalar_pairs():
+    for full, target in (*reversed(_EXTRA_PAIRS), *_load_libc_scalar_pairs()):
         if candidate == full:
             return target
         tail = full.split("::")[-1]
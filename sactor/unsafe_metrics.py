@@ -0,0 +1,146 @@
+import csv
+import io
+import os
+from dataclasses import asdict, dataclass, field
+from typing import Optional
+
+from sactor import logging as sactor_logging
+from sactor import rust_ast_parser, utils
+
+logger = sactor_logging.get_logger(__name__)
+
+# build output and version control, which hold no translated code
+SKIPPED_DIRS = {"target", ".git"}
+
+
+def _fraction(tokens: int, unsafe_tokens: int) -> float:
+    return unsafe_tokens / tokens if tokens else 0.0
+
+
+@dataclass
+class FunctionMetrics:
+    name: str
+    tokens: int
+    unsafe_tokens: int
+
+    @property
+    def unsafe_fraction(self) -> float:
+        return _fraction(self.tokens, self.unsafe_tokens)
+
+
+@dataclass
+class FileMetrics:
+    # relative to the measured path
+    path: str
+    tokens: int = 0
+    unsafe_tokens: int = 0
+    # top-level functions, by name
+    functions: list[FunctionMetrics] = field(default_factory=list)
+    # why the file could not be measured, e.g. a syntax error
+    error: Optional[str] = None
+
+    @property
+    def unsafe_fraction(self) -> float:
+        return _fraction(self.tokens, self.unsafe_tokens)
+
+
+@dataclass
+class MetricsReport:
+    path: str
+    files: list[FileMetrics]
+
+    @property
+    def tokens(self) -> int:
+        return sum(f.tokens for f in self.files)
+
+    @property
+    def unsafe_tokens(self) -> int:
+        return sum(f.unsafe_tokens for f in self.files)
+
+    @property
+    def unsafe_fraction(self) -> float:
+        return _fraction(self.tokens, self.unsafe_tokens)
+
+    def to_dict(self) -> dict:
+        files = []
+        for f in self.files:
+            entry = asdict(f)
+            entry["unsafe_fraction"] = f.unsafe_fraction
+            for function, metrics in zip(entry["functions"], f.functions):
+                function["unsafe_fraction"] = metrics.unsafe_fraction
+            files.append(entry)
+        return {
+            "path": self.path,
+            "tokens": self.tokens,
+            "unsafe_tokens": self.unsafe_tokens,
+            "unsafe_fraction": self.unsafe_fraction,
+            "files": files,
+        }
+
+
+def rust_files(path: str) -> list[str]:
+    """`path` if it is a Rust file, else the Rust files under it, outside of build output."""
+    if os.path.isfile(path):
+        return [path]
+    if not os.path.isdir(path):
+        raise FileNotFoundError(f"No such Rust file or directory: {path}")
+    files = []
+    for root, dirs, names in os.walk(path):
+        dirs[:] = sorted(d for d in dirs if d not in SKIPPED_DIRS)
+        files.extend(os.path.join(root, name) for name in sorted(names) if name.endswith(".rs"))
+    return files
+
+
+def file_metrics(path: str, relative_path: str) -> FileMetrics:
+    """The tokens of the function bodies in the Rust file `path`, and how many are unsafe, as `count_unsafe_tokens` counts them."""
+    metrics = FileMetrics(relative_path)
+    code = utils.read_file(path)
+    try:
+        metrics.tokens, metrics.unsafe_tokens = rust_ast_parser.count_unsafe_tokens(code)
+        names = sorted(rust_ast_parser.get_func_signatures(code))
+        for name in names:
+            tokens, unsafe_tokens = rust_ast_parser.count_unsafe_tokens(
+                rust_ast_parser.get_function_definition(code, name))
+            metrics.functions.append(FunctionMetrics(name, tokens, unsafe_tokens))
+    except (SyntaxError, ValueError) as e:
+        logger.warning("Cannot measure %s: %s", path, str(e).splitlines()[0])
+        return FileMetrics(relative_path, error=str(e).splitlines()[0])
+    return metrics
+
+
+def collect_metrics(path: str) -> MetricsReport:
+    """The unsafe metrics of the Rust file `path`, or of every Rust file in the crate or result directory `path`."""
+    root = path if os.path.isdir(path) else os.path.dirname(path)
+    files = [file_metrics(file, os.path.relpath(file, root)) for file in rust_files(path)]
+    return MetricsReport(path, files)
+
+
+def format_metrics(report: MetricsReport, functions: bool = True) -> str:
+    """`report` as text: a table of the files, one of their functions, and the aggregate unsafe ratio."""
+    file_rows = [[f.path, "-", "-", f"error: {f.error}"] if f.error else
+                 [f.path, f.tokens, f.unsafe_tokens, f"{f.unsafe_fraction:.1%}"] for f in report.files]
+    sections = [utils.format_table(["File", "Tokens", "Unsafe", "Unsafe %"], file_rows)]
+    if functions:
+        function_rows = [[f.path, function.name, function.tokens, function.unsafe_tokens,
+                          f"{function.unsafe_fraction:.1%}"]
+                         for f in report.files for function in f.functions]
+        if function_rows:
+            sections.append(utils.format_table(["File", "Function", "Tokens", "Unsafe", "Unsafe %"], function_rows))
+    measured = sum(1 for f in report.files if f.error is None)
+    sections.append(f"Total: {report.unsafe_tokens} of {report.tokens} tokens unsafe "
+                    f"({report.unsafe_fraction:.1%}) in {measured} file(s)"
+                    + (f", {len(report.files) - measured} could not be parsed" if measured < len(report.files) else ""))
+    return "\n\n".join(sections)
+
+
+def metrics_csv(report: MetricsReport) -> str:
+    """`report` as CSV, a row per file with an empty function, then a row per function of it."""
+    out = io.StringIO()
+    writer = csv.writer(out, lineterminator="\n")
+    writer.writerow(["file", "function", "tokens", "unsafe_tokens", "unsafe_fraction", "error"])
+    for f in report.files:
+        writer.writerow([f.path, "", f.tokens, f.unsafe_tokens, f"{f.unsafe_fraction:.4f}", f.error or ""])
+        for function in f.functions:
+            writer.writerow([f.path, function.name, function.tokens, function.unsafe_tokens,
+                             f"{function.unsafe_fraction:.4f}", ""])
+    return out.getvalue()
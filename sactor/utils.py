@@ -11,6 +11,7 @@ import tomli as toml
 import sys
 import time
 import select
+import signal
 from sactor import logging as sactor_logging
 from sactor import rust_ast_parser
 from sactor.data_types import DataType
@@ -144,10 +145,318 @@ def _copy_resource_tree(resource_root, destination: Path) -> None:
     for child in resource_root.iterdir():
         _copy(child, destination_path / child.name)
 
+# Crates generated code may use beyond libc, keyed by their path prefix
+OPTIONAL_DEPENDENCIES = {
+    "serde": 'serde = { version = "1", features = ["derive"] }',
+    "serde_json": 'serde_json = "1"',
+    "arbitrary": 'arbitrary = "1"',
+}
+
+
+def optional_dependencies(rust_code: str) -> list[str]:
+    """Manifest lines for the `OPTIONAL_DEPENDENCIES` crates the code uses."""
+    return [
+        dep for crate, dep in OPTIONAL_DEPENDENCIES.items()
+        if re.search(rf"\b{crate}::", rust_code)
+    ]
+
+
+C_VARIADIC_FEATURE = "#![feature(c_variadic)]"
+
+# Idiomatic stand-in for C `...` arguments: variadic functions take `args: &[VarArg]`
+VARARG_ENUM_CODE = '''#[derive(Debug, Clone, PartialEq)]
+pub enum VarArg {
+    Int(i64),
+    UInt(u64),
+    Double(f64),
+    Str(String),
+    Ptr(usize),
+}
+'''
+
+
+# Stand-in for C `setjmp`/`longjmp`, which Rust cannot call: `sactor_setjmp`
+# catches the unwinding started by `sactor_longjmp` for the same `JmpBuf`
+SETJMP_SCAFFOLD_CODE = '''/// Storage for a C `jmp_buf`, large and aligned enough for the `jmp_buf` of any glibc target.
+#[repr(C, align(16))]
+pub struct JmpBuf(pub [u8; 512]);
+
+/// Unwinding payload of a `sactor_longjmp` to an environment set by `sactor_setjmp`.
+pub struct SactorLongJmp {
+    pub env: usize,
+    pub value: i32,
+}
+
+thread_local! {
+    static SACTOR_SETJMP_ENVS: std::cell::RefCell<Vec<usize>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// `setjmp(env)`: runs `body` and returns `Ok` with its result, or `Err(value)`
+/// when `body` (or anything it calls) performs `sactor_longjmp(env, value)`.
+pub fn sactor_setjmp<T>(env: *mut JmpBuf, body: impl FnOnce() -> T) -> Result<T, i32> {
+    let key = env as usize;
+    SACTOR_SETJMP_ENVS.with(|envs| envs.borrow_mut().push(key));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(body));
+    SACTOR_SETJMP_ENVS.with(|envs| envs.borrow_mut().pop());
+    match result {
+        Ok(value) => Ok(value),
+        Err(payload) => match payload.downcast::<SactorLongJmp>() {
+            Ok(jump) if jump.env == key => Err(jump.value),
+            Ok(jump) => std::panic::resume_unwind(jump),
+            Err(payload) => std::panic::resume_unwind(payload),
+        },
+    }
+}
+
+/// `longjmp(env, value)`: unwinds to the `sactor_setjmp` of `env`, or calls the
+/// C `longjmp` when `env` was set by C code.
+///
+/// # Safety
+/// `env` must have been set by `sactor_setjmp` or C `setjmp` in a caller still running.
+#[no_mangle]
+pub unsafe extern "C-unwind" fn sactor_longjmp(env: *mut JmpBuf, value: i32) -> ! {
+    let key = env as usize;
+    if SACTOR_SETJMP_ENVS.with(|envs| envs.borrow().contains(&key)) {
+        let value = if value == 0 { 1 } else { value };
+        std::panic::resume_unwind(Box::new(SactorLongJmp { env: key, value }));
+    }
+    extern "C" {
+        fn longjmp(env: *mut JmpBuf, value: i32) -> !;
+    }
+    longjmp(env, value)
+}
+'''
+
+
+# Signal registration for idiomatic code, in the style of `signal_hook`: a flag
+# store, or a plain `fn` called from the handler
+SIGNAL_SCAFFOLD_CODE = '''const SACTOR_NSIG: usize = 65;
+
+static SACTOR_SIGNAL_FLAGS: [std::sync::atomic::AtomicPtr<std::sync::atomic::AtomicI32>; SACTOR_NSIG] =
+    [const { std::sync::atomic::AtomicPtr::new(std::ptr::null_mut()) }; SACTOR_NSIG];
+static SACTOR_SIGNAL_HANDLERS: [std::sync::atomic::AtomicUsize; SACTOR_NSIG] =
+    [const { std::sync::atomic::AtomicUsize::new(0) }; SACTOR_NSIG];
+
+extern "C" fn sactor_signal_trampoline(signal: libc::c_int) {
+    use std::sync::atomic::Ordering;
+    let index = signal as usize;
+    let flag = SACTOR_SIGNAL_FLAGS[index].load(Ordering::SeqCst);
+    if !flag.is_null() {
+        // SAFETY: only `'static` flags are registered
+        unsafe { (*flag).store(signal, Ordering::SeqCst) };
+    }
+    let handler = SACTOR_SIGNAL_HANDLERS[index].load(Ordering::SeqCst);
+    if handler != 0 {
+        // SAFETY: only `fn(i32)` pointers are registered
+        let handler: fn(i32) = unsafe { std::mem::transmute::<usize, fn(i32)>(handler) };
+        handler(signal);
+    }
+}
+
+fn sactor_set_disposition(signal: i32, disposition: libc::sighandler_t) -> std::io::Result<()> {
+    if signal <= 0 || signal as usize >= SACTOR_NSIG {
+        return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    // SAFETY: a zeroed `sigaction` is valid, and the handler is async-signal-safe
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = disposition;
+        // the BSD semantics of glibc's `signal`: interrupted calls restart
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(signal, &action, std::ptr::null_mut()) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Stores the signal number in `flag` whenever `signal` arrives, like
+/// `signal_hook::flag::register`; poll it with `flag.swap(0, Ordering::SeqCst)`.
+pub fn sactor_signal_flag(signal: i32, flag: &'static std::sync::atomic::AtomicI32) -> std::io::Result<()> {
+    if let Some(slot) = SACTOR_SIGNAL_FLAGS.get(signal.max(0) as usize) {
+        slot.store(flag as *const _ as *mut _, std::sync::atomic::Ordering::SeqCst);
+    }
+    sactor_set_disposition(signal, sactor_signal_trampoline as *const () as libc::sighandler_t)
+}
+
+/// Calls `handler` whenever `signal` arrives, like `signal_hook::low_level::register`.
+///
+/// # Safety
+/// `handler` runs inside the signal handler: it may only use atomics and
+/// async-signal-safe libc functions (no allocation, locks or `println!`).
+pub unsafe fn sactor_signal(signal: i32, handler: fn(i32)) -> std::io::Result<()> {
+    if let Some(slot) = SACTOR_SIGNAL_HANDLERS.get(signal.max(0) as usize) {
+        slot.store(handler as usize, std::sync::atomic::Ordering::SeqCst);
+    }
+    sactor_set_disposition(signal, sactor_signal_trampoline as *const () as libc::sighandler_t)
+}
+
+/// `signal(signal, SIG_IGN)`.
+pub fn sactor_ignore_signal(signal: i32) -> std::io::Result<()> {
+    sactor_set_disposition(signal, libc::SIG_IGN)
+}
+
+/// `signal(signal, SIG_DFL)`.
+pub fn sactor_default_signal(signal: i32) -> std::io::Result<()> {
+    sactor_set_disposition(signal, libc::SIG_DFL)
+}
+'''
+
+
+def uses_signal_scaffold(rust_code: str) -> bool:
+    """Whether the code refers to the items of `SIGNAL_SCAFFOLD_CODE`."""
+    return re.search(r'\bsactor_(signal|signal_flag|ignore_signal|default_signal)\b', rust_code) is not None
+
+
+def uses_setjmp_scaffold(rust_code: str) -> bool:
+    """Whether the code refers to the items of `SETJMP_SCAFFOLD_CODE`."""
+    return re.search(r'\b(JmpBuf|sactor_setjmp|sactor_longjmp)\b', rust_code) is not None
+
+
+def defines_c_variadic(rust_code: str) -> bool:
+    """Whether the code defines C-variadic functions, which need `C_VARIADIC_FEATURE`."""
+    try:
+        return rust_ast_parser.defines_c_variadic(rust_code)
+    except Exception:
+        # unparsable code fails to build either way
+        return False
+
+
+def enable_c_variadic(rust_code: str) -> str:
+    """Prepends `C_VARIADIC_FEATURE` to crate roots that define C-variadic functions."""
+    if C_VARIADIC_FEATURE in rust_code or not defines_c_variadic(rust_code):
+        return rust_code
+    return f"{C_VARIADIC_FEATURE}\n{rust_code}"
+
+
+# Toolchain generated crates must build on when `general.stable_output` is set
+_stable_toolchain: str | None = None
+_previous_toolchain: str | None = None
+
+
+def set_stable_output(msrv: str | None) -> None:
+    """
+    Build every generated crate with the stable toolchain (or the ``msrv``
+    release) and reject code needing nightly; ``None`` turns the mode off.
+    """
+    global _stable_toolchain, _previous_toolchain
+    if _stable_toolchain is None:
+        _previous_toolchain = os.environ.get("RUSTUP_TOOLCHAIN")
+    if msrv is None:
+        _stable_toolchain = None
+        if _previous_toolchain is None:
+            os.environ.pop("RUSTUP_TOOLCHAIN", None)
+        else:
+            os.environ["RUSTUP_TOOLCHAIN"] = _previous_toolchain
+        return
+    _stable_toolchain = msrv or "stable"
+    # overrides the repository's nightly rust-toolchain.toml for every cargo call
+    os.environ["RUSTUP_TOOLCHAIN"] = _stable_toolchain
+
+
+def stable_output() -> bool:
+    return _stable_toolchain is not None
+
+
+def nightly_constructs(rust_code: str) -> list[dict]:
+    """Constructs in the code that only build on nightly (see `find_nightly_constructs`)."""
+    from sactor import rust_ast_parser
+
+    try:
+        return rust_ast_parser.find_nightly_constructs(rust_code)
+    except Exception:
+        # unparsable code fails to build either way
+        return []
+
+
+def describe_nightly_constructs(constructs: list[dict]) -> str:
+    details = {
+        "feature": "`#![feature({detail})]`",
+        "c_variadic": "C-variadic function `{detail}`",
+        "va_list": "`{detail}`",
+        "intrinsics": "intrinsic `{detail}`",
+        "unstable_attribute": "`#[{detail}]`",
+    }
+    lines = [
+        f"line {c['line']}: " + details[c["kind"]].format(detail=c["detail"])
+        for c in constructs
+    ]
+    return ("The code must build on the stable Rust toolchain, but uses nightly-only constructs:\n"
+            + "\n".join(lines))
+
+
+NO_STD_CRATE_ATTRS = "#![no_std]\nextern crate alloc;"
+
+# Stand-in for a crate-local `no_std.logger` function, e.g. `crate::logger`
+LOGGER_STUB = '''
+/// Receives the translation's `print!` output; write it to the board's UART or RTT channel.
+pub struct SactorLogger;
+
+impl core::fmt::Write for SactorLogger {{
+    fn write_str(&mut self, _s: &str) -> core::fmt::Result {{
+        Ok(())
+    }}
+}}
+
+pub fn {name}() -> SactorLogger {{
+    SactorLogger
+}}
+'''
+
+
+def no_std_profile(config: dict) -> bool:
+    return config['general'].get('profile', 'std') == 'no_std'
+
+
+def no_std_crate(rust_code: str, logger: str = "") -> str:
+    """
+    The code as the root of a `#![no_std]` + `alloc` library (see
+    `rust_ast_parser.convert_to_no_std`), with a stub for a crate-local
+    ``logger``. Raises ValueError listing the constructs that need std.
+    """
+    from sactor import rust_ast_parser
+
+    code = rust_ast_parser.convert_to_no_std(rust_code, logger or None)
+    local_logger = re.fullmatch(r"crate::(\w+)", logger)
+    if local_logger and not re.search(rf"\bfn\s+{local_logger.group(1)}\b", code):
+        code += LOGGER_STUB.format(name=local_logger.group(1))
+    return f"{NO_STD_CRATE_ATTRS}\n{code}"
+
+
+def enable_alloc(rust_code: str) -> str:
+    """Declares the `alloc` crate in crate roots whose paths refer to it."""
+    if re.search(r"\bextern\s+crate\s+alloc\b", rust_code) \
+            or not re.search(r"(?<![\w:])alloc::", rust_code):
+        return rust_code
+    # after the crate's inner attributes
+    attrs_end = re.match(r"(?:\s*#!\[[^\]]*\])*", rust_code).end()
+    return f"{rust_code[:attrs_end]}\nextern crate alloc;\n{rust_code[attrs_end:]}".lstrip("\n")
+
+
+# `rustfmt.toml` copied into generated crates so `cargo fmt` keeps its style
+_rustfmt_config: str | None = None
+
+
+def set_rustfmt_config(path: str | None) -> None:
+    """Format emitted code (rust_ast_parser output and `cargo fmt` in generated crates) with ``path``."""
+    from sactor import rust_ast_parser
+
+    if path:
+        path = os.path.abspath(path)
+        if os.path.isdir(path):
+            path = os.path.join(path, "rustfmt.toml")
+    rust_ast_parser.set_rustfmt_config(path or None)
+    global _rustfmt_config
+    _rustfmt_config = path or None
+
+
 def create_rust_proj(rust_code, proj_name, path, is_lib: bool, proc_macro=False):
     if os.path.exists(path):
         shutil.rmtree(path)
     os.makedirs(os.path.join(path, "src"), exist_ok=True)
+    if _rustfmt_config:
+        shutil.copyfile(_rustfmt_config, os.path.join(path, "rustfmt.toml"))
 
     manifest = f'''
 [package]
@@ -160,6 +469,7 @@ libc = "0.2.159"'''
     if proc_macro:
         manifest += '''
 sactor_proc_macros = { path = "./sactor_proc_macros" }'''
+    manifest += "".join(f"\n{dep}" for dep in optional_dependencies(rust_code))
 
     if is_lib:
         manifest += f'''
@@ -170,6 +480,8 @@ crate-type = ["cdylib"]'''
     with open(f"{path}/Cargo.toml", "w") as f:
         f.write(manifest)
 
+    # inner attributes such as the c_variadic gate must come first
+    rust_code = enable_c_variadic(enable_alloc(rust_code))
     if is_lib:
         with open(f"{path}/src/lib.rs", "w") as f:
             f.write(rust_code)
@@ -813,6 +1125,23 @@ def get_compile_flags_from_commands(processed_compile_commands: List[List[str]])
     flags_without_tests = flags
     return flags_without_tests
 
+def format_table(headers: list[str], rows: list[list]) -> str:
+    """
+    `rows` as a plain text table under `headers`, with the columns of numbers
+    (or amounts, as "$1.50" or "12%", and "-" for none) right-aligned.
+    """
+    cells = [[str(value) for value in row] for row in rows]
+    numeric = [bool(cells) and all(re.fullmatch(r"[$]?-?[\d.,]+%?|-", row[i]) for row in cells)
+               for i in range(len(headers))]
+    widths = [max([len(header)] + [len(row[i]) for row in cells]) for i, header in enumerate(headers)]
+
+    def line(values: list[str]) -> str:
+        return "  ".join(value.rjust(width) if right else value.ljust(width)
+                         for value, width, right in zip(values, widths, numeric)).rstrip()
+
+    return "\n".join([line(headers), line(["-" * width for width in widths])] + [line(row) for row in cells])
+
+
 def read_file(path: str) -> str:
     if not os.path.exists(path):
         raise FileNotFoundError(f"Could not find file {path}")
@@ -868,6 +1197,105 @@ def sanitize_config(
 ProcessResult = namedtuple("ProcessResult", ["stdout", "stderr", "returncode"])
 
 
+@dataclass(frozen=True)
+class ScheduledSignal:
+    """
+    A signal sent to a running test command `after` seconds, to its whole
+    process group (e.g. the target under a `sh -c` wrapper) if `group`.
+    """
+    signum: int
+    after: float = 0.0
+    group: bool = False
+
+    @classmethod
+    def from_spec(cls, spec) -> "ScheduledSignal":
+        """Reads `{"signal": "SIGINT", "after": 0.5, "group": false}`; raises ValueError if malformed."""
+        if not isinstance(spec, dict):
+            raise ValueError("each of 'signals' must be an object with a 'signal'")
+        name = spec.get("signal")
+        if isinstance(name, str):
+            try:
+                signum = int(signal.Signals[name if name.startswith("SIG") else f"SIG{name}"])
+            except KeyError:
+                raise ValueError(f"unknown signal '{name}'") from None
+        elif isinstance(name, int) and not isinstance(name, bool) and name > 0:
+            signum = name
+        else:
+            raise ValueError("'signal' must be a signal name such as \"SIGINT\" or a number")
+        after = spec.get("after", 0)
+        if isinstance(after, bool) or not isinstance(after, (int, float)) or after < 0:
+            raise ValueError("'after' must be a non-negative number of seconds")
+        group = spec.get("group", False)
+        if not isinstance(group, bool):
+            raise ValueError("'group' must be a boolean")
+        return cls(signum, float(after), group)
+
+
+@dataclass(frozen=True)
+class TaskRunOptions:
+    """
+    Per-task overrides of how a test command runs, read from a test task or
+    test sample: `timeout` in seconds, `memory_limit_mb` capping the address
+    space, `cwd` relative to the default working directory, extra `env`
+    variables, and `signals` to send while it runs (test tasks only).
+    """
+    timeout: Optional[float] = None
+    memory_limit_mb: Optional[int] = None
+    cwd: Optional[str] = None
+    env: Optional[dict] = None
+    signals: tuple[ScheduledSignal, ...] = ()
+
+    @classmethod
+    def from_task(cls, task: dict) -> "TaskRunOptions":
+        """Reads the options of `task`; raises ValueError if any is malformed."""
+        timeout = task.get("timeout")
+        if timeout is not None and (
+                isinstance(timeout, bool) or not isinstance(timeout, (int, float)) or timeout <= 0):
+            raise ValueError("'timeout' must be a positive number of seconds")
+        memory_limit_mb = task.get("memory_limit_mb")
+        if memory_limit_mb is not None and (
+                isinstance(memory_limit_mb, bool) or not isinstance(memory_limit_mb, int)
+                or memory_limit_mb <= 0):
+            raise ValueError("'memory_limit_mb' must be a positive integer")
+        cwd = task.get("cwd")
+        if cwd is not None and not isinstance(cwd, str):
+            raise ValueError("'cwd' must be a string")
+        env = task.get("env")
+        if env is not None and (
+                not isinstance(env, dict)
+                or not all(isinstance(k, str) and isinstance(v, str) for k, v in env.items())):
+            raise ValueError("'env' must map variable names to strings")
+        signals = task.get("signals", [])
+        if not isinstance(signals, list):
+            raise ValueError("'signals' must be a list")
+        return cls(timeout, memory_limit_mb, cwd, env,
+                   tuple(ScheduledSignal.from_spec(spec) for spec in signals))
+
+    def resolve_cwd(self, default_cwd: str) -> str:
+        if not self.cwd:
+            return default_cwd
+        return os.path.normpath(os.path.join(default_cwd, self.cwd))
+
+    def merge_env(self, env: dict[str, str] | None) -> dict[str, str] | None:
+        if not self.env:
+            return env
+        merged = dict(os.environ if env is None else env)
+        merged.update(self.env)
+        return merged
+
+
+def _with_memory_limit(cmd: Sequence[str | os.PathLike[str]], memory_limit_mb: int | None) -> list:
+    """``cmd`` run through a shell that caps its address space first.
+
+    A ``preexec_fn`` would do the same, but it is not safe once sactor runs
+    commands from several threads (``test_jobs``), as the child may deadlock
+    before ``exec``; the shell ``exec``s the command, so it keeps the pid.
+    """
+    if memory_limit_mb is None:
+        return list(cmd)
+    return ["sh", "-c", f'ulimit -v {memory_limit_mb * 1024} && exec "$@"', "sh", *cmd]
+
+
 def _extend_with_limit(buffer: bytearray, chunk: bytes, limit: int) -> bool:
     """Append ``chunk`` into ``buffer`` up to ``limit`` bytes.
 
@@ -883,6 +1311,26 @@ def _extend_with_limit(buffer: bytearray, chunk: bytes, limit: int) -> bool:
     return len(chunk) > remaining
 
 
+def signal_name(signum: int) -> str:
+    try:
+        return signal.Signals(signum).name
+    except ValueError:
+        return f"signal {signum}"
+
+
+def _send_scheduled_signal(process: subprocess.Popen, scheduled: ScheduledSignal) -> None:
+    if process.poll() is not None:
+        return
+    logger.debug("Sending signal %d to command %d", scheduled.signum, process.pid)
+    try:
+        if scheduled.group:
+            os.killpg(process.pid, scheduled.signum)
+        else:
+            process.send_signal(scheduled.signum)
+    except ProcessLookupError:
+        pass
+
+
 def _run_command_streaming(
     cmd: Sequence[str | os.PathLike[str]],
     *,
@@ -891,20 +1339,25 @@ def _run_command_streaming(
     env: dict[str, str] | None,
     cwd: str | os.PathLike[str] | None,
     text: bool,
+    memory_limit_mb: int | None = None,
+    signals: Sequence[ScheduledSignal] = (),
 ) -> ProcessResult:
     if limit_bytes is None or limit_bytes <= 0:
         raise ValueError("limit_bytes must be a positive integer")
 
     configured_time_limit = time_limit_sec
     process = subprocess.Popen(
-        cmd,
+        _with_memory_limit(cmd, memory_limit_mb),
         stdout=subprocess.PIPE,
         stderr=subprocess.PIPE,
         bufsize=0,
         env=env,
         cwd=cwd,
         text=False,
+        # signals for the process group must not reach sactor itself
+        start_new_session=any(scheduled.group for scheduled in signals),
     )
+    pending_signals = sorted(signals, key=lambda scheduled: scheduled.after)
 
     stdout_buf = bytearray()
     stderr_buf = bytearray()
@@ -927,9 +1380,15 @@ def _run_command_streaming(
             process.terminate()
             time_limit_sec = None  # avoid repeated termination attempts
 
+        while pending_signals and now - start_time >= pending_signals[0].after:
+            _send_scheduled_signal(process, pending_signals.pop(0))
+
         timeout = None
         if time_limit_sec is not None:
             timeout = max(0.0, min(0.2, time_limit_sec - (now - start_time)))
+        if pending_signals:
+            until_signal = max(0.0, pending_signals[0].after - (now - start_time))
+            timeout = until_signal if timeout is None else min(timeout, until_signal)
 
         readable, _, _ = select.select(streams, [], [], timeout)
         if not readable:
@@ -1014,13 +1473,19 @@ def run_command(
     cwd: str | os.PathLike[str] | None = None,
     check: bool = False,
     input_data: str | bytes | None = None,
+    memory_limit_mb: int | None = None,
+    signals: Sequence[ScheduledSignal] = (),
 ) -> ProcessResult:
     """
     Unified command execution helper.
 
     Streams output when ``limit_bytes`` is provided, enforcing byte/time limits.
     Otherwise delegates to ``subprocess.run`` with consistent return semantics.
+    ``memory_limit_mb`` caps the address space of the command, and ``signals``
+    are sent to it while it runs (always streaming).
     """
+    if signals and limit_bytes is None:
+        limit_bytes = sys.maxsize
     if limit_bytes is not None:
         if not capture_output:
             raise ValueError("capture_output must be True when enforcing byte limits")
@@ -1034,6 +1499,8 @@ def run_command(
             env=env,
             cwd=cwd,
             text=text,
+            memory_limit_mb=memory_limit_mb,
+            signals=signals,
         )
         if check and result.returncode != 0:
             raise subprocess.CalledProcessError(
@@ -1042,7 +1509,7 @@ def run_command(
         return result
 
     completed = subprocess.run(
-        cmd,
+        _with_memory_limit(cmd, memory_limit_mb),
         stdout=subprocess.PIPE if capture_output else None,
         stderr=subprocess.PIPE if capture_output else None,
         env=env,
@@ -0,0 +1,63 @@
+import os
+import shlex
+import stat
+from dataclasses import dataclass, field
+from typing import Optional
+
+
+def gnu_triple(rust_triple: str) -> str:
+    """The GNU toolchain prefix of a Rust target, e.g. `aarch64-unknown-linux-gnu` -> `aarch64-linux-gnu`."""
+    return rust_triple.replace("-unknown-", "-")
+
+
+@dataclass
+class CrossTarget:
+    """
+    A non-host target verification builds and runs on (`[cross]` config):
+    Rust code is built with `cargo build --target`, the C harness with the
+    matching cross compiler, and test binaries run under `runner` (e.g.
+    qemu-user), so type-width and `char`-signedness differences show up.
+    """
+    triple: str
+    c_compiler: str
+    runner: list[str] = field(default_factory=list)
+
+    @classmethod
+    def from_config(cls, config: dict) -> Optional["CrossTarget"]:
+        cross_config = config.get('cross', {})
+        triple = cross_config.get('target', '')
+        if not triple:
+            return None
+        return cls(
+            triple=triple,
+            c_compiler=cross_config.get('c_compiler', '') or f"{gnu_triple(triple)}-gcc",
+            runner=shlex.split(cross_config.get('runner', '')),
+        )
+
+    def cargo_args(self) -> list[str]:
+        return ["--target", self.triple]
+
+    def cargo_env(self, env: Optional[dict] = None) -> dict:
+        """`env` (default: the current environment) linking Rust artifacts with the cross compiler."""
+        env = dict(os.environ if env is None else env)
+        env[f"CARGO_TARGET_{self.triple.upper().replace('-', '_')}_LINKER"] = self.c_compiler
+        return env
+
+    def target_dir(self, crate_dir: str) -> str:
+        return os.path.join(crate_dir, "target", self.triple, "debug")
+
+    def runnable(self, executable: str) -> str:
+        """
+        A path test commands can run in place of `executable` (`%t`): a
+        wrapper script invoking it under the runner, or the executable itself
+        when target binaries run directly (e.g. through binfmt_misc).
+        """
+        if not self.runner:
+            return executable
+        executable = os.path.abspath(executable)
+        wrapper = f"{executable}.run"
+        command = " ".join(shlex.quote(arg) for arg in [*self.runner, executable])
+        with open(wrapper, "w", encoding="utf-8") as fh:
+            fh.write(f'#!/bin/sh\nexec {command} "$@"\n')
+        os.chmod(wrapper, os.stat(wrapper).st_mode | stat.S_IXUSR | stat.S_IXGRP | stat.S_IXOTH)
+        return wrapper
@@ -42,7 +42,7 @@ class E2EVerifier(Verifier):
     def verify_function(self):
         raise NotImplementedError("Can not verify function in E2EVerifier")
 
-    def e2e_verify(self, code: str) -> tuple[VerifyResult, Optional[str]]:
+    def e2e_verify(self, code: str, threaded: bool = False) -> tuple[VerifyResult, Optional[str]]:
         # try compile the code
         compile_result = self.try_compile_rust_code(code, self.is_executable)
 
@@ -51,8 +51,9 @@ class E2EVerifier(Verifier):
 
         # Run the tests
         if self.is_executable:
-            test_error = self._run_tests(
-                os.path.join(self.build_attempt_path, "target", "debug", "build_attempt"))
+            executable = os.path.join(self._rust_target_dir(self.build_attempt_path), "build_attempt")
+            test_error = self._run_tests_repeatedly(
+                lambda: self._run_tests(executable), self._test_repetitions(threaded))
         else:
             # Library case: we must link provided object files against the built Rust lib
             executable_variants = self._iter_executable_variants()
@@ -60,15 +61,16 @@ class E2EVerifier(Verifier):
                 raise ValueError(
                     "executable_object must be provided for library code")
 
+            rust_lib_dir = self._rust_target_dir(self.build_attempt_path)
             link_flags = [
-                f'-L{self.build_attempt_path}/target/debug',
+                f'-L{rust_lib_dir}',
                 '-lbuild_attempt',
                 '-lm',
             ]
             program_combiner_path = os.path.join(self.build_path, "program_combiner")
             os.makedirs(program_combiner_path, exist_ok=True)
-            compiler = utils.get_compiler()
-            env = utils.patched_env("LD_LIBRARY_PATH", f"{self.build_attempt_path}/target/debug")
+            compiler = self._c_compiler()
+            env = utils.patched_env("LD_LIBRARY_PATH", rust_lib_dir)
 
             extra_compile_args = shlex.split(self.extra_compile_command) if self.extra_compile_command else []
 
@@ -92,7 +94,8 @@ class E2EVerifier(Verifier):
                     raise RuntimeError("Error: Failed to compile combined program for variant %s" % index)
 
                 logger.debug("Running E2E tests for variant %s", index)
-                last_result = self._run_tests(output_path, env=env)
+                last_result = self._run_tests_repeatedly(
+                    lambda: self._run_tests(output_path, env=env), self._test_repetitions(threaded))
                 if last_result[0] != VerifyResult.SUCCESS:
                     logger.error("E2E tests failed for variant %s", index)
                     return last_result[:2]
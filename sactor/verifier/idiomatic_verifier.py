@@ -1,5 +1,6 @@
 import os
 import json as json
+import re
 from typing import Optional, override
 
 from sactor import logging as sactor_logging, rust_ast_parser, utils
@@ -10,7 +11,7 @@ from sactor.llm import LLM
 from .verifier import Verifier
 from .verifier_types import VerifyResult
 from .selftest.struct_roundtrip import StructRoundTripTester
-from sactor.verifier.spec.harness_codegen import generate_struct_harness_from_spec_file, generate_function_harness_from_spec_file
+from sactor.verifier.spec.harness_codegen import generate_struct_harness_from_spec_file, generate_function_harness_from_spec_file, use_core_ffi
 
 logger = sactor_logging.get_logger(__name__)
 
@@ -366,6 +367,11 @@ unsafe fn C{struct_name}_to_{idiom_name}_mut(input: *mut C{struct_name}) -> &'st
 '''
             prompt += "```\n"
 
+        if re.search(r':\s*\.\.\.\s*\)', original_signature):
+            prompt += f'''
+{function_name} is C-variadic: declare its last parameter as `mut args: ...`, read each variadic argument with `args.arg::<T>()` in the order its other parameters (e.g. a format string or a count) describe, and pass them to {function_name}_idiomatic as a `&[VarArg]` slice (`VarArg::Int`, `VarArg::UInt`, `VarArg::Double`, `VarArg::Str`, `VarArg::Ptr`; the enum is provided by the verifier).
+'''
+
         if len(uses) > 0:
             prompt += f'''
 Following uses will be provied by the verifier, you should **ONLY** add uses that are not in the following list:
@@ -449,9 +455,26 @@ Analyze the error messages, think about the possible reasons, and try to avoid t
                 func_spec_path,
                 struct_idiomatic_name_map,
             )
+            if function_result is not None and utils.no_std_profile(self.config):
+                function_result = use_core_ffi(function_result)
         except Exception as e:
             logger.error("Spec-driven function harness failed: %s", e)
 
+        # Signatures without structs can be bridged mechanically
+        if function_result is None and len(struct_signature_dependency_names) == 0:
+            try:
+                spec_text = ""
+                if os.path.exists(func_spec_path):
+                    with open(func_spec_path, 'r') as _sf:
+                        spec_text = _sf.read()
+                function_result = rust_ast_parser.generate_ffi_shim(
+                    original_signature_renamed,
+                    idiomatic_signature_replaced,
+                    spec_text,
+                )
+            except Exception as e:
+                logger.info("FFI shim generation skipped: %s", e)
+
         # If spec-driven produced TODOs or failed previously, ask LLM to finish/fix
         if function_result is not None and 'TODO:' in function_result:
             helper_blocks: list[str] = []
@@ -676,6 +699,8 @@ Output only the final function in this format:
                 unidiomatic_struct_code_renamed,
                 spec_path,
             )
+            if harness_result is not None and utils.no_std_profile(self.config):
+                harness_result = use_core_ffi(harness_result)
             if os.path.exists(spec_path):
                 try:
                     with open(spec_path, 'r') as _sf:
@@ -1074,6 +1099,9 @@ Output only the two functions in this format:
             combined_code)
         if compile_result[0] != VerifyResult.SUCCESS:
             return compile_result
+        no_std_result = self.try_build_no_std(combined_code)
+        if no_std_result[0] != VerifyResult.SUCCESS:
+            return no_std_result
 
         try:
             rust_ast_parser.get_standalone_uses_code_paths(function_code)
@@ -1217,6 +1245,12 @@ Output only the two functions in this format:
             logger.error("Failed to run tests for function %s", function_name)
             return test_error
 
+        miri_check = self.config['general'].get('miri_check', 'off')
+        if miri_check != 'off':
+            miri_result, miri_feedback, _ = self.run_miri(function_name, combined_code)
+            if miri_result != VerifyResult.SUCCESS and miri_check == 'error':
+                return (miri_result, miri_feedback)
+
         # save harness code
         path = os.path.join(self.saved_test_harness_path,
                             "functions", f"{function_name}.rs")
@@ -52,6 +52,8 @@ class PointerInfo:
     null: str = "empty"
     len_from: Any = None
     len_const: Optional[int] = None
+    inner_len_from: Any = None
+    inner_len_const: Optional[int] = None
     raw: dict = field(default_factory=dict)
 
     @classmethod
@@ -66,11 +68,18 @@ class PointerInfo:
             len_const_val: Optional[int] = int(len_const)
         else:
             len_const_val = None
+        inner_len_const = ptr.get("inner_len_const")
+        if isinstance(inner_len_const, (int, float)):
+            inner_len_const_val: Optional[int] = int(inner_len_const)
+        else:
+            inner_len_const_val = None
         return cls(
             kind=ptr.get("kind"),
             null=ptr.get("null", "empty"),
             len_from=ptr.get("len_from"),
             len_const=len_const_val,
+            inner_len_from=ptr.get("inner_len_from"),
+            inner_len_const=inner_len_const_val,
             raw=ptr,
         )
 
@@ -353,13 +362,17 @@ def _render_struct_harness(
             struct_ptr = _analyze_struct_ptr_conversion(c_ty, raw_i_ty)
 
         if struct_ptr:
-            conv_name = f"C{struct_ptr['idiom_ident']}_to_{struct_ptr['idiom_ident']}_mut"
-            ptr_expr = f"{c_access} as *mut C{struct_ptr['idiom_ident']}"
+            idiom_ident = struct_ptr['idiom_ident']
+            conv_name = f"C{idiom_ident}_to_{idiom_ident}_mut"
+            ptr_expr = f"{c_access} as *mut C{idiom_ident}"
+            # the converter leaks its result; keep a copy and release it
             if struct_ptr['is_option']:
                 init_lines.append(
                     f"""            {rust_path}: if !{c_access}.is_null() {{
                 let tmp = unsafe {{ {conv_name}({ptr_expr}) }};
-                Some((*tmp).clone())
+                let value = (*tmp).clone();
+                unsafe {{ drop(Box::from_raw(tmp as *mut {idiom_ident})) }};
+                Some(value)
             }} else {{
                 None
             }},""".rstrip()
@@ -368,7 +381,9 @@ def _render_struct_harness(
                 init_lines.append(
                     f"""            {rust_path}: {{
                 let tmp = unsafe {{ {conv_name}({ptr_expr}) }};
-                (*tmp).clone()
+                let value = (*tmp).clone();
+                unsafe {{ drop(Box::from_raw(tmp as *mut {idiom_ident})) }};
+                value
             }},""".rstrip()
                 )
             continue
@@ -449,20 +464,18 @@ def _render_struct_harness(
             box_inner = _extract_box_inner(raw_i_ty)
             if kind == "ref" and box_inner:
                 conv_name = f"C{box_inner}_to_{box_inner}_mut"
+                # the leaked converter result becomes the box
                 if is_opt:
                     init_lines.append(
                         f"""            {rust_path}: if !{c_access}.is_null() {{
-                Some(Box::new(unsafe {{ {conv_name}({c_access}) }}.clone()))
+                Some(unsafe {{ Box::from_raw({conv_name}({c_access}) as *mut {box_inner}) }})
             }} else {{
                 None
             }},""".rstrip()
                     )
                 else:
                     init_lines.append(
-                        f"""            {rust_path}: {{
-                let tmp = unsafe {{ {conv_name}({c_access}) }};
-                Box::new((*tmp).clone())
-            }},""".rstrip()
+                        f"""            {rust_path}: unsafe {{ Box::from_raw({conv_name}({c_access}) as *mut {box_inner}) }},"""
                     )
                 continue
 
@@ -675,6 +688,23 @@ def _render_struct_harness(
         else:
             return None
 
+    release_lines: list[str] = []
+    for mapping in spec.fields:
+        u_desc = mapping.u
+        pointer = u_desc.pointer
+        c_field = u_desc.name
+        if pointer is None or mapping.i.name in preflight.derived_len_i_fields:
+            continue
+        len_info = ptr_len_info.get(c_field, {})
+        len_place = _c_field(len_info["len_from"].strip()) if len_info.get("len_from_is_field") else None
+        release_lines.extend(_release_field_lines(
+            _c_field(c_field),
+            pointer.kind,
+            mapping.i.type or "",
+            u_field_types.get(c_field, u_desc.type or ""),
+            len_place,
+        ))
+
     uses = [
         "use core::ptr;",
         "use std::ffi;",
@@ -690,6 +720,7 @@ def _render_struct_harness(
         init_lines=init_lines,
         back_lines=back_lines,
         c_struct_init_lines=c_fields_init,
+        release_lines=release_lines,
     )
     return render_struct_harness(context)
 
@@ -727,6 +758,15 @@ def _build_mut_struct_post_lines(mut_struct_params: list[dict[str, str]]) -> lis
                     c_name=c_name,
                 )
             )
+        elif mode == "file_stream":
+            post_lines.append(
+                render_function_macro(
+                    "post_file_stream",
+                    var_name=idiom_var,
+                    ptr_name=u_name,
+                    sync=entry.get("sync"),
+                )
+            )
         else:
             post_lines.append(
                 f"    // TODO: unsupported post-call conversion for {param_name}")
@@ -765,6 +805,15 @@ def _prepare_function_arguments(
                 u_param_info, dict) else None
         ) or u_field.type or ""
 
+        if (PointerInfo.from_shape(u_shape) or PointerInfo()).kind == "file":
+            _prepare_file_argument(plan, pname, u_name, raw_type)
+            continue
+
+        if _is_void_pointer(c_type_for_param):
+            _prepare_void_pointer_argument(
+                plan, pname, u_name, norm_type, c_type_for_param)
+            continue
+
         if norm_type in idiom_names and not traits.get("is_reference"):
             c_alias = c_alias_for(norm_type)
             struct_ptr = _analyze_struct_ptr_conversion(
@@ -780,6 +829,9 @@ def _prepare_function_arguments(
                 plan.pre_lines.append(
                     f"    let {pname}_val: {norm_type} = {pname}_ref.clone();"
                 )
+                plan.pre_lines.append(
+                    f"    unsafe {{ drop(Box::from_raw({pname}_ref as *mut {norm_type})) }};"
+                )
                 plan.call_args.append(f"{pname}_val")
             else:
                 msg = f"param {pname}: unsupported struct conversion"
@@ -893,6 +945,11 @@ def _prepare_function_arguments(
 
         is_slice, is_slice_optional, slice_elem, is_mut_slice = _classify_slice_traits(
             traits)
+        owned_rows = False
+        if not is_slice and norm_type.startswith("Vec<") and norm_type.endswith(">"):
+            vec_elem = norm_type[len("Vec<"):-1]
+            if vec_elem == "String" or vec_elem.startswith("Vec<"):
+                is_slice, slice_elem, owned_rows = True, vec_elem, True
         if is_slice:
             pointer = PointerInfo.from_shape(u_shape)
             if pointer is None or pointer.kind != "slice":
@@ -921,10 +978,20 @@ def _prepare_function_arguments(
             else:
                 plan.pre_lines.append(
                     f"    // Arg '{pname}': slice from {c_ptr_name} with len {len_expr}")
-            plan.pre_lines.append(f"    let {len_var} = {len_expr};")
+            if isinstance(len_from, str) and len_from in u_param_map:
+                plan.pre_lines.append(f"    let {len_var} = {_usize_len(len_from, u_param_map)};")
+            else:
+                plan.pre_lines.append(f"    let {len_var} = {len_expr};")
             plan.pre_lines.append(
                 f"    let {usable_len_var} = if {c_ptr_name}.is_null() {{ 0 }} else {{ {len_var} }};"
             )
+            if elem in {"String", "&str"} or elem.startswith("Vec<"):
+                _prepare_pointer_array_argument(
+                    plan, pname, elem, c_ptr_name, c_type_for_param or u_field.type or "",
+                    usable_len_var, pointer, u_param_map,
+                    is_slice_optional, is_mut_slice, owned_rows,
+                )
+                continue
             if is_slice_optional:
                 if is_mut_slice:
                     plan.pre_lines.append(
@@ -1037,6 +1104,177 @@ def _prepare_function_arguments(
     return plan
 
 
+def _prepare_void_pointer_argument(
+    plan: FunctionArgumentPlan,
+    pname: str,
+    u_name: str,
+    norm_type: str,
+    c_ty: str,
+) -> None:
+    """Pass a C `void *` argument in whichever form the idiomatic signature chose."""
+    inner = norm_type
+    is_option = norm_type.startswith("Option<") and norm_type.endswith(">")
+    if is_option:
+        inner = norm_type[len("Option<"):-1]
+    if inner == "usize" or inner.startswith("*"):
+        value = f"{u_name} as {inner}"
+        if is_option:
+            value = f"if {u_name}.is_null() {{ None }} else {{ Some({value}) }}"
+        plan.call_args.append(value)
+        return
+    if inner.startswith("Box<dyn") and inner.rstrip(">").endswith("Any"):
+        plan.pre_lines.append(
+            f"    // TODO: param {pname}: recover the Box<dyn Any> registered for {c_ty} '{u_name}'"
+        )
+        plan.call_args.append(f"/* TODO void pointer {pname} */")
+        return
+    plan.pre_lines.append(
+        f"    // TODO: param {pname}: map {c_ty} '{u_name}' to {norm_type}")
+    plan.call_args.append(f"/* TODO void pointer {pname} */")
+
+
+def _prepare_file_argument(
+    plan: FunctionArgumentPlan,
+    pname: str,
+    u_name: str,
+    raw_type: str,
+) -> None:
+    """Pass a `FILE *` argument as a `File` (or buffered wrapper) sharing the C stream's file."""
+    ty = re.sub(r"(std::)?(fs|io)::", "", raw_type.replace(" ", ""))
+    by_mut_ref = ty.startswith("&mut")
+    by_ref = not by_mut_ref and ty.startswith("&")
+    inner = ty[len("&mut"):] if by_mut_ref else ty.lstrip("&")
+    wrappers = {"File": None, "BufReader<File>": "BufReader", "BufWriter<File>": "BufWriter"}
+    if inner not in wrappers or (by_ref and inner != "File"):
+        plan.pre_lines.append(
+            f"    // TODO: param {pname}: map FILE * '{u_name}' to {raw_type}")
+        plan.call_args.append(f"/* TODO file {pname} */")
+        return
+    wrapper = wrappers[inner]
+    var_name = f"{pname}_stream"
+    plan.pre_lines.append(
+        f"    // Arg '{pname}': {inner} sharing the file of FILE * '{u_name}'")
+    plan.pre_lines.append(
+        render_function_macro(
+            "file_stream",
+            var_name=var_name,
+            ptr_name=u_name,
+            wrapper=wrapper,
+            mutable=by_mut_ref,
+        )
+    )
+    if by_mut_ref:
+        plan.call_args.append(f"&mut {var_name}")
+    elif by_ref:
+        plan.call_args.append(f"&{var_name}")
+    else:
+        plan.call_args.append(var_name)
+    plan.mut_struct_params.append(
+        {
+            "mode": "file_stream",
+            "param_name": pname,
+            "idiom_var": var_name,
+            "u_name": u_name,
+            # an owned wrapper is flushed or dropped by the callee
+            "sync": wrapper if by_mut_ref else "",
+        }
+    )
+
+
+_UNSIGNED_INTEGER_TYPES = {
+    "usize", "u8", "u16", "u32", "u64", "u128",
+    "c_uchar", "c_ushort", "c_uint", "c_ulong", "c_ulonglong", "size_t",
+}
+
+
+def _usize_len(name: str, u_param_map: dict[str, dict]) -> str:
+    """The count parameter `name` as a `usize`, with a negative signed count (e.g. `argc`) taken as empty."""
+    param = u_param_map.get(name)
+    ty = (param.get("type") if isinstance(param, dict) else None) or ""
+    if ty.replace(" ", "").split("::")[-1] in _UNSIGNED_INTEGER_TYPES:
+        return f"{name} as usize"
+    return f"if {name} <= 0 {{ 0 }} else {{ {name} as usize }}"
+
+
+def _prepare_pointer_array_argument(
+    plan: FunctionArgumentPlan,
+    pname: str,
+    elem: str,
+    c_ptr_name: str,
+    c_type: str,
+    len_var: str,
+    pointer: PointerInfo,
+    u_param_map: dict[str, dict],
+    is_optional: bool,
+    is_mut: bool,
+    owned: bool,
+) -> None:
+    """Build `Vec<String>` / `Vec<Vec<T>>` arguments from `T**` parameters such as argv."""
+    if is_optional:
+        msg = f"slice arg {pname}: optional pointer arrays are unsupported"
+        plan.pre_lines.append(f"    // TODO: {msg}")
+        plan.call_args.append(f"/* TODO slice {pname} */")
+        return
+
+    if elem.startswith("Vec<"):
+        row_elem = elem[len("Vec<"):-1]
+        row_ptr = _ensure_traits_dict(_get_type_traits(c_type).get("pointer_inner"))
+        c_row_elem = _ensure_traits_dict(row_ptr.get("pointer_inner")).get("normalized")
+        inner_len_from = pointer.inner_len_from
+        if isinstance(inner_len_from, str) and inner_len_from in u_param_map:
+            inner_len_expr = f"{inner_len_from} as usize"
+        elif pointer.inner_len_const is not None:
+            inner_len_expr = f"{pointer.inner_len_const}usize"
+        else:
+            inner_len_expr = None
+        if not c_row_elem or inner_len_expr is None:
+            msg = f"slice arg {pname}: need a T** type and inner_len_from or inner_len_const"
+            plan.pre_lines.append(f"    // TODO: {msg}")
+            plan.call_args.append(f"/* TODO slice {pname} */")
+            return
+        plan.pre_lines.append(
+            f"    // Arg '{pname}': rows from {c_ptr_name} with row len {inner_len_expr}")
+        row_len_expr = inner_len_expr
+        if isinstance(inner_len_from, str) and inner_len_from in u_param_map:
+            row_len_expr = f"{pname}_row_len"
+            plan.pre_lines.append(f"    let {row_len_expr} = {_usize_len(inner_len_from, u_param_map)};")
+        plan.pre_lines.append(
+            render_function_macro(
+                "slice_rows",
+                var_name=f"{pname}_rows",
+                row_elem=row_elem,
+                ptr_expr=f"{c_ptr_name} as *const *const {c_row_elem}",
+                len_expr=len_var,
+                inner_len_expr=row_len_expr,
+            )
+        )
+        arg = f"{pname}_rows"
+    else:
+        plan.pre_lines.append(
+            f"    // Arg '{pname}': C string array at {c_ptr_name}")
+        plan.pre_lines.append(
+            render_function_macro(
+                "cstring_array",
+                var_name=f"{pname}_strs",
+                ptr_expr=f"{c_ptr_name} as *const *const std::ffi::c_char",
+                len_expr=len_var,
+            )
+        )
+        arg = f"{pname}_strs"
+        if elem == "&str":
+            plan.pre_lines.append(
+                f"    let {pname}_refs: Vec<&str> = {arg}.iter().map(String::as_str).collect();"
+            )
+            arg = f"{pname}_refs"
+
+    if owned:
+        plan.call_args.append(arg)
+    elif is_mut:
+        plan.call_args.append(f"&mut {arg}")
+    else:
+        plan.call_args.append(f"&{arg}")
+
+
 def _struct_todo_skeleton(struct_name: str, idiomatic_name: str, todos: list[str]) -> str:
     todo_header = "\n".join(
         ["// TODO: Spec exceeds automatic rules. Items to handle manually:"]
@@ -1071,6 +1309,20 @@ def _build_function_return_handling(
 
     def c_alias_for(idiom): return c_name_for_idiom.get(idiom, idiom)
 
+    on_error = ret_spec.raw.get("on_error") if ret_spec is not None else None
+    if on_error is not None:
+        # `Result` in place of the errno protocol: `Err` sets errno and yields the C failure value
+        id_type = ret_spec.i.type or _ensure_traits_dict(id_ret).get("raw") or ""
+        ok_unit = re.match(r"^((std::)?io::)?Result\s*<\s*\(\s*\)", id_type) is not None
+        ret_lines.append(
+            render_function_macro(
+                "errno_result_unwrap",
+                ok_pattern="()" if ok_unit else "__value",
+                ok_value=("0" if has_ret else "()") if ok_unit else "__value",
+                err_value=str(on_error) if has_ret else "()",
+            )
+        )
+
     if ret_spec is not None:
         u_desc = ret_spec.u
         i_desc = ret_spec.i
@@ -1080,7 +1332,7 @@ def _build_function_return_handling(
         u_traits = _type_traits_from_param(u_param_info)
 
         if isinstance(shape, str) and shape == "scalar":
-            if not c_ret:
+            if not c_ret and on_error is None:
                 if _type_pointer_depth(u_traits) >= 1:
                     ret_lines.append(
                         render_function_macro(
@@ -1224,6 +1476,37 @@ def _c_field(name: str) -> str:
     return f"{_C_STRUCT_BIND}.{name}"
 
 
+def _release_field_lines(
+    place: str, kind: Optional[str], raw_i_ty: str, c_ty: str, len_place: Optional[str]
+) -> list[str]:
+    """Statements releasing what the idiomatic -> C conversion allocated for the C field ``place``.
+
+    Nested structs are released without their own fields, as their converters
+    may be hand-written without a ``free_C*`` helper; slices are released only
+    when ``len_place`` holds the length they were allocated with.
+    """
+    if kind == "cstring":
+        return [
+            f"    if !{place}.is_null() {{",
+            f"        drop(std::ffi::CString::from_raw({place} as *mut libc::c_char));",
+            "    }",
+        ]
+    if kind == "ref" and (_analyze_struct_ptr_conversion(c_ty, raw_i_ty) or _extract_box_inner(raw_i_ty)):
+        return [
+            f"    if !{place}.is_null() {{",
+            f"        drop(Box::from_raw({place}));",
+            "    }",
+        ]
+    if kind == "slice" and len_place:
+        elem = _infer_slice_elem_from_ptr_ty(c_ty)
+        return [
+            f"    if !{place}.is_null() && ({len_place} as usize) > 0 {{",
+            f"        drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut({place} as *mut {elem}, {len_place} as usize)));",
+            "    }",
+        ]
+    return []
+
+
 def _is_simple_identifier(text: Optional[str]) -> bool:
     if not isinstance(text, str):
         return False
@@ -1307,6 +1590,13 @@ def _get_type_traits(type_str: str) -> dict:
     return traits
 
 
+def _is_void_pointer(ty: Optional[str]) -> bool:
+    compact = (ty or "").replace(" ", "")
+    if not compact.startswith("*") or compact.count("*") != 1:
+        return False
+    return compact.split("::")[-1].removeprefix("*mut").removeprefix("*const") == "c_void"
+
+
 def _infer_slice_elem_from_ptr_ty(ptr_ty: str) -> str:
     traits = _get_type_traits(ptr_ty)
     candidate = (traits or {}).get("pointer_element")
@@ -1710,9 +2000,32 @@ def _generate_enum_struct_converters(
     # I(Enum) -> U: match on idiom_struct and build all fields
     # For inactive fields, zero/null them; write tag to equals
     variant_contexts: list[dict[str, Any]] = []
+    release_lines: list[str] = []
     for v in variants:
         vname = v.get("name")
         payload = v.get("payload") or []
+        # only the active variant's pointers were allocated
+        variant_release: list[str] = []
+        for pf in payload:
+            u = (pf.get("u_field") or {})
+            shape = u.get("shape")
+            if not isinstance(shape, dict) or "ptr" not in shape:
+                continue
+            ptr = shape["ptr"]
+            u_name = u.get("name")
+            variant_release.extend(_release_field_lines(
+                f"c_struct.{u_name}",
+                ptr.get("kind"),
+                (pf.get("i_field") or {}).get("type") or "",
+                u.get("type") or u_field_types.get(u_name, ""),
+                f"c_struct.{ptr['len_from']}" if ptr.get("kind") == "slice" and "len_from" in ptr else None,
+            ))
+        if variant_release:
+            equals = v.get("when", {}).get("equals")
+            tag_value = f"{equals}" if tag_ty is None else f"({equals}) as {tag_ty}"
+            release_lines.append(f"    if c_struct.{tag_name} == {tag_value} {{")
+            release_lines.extend(f"    {line}" for line in variant_release)
+            release_lines.append("    }")
         # arity from payload count
         arity = len(payload)
         binders = ", ".join([f"v{idx}" for idx in range(arity)])
@@ -1812,10 +2125,17 @@ def _generate_enum_struct_converters(
         tag_field=tag_name,
         to_rust_arms=arms,
         variants=variant_contexts,
+        release_lines=release_lines,
     )
     return render_enum_struct_converters(context)
 
 
+def use_core_ffi(code: str) -> str:
+    """Spells C string conversions with `core::ffi::CStr` and `alloc::ffi::CString` (the `no_std` profile)."""
+    return code.replace("std::ffi::CString", "alloc::ffi::CString").replace(
+        "std::ffi::CStr", "core::ffi::CStr")
+
+
 def generate_struct_harness_from_spec_file(
     struct_name: str,
     idiomatic_struct_code: str,
@@ -1869,6 +2189,18 @@ def _parse_fn_signature(sig: str):
         except Exception:
             return None
     name = details.get("name")
+    generics = details.get("generics") or {}
+    if not generics.get("monomorphic", True):
+        generic_names = [
+            entry.get("name")
+            for entry in generics.get("type_params", []) + generics.get("const_params", [])
+        ]
+        logger.warning(
+            "Function %s is generic over %s; a harness needs concrete types",
+            name,
+            ", ".join(generic_names) or "impl Trait parameters",
+        )
+        return None
     params = details.get("params", [])
     ret_info = details.get("return")
     return name, params, ret_info
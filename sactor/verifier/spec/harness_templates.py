@@ -46,6 +46,7 @@ class StructHarnessContext:
     init_lines: tuple[str, ...]
     back_lines: tuple[str, ...]
     c_struct_init_lines: tuple[str, ...]
+    release_lines: tuple[str, ...] = ()
 
     @classmethod
     def create(
@@ -60,6 +61,7 @@ class StructHarnessContext:
         init_lines: Iterable[str],
         back_lines: Iterable[str],
         c_struct_init_lines: Iterable[str],
+        release_lines: Iterable[str] = (),
     ) -> "StructHarnessContext":
         return cls(
             uses=_normalize_lines(uses),
@@ -71,6 +73,7 @@ class StructHarnessContext:
             init_lines=_normalize_lines(init_lines),
             back_lines=_normalize_lines(back_lines),
             c_struct_init_lines=_normalize_lines(c_struct_init_lines),
+            release_lines=_normalize_lines(release_lines),
         )
 
     def as_template_args(self) -> dict[str, Any]:
@@ -84,6 +87,7 @@ class StructHarnessContext:
             "init_lines": self.init_lines,
             "back_lines": self.back_lines,
             "c_struct_init_lines": self.c_struct_init_lines,
+            "release_lines": self.release_lines,
         }
 
 
@@ -97,6 +101,7 @@ class EnumHarnessContext:
     tag_field: str
     to_rust_arms: tuple[dict[str, str], ...]
     variants: tuple[dict[str, Any], ...]
+    release_lines: tuple[str, ...] = ()
 
     @classmethod
     def create(
@@ -108,6 +113,7 @@ class EnumHarnessContext:
         tag_field: str,
         to_rust_arms: Iterable[dict[str, str]],
         variants: Iterable[dict[str, Any]],
+        release_lines: Iterable[str] = (),
     ) -> "EnumHarnessContext":
         normalized_arms: list[dict[str, str]] = []
         for arm in to_rust_arms or []:
@@ -134,6 +140,7 @@ class EnumHarnessContext:
             tag_field=tag_field,
             to_rust_arms=tuple(normalized_arms),
             variants=tuple(normalized_variants),
+            release_lines=_normalize_lines(release_lines),
         )
 
     def as_template_args(self) -> dict[str, Any]:
@@ -144,6 +151,7 @@ class EnumHarnessContext:
             "tag_field": self.tag_field,
             "to_rust_arms": self.to_rust_arms,
             "variants": self.variants,
+            "release_lines": self.release_lines,
         }
 
 
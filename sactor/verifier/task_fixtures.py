@@ -0,0 +1,134 @@
+import difflib
+import os
+import shutil
+from typing import Optional
+
+from sactor import logging as sactor_logging
+from sactor import utils
+
+logger = sactor_logging.get_logger(__name__)
+
+FIXTURE_KEYS = ("input_files", "output_files")
+
+
+def validate_fixture_spec(task: dict) -> Optional[str]:
+    """
+    Checks the `input_files`/`output_files` of a test task. Each maps a
+    relative path to the file's contents, or to `{"path": ...}` naming a file
+    relative to the test task file (or `{"contents": ...}`). Input files may
+    set permission bits with `"mode"`, e.g. `"0444"`; an output file mapped to
+    null must not exist. Returns an error message, or None.
+    """
+    for key in FIXTURE_KEYS:
+        files = task.get(key)
+        if files is None:
+            continue
+        if not isinstance(files, dict):
+            return f"'{key}' must map file names to contents"
+        for name, spec in files.items():
+            if os.path.isabs(name) or ".." in name.replace("\\", "/").split("/"):
+                return f"'{key}' entry '{name}' must be a path inside the working directory"
+            if isinstance(spec, str) or (spec is None and key == "output_files"):
+                continue
+            if not isinstance(spec, dict) or not (
+                    isinstance(spec.get("path"), str) or isinstance(spec.get("contents"), str)):
+                return f"'{key}' entry '{name}' must be a string or {{\"path\": ...}}"
+            if "mode" in spec and (key != "input_files" or _file_mode(spec["mode"]) is None):
+                return f"'{key}' entry '{name}' has an invalid mode"
+    return None
+
+
+def _file_mode(mode) -> Optional[int]:
+    """Permission bits given as an int or an octal string such as "0444"."""
+    if isinstance(mode, str):
+        try:
+            mode = int(mode, 8)
+        except ValueError:
+            return None
+    if isinstance(mode, bool) or not isinstance(mode, int) or not 0 <= mode <= 0o7777:
+        return None
+    return mode
+
+
+class TaskFixtures:
+    """
+    File fixtures of one test task. When the task declares `input_files` or
+    `output_files`, its command runs in a fresh temporary directory holding
+    the input files, and afterwards every output file must match its expected
+    contents (or be absent, when mapped to null). Commands run concurrently
+    without fixtures get a fresh directory too, linking in the entries of the
+    task directory, so files they write do not clash. The directory is removed once the command has been checked.
+    """
+
+    def __init__(self, task: dict, task_dir: str):
+        self.task_dir = task_dir
+        self.input_files: dict = task.get("input_files") or {}
+        self.output_files: dict = task.get("output_files") or {}
+        self.work_dir: Optional[str] = None
+
+    @property
+    def active(self) -> bool:
+        return bool(self.input_files or self.output_files)
+
+    def _read_spec(self, spec) -> bytes:
+        if isinstance(spec, str):
+            return spec.encode()
+        if "contents" in spec:
+            return spec["contents"].encode()
+        with open(os.path.join(self.task_dir, spec["path"]), "rb") as f:
+            return f.read()
+
+    def setup(self, isolate: bool = False) -> str:
+        """Materializes the input files and returns the directory to run in.
+        Without fixtures, that is the task directory unless `isolate` is set."""
+        if not self.active:
+            if not isolate:
+                return self.task_dir
+            self.work_dir = utils.get_temp_dir()
+            for name in os.listdir(self.task_dir):
+                os.symlink(os.path.join(self.task_dir, name), os.path.join(self.work_dir, name))
+            logger.debug("Test command isolated in %s", self.work_dir)
+            return self.work_dir
+        self.work_dir = utils.get_temp_dir()
+        for name, spec in self.input_files.items():
+            path = os.path.join(self.work_dir, name)
+            os.makedirs(os.path.dirname(path), exist_ok=True)
+            with open(path, "wb") as f:
+                f.write(self._read_spec(spec))
+            if isinstance(spec, dict) and "mode" in spec:
+                os.chmod(path, _file_mode(spec["mode"]))
+        logger.debug("Test fixtures set up in %s", self.work_dir)
+        return self.work_dir
+
+    def check(self) -> Optional[str]:
+        """Compares the output files; returns a description of the mismatches, or None."""
+        if self.work_dir is None:
+            return None
+        problems = []
+        for name, spec in self.output_files.items():
+            path = os.path.join(self.work_dir, name)
+            if spec is None:
+                if os.path.lexists(path):
+                    problems.append(f"Output file '{name}' should not exist")
+                continue
+            if not os.path.isfile(path):
+                problems.append(f"Output file '{name}' was not created")
+                continue
+            with open(path, "rb") as f:
+                actual = f.read()
+            expected = self._read_spec(spec)
+            if actual == expected:
+                continue
+            diff = difflib.unified_diff(
+                expected.decode(errors="replace").splitlines(keepends=True),
+                actual.decode(errors="replace").splitlines(keepends=True),
+                fromfile=f"expected/{name}",
+                tofile=f"actual/{name}",
+            )
+            problems.append(f"Output file '{name}' differs:\n{''.join(diff)}")
+        return "\n".join(problems) if problems else None
+
+    def teardown(self) -> None:
+        if self.work_dir is not None:
+            shutil.rmtree(self.work_dir, ignore_errors=True)
+            self.work_dir = None
@@ -68,6 +68,8 @@ class UnidiomaticVerifier(Verifier):
         if compile_result[0] != VerifyResult.SUCCESS:
             return compile_result
 
+        self.prescreen_undefined_behavior(function.name, function_code)
+
         try:
             rust_ast_parser.get_standalone_uses_code_paths(function_code)
         except Exception as e:
@@ -5,7 +5,10 @@ import os, shlex
 from abc import ABC, abstractmethod
 from typing import Optional
 import glob
+import re
 import hashlib
+import shutil
+from concurrent.futures import ThreadPoolExecutor
 
 from sactor import logging as sactor_logging
 from sactor import rust_ast_parser, utils
@@ -13,12 +16,26 @@ from sactor.utils import is_compile_command, process_commands_to_compile, read_f
 
 from sactor.c_parser import FunctionInfo, StructInfo, c_parser_utils, CParser
 from sactor.combiner.combiner import RustCode, merge_uses
+from sactor.combiner.crate_emitter import cargo_manifest
 from sactor.combiner.partial_combiner import CombineResult, PartialCombiner
 
+from .cross import CrossTarget
+from .task_fixtures import TaskFixtures, validate_fixture_spec
 from .verifier_types import VerifyResult
 
 logger = sactor_logging.get_logger(__name__)
 
+SANITIZER_C_FLAGS = [
+    '-fsanitize=address,undefined',
+    '-fno-sanitize-recover=undefined',
+    '-fno-omit-frame-pointer',
+]
+
+# Prepended to C harness code before `<setjmp.h>` declares the functions
+SETJMP_REDIRECT = """#define longjmp sactor_longjmp
+#define siglongjmp sactor_longjmp
+"""
+
 class Verifier(ABC):
     def __init__(
         self,
@@ -35,6 +52,7 @@ class Verifier(ABC):
         link_closure: list[str] | None = None,
     ):
         self.config = config
+        self.cross = CrossTarget.from_config(config)
         if build_path:
             self.build_path = build_path
         else:
@@ -55,6 +73,8 @@ class Verifier(ABC):
         self.compile_commands_file = compile_commands_file
         self.entry_tu_file = entry_tu_file
         self.link_closure = link_closure or []
+        # function name -> goto label -> indices of the tests jumping to it in C
+        self._goto_coverage: dict[str, dict[str, list[int]]] = {}
 
     def _discover_cmake_libs(self) -> list[str]:
         """Discover library flags from CMake link.txt for the entry target, if present.
@@ -191,6 +211,19 @@ class Verifier(ABC):
                         test_cmd_path,
                     )
                     return False
+                fixture_error = validate_fixture_spec(cmd)
+                if fixture_error is None:
+                    try:
+                        utils.TaskRunOptions.from_task(cmd)
+                    except ValueError as e:
+                        fixture_error = str(e)
+                if fixture_error:
+                    logger.error(
+                        "Invalid test command file %s: %s",
+                        test_cmd_path,
+                        fixture_error,
+                    )
+                    return False
             return True
 
         except Exception as e:
@@ -260,6 +293,12 @@ class Verifier(ABC):
         return (VerifyResult.SUCCESS, None)
 
     def _try_compile_rust_code_impl(self, rust_code, executable=False) -> tuple[VerifyResult, Optional[str]]:
+        if utils.stable_output():
+            constructs = utils.nightly_constructs(rust_code)
+            if constructs:
+                logger.error("Rust code needs a nightly toolchain")
+                return (VerifyResult.COMPILE_ERROR, utils.describe_nightly_constructs(constructs))
+
         utils.create_rust_proj(rust_code, "build_attempt",
                                self.build_attempt_path, is_lib=(not executable))
 
@@ -273,26 +312,283 @@ class Verifier(ABC):
             return (VerifyResult.COMPILE_ERROR, result.stderr)
 
         # Try to compile the Rust code
-        cmd = ["cargo", "build", "--manifest-path",
-               f"{self.build_attempt_path}/Cargo.toml"]
+        cmd = ["cargo", "build", "--message-format=json", *self._cargo_target_args(),
+               "--manifest-path", f"{self.build_attempt_path}/Cargo.toml"]
         logger.debug("Compiling Rust project: %s", ' '.join(cmd))
-        result = utils.run_command(cmd)
+        result = utils.run_command(cmd, env=self._cargo_env())
         if result.returncode != 0:
             # Rust code failed to compile
             logger.error("Rust code failed to compile")
-            return (VerifyResult.COMPILE_ERROR, result.stderr)
+            # spans refer to the file as formatted by `cargo fmt`
+            source_path = os.path.join(
+                self.build_attempt_path, "src", "main.rs" if executable else "lib.rs")
+            return (VerifyResult.COMPILE_ERROR,
+                    self._summarize_compile_errors(result, read_file(source_path)))
         else:
             # Rust code compiled successfully
             logger.info("Rust code compiled successfully")
             return (VerifyResult.SUCCESS, None)
 
+    def _cargo_target_args(self) -> list[str]:
+        return self.cross.cargo_args() if self.cross else []
+
+    def _cargo_env(self) -> Optional[dict]:
+        return self.cross.cargo_env() if self.cross else None
+
+    def _rust_target_dir(self, crate_dir: str) -> str:
+        """Where cargo puts the debug artifacts of `crate_dir` for the verification target."""
+        if self.cross:
+            return self.cross.target_dir(crate_dir)
+        return os.path.join(crate_dir, "target", "debug")
+
+    def _c_compiler(self) -> str:
+        return self.cross.c_compiler if self.cross else utils.get_compiler()
+
+    def try_build_no_std(self, rust_code: str) -> tuple[VerifyResult, Optional[str]]:
+        """
+        Under the `no_std` profile, cross-compiles the code as a `#![no_std]` +
+        `alloc` library for `no_std.target` to prove it builds without std.
+        """
+        if not utils.no_std_profile(self.config):
+            return (VerifyResult.SUCCESS, None)
+        no_std_config = self.config.get('no_std', {})
+        target = no_std_config.get('target', 'thumbv7em-none-eabihf')
+        try:
+            code = utils.no_std_crate(rust_code, no_std_config.get('logger', ''))
+        except ValueError as e:
+            logger.error("Rust code needs the standard library")
+            return (VerifyResult.COMPILE_ERROR, f"The code must build as `#![no_std]` + `alloc`. {e}")
+
+        no_std_path = os.path.join(self.build_path, "no_std_build")
+        if os.path.exists(no_std_path):
+            shutil.rmtree(no_std_path)
+        utils.save_code(os.path.join(no_std_path, "src", "lib.rs"), code)
+        with open(os.path.join(no_std_path, "Cargo.toml"), "w", encoding="utf-8") as fh:
+            fh.write(cargo_manifest("no_std_build", code, lib_crate_types=["rlib"]))
+        cmd = ["cargo", "build", "--message-format=json", "--target", target,
+               "--manifest-path", os.path.join(no_std_path, "Cargo.toml")]
+        logger.debug("Cross-compiling for %s: %s", target, ' '.join(cmd))
+        result = utils.run_command(cmd)
+        if result.returncode != 0:
+            logger.error("Rust code failed to build as no_std for %s", target)
+            return (VerifyResult.COMPILE_ERROR,
+                    f"The code must build as `#![no_std]` + `alloc` for {target}:\n"
+                    + self._summarize_compile_errors(result, code))
+        logger.info("Rust code builds as no_std for %s", target)
+        return (VerifyResult.SUCCESS, None)
+
+    def _summarize_compile_errors(self, result, rust_code: str) -> str:
+        """
+        Turns cargo's JSON diagnostics into a compact per-error summary for the
+        repair prompt. When no error could be parsed (e.g. linker failures),
+        falls back to the rendered compiler messages, then to the raw stderr.
+        """
+        try:
+            diagnostics = rust_ast_parser.summarize_rustc_diagnostics(result.stdout, rust_code)
+        except Exception as e:
+            logger.debug("Failed to summarize rustc diagnostics: %s", e)
+            diagnostics = []
+        if not diagnostics:
+            return self.rendered_compiler_messages(result.stdout) or result.stderr
+        limit = self.config.get('general', {}).get('max_reported_compile_errors', 10)
+        return self.format_rustc_diagnostics(diagnostics, limit)
+
+    @staticmethod
+    def rendered_compiler_messages(output: str) -> str:
+        """
+        Joins the `message.rendered` text of every compiler message in cargo's
+        `--message-format=json` output, whatever its level.
+        """
+        rendered = []
+        for line in (output or "").splitlines():
+            try:
+                entry = json.loads(line)
+            except json.JSONDecodeError:
+                continue
+            if not isinstance(entry, dict) or entry.get("reason") != "compiler-message":
+                continue
+            text = (entry.get("message") or {}).get("rendered")
+            if text:
+                rendered.append(text)
+        return "".join(rendered)
+
+    @staticmethod
+    def format_rustc_diagnostics(diagnostics: list[dict], limit: int = 10) -> str:
+        lines = []
+        for diagnostic in diagnostics[:limit]:
+            header = "error"
+            if diagnostic["code"]:
+                header += f"[{diagnostic['code']}]"
+            header += f" ({diagnostic['kind'].replace('_', ' ')})"
+            if diagnostic["item"]:
+                header += f" in `{diagnostic['item']}`"
+            if diagnostic["line"] is not None:
+                header += f" at line {diagnostic['line']}:{diagnostic['column']}"
+            lines.append(f"{header}: {diagnostic['message']}")
+            if diagnostic["label"]:
+                lines.append(f"    {diagnostic['label']}")
+            for note in diagnostic["notes"]:
+                lines.append(f"    note: {note}")
+        if len(diagnostics) > limit:
+            lines.append(f"... and {len(diagnostics) - limit} more errors")
+        return "\n".join(lines) + "\n"
+
     def try_compile_rust_code(self, rust_code, executable=False) -> tuple[VerifyResult, Optional[str]]:
         return self._try_compile_rust_code_impl(rust_code, executable)
 
+    def prescreen_undefined_behavior(self, name: str, rust_code: str) -> list[dict]:
+        """
+        Cheap static scan for likely undefined behavior (size-changing transmutes,
+        unchecked null dereferences, undocumented `static mut` access, reads of
+        uninitialized memory) before the differential tests run. Findings are
+        logged as warnings and returned; they do not fail verification.
+        """
+        try:
+            suspects = rust_ast_parser.find_ub_suspects(rust_code)
+        except Exception as e:
+            logger.debug("UB pre-screen skipped for %s: %s", name, e)
+            return []
+        for suspect in suspects:
+            logger.warning(
+                "Possible UB in %s (%s, line %d): %s",
+                suspect["function"], suspect["kind"], suspect["line"], suspect["detail"])
+        return suspects
+
+    def clippy_fix(self, name: str, rust_code: str, context_code: str = "") -> Optional[str]:
+        """
+        Applies clippy's machine-applicable suggestions to `rust_code`.
+        `context_code` holds the items it depends on; it is compiled alongside
+        (with `rust_code` in a child module that glob-imports it) but fixes to
+        it are discarded. `general.clippy_fix_lints` restricts clippy to the
+        listed lints. Returns the fixed code, or None when nothing changed or
+        clippy could not run.
+        """
+        begin, end = "// __SACTOR_CLIPPY_FIX_BEGIN__", "// __SACTOR_CLIPPY_FIX_END__"
+        crate_code = f'''{context_code}
+
+mod sactor_clippy_fix {{
+#![allow(unused_imports)]
+use super::*;
+{begin}
+{rust_code}
+{end}
+}}
+'''
+        fix_path = os.path.join(self.build_path, "clippy_fix")
+        utils.create_rust_proj(crate_code, "clippy_fix", fix_path, is_lib=True)
+
+        cmd = ["cargo", "clippy", "--fix", "--allow-no-vcs", "--lib", "--manifest-path",
+               f"{fix_path}/Cargo.toml"]
+        lints = self.config.get('general', {}).get('clippy_fix_lints', [])
+        if lints:
+            cmd += ["--", "-A", "clippy::all"]
+            for lint in lints:
+                cmd += ["-W", lint if lint.startswith("clippy::") else f"clippy::{lint}"]
+        logger.debug("Running clippy fix for %s: %s", name, " ".join(cmd))
+        result = utils.run_command(cmd)
+        if result.returncode != 0:
+            logger.warning("clippy --fix failed for %s: %s", name, result.stderr)
+            return None
+
+        fixed = read_file(os.path.join(fix_path, "src", "lib.rs"))
+        try:
+            fixed = fixed[fixed.index(begin) + len(begin):fixed.index(end)].strip("\n") + "\n"
+        except ValueError:
+            logger.warning("clippy --fix lost the markers for %s", name)
+            return None
+        if fixed.strip() == rust_code.strip():
+            return None
+        return fixed
+
+    @staticmethod
+    def parse_miri_output(output: str) -> list[dict]:
+        """
+        Extracts Miri findings from `cargo miri test` output. Each finding has
+        `kind` (`undefined_behavior` or `memory_leak`), `message`, `test` (the
+        test that was running, if known) and `location` (`file:line:col`, if
+        reported).
+        """
+        findings: list[dict] = []
+        current_test = None
+        pending = None
+        for line in output.splitlines():
+            stripped = line.strip()
+            test_match = re.match(r"^test (\S+) \.\.\.", stripped)
+            if test_match:
+                current_test = test_match.group(1)
+                continue
+            ub_match = re.match(r"^error: Undefined Behavior: (.*)$", stripped)
+            leak_match = re.match(r"^error: memory leaked: (.*)$", stripped)
+            if ub_match or leak_match:
+                kind = "undefined_behavior" if ub_match else "memory_leak"
+                message = (ub_match or leak_match).group(1).rstrip(":, ")
+                pending = {
+                    "kind": kind,
+                    "message": message,
+                    # leaks are only reported once every test has finished
+                    "test": current_test if ub_match else None,
+                    "location": None,
+                }
+                findings.append(pending)
+                continue
+            location_match = re.match(r"^--> (\S+:\d+:\d+)$", stripped)
+            if pending is not None and location_match:
+                pending["location"] = location_match.group(1)
+                pending = None
+        return findings
+
+    def run_miri(self, name: str, rust_code: str) -> tuple[VerifyResult, Optional[str], list[dict]]:
+        """
+        Runs the unit tests embedded in `rust_code` under Miri to catch undefined
+        behavior and leaks that still print the expected output. Returns
+        SUCCESS without findings when the code has no tests or Miri is not
+        installed.
+        """
+        if "#[test]" not in rust_code:
+            logger.debug("No unit tests in %s, skipping Miri", name)
+            return (VerifyResult.SUCCESS, None, [])
+
+        miri_path = os.path.join(self.build_path, "miri")
+        utils.create_rust_proj(rust_code, "miri_check", miri_path, is_lib=True)
+
+        general_config = self.config.get('general', {})
+        timeout = general_config.get('miri_timeout_seconds', 300)
+        byte_limit = general_config.get('command_output_byte_limit', 40000)
+        env = os.environ.copy()
+        # translated programs may read files or the environment
+        env["MIRIFLAGS"] = (env.get("MIRIFLAGS", "") + " -Zmiri-disable-isolation").strip()
+        cmd = ["cargo", "+nightly", "miri", "test", "--manifest-path",
+               f"{miri_path}/Cargo.toml"]
+        logger.debug("Running Miri: %s", ' '.join(cmd))
+        try:
+            res = utils.run_command(cmd, limit_bytes=byte_limit, timeout=timeout, env=env)
+        except TimeoutError as e:
+            return (VerifyResult.TEST_TIMEOUT, f'Miri timed out: {e}', [])
+        output = res.stdout + res.stderr
+        if res.returncode != 0 and ("no such command: `miri`" in output
+                                    or "cargo-miri' is not installed" in output):
+            logger.warning("Miri is not installed, skipping Miri check for %s", name)
+            return (VerifyResult.SUCCESS, None, [])
+
+        findings = self.parse_miri_output(output)
+        for finding in findings:
+            logger.warning(
+                "Miri found %s in %s (test %s, %s): %s",
+                finding["kind"], name, finding["test"], finding["location"], finding["message"])
+        if findings:
+            feedback = "Miri detected the following problems while running the unit tests:\n"
+            for finding in findings:
+                where = " at ".join(filter(None, [finding["test"], finding["location"]]))
+                feedback += f"- {finding['kind'].replace('_', ' ')}: {finding['message']}"
+                feedback += f" ({where})\n" if where else "\n"
+            return (VerifyResult.TEST_ERROR, feedback, findings)
+        if res.returncode != 0:
+            return (VerifyResult.TEST_ERROR, output, [])
+        return (VerifyResult.SUCCESS, None, [])
+
     def _load_test_cmd(self, target) -> list[list[str]]:
-        test_cmd_str = read_file(self.test_cmd_path)
-        test_cmd_str = test_cmd_str.strip()
-        test_cmd_json = json.loads(test_cmd_str)
+        test_cmd_json = self._load_test_tasks()
+        task_dir = os.path.dirname(os.path.abspath(self.test_cmd_path))
         test_cmd = []
         for item in test_cmd_json:
             cmd = item['command']
@@ -301,10 +597,17 @@ class Verifier(ABC):
             for i, arg in enumerate(cmd):
                 if arg == "%t":
                     cmd[i] = os.path.abspath(target)
+                elif arg == "%d":
+                    # the test task directory, for commands run among file fixtures
+                    cmd[i] = task_dir
             test_cmd.append(cmd)
 
         return test_cmd
 
+    def _load_test_tasks(self) -> list[dict]:
+        test_cmd_str = read_file(self.test_cmd_path)
+        return json.loads(test_cmd_str.strip())
+
     def _collect_feedback(self, output) -> str:
         lines = output.split('\n')
         feedback = ""
@@ -325,6 +628,10 @@ class Verifier(ABC):
         # /bin/sh or bash to emit warnings that break output-based tests.
         env["LC_ALL"] = "C"
         env["LANG"] = "C"
+        if self.cross:
+            target = self.cross.runnable(target)
+            # valgrind cannot instrument binaries of another architecture
+            valgrind = False
         test_cmds = self._load_test_cmd(target)
         valgrind_cmd = [
             'valgrind',
@@ -334,51 +641,254 @@ class Verifier(ABC):
             '--',
         ]
 
+        general_config = self.config.get('general', {})
+        jobs = general_config.get('test_jobs', 1) or os.cpu_count() or 1
+        test_tasks = self._load_test_tasks()
+
+        selected = [
+            (i, valgrind_cmd + cmd if valgrind else cmd)
+            for i, cmd in enumerate(test_cmds)
+            if test_number is None or i == test_number
+        ]
+        if jobs == 1 or len(selected) <= 1:
+            for i, cmd in selected:
+                failure = self._run_test_command(i, cmd, test_tasks[i], env)
+                if failure is not None:
+                    return failure
+            return (VerifyResult.SUCCESS, None, None)
+
+        # Commands are independent processes, each in its own directory; report
+        # the failure of the lowest index so the result matches a serial run,
+        # and skip what hasn't started.
+        with ThreadPoolExecutor(max_workers=jobs) as executor:
+            futures = [
+                executor.submit(self._run_test_command, i, cmd, test_tasks[i], env, True)
+                for i, cmd in selected
+            ]
+            for future in futures:
+                failure = future.result()
+                if failure is not None:
+                    executor.shutdown(wait=True, cancel_futures=True)
+                    return failure
+        return (VerifyResult.SUCCESS, None, None)
+
+    def _run_test_command(
+        self, i: int, cmd: list[str], task: dict, env: dict, isolate: bool = False
+    ) -> Optional[tuple[VerifyResult, Optional[str], Optional[int]]]:
+        """Runs test command `i`, in a directory of its own when `isolate` is set;
+        returns None if it passed, else the `_run_tests` result."""
         general_config = self.config.get('general', {})
         timeout = general_config.get('timeout_seconds', 60)
         byte_limit = general_config.get('command_output_byte_limit', 40000)
+        task_dir = os.path.dirname(os.path.abspath(self.test_cmd_path))
 
-        for i, cmd in enumerate(test_cmds):
-            if test_number is not None and i != test_number:
-                continue
-            logger.debug("Running test command: %s", cmd)
-            if valgrind:
-                cmd = valgrind_cmd + cmd
-            try:
-                res = utils.run_command(
-                    cmd,
-                    limit_bytes=byte_limit,
-                    timeout=timeout,
-                    cwd=os.path.dirname(os.path.abspath(self.test_cmd_path)),
-                    env=env,
-                )
-            except TimeoutError as e:
-                return (VerifyResult.TEST_TIMEOUT, f'Failed to run test due to timeout: {e}', i)
-            stdout = res.stdout
-            stderr = res.stderr
-            if stdout:
-                logger.debug("Test stdout: %s", stdout)
-            if stderr:
-                logger.debug("Test stderr: %s", stderr)
-            if res.returncode != 0:
-
-                feedback = self._collect_feedback(stdout + stderr)
-                if feedback != "":
-                    return (VerifyResult.FEEDBACK, feedback, i)
-                if stderr == "":
-                    if stdout != "":
-                        return (VerifyResult.TEST_ERROR, stdout, i)
-                    else:
-                        return (VerifyResult.TEST_ERROR, "No output", i)
-                return (VerifyResult.TEST_ERROR, stderr, i)
+        logger.debug("Running test command: %s", cmd)
+        fixtures = TaskFixtures(task, task_dir)
+        options = utils.TaskRunOptions.from_task(task)
+        try:
+            res = utils.run_command(
+                cmd,
+                limit_bytes=byte_limit,
+                timeout=options.timeout or timeout,
+                cwd=options.resolve_cwd(fixtures.setup(isolate)),
+                env=options.merge_env(env),
+                memory_limit_mb=options.memory_limit_mb,
+                signals=options.signals,
+            )
+            fixture_error = fixtures.check() if res.returncode == 0 else None
+        except TimeoutError as e:
+            return (VerifyResult.TEST_TIMEOUT, f'Failed to run test due to timeout: {e}', i)
+        finally:
+            fixtures.teardown()
+        if fixture_error:
+            return (VerifyResult.TEST_ERROR, fixture_error, i)
+        stdout = res.stdout
+        stderr = res.stderr
+        if stdout:
+            logger.debug("Test stdout: %s", stdout)
+        if stderr:
+            logger.debug("Test stderr: %s", stderr)
+        if res.returncode != 0:
 
-        return (VerifyResult.SUCCESS, None, None)
+            feedback = self._collect_feedback(stdout + stderr)
+            if feedback != "":
+                return (VerifyResult.FEEDBACK, feedback, i)
+            if stderr == "":
+                if stdout != "":
+                    return (VerifyResult.TEST_ERROR, stdout, i)
+                elif res.returncode < 0:
+                    # e.g. a signal of the task's `signals` the translation does not handle
+                    return (VerifyResult.TEST_ERROR, f"Killed by {utils.signal_name(-res.returncode)}", i)
+                else:
+                    return (VerifyResult.TEST_ERROR, "No output", i)
+            return (VerifyResult.TEST_ERROR, stderr, i)
+        return None
 
-    def _run_tests_with_rust(self, target, test_number=None, valgrind=False) -> tuple[VerifyResult, Optional[str], Optional[int]]:
+    def _run_tests_with_rust(
+        self,
+        target,
+        test_number=None,
+        valgrind=False,
+        rust_lib_dir=None,
+        sanitize=False,
+    ) -> tuple[VerifyResult, Optional[str], Optional[int]]:
         # get absolute path of the target
         target = os.path.abspath(target)
-        env = utils.patched_env("LD_LIBRARY_PATH", f"{self.embed_test_rust_dir}/target/debug")
-        return self._run_tests(target, env, test_number, valgrind)
+        if rust_lib_dir is None:
+            rust_lib_dir = self._rust_target_dir(self.embed_test_rust_dir)
+        env = utils.patched_env("LD_LIBRARY_PATH", rust_lib_dir)
+        if not sanitize:
+            return self._run_tests(target, env, test_number, valgrind)
+
+        # Write reports to files so they are seen even when a test command
+        # swallows the target's stderr or exit status.
+        report_dir = os.path.join(self.embed_test_c_dir, "sanitizer_reports")
+        shutil.rmtree(report_dir, ignore_errors=True)
+        os.makedirs(report_dir)
+        log_path = os.path.join(report_dir, "report")
+        env["ASAN_OPTIONS"] = f"detect_leaks=1:abort_on_error=0:log_path={log_path}"
+        env["UBSAN_OPTIONS"] = f"print_stacktrace=1:halt_on_error=1:log_path={log_path}"
+        result = self._run_tests(target, env, test_number, valgrind)
+
+        reports = self._collect_sanitizer_reports(report_dir)
+        if reports:
+            logger.error("Sanitizer reported errors while testing %s", target)
+            return (VerifyResult.TEST_ERROR, reports, result[2])
+        return result
+
+    def _goto_targeted_tests(self, c_function: FunctionInfo) -> dict[str, list[int]]:
+        """
+        Maps each `goto` label of the function to the tests that execute a
+        jump to it, found by running the original C program built with gcov
+        coverage.
+        Only single-file programs built natively are measured; anything else
+        (or any failure) yields no targets.
+        """
+        if not c_function.goto_labels or self.cross or self.link_closure or self._iter_executable_variants():
+            return {}
+        if c_function.name in self._goto_coverage:
+            return self._goto_coverage[c_function.name]
+
+        targets: dict[str, list[int]] = {}
+        self._goto_coverage[c_function.name] = targets
+        source = c_function.node.location.file.name
+        extra_args = shlex.split(self.extra_compile_command) if self.extra_compile_command else []
+        with tempfile.TemporaryDirectory(prefix="sactor_goto_") as tmpdir:
+            executable = os.path.join(tmpdir, "coverage")
+            compile_cmd = [utils.get_compiler(), "--coverage", "-O0", "-o", executable,
+                           source, *self.link_args, "-lm", *extra_args]
+            res = utils.run_command(compile_cmd, capture_output=True)
+            if res.returncode != 0:
+                logger.debug("Skipping goto path checks for %s: coverage build failed", c_function.name)
+                return targets
+
+            env = os.environ.copy()
+            env["LC_ALL"] = "C"
+            env["LANG"] = "C"
+            goto_lines = {
+                line: label.name for label in c_function.goto_labels for line in label.goto_lines}
+            test_tasks = self._load_test_tasks()
+            for i, cmd in enumerate(self._load_test_cmd(executable)):
+                for gcda in glob.glob(os.path.join(tmpdir, "*.gcda")):
+                    os.remove(gcda)
+                if self._run_test_command(i, cmd, test_tasks[i], env) is not None:
+                    continue
+                covered = self._covered_lines(tmpdir, source)
+                for label in {goto_lines[line] for line in covered if line in goto_lines}:
+                    targets.setdefault(label, []).append(i)
+        logger.debug("goto paths of %s taken by tests: %s", c_function.name, targets)
+        return targets
+
+    @staticmethod
+    def _covered_lines(coverage_dir: str, source: str) -> set[int]:
+        """Lines of `source` executed at least once, from the gcov data in `coverage_dir`."""
+        covered: set[int] = set()
+        for gcda in glob.glob(os.path.join(coverage_dir, "*.gcda")):
+            res = utils.run_command(["gcov", "--json-format", "--stdout", gcda],
+                                    capture_output=True, cwd=coverage_dir)
+            if res.returncode != 0:
+                continue
+            for document in res.stdout.splitlines():
+                try:
+                    files = json.loads(document).get("files", [])
+                except json.JSONDecodeError:
+                    continue
+                for entry in files:
+                    if os.path.realpath(entry.get("file", "")) != os.path.realpath(source):
+                        continue
+                    covered.update(
+                        line["line_number"] for line in entry.get("lines", []) if line.get("count"))
+        return covered
+
+    def _run_goto_targeted_tests(
+        self,
+        c_function: FunctionInfo,
+        target: str,
+        rust_lib_dir: str,
+        sanitize: bool,
+    ) -> Optional[tuple[VerifyResult, Optional[str], Optional[int]]]:
+        """
+        Runs, for each `goto` label, the first test whose C run jumps to it, so a
+        restructured control flow that breaks a jump target is reported with
+        the path it broke. Returns None when all of them pass.
+        """
+        targets = self._goto_targeted_tests(c_function)
+        for label in c_function.goto_labels:
+            if label.name not in targets:
+                logger.warning("No test takes `goto %s` in %s; its restructured path is unverified",
+                               label.name, c_function.name)
+        checked: set[int] = set()
+        for label in c_function.goto_labels:
+            tests = targets.get(label.name)
+            if not tests or tests[0] in checked:
+                continue
+            checked.add(tests[0])
+            result = self._run_tests_with_rust(
+                target, tests[0], rust_lib_dir=rust_lib_dir, sanitize=sanitize)
+            if result[0] != VerifyResult.SUCCESS:
+                message = (f"{result[1] or ''}\nTest {tests[0]} takes `goto {label.name}` ({label.kind}) "
+                           f"in the C version of {c_function.name}; the restructured control flow "
+                           "does not reproduce that path.")
+                return (result[0], message, result[2])
+        return None
+
+    def _test_repetitions(self, threaded: bool) -> int:
+        """How often the tests run: threaded code repeats them to expose nondeterminism."""
+        if not threaded:
+            return 1
+        return max(1, self.config.get('general', {}).get('thread_test_repetitions', 1))
+
+    def _run_tests_repeatedly(self, run_tests, repetitions: int):
+        """Calls `run_tests` up to `repetitions` times, returning the first failure."""
+        for run in range(1, repetitions + 1):
+            result = run_tests()
+            if result[0] != VerifyResult.SUCCESS:
+                if run > 1:
+                    logger.error("Tests diverged on run %d of %d", run, repetitions)
+                    message = (f"{result[1] or ''}\nThe tests passed on earlier runs but failed on run "
+                               f"{run} of {repetitions}: the threaded code does not behave deterministically.")
+                    result = (result[0], message, *result[2:])
+                return result
+        return result
+
+    def _collect_sanitizer_reports(self, report_dir: str) -> str:
+        byte_limit = self.config.get('general', {}).get('command_output_byte_limit', 40000)
+        reports = []
+        for path in sorted(glob.glob(os.path.join(report_dir, "report.*"))):
+            report = read_file(path).strip()
+            if report:
+                reports.append(report)
+        text = "\n\n".join(reports)
+        if len(text) > byte_limit:
+            text = text[:byte_limit] + "\n... (sanitizer report truncated)"
+        return text
+
+    def _rust_host_triple(self) -> str:
+        res = utils.run_command(["rustc", "+nightly", "-vV"], check=True)
+        for line in res.stdout.splitlines():
+            if line.startswith("host:"):
+                return line.split(":", 1)[1].strip()
+        raise RuntimeError("Failed to determine the Rust host target")
 
     def _mutate_c_code(self, c_function: FunctionInfo, filename, prefix=False) -> str:
         # remove the c code of the function, but keep the function signature
@@ -538,11 +1048,13 @@ class Verifier(ABC):
             all_uses = [list(x) for x in all_uses_tuples]
             all_dependency_uses = merge_uses(all_uses)
             joint_function_dependency_uses = '\n'.join(all_dependency_uses)
+            # C dependencies may `longjmp` into a `sactor_setjmp` of the function
+            abi = "C-unwind" if "sactor_setjmp" in remained_code else "C"
             rust_code = f'''
 #![allow(unused_imports)]
 {joint_function_dependency_uses}
 
-extern "C" {{
+extern "{abi}" {{
 {joint_function_depedency_signatures}
 }}
 
@@ -560,34 +1072,58 @@ extern "C" {{
         utils.create_rust_proj(
             rust_code, name, self.embed_test_rust_dir, is_lib=True)
 
+        # The unidiomatic stage is FFI-heavy, so it is optionally built with
+        # AddressSanitizer (and the C side with ASan+UBSan) to catch memory
+        # errors that still print the expected output.
+        # Sanitizer runtimes are host-only, so cross targets skip them.
+        sanitize = (not idiomatic and self.cross is None
+                    and self.config.get('general', {}).get('sanitizers', False))
+
         # compile
         # should succeed, omit output
-        rust_compile_cmd = ["cargo", "build", "--manifest-path",
+        rust_compile_cmd = ["cargo", "build", *self._cargo_target_args(), "--manifest-path",
                             f"{self.embed_test_rust_dir}/Cargo.toml"]
+        rust_compile_env = self._cargo_env()
+        rust_lib_dir = self._rust_target_dir(self.embed_test_rust_dir)
+        if sanitize:
+            # `--target` keeps the sanitizer flags away from build scripts and proc macros
+            triple = self._rust_host_triple()
+            rust_compile_cmd = ["cargo", "+nightly", "build", "--target", triple,
+                                "--manifest-path", f"{self.embed_test_rust_dir}/Cargo.toml"]
+            rust_compile_env = os.environ.copy()
+            rust_compile_env["RUSTFLAGS"] = (
+                rust_compile_env.get("RUSTFLAGS", "") + " -Zsanitizer=address").strip()
+            rust_lib_dir = f"{self.embed_test_rust_dir}/target/{triple}/debug"
         logger.debug("Compiling embedded Rust crate: %s", " ".join(rust_compile_cmd))
-        res = utils.run_command(rust_compile_cmd, capture_output=False)
+        res = utils.run_command(rust_compile_cmd, capture_output=False, env=rust_compile_env)
         if res.returncode != 0:
             raise RuntimeError(
                 f"Failed to compile Rust code for function {name}")
 
         c_code_removed = self._mutate_c_code(c_function, filename, prefix)
+        if "sactor_setjmp" in rust_code:
+            # route the C code's jumps through the Rust `sactor_longjmp`, which
+            # unwinds to a `sactor_setjmp` or falls back to the C `longjmp`
+            c_code_removed = SETJMP_REDIRECT + c_code_removed
 
         os.makedirs(self.embed_test_c_dir, exist_ok=True)
 
         with open(f"{self.embed_test_c_dir}/{name}.c", "w") as f:
             f.write(c_code_removed)
 
-        compiler = utils.get_compiler()
+        compiler = self._c_compiler()
         source_path = os.path.join(self.embed_test_c_dir, f'{name}.c')
 
         extra_compile_args = shlex.split(self.extra_compile_command) if self.extra_compile_command else []
+        if sanitize:
+            extra_compile_args += SANITIZER_C_FLAGS
         executable_variants = self._iter_executable_variants()
         if not executable_variants:
             executable_variants = [[]]
         multi_variant = len(executable_variants) > 1
 
         link_flags = [
-            f'-L{self.embed_test_rust_dir}/target/debug',
+            f'-L{rust_lib_dir}',
             '-lm',
             f'-l{name}',
         ]
@@ -632,6 +1168,8 @@ extern "C" {{
                         to_check = False
                         if is_compile_command(command):
                             to_check = True
+                            if sanitize:
+                                command = [*command, *SANITIZER_C_FLAGS]
                         logger.debug("Running compile command: %s", command)
                         res = utils.run_command(command, capture_output=False)
                         if to_check and res.returncode != 0:
@@ -693,6 +1231,8 @@ extern "C" {{
                         to_check = False
                         if is_compile_command(command):
                             to_check = True
+                            if sanitize:
+                                command = [*command, *SANITIZER_C_FLAGS]
                         logger.debug("Running compile command: %s", command)
                         res = utils.run_command(command, capture_output=False)
                         if to_check and res.returncode != 0:
@@ -731,12 +1271,19 @@ extern "C" {{
                     if res.returncode != 0:
                         raise RuntimeError(
                             f"Error: Failed to compile C code for function {name}")
-            # run tests
-            result = self._run_tests_with_rust(output_path)
+            # run tests, first the ones exercising each `goto` path
+            result = self._run_goto_targeted_tests(
+                c_function, output_path, rust_lib_dir, sanitize)
+            if result is None:
+                result = self._run_tests_repeatedly(
+                    lambda: self._run_tests_with_rust(
+                        output_path, rust_lib_dir=rust_lib_dir, sanitize=sanitize),
+                    self._test_repetitions(bool(c_function.pthread_calls)),
+                )
             if result[0] != VerifyResult.SUCCESS:
                 failed_test_number = result[2]
-                assert failed_test_number is not None
-                if self.no_feedback:
+                if self.no_feedback or failed_test_number is None:
+                    # sanitizer reports from otherwise passing tests have no test to rerun
                     return (result[0], result[1])
                 # rerun with feedback from `trace_fn`
                 logger.error(
@@ -751,7 +1298,7 @@ extern "C" {{
                         rust_code, name, "#[sactor_proc_macros::trace_fn]")
                 utils.create_rust_proj(
                     rust_code, name, self.embed_test_rust_dir, is_lib=True, proc_macro=True)
-                res = utils.run_command(rust_compile_cmd)
+                res = utils.run_command(rust_compile_cmd, env=rust_compile_env)
                 if res.returncode != 0:
                     logger.error(
                         "Failed to compile Rust code for function %s during feedback rerun",
@@ -767,7 +1314,10 @@ extern "C" {{
                 result = self._run_tests_with_rust(
                     output_path,
                     failed_test_number,
-                    valgrind=True,
+                    # valgrind cannot run ASan-instrumented binaries
+                    valgrind=not sanitize,
+                    rust_lib_dir=rust_lib_dir,
+                    sanitize=sanitize,
                 )
 
                 if result[0] == VerifyResult.FEEDBACK:
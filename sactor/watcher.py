@@ -0,0 +1,263 @@
+import hashlib
+import json
+import os
+import time
+from dataclasses import dataclass, field
+from typing import Callable, Optional
+
+from clang import cindex
+from clang.cindex import CursorKind
+
+from sactor import logging as sactor_logging
+from sactor.c_parser import CParser
+from sactor.translator import TranslateResult
+
+logger = sactor_logging.get_logger(__name__)
+
+WATCH_STATE_FILE = "watch_state.json"
+WATCH_STATE_VERSION = 1
+
+def _macro_definitions(translation_unit) -> dict[str, list[str]]:
+    return {
+        cursor.spelling: [t.spelling for t in cursor.get_tokens()]
+        for cursor in translation_unit.cursor.get_children()
+        if cursor.kind == CursorKind.MACRO_DEFINITION
+    }
+
+
+def _ast_hash(node, macros: dict[str, list[str]]) -> str:
+    """
+    Hash of the definition at `node`: its tokens, without comments and
+    layout, the definitions of the macros it expands, and the kinds and types
+    of its AST, whose canonical types change with the typedefs it uses.
+    """
+    digest = hashlib.sha256()
+    tokens = [t.spelling for t in node.get_tokens()]
+    digest.update("\0".join(tokens).encode())
+    pending = [t for t in tokens if t in macros]
+    expanded = set()
+    while pending:
+        name = pending.pop()
+        if name in expanded:
+            continue
+        expanded.add(name)
+        pending.extend(t for t in macros[name] if t in macros)
+    for name in sorted(expanded):
+        digest.update("\0".join(macros[name]).encode())
+    for cursor in node.walk_preorder():
+        cursor_type = cursor.type
+        digest.update(
+            f"{cursor.kind.name}|{cursor.spelling}|{cursor_type.spelling}|{cursor_type.get_canonical().spelling}\0".encode())
+        if cursor.kind in (CursorKind.INTEGER_LITERAL, CursorKind.FLOATING_LITERAL,
+                           CursorKind.CHARACTER_LITERAL, CursorKind.STRING_LITERAL):
+            digest.update("".join(t.spelling for t in cursor.get_tokens()).encode())
+    return digest.hexdigest()
+
+
+def _local_includes(c_parser: CParser, input_file: str) -> list[str]:
+    """The input file and the headers it includes from its own directory tree."""
+    root = os.path.dirname(os.path.abspath(input_file))
+    files = {os.path.abspath(input_file)}
+    for include in c_parser.translation_unit.get_includes():
+        path = os.path.abspath(include.include.name)
+        if os.path.commonpath([root, path]) == root:
+            files.add(path)
+    return sorted(files)
+
+
+def source_fingerprint(input_file: str) -> dict:
+    """
+    The AST hashes of the definitions in `input_file`: for every function the
+    hash of its definition and of its signature, and the functions it calls;
+    and the hashes of the structs, enums and global variables the functions
+    use. Raises ValueError when the file does not compile, as while it is
+    being edited.
+    """
+    c_parser = CParser(input_file, omit_error=True)
+    errors = [d for d in c_parser.translation_unit.diagnostics if d.severity >= cindex.Diagnostic.Error]
+    if errors:
+        raise ValueError(f"{input_file} does not compile: {errors[0].spelling}")
+    macros = _macro_definitions(c_parser.translation_unit)
+    functions = {}
+    for function in c_parser.get_functions():
+        functions[function.name] = {
+            "hash": _ast_hash(function.node, macros),
+            "signature": hashlib.sha256(
+                f"{function.node.type.spelling}|{function.is_variadic}".encode()).hexdigest(),
+            "calls": sorted(set(function.called_function_names)),
+        }
+    types = {}
+    for kind, items in (("struct", c_parser.get_structs()),
+                        ("enum", c_parser.get_enums()),
+                        ("global_var", c_parser.get_global_vars())):
+        for item in items:
+            types[f"{kind} {item.name}"] = _ast_hash(item.node, macros)
+    return {
+        "functions": functions,
+        "types": types,
+        "files": _local_includes(c_parser, input_file),
+    }
+
+
+@dataclass
+class SyncPlan:
+    # functions to translate again, callees first
+    functions: list[str] = field(default_factory=list)
+    # why the whole file has to be translated again, None when the functions suffice
+    full_reason: Optional[str] = None
+
+
+def plan_sync(previous: Optional[dict], current: dict) -> SyncPlan:
+    """
+    What to translate again to bring the translation of the `previous`
+    fingerprint, see `source_fingerprint`, up to date with `current`.
+    Changed and added functions are translated again, with the callers of
+    the functions whose signature changed. Changed types and removed
+    functions affect the whole translation.
+    """
+    if previous is None:
+        return SyncPlan(full_reason="it has no earlier watched translation")
+    changed_types = sorted(
+        name for name in set(previous["types"]) | set(current["types"])
+        if previous["types"].get(name) != current["types"].get(name))
+    if changed_types:
+        return SyncPlan(full_reason=f"the definitions of {', '.join(changed_types)} changed")
+    removed = sorted(set(previous["functions"]) - set(current["functions"]))
+    if removed:
+        return SyncPlan(full_reason=f"the functions {', '.join(removed)} were removed")
+
+    old, new = previous["functions"], current["functions"]
+    changed = {name for name, info in new.items() if name not in old or old[name]["hash"] != info["hash"]}
+    new_signatures = {name for name in changed if name in old and old[name]["signature"] != new[name]["signature"]}
+    changed |= {name for name, info in new.items() if new_signatures & set(info["calls"])}
+
+    ordered: list[str] = []
+
+    def visit(name: str, path: set[str]):
+        if name in ordered or name in path:
+            return
+        for callee in new[name]["calls"]:
+            if callee in changed:
+                visit(callee, path | {name})
+        ordered.append(name)
+
+    for name in sorted(changed):
+        visit(name, set())
+    return SyncPlan(functions=ordered)
+
+
+class Watcher:
+    """
+    Keeps the translation of `input_file` in `result_dir` in sync with the C
+    source: each `sync` translates again what changed since the last one, as
+    planned by `plan_sync`, using `translate` for the whole file and
+    `retranslate` for one function. The fingerprint of the translated source
+    is kept in `watch_state.json` of the result directory.
+    """
+
+    def __init__(
+        self,
+        input_file: str,
+        result_dir: str,
+        translate: Callable[[], None],
+        retranslate: Callable[[str], TranslateResult],
+        fingerprint: Callable[[str], dict] = source_fingerprint,
+    ):
+        self.input_file = input_file
+        self.state_path = os.path.join(result_dir, WATCH_STATE_FILE)
+        self.translate = translate
+        self.retranslate = retranslate
+        self.fingerprint = fingerprint
+        self.files = [os.path.abspath(input_file)]
+
+    def _load_state(self) -> Optional[dict]:
+        if not os.path.isfile(self.state_path):
+            return None
+        try:
+            with open(self.state_path) as f:
+                state = json.load(f)
+        except (OSError, ValueError) as e:
+            logger.warning("Ignoring unreadable watch state %s: %s", self.state_path, e)
+            return None
+        if state.get("version") != WATCH_STATE_VERSION or state.get("input_file") != os.path.abspath(self.input_file):
+            return None
+        return state["fingerprint"]
+
+    def _save_state(self, fingerprint: dict) -> None:
+        os.makedirs(os.path.dirname(self.state_path), exist_ok=True)
+        with open(self.state_path, "w") as f:
+            json.dump({
+                "version": WATCH_STATE_VERSION,
+                "input_file": os.path.abspath(self.input_file),
+                "fingerprint": fingerprint,
+            }, f, indent=4)
+
+    def sync(self) -> bool:
+        """Translates what changed since the last sync, returns whether all of it succeeded."""
+        try:
+            current = self.fingerprint(self.input_file)
+        except ValueError as e:
+            logger.warning("Not syncing: %s", e)
+            return False
+        self.files = current["files"]
+        previous = self._load_state()
+        plan = plan_sync(previous, current)
+
+        if plan.full_reason is not None:
+            logger.info("Translating %s, %s", self.input_file, plan.full_reason)
+            try:
+                self.translate()
+            except ValueError as e:
+                logger.error("Translation of %s failed: %s", self.input_file, e)
+                return False
+            self._save_state(current)
+            return True
+
+        if not plan.functions:
+            logger.info("No function of %s changed", self.input_file)
+            self._save_state(current)
+            return True
+
+        assert previous is not None
+        logger.info("Translating again the changed functions: %s", ", ".join(plan.functions))
+        synced = dict(previous["functions"])
+        ok = True
+        for name in plan.functions:
+            try:
+                result = self.retranslate(name)
+            except ValueError as e:
+                logger.error("%s", e)
+                result = None
+            if result == TranslateResult.SUCCESS:
+                synced[name] = current["functions"][name]
+            else:
+                # left out of the state, so the next sync tries it again
+                logger.error("Failed to translate %s again, its previous translation is kept", name)
+                ok = False
+        self._save_state({**current, "functions": synced})
+        return ok
+
+    def _stamp(self) -> list[tuple[str, int, int]]:
+        stamp = []
+        for path in self.files:
+            try:
+                stat = os.stat(path)
+            except OSError:
+                continue
+            stamp.append((path, stat.st_mtime_ns, stat.st_size))
+        return stamp
+
+    def run(self, interval: float) -> None:
+        """Syncs, then again whenever the input file or a local header it includes changes, until interrupted."""
+        self.sync()
+        stamp = self._stamp()
+        logger.info("Watching %s for changes, press Ctrl-C to stop", self.input_file)
+        try:
+            while True:
+                time.sleep(interval)
+                current = self._stamp()
+                if current != stamp:
+                    stamp = current
+                    self.sync()
+        except KeyboardInterrupt:
+            logger.info("Stopped watching %s", self.input_file)
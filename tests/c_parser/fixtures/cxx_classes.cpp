@@ -0,0 +1,55 @@
+#include <iostream>
+#include <string>
+#include <vector>
+
+class Shape {
+public:
+    std::string name;
+
+    Shape(const std::string &name) : name(name) {}
+    double area() const { return 0.0; }
+};
+
+class Rect : public Shape {
+    double width;
+    double height;
+
+public:
+    Rect(double width, double height);
+    Rect(double side);
+    ~Rect();
+    double area() const;
+    void scale(double factor);
+};
+
+Rect::Rect(double width, double height) : Shape("rect"), width(width), height(height) {}
+
+Rect::Rect(double side) : Shape("square"), width(side), height(side) {}
+
+Rect::~Rect() {}
+
+double Rect::area() const {
+    return width * height;
+}
+
+void Rect::scale(double factor) {
+    width *= factor;
+    height *= factor;
+}
+
+double total_area(const std::vector<Rect> &rects) {
+    double total = 0.0;
+    for (const Rect &rect : rects) {
+        total += rect.area();
+    }
+    return total;
+}
+
+int main() {
+    std::vector<Rect> rects;
+    rects.push_back(Rect(2.0, 3.0));
+    rects.push_back(Rect(4.0));
+    rects[0].scale(2.0);
+    std::cout << total_area(rects) << std::endl;
+    return 0;
+}
@@ -0,0 +1,29 @@
+#include <map>
+#include <stdexcept>
+
+class Base {
+public:
+    int id;
+};
+
+class Derived : public virtual Base {
+public:
+    std::map<int, int> table;
+
+    int lookup(int key) {
+        if (table.count(key) == 0) {
+            throw std::out_of_range("missing key");
+        }
+        return table[key];
+    }
+};
+
+template <typename T>
+T twice(T value) {
+    return value + value;
+}
+
+int main() {
+    Derived d;
+    return twice(d.id);
+}
@@ -0,0 +1,31 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+
+int check_name(const char *name) {
+    if (name[0] == '\0') {
+        errno = EINVAL;
+        return -1;
+    }
+    return 0;
+}
+
+int open_config(const char *path) {
+    FILE *file = fopen(path, "r");
+    if (file == NULL) {
+        if (errno == ENOENT)
+            return 0;
+        perror(path);
+        return -1;
+    }
+    fclose(file);
+    return 1;
+}
+
+int main(int argc, char **argv) {
+    if (check_name(argc > 1 ? argv[1] : "") != 0) {
+        printf("%s\n", strerror(errno));
+        return 1;
+    }
+    return open_config(argv[1]) < 0;
+}
@@ -0,0 +1,53 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+int copy_twice(int n) {
+    int *a = malloc(sizeof(int) * n);
+    if (!a)
+        goto out;
+    int *b = malloc(sizeof(int) * n);
+    if (!b)
+        goto free_a;
+    if (n < 0)
+        goto free_b;
+    printf("%d\n", n);
+    free(b);
+    free(a);
+    return 0;
+free_b:
+    free(b);
+free_a:
+    free(a);
+out:
+    return -1;
+}
+
+int retry(int tries) {
+    int attempt = 0;
+again:
+    attempt++;
+    if (attempt < tries)
+        goto again;
+    return attempt;
+}
+
+int find(int grid[3][3], int value) {
+    int found = -1;
+    for (int i = 0; i < 3; i++) {
+        for (int j = 0; j < 3; j++) {
+            if (grid[i][j] == value) {
+                found = i * 3 + j;
+                goto done;
+            }
+        }
+    }
+done:
+    return found;
+}
+
+int main(int argc, char **argv) {
+    int n = argc > 1 ? atoi(argv[1]) : 1;
+    printf("%d\n", copy_twice(n));
+    printf("%d\n", retry(n));
+    return 0;
+}
@@ -0,0 +1,16 @@
+#include <stdio.h>
+
+#define BUF_SIZE 256
+#define DOUBLE_BUF (BUF_SIZE * 2)
+#define RATIO 0.75
+#define GREETING "hello"
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define CLAMP_BUF(x) MAX(x, BUF_SIZE)
+#define LOG(x) printf("%d\n", (x))
+
+int use_constants(int n) {
+    char buf[DOUBLE_BUF];
+    printf("%s %d\n", GREETING, (int)(n * RATIO));
+    LOG(n);
+    return CLAMP_BUF(n) + (int)sizeof(buf) + EOF;
+}
@@ -0,0 +1,31 @@
+#include <pthread.h>
+#include <stdio.h>
+
+static pthread_mutex_t counter_lock = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t counter_done = PTHREAD_COND_INITIALIZER;
+static int counter = 0;
+
+void *increment(void *arg) {
+    int times = *(int *)arg;
+    for (int i = 0; i < times; i++) {
+        pthread_mutex_lock(&counter_lock);
+        counter++;
+        pthread_cond_signal(&counter_done);
+        pthread_mutex_unlock(&counter_lock);
+    }
+    return NULL;
+}
+
+int run(int threads, int times) {
+    pthread_t ids[8];
+    for (int i = 0; i < threads; i++)
+        pthread_create(&ids[i], NULL, increment, &times);
+    for (int i = 0; i < threads; i++)
+        pthread_join(ids[i], NULL);
+    return counter;
+}
+
+int main(void) {
+    printf("%d\n", run(4, 1000));
+    return 0;
+}
@@ -0,0 +1,24 @@
+#include <setjmp.h>
+#include <stdio.h>
+
+static jmp_buf parse_env;
+
+int parse_digit(char c) {
+    if (c < '0' || c > '9')
+        longjmp(parse_env, 1);
+    return c - '0';
+}
+
+int parse(const char *text) {
+    int value = 0;
+    if (setjmp(parse_env) != 0)
+        return -1;
+    for (; *text; text++)
+        value = value * 10 + parse_digit(*text);
+    return value;
+}
+
+int main(void) {
+    printf("%d %d\n", parse("42"), parse("4x"));
+    return 0;
+}
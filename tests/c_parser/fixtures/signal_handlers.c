@@ -0,0 +1,29 @@
+#include <signal.h>
+#include <stdio.h>
+#include <unistd.h>
+
+static volatile sig_atomic_t stop = 0;
+
+static void on_interrupt(int sig) {
+    stop = 1;
+}
+
+static void on_fault(int sig) {
+    _exit(3);
+}
+
+int main(void) {
+    struct sigaction action;
+    action.sa_handler = on_fault;
+    sigemptyset(&action.sa_mask);
+    action.sa_flags = 0;
+    sigaction(SIGSEGV, &action, NULL);
+
+    signal(SIGINT, on_interrupt);
+    signal(SIGPIPE, SIG_IGN);
+    while (!stop) {
+        pause();
+    }
+    printf("stopped\n");
+    return 0;
+}
@@ -0,0 +1,47 @@
+#include <stdio.h>
+#include <string.h>
+
+int count_lines(FILE *fp) {
+    char line[256];
+    int count = 0;
+    while (fgets(line, sizeof(line), fp) != NULL) {
+        count++;
+    }
+    return count;
+}
+
+int copy_file(const char *from, const char *to) {
+    FILE *in = fopen(from, "rb");
+    if (in == NULL)
+        return -1;
+    FILE *out = fopen(to, "w");
+    if (out == NULL) {
+        fclose(in);
+        return -1;
+    }
+    char buf[512];
+    size_t n;
+    while ((n = fread(buf, 1, sizeof(buf), in)) > 0) {
+        fwrite(buf, 1, n, out);
+    }
+    fclose(out);
+    fclose(in);
+    return 0;
+}
+
+void report(const char *msg) {
+    fprintf(stderr, "error: %s\n", msg);
+}
+
+int main(int argc, char **argv) {
+    if (argc < 3) {
+        report("usage: copy FROM TO");
+        return 1;
+    }
+    if (copy_file(argv[1], argv[2]) != 0)
+        return 1;
+    FILE *fp = fopen(argv[2], "r");
+    printf("%d\n", count_lines(fp));
+    fclose(fp);
+    return 0;
+}
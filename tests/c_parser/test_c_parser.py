@@ -271,3 +271,25 @@ int main() {
         c_parser = CParser(file_path)
         main = c_parser.get_function_info('main')
         assert set(main.stdio_list) == {'stdin', 'stderr'}
+
+
+def test_variadic():
+    code = '''
+#include <stdarg.h>
+int sum(int count, ...) {
+    va_list args;
+    va_start(args, count);
+    int total = 0;
+    for (int i = 0; i < count; i++)
+        total += va_arg(args, int);
+    va_end(args);
+    return total;
+}
+int twice(int x) { return sum(2, x, x); }'''
+    with tempfile.TemporaryDirectory() as tmpdir:
+        file_path = f'{tmpdir}/tmp.c'
+        with open(file_path, 'w') as f:
+            f.write(code)
+        c_parser = CParser(file_path)
+        assert c_parser.get_function_info('sum').is_variadic
+        assert not c_parser.get_function_info('twice').is_variadic
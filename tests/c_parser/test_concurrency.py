@@ -0,0 +1,37 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.concurrency import concurrency_plan, is_pthread_call, sync_global_code
+
+FIXTURE = os.path.join(os.path.dirname(__file__), "fixtures", "pthread_counter.c")
+
+
+def test_sync_global_code():
+    assert sync_global_code("counter_lock", "pthread_mutex_t") \
+        == "pub static counter_lock: std::sync::Mutex<()> = std::sync::Mutex::new(());\n"
+    assert sync_global_code("ready", "volatile pthread_cond_t") \
+        == "pub static ready: std::sync::Condvar = std::sync::Condvar::new();\n"
+    assert sync_global_code("counter", "int") is None
+
+
+def test_concurrency_plan():
+    assert is_pthread_call("pthread_mutex_lock")
+    assert not is_pthread_call("printf")
+
+    plan = concurrency_plan(["pthread_create", "pthread_join"], {})
+    assert "std::thread::spawn" in plan
+    assert "Mutex" not in plan
+
+    lock = sync_global_code("counter_lock", "pthread_mutex_t")
+    plan = concurrency_plan(["pthread_cond_signal", "pthread_mutex_lock"], {"counter_lock": lock})
+    assert "`std::sync::Mutex<T>`" in plan
+    assert "notify_one()" in plan
+    assert lock in plan
+
+
+def test_c_parser_pthread_calls():
+    parser = CParser(FIXTURE, omit_error=True)
+    assert parser.get_function_info("increment").pthread_calls == [
+        "pthread_cond_signal", "pthread_mutex_lock", "pthread_mutex_unlock"]
+    assert parser.get_function_info("run").pthread_calls == ["pthread_create", "pthread_join"]
+    assert parser.get_function_info("main").pthread_calls == []
@@ -0,0 +1,101 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.cxx_classes import (CONSTRUCTOR, DESTRUCTOR, METHOD, CxxClass, CxxMethod,
+                                         class_rust_skeleton, cxx_class_plan, is_cxx_source,
+                                         parse_args, rust_type_of_cxx)
+
+FIXTURES = os.path.join(os.path.dirname(__file__), "fixtures")
+
+
+def test_cxx_sources():
+    assert is_cxx_source("src/shape.cpp")
+    assert is_cxx_source("shape.cc")
+    assert not is_cxx_source("shape.c")
+    assert parse_args("shape.cpp") == ['-x', 'c++', '-std=c++17']
+    assert parse_args("shape.c") == ['-x', 'c', '-std=c99']
+
+
+def test_rust_type_of_cxx():
+    classes = {"Rect"}
+    assert rust_type_of_cxx("std::string", classes) == "String"
+    assert rust_type_of_cxx("const std::string &", classes, param=True) == "&str"
+    assert rust_type_of_cxx("std::vector<Rect>", classes) == "Vec<Rect>"
+    assert rust_type_of_cxx("const std::vector<int> &", classes, param=True) == "&[i32]"
+    assert rust_type_of_cxx("std::vector<std::string> &", classes, param=True) == "&mut Vec<String>"
+    assert rust_type_of_cxx("Rect *", classes) == "Option<Box<Rect>>"
+    assert rust_type_of_cxx("const Rect *", classes, param=True) == "Option<&Rect>"
+    assert rust_type_of_cxx("unsigned long", classes) == "u64"
+    assert rust_type_of_cxx("std::map<int, int>", classes) is None
+    assert rust_type_of_cxx("std::vector<std::map<int, int>>", classes) is None
+
+
+def test_class_rust_skeleton():
+    rect = CxxClass(
+        "Rect",
+        "rect.cpp:1",
+        fields=[("width", "double"), ("label", "std::string")],
+        methods=[
+            CxxMethod("Rect", CONSTRUCTOR, "void", [("width", "double")]),
+            CxxMethod("Rect", CONSTRUCTOR, "void", [("width", "double"), ("height", "double")]),
+            CxxMethod("Rect", CONSTRUCTOR, "void", [("other", "const Rect &")]),
+            CxxMethod("~Rect", DESTRUCTOR, "void"),
+            CxxMethod("getLabel", METHOD, "const std::string &", is_const=True),
+            CxxMethod("scale", METHOD, "void", [("factor", "double")]),
+            CxxMethod("unit", METHOD, "Rect", is_static=True, is_public=False),
+        ],
+        base="Shape",
+        public_fields={"label"},
+    )
+    skeleton = class_rust_skeleton(rect, {"Rect", "Shape"})
+    assert "pub struct Rect {\n    pub base: Shape,\n    width: f64,\n    pub label: String,\n}" in skeleton
+    assert "pub fn new(width: f64) -> Self { todo!() }" in skeleton
+    assert "pub fn new_with_width_height(width: f64, height: f64) -> Self { todo!() }" in skeleton
+    assert "pub fn get_label(&self) -> &str { todo!() }" in skeleton
+    assert "pub fn scale(&mut self, factor: f64) { todo!() }" in skeleton
+    assert "    fn unit() -> Rect { todo!() }" in skeleton
+    assert "impl Clone for Rect" in skeleton
+    assert "impl Drop for Rect" in skeleton
+
+
+def test_cxx_class_plan():
+    shape = CxxClass("Shape", "shape.cpp:1", fields=[("name", "std::string")])
+    rect = CxxClass("Rect", "shape.cpp:5", base="Shape",
+                    methods=[CxxMethod("operator==", METHOD, "bool", [("other", "const Rect &")], is_const=True)])
+    plan = cxx_class_plan([rect], "class Rect : public Shape {};\n", [shape], {"Shape", "Rect"})
+    assert "already translated as these Rust items" in plan
+    assert "pub struct Shape {\n    name: String,\n}" in plan
+    assert "class Rect : public Shape {};" in plan
+    assert "`base` field" in plan
+    assert "`operator==` becomes `PartialEq`" in plan
+    assert "pub struct Rect {\n    pub base: Shape,\n}" in plan
+
+    defined_only = cxx_class_plan([], "", [shape], {"Shape"})
+    assert "Translate the following classes" not in defined_only
+
+
+def test_c_parser_cxx_classes():
+    parser = CParser(os.path.join(FIXTURES, "cxx_classes.cpp"), omit_error=True)
+
+    assert parser.is_cxx
+    assert parser.get_unsupported_cxx_constructs() == []
+    rect = parser.get_cxx_class("Rect")
+    assert rect.base == "Shape"
+    assert rect.fields == [("width", "double"), ("height", "double")]
+    assert [(m.name, m.kind) for m in rect.methods] == [
+        ("Rect", CONSTRUCTOR), ("Rect", CONSTRUCTOR), ("~Rect", DESTRUCTOR), ("area", METHOD), ("scale", METHOD),
+    ]
+    assert parser.get_cxx_class("Shape").public_fields == {"name"}
+    assert "double Rect::area() const {" in parser.extract_cxx_class_code("Rect")
+    assert parser.get_function_info("total_area").cxx_classes == ["Shape", "Rect"]
+    assert [s.name for s in parser.get_structs()] == []
+
+
+def test_c_parser_unsupported_cxx_constructs():
+    parser = CParser(os.path.join(FIXTURES, "cxx_unsupported.cpp"), omit_error=True)
+
+    unsupported = "\n".join(parser.get_unsupported_cxx_constructs())
+    assert "virtual inheritance in class `Derived`" in unsupported
+    assert "field `Derived::table` has type `std::map<int, int>`" in unsupported
+    assert "exception is not supported" in unsupported
+    assert "template `twice` is not supported" in unsupported
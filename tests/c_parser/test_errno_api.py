@@ -0,0 +1,37 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.errno_api import errno_guidance
+
+FIXTURE = os.path.join(os.path.dirname(__file__), "fixtures", "errno_paths.c")
+
+
+def test_errno_guidance():
+    unidiomatic = errno_guidance(["fopen"], False, idiomatic=False)
+    assert "std::io::Error::last_os_error()" in unidiomatic
+    assert "libc::__errno_location()" in unidiomatic
+
+    idiomatic = errno_guidance(["fclose", "fopen", "printf"], False, idiomatic=True)
+    assert "`fopen` becomes `std::fs::File::open`" in idiomatic
+    assert "printf" not in idiomatic
+    assert "on_error" not in idiomatic
+
+    setter = errno_guidance([], True, idiomatic=True, result_callees=["check_name"])
+    assert "already return `Result` in place of the sentinel and `errno`" in setter
+    assert "instead of checking `errno`: `check_name`." in setter
+    assert '"on_error": "-1"' in setter
+
+
+def test_c_parser_errno_usage():
+    parser = CParser(FIXTURE, omit_error=True)
+
+    check_name = parser.get_function_info("check_name")
+    assert check_name.uses_errno and check_name.sets_errno
+    assert check_name.errno_calls == []
+
+    open_config = parser.get_function_info("open_config")
+    assert open_config.uses_errno and not open_config.sets_errno
+    assert open_config.errno_calls == ["fclose", "fopen"]
+
+    main = parser.get_function_info("main")
+    assert main.uses_errno and not main.sets_errno
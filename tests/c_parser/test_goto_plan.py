@@ -0,0 +1,50 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.goto_plan import (GOTO_CLEANUP, GOTO_FORWARD, GOTO_LOOP,
+                                       classify_goto_label, goto_plan)
+
+
+def test_classify_goto_label():
+    assert classify_goto_label("out", 20, [7, 3], True, False).kind == GOTO_CLEANUP
+    assert classify_goto_label("out", 20, [7, 3], True, False).goto_lines == [3, 7]
+    assert classify_goto_label("again", 5, [9], True, False).kind == GOTO_LOOP
+    # a loop after the label means it is not the function's exit path
+    assert classify_goto_label("next", 20, [12], True, True).kind == GOTO_FORWARD
+    assert classify_goto_label("inner", 20, [12], False, False).kind == GOTO_FORWARD
+
+
+def test_goto_plan():
+    labels = [
+        classify_goto_label("again", 5, [9], True, False),
+        classify_goto_label("free_a", 30, [12], True, False),
+        classify_goto_label("out", 32, [8], True, False),
+    ]
+    plan = goto_plan(labels)
+    assert plan.startswith("The function uses `goto`")
+    assert "1. Labels `free_a`, `out` form an error-cleanup ladder" in plan
+    assert "break 'label;" in plan
+    assert "2. `goto again` jumps backwards" in plan
+    assert "continue 'again;" in plan
+
+    idiomatic = goto_plan(labels, idiomatic=True)
+    assert "`Drop`" in idiomatic
+    assert "break 'label;" not in idiomatic
+
+
+def test_c_parser_goto_labels():
+    file_path = os.path.join(os.path.dirname(__file__), "fixtures", "goto_paths.c")
+    parser = CParser(file_path, omit_error=True)
+
+    labels = parser.get_function_info("copy_twice").goto_labels
+    assert [(label.name, label.kind) for label in labels] == [
+        ("free_b", GOTO_CLEANUP), ("free_a", GOTO_CLEANUP), ("out", GOTO_CLEANUP)]
+    assert labels[1].goto_lines == [10]
+
+    labels = parser.get_function_info("retry").goto_labels
+    assert [(label.name, label.kind) for label in labels] == [("again", GOTO_LOOP)]
+
+    labels = parser.get_function_info("find").goto_labels
+    assert [(label.name, label.kind) for label in labels] == [("done", GOTO_CLEANUP)]
+
+    assert parser.get_function_info("main").goto_labels == []
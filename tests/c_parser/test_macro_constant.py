@@ -0,0 +1,62 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.macro_constant import infer_macro_constant
+
+
+def _infer(body: str, known=None):
+    constant = infer_macro_constant("C", body.split(), known or {})
+    return constant and (constant.rust_type, constant.rust_value)
+
+
+def test_infer_literal_types():
+    assert _infer("1024") == ("i32", "1024")
+    assert _infer("3000000000") == ("i64", "3000000000")
+    assert _infer("0xFFFFFFFF") == ("u32", "0xFFFFFFFF")
+    assert _infer("1UL") == ("u64", "1")
+    assert _infer("0755") == ("i32", "0o755")
+    assert _infer(".5f") == ("f32", "0.5")
+    assert _infer("1e3") == ("f64", "1.0e3")
+    assert _infer("'A'") == ("i32", "b'A' as i32")
+    assert _infer("'\\n'") == ("i32", "10")
+    assert _infer('"a\\101" "b"') == ("&core::ffi::CStr", 'c"aAb"')
+
+
+def test_infer_expressions():
+    size = infer_macro_constant("SIZE", ["64"])
+    known = {"SIZE": size}
+    assert _infer("( SIZE * 2 )", known) == ("i32", "SIZE * 2")
+    assert _infer("SIZE + 1U", known) == ("u32", "(SIZE as u32) + 1")
+    assert _infer("SIZE * 0.5", known) == ("f64", "(SIZE as f64) * 0.5")
+    assert _infer("( 1UL << 40 )") == ("u64", "1 << 40")
+    assert _infer("- 1") == ("i32", "-1")
+    assert _infer("- 1U") == ("u32", "1u32.wrapping_neg()")
+    assert _infer("( unsigned char ) 300") == ("u8", "300i32 as u8")
+    assert _infer("( uint8_t ) 7") == ("u8", "7")
+    assert _infer("SIZE > 8", known) == ("i32", "(SIZE > 8) as i32")
+
+
+def test_infer_rejects_non_constants():
+    assert _infer("foo ( 1 )") is None
+    assert _infer("sizeof ( int )") is None
+    assert _infer("1 ? 2 : 3") is None
+    assert _infer('"a\\0b"') is None
+    assert _infer("") is None
+    assert infer_macro_constant("type", ["1"]) is None
+
+
+def test_macro_constants_for_function():
+    fixture = os.path.join(os.path.dirname(__file__), "fixtures", "macro_constants.c")
+    parser = CParser(fixture, omit_error=True)
+
+    constants = parser.get_macro_constants_for_function("use_constants")
+    assert [constant.rust_code() for constant in constants] == [
+        "pub const BUF_SIZE: i32 = 256;\n",
+        "pub const DOUBLE_BUF: i32 = BUF_SIZE * 2;\n",
+        "pub const RATIO: f64 = 0.75;\n",
+        'pub const GREETING: &core::ffi::CStr = c"hello";\n',
+    ]
+    # macros that are not constants are still shown to the LLM as definitions
+    macros = "\n".join(parser.get_macro_definitions_for_function("use_constants", exclude_translated=True))
+    assert "#define LOG" in macros
+    assert "BUF_SIZE 256" not in macros
@@ -0,0 +1,58 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.macro_constant import infer_macro_constant
+from sactor.c_parser.macro_function import translate_macro_function
+
+
+def _translate(params: str, body: str, constants=None, macros=None):
+    macro = translate_macro_function("M", params.split(), body.split(), constants, macros)
+    return macro and macro.rust_body
+
+
+def test_translate_pure_expressions():
+    assert _translate("a b", "( ( a ) > ( b ) ? ( a ) : ( b ) )") == "if $a > $b { $a } else { $b }"
+    assert _translate("x", "( ( x ) & 0xFF )") == "$x & 0xFF"
+    assert _translate("n", "( 1UL << ( n ) )") == "1u64 << $n"
+    assert _translate("x", "( ( x ) < 0 )") == "$x < 0"
+    assert _translate("x", "! ( x )") == "!($x != 0)"
+    assert _translate("x", "( ( uint8_t ) ( x ) )") == "$x as u8"
+    assert _translate("x", "( x ) * 0.5f") == "$x * 0.5f32"
+
+    size = {"SIZE": infer_macro_constant("SIZE", ["8"])}
+    assert _translate("x", "( x ) % SIZE", constants=size) == "$x % SIZE"
+
+
+def test_translate_nested_macros():
+    max_macro = translate_macro_function("MAX", ["a", "b"], "( a ) > ( b ) ? ( a ) : ( b )".split())
+    assert max_macro.rust_code() == (
+        "macro_rules! MAX {\n"
+        "    ($a:expr, $b:expr) => {\n"
+        "        if $a > $b { $a } else { $b }\n"
+        "    };\n"
+        "}\n"
+    )
+    assert _translate("a b c", "MAX ( a , MAX ( b , c ) )", macros={"MAX": max_macro}) \
+        == "MAX!($a, MAX!($b, $c))"
+    assert _translate("a", "MAX ( a )", macros={"MAX": max_macro}) is None
+
+
+def test_translate_rejects_impure_expansions():
+    assert _translate("x", "( ( x ) ++ )") is None
+    assert _translate("x", "( x ) = 0") is None
+    assert _translate("x", "( ( x ) , 1 )") is None
+    assert _translate("x", "foo ( x )") is None
+    assert _translate("x", "sizeof ( x )") is None
+    assert _translate("x", "# x") is None
+    assert _translate("x ...", "( x )") is None
+
+
+def test_macro_functions_for_function():
+    fixture = os.path.join(os.path.dirname(__file__), "fixtures", "macro_constants.c")
+    parser = CParser(fixture, omit_error=True)
+
+    macros = parser.get_macro_functions_for_function("use_constants")
+    assert [(macro.name, macro.rust_body) for macro in macros] == [
+        ("MAX", "if $a > $b { $a } else { $b }"),
+        ("CLAMP_BUF", "MAX!($x, BUF_SIZE)"),
+    ]
@@ -0,0 +1,51 @@
+import json
+import os
+
+import pytest
+
+from sactor.c_parser.make_capture import _c_entries, capture_make_compile_commands
+
+
+def test_capture_make_compile_commands(tmp_path):
+    (tmp_path / "util.c").write_text("int util(void) { return 42; }\n")
+    (tmp_path / "main.c").write_text("int util(void);\nint main(void) { return util() - 42; }\n")
+    (tmp_path / "tool.c").write_text("int main(void) { return 0; }\n")
+    (tmp_path / "Makefile").write_text(
+        "CC = false\n"
+        "all: prog tool\n"
+        "prog: main.o util.o\n"
+        "\t$(CC) -o prog main.o util.o\n"
+        "%.o: %.c\n"
+        "\t$(CC) -DFROM_MAKE -c $< -o $@\n"
+        "tool: tool.c\n"
+        "\tgcc -O1 -o tool tool.c\n"
+    )
+
+    output = capture_make_compile_commands(str(tmp_path), use_bear=False)
+    assert output == os.path.realpath(tmp_path / "compile_commands.json")
+    with open(output) as fh:
+        entries = {os.path.basename(entry["file"]): entry for entry in json.load(fh)}
+
+    assert set(entries) == {"main.c", "util.c", "tool.c"}
+    assert entries["main.c"]["directory"] == os.path.realpath(tmp_path)
+    assert "-DFROM_MAKE" in entries["main.c"]["arguments"]
+    assert os.path.isabs(entries["main.c"]["arguments"][0])
+    assert entries["tool.c"]["arguments"][1:] == ["-O1", "tool.c", "-c"]
+    assert (tmp_path / "prog").exists()
+
+
+def test_capture_make_failure(tmp_path):
+    (tmp_path / "Makefile").write_text("all:\n\texit 3\n")
+    with pytest.raises(ValueError, match="make failed"):
+        capture_make_compile_commands(str(tmp_path), use_bear=False)
+
+
+def test_c_entries_split_multi_source_commands():
+    entries = _c_entries([
+        {"directory": "/p", "command": "cc -Iinc a.c b.c -o app -lm"},
+        {"directory": "/p", "arguments": ["cc", "a.o", "-o", "app"]},
+    ])
+    assert entries == [
+        {"directory": "/p", "arguments": ["cc", "-Iinc", "a.c", "-lm", "-c"], "file": "/p/a.c"},
+        {"directory": "/p", "arguments": ["cc", "-Iinc", "b.c", "-lm", "-c"], "file": "/p/b.c"},
+    ]
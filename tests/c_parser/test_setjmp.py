@@ -0,0 +1,82 @@
+import os
+import subprocess
+
+from sactor import utils
+from sactor.c_parser import CParser
+from sactor.verifier.verifier import SETJMP_REDIRECT
+
+FIXTURE = os.path.join(os.path.dirname(__file__), "fixtures", "setjmp_paths.c")
+
+
+def test_c_parser_detects_setjmp():
+    parser = CParser(FIXTURE, omit_error=True)
+    parse = parser.get_function_info("parse")
+    assert parse.uses_setjmp and not parse.uses_longjmp
+    parse_digit = parser.get_function_info("parse_digit")
+    assert parse_digit.uses_longjmp and not parse_digit.uses_setjmp
+    main = parser.get_function_info("main")
+    assert not main.uses_setjmp and not main.uses_longjmp
+
+
+def test_uses_setjmp_scaffold():
+    assert utils.uses_setjmp_scaffold("static mut ENV: JmpBuf = JmpBuf([0; 512]);")
+    assert utils.uses_setjmp_scaffold("unsafe { sactor_longjmp(&raw mut ENV, 1) }")
+    assert not utils.uses_setjmp_scaffold("fn setjmp_like() {}")
+
+
+def test_setjmp_scaffold_interoperates_with_c(tmp_path):
+    # `parse` translated with the scaffold; `parse_digit` still C and jumping with `longjmp`
+    rust_code = utils.SETJMP_SCAFFOLD_CODE + '''
+static mut PARSE_ENV: JmpBuf = JmpBuf([0; 512]);
+
+extern "C-unwind" {
+    fn parse_digit(env: *mut JmpBuf, c: i8) -> i32;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn parse(text: *const i8) -> i32 {
+    let result = sactor_setjmp(&raw mut PARSE_ENV, || {
+        let mut value = 0;
+        let mut p = text;
+        while *p != 0 {
+            value = value * 10 + parse_digit(&raw mut PARSE_ENV, *p);
+            p = p.add(1);
+        }
+        value
+    });
+    result.unwrap_or(-1)
+}
+'''
+    (tmp_path / "lib.rs").write_text(rust_code)
+    subprocess.run(
+        ["rustc", "--edition", "2021", "-D", "warnings", "--crate-type", "cdylib",
+         "--crate-name", "parse", "lib.rs"],
+        cwd=tmp_path, check=True, capture_output=True,
+    )
+    (tmp_path / "main.c").write_text(SETJMP_REDIRECT + '''
+#include <setjmp.h>
+#include <stdio.h>
+
+int parse(const char *text);
+
+int parse_digit(jmp_buf env, char c) {
+    if (c < '0' || c > '9')
+        longjmp(env, 1);
+    return c - '0';
+}
+
+int main(void) {
+    jmp_buf env;
+    if (setjmp(env) == 0) {
+        parse_digit(env, 'x');
+    }
+    printf("%d %d\\n", parse("42"), parse("4x"));
+    return 0;
+}
+''')
+    subprocess.run(["cc", "main.c", "-L.", "-lparse", "-o", "main"], cwd=tmp_path, check=True)
+    result = subprocess.run(
+        ["./main"], cwd=tmp_path, capture_output=True, text=True, check=True,
+        env={**os.environ, "LD_LIBRARY_PATH": str(tmp_path)},
+    )
+    assert result.stdout == "42 -1\n"
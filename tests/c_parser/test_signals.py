@@ -0,0 +1,47 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.signals import SignalRegistration, signal_flag_global_code, signal_plan
+
+FIXTURE = os.path.join(os.path.dirname(__file__), "fixtures", "signal_handlers.c")
+
+
+def test_signal_flag_global_code():
+    assert signal_flag_global_code("stop", "volatile sig_atomic_t") == (
+        "pub static stop: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);\n")
+    assert "AtomicI32::new(1)" in signal_flag_global_code("armed", "sig_atomic_t", "1")
+    assert signal_flag_global_code("count", "volatile int") is None
+
+
+def test_signal_plan():
+    registrations = [
+        SignalRegistration("SIGSEGV", "on_fault"),
+        SignalRegistration("SIGINT", "on_interrupt"),
+        SignalRegistration("SIGPIPE", "SIG_IGN"),
+    ]
+    plan = signal_plan(registrations, [], idiomatic=True)
+    assert "sactor_ignore_signal(sig)" in plan
+    assert "sactor_signal_flag(libc::SIGINT, &FLAG)" in plan
+    assert "Do not register handlers for `SIGSEGV`" in plan
+    assert "std::panic::set_hook" in plan
+
+    handler = signal_plan([], ["SIGINT"], idiomatic=True)
+    assert "The function is the handler for `SIGINT`" in handler
+    assert "sactor_ignore_signal" not in handler
+
+    unidiomatic = signal_plan(registrations, [], idiomatic=False)
+    assert "libc::sigaction" in unidiomatic
+    assert "sactor_" not in unidiomatic
+
+
+def test_c_parser_signal_registrations():
+    parser = CParser(FIXTURE, omit_error=True)
+
+    main = parser.get_function_info("main")
+    assert main.signal_registrations == [
+        SignalRegistration("SIGSEGV", "on_fault"),
+        SignalRegistration("SIGINT", "on_interrupt"),
+        SignalRegistration("SIGPIPE", "SIG_IGN"),
+    ]
+    assert parser.get_function_info("on_interrupt").handled_signals == ["SIGINT"]
+    assert parser.get_function_info("on_fault").handled_signals == ["SIGSEGV"]
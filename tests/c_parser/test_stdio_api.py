@@ -0,0 +1,58 @@
+import os
+
+from sactor.c_parser import CParser
+from sactor.c_parser.stdio_api import fopen_open_call, stdio_plan, uses_file_streams
+
+FIXTURE = os.path.join(os.path.dirname(__file__), "fixtures", "stdio_paths.c")
+
+
+def test_fopen_open_call():
+    assert fopen_open_call("r") == "File::open(path)"
+    assert fopen_open_call("rb") == "File::open(path)"
+    assert fopen_open_call("w") == "File::create(path)"
+    assert fopen_open_call("a") == "OpenOptions::new().append(true).create(true).open(path)"
+    assert fopen_open_call("w+x") == (
+        "OpenOptions::new().read(true).write(true).create_new(true).open(path)")
+    assert fopen_open_call("wx") == "OpenOptions::new().write(true).create_new(true).open(path)"
+    assert fopen_open_call("rx") is None
+    assert fopen_open_call("q") is None
+
+
+def test_stdio_plan():
+    plan = stdio_plan(["fclose", "fgets", "fopen", "printf"], ["r", "w"], [])
+    assert "`fgets` becomes `BufRead::read_line`" in plan
+    assert "printf" not in plan
+    assert '`fopen(path, "r")` is `File::open(path)`' in plan
+    assert '`fopen(path, "w")` is `File::create(path)`' in plan
+    assert "Buffering compatibility" in plan
+    assert "std::process::exit" in plan
+    assert "stdout().lock()" not in plan
+
+    assert "stdout().lock()" in stdio_plan(["fgets"], [], ["stdin"])
+
+
+def test_uses_file_streams():
+    # writing diagnostics to stderr alone does not need the File mapping
+    assert not uses_file_streams(["fprintf"], ["stderr"])
+    assert uses_file_streams(["fprintf"], [])
+    assert uses_file_streams(["fgets", "fprintf"], ["stderr"])
+    assert not uses_file_streams([], ["stdout"])
+
+
+def test_c_parser_stdio_usage():
+    parser = CParser(FIXTURE, omit_error=True)
+
+    count_lines = parser.get_function_info("count_lines")
+    assert count_lines.stdio_calls == ["fgets"]
+    assert count_lines.fopen_modes == []
+
+    copy_file = parser.get_function_info("copy_file")
+    assert copy_file.stdio_calls == ["fclose", "fopen", "fread", "fwrite"]
+    assert copy_file.fopen_modes == ["rb", "w"]
+
+    report = parser.get_function_info("report")
+    assert report.stdio_calls == ["fprintf"]
+    assert not uses_file_streams(report.stdio_calls, report.stdio_list)
+
+    main = parser.get_function_info("main")
+    assert main.fopen_modes == ["r"]
@@ -88,6 +88,9 @@ def test_combine(config):
         assert stat["total_tokens"] == total_tokens
         assert stat["unsafe_tokens"] == unsafe_tokens
         assert stat["unsafe_fraction"] == unsafe_fraction
+        functions = rust_ast_parser.find_pure_functions(combined_code)
+        assert stat["total_functions"] == len(functions)
+        assert stat["pure_functions"] == sum(1 for f in functions if f["pure"])
         assert utils.normalize_string(
             combined_code) == utils.normalize_string(expected_code)
 
@@ -130,5 +133,8 @@ def test_combine_idiomatic(config):
         assert stat["total_tokens"] == total_tokens
         assert stat["unsafe_tokens"] == unsafe_tokens
         assert stat["unsafe_fraction"] == unsafe_fraction
+        functions = rust_ast_parser.find_pure_functions(combined_code)
+        assert stat["total_functions"] == len(functions)
+        assert stat["pure_functions"] == sum(1 for f in functions if f["pure"])
         assert utils.normalize_string(
             combined_code) == utils.normalize_string(expected_code)
@@ -0,0 +1,121 @@
+import subprocess
+
+import pytest
+
+from sactor.combiner import CrateEmitter, CrateUnit
+from sactor.combiner.crate_emitter import cargo_manifest, uses_libc
+from sactor.utils import read_file
+
+
+def test_uses_libc():
+    assert uses_libc("fn f(x: libc::c_int) {}")
+    assert uses_libc("use libc;\nfn f() {}")
+    assert not uses_libc("fn f(x: i32) -> i32 { x }")
+    assert not uses_libc("use mylibc::Handle;")
+
+
+def test_cargo_manifest():
+    manifest = cargo_manifest("demo", "fn main() {}", edition="2024", bins=[("demo", "src/main.rs")])
+    assert 'edition = "2024"' in manifest
+    assert "libc" not in manifest
+    assert "[lib]" not in manifest
+    assert '[[bin]]\nname = "demo"\npath = "src/main.rs"' in manifest
+
+    manifest = cargo_manifest(
+        "demo", "fn f() -> String { serde_json::to_string(&1).unwrap() } fn g(_: libc::c_int) {}",
+        lib_crate_types=["cdylib", "staticlib"], build_script=True)
+    assert 'libc = "0.2.159"' in manifest
+    assert 'serde_json = "1"' in manifest
+    assert 'crate-type = ["cdylib", "staticlib"]' in manifest
+    assert 'build = "build.rs"' in manifest
+
+
+def test_emit_targets(tmp_path):
+    emitter = CrateEmitter({"general": {"rust_edition": "2018"}})
+    units = [
+        CrateUnit("core", "pub fn helper() -> i32 { 1 }", kind="lib"),
+        CrateUnit("first", "fn main() {}"),
+        CrateUnit("second", "fn main() {}"),
+    ]
+    crate_dir = emitter.emit(str(tmp_path / "crate"), "demo", units)
+
+    manifest = read_file(str(tmp_path / "crate" / "Cargo.toml"))
+    assert 'edition = "2018"' in manifest
+    assert 'crate-type = ["rlib"]' in manifest
+    assert 'path = "src/bin/second.rs"' in manifest
+    assert (tmp_path / "crate" / "src" / "lib.rs").exists()
+    assert (tmp_path / "crate" / "src" / "bin" / "first.rs").exists()
+    assert not (tmp_path / "crate" / "build.rs").exists()
+    assert crate_dir == str(tmp_path / "crate")
+
+    with pytest.raises(ValueError, match="at most one library"):
+        emitter.emit(str(tmp_path / "bad"), "demo", [units[0], units[0]])
+
+
+def test_emit_links_remaining_c_objects(tmp_path):
+    c_file = tmp_path / "square.c"
+    c_file.write_text("int square(int x) { return x * x; }\n")
+    obj = tmp_path / "square.o"
+    subprocess.run(["cc", "-c", str(c_file), "-o", str(obj)], check=True)
+    code = '''
+extern "C" {
+    fn square(x: i32) -> i32;
+}
+
+fn main() {
+    println!("{}", unsafe { square(7) });
+}
+'''
+    crate_dir = tmp_path / "crate"
+    CrateEmitter({"general": {}}).emit(
+        str(crate_dir), "demo", [CrateUnit("demo", code)], c_objects=[str(obj)], link_args=["-lm"])
+
+    build_script = read_file(str(crate_dir / "build.rs"))
+    assert "rustc-link-lib=static=sactor_c" in build_script
+    assert "rustc-link-lib=m" in build_script
+    assert 'edition = "2021"' in read_file(str(crate_dir / "Cargo.toml"))
+    result = subprocess.run(
+        ["cargo", "run", "--quiet", "--manifest-path", str(crate_dir / "Cargo.toml")],
+        capture_output=True, text=True, check=True)
+    assert result.stdout == "49\n"
+
+
+def test_emit_stable_output(tmp_path):
+    emitter = CrateEmitter({"general": {"stable_output": True, "msrv": "1.80"}})
+    crate_dir = emitter.emit(str(tmp_path / "crate"), "demo", [CrateUnit("demo", "fn main() {}")])
+    assert 'rust-version = "1.80"' in read_file(str(tmp_path / "crate" / "Cargo.toml"))
+    assert crate_dir == str(tmp_path / "crate")
+
+    variadic = 'pub unsafe extern "C" fn sum(count: i32, mut args: ...) -> i32 { count }'
+    with pytest.raises(ValueError, match="line 1: C-variadic function `sum`"):
+        emitter.emit(str(tmp_path / "bad"), "demo", [CrateUnit("demo", variadic, kind="lib")])
+    # without the mode the feature gate is added instead
+    CrateEmitter({"general": {}}).emit(
+        str(tmp_path / "nightly"), "demo", [CrateUnit("demo", variadic, kind="lib")])
+    assert read_file(str(tmp_path / "nightly" / "src" / "lib.rs")).startswith("#![feature(c_variadic)]")
+
+
+def test_emit_no_std(tmp_path):
+    host = subprocess.run(["rustc", "-vV"], capture_output=True, text=True, check=True).stdout
+    target = next(line.split(": ")[1] for line in host.splitlines() if line.startswith("host: "))
+    emitter = CrateEmitter({
+        "general": {"profile": "no_std"},
+        "no_std": {"target": target, "logger": "crate::logger"},
+    })
+    code = '''
+pub fn greet(names: &[String]) -> Vec<String> {
+    println!("greeting {} names", names.len());
+    names.iter().map(|name| format!("hello {}", name)).collect()
+}
+'''
+    crate_dir = tmp_path / "crate"
+    emitter.emit(str(crate_dir), "demo", [CrateUnit("demo", code, kind="lib")])
+
+    lib = read_file(str(crate_dir / "src" / "lib.rs"))
+    assert lib.startswith("#![no_std]\nextern crate alloc;\n")
+    assert "pub fn logger() -> SactorLogger" in lib
+    assert read_file(str(crate_dir / ".cargo" / "config.toml")) == f'[build]\ntarget = "{target}"\n'
+    subprocess.run(["cargo", "build", "--quiet"], cwd=crate_dir, check=True)
+
+    with pytest.raises(ValueError, match="emits a library"):
+        emitter.emit(str(tmp_path / "bin"), "demo", [CrateUnit("demo", "fn main() {}")])
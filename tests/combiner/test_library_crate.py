@@ -0,0 +1,67 @@
+import os
+import subprocess
+from types import SimpleNamespace
+
+from sactor import rust_ast_parser
+from sactor.combiner.library_crate import library_crate_name, write_library_crate
+from sactor.utils import read_file
+
+
+class _FakeFunction:
+    def __init__(self, name, signature):
+        self.name = name
+        self._signature = signature
+
+    def get_signature(self):
+        return self._signature
+
+
+def _fake_parser(raw_filename):
+    return SimpleNamespace(
+        raw_filename=raw_filename,
+        get_enums=lambda: [],
+        get_structs=lambda: [],
+        get_functions=lambda: [
+            _FakeFunction("add", "int add ( int a , int b )"),
+            _FakeFunction("main", "int main ( )"),
+        ],
+    )
+
+
+def test_library_crate_name():
+    assert library_crate_name("/src/libfoo.c") == "libfoo"
+    assert library_crate_name("my-lib.c") == "my_lib"
+    assert library_crate_name("7zip.c") == "lib_7zip"
+
+
+def test_write_library_crate(tmp_path):
+    rust_code = rust_ast_parser.expose_function_to_c(
+        "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n", "add")
+    crate_dir = tmp_path / "library"
+
+    name = write_library_crate(
+        str(crate_dir), _fake_parser("/src/mathlib.c"), rust_code, {"general": {}})
+
+    assert name == "mathlib"
+    manifest = read_file(str(crate_dir / "Cargo.toml"))
+    assert 'crate-type = ["cdylib", "staticlib"]' in manifest
+    assert "#[no_mangle]" in read_file(str(crate_dir / "src" / "lib.rs"))
+    header = read_file(str(crate_dir / "include" / "mathlib.h"))
+    assert header.startswith("#ifndef MATHLIB_H\n#define MATHLIB_H\n")
+    assert "int add ( int a , int b );" in header
+    assert "main" not in header
+
+    # a C program built against the header links against the static library
+    subprocess.run(
+        ["cargo", "build", "--quiet", "--manifest-path", str(crate_dir / "Cargo.toml")], check=True)
+    program = tmp_path / "program.c"
+    program.write_text(
+        '#include "mathlib.h"\nint main(void) { printf("%d\\n", add(2, 3)); return 0; }\n')
+    executable = tmp_path / "program"
+    subprocess.run(
+        ["cc", str(program), "-I", str(crate_dir / "include"),
+         str(crate_dir / "target" / "debug" / "libmathlib.a"), "-lpthread", "-ldl", "-lm",
+         "-o", str(executable)],
+        check=True)
+    assert subprocess.run([str(executable)], capture_output=True, text=True).stdout == "5\n"
+    assert os.path.exists(crate_dir / "target" / "debug" / "libmathlib.so")
@@ -99,4 +99,23 @@ def test_function_order(divider):
 def test_struct_order(divider):
     struct_order = divider.get_struct_order()
     struct_order_name = [[s.name for s in lst] for lst in struct_order]
-    assert struct_order_name == [['Course'], ['Student']]
\ No newline at end of file
+    assert struct_order_name == [['Course'], ['Student']]
+
+
+def test_get_function_globals(divider):
+    code = '''
+static mut COUNT: i32 = 0;
+const MAX: i32 = 3;
+pub unsafe fn bump() {
+    if COUNT < MAX {
+        COUNT += 1;
+    }
+}
+pub fn pure(x: i32) -> i32 {
+    x
+}
+'''
+    assert divider.get_function_globals(code) == {
+        "bump": ["COUNT", "MAX"],
+        "pure": [],
+    }
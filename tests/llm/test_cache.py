@@ -0,0 +1,54 @@
+from unittest.mock import MagicMock
+
+from sactor.llm import ResponseCache, llm_factory
+
+from tests.utils import config
+
+
+def make_llm(config, cache_dir, responses):
+    config["general"]["model"] = "gpt-4o"
+    config["litellm"] = {"router_settings": {}, "model_list": []}
+    config["cache"] = {"enabled": True, "path": str(cache_dir)}
+    llm = llm_factory(config)
+    llm.router.completion = MagicMock(side_effect=[
+        MagicMock(choices=[MagicMock(message=MagicMock(content=response))]) for response in responses
+    ])
+    return llm
+
+
+def test_cache_key():
+    key = ResponseCache.key("gpt-4o", "unidiomatic", "system", "prompt")
+    assert key == ResponseCache.key("gpt-4o", "unidiomatic", "system", "prompt")
+    assert key != ResponseCache.key("gpt-4o-mini", "unidiomatic", "system", "prompt")
+    assert key != ResponseCache.key("gpt-4o", "idiomatic", "system", "prompt")
+    assert key != ResponseCache.key("gpt-4o", "unidiomatic", "system", "prompt 2")
+
+
+def test_cache_replays_responses(config, tmp_path):
+    first = make_llm(config, tmp_path, ["first", "retry", "other"])
+    first.cost.stage = "unidiomatic"
+    assert [first.query("prompt"), first.query("prompt"), first.query("another")] == ["first", "retry", "other"]
+    assert first.cached_queries == 0
+
+    second = make_llm(config, tmp_path, ["fresh", "idiomatic"])
+    second.cost.stage = "unidiomatic"
+    assert second.query("prompt") == "first"
+    assert second.query("prompt") == "retry"
+    # asked more often than in the cached run
+    assert second.query("prompt") == "fresh"
+    assert second.router.completion.call_count == 1
+    assert second.cached_queries == 2
+    assert second.cache.hits == 2
+    assert len(second.cost.records) == 1
+
+    # other stages do not share responses
+    second.cost.stage = "idiomatic"
+    assert second.query("another") == "idiomatic"
+
+
+def test_cache_disabled(config, tmp_path):
+    llm = make_llm(config, tmp_path, ["first", "second"])
+    llm.cache.enabled = False
+    assert llm.query("prompt") == "first"
+    assert llm.query("prompt") == "second"
+    assert not any(tmp_path.iterdir())
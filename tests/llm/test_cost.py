@@ -0,0 +1,72 @@
+import json
+
+import pytest
+
+from sactor.llm import BudgetExceededError, CostTracker
+
+
+def make_tracker(**cost):
+    return CostTracker({
+        "cost": {"prices": {"big": {"input": 10.0, "output": 30.0}, "small": {"input": 1.0, "output": 2.0}}, **cost},
+        "litellm": {"model_list": []},
+    })
+
+
+def test_cost_report(tmp_path):
+    tracker = make_tracker()
+    tracker.unit = "main.c"
+    tracker.stage = "unidiomatic"
+    tracker.item = "add"
+    tracker.record("big", 1000, 100)
+    tracker.item = "sub"
+    tracker.record("big", 2000, 200)
+    tracker.stage = "idiomatic"
+    tracker.item = "add"
+    tracker.record("small", 1000, 1000)
+    tracker.unit = "util.c"
+    tracker.stage = None
+    tracker.item = None
+    tracker.record("unknown", 10, 10)
+
+    assert tracker.total_tokens == 5320
+    assert tracker.total_cost == pytest.approx(0.013 + 0.026 + 0.003)
+
+    report = tracker.write_report(str(tmp_path / "cost_report.json"))
+    assert json.loads((tmp_path / "cost_report.json").read_text()) == report
+    assert report["total"]["queries"] == 4
+    assert report["by_model"]["big"]["cost"] == pytest.approx(0.039)
+    assert report["by_model"]["unknown"]["cost"] == 0.0
+    assert report["by_stage"]["unidiomatic"]["input_tokens"] == 3000
+    assert report["by_stage"]["other"]["queries"] == 1
+    assert report["by_item"]["add"]["output_tokens"] == 1100
+    assert set(report["by_unit"]) == {"main.c", "util.c"}
+    assert report["unpriced_models"] == ["unknown"]
+
+    unit_report = tracker.report(unit="main.c")
+    assert unit_report["total"]["queries"] == 3
+    assert "by_unit" not in unit_report
+
+
+def test_budget_abort():
+    tracker = make_tracker(max_cost=0.01)
+    assert tracker.model_for("big") == "big"
+    tracker.record("big", 500, 0)
+    assert tracker.model_for("big") == "big"
+    tracker.record("big", 0, 200)
+    assert tracker.exceeded()
+    with pytest.raises(BudgetExceededError, match=r"\$0.0110 of \$0.01"):
+        tracker.model_for("big")
+
+
+def test_budget_downgrade():
+    tracker = make_tracker(max_tokens=100, on_budget_exceeded="downgrade", downgrade_model="small")
+    tracker.record("big", 60, 40)
+    assert tracker.model_for("big") == "small"
+    assert tracker.report()["budget"]["downgraded_to"] == "small"
+
+
+def test_budget_config():
+    with pytest.raises(ValueError, match="on_budget_exceeded"):
+        make_tracker(on_budget_exceeded="ignore")
+    with pytest.raises(ValueError, match="downgrade_model"):
+        make_tracker(on_budget_exceeded="downgrade")
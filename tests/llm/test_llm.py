@@ -91,4 +91,17 @@ def test_litellm_factory(config):
     
     llm = llm_factory(config)
     assert llm.default_model == "gpt-4o"
-    assert hasattr(llm, 'router')
\ No newline at end of file
+    assert hasattr(llm, 'router')
+
+
+def test_litellm_budget_downgrade(litellm_llm):
+    litellm_llm.cost.max_tokens = 1
+    litellm_llm.cost.on_budget_exceeded = "downgrade"
+    litellm_llm.cost.downgrade_model = "gpt-4o-mini"
+
+    litellm_llm.query("prompt")
+    litellm_llm.query("prompt")
+
+    models = [call.kwargs["model"] for call in litellm_llm.router.completion.call_args_list]
+    assert models == ["gpt-4o", "gpt-4o-mini"]
+    assert [r["model"] for r in litellm_llm.cost.records] == ["gpt-4o", "gpt-4o-mini"]
@@ -0,0 +1,76 @@
+from unittest.mock import MagicMock, patch
+
+import httpx
+import openai
+import pytest
+
+from sactor.llm import LocalLLM, llm_factory
+
+from tests.utils import config
+
+
+def make_response(content, finish_reason="stop"):
+    response = MagicMock()
+    response.choices = [MagicMock(message=MagicMock(content=content), finish_reason=finish_reason)]
+    return response
+
+
+def connection_error():
+    return openai.APIConnectionError(request=httpx.Request("POST", "http://localhost:11434/v1/chat/completions"))
+
+
+@pytest.fixture
+def local_llm(config):
+    config["local"] = {
+        "api_base": "http://localhost:11434/v1/",
+        "model": "qwen2.5-coder",
+        "context_window": 4096,
+        "max_output_tokens": 1024,
+        "max_retries": 2,
+        "retry_backoff_seconds": 0.5,
+    }
+    llm = llm_factory(config)
+    llm.client = MagicMock()
+    return llm
+
+
+def test_local_factory(local_llm):
+    assert isinstance(local_llm, LocalLLM)
+    assert local_llm.api_base == "http://localhost:11434/v1"
+    assert local_llm.default_model == "qwen2.5-coder"
+    system_tokens = len(local_llm.enc.encode(local_llm.system_msg))
+    assert local_llm.max_input_tokens == 4096 - 1024 - system_tokens
+
+
+def test_local_context_window_too_small(config):
+    config["local"] = {"api_base": "http://localhost:8080/v1", "context_window": 1024, "max_output_tokens": 1024}
+    with pytest.raises(ValueError, match="context_window"):
+        llm_factory(config)
+
+
+def test_local_query_truncates_and_retries(local_llm):
+    create = local_llm.client.chat.completions.create
+    create.side_effect = [connection_error(), connection_error(), make_response("fn main() {}")]
+
+    with patch("sactor.llm.local_llm.time.sleep") as sleep:
+        assert local_llm.query("word " * 10000) == "fn main() {}"
+
+    assert [call.args[0] for call in sleep.call_args_list] == [0.5, 1.0]
+    assert create.call_count == 3
+    kwargs = create.call_args.kwargs
+    assert kwargs["model"] == "qwen2.5-coder"
+    assert kwargs["max_tokens"] == 1024
+    prompt = kwargs["messages"][-1]["content"]
+    assert len(local_llm.enc.encode(prompt)) <= local_llm.max_input_tokens
+    # locally served models are free
+    assert local_llm.cost.total_cost == 0.0
+    assert local_llm.cost.unpriced_models == set()
+
+
+def test_local_query_gives_up(local_llm):
+    local_llm.client.chat.completions.create.side_effect = connection_error()
+
+    with patch("sactor.llm.local_llm.time.sleep"):
+        with pytest.raises(Exception, match="after 3 tries"):
+            local_llm.query("prompt")
+    assert local_llm.client.chat.completions.create.call_count == 3
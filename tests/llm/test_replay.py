@@ -0,0 +1,78 @@
+import json
+from unittest.mock import MagicMock
+
+import pytest
+
+from sactor.llm import ReplayBundle, ReplayDivergenceError, ReplayLLM, llm_factory
+
+from tests.utils import config
+
+
+@pytest.fixture
+def replay_config(config):
+    config["general"]["model"] = "gpt-4o"
+    config["litellm"] = {"router_settings": {}, "model_list": []}
+    return config
+
+
+def make_llm(config, replay, responses=()):
+    llm = llm_factory(config, replay=replay)
+    llm.router.completion = MagicMock(side_effect=[
+        MagicMock(choices=[MagicMock(message=MagicMock(content=response))]) for response in responses
+    ])
+    return llm
+
+
+def record_run(config, bundle_dir):
+    llm = make_llm(config, ReplayBundle.record(str(bundle_dir), config), ["fn add() {}", "fn sub() {}"])
+    llm.cost.stage = "unidiomatic"
+    llm.cost.item = "add"
+    llm.query("translate add")
+    llm.cost.item = "sub"
+    llm.query("translate sub")
+    return llm
+
+
+def test_replay_bundle_records_interactions(replay_config, tmp_path):
+    record_run(replay_config, tmp_path / "bundle")
+
+    manifest = json.loads((tmp_path / "bundle" / "manifest.json").read_text())
+    assert manifest["version"] == 1
+    assert "rustc" in manifest["toolchain"]
+    interactions = [json.loads(line) for line in (tmp_path / "bundle" / "interactions.jsonl").read_text().splitlines()]
+    assert [(i["index"], i["item"], i["prompt"], i["response"]) for i in interactions] == [
+        (0, "add", "translate add", "fn add() {}"),
+        (1, "sub", "translate sub", "fn sub() {}"),
+    ]
+
+
+def test_replay_answers_from_bundle(replay_config, tmp_path):
+    recorded = record_run(replay_config, tmp_path / "bundle")
+
+    llm = make_llm(replay_config, ReplayBundle.load(str(tmp_path / "bundle"), replay_config))
+    assert isinstance(llm, ReplayLLM)
+    llm.cost.stage = "unidiomatic"
+    llm.cost.item = "add"
+    assert llm.query("translate add") == "fn add() {}"
+    llm.cost.item = "sub"
+    assert llm.query("translate sub") == "fn sub() {}"
+    llm.router.completion.assert_not_called()
+    # the replayed run costs what the recorded one did
+    assert llm.costed_input_tokens == recorded.costed_input_tokens
+    assert llm.costed_output_tokens == recorded.costed_output_tokens
+
+    with pytest.raises(ReplayDivergenceError, match="more LLM queries"):
+        llm.query("translate mul")
+
+
+def test_replay_divergence(replay_config, tmp_path):
+    record_run(replay_config, tmp_path / "bundle")
+
+    llm = make_llm(replay_config, ReplayBundle.load(str(tmp_path / "bundle"), replay_config))
+    llm.cost.stage = "unidiomatic"
+    llm.cost.item = "add"
+    with pytest.raises(ReplayDivergenceError, match="query 0 diverges .* in prompt"):
+        llm.query("translate add, differently")
+
+    with pytest.raises(FileNotFoundError):
+        ReplayBundle.load(str(tmp_path / "missing"), replay_config)
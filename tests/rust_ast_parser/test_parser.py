@@ -1,3 +1,7 @@
+import json
+import os
+import subprocess
+
 import pytest
 
 from sactor import rust_ast_parser
@@ -28,6 +32,17 @@ def _traits_base(raw: str, normalized: str) -> dict:
         "pointer_base_raw": None,
         "pointer_element": None,
         "box_innermost": None,
+        "is_array": False,
+        "array_len": None,
+        "array_elem": None,
+        "is_tuple": False,
+        "tuple_elems": [],
+        "is_fn_pointer": False,
+        "fn_params": [],
+        "fn_return": None,
+        "is_result": False,
+        "result_ok": None,
+        "result_err": None,
     }
 
 
@@ -187,6 +202,97 @@ def test_parse_type_traits_normalizes_whitespace():
     assert traits["option_inner"]["normalized"] == "&mut [u8]"
 
 
+def test_parse_type_traits_array():
+    traits = rust_ast_parser.parse_type_traits("[libc::c_char; 16]")
+    assert traits == _traits(
+        "[libc :: c_char ; 16]",
+        "[libc::c_char ; 16]",
+        is_array=True,
+        array_len=16,
+        array_elem="libc :: c_char",
+    )
+    assert rust_ast_parser.parse_type_traits("[u8; N]")["array_len"] is None
+
+
+def test_parse_type_traits_tuple():
+    traits = rust_ast_parser.parse_type_traits("(i32, *const u8)")
+    assert traits["is_tuple"]
+    assert [elem["normalized"] for elem in traits["tuple_elems"]] == ["i32", "*const u8"]
+    assert traits["tuple_elems"][1]["is_pointer"]
+    assert not rust_ast_parser.parse_type_traits("()")["is_tuple"]
+
+
+def test_parse_type_traits_fn_pointer():
+    traits = rust_ast_parser.parse_type_traits(
+        'Option<unsafe extern "C" fn(*const libc::c_void, libc::c_int) -> libc::c_int>'
+    )
+    callback = traits["option_inner"]
+    assert callback["is_fn_pointer"]
+    assert [p["normalized"] for p in callback["fn_params"]] == ["*const libc::c_void", "libc::c_int"]
+    assert callback["fn_params"][0]["is_pointer"]
+    assert callback["fn_return"]["path_ident"] == "c_int"
+    assert rust_ast_parser.parse_type_traits("fn(i32)")["fn_return"] is None
+
+
+def test_parse_function_signature_result_return():
+    result = rust_ast_parser.parse_function_signature(
+        "fn parse(input: &str) -> Result<u32, ParseError>"
+    )
+    ret = result["return"]
+    assert ret["is_result"]
+    assert ret["result_ok"] == _traits("u32", "u32", path_ident="u32")
+    assert ret["result_err"] == _traits("ParseError", "ParseError", path_ident="ParseError")
+
+    ret = rust_ast_parser.parse_function_signature(
+        "fn lookup(key: &str) -> Option<Result<(), String>>"
+    )["return"]
+    assert ret["is_option"] and not ret["is_result"]
+    inner = ret["option_inner"]
+    assert inner["is_result"]
+    assert inner["result_ok"]["normalized"] == "()"
+    assert inner["result_err"]["is_string"]
+
+    ret = rust_ast_parser.parse_function_signature(
+        "fn read(path: &str) -> std::io::Result<Vec<u8>>"
+    )["return"]
+    assert ret["is_result"]
+    assert ret["result_ok"]["normalized"] == "Vec<u8>"
+    assert ret["result_err"] is None
+
+
+def test_parse_function_signature_generics():
+    result = rust_ast_parser.parse_function_signature(
+        "fn find<'a>(haystack: &'a str, needle: &str) -> Option<&'a str>"
+    )
+    assert result["generics"] == {
+        "lifetimes": ["'a"],
+        "type_params": [],
+        "const_params": [],
+        "where_predicates": [],
+        "monomorphic": True,
+    }
+    assert result["params"][0]["type"] == "& str"
+    assert result["params"][0]["traits"]["is_str"]
+    assert result["return"]["normalized"] == "Option<& str>"
+
+    generics = rust_ast_parser.parse_function_signature(
+        "fn pick<T: Clone + Default, const N: usize>(items: [T; N]) -> T where T: Copy"
+    )["generics"]
+    assert generics["type_params"] == [{"name": "T", "bounds": ["Clone", "Default"]}]
+    assert generics["const_params"] == [{"name": "N", "type": "usize"}]
+    assert generics["where_predicates"] == ["T: Copy"]
+    assert not generics["monomorphic"]
+
+    generics = rust_ast_parser.parse_function_signature(
+        "fn show(value: impl Display)"
+    )["generics"]
+    assert not generics["monomorphic"]
+
+    with pytest.raises(ValueError, match="Generic parameter 'T'"):
+        rust_ast_parser.generate_ffi_shim(
+            "fn f(p: *const i32)", "fn g<T>(p: &T)", "")
+
+
 def test_parse_function_signature_full_traits():
     signature = (
         "fn process(a: &mut i32, data: Option<&[u8]>, handle: *mut *const u8) "
@@ -270,6 +376,13 @@ def test_parse_function_signature_full_traits():
 
     expected = {
         "name": "process",
+        "generics": {
+            "lifetimes": [],
+            "type_params": [],
+            "const_params": [],
+            "where_predicates": [],
+            "monomorphic": True,
+        },
         "params": [
             {"name": "a", "type": "& mut i32", "traits": param_a_traits},
             {
@@ -304,6 +417,44 @@ def test_get_function_definition_errors_for_missing_function(code):
     with pytest.raises(ValueError):
         rust_ast_parser.get_function_definition(code, "missing")
 
+
+MACRO_CODE = """
+macro_rules! square {
+    ($x:expr) => { $x * $x };
+}
+macro_rules! square {
+    ($x:expr) => { $x * $x };
+}
+fn helper(x: i32) -> i32 { x }
+fn run(v: i32) -> i32 {
+    let a = square!(helper(v));
+    println!("{}", crate::util::clamp(a));
+    vec![format!("{}", a)];
+    a
+}
+"""
+
+
+def test_get_macro_definition():
+    definition = rust_ast_parser.get_macro_definition(MACRO_CODE, "square")
+    assert definition.startswith("macro_rules! square {")
+    assert definition.count("macro_rules!") == 1
+    with pytest.raises(ValueError):
+        rust_ast_parser.get_macro_definition(MACRO_CODE, "missing")
+
+
+def test_dedup_items_dedups_macros():
+    assert rust_ast_parser.dedup_items(MACRO_CODE).count("macro_rules! square") == 1
+
+
+def test_get_function_dependencies_reports_macros():
+    deps = rust_ast_parser.get_function_dependencies(MACRO_CODE, "run")
+    assert deps["functions"] == ["clamp", "helper"]
+    assert deps["macros"] == ["format", "println", "square", "vec"]
+    with pytest.raises(ValueError):
+        rust_ast_parser.get_function_dependencies(MACRO_CODE, "missing")
+
+
 def test_get_union_definition(code):
     union_definition = rust_ast_parser.get_union_definition(code, "Bar")
     assert (
@@ -575,3 +726,2035 @@ const MAX_SIZE: usize = 100;
         assert False, "Should have raised an exception"
     except Exception as e:
         assert "Item 'D' not found" in str(e)
+
+
+def test_list_string_literals():
+    code = '''
+static GREETING: &str = "hello";
+
+/// Documented helper.
+pub unsafe extern "C" fn report(count: i32) {
+    libc::printf(b"count=%d\\n\\0" as *const u8 as *const libc::c_char, count);
+    println!("done {}", count);
+}
+'''
+    literals = rust_ast_parser.list_string_literals(code)
+    assert [(lit["item"], lit["kind"], lit["value"]) for lit in literals] == [
+        ("GREETING", "str", "hello"),
+        ("report", "byte_str", "count=%d\n\0"),
+        ("report", "str", "done {}"),
+    ]
+    assert literals[1]["line"] == 6
+
+
+def test_list_function_string_literals():
+    code = '''
+fn a() { let _ = "a"; }
+impl Foo {
+    fn b(&self) { let _ = b"b\\0"; }
+}
+'''
+    literals = rust_ast_parser.list_function_string_literals(code, "a")
+    assert [(lit["item"], lit["value"]) for lit in literals] == [("a", "a")]
+
+    all_literals = rust_ast_parser.list_string_literals(code)
+    assert all_literals[1]["item"] == "Foo::b"
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.list_function_string_literals(code, "missing")
+
+
+def test_rewrite_printf_to_println():
+    code = '''
+unsafe fn report(id: libc::c_int, name: *const libc::c_char, gpa: libc::c_double) {
+    libc::printf(b"Student %d: %s\\n\\0" as *const u8 as *const libc::c_char, id, name);
+    printf(b"GPA %.2f, raw %f {}\\0" as *const u8 as *const libc::c_char, gpa, gpa);
+    printf(b"[%-5d|%5s|%c] 100%%\\n\\0".as_ptr() as *const libc::c_char, id, name, 65);
+}
+unsafe fn widths(id: libc::c_int, n: libc::c_long) {
+    printf(b"%hd %hhd %u %lu %x\\n\\0" as *const u8 as *const libc::c_char, id, id, id - 1, n, id);
+}
+'''
+    result = rust_ast_parser.rewrite_printf_to_println(code)
+    assert (
+        'println!("Student {}: {}", id, std::ffi::CStr::from_ptr(name).to_string_lossy());'
+        in result
+    )
+    assert 'print!("GPA {:.2}, raw {:.6} {{}}", gpa, gpa);' in result
+    assert "{:<5}|{:>5}|{}] 100%" in result
+    assert "(65 as u8 as char)" in result
+    # integers print as the type their length modifier and conversion read them as
+    assert (
+        'println!( "{} {} {} {} {:x}", id as i16, id as i8, (id - 1) as libc::c_uint, '
+        'n as libc::c_ulong, id as libc::c_uint );' in " ".join(result.split())
+    )
+
+    code = '''
+unsafe fn report(id: libc::c_int) {
+    libc::printf(b"Student %d\\n\\0" as *const u8 as *const libc::c_char, id);
+}
+unsafe fn written() -> libc::c_int {
+    let written = libc::printf(b"x\\n\\0" as *const u8 as *const libc::c_char);
+    written
+}
+'''
+    # C stdio and Rust buffer stdout separately, so a file still writing
+    # through C keeps all of its output there to stay in order
+    result = rust_ast_parser.rewrite_printf_to_println(code)
+    assert 'libc::printf(b"Student %d\\n\\0"' in result
+
+    # return value used, non-constant format, and unsupported specifiers are untouched
+    for call in [
+        'let written = libc::printf(b"x\\n\\0" as *const u8 as *const libc::c_char);',
+        "libc::printf(fmt, id);",
+        'libc::printf(b"%p\\n\\0" as *const u8 as *const libc::c_char, fmt);',
+        'libc::printf(b"%ls\\n\\0" as *const u8 as *const libc::c_char, fmt);',
+        # `char` prints non-ASCII bytes as two UTF-8 bytes
+        'libc::printf(b"%c\\n\\0" as *const u8 as *const libc::c_char, id);',
+        'libc::printf(b"%c\\n\\0" as *const u8 as *const libc::c_char, 200);',
+    ]:
+        code = f"unsafe fn f(fmt: *const libc::c_char, id: libc::c_int) {{ {call} }}"
+        assert call in rust_ast_parser.rewrite_printf_to_println(code)
+
+
+def test_rewrite_fprintf_to_eprintln():
+    code = '''
+unsafe fn fail(code: libc::c_int, msg: *const libc::c_char) {
+    fprintf(stderr, b"error %d: %s\\n\\0" as *const u8 as *const libc::c_char, code, msg);
+    libc::fputs(b"usage: prog {file}\\n\\0" as *const u8 as *const libc::c_char, stderr);
+    fputs(msg, stderr);
+    fprintf(stdout, b"ok\\n\\0" as *const u8 as *const libc::c_char);
+}
+'''
+    result = rust_ast_parser.rewrite_fprintf_to_eprintln(code)
+    assert (
+        'eprintln!("error {}: {}", code, std::ffi::CStr::from_ptr(msg).to_string_lossy());'
+        in result
+    )
+    assert 'eprintln!("usage: prog {{file}}");' in result
+    assert 'eprint!("{}", std::ffi::CStr::from_ptr(msg).to_string_lossy());' in result
+    assert "fprintf(stdout" in result
+
+
+def test_normalize_pointer_arithmetic():
+    code = '''
+unsafe fn f(p: *mut Student, i: libc::c_uint, n: usize, s: *const libc::c_char, k: libc::c_int, r: &libc::c_char) {
+    let a = *p.offset(i as isize);
+    let b = *p.offset(3 as isize);
+    let c = *p.offset(-(i as isize));
+    let d = *p.offset(n as usize as isize);
+    let e = s as *const libc::c_char as *mut libc::c_char;
+    let q: *mut Student = 0 as *mut Student;
+    let r0 = 0 as libc::c_int as *const libc::c_char;
+    let x = 0;
+    let t = &mut x as *mut i32 as *const i32;
+    let g = *p.offset(k as isize);
+    let h = *p.offset((k - 1) as isize);
+    let j = *p.offset((i + 1) as isize);
+    let m = r as *const libc::c_char as *mut libc::c_char;
+}
+'''
+    result = rust_ast_parser.normalize_pointer_arithmetic(code)
+    assert "let a = *p.add(i as usize);" in result
+    assert "let b = *p.add(3);" in result
+    assert "let c = *p.sub(i as usize);" in result
+    assert "let d = *p.add(n as usize);" in result
+    assert "let e = s as *mut libc::c_char;" in result
+    assert "let q: *mut Student = std::ptr::null_mut::<Student>();" in result
+    assert "let r0 = std::ptr::null::<libc::c_char>();" in result
+    assert "let t = &mut x as *mut i32 as *const i32;" in result
+    # a signed offset may be negative, which `.add` cannot express
+    assert "let g = *p.offset(k as isize);" in result
+    assert "let h = *p.offset((k - 1) as isize);" in result
+    assert "let j = *p.add((i + 1) as usize);" in result
+    # a reference cannot be cast to a pointer of another mutability
+    assert "let m = r as *const libc::c_char as *mut libc::c_char;" in result
+
+
+def test_normalize_pointer_arithmetic_shadowing():
+    code = '''
+unsafe fn g(p: *mut i32, i: libc::c_int, n: libc::c_uint) {
+    {
+        let i: usize = 1;
+        let a = *p.offset(i as isize);
+    }
+    let b = *p.offset(i as isize);
+    for n in -2..0 {
+        let c = *p.offset(n as isize);
+    }
+    let f = |n: i32| *p.offset(n as isize);
+    if let Some(n) = Some(-1) {
+        let e = *p.offset(n as isize);
+    }
+    let d = *p.offset(n as isize);
+}
+'''
+    result = rust_ast_parser.normalize_pointer_arithmetic(code)
+    assert "let a = *p.add(i" in result
+    # the inner `usize` binding does not outlive its block
+    assert "let b = *p.offset(i as isize);" in result
+    # `for`, closure and `if let` bindings shadow the unsigned parameter
+    assert "let c = *p.offset(n as isize);" in result
+    assert "let f = |n: i32| *p.offset(n as isize);" in result
+    assert "let e = *p.offset(n as isize);" in result
+    assert "let d = *p.add(n as usize);" in result
+
+
+def test_normalize_c_char_pointers():
+    code = '''
+extern "C" {
+    fn puts(s: *const i8) -> libc::c_int;
+    fn getenv(name: *const i8) -> *mut i8;
+}
+pub extern "C" fn run(argv: *mut *mut i8, bytes: *const i8) -> i32 {
+    let msg = b"hi\0".as_ptr() as *const i8;
+    let buf = argv as *mut *mut i8;
+    let raw: *const i8 = bytes;
+    let n = 1u8 as i8;
+    0
+}
+'''
+    result = rust_ast_parser.normalize_c_char_pointers(code)
+    assert "fn puts(s: *const libc::c_char) -> libc::c_int;" in result
+    assert "fn getenv(name: *const libc::c_char) -> *mut libc::c_char;" in result
+    assert "fn run(argv: *mut *mut libc::c_char, bytes: *const libc::c_char)" in result
+    assert "as_ptr() as *const libc::c_char;" in result
+    assert "let buf = argv as *mut *mut libc::c_char;" in result
+    # plain integer casts and local annotations are not FFI positions
+    assert "let raw: *const i8 = bytes;" in result
+    assert "let n = 1u8 as i8;" in result
+
+    cleaned = rust_ast_parser.unidiomatic_function_cleanup(code)
+    assert "as_ptr() as *const libc::c_char;" in cleaned
+
+
+def test_cleanup_c2rust_artifacts():
+    code = """
+#![allow(dead_code, mutable_transmutes, non_camel_case_types, unused_mut)]
+unsafe fn main_0(mut argc: libc::c_int, mut argv: *mut *mut libc::c_char) -> libc::c_int {
+    let mut p: *mut libc::c_int = 0 as *mut libc::c_int;
+    let mut q: *const libc::c_char = 0 as *const libc::c_char;
+    let mut n: libc::c_int = 5 as libc::c_int;
+    let m = (1 as libc::c_int).wrapping_add(n);
+    if argc < 2 as libc::c_int {
+        return 1 as libc::c_int;
+    }
+    return 0 as libc::c_int;
+}
+pub fn main() {
+    let mut args: Vec<*mut libc::c_char> = Vec::new();
+    args.push(::core::ptr::null_mut());
+    unsafe {
+        ::std::process::exit(
+            main_0(
+                (args.len() - 1) as libc::c_int,
+                args.as_mut_ptr() as *mut *mut libc::c_char,
+            ) as i32,
+        )
+    }
+}
+"""
+    result = rust_ast_parser.cleanup_c2rust_artifacts(code)
+    assert "#![allow" not in result
+    assert "main_0" not in result
+    assert "pub fn main() {" in result
+    assert "let mut argc: libc::c_int = (args.len() - 1) as libc::c_int;" in result
+    assert "let mut p: *mut libc::c_int = std::ptr::null_mut::<libc::c_int>();" in result
+    assert "let mut q: *const libc::c_char = std::ptr::null::<libc::c_char>();" in result
+    assert "let mut n: libc::c_int = 5;" in result
+    # a method receiver keeps its cast so the literal type stays fixed
+    assert "(1 as libc::c_int).wrapping_add(n)" in result
+    assert "::std::process::exit(1);" in result
+    assert "return;" in result
+
+
+def test_remove_unreachable_items():
+    code = """
+use std::fmt;
+extern "C" {
+    fn puts(s: *const libc::c_char) -> libc::c_int;
+    fn strlen(s: *const libc::c_char) -> libc::c_ulong;
+}
+pub struct Point { pub x: i32 }
+pub struct Unused { pub y: i32 }
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", helper(self.x)) }
+}
+impl Unused {
+    fn get(&self) -> i32 { self.y }
+}
+const _: () = assert!(core::mem::size_of::<Unused>() == 4);
+fn helper(x: i32) -> i32 { x }
+fn dead() -> i32 { helper(1) }
+#[no_mangle]
+pub extern "C" fn exported() { unsafe { puts(b"hi\\0".as_ptr() as *const libc::c_char); } }
+pub fn main() {
+    let p = Point { x: 1 };
+    println!("{}", p);
+}
+"""
+    result = rust_ast_parser.remove_unreachable_items(code)
+    assert "use std::fmt;" in result
+    assert "pub struct Point" in result
+    assert "impl fmt::Display for Point" in result
+    assert "fn helper(" in result
+    assert "fn puts(" in result
+    assert "fn exported()" in result
+    assert "Unused" not in result
+    assert "fn dead(" not in result
+    assert "strlen" not in result
+
+    only_dead = rust_ast_parser.remove_unreachable_items(code, ["dead"])
+    assert "fn dead(" in only_dead
+    assert "fn helper(" in only_dead
+    assert "fn main(" not in only_dead
+    assert "extern \"C\"" not in only_dead
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.remove_unreachable_items(code, ["missing"])
+
+
+def test_combine_as_modules():
+    files = {
+        "src/main.c": """
+#![allow(non_snake_case)]
+extern "C" {
+    fn list_push(list: *mut Node, value: i32);
+    fn printf(fmt: *const libc::c_char, ...) -> libc::c_int;
+}
+pub fn main() {
+    let mut head = Node { value: 0, next: std::ptr::null_mut() };
+    unsafe { list_push(&mut head, 1) };
+}
+""",
+        "src/util/list.c": """
+#[repr(C)]
+struct Node { value: i32, next: *mut Node }
+unsafe fn list_push(list: *mut Node, value: i32) {
+    let node = xmalloc(std::mem::size_of::<Node>()) as *mut Node;
+    (*node).value = value;
+    (*list).next = node;
+}
+""",
+        "src/util/mem.c": """
+fn xmalloc(size: usize) -> *mut libc::c_void { unsafe { libc::malloc(size) } }
+""",
+    }
+    result = rust_ast_parser.combine_as_modules(files)
+    assert sorted(result) == ["main.rs", "util/list.rs", "util/mem.rs", "util/mod.rs"]
+
+    main_rs = result["main.rs"]
+    assert "#![allow(non_snake_case)]" in main_rs
+    assert "pub mod util;" in main_rs
+    assert "use crate::util::list::Node;" in main_rs
+    assert "use crate::util::list::list_push;" in main_rs
+    assert "fn list_push(" not in main_rs
+    assert "fn printf(" in main_rs
+
+    assert "pub mod list;" in result["util/mod.rs"]
+    assert "pub mod mem;" in result["util/mod.rs"]
+    list_rs = result["util/list.rs"]
+    assert "use crate::util::mem::xmalloc;" in list_rs
+    assert "pub struct Node {" in list_rs
+    assert "pub value: i32," in list_rs
+    assert "pub unsafe fn list_push(" in list_rs
+    assert "pub fn xmalloc(" in result["util/mem.rs"]
+
+    library = rust_ast_parser.combine_as_modules({"a.c": "fn f() {}"})
+    assert sorted(library) == ["a.rs", "lib.rs"]
+    assert "pub mod a;" in library["lib.rs"]
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.combine_as_modules({"a.c": "fn main() {}", "b.c": "fn main() {}"})
+
+
+def test_move_item_to_module():
+    code_map = {
+        "main.rs": """
+pub mod util;
+use crate::util::list::{list_push, Node};
+fn main() {
+    let mut head = Node { value: 0 };
+    unsafe { list_push(&mut head, 1) };
+    let n: crate::util::list::Node = Node { value: 2 };
+}
+""",
+        "util/mod.rs": "pub mod list;\npub mod mem;\n",
+        "util/list.rs": """
+use crate::util::mem::xmalloc;
+pub struct Node { pub value: i32 }
+impl Node {
+    fn new(value: i32) -> Node { Node { value: limit(value) } }
+}
+fn limit(value: i32) -> i32 { value }
+pub unsafe fn list_push(list: *mut Node, value: i32) {
+    let node = xmalloc(4) as *mut Node;
+    (*list).value = value;
+}
+""",
+        "util/mem.rs": "pub fn xmalloc(size: usize) -> *mut u8 { std::ptr::null_mut() }\n",
+    }
+    result = rust_ast_parser.move_item_to_module(code_map, "Node", "util::list", "crate::util::mem")
+
+    main_rs = result["main.rs"]
+    assert "use crate::util::list::list_push;" in main_rs
+    assert "use crate::util::mem::Node;" in main_rs
+    assert "let n: crate::util::mem::Node" in main_rs
+
+    list_rs = result["util/list.rs"]
+    assert "struct Node" not in list_rs
+    assert "impl Node" not in list_rs
+    assert "use crate::util::mem::Node;" in list_rs
+    assert "pub fn limit(" in list_rs
+
+    mem_rs = result["util/mem.rs"]
+    assert "pub struct Node" in mem_rs
+    assert "impl Node" in mem_rs
+    assert "use crate::util::list::limit;" in mem_rs
+    assert "use crate::util::mem::Node" not in mem_rs
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.move_item_to_module(code_map, "Missing", "util::list", "util::mem")
+    with pytest.raises(ValueError):
+        rust_ast_parser.move_item_to_module(code_map, "Node", "util::list", "util::nope")
+
+
+def test_detect_symbol_collisions():
+    files = {
+        "src/main.c": """
+pub struct Point { pub x: i32 }
+fn helper(x: i32) -> i32 { x + 1 }
+pub fn main() { println!("{}", helper(1)); }
+""",
+        "src/util/list.c": """
+pub struct Point { pub x: i32 }
+static mut count: i32 = 0;
+fn helper(x: i32) -> i32 { x * 2 }
+pub fn twice(x: i32) -> i32 { unsafe { count += 1; } helper(x) }
+pub fn show(p: Point) { println!("{} {}", helper(p.x), p.helper); }
+""",
+        "src/util/map.c": """
+static mut count: i64 = 0;
+fn helper(x: i32) -> i32 { x * 2 }
+""",
+    }
+    report = rust_ast_parser.detect_symbol_collisions(files)
+    collisions = {(c["kind"], c["name"]): c for c in report["collisions"]}
+    # identical definitions (shared header types) are not collisions
+    assert sorted(collisions) == [("function", "helper"), ("static", "count")]
+    assert collisions[("function", "helper")]["files"] == [
+        "src/main.c", "src/util/list.c", "src/util/map.c"]
+    assert collisions[("function", "helper")]["renamed"] == {}
+    assert report["files"]["src/util/list.c"].count("helper(") == 3
+
+    renamed = rust_ast_parser.detect_symbol_collisions(files, rename=True)
+    helper = next(c for c in renamed["collisions"] if c["name"] == "helper")
+    assert helper["renamed"] == {
+        "src/util/list.c": "helper_util_list",
+        "src/util/map.c": "helper_util_map",
+    }
+    list_rs = renamed["files"]["src/util/list.c"]
+    assert "fn helper_util_list(x: i32)" in list_rs
+    assert "helper_util_list(x)" in list_rs
+    # calls inside macro arguments are renamed too, field accesses are not
+    assert 'println!("{} {}", helper_util_list(p.x), p.helper)' in list_rs
+    assert "static mut count: i32" in list_rs
+    assert "static mut count_util_map: i64" in renamed["files"]["src/util/map.c"]
+    assert "fn helper(x: i32)" in renamed["files"]["src/main.c"]
+
+
+def test_register_scalar_mapping(tmp_path):
+    code = """
+pub fn f(a: DWORD_T, b: libc::sactor_ulong_t, c: libc::c_int) -> DWORD_T {
+    a
+}
+"""
+    # unknown typedefs are left alone until registered
+    result = rust_ast_parser.replace_libc_numeric_types_to_rust_primitive_types(code)
+    assert "a: DWORD_T" in result
+
+    rust_ast_parser.register_scalar_mapping("DWORD_T", "u32")
+    scalar_map = tmp_path / "scalars.txt"
+    scalar_map.write_text("# extra\nlibc::sactor_ulong_t = u64\n")
+    assert rust_ast_parser.load_scalar_map(str(scalar_map)) == 1
+
+    result = rust_ast_parser.replace_libc_numeric_types_to_rust_primitive_types(code)
+    assert "pub fn f(a: u32, b: u64, c: i32) -> u32" in result
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.register_scalar_mapping("HANDLE_T", "Box<u8>")
+    scalar_map.write_text("missing_equals u8\n")
+    with pytest.raises(ValueError):
+        rust_ast_parser.load_scalar_map(str(scalar_map))
+
+
+def test_resolve_type_aliases():
+    code = """
+pub type __uint32_t = libc::c_uint;
+pub type uint32_t = __uint32_t;
+pub type id_t = uint32_t;
+pub type Student = student;
+pub type Handler = Option<unsafe extern "C" fn(id_t) -> id_t>;
+#[repr(C)]
+pub struct student {
+    pub id: id_t,
+    pub next: *mut Student,
+}
+pub unsafe fn lookup(s: *const Student, h: Handler) -> id_t {
+    let id: id_t = (*s).id;
+    id
+}
+"""
+    aliases = rust_ast_parser.get_type_aliases(code)
+    assert aliases["id_t"] == "uint32_t"
+    assert aliases["__uint32_t"] == "libc :: c_uint"
+
+    result = rust_ast_parser.resolve_type_aliases(code)
+    # libc/stdint names stop the chain and keep their (resolved) item
+    assert "pub type uint32_t = libc::c_uint;" in result
+    assert "__uint32_t" not in result
+    assert "id_t" not in result
+    assert "pub id: uint32_t," in result
+    assert "pub next: *mut student," in result
+    assert "pub unsafe fn lookup(\n    s: *const student,\n    h: Option<unsafe extern \"C\" fn(uint32_t) -> uint32_t>," in result
+    assert "let id: uint32_t = (*s).id;" in result
+
+    result = rust_ast_parser.resolve_type_aliases(code, keep_libc_aliases=False)
+    assert "type " not in result
+    assert "pub id: libc::c_uint," in result
+
+
+def test_extract_and_strip_extern_declarations():
+    code = """
+extern "C" {
+    pub type _IO_wide_data;
+    fn printf(_: *const libc::c_char, _: ...) -> libc::c_int;
+    fn helper(x: libc::c_int);
+    static mut stdout: *mut FILE;
+}
+pub unsafe extern "C" fn helper(x: libc::c_int) {}
+"""
+    decls = {decl["name"]: decl for decl in rust_ast_parser.extract_extern_declarations(code)}
+    assert set(decls) == {"_IO_wide_data", "printf", "helper", "stdout"}
+    assert decls["_IO_wide_data"]["kind"] == "type"
+    printf = decls["printf"]
+    assert printf["kind"] == "fn" and printf["abi"] == "C"
+    assert printf["variadic"] is True
+    assert printf["return_type"] == "libc :: c_int"
+    assert printf["params"] == [("_", "* const libc :: c_char")]
+    assert decls["helper"]["return_type"] is None
+    assert decls["stdout"]["kind"] == "static"
+    assert decls["stdout"]["mutable"] is True
+    assert decls["stdout"]["type"] == "* mut FILE"
+
+    stripped = rust_ast_parser.strip_extern_blocks(code)
+    assert "extern \"C\" {" not in stripped
+    assert "pub unsafe extern \"C\" fn helper" in stripped
+
+    partial = rust_ast_parser.strip_extern_blocks(code, ["helper"])
+    assert "fn printf(" in partial
+    assert "fn helper(x: libc::c_int);" not in partial
+    assert rust_ast_parser.strip_extern_blocks(partial, ["printf", "stdout", "_IO_wide_data"]).count("extern \"C\" {") == 0
+
+
+def test_shrink_unsafe_blocks():
+    code = '''
+extern "C" {
+    fn strlen(s: *const libc::c_char) -> libc::c_ulong;
+}
+static mut COUNTER: i32 = 0;
+pub fn sum(arr: *const i32, n: i32, out: &mut i32) -> i32 {
+    unsafe {
+        let mut total = 0;
+        let mut i = 0;
+        while i < n {
+            total += *arr.add(i as usize);
+            i += 1;
+        }
+        *out = total;
+        COUNTER += 1;
+        let len = strlen(b"x\\0".as_ptr() as *const libc::c_char);
+        total
+    }
+}
+'''
+    result = rust_ast_parser.shrink_unsafe_blocks(code)
+    expected = '''pub fn sum(arr: *const i32, n: i32, out: &mut i32) -> i32 {
+    let mut total = 0;
+    let mut i = 0;
+    while i < n {
+        unsafe {
+            total += *arr.add(i as usize);
+        }
+        i += 1;
+    }
+    *out = total;
+    unsafe {
+        COUNTER += 1;
+    }
+    let len = unsafe { strlen(b"x\\0".as_ptr() as *const libc::c_char) };
+    total
+}
+'''
+    assert result.endswith(expected)
+    assert rust_ast_parser.count_unsafe_tokens(result)[1] < rust_ast_parser.count_unsafe_tokens(code)[1]
+
+    # a let-bound reference derefs safely, a let-bound pointer does not
+    code = '''
+pub fn bump(p: *mut i32, out: &mut i32) {
+    unsafe {
+        let r = &mut *out;
+        *r += 1;
+        let q = p;
+        *q += 1;
+    }
+}
+'''
+    expected = '''pub fn bump(p: *mut i32, out: &mut i32) {
+    let r = &mut *out;
+    *r += 1;
+    let q = p;
+    unsafe {
+        *q += 1;
+    }
+}
+'''
+    assert rust_ast_parser.shrink_unsafe_blocks(code).endswith(expected)
+
+
+def test_shrink_unsafe_blocks_keeps_unsafe_calls():
+    code = '''
+pub struct Handlers {
+    pub fp: unsafe extern "C" fn(i32) -> i32,
+}
+struct S;
+impl S {
+    unsafe fn assoc() -> i32 {
+        0
+    }
+}
+pub fn run(cb: Option<unsafe extern "C" fn(i32) -> i32>, s: &Handlers) -> i32 {
+    unsafe {
+        let a = cb.expect("non-null function pointer")(1);
+        let b = (s.fp)(2);
+        let c = S::assoc();
+        a + b + c
+    }
+}
+'''
+    expected = '''pub fn run(cb: Option<unsafe extern "C" fn(i32) -> i32>, s: &Handlers) -> i32 {
+    let a = unsafe { cb.expect("non-null function pointer")(1) };
+    let b = unsafe { (s.fp)(2) };
+    let c = unsafe { S::assoc() };
+    a + b + c
+}
+'''
+    assert rust_ast_parser.shrink_unsafe_blocks(code).endswith(expected)
+
+
+def test_find_unnecessary_unsafe():
+    code = '''
+pub unsafe fn len(s: &[u8]) -> usize {
+    s.len()
+}
+
+pub unsafe fn first(p: *const i32) -> i32 {
+    *p
+}
+
+pub fn total(values: &[i32], out: &mut i32) {
+    unsafe {
+        *out = values.iter().sum();
+    }
+    unsafe {
+        libc::puts(b"done\\0".as_ptr() as *const libc::c_char);
+    }
+}
+
+struct Counter;
+impl Counter {
+    unsafe fn reset(&mut self) {}
+}
+'''
+    found = rust_ast_parser.find_unnecessary_unsafe(code)
+    assert [(entry["kind"], entry["function"]) for entry in found] == [
+        ("unsafe_fn", "len"),
+        ("unsafe_block", "total"),
+        ("unsafe_fn", "Counter::reset"),
+    ]
+    assert found[1]["line"] == 11
+
+    # calls through fn pointers and unsafe associated fns need their blocks
+    code = '''
+struct S;
+impl S {
+    unsafe fn a() -> i32 {
+        libc::rand()
+    }
+}
+pub fn call(cb: Option<unsafe extern "C" fn(i32) -> i32>) -> i32 {
+    unsafe { cb.unwrap()(1) }
+}
+pub fn assoc() -> i32 {
+    unsafe { S::a() }
+}
+'''
+    assert rust_ast_parser.find_unnecessary_unsafe(code) == []
+
+
+def test_rewrite_static_mut_globals():
+    code = '''
+static mut COUNTER: libc::c_int = 0;
+static mut LIMIT: i32 = 10;
+static mut HEAD: *mut i32 = 0 as *mut i32;
+static mut TABLE: [i32; 4] = [0; 4];
+pub unsafe fn bump(x: i32) -> i32 {
+    COUNTER += 1;
+    TABLE[0] = TABLE[1] + x;
+    if COUNTER > LIMIT {
+        COUNTER = 0;
+    }
+    return COUNTER;
+}
+'''
+    result = rust_ast_parser.rewrite_static_mut_globals(code)
+    assert "static COUNTER: std::sync::atomic::AtomicI32" in result
+    assert "COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst)" in result
+    assert "COUNTER.store(0, std::sync::atomic::Ordering::SeqCst)" in result
+    assert "return COUNTER.load(std::sync::atomic::Ordering::SeqCst)" in result
+    # never written: only the mutability is dropped
+    assert "static LIMIT: i32 = 10;" in result
+    # raw pointers are not Sync and stay as they are
+    assert "static mut HEAD: *mut i32" in result
+    assert "static TABLE: std::sync::Mutex<[i32; 4]>" in result
+    assert "let mut table_guard = TABLE.lock().unwrap();" in result
+    assert "table_guard[0] = table_guard[1] + x;" in result
+
+
+def test_rewrite_static_mut_globals_runs(tmp_path):
+    code = '''
+#[derive(Clone, Copy)]
+pub struct P { pub x: i32, pub y: i32 }
+static mut S: P = P { x: 1, y: 2 };
+static mut LOG: [i32; 4] = [0; 4];
+static mut BUF: [i32; 4] = [0; 4];
+unsafe fn get_x() -> i32 { S.x }
+unsafe fn bump() -> i32 { S.x += 1; S.x }
+unsafe fn buffer() -> *mut i32 { BUF.as_mut_ptr() }
+fn main() {
+    unsafe {
+        let v = S.y + get_x();
+        S.y = S.y + bump();
+        LOG[get_x() as usize - 1] = S.y;
+        *buffer() = v;
+        println!("{} {} {} {}", v, S.y, LOG[1], BUF[0]);
+    }
+}
+'''
+    result = rust_ast_parser.rewrite_static_mut_globals(code)
+    assert "static S: std::sync::Mutex<P>" in result
+    # a raw pointer into the static would outlive any guard
+    assert "static mut BUF: [i32; 4]" in result
+
+    (tmp_path / "main.rs").write_text(result)
+    subprocess.run(["rustc", "--edition", "2021", "main.rs", "-o", "main"],
+                   cwd=tmp_path, check=True, capture_output=True)
+    # `get_x` and `bump` lock `S` while the calling statement reads it
+    run = subprocess.run([os.path.join(tmp_path, "main")], capture_output=True, text=True, check=True, timeout=10)
+    assert run.stdout == "3 4 4 3\n"
+
+
+def test_rewrite_static_mut_globals_c_long(tmp_path):
+    code = '''
+mod libc {
+    pub type c_int = i32;
+    pub type c_long = i64;
+    pub type c_ulong = u64;
+}
+static mut TOTAL: libc::c_long = 0;
+static mut SEEN: libc::c_ulong = 0;
+unsafe fn add(c: libc::c_int) {
+    TOTAL += c as libc::c_long;
+    SEEN = SEEN + 1 as libc::c_ulong;
+}
+fn main() {
+    unsafe {
+        add(3);
+        add(-5);
+        println!("{} {}", TOTAL, SEEN);
+    }
+}
+'''
+    result = rust_ast_parser.rewrite_static_mut_globals(code)
+    # `libc` defines these as 64-bit, whatever width the scalar map gives them
+    assert "static TOTAL: std::sync::atomic::AtomicI64" in result
+    assert "static SEEN: std::sync::atomic::AtomicU64" in result
+
+    (tmp_path / "main.rs").write_text(result)
+    subprocess.run(["rustc", "--edition", "2021", "main.rs", "-o", "main"],
+                   cwd=tmp_path, check=True, capture_output=True)
+    run = subprocess.run([os.path.join(tmp_path, "main")], capture_output=True, text=True, check=True, timeout=10)
+    assert run.stdout == "-2 2\n"
+
+
+def test_analyze_parameter_usage():
+    code = '''
+pub unsafe extern "C" fn divide(a: libc::c_int, b: libc::c_int, quot: *mut libc::c_int, rem: *mut libc::c_int, count: *mut libc::c_int, unused: i32, name: *const libc::c_char) -> libc::c_int {
+    if b == 0 {
+        return -1;
+    }
+    *quot = a / b;
+    *rem = a % b;
+    *count += 1;
+    if !name.is_null() {
+        puts(name);
+    }
+    return 0;
+}
+pub fn maybe_set(flag: bool, out: &mut i32) -> i32 {
+    if flag {
+        *out = 1;
+    }
+    *out
+}
+'''
+    usage = {
+        p["name"]: p["usage"]
+        for p in rust_ast_parser.analyze_parameter_usage(code, "divide")
+    }
+    assert usage == {
+        "a": "read",
+        "b": "read",
+        "quot": "write",
+        "rem": "write",
+        "count": "read_write",
+        "unused": "unused",
+        "name": "read",
+    }
+    # a conditional write does not hide the caller's value from the later read
+    usage = rust_ast_parser.analyze_parameter_usage(code, "maybe_set")
+    assert usage[1] == {"name": "out", "type": "& mut i32", "usage": "read_write"}
+    with pytest.raises(ValueError):
+        rust_ast_parser.analyze_parameter_usage(code, "missing")
+
+
+def test_suggest_immutable_params():
+    code = '''
+pub struct Point { pub x: i32, pub y: i32 }
+pub unsafe fn length(p: *mut Point, scratch: *mut i32, out: &mut i32, seen: &mut Vec<i32>, log: &mut Vec<i32>, n: i32) {
+    let d = (*p).x * (*p).x + (*p).y * (*p).y;
+    *scratch = d;
+    *out = d + n;
+    if seen.len() > 3 {
+        return;
+    }
+    log.push(d);
+}
+pub fn forward(p: *mut Point, q: &mut Point) -> i32 {
+    unsafe { reset(p) };
+    q.x
+}
+'''
+    suggestions = rust_ast_parser.suggest_immutable_params(code, "length")
+    assert suggestions == [
+        {"name": "p", "type": "* mut Point", "suggested_type": "* const Point"},
+        {"name": "seen", "type": "& mut Vec < i32 >", "suggested_type": "& Vec < i32 >"},
+    ]
+    # a pointer handed to unknown code may be written there
+    suggestions = rust_ast_parser.suggest_immutable_params(code, "forward")
+    assert [s["name"] for s in suggestions] == ["q"]
+    with pytest.raises(ValueError):
+        rust_ast_parser.suggest_immutable_params(code, "missing")
+
+
+def test_analyze_pointer_ownership():
+    code = '''
+pub struct Node { pub value: i32, pub next: *mut Node }
+pub struct List { pub head: *mut Node, pub last: *mut Node }
+pub unsafe fn push(list: *mut List, value: i32, scratch: *const i32) -> *mut Node {
+    let node: *mut Node = malloc(::core::mem::size_of::<Node>() as libc::c_ulong) as *mut Node;
+    if node.is_null() {
+        return 0 as *mut Node;
+    }
+    (*node).value = value + *scratch;
+    (*node).next = (*list).head;
+    (*list).head = node;
+    (*list).last = node;
+    node
+}
+pub unsafe fn sum(values: *const i32, n: usize, buf: *mut libc::c_char) -> i32 {
+    let mut total = 0;
+    for i in 0..n {
+        total += *values.add(i);
+    }
+    *buf = 0;
+    free(buf as *mut libc::c_void);
+    total
+}
+pub unsafe fn link(a: *mut Node, b: *mut Node) {
+    (*a).next = b;
+    swap(a, a);
+    (*b).value = 1;
+}
+pub unsafe fn prepend(list: *mut List, node: *mut Node) {
+    (*node).next = (*list).head;
+    (*list).head = node;
+}
+'''
+    pointers = rust_ast_parser.analyze_pointer_ownership(code, "push")
+    assert [(p["name"], p["kind"], p["ownership"]) for p in pointers] == [
+        ("list", "param", "borrowed"), ("scratch", "param", "borrowed"), ("node", "local", "aliased"),
+    ]
+    list_ptr, scratch, node = pointers
+    assert list_ptr["mutable"] and list_ptr["suggested_type"] == "&mut List"
+    assert not scratch["mutable"] and scratch["suggested_type"] == "&i32"
+    assert node["nullable"] and node["suggested_type"] == "Option<Rc<RefCell<Node>>>"
+    assert node["evidence"] == [
+        "allocated with `malloc`",
+        "stored in `(*list).head` and used afterwards",
+        "stored in `(*list).last` and used afterwards",
+        "returned",
+    ]
+
+    values, buf = rust_ast_parser.analyze_pointer_ownership(code, "sum")
+    assert values["ownership"] == "borrowed" and values["array"]
+    assert values["suggested_type"] == "&[i32]"
+    assert buf["ownership"] == "owning" and buf["evidence"] == ["released with `free`"]
+    assert buf["suggested_type"] == "String"
+
+    a, b = rust_ast_parser.analyze_pointer_ownership(code, "link")
+    assert a["ownership"] == "aliased" and a["evidence"] == ["passed twice to `swap`"]
+    assert b["evidence"] == ["stored in `(*a).next` and used afterwards"]
+
+    _, node = rust_ast_parser.analyze_pointer_ownership(code, "prepend")
+    assert node["ownership"] == "owning" and node["evidence"] == ["moved into `(*list).head`"]
+    assert node["suggested_type"] == "Box<Node>"
+    with pytest.raises(ValueError):
+        rust_ast_parser.analyze_pointer_ownership(code, "missing")
+
+
+def test_analyze_struct_field_usage():
+    code = '''
+#[repr(C)]
+pub struct Student { pub name: *mut libc::c_char, pub age: libc::c_int, pub grades: *mut libc::c_float, pub numGrades: libc::c_int, pub capacity: usize }
+pub unsafe fn student_new(name: *mut libc::c_char, capacity: usize) -> *mut Student {
+    let s = malloc(::core::mem::size_of::<Student>() as libc::c_ulong) as *mut Student;
+    (*s).name = name;
+    (*s).age = 0;
+    (*s).numGrades = 0;
+    (*s).capacity = capacity;
+    (*s).grades = malloc(capacity.wrapping_mul(4) as libc::c_ulong) as *mut libc::c_float;
+    s
+}
+pub unsafe fn add_grade(student: *mut Student, grade: libc::c_float) {
+    if (*student).numGrades as usize >= (*student).capacity {
+        (*student).capacity *= 2;
+        (*student).grades = realloc((*student).grades as *mut libc::c_void, (*student).capacity.wrapping_mul(4) as libc::c_ulong) as *mut libc::c_float;
+    }
+    *(*student).grades.offset((*student).numGrades as isize) = grade;
+    (*student).numGrades += 1;
+}
+pub unsafe fn average(student: *const Student) -> libc::c_float {
+    let mut sum: libc::c_float = 0.0;
+    let mut i = 0;
+    while i < (*student).numGrades {
+        sum += *(*student).grades.offset(i as isize);
+        i += 1;
+    }
+    printf(b"%d\\\\0" as *const u8 as *const libc::c_char, (*student).capacity);
+    sum
+}
+'''
+    usage = rust_ast_parser.analyze_struct_field_usage(code, "Student")
+    assert usage["fields"][2]["type"] == "*mut libc::c_float"
+    fields = {f["name"]: (f["read_by"], f["written_by"]) for f in usage["fields"]}
+    assert fields["name"] == ([], ["student_new"])
+    assert fields["age"] == ([], ["student_new"])
+    assert fields["grades"] == (["add_grade", "average"], ["student_new", "add_grade"])
+    assert fields["numGrades"] == (["add_grade", "average"], ["student_new", "add_grade"])
+    # `capacity` is also printed, so it is not only a bound
+    assert usage["length_fields"] == [{
+        "field": "numGrades",
+        "pointer": "grades",
+        "element_type": "f32",
+        "evidence": ["indexes `grades` in `add_grade`", "bounds a loop over `grades` in `average`"],
+    }]
+    with pytest.raises(ValueError):
+        rust_ast_parser.analyze_struct_field_usage(code, "Missing")
+
+
+def test_find_pure_functions():
+    code = '''
+use std::cell::Cell;
+extern "C" {
+    fn abs(x: libc::c_int) -> libc::c_int;
+}
+static mut COUNTER: i32 = 0;
+pub fn square(x: i32) -> i32 { x * x }
+pub fn sum_squares(a: i32, b: i32) -> Option<i32> { Some(square(a) + square(b)) }
+pub fn fact(n: u64) -> u64 { if n == 0 { 1 } else { n * fact(n - 1) } }
+pub fn largest(v: &[i32]) -> i32 { let mut m = 0; for x in v.iter() { m = m.max(*x); } m }
+pub const fn already(x: i32) -> i32 { x + 1 }
+pub fn shout(x: i32) { println!("{}", square(x)); }
+pub fn bump() -> i32 { unsafe { COUNTER += 1; COUNTER } }
+pub fn via_ffi(x: i32) -> i32 { unsafe { abs(x) } }
+pub fn set(out: &mut i32) { *out = 1; }
+pub fn cached(c: &Cell<i32>) -> i32 { c.get() }
+pub fn uses_shout(x: i32) -> i32 { shout(x); x }
+'''
+    result = {f["name"]: f for f in rust_ast_parser.find_pure_functions(code)}
+    pure = sorted(name for name, f in result.items() if f["pure"])
+    assert pure == ["already", "fact", "largest", "square", "sum_squares"]
+    const = sorted(name for name, f in result.items() if f["const_candidate"])
+    assert const == ["already", "fact", "square", "sum_squares"]
+    assert result["already"]["is_const"] is True
+    assert result["shout"]["reasons"] == ["io"]
+    assert "global" in result["bump"]["reasons"]
+    assert "ffi" in result["via_ffi"]["reasons"]
+    assert result["set"]["reasons"] == ["mutable_param"]
+    assert result["cached"]["reasons"] == ["interior_mutability"]
+    assert result["uses_shout"]["reasons"] == ["impure_call"]
+
+
+def test_code_metrics():
+    code = '''
+pub fn simple(x: i32) -> i32 { x + 1 }
+pub fn branchy(v: &[i32], flag: bool) -> Option<i32> {
+    let mut total = 0;
+    for x in v.iter() {
+        if *x > 0 && flag {
+            total += x;
+        } else if *x < -10 || !flag {
+            while total > 100 {
+                total -= 1;
+            }
+        }
+    }
+    let parsed: i32 = "1".parse().ok()?;
+    match total {
+        0 => None,
+        n if n > 5 => Some(n + parsed),
+        n => Some(n),
+    }
+}
+struct Counter { n: i32 }
+impl Counter {
+    fn bump(&mut self) { self.n += 1; }
+}
+'''
+    metrics = {m["name"]: m for m in rust_ast_parser.code_metrics(code)}
+    assert sorted(metrics) == ["Counter::bump", "branchy", "simple"]
+    assert metrics["simple"]["cyclomatic_complexity"] == 1
+    assert metrics["simple"]["statements"] == 1
+    assert metrics["simple"]["max_nesting"] == 0
+    # for, if, &&, else if, ||, while, ?, two extra arms and one guard
+    assert metrics["branchy"]["cyclomatic_complexity"] == 11
+    assert metrics["branchy"]["statements"] == 8
+    assert metrics["branchy"]["max_nesting"] == 3
+    assert metrics["branchy"]["tokens"] > metrics["simple"]["tokens"]
+    assert metrics["Counter::bump"]["statements"] == 1
+
+
+def test_find_leak_patterns():
+    code = '''
+use std::ffi::CString;
+use std::mem::ManuallyDrop;
+pub fn name() -> &'static str {
+    Box::leak(String::from("x").into_boxed_str())
+}
+pub fn keep(v: Vec<u8>) {
+    std::mem::forget(v);
+}
+pub fn to_c(s: &str) -> *mut libc::c_char {
+    CString::new(s).unwrap().into_raw()
+}
+pub fn node() -> *mut i32 {
+    Box::into_raw(Box::new(1))
+}
+pub fn wrap(v: Vec<u8>) -> ManuallyDrop<Vec<u8>> {
+    ManuallyDrop::new(v)
+}
+pub fn release(p: *mut libc::c_char) {
+    unsafe { drop(CString::from_raw(p)) };
+}
+'''
+    found = [(f["kind"], f["function"]) for f in rust_ast_parser.find_leak_patterns(code)]
+    assert ("box_leak", "name") in found
+    assert ("mem_forget", "keep") in found
+    assert ("into_raw", "node") in found
+    assert ("manually_drop", "wrap") in found
+    # CString::from_raw releases what `into_raw` handed out
+    assert ("into_raw", "to_c") not in found
+    lines = {f["kind"]: f["line"] for f in rust_ast_parser.find_leak_patterns(code)}
+    assert lines["box_leak"] == 5
+    assert lines["mem_forget"] == 8
+
+
+def test_find_ub_suspects():
+    code = '''
+use std::mem::{self, MaybeUninit};
+static mut COUNT: i32 = 0;
+pub unsafe fn reinterpret(x: u32, y: u64) -> f64 {
+    let a: f32 = mem::transmute(x);
+    let b = mem::transmute::<u64, f64>(y);
+    let c = mem::transmute::<u32, u64>(x as u32);
+    b + a as f64 + c as f64
+}
+pub unsafe fn first(p: *const i32, q: *const i32) -> i32 {
+    if q.is_null() {
+        return 0;
+    }
+    *p + *q
+}
+pub unsafe fn alloc_one() -> i32 {
+    let buf = libc::malloc(4) as *mut i32;
+    *buf = 1;
+    *buf
+}
+pub fn bump() {
+    unsafe {
+        COUNT += 1;
+    }
+    // SAFETY: single-threaded test harness
+    unsafe {
+        COUNT += 1;
+    }
+}
+pub unsafe fn uninit() -> (i32, i32) {
+    let a: i32 = MaybeUninit::uninit().assume_init();
+    let mut b = MaybeUninit::<i32>::uninit();
+    b.write(1);
+    (a, b.assume_init())
+}
+'''
+    found = rust_ast_parser.find_ub_suspects(code)
+    kinds = [(f["kind"], f["function"]) for f in found]
+    assert kinds.count(("transmute_size_mismatch", "reinterpret")) == 1
+    transmute = next(f for f in found if f["kind"] == "transmute_size_mismatch")
+    assert transmute["detail"] == "u32 (4 bytes) -> u64 (8 bytes)"
+    assert transmute["line"] == 7
+    null_derefs = [f["detail"] for f in found if f["kind"] == "unchecked_null_deref"]
+    assert null_derefs == [
+        "`p` is dereferenced without a null check",
+        "`buf` is dereferenced without a null check",
+    ]
+    statics = [f for f in found if f["kind"] == "static_mut_access"]
+    assert [f["line"] for f in statics] == [23]
+    assert kinds.count(("uninit_assume_init", "uninit")) == 1
+
+
+def test_global_usage():
+    code = '''
+use std::sync::atomic::{AtomicUsize, Ordering};
+const LIMIT: i32 = 10;
+static mut TOTAL: i32 = 0;
+static mut TABLE: [i32; 4] = [0; 4];
+static HITS: AtomicUsize = AtomicUsize::new(0);
+extern "C" {
+    static mut stdout: *mut libc::FILE;
+}
+pub unsafe fn add(x: i32) {
+    if x < LIMIT {
+        TOTAL += x;
+    }
+    TABLE[0] = x;
+    HITS.fetch_add(1, Ordering::SeqCst);
+}
+pub unsafe fn report() -> i32 {
+    libc::fflush(stdout);
+    TOTAL + TABLE[1] + HITS.load(Ordering::SeqCst) as i32
+}
+pub fn shadowed(TOTAL: i32) -> i32 {
+    TOTAL
+}
+pub unsafe fn reset() {
+    let p = &mut TOTAL;
+    *p = 0;
+}
+'''
+    usage = rust_ast_parser.global_usage(code)
+    assert usage["add"] == {"reads": ["HITS", "LIMIT", "TOTAL"], "writes": ["HITS", "TABLE", "TOTAL"]}
+    assert usage["report"] == {"reads": ["HITS", "TABLE", "TOTAL", "stdout"], "writes": []}
+    assert usage["shadowed"] == {"reads": [], "writes": []}
+    assert usage["reset"]["writes"] == ["TOTAL"]
+
+
+def test_generate_ffi_shim():
+    spec = json.dumps({"fields": [
+        {"u_field": {"name": "arr", "type": "*const i32",
+                     "shape": {"ptr": {"kind": "slice", "len_from": "n"}}},
+         "i_field": {"name": "values", "type": "&[i32]"}},
+    ]})
+    shim = rust_ast_parser.generate_ffi_shim(
+        "fn sum(arr: *const libc::c_int, n: libc::c_int, out: *mut libc::c_long, ok: libc::c_int) -> libc::c_int",
+        "fn sum_idiomatic(values: &[i32], out: &mut i32, ok: bool) -> bool",
+        spec,
+    )
+    assert '#[no_mangle]\npub unsafe extern "C" fn sum(' in shim
+    # a negative C length is rejected instead of wrapping around
+    assert "let __values_len: usize = usize::try_from(n)\n        .expect(\"negative length 'n' for 'values'\");" in shim
+    assert "std::slice::from_raw_parts(arr as *const i32, __values_len)" in shim
+    assert "let mut __out = unsafe { *out } as i32;" in shim
+    assert "sum_idiomatic(__values, &mut __out, ok != 0)" in shim
+    assert "unsafe { *out = __out as libc::c_long };" in shim
+    assert "__ret as libc::c_int" in shim
+
+    shim = rust_ast_parser.generate_ffi_shim(
+        "pub unsafe extern \"C\" fn atoi(s: *const libc::c_char) -> libc::c_int",
+        "fn atoi_idiomatic(s: &str) -> i32",
+        "",
+    )
+    assert "let __s = if s.is_null() {" in shim
+    assert "unsafe { std::ffi::CStr::from_ptr(s) }.to_string_lossy()" in shim
+    assert "    atoi_idiomatic(&__s)\n}" in shim
+
+    shim = rust_ast_parser.generate_ffi_shim(
+        "fn greet(name: *const libc::c_char) -> *mut libc::c_char",
+        "fn greet_idiomatic(name: Option<&str>) -> String",
+        "",
+    )
+    assert "__name.as_deref()" in shim
+    assert "std::ffi::CString::new(__ret).unwrap_or_default().into_raw()" in shim
+    assert 'pub unsafe extern "C" fn free_greet_result(ptr: *mut libc::c_char)' in shim
+
+    with pytest.raises(ValueError, match="len_from or len_const"):
+        rust_ast_parser.generate_ffi_shim(
+            "fn f(p: *const i32, n: usize)", "fn g(p: &[i32])", "")
+    with pytest.raises(ValueError, match="Cannot convert"):
+        rust_ast_parser.generate_ffi_shim("fn f(p: *mut i32)", "fn g(p: Vec<i32>)", "")
+
+
+def test_generate_ffi_shim_runs(tmp_path):
+    spec = json.dumps({"fields": [
+        {"u_field": {"name": "arr", "shape": {"ptr": {"kind": "slice", "len_from": "n"}}},
+         "i_field": {"name": "values"}},
+    ]})
+    sum_shim = rust_ast_parser.generate_ffi_shim(
+        "fn sum(arr: *const i32, n: i32) -> i32", "fn sum_idiomatic(values: &[i32]) -> i32", spec)
+    greet_shim = rust_ast_parser.generate_ffi_shim(
+        "fn greet(name: *const std::ffi::c_char) -> *mut std::ffi::c_char",
+        "fn greet_idiomatic(name: &str) -> String", "")
+    code = sum_shim + greet_shim + """
+fn sum_idiomatic(values: &[i32]) -> i32 { values.iter().sum() }
+fn greet_idiomatic(name: &str) -> String { format!("hi {}", name) }
+fn main() {
+    unsafe {
+        let values = [1, 2, 3];
+        let greeting = greet(std::ptr::null());
+        println!("{} {} {:?}", sum(values.as_ptr(), 3), sum(std::ptr::null(), 3),
+                 std::ffi::CStr::from_ptr(greeting));
+        free_greet_result(greeting);
+        sum(values.as_ptr(), -1);
+    }
+}
+"""
+    (tmp_path / "main.rs").write_text(code)
+    subprocess.run(["rustc", "--edition", "2021", "main.rs", "-o", "main"],
+                   cwd=tmp_path, check=True, capture_output=True)
+    run = subprocess.run([os.path.join(tmp_path, "main")], capture_output=True, text=True, timeout=10)
+    assert run.stdout == '6 0 "hi "\n'
+    # the shim aborts rather than reading a slice of negative length
+    assert run.returncode != 0
+    assert "negative length 'n' for 'values'" in run.stderr
+
+
+def test_has_derive_and_implements_trait():
+    code = '''
+#[derive(Clone, core::fmt::Debug)]
+#[derive(PartialEq)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl Default for Point {
+    fn default() -> Self {
+        Point { x: 0, y: 0 }
+    }
+}
+
+pub enum Shape {
+    Dot(Point),
+}
+
+mod fmt_impls {
+    impl std::fmt::Display for super::Shape {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "shape")
+        }
+    }
+}
+'''
+    assert rust_ast_parser.has_derive(code, "Point", "Clone")
+    assert rust_ast_parser.has_derive(code, "Point", "std::fmt::Debug")
+    assert rust_ast_parser.has_derive(code, "Point", "PartialEq")
+    assert not rust_ast_parser.has_derive(code, "Point", "Default")
+    assert rust_ast_parser.implements_trait(code, "Point", "Default")
+    assert rust_ast_parser.implements_trait(code, "Point", "Debug")
+    assert not rust_ast_parser.implements_trait(code, "Point", "Copy")
+    assert rust_ast_parser.implements_trait(code, "Shape", "Display")
+    assert not rust_ast_parser.has_derive(code, "Shape", "Debug")
+    with pytest.raises(ValueError, match="Type 'Missing' not found"):
+        rust_ast_parser.has_derive(code, "Missing", "Clone")
+
+
+def test_infer_derives():
+    code = '''
+pub type Id = u32;
+
+pub struct Inner {
+    tag: libc::c_int,
+    weight: f64,
+}
+
+#[derive(Debug)]
+pub struct Node {
+    id: Id,
+    inner: Inner,
+    next: Option<Box<Node>>,
+    name: String,
+}
+
+pub struct Raw {
+    p: *mut Inner,
+    cb: Option<unsafe extern "C" fn(i32) -> i32>,
+}
+
+pub union Value {
+    i: i32,
+    f: f32,
+}
+
+pub struct Tagged {
+    tag: i32,
+    value: Value,
+}
+
+pub struct Handle {
+    file: std::fs::File,
+}
+'''
+    result = rust_ast_parser.infer_derives(code, "Node")
+    # the existing Debug derive is kept and Inner gets what Node relies on
+    assert "#[derive(Debug, Clone, PartialEq, Default)]\npub struct Node" in result
+    assert "#[derive(Clone, Debug, PartialEq, Default)]\npub struct Inner" in result
+
+    result = rust_ast_parser.infer_derives(code, "Raw")
+    assert "#[derive(Clone, Copy, Debug)]\npub struct Raw" in result
+    # Inner is only reached through a raw pointer
+    assert "pub type Id = u32;\npub struct Inner" in result
+
+    result = rust_ast_parser.infer_derives(code, "Tagged")
+    assert "#[derive(Clone, Copy)]\npub union Value" in result
+    assert "#[derive(Clone, Copy)]\npub struct Tagged" in result
+
+    result = rust_ast_parser.infer_derives(code, "Handle")
+    assert "#[derive" not in result.split("pub struct Handle")[0].rsplit("}", 1)[-1]
+
+    with pytest.raises(ValueError, match="Type 'Missing' not found"):
+        rust_ast_parser.infer_derives(code, "Missing")
+
+
+def test_generate_default_impl():
+    code = '''
+pub type Id = libc::c_uint;
+pub enum Color { Red = 1, Black = 0 }
+#[derive(Default)]
+pub struct Stats { n: u32 }
+pub struct Inner { w: f64, tag: [libc::c_char; 8], names: [String; 2] }
+pub union Raw { i: i32, f: f32 }
+pub struct Node<T> {
+    id: Id,
+    color: Color,
+    inner: Inner,
+    stats: Stats,
+    raw: Raw,
+    name: String,
+    items: Vec<T>,
+    next: Option<Box<Node<T>>>,
+    ptr: *const u8,
+    pair: (bool, char),
+}
+pub struct Cycle { inner: Box<Cycle> }
+'''
+    result = rust_ast_parser.generate_default_impl(code, "Node")
+    assert result.startswith("impl<T: Default> Default for Node<T> {")
+    for line in [
+        "id: 0,",
+        "color: Color::Black,",
+        "tag: [0; 8],",
+        "names: std::array::from_fn(|_| String::new()),",
+        "stats: Default::default(),",
+        "raw: Raw { i: 0 },",
+        "name: String::new(),",
+        "items: Vec::new(),",
+        "next: None,",
+        "ptr: std::ptr::null(),",
+        "pair: (false, '\\0'),",
+    ]:
+        assert line in result
+
+    with pytest.raises(ValueError, match="contains itself"):
+        rust_ast_parser.generate_default_impl(code, "Cycle")
+    with pytest.raises(ValueError, match="Struct or union 'Color' not found"):
+        rust_ast_parser.generate_default_impl(code, "Color")
+
+
+def test_infer_slice_params():
+    code = '''
+pub unsafe extern "C" fn sum(arr: *const libc::c_int, n: libc::c_int, out: *mut f64, count: usize, flags: libc::c_int) -> libc::c_int {
+    let mut total = 0;
+    let mut i = 0;
+    while i < n {
+        total += *arr.offset(i as isize);
+        i += 1;
+    }
+    let values = std::slice::from_raw_parts_mut(out, count as usize);
+    for j in 0..count {
+        values[j] = 0.0;
+    }
+    return total + flags;
+}
+'''
+    pairs = rust_ast_parser.infer_slice_params(code, "sum")
+    assert pairs == [
+        {"mutable": False, "pointer": "arr", "length": "n", "evidence": ["offset_loop"]},
+        {
+            "mutable": True,
+            "pointer": "out",
+            "length": "count",
+            "evidence": ["from_raw_parts", "index_loop"],
+        },
+    ]
+
+
+def test_error_code_to_result():
+    code = '''
+pub fn open_db(path: &str, size: i32) -> libc::c_int {
+    if path.is_empty() {
+        return -1;
+    }
+    if size > 10 {
+        return -2;
+    }
+    0
+}
+pub fn len(x: i32) -> i32 {
+    if x > 0 {
+        return x;
+    }
+    0
+}
+pub fn run() -> i32 {
+    if open_db("a", 1) != 0 {
+        return 1;
+    }
+    if open_db("b", 1) == 0 {
+        println!("ok");
+    }
+    open_db("c", 2);
+    let rc = open_db("d", 3);
+    println!("{}", open_db("e", 4));
+    return 0;
+}
+'''
+    found = rust_ast_parser.find_error_code_functions(code)
+    assert found[0] == {
+        "function": "open_db",
+        "failure_values": [-2, -1],
+        "call_sites": 5,
+        "checked_call_sites": 2,
+    }
+    assert all(entry["function"] != "len" for entry in found)
+
+    result = rust_ast_parser.convert_error_code_to_result(code, "open_db")
+    assert "-> Result<(), libc::c_int>" in result
+    assert "return Err(-1);" in result
+    assert "Ok(())\n}" in result
+    assert 'if open_db("a", 1).is_err()' in result
+    assert 'if open_db("b", 1).is_ok()' in result
+    assert 'let _ = open_db("c", 2);' in result
+    assert 'let rc = open_db("d", 3).err().unwrap_or(0);' in result
+    assert 'println!("{}", open_db("e", 4).err().unwrap_or(0));' in result
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.convert_error_code_to_result(code, "len")
+    # calls inside a macro whose arguments are not expressions cannot be rewritten
+    with pytest.raises(ValueError, match="inside a macro"):
+        rust_ast_parser.convert_error_code_to_result(
+            code.replace('let rc = open_db("d", 3);', 'let rc = vec![open_db("d", 3); 2];'), "open_db")
+
+
+def test_convert_to_method():
+    code = '''
+pub struct Student {
+    pub age: i32,
+}
+pub unsafe fn student_update(s: *mut Student, age: i32) {
+    (*s).age = age;
+}
+pub fn student_age(s: &Student) -> i32 {
+    s.age
+}
+pub unsafe fn run() {
+    let mut st = Student { age: 1 };
+    student_update(&mut st, 3);
+    let p = &mut st as *mut Student;
+    student_update(p, 4);
+    let age = student_age(&st);
+}
+'''
+    result = rust_ast_parser.convert_to_method(code, "student_update", "Student")
+    result = rust_ast_parser.convert_to_method(result, "student_age", "Student")
+    assert "impl Student {" in result
+    assert result.count("impl Student") == 1
+    assert "pub unsafe fn update(&mut self, age: i32)" in result
+    assert "self.age = age;" in result
+    assert "pub fn age(&self) -> i32" in result
+    assert "st.update(3);" in result
+    assert "(*p).update(4);" in result
+    assert "let age = st.age();" in result
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.convert_to_method(code, "run", "Student")
+
+    exported = code.replace("pub unsafe fn student_update", '#[no_mangle]\npub unsafe extern "C" fn student_update')
+    with pytest.raises(ValueError, match="exported to C"):
+        rust_ast_parser.convert_to_method(exported, "student_update", "Student")
+    checked = code.replace("(*s).age = age;", "if s.is_null() {\n        return;\n    }\n    (*s).age = age;")
+    with pytest.raises(ValueError, match="as a raw pointer"):
+        rust_ast_parser.convert_to_method(checked, "student_update", "Student")
+
+
+def test_generate_test_module():
+    code = '''
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+pub unsafe fn half(a: f64) -> f64 {
+    a / 2.0
+}
+'''
+    cases = json.dumps([
+        {"args": [1, 2], "expected": 3},
+        {"args": ["-1", "1"], "expected": "0", "name": "adds_negatives"},
+    ])
+    result = rust_ast_parser.generate_test_module(code, "add", cases)
+    result = rust_ast_parser.generate_test_module(
+        result, "half", json.dumps([{"args": [3.0], "expected": 1.5}, {"args": [6], "expected": 3}])
+    )
+    assert result.count("mod tests") == 1
+    assert "#[cfg(test)]" in result
+    assert "fn test_add_1() {" in result
+    assert "assert_eq!(result, 3);" in result
+    assert "fn adds_negatives() {" in result
+    assert "let result = add(-1, 1);" in result
+    assert "let result = unsafe { half(3.0) };" in result
+    assert "(result - 1.5).abs() < 1e-6" in result
+    # integers are written as literals of the parameter and return types
+    assert "let result = unsafe { half(6.0) };" in result
+    assert "(result - 3.0).abs() < 1e-6" in result
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.generate_test_module(code, "add", json.dumps([{"args": [1]}]))
+    with pytest.raises(ValueError, match="not a valid Rust identifier"):
+        rust_ast_parser.generate_test_module(code, "add", json.dumps([{"args": [1, 2], "name": "adds one"}]))
+
+
+def test_add_serde_capture():
+    code = '''
+use std::collections::HashMap;
+#[derive(Debug)]
+pub enum Kind { A, B(u32) }
+pub struct Point { x: i32, y: i32 }
+pub struct Shape<T> { kind: Kind, points: Vec<Point>, extra: HashMap<String, T> }
+pub struct Raw { data: *mut u8 }
+'''
+    result = rust_ast_parser.add_serde_capture(code, "Shape")
+    assert "use serde::{Deserialize, Serialize};" in result
+    assert result.count("Serialize, Deserialize") == 3
+    assert "#[derive(Debug, Serialize, Deserialize)]\npub enum Kind" in result
+    assert "impl<T: Serialize> Shape<T> {" in result
+    assert "serde_json::to_string(self).unwrap_or_default()" in result
+
+    again = rust_ast_parser.add_serde_capture(result, "Shape")
+    assert again.count("to_capture_json") == 1
+    assert again.count("use serde::") == 1
+
+    with pytest.raises(ValueError, match="cannot be serialized"):
+        rust_ast_parser.add_serde_capture(code, "Raw")
+    with pytest.raises(ValueError, match="not found"):
+        rust_ast_parser.add_serde_capture(code, "Missing")
+
+
+def test_generate_arbitrary_impl():
+    code = '''
+pub struct Buffer<T> {
+    items: Vec<String>,
+    n_items: usize,
+    data: *mut libc::c_int,
+    data_len: libc::c_int,
+    name: *const libc::c_char,
+    opaque: *mut libc::c_void,
+    extra: T,
+}
+pub struct Bytes { u: Vec<u8>, len: u32 }
+pub struct Borrowed<'a> { s: &'a str }
+'''
+    result = rust_ast_parser.generate_arbitrary_impl(code, "Buffer")
+    assert result.startswith(
+        "impl<'arbitrary, T: arbitrary::Arbitrary<'arbitrary>> arbitrary::Arbitrary<'arbitrary>\nfor Buffer<T> {"
+    )
+    for line in [
+        ".map(|_| Ok(u.arbitrary::<String>()?.replace('\\0', \"\")))",
+        "let data: Vec<libc::c_int> = u.arbitrary()?;",
+        "let opaque: *mut libc::c_void = std::ptr::null_mut();",
+        "let n_items = items.len() as usize;",
+        "let data_len = data.len() as libc::c_int;",
+        "let data = Box::leak(data.into_boxed_slice()).as_mut_ptr();",
+        ".into_raw() as *const _;",
+    ]:
+        assert line in result
+    assert result.index("let data_len") < result.index("let data = Box::leak")
+
+    bytes_impl = rust_ast_parser.generate_arbitrary_impl(code, "Bytes")
+    assert "unstructured: &mut arbitrary::Unstructured<'arbitrary>" in bytes_impl
+    assert "let len = u.len() as u32;" in bytes_impl
+
+    with pytest.raises(ValueError, match="borrows data"):
+        rust_ast_parser.generate_arbitrary_impl(code, "Borrowed")
+    with pytest.raises(ValueError, match="not found"):
+        rust_ast_parser.generate_arbitrary_impl(code, "Missing")
+
+
+def test_generate_fuzz_target():
+    code = '''
+#[repr(C)]
+pub struct Point { pub x: libc::c_int, pub y: libc::c_int }
+#[no_mangle]
+pub unsafe extern "C" fn sum(items: *const libc::c_int, n_items: libc::c_int) -> libc::c_int { 0 }
+#[no_mangle]
+pub unsafe extern "C" fn shift(p: *mut Point, dx: libc::c_int) {}
+#[no_mangle]
+pub unsafe extern "C" fn upcase(mut str_: *mut libc::c_char) {}
+#[no_mangle]
+pub extern "C" fn opaque(data: *mut libc::c_void) {}
+fn helper() {}
+'''
+    result = rust_ast_parser.generate_fuzz_target(code, "sum", "sactor_c_")
+    assert result["renamed"] == {
+        "opaque": "sactor_c_opaque",
+        "shift": "sactor_c_shift",
+        "sum": "sactor_c_sum",
+        "upcase": "sactor_c_upcase",
+    }
+    target = result["code"]
+    assert target.startswith("#![no_main]")
+    for line in [
+        "fn sactor_c_sum(items: *const libc::c_int, n_items: libc::c_int) -> libc::c_int;",
+        "items: Vec<libc::c_int>,",
+        "let items_rust = items_c.clone();",
+        "sactor_c_sum(items_c.as_ptr() as *const _, items_c.len() as libc::c_int)",
+        "sum(items_rust.as_ptr() as *const _, items_rust.len() as libc::c_int)",
+        "fuzz_same(& c_result, & rust_result)",
+        "libfuzzer_sys::fuzz_target!(| input : FuzzInput | fuzz_one(input));",
+    ]:
+        assert line in target
+    assert "n_items:" not in target.split("struct FuzzInput")[1].split("}")[0]
+
+    target = rust_ast_parser.generate_fuzz_target(code, "shift", "sactor_c_")["code"]
+    assert "p: (libc::c_int, libc::c_int)," in target
+    assert "p_rust.y = input.p.1;" in target
+    assert "fuzz_same(& p_c.x, & p_rust.x)" in target
+    assert "let c_result" not in target
+
+    target = rust_ast_parser.generate_fuzz_target(code, "upcase", "sactor_c_")["code"]
+    assert "fn sactor_c_upcase(str_: *mut libc::c_char);" in target
+    assert "let mut str_c: Vec<u8> = input.str_.replace('\\0', \"\").into_bytes();" in target
+    assert "fuzz_same(& str_c, & str_rust)" in target
+
+    with pytest.raises(ValueError, match="cannot generate"):
+        rust_ast_parser.generate_fuzz_target(code, "opaque", "sactor_c_")
+    with pytest.raises(ValueError, match="not found"):
+        rust_ast_parser.generate_fuzz_target(code, "helper", "sactor_c_")
+
+
+def test_generate_differential_driver():
+    code = '''
+#![no_main]
+#[no_mangle]
+pub extern "C" fn add(a: libc::c_int, b: libc::c_int) -> libc::c_int { a + b }
+pub fn main() {}
+'''
+    result = rust_ast_parser.generate_differential_driver(code, "add", "sactor_c_")
+    assert result["renamed"] == {"add": "sactor_c_add"}
+    driver = result["code"]
+    assert "no_main" not in driver
+    assert "pub fn main() {}" not in driver
+    assert "#[derive(Debug, serde::Deserialize)]" in driver
+    assert "fn sactor_c_add(a: libc::c_int, b: libc::c_int) -> libc::c_int;" in driver
+    assert 'include_str!("../samples.json")' in driver
+    assert "fuzz_target!" not in driver
+
+
+def test_generate_kani_harness():
+    signature = (
+        'pub unsafe extern "C" fn scale(mut items: *mut libc::c_int, n_items: libc::c_int, '
+        'factor: f64, name: *const libc::c_char) -> f64'
+    )
+    preconditions = json.dumps({
+        "params": {
+            "items": {"max_len": 4},
+            "factor": {"min": 0, "max": 2.5},
+            "name": {"max_len": 3, "non_null": False},
+        }
+    })
+    harness = rust_ast_parser.generate_kani_harness(signature, preconditions)
+    assert harness.startswith("#[cfg(kani)]\nmod kani_scale {")
+    for line in [
+        "fn sactor_c_scale(",
+        "#[kani::unwind(5)]",
+        "fn scale_matches_c() {",
+        "kani::assume(items_len <= 4);",
+        "let mut items_c: [libc::c_int; 4] = kani::any();",
+        "kani::assume(factor >= 0.0);",
+        "kani::assume(factor <= 2.5);",
+        "name_c[name_len] = 0;",
+        "if name_is_null { core::ptr::null() } else { name_c.as_ptr() },",
+        "items_len as libc::c_int,",
+        "assert!(c_result == rust_result || (c_result.is_nan() && rust_result.is_nan()));",
+        "assert!(items_c == items_rust);",
+    ]:
+        assert line in harness
+
+    simple = rust_ast_parser.generate_kani_harness(
+        'pub extern "C" fn add(a: i32, b: i32) -> i32', '{"unwind": 3}', "c_")
+    assert "fn c_add(a: i32, b: i32) -> i32;" in simple
+    assert "#[kani::unwind(3)]" in simple
+    assert "let rust_result = add(a, b);" in simple
+    assert "assert_eq!(c_result, rust_result);" in simple
+
+    with pytest.raises(ValueError, match="unknown parameter 'c'"):
+        rust_ast_parser.generate_kani_harness(
+            'fn add(a: i32, b: i32) -> i32', '{"params": {"c": {"min": 0}}}')
+
+    struct_code = """
+#[repr(C)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+#[repr(C)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+    pub label: *const libc::c_char,
+    pub tags: [u8; 2],
+}
+"""
+    point = rust_ast_parser.generate_kani_harness(
+        'pub extern "C" fn make_point(x: f32, y: f32) -> Point', "{}", struct_code=struct_code)
+    assert "fn sactor_c_make_point(x: f32, y: f32) -> Point;" in point
+    assert "let c_result = unsafe { sactor_c_make_point(x, y) };" in point
+    assert "c_result.x == rust_result.x || (c_result.x.is_nan()" in point
+    assert "c_result.y == rust_result.y" in point
+
+    segment = rust_ast_parser.generate_kani_harness(
+        'pub extern "C" fn make_segment(a: f32) -> Segment', "{}", struct_code=struct_code)
+    assert "c_result.start.x == rust_result.start.x" in segment
+    assert "c_result.end.y == rust_result.end.y" in segment
+    assert "label" not in segment
+    assert "c_result.tags.iter().zip(rust_result.tags.iter())" in segment
+
+    with pytest.raises(ValueError, match="cannot compare"):
+        rust_ast_parser.generate_kani_harness('pub extern "C" fn make_point(x: f32) -> Point', "{}")
+    with pytest.raises(ValueError, match="Unknown precondition 'step'"):
+        rust_ast_parser.generate_kani_harness(
+            'fn add(a: i32, b: i32) -> i32', '{"params": {"a": {"step": 2}}}')
+
+
+def test_summarize_rustc_diagnostics():
+    code = '''pub struct Counter {
+    counts: HashMap<String, i32>,
+}
+
+impl Counter {
+    pub fn total(&self) -> i64 {
+        let sum: i32 = self.counts.values().sum();
+        sum
+    }
+}
+
+pub fn first(v: Vec<i32>) -> i32 {
+    let w = v;
+    v[0] + w[0]
+}
+'''
+
+    def span(line, column, label=None, replacement=None, primary=True):
+        return {
+            "file_name": "src/lib.rs",
+            "line_start": line,
+            "line_end": line,
+            "column_start": column,
+            "column_end": column + 1,
+            "is_primary": primary,
+            "label": label,
+            "suggested_replacement": replacement,
+        }
+
+    def message(level, text, code=None, spans=(), children=()):
+        return {
+            "reason": "compiler-message",
+            "message": {
+                "level": level,
+                "message": text,
+                "code": {"code": code} if code else None,
+                "spans": list(spans),
+                "children": list(children),
+                "rendered": f"{level}: {text}\n",
+            },
+        }
+
+    import_help = {
+        "level": "help",
+        "message": "consider importing this struct",
+        "spans": [span(1, 1, replacement="use std::collections::HashMap;\n")],
+        "children": [],
+    }
+    records = [
+        {"reason": "compiler-artifact", "package_id": "libc"},
+        message("error", "cannot find type `HashMap` in this scope", "E0412",
+                [span(2, 13, "not found in this scope")], [import_help]),
+        message("error", "mismatched types", "E0308",
+                [span(8, 9, "expected `i64`, found `i32`")]),
+        message("error", "borrow of moved value: `v`", "E0382",
+                [span(13, 9, "value moved here", primary=False),
+                 span(14, 5, "value borrowed here after move")]),
+        message("error", "mismatched types", "E0308",
+                [span(8, 9, "expected `i64`, found `i32`")]),
+        message("warning", "unused variable: `w`", None, [span(13, 9)]),
+        message("error", "aborting due to 3 previous errors"),
+        {"reason": "build-finished", "success": False},
+    ]
+    output = "\n".join(json.dumps(record) for record in records) + "\n   Compiling x\n"
+
+    diagnostics = rust_ast_parser.summarize_rustc_diagnostics(output, code)
+    assert diagnostics == [
+        {
+            "kind": "missing_import",
+            "code": "E0412",
+            "message": "cannot find type `HashMap` in this scope",
+            "item": "Counter",
+            "line": 2,
+            "column": 13,
+            "label": "not found in this scope",
+            "notes": ["consider importing this struct: `use std::collections::HashMap;`"],
+        },
+        {
+            "kind": "type_mismatch",
+            "code": "E0308",
+            "message": "mismatched types",
+            "item": "Counter::total",
+            "line": 8,
+            "column": 9,
+            "label": "expected `i64`, found `i32`",
+            "notes": [],
+        },
+        {
+            "kind": "borrowck",
+            "code": "E0382",
+            "message": "borrow of moved value: `v`",
+            "item": "first",
+            "line": 14,
+            "column": 5,
+            "label": "value borrowed here after move",
+            "notes": [],
+        },
+    ]
+
+    # plain rustc `--error-format=json` lines, without source to map items
+    lifetime = message("error", "lifetime may not live long enough", spans=[span(3, 5)])
+    diagnostics = rust_ast_parser.summarize_rustc_diagnostics(json.dumps(lifetime["message"]))
+    assert diagnostics[0]["kind"] == "lifetime"
+    assert diagnostics[0]["item"] is None
+    assert rust_ast_parser.summarize_rustc_diagnostics("error: linking with `cc` failed") == []
+
+
+def test_rustfmt_config(tmp_path):
+    config = tmp_path / "rustfmt.toml"
+    config.write_text("hard_tabs = true\n")
+    code = "fn add(a: i32, b: i32) -> i32 { a + b }"
+
+    formatted = rust_ast_parser.format_with_rustfmt(code, str(config))
+    assert formatted == "fn add(a: i32, b: i32) -> i32 {\n\ta + b\n}\n"
+    assert rust_ast_parser.format_with_rustfmt(code) == (
+        "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n")
+    with pytest.raises(ValueError, match="rustfmt failed"):
+        rust_ast_parser.format_with_rustfmt("fn add(", str(config))
+
+    try:
+        rust_ast_parser.set_rustfmt_config(str(tmp_path))
+        assert rust_ast_parser.dedup_items(code) == formatted
+    finally:
+        rust_ast_parser.set_rustfmt_config(None)
+    assert "\t" not in rust_ast_parser.dedup_items(code)
+    with pytest.raises(ValueError, match="not found"):
+        rust_ast_parser.set_rustfmt_config(str(tmp_path / "missing.toml"))
+
+
+def test_generate_variadic_wrapper():
+    code = """
+use core::ffi::VaList;
+use libc::c_int;
+
+#[no_mangle]
+pub unsafe extern "C" fn sum_va(mut count: c_int, mut args: VaList) -> c_int {
+    let mut total = 0;
+    while count > 0 {
+        total += args.arg::<c_int>();
+        count -= 1;
+    }
+    total
+}
+"""
+    wrapped = rust_ast_parser.generate_variadic_wrapper(code, "sum")
+    assert "use core::ffi::VaList;" in wrapped
+    assert 'pub unsafe extern "C" fn sum(count: c_int, mut args: ...) -> c_int {' in wrapped
+    assert "    unsafe fn sum_va(mut count: c_int, mut args: VaList) -> c_int {" in wrapped
+    assert "    sum_va(count, args.as_va_list())\n}" in wrapped
+    assert "no_mangle" not in wrapped
+    assert rust_ast_parser.get_func_signatures(wrapped) == {
+        "sum": 'extern "C" fn sum (count : c_int , args : ...) -> c_int'
+    }
+    assert rust_ast_parser.defines_c_variadic(wrapped)
+    assert not rust_ast_parser.defines_c_variadic(code)
+    assert not rust_ast_parser.defines_c_variadic(
+        'extern "C" { fn printf(fmt: *const libc::c_char, ...) -> libc::c_int; }')
+
+    with pytest.raises(ValueError, match="'sum_va' taking a trailing `VaList` not found"):
+        rust_ast_parser.generate_variadic_wrapper("fn sum_va(count: i32) -> i32 { count }", "sum")
+
+
+def test_convert_to_no_std():
+    code = """use std::ffi::{CStr, CString};
+use libc::size_t;
+pub struct Node {
+    pub name: String,
+    pub children: Vec<Box<Node>>,
+    pub len: size_t,
+}
+pub unsafe fn describe(node: &Node, raw: *const libc::c_char) -> String {
+    let label = CStr::from_ptr(raw).to_string_lossy().to_string();
+    let _owned = CString::new(node.name.clone()).unwrap();
+    println!("{} has {} children", label, node.children.len());
+    format!("{}:{}", label, std::cmp::max(node.len, 1))
+}
+fn main() {}
+"""
+    converted = rust_ast_parser.convert_to_no_std(code, "crate::logger")
+    assert converted.startswith(
+        "use alloc::boxed::Box;\nuse alloc::string::String;\nuse alloc::string::ToString;\nuse alloc::vec::Vec;\n")
+    assert "use {core::ffi::CStr, alloc::ffi::CString};" in converted
+    assert "use core::primitive::usize as size_t;" in converted
+    assert "raw: *const core::ffi::c_char" in converted
+    assert "core::fmt::Write::write_fmt(\n            &mut crate::logger(),\n" in converted
+    assert 'alloc::format!("{}:{}", label, core::cmp::max(node.len, 1))' in converted
+    assert "fn main" not in converted
+
+    with pytest.raises(ValueError) as e:
+        rust_ast_parser.convert_to_no_std(
+            "use std::collections::HashMap;\nfn f() { println!(\"x\"); std::process::exit(libc::abs(1)); }")
+    assert str(e.value) == (
+        "Not available without std: `libc::abs` has no binding on bare-metal targets; "
+        "`println!` needs the standard library; pass a logger to route output through `core::fmt::Write`; "
+        "`std::collections::HashMap` needs the standard library; `std::process::exit` needs the standard library")
+
+    # float math lives in std, not core
+    with pytest.raises(ValueError) as e:
+        rust_ast_parser.convert_to_no_std(
+            "fn norm(x: f64, y: f64) -> f64 { (x.powi(2) + y * y).sqrt() }\nfn root(x: f32) -> f32 { f32::sqrt(x.abs()) }")
+    assert str(e.value) == (
+        "Not available without std: `.powi()` on floats needs the standard library; "
+        "`.sqrt()` on floats needs the standard library; `f32::sqrt` needs the standard library")
+
+
+def test_find_nightly_constructs():
+    code = """#![feature(c_variadic)]
+extern "C" {
+    fn printf(fmt: *const libc::c_char, ...) -> libc::c_int;
+}
+#[thread_local]
+static mut DEPTH: i32 = 0;
+pub unsafe extern "C" fn log_msg(level: i32, mut args: ...) {
+    log_msg_va(level, args.as_va_list());
+}
+unsafe fn log_msg_va(level: i32, args: core::ffi::VaList) {
+    core::intrinsics::abort();
+}
+"""
+    assert [(c["kind"], c["detail"], c["line"]) for c in rust_ast_parser.find_nightly_constructs(code)] == [
+        ("feature", "c_variadic", 1),
+        ("unstable_attribute", "thread_local", 5),
+        ("c_variadic", "log_msg", 7),
+        ("va_list", "core::ffi::VaList", 10),
+        ("intrinsics", "core::intrinsics::abort", 11),
+    ]
+    assert rust_ast_parser.find_nightly_constructs(
+        'extern "C" { fn printf(fmt: *const libc::c_char, ...) -> libc::c_int; }\nfn main() {}') == []
+
+
+def test_generate_c_header():
+    code = '''
+pub type score_t = libc::c_double;
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Student {
+    pub name: *mut libc::c_char,
+    pub scores: [score_t; 3],
+    pub grade: Grade,
+    pub next: *mut Student,
+}
+#[repr(C)]
+pub struct Grade { pub letter: libc::c_char }
+#[derive(Copy, Clone)]
+#[repr(u32)]
+pub enum Level { Low = 0, High = 1 }
+#[no_mangle]
+pub static mut counter: libc::c_int = 0;
+#[no_mangle]
+pub unsafe extern "C" fn find(list: *const *const Student, name: *const libc::c_char, n: usize) -> *mut Student {
+    std::ptr::null_mut()
+}
+#[no_mangle]
+pub extern "C" fn apply(cb: Option<unsafe extern "C" fn(libc::c_int) -> libc::c_int>, level: Level) {}
+#[no_mangle]
+pub extern "C" fn reset() -> bool { true }
+fn helper() {}
+fn main() {}
+'''
+    header = rust_ast_parser.generate_c_header(code, "STUDENTS_H")
+    assert header.startswith("#ifndef STUDENTS_H\n#define STUDENTS_H\n")
+    assert header.rstrip().endswith("#endif /* STUDENTS_H */")
+    for line in [
+        "struct Student;",
+        "typedef double score_t;",
+        "    char *name;",
+        "    score_t scores[3];",
+        "    struct Grade grade;",
+        "    struct Student *next;",
+        "enum Level {\n    Low = 0,\n    High = 1,\n};",
+        "extern int counter;",
+        "struct Student *find(const struct Student *const *list, const char *name, size_t n);",
+        "void apply(int (*cb)(int), enum Level level);",
+        "bool reset(void);",
+    ]:
+        assert line in header
+    # a type used by value is defined before its user
+    assert header.index("struct Grade {") < header.index("struct Student {")
+    assert "helper" not in header
+    assert "main" not in header
+
+    with pytest.raises(ValueError, match="no C equivalent"):
+        rust_ast_parser.generate_c_header(
+            '#[no_mangle]\npub extern "C" fn f(v: Vec<i32>) {}', "F_H")
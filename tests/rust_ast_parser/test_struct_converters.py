@@ -0,0 +1,448 @@
+import json
+import os
+import subprocess
+from textwrap import dedent
+
+import pytest
+
+from sactor import rust_ast_parser
+
+C_STUDENT = dedent(
+    """
+    #[derive(Copy, Clone, Debug)]
+    #[repr(C)]
+    pub struct CStudent {
+        pub name: *mut libc::c_char,
+        pub age: libc::c_int,
+        pub enrolledCourse: *mut CCourse,
+        pub grades: *mut libc::c_float,
+        pub numGrades: libc::c_int,
+    }
+    """
+)
+
+STUDENT = dedent(
+    """
+    #[derive(Clone, Debug)]
+    pub struct Student {
+        pub name: Option<String>,
+        pub age: i32,
+        pub enrolled_course: Option<Course>,
+        pub grades: Vec<f32>,
+    }
+    """
+)
+
+STUDENT_SPEC = {
+    "struct_name": "Student",
+    "fields": [
+        {"u_field": {"name": "name", "shape": {"ptr": {"kind": "cstring", "null": "nullable"}}},
+         "i_field": {"name": "name"}},
+        {"u_field": {"name": "age", "shape": "scalar"}, "i_field": {"name": "age"}},
+        {"u_field": {"name": "enrolledCourse", "shape": {"ptr": {"kind": "ref", "null": "nullable"}}},
+         "i_field": {"name": "enrolled_course"}},
+        {"u_field": {"name": "grades", "shape": {"ptr": {"kind": "slice", "len_from": "numGrades"}}},
+         "i_field": {"name": "grades"}},
+        {"u_field": {"name": "numGrades", "shape": "scalar"}, "i_field": {"name": "grades.len"}},
+    ],
+}
+
+
+def test_generate_struct_converters():
+    code = rust_ast_parser.generate_struct_converters(
+        C_STUDENT, STUDENT, json.dumps(STUDENT_SPEC)
+    )
+
+    assert "unsafe fn CStudent_to_Student_mut(input: *mut CStudent) -> &'static mut Student" in code
+    assert "unsafe fn Student_to_CStudent_mut(idiom_struct: &mut Student) -> *mut CStudent" in code
+    # C -> idiomatic
+    assert "std::ffi::CStr::from_ptr(c_struct.name)" in code
+    assert "CCourse_to_Course_mut(c_struct.enrolledCourse as *mut CCourse)" in code
+    assert "as *mut Course" in code
+    assert "std::slice::from_raw_parts(" in code
+    assert "(c_struct.numGrades as usize)" in code
+    # idiomatic -> C
+    assert "match idiom_struct.name.as_mut()" in code
+    assert "Course_to_CCourse_mut(v) as *mut CCourse" in code
+    assert "let _numGrades: libc::c_int = (idiom_struct.grades.len() as libc::c_int);" in code
+    assert "TODO" not in code
+
+
+def test_generate_struct_converters_free_functions():
+    code = rust_ast_parser.generate_struct_converters(
+        C_STUDENT, STUDENT, json.dumps(STUDENT_SPEC)
+    )
+
+    assert "unsafe fn free_CStudent(input: *mut CStudent)" in code
+    assert "unsafe fn free_Student(idiom_struct: &'static mut Student)" in code
+    assert "std::ffi::CString::from_raw(c_struct.name as *mut std::ffi::c_char)" in code
+    assert "free_CCourse(c_struct.enrolledCourse as *mut CCourse);" in code
+    assert "(c_struct.numGrades as usize)" in code.split("unsafe fn free_CStudent_fields")[1]
+    assert "struct CStudentGuard(*mut CStudent);" in code
+    assert "impl Drop for CStudentGuard" in code
+
+
+def test_generate_struct_converters_marks_unsupported_fields():
+    spec = [
+        {"u_field": {"name": "age", "shape": "scalar"}, "i_field": {"name": "age"}},
+    ]
+    code = rust_ast_parser.generate_struct_converters(C_STUDENT, STUDENT, json.dumps(spec))
+
+    assert "todo!(\"TODO: no mapping for idiomatic field 'grades'\")" in code
+    # unmapped C fields are zero-initialised
+    assert "let _grades: *mut libc::c_float = core::mem::zeroed();" in code
+
+
+C_EVENT = dedent(
+    """
+    #[derive(Copy, Clone)]
+    #[repr(C)]
+    pub struct CEvent {
+        pub tag: u8,
+        pub data: CEventData,
+    }
+
+    #[derive(Copy, Clone)]
+    #[repr(C)]
+    pub union CEventData {
+        pub message: *mut libc::c_char,
+        pub code: i32,
+    }
+    """
+)
+
+EVENT = dedent(
+    """
+    pub enum Event {
+        Message(String),
+        Code(i32),
+        Quit,
+    }
+    """
+)
+
+EVENT_SPEC = {
+    "i_kind": "enum",
+    "i_type": "Event",
+    "variants": [
+        {"name": "Message", "when": {"tag": "tag", "equals": 0},
+         "payload": [{"u_field": {"name": "data.message", "shape": {"ptr": {"kind": "cstring"}}},
+                      "i_field": {"name": "0"}}]},
+        {"name": "Code", "when": {"tag": "tag", "equals": 1},
+         "payload": [{"u_field": {"name": "data.code", "shape": "scalar"}, "i_field": {"name": "0"}}]},
+    ],
+}
+
+
+def test_generate_enum_converters():
+    code = rust_ast_parser.generate_struct_converters(C_EVENT, EVENT, json.dumps(EVENT_SPEC))
+
+    assert "unsafe fn CEvent_to_Event_mut(input: *mut CEvent) -> &'static mut Event" in code
+    assert "unsafe fn Event_to_CEvent_mut(idiom_struct: &mut Event) -> *mut CEvent" in code
+    # C -> idiomatic dispatches on the tag and reads the active union member
+    assert "if c_struct.tag == (0 as u8)" in code
+    assert "std::ffi::CStr::from_ptr(c_struct.data.message)" in code
+    assert "Event::Code(c_struct.data.code)" in code
+    assert 'panic!("unsupported tag value")' in code
+    # idiomatic -> C writes the tag and a single union member
+    assert "Event::Code(v0) => {" in code
+    assert "let _tag: u8 = 1 as u8;" in code
+    assert "tmp.code = *v0;" in code
+    # `Quit` has no mapping
+    assert 'panic!("unsupported variant")' in code
+    # only the active string payload is released
+    release = code.split("unsafe fn free_CEvent_fields")[1].split("unsafe fn free_CEvent(")[0]
+    assert "if c_struct.tag == (0 as u8)" in release
+    assert "c_struct.data.message as *mut std::ffi::c_char" in release
+
+
+def test_generate_enum_converters_requires_variants():
+    with pytest.raises(ValueError):
+        rust_ast_parser.generate_struct_converters(C_EVENT, EVENT, json.dumps({"fields": []}))
+
+
+def test_generate_callback_converters():
+    c_def = dedent(
+        """
+        #[repr(C)]
+        pub struct CHandler {
+            pub on_event: Option<unsafe extern "C" fn(libc::c_int) -> libc::c_int>,
+            pub id: libc::c_int,
+        }
+        """
+    )
+    i_def = dedent(
+        """
+        pub struct Handler {
+            pub on_event: Option<Box<dyn Fn(i32) -> i32>>,
+            pub id: i32,
+        }
+        """
+    )
+    spec = [
+        {"u_field": {"name": "on_event", "shape": "scalar"}, "i_field": {"name": "on_event"}},
+        {"u_field": {"name": "id", "shape": "scalar"}, "i_field": {"name": "id"}},
+    ]
+    code = rust_ast_parser.generate_struct_converters(c_def, i_def, json.dumps(spec))
+
+    # C -> idiomatic wraps the raw pointer in a closure
+    assert "c_struct\n            .on_event\n            .map(|f|" in code
+    assert "as Box<dyn Fn(i32) -> i32>" in code
+    # idiomatic -> C installs the closure and hands out a trampoline
+    assert 'unsafe extern "C" fn CHandler_on_event_trampoline(a0: libc::c_int) -> libc::c_int' in code
+    assert "CHANDLER_ON_EVENT_CLOSURE" in code
+    installed = code.split("fn Handler_to_CHandler_mut")[1]
+    assert "CHandler_on_event_trampoline" in installed
+    assert "None => None," in installed
+    assert "TODO" not in code
+    # freeing the C struct releases the slot it holds
+    release = code.split("unsafe fn free_CHandler_fields")[1].split("unsafe fn free_CHandler(")[0]
+    assert "CHANDLER_ON_EVENT_CLOSURE.with(|slot| slot.set(None))" in release
+
+
+def test_generate_callback_converters_runs(tmp_path):
+    c_def = dedent(
+        """
+        #[repr(C)]
+        pub struct CHandler {
+            pub on_event: Option<unsafe extern "C" fn(i32) -> i32>,
+        }
+        """
+    )
+    i_def = dedent(
+        """
+        pub struct Handler {
+            pub on_event: Option<Box<dyn Fn(i32) -> i32>>,
+        }
+        """
+    )
+    spec = [{"u_field": {"name": "on_event", "shape": "scalar"}, "i_field": {"name": "on_event"}}]
+    code = rust_ast_parser.generate_struct_converters(c_def, i_def, json.dumps(spec))
+    main = dedent(
+        """
+        fn main() {
+            unsafe {
+                let mut first = Handler { on_event: Some(Box::new(|x| x + 1)) };
+                let mut second = Handler { on_event: Some(Box::new(|x| x * 10)) };
+                let c_first = Handler_to_CHandler_mut(&mut first);
+                print!("{} ", ((*c_first).on_event.unwrap())(1));
+                // a second live struct would make the trampoline call the wrong closure
+                let clash = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    Handler_to_CHandler_mut(&mut second);
+                }));
+                print!("{} ", clash.is_err());
+                free_CHandler(c_first);
+                let c_second = Handler_to_CHandler_mut(&mut second);
+                println!("{}", ((*c_second).on_event.unwrap())(1));
+                free_CHandler(c_second);
+            }
+        }
+        """
+    )
+    (tmp_path / "main.rs").write_text(c_def + i_def + code + main)
+    subprocess.run(["rustc", "--edition", "2021", "-A", "warnings", "main.rs", "-o", "main"],
+                   cwd=tmp_path, check=True, capture_output=True)
+    run = subprocess.run([os.path.join(tmp_path, "main")], capture_output=True, text=True, check=True, timeout=10)
+    assert run.stdout == "2 true 10\n", run.stdout + run.stderr
+
+
+def test_generate_pointer_array_converters():
+    c_def = dedent(
+        """
+        #[repr(C)]
+        pub struct CArgs {
+            pub argv: *mut *mut libc::c_char,
+            pub argc: libc::c_int,
+            pub grid: *mut *mut libc::c_int,
+            pub rows: libc::c_int,
+            pub cols: libc::c_int,
+        }
+        """
+    )
+    i_def = dedent(
+        """
+        pub struct Args {
+            pub argv: Vec<String>,
+            pub grid: Vec<Vec<i32>>,
+        }
+        """
+    )
+    spec = [
+        {"u_field": {"name": "argv", "shape": {"ptr": {"kind": "slice", "len_from": "argc"}}},
+         "i_field": {"name": "argv"}},
+        {"u_field": {"name": "argc", "shape": "scalar"}, "i_field": {"name": "argv.len"}},
+        {"u_field": {"name": "grid",
+                     "shape": {"ptr": {"kind": "slice", "len_from": "rows", "inner_len_from": "cols"}}},
+         "i_field": {"name": "grid"}},
+    ]
+    code = rust_ast_parser.generate_struct_converters(c_def, i_def, json.dumps(spec))
+
+    assert "std::ffi::CStr::from_ptr(*p).to_string_lossy().into_owned()" in code
+    assert ".collect::<Vec<String>>()" in code
+    assert "let len = (c_struct.cols as usize);" in code
+    assert ".collect::<Vec<Vec<i32>>>()" in code
+    # row length comes from the first row, row count from the Vec length
+    assert "idiom_struct.grid.first().map_or(0, |row| row.len())" in code
+    assert "let _rows: libc::c_int = (idiom_struct.grid.len() as libc::c_int);" in code
+    # every string and row is released
+    assert "drop(std::ffi::CString::from_raw(*p as *mut std::ffi::c_char));" in code
+    assert "*p as *mut libc::c_int" in code
+    assert "TODO" not in code
+
+
+def test_generate_fixed_array_converters():
+    c_def = dedent(
+        """
+        #[repr(C)]
+        pub struct CRecord {
+            pub name: [libc::c_char; 64],
+            pub tag: [libc::c_char; 4],
+            pub grid: [[libc::c_int; 3]; 2],
+            pub scores: [libc::c_double; 8],
+        }
+        """
+    )
+    i_def = dedent(
+        """
+        pub struct Record {
+            pub name: String,
+            pub tag: [u8; 4],
+            pub grid: [[i32; 3]; 2],
+            pub scores: Vec<f64>,
+        }
+        """
+    )
+    spec = [
+        {"u_field": {"name": name, "shape": "scalar"}, "i_field": {"name": name}}
+        for name in ["name", "tag", "grid", "scores"]
+    ]
+    code = rust_ast_parser.generate_struct_converters(c_def, i_def, json.dumps(spec))
+
+    # char arrays stop at the NUL terminator and are truncated on the way back
+    assert ".take_while(|c| **c != 0)" in code
+    assert "let mut out = [0 as libc::c_char; 64];" in code
+    assert "idiom_struct.name.bytes().take(64 - 1)" in code
+    # arrays convert element-wise, nested arrays included
+    assert "(c_struct.tag[i0] as u8)" in code
+    assert "(c_struct.grid[i0][i1] as i32)" in code
+    assert "(0..8).map(|i0|" in code
+    assert "if i0 < idiom_struct.scores.len() {" in code
+    assert "TODO" not in code
+
+
+def test_generate_void_pointer_converters():
+    c_def = dedent(
+        """
+        #[repr(C)]
+        pub struct CSlot {
+            pub raw: *mut libc::c_void,
+            pub addr: *const libc::c_void,
+            pub data: *mut libc::c_void,
+        }
+        """
+    )
+    i_def = dedent(
+        """
+        pub struct Slot {
+            pub raw: *mut core::ffi::c_void,
+            pub addr: usize,
+            pub data: Option<Box<dyn std::any::Any>>,
+        }
+        """
+    )
+    spec = [
+        {"u_field": {"name": name, "shape": "scalar"}, "i_field": {"name": name}}
+        for name in ["raw", "addr", "data"]
+    ]
+    code = rust_ast_parser.generate_struct_converters(c_def, i_def, json.dumps(spec))
+
+    # raw pointers and addresses are plain casts
+    assert "raw: c_struct.raw as *mut core::ffi::c_void" in code
+    assert "addr: c_struct.addr as usize" in code
+    assert "idiom_struct.addr as *const libc::c_void" in code
+    # Box<dyn Any> round-trips through a per-struct registry
+    assert "static CSLOT_VOID_PTRS" in code
+    assert "Some(v) => CSlot_register_void_ptr(v) as *mut libc::c_void" in code
+    assert "Some(CSlot_take_void_ptr(c_struct.data as *const core::ffi::c_void))" in code
+    assert "drop(CSlot_take_void_ptr(c_struct.data));" in code
+    assert "TODO" not in code
+
+
+def _linked_node_converters(link_type):
+    c_def = dedent(
+        """
+        #[repr(C)]
+        pub struct CNode {
+            pub value: libc::c_int,
+            pub next: *mut CNode,
+        }
+        """
+    )
+    i_def = dedent(
+        f"""
+        pub struct Node {{
+            pub value: i32,
+            pub next: {link_type},
+        }}
+        """
+    )
+    spec = [
+        {"u_field": {"name": "value", "shape": "scalar"}, "i_field": {"name": "value"}},
+        {"u_field": {"name": "next", "shape": {"ptr": {"kind": "ref"}}}, "i_field": {"name": "next"}},
+    ]
+    return rust_ast_parser.generate_struct_converters(c_def, i_def, json.dumps(spec))
+
+
+def test_generate_self_referential_converters():
+    # owned links recurse through the regular converters
+    code = _linked_node_converters("Option<Box<Node>>")
+    assert "CNode_to_Node_mut(c_struct.next as *mut CNode) as *mut Node" in code
+    assert "free_CNode(c_struct.next as *mut CNode);" in code
+
+    # shared links are memoised per conversion and freed once
+    code = _linked_node_converters("Option<Rc<RefCell<Node>>>")
+    assert "unsafe fn CNode_to_Node_rc(ptr: *mut CNode)" in code
+    assert "unsafe fn Node_rc_to_CNode(" in code
+    assert "let _links = CNodeLinkScope::enter();" in code
+    assert "CNode_free_link(c_struct.next as *mut _);" in code
+
+    # arena links become indices into a Vec of nodes
+    code = _linked_node_converters("Option<usize>")
+    assert "unsafe fn CNode_to_Node_arena(head: *mut CNode) -> (Vec<Node>, Option<usize>)" in code
+    assert "unsafe fn Node_arena_to_CNode(arena: &mut [Node], head: Option<usize>)" in code
+    assert "unsafe fn free_CNode_arena(head: *mut CNode)" in code
+    assert "stack.push((*ptr).next as *mut CNode);" in code
+    assert "TODO" not in code
+
+
+def test_generate_layout_asserts():
+    code = dedent(
+        """
+        #[repr(C)]
+        pub struct CStudent {
+            pub id: libc::c_int,
+            pub gpa: libc::c_double,
+        }
+        #[repr(C)]
+        pub union CValue {
+            pub i: libc::c_int,
+            pub f: libc::c_float,
+        }
+        """
+    )
+    layouts = {"CStudent": {"size": 16, "align": 8}, "CValue": {"size": 4, "align": 4}}
+    asserts = rust_ast_parser.generate_layout_asserts(
+        code, ["CStudent", "CValue"], json.dumps(layouts)
+    )
+    assert "core::mem::size_of::<CStudent>() == 16" in asserts
+    assert "core::mem::align_of::<CStudent>() == 8" in asserts
+    assert "core::mem::size_of::<CValue>() == 4" in asserts
+    assert asserts.count("const _: () = assert!(") == 2
+
+    # anonymous consts survive deduplication
+    assert rust_ast_parser.dedup_items(code + asserts).count("const _") == 2
+
+    with pytest.raises(ValueError):
+        rust_ast_parser.generate_layout_asserts(code, ["CMissing"], json.dumps(layouts))
+    with pytest.raises(ValueError):
+        rust_ast_parser.generate_layout_asserts(code, ["CStudent"], json.dumps({}))
@@ -0,0 +1,74 @@
+import copy
+
+import pytest
+
+from sactor import utils
+from sactor.config_check import ConfigError, check_config, validate_config
+
+ENVIRON = {"OPENAI_API_KEY": "sk-test"}
+
+
+@pytest.fixture
+def config():
+    return copy.deepcopy(utils.load_default_config())
+
+
+def _issues(config, severity="error"):
+    return {issue.path: issue.message for issue in check_config(config, ENVIRON) if issue.severity == severity}
+
+
+def test_default_config_is_valid(config):
+    assert _issues(config) == {}
+    assert _issues(config, "warning") == {}
+    # litellm reads the API key of the default model from the environment
+    warnings = [issue for issue in check_config(config, {}) if issue.severity == "warning"]
+    assert [issue.path for issue in warnings] == ["litellm.model_list[0].litellm_params.api_key"]
+
+
+def test_schema_errors(config):
+    config["general"]["max_translation_attempt"] = 3
+    config["general"]["max_translation_attempts"] = 0
+    config["general"]["timeout_seconds"] = "60"
+    config["logging"]["console_level"] = "VERBOSE"
+    config["cost"]["on_budget_exceeded"] = "stop"
+    config["house_style"] = {"header": "// house style"}
+
+    errors = _issues(config)
+    assert errors["general.max_translation_attempt"] == "unknown key, did you mean `max_translation_attempts`?"
+    assert errors["general.max_translation_attempts"] == "must be at least 1, got 0 (default: 6)"
+    assert errors["general.timeout_seconds"] == 'expected number, got "60" (default: 60)'
+    assert errors["logging.console_level"].startswith('"VERBOSE" is not valid, expected DEBUG')
+    assert errors["cost.on_budget_exceeded"] == 'expected one of "abort", "downgrade", got "stop" (default: "abort")'
+    # sections of plugins are not misspelt ones of sactor
+    assert _issues(config, "warning") == {"house_style": "unknown key, ignored"}
+
+    with pytest.raises(ConfigError, match="general.max_translation_attempts: must be at least 1"):
+        validate_config(config)
+
+
+def test_semantic_errors(config):
+    config["general"]["model"] = "gpt4o"
+    config["ensemble"]["idiomatic"] = ["gpt-4o", "mistral"]
+    config["cost"]["on_budget_exceeded"] = "downgrade"
+    config["general"]["stable_output"] = True
+    config["general"]["sanitizers"] = True
+
+    errors = _issues(config)
+    assert errors["general.model"] == "model `gpt4o` is not a model_name of litellm.model_list, did you mean `gpt-4o`?"
+    assert errors["ensemble.idiomatic[1]"].startswith("model `mistral` is not a model_name of litellm.model_list (")
+    assert errors["cost.downgrade_model"] == 'required with on_budget_exceeded = "downgrade"'
+    assert "general.sanitizers" in errors
+
+    # models are served by the local endpoint instead
+    config["local"]["api_base"] = "http://localhost:11434/v1"
+    config["local"]["max_output_tokens"] = config["local"]["context_window"]
+    errors = _issues(config)
+    assert "general.model" not in errors
+    assert errors["local.max_output_tokens"].startswith("must be less than local.context_window")
+
+
+def test_missing_api_key_warns(config):
+    config["ensemble"]["unidiomatic"] = ["claude-3-5-sonnet-20241022"]
+    warnings = _issues(config, "warning")
+    assert list(warnings) == ["litellm.model_list[2].litellm_params.api_key"]
+    assert "ANTHROPIC_API_KEY is not set" in warnings["litellm.model_list[2].litellm_params.api_key"]
@@ -0,0 +1,49 @@
+import json
+import os
+
+from sactor.test_generator import BenchmarkGenerator
+from sactor.utils import read_file
+
+
+def _generator(tmp_path, feed_as_arguments=True):
+    samples_path = tmp_path / "test_samples.json"
+    samples_path.write_text(json.dumps([
+        {"input": "1 2", "output": "3"},
+        {"input": "say \"hi\"", "output": "hi"},
+    ]))
+    return BenchmarkGenerator(
+        str(samples_path), "/bin/true", "/bin/echo", str(tmp_path / "bench"),
+        feed_as_arguments=feed_as_arguments)
+
+
+def test_generate_bench_crate(tmp_path):
+    crate_dir = _generator(tmp_path).generate()
+
+    manifest = read_file(os.path.join(crate_dir, "Cargo.toml"))
+    assert 'criterion = "0.5"' in manifest
+    assert "harness = false" in manifest
+    bench = read_file(os.path.join(crate_dir, "benches", "compare.rs"))
+    assert 'const C_EXECUTABLE: &str = "/bin/true";' in bench
+    assert 'const RUST_EXECUTABLE: &str = "/bin/echo";' in bench
+    assert "const FEED_AS_ARGUMENTS: bool = true;" in bench
+    assert '"say \\"hi\\""' in bench
+    assert "criterion_main!(benches);" in bench
+
+
+def test_write_bench_report(tmp_path):
+    generator = _generator(tmp_path)
+    generator.generate()
+    criterion_dir = tmp_path / "criterion"
+    for group, c_ns, rust_ns in (("sample_0", 1000.0, 2000.0), ("sample_1", 4000.0, 2000.0)):
+        for name, ns in (("c", c_ns), ("rust", rust_ns)):
+            estimates = criterion_dir / group / name / "new"
+            estimates.mkdir(parents=True)
+            (estimates / "estimates.json").write_text(json.dumps({"mean": {"point_estimate": ns}}))
+
+    report = generator.write_report(str(criterion_dir))
+    assert [row["ratio"] for row in report["samples"]] == [2.0, 0.5]
+    assert abs(report["geomean_ratio"] - 1.0) < 1e-9
+    markdown = read_file(os.path.join(generator.out_dir, "report.md"))
+    assert "| 0 | `1 2` | 1.00 µs | 2.00 µs | 2.00x |" in markdown
+    assert "Geometric mean of Rust / C: 1.00x" in markdown
+    assert json.loads(read_file(os.path.join(generator.out_dir, "report.json")))["samples"][1]["sample"] == 1
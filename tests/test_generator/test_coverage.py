@@ -0,0 +1,78 @@
+import shutil
+
+import pytest
+
+from sactor.test_generator import ExecutableTestGenerator
+from sactor.test_generator.coverage import BranchCoverage, mutate_inputs
+
+C_CODE = '''#include <stdio.h>
+#include <stdlib.h>
+
+int main(int argc, char **argv) {
+    if (argc < 2) {
+        puts("none");
+        return 0;
+    }
+    int n = atoi(argv[1]);
+    if (n > 100)
+        puts("big");
+    else if (n < 0)
+        puts("negative");
+    else
+        puts("small");
+    return 0;
+}
+'''
+
+needs_gcov = pytest.mark.skipif(
+    shutil.which('gcc') is None or shutil.which('gcov') is None, reason='needs gcc and gcov')
+
+
+def test_mutate_inputs():
+    mutants = list(mutate_inputs(['-n 5'], feed_as_arguments=True))
+    assert '-n 6' in mutants
+    assert '-n -2147483648' in mutants
+    assert '-n' in mutants
+    assert '5 -n' in mutants
+    assert '-n -n 5' in mutants
+    assert '-n 5' not in mutants
+
+    mutants = list(mutate_inputs(['a\nb'], feed_as_arguments=False))
+    assert 'b' in mutants
+    assert 'a\na\nb' in mutants
+
+
+@needs_gcov
+def test_branch_coverage(tmp_path):
+    c_file = tmp_path / 'prog.c'
+    c_file.write_text(C_CODE)
+    coverage = BranchCoverage(str(c_file), feed_as_arguments=True, timeout_seconds=10)
+    try:
+        small = coverage.covered('5')
+        big = coverage.covered('500')
+        assert small and big
+        assert small != big
+        assert len(small | big | coverage.covered('-3') | coverage.covered('')) == coverage.total
+    finally:
+        coverage.cleanup()
+
+
+@needs_gcov
+def test_extend_by_coverage(tmp_path):
+    c_file = tmp_path / 'prog.c'
+    c_file.write_text(C_CODE)
+    generator = object.__new__(ExecutableTestGenerator)
+    generator.file_path = str(c_file)
+    generator.feed_as_arguments = True
+    generator.timeout_seconds = 10
+    generator.config = {'test_generator': {}}
+    generator.test_samples = {'5'}
+    generator.test_samples_output = [{'input': '5', 'output': 'small'}]
+    generator._execute_test_sample = lambda sample: f'output of {sample}'
+
+    added = generator.extend_by_coverage()
+    assert added == 2
+    inputs = [s['input'] for s in generator.test_samples_output]
+    assert any(int(i) > 100 for i in inputs[1:])
+    assert any(int(i) < 0 for i in inputs[1:])
+    assert len(generator.test_samples) == 3
@@ -0,0 +1,62 @@
+import json
+import os
+
+from sactor.test_generator import DifferentialDriverGenerator
+from sactor.utils import read_file
+
+
+def test_generate_differential_driver(tmp_path):
+    rust_code = read_file(
+        "tests/c_examples/atoi/result/translated_code_unidiomatic/functions/atoi.rs")
+    generator = DifferentialDriverGenerator(
+        c_file="tests/c_examples/atoi/atoi.c",
+        rust_code=rust_code,
+        out_dir=str(tmp_path),
+    )
+
+    crate_dir = generator.generate("atoi", [{"str_": "42"}, {"str_": " -7"}])
+
+    assert crate_dir == os.path.join(str(tmp_path), "atoi")
+    manifest = read_file(os.path.join(crate_dir, "Cargo.toml"))
+    assert 'name = "atoi-diff"' in manifest
+    assert "serde_json" in manifest
+    assert "libfuzzer" not in manifest
+
+    driver = read_file(os.path.join(crate_dir, "src", "main.rs"))
+    assert "fn sactor_c_atoi(str_: *mut libc::c_char) -> libc::c_int;" in driver
+    assert "serde::Deserialize" in driver
+    assert "fn main()" in driver
+    assert "fuzz_target!" not in driver
+    assert json.loads(read_file(os.path.join(crate_dir, "samples.json")))[1] == {"str_": " -7"}
+    assert os.path.exists(os.path.join(crate_dir, "c_ref", "libsactor_c_ref.a"))
+
+
+def test_run_differential_driver(tmp_path):
+    c_file = tmp_path / "lib.c"
+    c_file.write_text(
+        "int clamp_add(int a, int b) { int s = a + b; return s < 0 ? 0 : s; }\n"
+        "void fill(int *buf, int n, int v) { for (int i = 0; i < n; i++) buf[i] = v; }\n"
+    )
+    # `clamp_add` forgets the clamp; `fill` is correct
+    rust_code = '''
+pub fn clamp_add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+pub unsafe fn fill(buf: *mut i32, n: i32, v: i32) {
+    for i in 0..n as usize {
+        *buf.add(i) = v;
+    }
+}
+'''
+    generator = DifferentialDriverGenerator(
+        c_file=str(c_file), rust_code=rust_code, out_dir=str(tmp_path / "diff"))
+
+    crate_dir = generator.generate("clamp_add", [{"a": 1, "b": 2}, {"a": -5, "b": 2}])
+    summary = generator.run(crate_dir)
+    assert summary["total"] == 2
+    assert summary["diverged"] == [1]
+    assert "result differs" in summary["log"]
+
+    crate_dir = generator.generate("fill", [{"buf": [0, 0, 0], "n": 3, "v": 9}])
+    assert generator.run(crate_dir)["diverged"] == []
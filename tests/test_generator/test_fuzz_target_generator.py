@@ -0,0 +1,38 @@
+import os
+
+from sactor.test_generator import FuzzTargetGenerator
+from sactor.utils import read_file
+
+
+def test_generate_fuzz_crate(tmp_path):
+    rust_code = read_file(
+        "tests/c_examples/atoi/result/translated_code_unidiomatic/functions/atoi.rs")
+    generator = FuzzTargetGenerator(
+        c_file="tests/c_examples/atoi/atoi.c",
+        rust_code=rust_code,
+        out_dir=str(tmp_path),
+        link_args=["-lm"],
+    )
+
+    crate_dir = generator.generate("atoi")
+
+    assert crate_dir == os.path.join(str(tmp_path), "atoi")
+    manifest = read_file(os.path.join(crate_dir, "Cargo.toml"))
+    assert "cargo-fuzz = true" in manifest
+    assert 'libfuzzer-sys = "0.4"' in manifest
+    assert 'path = "fuzz_targets/atoi.rs"' in manifest
+
+    target = read_file(os.path.join(crate_dir, "fuzz_targets", "atoi.rs"))
+    assert target.startswith("#![no_main]")
+    assert "fn sactor_c_atoi(str_: *mut libc::c_char) -> libc::c_int;" in target
+    assert "fuzz_target!" in target
+
+    build_script = read_file(os.path.join(crate_dir, "build.rs"))
+    assert "rustc-link-lib=static=sactor_c_ref" in build_script
+    assert 'rustc-link-lib=m' in build_script
+
+    # the C definition is renamed so it can sit beside the Rust one
+    assert os.path.exists(os.path.join(crate_dir, "c_ref", "libsactor_c_ref.a"))
+    symbols = os.popen(f"nm {os.path.join(crate_dir, 'c_ref', 'c_ref.o')}").read()
+    assert "sactor_c_atoi" in symbols
+    assert "sactor_c_main" in symbols
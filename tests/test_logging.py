@@ -0,0 +1,67 @@
+import json
+import logging
+
+import pytest
+
+from sactor import logging as sactor_logging
+
+
+@pytest.fixture
+def logging_config(tmp_path, monkeypatch):
+    # restored, with the handlers, for the tests that follow
+    monkeypatch.setattr(sactor_logging, "_state", sactor_logging.get_logging_state())
+    config = {"logging": {"console_level": "INFO", "file_level": "DEBUG", "jsonl": True,
+                          "item_logs": True, "color": False}}
+    handlers = list(sactor_logging.get_logger().handlers)
+    yield config, tmp_path / "logs"
+    sactor_logging.get_logger().handlers[:] = handlers
+
+
+def test_span_fields_and_item_log(logging_config):
+    config, log_dir = logging_config
+    state = sactor_logging.configure_logging(config, log_dir_override=str(log_dir), force_reconfigure=True)
+    logger = sactor_logging.get_logger("tests")
+
+    with sactor_logging.span("translation", unit="/src/add.c"):
+        with sactor_logging.span("unidiomatic translation", stage="unidiomatic"):
+            with sactor_logging.span("function add", item_log=True, item="add", item_type="function"):
+                logger.info("Translating add")
+            with pytest.raises(RuntimeError):
+                with sactor_logging.span("function sub", item_log=True, item="sub", item_type="function"):
+                    raise RuntimeError("boom")
+        logger.info("Combining")
+
+    records = [json.loads(line) for line in open(state.jsonl_log_path)]
+    translating = next(r for r in records if r["message"] == "Translating add")
+    assert translating["unit"] == "/src/add.c"
+    assert (translating["stage"], translating["item"], translating["item_type"]) == ("unidiomatic", "add", "function")
+    assert translating["span"] == "translation/unidiomatic translation/function add"
+    combining = next(r for r in records if r["message"] == "Combining")
+    assert "stage" not in combining and "item" not in combining
+
+    ends = {r["message"].split(" in ")[0]: r for r in records if r.get("event") == "span_end"}
+    assert ends["Finished function add"]["span_status"] == "ok"
+    assert ends["Finished function sub"]["span_status"] == "error"
+    assert ends["Finished function sub"]["level"] == "WARNING"
+    assert ends["Finished translation"]["duration_seconds"] >= 0
+
+    with open(state.text_log_path) as f:
+        assert "[unidiomatic/add] | Translating add" in f.read()
+    item_log = log_dir / "items" / "add.c" / "unidiomatic" / "add.log"
+    assert "Translating add" in item_log.read_text()
+    assert "Combining" not in item_log.read_text()
+    assert (log_dir / "items" / "add.c" / "unidiomatic" / "sub.log").exists()
+
+
+def test_verbosity(logging_config):
+    config, log_dir = logging_config
+    levels = {}
+    for verbosity in (-1, 1, 2, 10, -10):
+        state = sactor_logging.configure_logging(
+            config, log_dir_override=str(log_dir), verbosity=verbosity, force_reconfigure=True)
+        levels[verbosity] = state.console_level
+    assert levels[-1] == logging.WARNING
+    assert levels[1] == logging.getLevelName("RESPONSE")
+    assert levels[2] == logging.getLevelName("PROMPT")
+    assert levels[10] == logging.DEBUG
+    assert levels[-10] == logging.CRITICAL
@@ -0,0 +1,77 @@
+import copy
+
+import pytest
+
+from sactor import utils
+from sactor.c_parser import CParser
+from sactor.planner import (FEEDBACK_TOKENS, ItemMetrics, PlannedItem, Planner,
+                            TranslationPlan, UnitPlan, difficulty,
+                            difficulty_level, estimate_queries, format_plan)
+
+
+def _count_tokens(text):
+    return len(text.split())
+
+
+@pytest.fixture
+def config():
+    config = copy.deepcopy(utils.load_default_config())
+    config["cost"]["prices"] = {"gpt-4o": {"input": 2.5, "output": 10.0}}
+    return config
+
+
+def test_difficulty():
+    small = ItemMetrics(lines=8, c_tokens=40, pointers=2)
+    assert difficulty(small) == 1.8
+    assert difficulty_level(difficulty(small)) == ("easy", 1)
+
+    tangled = ItemMetrics(lines=60, c_tokens=500, pointers=12, globals=2, gotos=3, notes=["variadic"])
+    assert difficulty(tangled) == 6 + 6 + 4 + 12 + 5
+    assert difficulty_level(difficulty(tangled)) == ("hard", 3)
+
+
+def test_estimate_queries():
+    metrics = ItemMetrics(lines=10, c_tokens=100)
+    first, retry = estimate_queries("unidiomatic", metrics, context_tokens=60, attempts=2)
+    assert first[1] == retry[1] == 140
+    # retries give back the failed translation and its errors
+    assert retry[0] == first[0] + 140 + FEEDBACK_TOKENS
+    # the idiomatic prompt holds the unidiomatic translation
+    (idiomatic,) = estimate_queries("idiomatic", metrics, context_tokens=60, attempts=1)
+    assert idiomatic[0] > first[0] + 140
+
+
+def test_plan_unit(config):
+    file_path = "tests/c_examples/course_manage/course_manage.c"
+    planner = Planner(config, count_tokens=_count_tokens)
+    unit = planner.plan_unit(file_path, CParser(file_path))
+
+    names = [item.name for item in unit.items]
+    assert names.index("Course") < names.index("Student")
+    assert names.index("updateStudentInfo") < names.index("main")
+    assert unit.cycles == []
+    main = unit.items[names.index("main")]
+    assert main.calls == ["printUsage", "updateStudentInfo"]
+    assert main.metrics.pointers > 0
+    assert all(item.cost > 0 for item in unit.items)
+
+    unidiomatic = Planner(config, unidiomatic_only=True, count_tokens=_count_tokens)
+    unidiomatic.plan_unit(file_path, CParser(file_path))
+    assert set(unidiomatic.cost.report()["by_stage"]) == {"unidiomatic"}
+
+
+def test_format_plan(config):
+    planner = Planner(config, count_tokens=_count_tokens)
+    planner.cost.unit, planner.cost.stage, planner.cost.item = "add.c", "unidiomatic", "add"
+    planner.cost.record("gpt-4o", 1000, 100)
+    item = PlannedItem("function", "add", ItemMetrics(lines=3, c_tokens=20), ["helper"], 0.3, "easy", 1,
+                       input_tokens=1000, output_tokens=100, cost=0.0035)
+    plan = TranslationPlan([UnitPlan("add.c", [item], [["even", "odd"]])], planner.stages, planner.models,
+                           planner.cost.report())
+
+    text = format_plan(plan)
+    assert "$0.0035  helper" in text
+    assert "Circular dependency between even, odd" in text
+    assert "Items: 1 (1 easy, 0 medium, 0 hard)" in text
+    assert "Expected queries: 1, 1000 prompt and 100 response tokens, $0.00" in text
+    assert plan.to_dict()["units"][0]["items"][0]["metrics"]["lines"] == 3
@@ -0,0 +1,77 @@
+import pytest
+
+from sactor.plugins import HookContext, Plugins, PluginVeto
+
+PLUGIN_CODE = '''
+from sactor.plugins import PluginVeto
+
+
+class HouseStyle:
+    def __init__(self, config):
+        self.header = config["house_style"]["header"]
+
+    def pre_verify(self, code, context):
+        return f"{self.header}\\n{code}"
+
+    def post_idiomatic(self, code, context):
+        if ".unwrap()" in code:
+            raise PluginVeto("handle errors instead of calling unwrap()")
+
+
+def pre_verify(code, context):
+    return code.replace("\\t", "    ")
+'''
+
+
+@pytest.fixture
+def plugin_config(tmp_path):
+    path = tmp_path / "house_style.py"
+    path.write_text(PLUGIN_CODE)
+    return {
+        "house_style": {"header": "// house style"},
+        "plugins": {
+            "modules": [f"{path}:HouseStyle", str(path)],
+            "entry_points": False,
+            "disabled": [],
+        },
+    }
+
+
+def test_plugins_transform_and_veto(plugin_config):
+    plugins = Plugins.load(plugin_config)
+    assert len(plugins.plugins) == 2
+    context = HookContext("idiomatic", "function", "add", plugin_config)
+
+    assert plugins.run("pre_verify", "fn add() {\n\t1\n}", context) == "// house style\nfn add() {\n    1\n}"
+    assert plugins.run("post_idiomatic", "fn add() {}", context) == "fn add() {}"
+    # hooks a plugin does not implement leave the code as is
+    assert plugins.run("post_unidiomatic", "fn add() {}", context) == "fn add() {}"
+    with pytest.raises(PluginVeto, match="HouseStyle: handle errors"):
+        plugins.run("post_idiomatic", "fn add() { f().unwrap() }", context)
+
+
+def test_plugins_config(plugin_config, tmp_path):
+    plugin_config["plugins"]["disabled"] = plugin_config["plugins"]["modules"][:1]
+    assert len(Plugins.load(plugin_config).plugins) == 1
+
+    plugin_config["plugins"]["modules"] = [str(tmp_path / "missing.py")]
+    with pytest.raises(ValueError, match="Failed to load plugin"):
+        Plugins.load(plugin_config)
+
+    broken = tmp_path / "broken.py"
+    broken.write_text("def pre_verify(code, context):\n    return 1\n")
+    plugins = Plugins.load({"plugins": {"modules": [str(broken)], "entry_points": False}})
+    with pytest.raises(RuntimeError, match="returned int"):
+        plugins.run("pre_verify", "", HookContext(None, "function", "add", {}))
+
+
+def test_post_hooks_cannot_change_verified_code(tmp_path):
+    path = tmp_path / "breaking.py"
+    path.write_text("def post_unidiomatic(code, context):\n    return code.replace('a + b', 'a +')\n")
+    plugins = Plugins.load({"plugins": {"modules": [str(path)], "entry_points": False}})
+    context = HookContext("unidiomatic", "function", "add", {})
+    # the verified translation would be saved as is, so the broken one never is
+    with pytest.raises(RuntimeError, match="changed the code in post_unidiomatic"):
+        plugins.run("post_unidiomatic", "fn add(a: i32, b: i32) -> i32 { a + b }", context)
+    # returning the code unchanged is allowed
+    assert plugins.run("post_unidiomatic", "fn one() -> i32 { 1 }", context) == "fn one() -> i32 { 1 }"
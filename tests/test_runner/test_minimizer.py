@@ -0,0 +1,39 @@
+from sactor.test_runner.minimizer import InputMinimizer, ddmin, shrink_integer
+
+
+def test_ddmin():
+    # the failure needs both 3 and 7
+    assert ddmin(list(range(10)), lambda items: 3 in items and 7 in items) == [3, 7]
+    assert ddmin(list('abcdef'), lambda items: True) == []
+    assert ddmin([1], lambda items: bool(items)) == [1]
+
+
+def test_shrink_integer():
+    # anything at or above 37 fails
+    assert shrink_integer('1000', lambda token: int(token) >= 37) == '37'
+    assert shrink_integer('-64', lambda token: int(token) <= -5) == '-5'
+    assert shrink_integer('12', lambda token: True) == '0'
+
+
+def test_minimize_arguments():
+    def diverges(sample_input):
+        # the translation mishandles any argument over 100 following `-n`
+        args = sample_input.split()
+        return any(a == '-n' and b.isdigit() and int(b) > 100 for a, b in zip(args, args[1:]))
+
+    minimizer = InputMinimizer(diverges, feed_as_arguments=True)
+    assert minimizer.minimize('-v -x foo -n 5000 bar baz') == '-n 101'
+
+
+def test_minimize_stdin():
+    def diverges(sample_input):
+        return any('xyz' in line for line in sample_input.split('\n'))
+
+    minimizer = InputMinimizer(diverges, feed_as_arguments=False)
+    assert minimizer.minimize('hello world\nfoo wxyzw bar\nlast line') == 'xyz'
+
+
+def test_minimize_budget():
+    minimizer = InputMinimizer(lambda sample_input: True, feed_as_arguments=True, max_checks=3)
+    minimizer.minimize('a b c d e f g h')
+    assert minimizer.checks == 3
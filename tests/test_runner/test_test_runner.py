@@ -2,6 +2,8 @@ import json
 import os
 import tempfile
 
+import pytest
+
 from sactor.test_runner import ExecutableTestRunner
 from sactor.test_runner import TestRunnerResult as Result
 from sactor.verifier import UnidiomaticVerifier, VerifyResult
@@ -73,3 +75,150 @@ def test_test_runner_e2e_2(c_file_executable_scanf, config):
         verifier = UnidiomaticVerifier(f'{tmpdirname}/test_task.json', config=config)
         result = verifier._run_tests(c_file_executable_scanf[0])
         assert result[0] == VerifyResult.SUCCESS
+
+
+VALGRIND_LOG = '''==42== Invalid read of size 4
+==42==    at 0x109189: main (main.c:5)
+==42== Invalid write of size 1
+==42==    at 0x1091A0: main (main.c:6)
+==42== LEAK SUMMARY:
+==42==    definitely lost: 1,024 bytes in 2 blocks
+==42==    indirectly lost: 0 bytes in 0 blocks
+==42==      possibly lost: 16 bytes in 1 blocks
+==42== ERROR SUMMARY: 4 errors from 4 contexts (suppressed: 0 from 0)
+'''
+
+
+def test_parse_valgrind_log():
+    summary = ExecutableTestRunner.parse_valgrind_log(VALGRIND_LOG)
+    assert summary == {
+        'errors': 4,
+        'invalid_reads': 1,
+        'invalid_writes': 1,
+        'invalid_frees': 0,
+        'uninitialised_values': 0,
+        'definitely_lost_bytes': 1024,
+        'indirectly_lost_bytes': 0,
+        'possibly_lost_bytes': 16,
+    }
+
+
+def test_test_runner_valgrind(tmp_path, monkeypatch):
+    # a fake valgrind that writes a fixed log and runs the program
+    fake_valgrind = tmp_path / 'valgrind'
+    fake_valgrind.write_text(f'''#!/bin/sh
+for arg in "$@"; do
+    shift
+    case "$arg" in
+        --log-file=*) printf '%s' '{VALGRIND_LOG}' > "${{arg#--log-file=}}" ;;
+        --suppressions=*) echo "$arg" > {tmp_path}/suppressions_arg ;;
+        --) break ;;
+    esac
+done
+exec "$@"
+''')
+    fake_valgrind.chmod(0o755)
+    monkeypatch.setenv('PATH', f'{tmp_path}:{os.environ["PATH"]}')
+
+    test_samples_path = tmp_path / 'test_samples.json'
+    test_samples_path.write_text(json.dumps([{'input': 'hello', 'output': 'hello'}]))
+    save_path = tmp_path / 'result.json'
+
+    runner = ExecutableTestRunner(
+        str(test_samples_path),
+        '/bin/echo',
+        feed_as_arguments=True,
+        valgrind=True,
+        valgrind_suppressions='tests/test_runner/missing.supp',
+    )
+    result, _ = runner.run_test(0, str(save_path))
+    assert result == Result.PASSED
+    assert runner.valgrind_summary['errors'] == 4
+    saved = json.loads(save_path.read_text())
+    assert saved[0]['valgrind']['definitely_lost_bytes'] == 1024
+    assert 'tests/test_runner/missing.supp' in (tmp_path / 'suppressions_arg').read_text()
+
+
+def test_numeric_outputs_match():
+    match = ExecutableTestRunner.numeric_outputs_match
+    assert match('pi = 3.14159\n', 'pi = 3.141592\n', 1e-5, 0.0)
+    assert not match('pi = 3.14159\n', 'pi = 3.141592\n', 1e-7, 0.0)
+    assert match('x: 1.0000001e10', 'x: 1e10', 0.0, 1e-6)
+    assert match('nan inf -inf', '-nan inf -inf', 1e-6, 0.0)
+    # integers and the surrounding text still have to match exactly
+    assert not match('count 3, mean 2.5', 'count 4, mean 2.5', 1.0, 0.0)
+    assert not match('mean: 2.5', 'avg: 2.5', 1.0, 0.0)
+    assert not match('1.0 2.0', '1.0', 1.0, 0.0)
+
+
+def test_test_runner_float_tolerance(tmp_path):
+    test_samples_path = tmp_path / 'test_samples.json'
+    test_samples_path.write_text(json.dumps([{'input': '0.1000001', 'output': '0.1'}]))
+
+    runner = ExecutableTestRunner(str(test_samples_path), '/bin/echo', feed_as_arguments=True)
+    result, diff = runner.run_test(0)
+    assert result == Result.FAILED
+    assert diff
+
+    runner = ExecutableTestRunner(
+        str(test_samples_path), '/bin/echo', feed_as_arguments=True, abs_tolerance=1e-6)
+    result, _ = runner.run_test(0)
+    assert result == Result.PASSED
+
+
+def test_normalize_output():
+    normalize = ExecutableTestRunner.normalize_output
+    output = 'started 2024-05-01 12:30:00.123 pid 4242\nnode at 0x7ffd5e8a, next (nil)\n'
+    normalized = normalize(output, ['timestamps', 'addresses', {'pattern': r'pid \d+', 'replacement': 'pid N'}])
+    assert normalized == 'started <TIMESTAMP> pid N\nnode at <ADDR>, next <ADDR>\n'
+    assert normalize('a \t b   c', ['whitespace']) == 'a b c'
+    assert normalize('wrote /tmp/tmpab12_x/out.txt', ['temp_paths']) == 'wrote <TMP>'
+    with pytest.raises(ValueError):
+        normalize('', ['dates'])
+
+
+def test_test_runner_normalizers(tmp_path):
+    test_samples_path = tmp_path / 'test_samples.json'
+    test_samples_path.write_text(json.dumps([{
+        'input': 'ptr 0x55d0c0a2b2a0',
+        'output': 'ptr 0x5601f3e4c2a0',
+        'normalizers': ['addresses'],
+    }]))
+    runner = ExecutableTestRunner(str(test_samples_path), '/bin/echo', feed_as_arguments=True)
+    result, _ = runner.run_test(0)
+    assert result == Result.PASSED
+
+
+def test_test_runner_sample_env(tmp_path):
+    test_samples_path = tmp_path / 'test_samples.json'
+    test_samples_path.write_text(json.dumps([
+        {'input': 'GREETING', 'output': 'hello', 'env': {'GREETING': 'hello'}},
+    ]))
+    runner = ExecutableTestRunner(str(test_samples_path), '/usr/bin/printenv', feed_as_arguments=True)
+    result, _ = runner.run_test(0)
+    assert result == Result.PASSED
+
+
+def test_test_runner_minimize(tmp_path):
+    # the "translation" drops the last character of arguments longer than 3
+    reference = tmp_path / 'reference'
+    reference.write_text('#!/bin/sh\necho "$@"\n')
+    target = tmp_path / 'target'
+    target.write_text('#!/bin/sh\nfor a in "$@"; do\n'
+                      '  if [ ${#a} -gt 3 ]; then a=${a%?}; fi\n'
+                      '  printf "%s " "$a"\ndone\necho\n')
+    for script in (reference, target):
+        script.chmod(0o755)
+    test_samples_path = tmp_path / 'test_samples.json'
+    test_samples_path.write_text(json.dumps([{'input': 'ab cd hello xy', 'output': 'ab cd hello xy'}]))
+    save_path = tmp_path / 'result.json'
+
+    runner = ExecutableTestRunner(
+        str(test_samples_path), str(target), feed_as_arguments=True, minimize_reference=str(reference))
+    result, _ = runner.run_test(0, str(save_path))
+    assert result == Result.FAILED
+    # a single argument of four characters, taken from `hello`
+    assert len(runner.minimized['input']) == 4
+    assert set(runner.minimized['input']) <= set('hello')
+    saved = json.loads(save_path.read_text())
+    assert saved[0]['minimized']['expected_output'] == runner.minimized['input']
@@ -1,15 +1,21 @@
+import json
 import os
 
 import pytest
 
 from sactor.combiner import CombineResult
+from sactor.llm import CostTracker
 from sactor.sactor import Sactor
+from sactor.translator.checkpoint import Checkpoint
 from sactor.translator.translator_types import TranslateResult
 
 
 class DummyLLM:
     def __init__(self):
         self.calls = []
+        self.costed_input_tokens = []
+        self.costed_output_tokens = []
+        self.cost = CostTracker({})
 
     def statistic(self, path):
         self.calls.append(path)
@@ -27,9 +33,19 @@ class DummyCombiner:
         return CombineResult.SUCCESS, None
 
 
+class DummyCParser:
+    def extract_function_code(self, name):
+        return f"int {name}(void) {{ return 0; }}\n"
+
+    extract_struct_union_definition_code = extract_function_code
+    extract_enum_definition_code = extract_function_code
+    extract_global_var_definition_code = extract_function_code
+
+
 class DummyTranslator:
     def __init__(self, tmp_path):
         self.failure_info_path = str(tmp_path / "failure.json")
+        self.failure_info = {}
         self.saved = []
         self.summary = []
 
@@ -45,11 +61,15 @@ def make_sactor(tmp_path, continue_flag, idiomatic_result):
     sactor.idiomatic_only = False
     sactor.unidiomatic_only = False
     sactor.continue_run_when_incomplete = continue_flag
+    sactor.input_file = "input.c"
     sactor.result_dir = str(tmp_path)
     sactor.llm_stat = str(tmp_path / "llm_stat.json")
     sactor.llm = DummyLLM()
     sactor.combiner = DummyCombiner()
+    sactor.c_parser = DummyCParser()
     sactor.c2rust_translation = None
+    sactor.processed_compile_commands = []
+    sactor.checkpoint = Checkpoint(str(tmp_path / "checkpoint.json"), "input.c")
 
     unidiomatic_translator = DummyTranslator(tmp_path)
     idiomatic_translator = DummyTranslator(tmp_path)
@@ -68,11 +88,15 @@ def make_sactor(tmp_path, continue_flag, idiomatic_result):
 
 def make_base_sactor(tmp_path):
     sactor = object.__new__(Sactor)
+    sactor.input_file = "input.c"
     sactor.result_dir = str(tmp_path)
     sactor.llm_stat = str(tmp_path / "llm_stat.json")
     sactor.llm = DummyLLM()
     sactor.combiner = DummyCombiner()
+    sactor.c_parser = DummyCParser()
     sactor.c2rust_translation = None
+    sactor.processed_compile_commands = []
+    sactor.checkpoint = Checkpoint(str(tmp_path / "checkpoint.json"), "input.c")
     return sactor
 
 
@@ -146,3 +170,51 @@ def test_idiomatic_continue_flag_raises_without_flag(tmp_path):
     assert len(sactor.combiner.calls) == 1
     assert sactor.combiner.calls[0][1] is False
     assert idiomatic_translator.summary == ["Idiomatic"]
+    summary = json.loads((tmp_path / "summary.json").read_text())
+    assert summary["status"] == "error"
+    assert summary["units"][0]["error"].startswith("Failed to translate idiomatic code")
+
+
+def test_resume_skips_completed_stage(tmp_path):
+    sactor, _, _ = make_sactor(tmp_path, False, TranslateResult.SUCCESS)
+    sactor.run()
+    assert sactor.checkpoint.is_completed("unidiomatic")
+    assert sactor.checkpoint.is_completed("idiomatic")
+
+    resumed, _, _ = make_sactor(tmp_path, False, TranslateResult.SUCCESS)
+    resumed.checkpoint = Checkpoint.load(str(tmp_path / "checkpoint.json"), "input.c")
+    resumed._run_unidomatic_translation = lambda: (_ for _ in ()).throw(
+        AssertionError("completed stage should not run"))
+    resumed._run_idiomatic_translation = resumed._run_unidomatic_translation
+
+    resumed.run()
+
+    assert resumed.combiner.calls == []
+    assert resumed.llm.calls == []
+
+
+def test_summary_keeps_items_of_skipped_stages(tmp_path):
+    add = {"type": "function", "errors": [], "status": "success", "attempts": [1]}
+    sactor = make_base_sactor(tmp_path)
+    sactor.idiomatic_only = False
+    sactor.unidiomatic_only = True
+    sactor.continue_run_when_incomplete = False
+    unidiomatic_translator = DummyTranslator(tmp_path)
+    unidiomatic_translator.failure_info = {"add": add}
+    sactor._run_unidomatic_translation = lambda: (TranslateResult.SUCCESS, unidiomatic_translator)
+    sactor.run()
+
+    sactor = make_base_sactor(tmp_path)
+    sactor.idiomatic_only = True
+    sactor.unidiomatic_only = False
+    sactor.continue_run_when_incomplete = False
+    idiomatic_translator = DummyTranslator(tmp_path)
+    idiomatic_translator.failure_info = {"add": add}
+    sactor._run_idiomatic_translation = lambda: (TranslateResult.SUCCESS, idiomatic_translator)
+    sactor.run()
+
+    summary = json.loads((tmp_path / "summary.json").read_text())
+    assert summary["status"] == "success"
+    assert [(i["stage"], i["name"]) for i in summary["units"][0]["items"]] == [
+        ("unidiomatic", "add"), ("idiomatic", "add")]
+    assert summary["stages"]["idiomatic"]["test_pass_rate"] == 1.0
@@ -0,0 +1,161 @@
+import json
+import os
+from types import SimpleNamespace
+
+import pytest
+
+from sactor.combiner import CombineResult
+from sactor.llm import CostTracker
+from sactor.sactor import Sactor
+from sactor.translator import Translator
+from sactor.translator.checkpoint import Checkpoint
+from sactor.translator.translator_types import TranslateResult
+
+
+class DummyLLM:
+    def __init__(self):
+        self.calls = []
+        self.costed_input_tokens = []
+        self.costed_output_tokens = []
+        self.cost = CostTracker({})
+
+    def statistic(self, path):
+        self.calls.append(path)
+
+    def reset_statistics(self):
+        pass
+
+
+class DummyCombiner:
+    def __init__(self, result=CombineResult.SUCCESS):
+        self.calls = []
+        self.result = result
+
+    def combine(self, path, is_idiomatic):
+        self.calls.append((path, is_idiomatic))
+        with open(os.path.join(path, "combined.rs"), "w") as f:
+            f.write("// recombined\n")
+        return self.result, None
+
+
+class DummyTranslator(Translator):
+    """Writes `code` as the translation of a function, or fails without it."""
+
+    def __init__(self, result_path, code):
+        super().__init__(DummyLLM(), None, {"general": {"max_translation_attempts": 3}}, result_path=result_path)
+        self.failure_info_path = os.path.join(result_path, "unidiomatic_failure_info.json")
+        if os.path.isfile(self.failure_info_path):
+            with open(self.failure_info_path) as f:
+                self.failure_info = json.load(f)
+        self.translated_function_path = os.path.join(result_path, "translated_code_unidiomatic", "functions")
+        self.code = code
+
+    def _translate_enum_impl(self, enum, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_global_vars_impl(self, global_var, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_struct_impl(self, struct_union, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_function_impl(self, function, verify_result=(None, None), error_translation=None, attempts=0):
+        self.init_failure_info("function", function.name)
+        path = os.path.join(self.translated_function_path, f"{function.name}.rs")
+        assert not os.path.exists(path)
+        self.failure_info_set_attempts(function.name, 1)
+        if self.code is None:
+            self.append_failure_info(function.name, "COMPILE_ERROR", "does not compile", "fn add() {")
+            return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        with open(path, "w") as f:
+            f.write(self.code)
+        self.mark_translation_success("function", function.name)
+        return TranslateResult.SUCCESS
+
+
+def make_sactor(tmp_path, code, combine_result=CombineResult.SUCCESS):
+    stage_dir = tmp_path / "translated_code_unidiomatic"
+    (stage_dir / "functions").mkdir(parents=True)
+    (stage_dir / "functions" / "add.rs").write_text("// old add\n")
+    (stage_dir / "functions" / "main.rs").write_text("// main\n")
+    (stage_dir / "combined.rs").write_text("// old combined\n")
+    (tmp_path / "unidiomatic_failure_info.json").write_text(json.dumps({
+        "add": {"type": "function", "errors": [], "status": "success", "attempts": [2]},
+        "main": {"type": "function", "errors": [], "status": "success", "attempts": [1]},
+    }))
+
+    sactor = object.__new__(Sactor)
+    sactor.idiomatic_only = False
+    sactor.unidiomatic_only = True
+    sactor.input_file = "input.c"
+    sactor.result_dir = str(tmp_path)
+    sactor.llm_stat = str(tmp_path / "llm_stat.json")
+    sactor.llm = DummyLLM()
+    sactor.combiner = DummyCombiner(combine_result)
+    sactor.processed_compile_commands = []
+    sactor.checkpoint = Checkpoint(str(tmp_path / "checkpoint.json"), "input.c")
+    functions = {"add": SimpleNamespace(name="add", struct_dependencies=[], function_dependencies=[])}
+    sactor.c_parser = SimpleNamespace(
+        get_function_info=lambda name: functions[name],
+        extract_function_code=lambda name: f"int {name}(void);\n",
+        extract_struct_union_definition_code=lambda name: f"struct {name};\n",
+        extract_enum_definition_code=lambda name: f"enum {name};\n",
+        extract_global_var_definition_code=lambda name: f"int {name};\n",
+    )
+    sactor._new_unidiomatic_translator = lambda: DummyTranslator(str(tmp_path), code)
+    sactor._emit_crate = lambda: None
+    return sactor
+
+
+def test_retranslate_replaces_function(tmp_path):
+    sactor = make_sactor(tmp_path, "// new add\n")
+
+    assert sactor.retranslate_function("add") == TranslateResult.SUCCESS
+
+    stage_dir = tmp_path / "translated_code_unidiomatic"
+    assert (stage_dir / "functions" / "add.rs").read_text() == "// new add\n"
+    assert (stage_dir / "functions" / "main.rs").read_text() == "// main\n"
+    assert (stage_dir / "combined.rs").read_text() == "// recombined\n"
+    assert sactor.combiner.calls == [(str(stage_dir), False)]
+    failure_info = json.loads((tmp_path / "unidiomatic_failure_info.json").read_text())
+    assert failure_info["add"]["attempts"] == [2, 1]
+    assert failure_info["add"]["status"] == "success"
+    assert sactor.checkpoint.stages["unidiomatic"]["items"]["add"]["status"] == "success"
+    assert sactor.llm.calls == [str(tmp_path / "llm_stat_add_unidiomatic.json")]
+    results = json.loads((tmp_path / "results.json").read_text())
+    assert [(r["name"], r["attempts"], r["passed"]) for r in results["results"]] == [
+        ("add", 3, True), ("main", 1, True)]
+
+
+def test_retranslate_keeps_previous_translation_on_failure(tmp_path):
+    sactor = make_sactor(tmp_path, None)
+
+    assert sactor.retranslate_function("add") == TranslateResult.MAX_ATTEMPTS_EXCEEDED
+
+    stage_dir = tmp_path / "translated_code_unidiomatic"
+    assert (stage_dir / "functions" / "add.rs").read_text() == "// old add\n"
+    assert (stage_dir / "combined.rs").read_text() == "// old combined\n"
+    assert sactor.combiner.calls == []
+    failure_info = json.loads((tmp_path / "unidiomatic_failure_info.json").read_text())
+    assert failure_info["add"]["status"] == "success"
+    assert failure_info["add"]["errors"][0]["type"] == "COMPILE_ERROR"
+
+
+def test_retranslate_restores_when_combine_fails(tmp_path):
+    sactor = make_sactor(tmp_path, "// new add\n", CombineResult.COMPILE_FAILED)
+
+    with pytest.raises(ValueError, match="Failed to combine"):
+        sactor.retranslate_function("add")
+
+    stage_dir = tmp_path / "translated_code_unidiomatic"
+    assert (stage_dir / "functions" / "add.rs").read_text() == "// old add\n"
+    assert (stage_dir / "combined.rs").read_text() == "// old combined\n"
+
+
+def test_retranslate_needs_earlier_translation(tmp_path):
+    sactor = make_sactor(tmp_path, "// new add\n")
+    sactor.unidiomatic_only = False
+    sactor.idiomatic_only = True
+
+    with pytest.raises(ValueError, match="No earlier translation"):
+        sactor.retranslate_function("add")
@@ -2,7 +2,9 @@ from sactor.type_normalization import (
     get_libc_scalar_map,
     get_libc_scalar_pairs,
     iter_numeric_primitives,
+    load_scalar_map,
     map_libc_scalar,
+    register_scalar_mapping,
 )
 
 
@@ -24,3 +26,16 @@ def test_iter_numeric_primitives_order():
     assert primitives[0] == "u8"
     assert primitives[-1] == "f64"
     assert len(primitives) == len(set(primitives))
+
+
+def test_load_scalar_map_registers_platform_typedefs(tmp_path):
+    scalar_map = tmp_path / "platform_scalars.txt"
+    scalar_map.write_text("# Win32 typedefs\nSACTOR_TEST_DWORD = u32\nlibc::sactor_test_time_t = i64\n")
+
+    assert load_scalar_map(str(scalar_map)) == 2
+    assert map_libc_scalar("SACTOR_TEST_DWORD") == "u32"
+    assert map_libc_scalar("sactor_test_time_t") == "i64"
+    assert get_libc_scalar_map()["libc::sactor_test_time_t"] == "i64"
+
+    register_scalar_mapping("SACTOR_TEST_WORD", "u16")
+    assert map_libc_scalar("SACTOR_TEST_WORD") == "u16"
@@ -0,0 +1,56 @@
+import csv
+import io
+import os
+
+import pytest
+
+from sactor.unsafe_metrics import collect_metrics, format_metrics, metrics_csv
+
+COMBINED_DIR = "tests/c_examples/cmake_multi/result/combined"
+
+
+def test_collect_metrics():
+    report = collect_metrics(COMBINED_DIR)
+    files = {f.path: f for f in report.files}
+    assert len(files) == 6
+
+    stats = files[os.path.join("unidiomatic", "cmake_multi", "src", "stats.rs")]
+    assert stats.unsafe_fraction == 1.0
+    assert [function.name for function in stats.functions] == ["average", "max_value"]
+    assert sum(function.tokens for function in stats.functions) == stats.tokens
+    assert files[os.path.join("idiomatic", "cmake_multi", "src", "stats.rs")].unsafe_tokens == 0
+    assert report.unsafe_tokens == sum(f.unsafe_tokens for f in report.files)
+    assert 0 < report.unsafe_fraction < 1
+
+    text = format_metrics(report)
+    assert f"Total: {report.unsafe_tokens} of {report.tokens} tokens unsafe" in text
+    assert "max_value" in text
+    assert "max_value" not in format_metrics(report, functions=False)
+
+
+def test_collect_metrics_skips_and_reports(tmp_path):
+    (tmp_path / "src").mkdir()
+    (tmp_path / "src" / "lib.rs").write_text(
+        "fn safe(a: i32) -> i32 { a + 1 }\n"
+        "unsafe fn raw(p: *const i32) -> i32 { *p }\n")
+    (tmp_path / "src" / "broken.rs").write_text("fn broken( {\n")
+    (tmp_path / "target" / "debug").mkdir(parents=True)
+    (tmp_path / "target" / "debug" / "build.rs").write_text("fn main() {}\n")
+
+    report = collect_metrics(str(tmp_path))
+    assert [f.path for f in report.files] == [os.path.join("src", "broken.rs"), os.path.join("src", "lib.rs")]
+    broken, lib = report.files
+    assert broken.error is not None
+    assert lib.unsafe_tokens == {f.name: f for f in lib.functions}["raw"].tokens
+
+    rows = list(csv.DictReader(io.StringIO(metrics_csv(report))))
+    assert [(row["file"], row["function"]) for row in rows] == [
+        (os.path.join("src", "broken.rs"), ""),
+        (os.path.join("src", "lib.rs"), ""),
+        (os.path.join("src", "lib.rs"), "raw"),
+        (os.path.join("src", "lib.rs"), "safe"),
+    ]
+    assert "1 could not be parsed" in format_metrics(report)
+
+    with pytest.raises(FileNotFoundError):
+        collect_metrics(str(tmp_path / "missing"))
@@ -0,0 +1,77 @@
+import json
+
+from sactor.translator import TranslateResult
+from sactor.watcher import WATCH_STATE_FILE, Watcher, plan_sync
+
+
+def _function(hash_, signature="int (int)", calls=()):
+    return {"hash": hash_, "signature": signature, "calls": list(calls)}
+
+
+def _fingerprint(functions, types=None):
+    return {"functions": functions, "types": types or {"struct point": "p1"}, "files": []}
+
+
+BASE = _fingerprint({
+    "add": _function("a1"),
+    "twice": _function("t1", calls=["add"]),
+    "main": _function("m1", signature="int (void)", calls=["twice"]),
+})
+
+
+def test_plan_sync():
+    assert plan_sync(None, BASE).full_reason is not None
+    assert plan_sync(BASE, BASE).functions == []
+
+    changed = _fingerprint({**BASE["functions"], "add": _function("a2")})
+    plan = plan_sync(BASE, changed)
+    assert plan.full_reason is None
+    assert plan.functions == ["add"]
+
+    # the callers of a new signature are retranslated after it
+    resigned = _fingerprint({**BASE["functions"], "add": _function("a2", signature="long (long)"),
+                             "main": _function("m2", signature="int (void)", calls=["twice"])})
+    assert plan_sync(BASE, resigned).functions == ["add", "twice", "main"]
+
+    added = _fingerprint({**BASE["functions"], "sub": _function("s1")})
+    assert plan_sync(BASE, added).functions == ["sub"]
+
+    removed = _fingerprint({name: f for name, f in BASE["functions"].items() if name != "main"})
+    assert "main" in plan_sync(BASE, removed).full_reason
+    retyped = _fingerprint(BASE["functions"], {"struct point": "p2"})
+    assert "struct point" in plan_sync(BASE, retyped).full_reason
+
+
+def test_watcher_sync(tmp_path):
+    fingerprints = [BASE]
+    calls = []
+
+    def retranslate(name):
+        calls.append(name)
+        return TranslateResult.MAX_ATTEMPTS_EXCEEDED if name == "twice" else TranslateResult.SUCCESS
+
+    watcher = Watcher("add.c", str(tmp_path), translate=lambda: calls.append("<all>"),
+                      retranslate=retranslate, fingerprint=lambda _: fingerprints[-1])
+    assert watcher.sync()
+    assert calls == ["<all>"]
+
+    fingerprints.append(_fingerprint({**BASE["functions"], "add": _function("a2"), "twice": _function("t2")}))
+    calls.clear()
+    assert not watcher.sync()
+    assert calls == ["add", "twice"]
+    state = json.loads((tmp_path / WATCH_STATE_FILE).read_text())
+    assert state["fingerprint"]["functions"]["add"]["hash"] == "a2"
+    # the failed function keeps the hash of its previous translation
+    assert state["fingerprint"]["functions"]["twice"]["hash"] == "t1"
+
+    calls.clear()
+    assert not watcher.sync()
+    assert calls == ["twice"]
+
+    def broken(_):
+        raise ValueError("add.c does not compile")
+
+    watcher.fingerprint = broken
+    calls.clear()
+    assert not watcher.sync()
+    assert calls == []
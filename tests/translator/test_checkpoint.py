@@ -0,0 +1,111 @@
+import json
+from types import SimpleNamespace
+from unittest.mock import Mock
+
+import pytest
+
+from sactor.llm import CostTracker
+from sactor.translator import Translator
+from sactor.translator.checkpoint import Checkpoint
+from sactor.translator.translator_types import TranslateResult
+
+
+class RecordingTranslator(Translator):
+    """Translator recording the attempt each item starts at."""
+
+    def __init__(self, *args, **kwargs):
+        super().__init__(*args, **kwargs)
+        self.started = {}
+
+    def _translate_enum_impl(self, enum, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_global_vars_impl(self, global_var, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_struct_impl(self, struct_union, verify_result=(None, None), error_translation=None, attempts=0):
+        self.started[struct_union.name] = attempts
+        return TranslateResult.SUCCESS
+
+    def _translate_function_impl(self, function, verify_result=(None, None), error_translation=None, attempts=0):
+        self.started[function.name] = attempts
+        self.init_failure_info("function", function.name)
+        self.mark_translation_success("function", function.name)
+        return TranslateResult.SUCCESS
+
+
+def make_llm(input_tokens, output_tokens):
+    return SimpleNamespace(costed_input_tokens=input_tokens, costed_output_tokens=output_tokens,
+                           cost=CostTracker({}))
+
+
+def test_checkpoint_round_trip(tmp_path):
+    path = str(tmp_path / "checkpoint.json")
+    functions = tmp_path / "translated_code_unidiomatic" / "functions"
+    functions.mkdir(parents=True)
+    (functions / "add.rs").write_text("fn add() {}\n")
+
+    checkpoint = Checkpoint(path, "main.c")
+    failure_info = {
+        "add": {"type": "function", "status": "success", "attempts": [2], "errors": []},
+        "sub": {"type": "function", "status": "failure", "attempts": [3], "errors": []},
+    }
+    checkpoint.record_stage("unidiomatic", failure_info, make_llm([100, 50], [10, 5]))
+    checkpoint.complete_stage("unidiomatic")
+    checkpoint.save()
+
+    loaded = Checkpoint.load(path, "main.c")
+    assert loaded.resumed
+    assert loaded.is_completed("unidiomatic")
+    assert not loaded.is_completed("idiomatic")
+    assert loaded.resumed_item("unidiomatic", "add") == {
+        "type": "function", "status": "success", "attempts": 2,
+        "translation": "translated_code_unidiomatic/functions/add.rs",
+    }
+    assert loaded.resumed_item("unidiomatic", "sub")["translation"] is None
+    assert loaded.resumed_item("idiomatic", "add") is None
+
+    # tokens of the resumed run add up with those of the interrupted one
+    loaded.record_stage("unidiomatic", {}, make_llm([20], [2]))
+    loaded.save()
+    with open(path) as f:
+        tokens = json.load(f)["stages"]["unidiomatic"]["tokens"]
+    assert tokens == {"queries": 3, "input_tokens": 170, "output_tokens": 17}
+
+
+def test_checkpoint_load(tmp_path):
+    path = str(tmp_path / "checkpoint.json")
+    assert not Checkpoint.load(path, "main.c").resumed
+
+    Checkpoint(path, "main.c").save()
+    with pytest.raises(ValueError, match="belongs to the translation of"):
+        Checkpoint.load(path, "other.c")
+
+
+def test_translator_resumes_attempts(tmp_path):
+    path = str(tmp_path / "checkpoint.json")
+    interrupted = Checkpoint(path, "main.c")
+    interrupted.record_stage("idiomatic", {
+        "done_failing": {"type": "function", "status": "failure", "attempts": [2]},
+        "retrying": {"type": "function", "status": "failure", "attempts": [1]},
+        "interrupted": {"type": "struct", "status": "untranslated", "attempts": [2]},
+    }, make_llm([], []))
+    interrupted.save()
+
+    config = {"general": {"max_translation_attempts": 2}}
+    llm = make_llm([7], [3])
+    translator = RecordingTranslator(llm, Mock(), config, result_path=str(tmp_path))
+    translator.use_checkpoint(Checkpoint.load(path, "main.c"), "idiomatic")
+
+    function = SimpleNamespace
+    assert translator.translate_function(function(name="done_failing")) == TranslateResult.MAX_ATTEMPTS_EXCEEDED
+    assert translator.translate_function(function(name="retrying")) == TranslateResult.SUCCESS
+    assert translator.translate_struct(function(name="interrupted")) == TranslateResult.SUCCESS
+    assert translator.translate_function(function(name="new")) == TranslateResult.SUCCESS
+
+    assert translator.started == {"retrying": 0, "interrupted": 1, "new": 0}
+    assert translator.translation_status["function"]["done_failing"].value == "failure"
+    with open(path) as f:
+        state = json.load(f)["stages"]["idiomatic"]
+    assert state["items"]["new"]["status"] == "success"
+    assert state["tokens"] == {"queries": 1, "input_tokens": 7, "output_tokens": 3}
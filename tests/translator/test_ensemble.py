@@ -0,0 +1,93 @@
+import os
+from types import SimpleNamespace
+
+from sactor.llm import CostTracker
+from sactor.translator import Translator, result_export
+from sactor.translator.translator_types import TranslateResult
+
+
+class ModelTranslator(Translator):
+    """Translates functions by retrying until `max_attempts`, succeeding only with `good_models`."""
+
+    def __init__(self, tmp_path, config, good_models):
+        llm = SimpleNamespace(default_model="gpt-4o", cost=CostTracker({}))
+        super().__init__(llm, None, config, result_path=str(tmp_path))
+        self.stage = "unidiomatic"
+        self.translated_function_path = str(tmp_path / "functions")
+        os.makedirs(self.translated_function_path)
+        self.good_models = good_models
+        self.queried = []
+
+    def _translate_enum_impl(self, enum, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_global_vars_impl(self, global_var, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_struct_impl(self, struct_union, verify_result=(None, None), error_translation=None, attempts=0):
+        return TranslateResult.SUCCESS
+
+    def _translate_function_impl(self, function, verify_result=(None, None), error_translation=None, attempts=0):
+        self.init_failure_info("function", function.name)
+        if os.path.exists(os.path.join(self.translated_function_path, f"{function.name}.rs")):
+            self.mark_translation_success("function", function.name)
+            return TranslateResult.SUCCESS
+        if attempts > self.max_attempts - 1:
+            return TranslateResult.MAX_ATTEMPTS_EXCEEDED
+        self.failure_info_set_attempts(function.name, attempts + 1)
+        self.queried.append(self.llm.default_model)
+        if self.llm.default_model not in self.good_models:
+            self.append_failure_info(function.name, "TEST_ERROR", "wrong output", "")
+            return self._translate_function_impl(function, attempts=attempts + 1)
+        self.mark_translation_success("function", function.name)
+        return TranslateResult.SUCCESS
+
+
+def make_config(**ensemble):
+    return {"general": {"max_translation_attempts": 3}, "ensemble": ensemble}
+
+
+def test_ensemble_accepts_first_passing_model(tmp_path):
+    config = make_config(unidiomatic=["small", "large", "huge"], attempts_per_model=2)
+    translator = ModelTranslator(tmp_path, config, good_models={"large", "huge"})
+
+    result = translator.translate_function(SimpleNamespace(name="parse"))
+
+    assert result == TranslateResult.SUCCESS
+    assert translator.queried == ["small", "small", "large"]
+    info = translator.failure_info["parse"]
+    assert info["attempts"] == [2, 1]
+    assert info["status"] == "success"
+    assert info["model"] == "large"
+    assert info["candidates"] == [
+        {"model": "small", "status": "failure", "attempts": 2},
+        {"model": "large", "status": "success", "attempts": 1},
+    ]
+    assert translator.llm.default_model == "gpt-4o"
+    assert translator.max_attempts == 3
+    assert result_export.stage_results("unidiomatic", translator.failure_info)[0]["model"] == "large"
+
+
+def test_ensemble_without_passing_model(tmp_path):
+    config = make_config(unidiomatic=["small", "large"])
+    translator = ModelTranslator(tmp_path, config, good_models=set())
+
+    result = translator.translate_function(SimpleNamespace(name="parse"))
+
+    assert result == TranslateResult.MAX_ATTEMPTS_EXCEEDED
+    assert translator.queried == ["small"] * 3 + ["large"] * 3
+    assert "model" not in translator.failure_info["parse"]
+    assert [c["status"] for c in translator.failure_info["parse"]["candidates"]] == ["failure", "failure"]
+
+
+def test_ensemble_of_other_stage_or_translated_item(tmp_path):
+    config = make_config(idiomatic=["small", "large"])
+    translator = ModelTranslator(tmp_path, config, good_models={"gpt-4o"})
+    assert translator.translate_function(SimpleNamespace(name="parse")) == TranslateResult.SUCCESS
+    assert translator.queried == ["gpt-4o"]
+
+    config["ensemble"]["unidiomatic"] = ["small"]
+    (tmp_path / "functions" / "parse.rs").write_text("fn parse() {}\n")
+    assert translator.translate_function(SimpleNamespace(name="parse")) == TranslateResult.SUCCESS
+    assert translator.queried == ["gpt-4o"]
+    assert "candidates" not in translator.failure_info["parse"]
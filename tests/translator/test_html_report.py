@@ -0,0 +1,47 @@
+from sactor.translator import html_report, result_export
+
+
+def test_html_report(tmp_path):
+    unidiomatic_dir = tmp_path / "translated_code_unidiomatic" / "functions"
+    idiomatic_dir = tmp_path / "translated_code_idiomatic" / "functions"
+    unidiomatic_dir.mkdir(parents=True)
+    idiomatic_dir.mkdir(parents=True)
+    (unidiomatic_dir / "add.rs").write_text(
+        "pub unsafe fn add(a: *const i32, b: i32) -> i32 {\n    unsafe { *a + b }\n}\n")
+    (idiomatic_dir / "add.rs").write_text("pub fn add(a: &i32, b: i32) -> i32 {\n    *a + b\n}\n")
+
+    results = result_export.stage_results("unidiomatic", {
+        "add": {"type": "function", "errors": [], "status": "success", "attempts": [2], "duration_seconds": 3.0},
+        "parse": {
+            "type": "function",
+            "errors": [{"type": "TEST_ERROR", "message": "- <ok>\n+ <err>", "translation": ""}],
+            "status": "failure",
+            "attempts": [6],
+        },
+    })
+    results += result_export.stage_results("idiomatic", {
+        "add": {"type": "function", "errors": [], "status": "success", "attempts": [1], "model": "gpt-4o"},
+    })
+
+    path = tmp_path / html_report.REPORT_FILE
+    html_report.write_html_report(str(path), "add.c", results, str(tmp_path), {
+        ("function", "add"): "int add(const int *a, int b) { return *a + b; }",
+    })
+    report = path.read_text()
+
+    assert "<title>Sactor report: add.c</title>" in report
+    assert "unidiomatic: 1 of 2 items passed" in report
+    assert "idiomatic: 1 of 1 items passed, 0.0% of the Rust tokens unsafe" in report
+    assert "int add(const int *a, int b) { return *a + b; }" in report
+    assert "pub fn add(a: &amp;i32, b: i32) -&gt; i32 {" in report
+    assert "(2 attempt(s))" in report
+    assert "(1 attempt(s), gpt-4o)" in report
+    # the last error is escaped, and the missing translations are marked
+    assert "- &lt;ok&gt;\n+ &lt;err&gt;" in report
+    assert "<em>not available</em>" in report
+    assert "Unidiomatic to idiomatic" in report
+    assert 'class="diff"' in report
+
+    metrics = html_report.unsafe_metrics((unidiomatic_dir / "add.rs").read_text())
+    assert metrics["unsafe_tokens"] > 0
+    assert html_report.unsafe_metrics(None) is None
@@ -0,0 +1,69 @@
+import json
+import xml.etree.ElementTree as ET
+
+from sactor.translator import result_export
+
+
+FAILURE_INFO = {
+    "add": {
+        "type": "function",
+        "errors": [],
+        "status": "success",
+        "attempts": [1],
+        "duration_seconds": 1.5,
+    },
+    "parse": {
+        "type": "function",
+        "errors": [
+            {"type": "COMPILE_ERROR", "message": "error[E0308]", "translation": ""},
+            {"type": "TEST_ERROR", "message": "- 3\n+ 4" + "x" * 3000, "translation": ""},
+        ],
+        "status": "failure",
+        "attempts": [6],
+        "duration_seconds": 20.25,
+    },
+    "main": {
+        "type": "function",
+        "errors": [],
+        "status": "blocked_by_failed_dependency",
+        "attempts": [0],
+        "blockers": [{"type": "function", "name": "parse", "status": "failure"}],
+    },
+}
+
+
+def test_stage_results():
+    results = result_export.stage_results("unidiomatic", FAILURE_INFO)
+    by_name = {r["name"]: r for r in results}
+    assert by_name["add"]["passed"]
+    assert by_name["add"]["diff_excerpt"] is None
+    assert not by_name["parse"]["passed"]
+    assert by_name["parse"]["attempts"] == 6
+    assert by_name["parse"]["error_type"] == "TEST_ERROR"
+    assert by_name["parse"]["diff_excerpt"].startswith("- 3\n+ 4")
+    assert "more characters" in by_name["parse"]["diff_excerpt"]
+    assert by_name["main"]["blockers"] == ["parse"]
+
+
+def test_write_results(tmp_path):
+    results = result_export.stage_results("unidiomatic", FAILURE_INFO)
+    results += result_export.stage_results("idiomatic", {"add": FAILURE_INFO["add"]})
+
+    json_path = tmp_path / "results.json"
+    result_export.write_json_summary(str(json_path), results)
+    summary = json.loads(json_path.read_text())
+    assert (summary["total"], summary["passed"], summary["failed"]) == (4, 2, 2)
+
+    xml_path = tmp_path / "junit.xml"
+    result_export.write_junit_xml(str(xml_path), results)
+    suites = ET.parse(xml_path).getroot()
+    unidiomatic, idiomatic = suites.findall("testsuite")
+    assert unidiomatic.get("name") == "unidiomatic"
+    assert (unidiomatic.get("tests"), unidiomatic.get("failures"), unidiomatic.get("skipped")) == ("3", "1", "1")
+    assert idiomatic.get("failures") == "0"
+    parse = unidiomatic.find("testcase[@name='parse']")
+    assert parse.get("classname") == "unidiomatic.function"
+    assert parse.get("time") == "20.250"
+    assert parse.find("failure").get("type") == "TEST_ERROR"
+    assert parse.find("failure").text.startswith("- 3")
+    assert unidiomatic.find("testcase[@name='main']/skipped").get("message") == "blocked by parse"
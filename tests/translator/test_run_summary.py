@@ -0,0 +1,76 @@
+import json
+import os
+
+from jsonschema import Draft202012Validator
+
+from sactor.translator import result_export, run_summary
+
+SCHEMA_PATH = os.path.join(os.path.dirname(__file__), "..", "..", "sactor", "_resources", "summary.schema.json")
+
+FAILURE_INFO = {
+    "add": {"type": "function", "errors": [], "status": "success", "attempts": [1], "duration_seconds": 2.0},
+    "parse": {
+        "type": "function",
+        "errors": [
+            {"type": "COMPILE_ERROR", "message": "error[E0308]", "translation": ""},
+            {"type": "TEST_ERROR", "message": "- 3\n+ 4", "translation": ""},
+            {"type": "TEST_ERROR", "message": "- 3\n+ 5", "translation": ""},
+        ],
+        "status": "failure",
+        "attempts": [3],
+        "duration_seconds": 9.5,
+    },
+    "main": {
+        "type": "function",
+        "errors": [],
+        "status": "blocked_by_failed_dependency",
+        "attempts": [0],
+        "blockers": [{"type": "function", "name": "parse", "status": "failure"}],
+    },
+}
+
+
+def test_run_summary(tmp_path):
+    functions_dir = tmp_path / "translated_code_unidiomatic" / "functions"
+    functions_dir.mkdir(parents=True)
+    (functions_dir / "add.rs").write_text("pub unsafe fn add(a: *const i32) -> i32 {\n    unsafe { *a }\n}\n")
+
+    results = result_export.stage_results("unidiomatic", FAILURE_INFO)
+    items = run_summary.item_summaries(results, str(tmp_path))
+    unit = run_summary.unit_summary("add.c", str(tmp_path), items, None)
+    summary = run_summary.run_summary([unit], 100.0, 112.5, {"queries": 3, "input_tokens": 300,
+                                                             "output_tokens": 30, "cost": 0.01})
+
+    assert summary["schema_version"] == run_summary.SUMMARY_SCHEMA_VERSION
+    assert summary["duration_seconds"] == 12.5
+    assert summary["status"] == unit["status"] == "failure"
+    by_name = {item["name"]: item for item in unit["items"]}
+    assert by_name["add"]["unsafe_tokens"] > 0
+    assert 0 < by_name["add"]["unsafe_ratio"] <= 1
+    assert by_name["parse"]["tokens"] is None
+    assert by_name["parse"]["failure_category"] == "TEST_ERROR"
+    assert by_name["parse"]["failure_categories"] == {"COMPILE_ERROR": 1, "TEST_ERROR": 2}
+
+    totals = summary["stages"]["unidiomatic"]
+    assert (totals["items"], totals["passed"], totals["failed"], totals["blocked"]) == (3, 1, 1, 1)
+    assert totals["attempts"] == 4
+    assert totals["test_pass_rate"] == 0.5
+    assert totals["failure_categories"] == {"COMPILE_ERROR": 1, "TEST_ERROR": 2}
+    assert "idiomatic" not in summary["stages"]
+
+    path = tmp_path / run_summary.SUMMARY_FILE
+    run_summary.write_summary(str(path), summary)
+    with open(SCHEMA_PATH) as f:
+        schema = json.load(f)
+    Draft202012Validator.check_schema(schema)
+    assert list(Draft202012Validator(schema).iter_errors(json.loads(path.read_text()))) == []
+    assert run_summary.load_units(str(path)) == [unit]
+
+
+def test_run_summary_status():
+    passed = run_summary.unit_summary("a.c", "a", [], None)
+    failed = run_summary.unit_summary("b.c", "b", [], "Failed to translate unidiomatic code")
+    assert passed["status"] == "success"
+    assert failed["status"] == "error"
+    assert run_summary.run_summary([passed], 0.0, 1.0, None)["status"] == "success"
+    assert run_summary.run_summary([passed, failed], 0.0, 1.0, None)["status"] == "error"
@@ -6,7 +6,7 @@ from functools import partial
 from unittest.mock import patch
 from sactor.c_parser import CParser
 from sactor.translator import UnidiomaticTranslator
-from sactor.translator.translator_types import TranslateResult
+from sactor.translator.translator_types import TranslateResult, TranslationOutcome
 from sactor.llm import LLM, llm_factory
 from tests.utils import config
 from tests.mock_llm import llm_with_mock
@@ -103,6 +103,40 @@ def test_c2rust_fallback_main_appends_exit(monkeypatch, tmp_path, config, llm):
     assert saved == expected
 
 
+def test_import_c2rust_skips_llm(monkeypatch, tmp_path, config, llm):
+    config['general']['unidiomatic_fallback_c2rust'] = False
+
+    def fail_query(*args, **kwargs):
+        raise AssertionError("LLM should not be called when importing c2rust output")
+
+    monkeypatch.setattr(llm, "query", fail_query)
+
+    c_file = tmp_path / "main_only.c"
+    c_file.write_text(
+        "#include <stdio.h>\n\nint main(void) {\n    puts(\"hi\");\n    return 0;\n}\n"
+    )
+    c_parser = CParser(str(c_file))
+    test_cmd_path = tmp_path / "test_commands.json"
+    test_cmd_path.write_text("[]")
+    result_path = tmp_path / "result"
+    translator = UnidiomaticTranslator(
+        llm=llm,
+        c2rust_translation='fn main() {\n    println!("hi");\n}\n',
+        c_parser=c_parser,
+        config=config,
+        test_cmd_path=str(test_cmd_path),
+        result_path=str(result_path),
+        build_path=str(tmp_path / "build"),
+        import_c2rust=True,
+    )
+
+    result = translator.translate_function(c_parser.get_function_info('main'))
+
+    assert result == TranslateResult.SUCCESS
+    assert (result_path / 'translated_code_unidiomatic' / 'functions' / 'main.rs').exists()
+    assert translator.failure_info['main']['status'] == TranslationOutcome.IMPORTED_C2RUST.value
+
+
 def test_llm_main_translation_appends_exit(monkeypatch, tmp_path, config, llm):
     config['general']['unidiomatic_fallback_c2rust'] = False
     config['general']['max_translation_attempts'] = 1
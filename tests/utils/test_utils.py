@@ -190,6 +190,17 @@ def test_run_command_check_raises():
         )
 
 
+def test_run_command_memory_limit():
+    allocate = "bytearray(512 * 1024 * 1024); print('allocated')"
+    for limit_bytes in (None, 1024):
+        result = utils.run_command([sys.executable, "-c", allocate], limit_bytes=limit_bytes,
+                                   memory_limit_mb=256)
+        assert result.returncode != 0
+        assert "MemoryError" in result.stderr
+    result = utils.run_command([sys.executable, "-c", "print('ok')"], memory_limit_mb=256)
+    assert result.stdout == "ok\n"
+
+
 def test_run_command_limit_requires_capture_output():
     with pytest.raises(ValueError):
         utils.run_command(
@@ -310,3 +321,13 @@ def test_list_c_files_from_compile_commands(tmp_path):
 
     files = utils.list_c_files_from_compile_commands(str(commands_path))
     assert sorted(files) == sorted([str(a_c.resolve()), str(b_c.resolve())])
+
+
+def test_enable_c_variadic():
+    variadic = 'pub unsafe extern "C" fn sum(count: i32, mut args: ...) -> i32 { count }\n'
+    assert utils.enable_c_variadic(variadic) == utils.C_VARIADIC_FEATURE + "\n" + variadic
+    gated = utils.enable_c_variadic(variadic)
+    assert utils.enable_c_variadic(gated) == gated
+    plain = 'extern "C" { fn printf(fmt: *const i8, ...) -> i32; }\nfn main() {}\n'
+    assert utils.enable_c_variadic(plain) == plain
+    assert utils.enable_c_variadic("fn broken(") == "fn broken("
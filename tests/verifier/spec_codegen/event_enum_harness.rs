@@ -50,3 +50,23 @@ unsafe fn Event_to_CEvent_mut(idiom_struct: &mut Event) -> *mut CEvent {
     };
     Box::into_raw(Box::new(c_struct))
 }
+
+unsafe fn free_CEvent_fields(c_struct: &mut CEvent) {
+    if c_struct.tag == (0) as u8 {
+        if !c_struct.message.is_null() {
+            drop(std::ffi::CString::from_raw(c_struct.message as *mut libc::c_char));
+        }
+    }
+}
+
+unsafe fn free_CEvent(input: *mut CEvent) {
+    if input.is_null() {
+        return;
+    }
+    let mut c_struct = Box::from_raw(input);
+    free_CEvent_fields(&mut c_struct);
+}
+
+unsafe fn free_Event(idiom_struct: &'static mut Event) {
+    drop(Box::from_raw(idiom_struct as *mut Event));
+}
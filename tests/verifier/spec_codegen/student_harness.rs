@@ -16,7 +16,9 @@ unsafe fn CStudent_to_Student_mut(input: *mut CStudent) -> &'static mut Student
             // Field 'enrolledCourse' -> 'enrolled_course' (C -> idiomatic)
             enrolled_course: if !c_struct.enrolledCourse.is_null() {
                 let tmp = unsafe { CCourse_to_Course_mut(c_struct.enrolledCourse as *mut CCourse) };
-                Some((*tmp).clone())
+                let value = (*tmp).clone();
+                unsafe { drop(Box::from_raw(tmp as *mut Course)) };
+                Some(value)
             } else {
                 None
             },
@@ -69,3 +71,27 @@ unsafe fn Student_to_CStudent_mut(idiom_struct: &mut Student) -> *mut CStudent {
     };
     Box::into_raw(Box::new(c_struct))
 }
+
+unsafe fn free_CStudent_fields(c_struct: &mut CStudent) {
+    if !c_struct.name.is_null() {
+        drop(std::ffi::CString::from_raw(c_struct.name as *mut libc::c_char));
+    }
+    if !c_struct.enrolledCourse.is_null() {
+        drop(Box::from_raw(c_struct.enrolledCourse));
+    }
+    if !c_struct.grades.is_null() && (c_struct.numGrades as usize) > 0 {
+        drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(c_struct.grades as *mut f32, c_struct.numGrades as usize)));
+    }
+}
+
+unsafe fn free_CStudent(input: *mut CStudent) {
+    if input.is_null() {
+        return;
+    }
+    let mut c_struct = Box::from_raw(input);
+    free_CStudent_fields(&mut c_struct);
+}
+
+unsafe fn free_Student(idiom_struct: &'static mut Student) {
+    drop(Box::from_raw(idiom_struct as *mut Student));
+}
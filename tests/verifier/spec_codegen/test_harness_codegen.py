@@ -129,6 +129,27 @@ pub struct CA {
             };
             Box::into_raw(Box::new(c_struct))
         }
+
+        unsafe fn free_CA_fields(c_struct: &mut CA) {
+            if !c_struct.name.is_null() {
+                drop(std::ffi::CString::from_raw(c_struct.name as *mut libc::c_char));
+            }
+            if !c_struct.data.is_null() && (c_struct.data_len as usize) > 0 {
+                drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(c_struct.data as *mut u8, c_struct.data_len as usize)));
+            }
+        }
+
+        unsafe fn free_CA(input: *mut CA) {
+            if input.is_null() {
+                return;
+            }
+            let mut c_struct = Box::from_raw(input);
+            free_CA_fields(&mut c_struct);
+        }
+
+        unsafe fn free_A(idiom_struct: &'static mut A) {
+            drop(Box::from_raw(idiom_struct as *mut A));
+        }
         """
     ).strip()
     assert code.strip() == expected
@@ -369,6 +390,100 @@ def test_generate_function_harness_basic(tmp_path: Path):
     assert code == expected
 
 
+def test_generate_function_harness_errno_result(tmp_path: Path):
+    # C: remove_entry(path) returns -1 and sets errno on failure; Rust returns io::Result<()>
+    spec = {
+        "function_name": "remove_entry",
+        "fields": [
+            {
+                "u_field": {"name": "path", "type": "*const c_char", "shape": {"ptr": {"kind": "cstring"}}},
+                "i_field": {"name": "path", "type": "&str"},
+            },
+            {
+                "u_field": {"name": "ret", "type": "i32", "shape": "scalar"},
+                "i_field": {"name": "ret", "type": "std::io::Result<()>"},
+                "on_error": "-1",
+            },
+        ],
+    }
+    spec_path = write_json(tmp_path / "remove_entry.json", spec)
+
+    idiomatic_sig = "pub fn remove_entry_idiomatic(path: &str) -> std::io::Result<()>;"
+    c_sig = "pub unsafe extern \"C\" fn remove_entry(path: *const libc::c_char) -> i32;"
+
+    code = generate_function_harness_from_spec_file(
+        "remove_entry", idiomatic_sig, c_sig, [], str(spec_path)
+    )
+    expected = textwrap.dedent(
+        """\
+        pub unsafe extern \"C\" fn remove_entry(path: *const libc::c_char) -> i32
+        {
+            // Arg 'path': borrowed C string at path
+            let path_str = if !path.is_null() {
+                unsafe { std::ffi::CStr::from_ptr(path) }.to_string_lossy().into_owned()
+            } else {
+                String::new()
+            };
+            let __ret = remove_entry_idiomatic(&path_str);
+            let __ret = match __ret {
+                Ok(()) => 0,
+                Err(__err) => {
+                    unsafe { *libc::__errno_location() = std::io::Error::from(__err).raw_os_error().unwrap_or(libc::EIO); }
+                    -1
+                }
+            };
+            return __ret;
+        }
+        """
+    ).strip("\n")
+    assert code == expected
+
+
+def test_generate_function_harness_file_stream(tmp_path: Path):
+    # C: count_lines(FILE *fp) reads the stream; Rust reads a &mut BufReader<File>
+    spec = {
+        "function_name": "count_lines",
+        "fields": [
+            {
+                "u_field": {"name": "fp", "type": "*mut libc::FILE", "shape": {"ptr": {"kind": "file"}}},
+                "i_field": {"name": "reader", "type": "&mut BufReader<File>"},
+            },
+            {
+                "u_field": {"name": "ret", "type": "i32", "shape": "scalar"},
+                "i_field": {"name": "ret", "type": "i32"},
+            },
+        ],
+    }
+    spec_path = write_json(tmp_path / "count_lines.json", spec)
+
+    idiomatic_sig = "pub fn count_lines_idiomatic(reader: &mut std::io::BufReader<std::fs::File>) -> i32;"
+    c_sig = "pub unsafe extern \"C\" fn count_lines(fp: *mut libc::FILE) -> i32;"
+
+    code = generate_function_harness_from_spec_file(
+        "count_lines", idiomatic_sig, c_sig, [], str(spec_path)
+    )
+    assert code is not None
+    assert "// Arg 'reader': BufReader<File> sharing the file of FILE * 'fp'" in code
+    assert "let mut reader_stream = unsafe {" in code
+    assert "libc::fflush(fp.cast());" in code
+    assert "std::io::BufReader::new(file)" in code
+    assert "count_lines_idiomatic(&mut reader_stream)" in code
+    # the read-ahead is given back before the C stream resumes at the file position
+    seek = code.index("std::io::Seek::seek(&mut reader_stream, std::io::SeekFrom::Current(0));")
+    assert seek < code.index("libc::fseek(fp.cast(), __pos as libc::c_long, libc::SEEK_SET);")
+    assert code.rstrip().endswith("return __ret;\n}")
+
+    # an owned File is simply moved into the call
+    spec["fields"][0]["i_field"] = {"name": "file", "type": "File"}
+    write_json(spec_path, spec)
+    code = generate_function_harness_from_spec_file(
+        "count_lines", "pub fn count_lines_idiomatic(file: File) -> i32;", c_sig, [], str(spec_path)
+    )
+    assert "let file_stream = unsafe {" in code
+    assert "count_lines_idiomatic(file_stream)" in code
+    assert "std::io::Seek::seek" not in code
+
+
 def test_generate_function_harness_struct_param(tmp_path: Path):
     spec = {
         "function_name": "updateStudentInfo",
@@ -492,6 +607,7 @@ def test_generate_function_harness_mut_ref_struct(tmp_path: Path):
                 let __c_student = unsafe { Student_to_CStudent_mut(student_idiom) };
                 unsafe { *student = *__c_student; }
                 unsafe { let _ = Box::from_raw(__c_student); }
+                unsafe { drop(Box::from_raw(student_idiom as *mut Student)); }
             }
         }
         """
@@ -614,6 +730,7 @@ def test_generate_function_harness_mut_struct_param(tmp_path: Path):
                 let __c_student = unsafe { Student_to_CStudent_mut(student_idiom) };
                 unsafe { *student = *__c_student; }
                 unsafe { let _ = Box::from_raw(__c_student); }
+                unsafe { drop(Box::from_raw(student_idiom as *mut Student)); }
             }
         }
         """
@@ -655,10 +772,11 @@ def test_generate_function_harness_option_mut_struct_param(tmp_path: Path):
             };
             opt_student_idiomatic(student_storage.as_deref_mut());
             if !student.is_null() {
-                if let Some(inner) = student_storage.as_deref_mut() {
+                if let Some(inner) = student_storage.take() {
                     let __c_student = unsafe { Student_to_CStudent_mut(inner) };
                     unsafe { *student = *__c_student; }
                     unsafe { let _ = Box::from_raw(__c_student); }
+                    unsafe { drop(Box::from_raw(inner as *mut Student)); }
                 }
             }
         }
@@ -703,6 +821,41 @@ def test_generate_function_harness_mut_scalar_param(tmp_path: Path):
     assert code == expected
 
 
+def test_generate_function_harness_lifetimes_erased(tmp_path: Path):
+    spec = {
+        "function_name": "first_word",
+        "fields": [
+            {
+                "u_field": {"name": "text", "type": "*const c_char", "shape": {"ptr": {"kind": "cstring"}}},
+                "i_field": {"name": "text", "type": "&'a str"},
+            },
+        ],
+    }
+    spec_path = write_json(tmp_path / "lifetime_spec.json", spec)
+
+    idiomatic_sig = "pub fn first_word_idiomatic<'a>(text: &'a str) -> usize;"
+    c_sig = "pub unsafe extern \"C\" fn first_word(text: *const libc::c_char) -> usize;"
+
+    code = generate_function_harness_from_spec_file(
+        "first_word", idiomatic_sig, c_sig, [], str(spec_path)
+    )
+    assert code is not None
+    assert "'a" not in code
+    assert "first_word_idiomatic(&text_str)" in code
+
+
+def test_generate_function_harness_rejects_generic(tmp_path: Path):
+    spec = {"function_name": "largest", "fields": []}
+    spec_path = write_json(tmp_path / "generic_spec.json", spec)
+
+    idiomatic_sig = "pub fn largest_idiomatic<T: PartialOrd>(items: &[T]) -> usize;"
+    c_sig = "pub unsafe extern \"C\" fn largest(items: *const i32, len: usize) -> usize;"
+
+    assert generate_function_harness_from_spec_file(
+        "largest", idiomatic_sig, c_sig, [], str(spec_path)
+    ) is None
+
+
 def test_generate_function_harness_todo_fallback(tmp_path: Path):
     # Unsupported param type triggers TODO skeleton generation
     spec = {
@@ -735,6 +888,51 @@ def test_generate_function_harness_todo_fallback(tmp_path: Path):
     ).strip("\n")
     assert code == expected
 
+def test_generate_function_harness_pointer_array_params(tmp_path: Path):
+    # Function: run(args: &[String], grid: Vec<Vec<i32>>) -> i32
+    # C: run(argv: *mut *mut c_char, argc: c_int, grid: *mut *mut c_int, rows: c_int, cols: c_int) -> i32
+    spec = {
+        "function_name": "run",
+        "fields": [
+            {
+                "u_field": {"name": "argv", "type": "*mut *mut c_char", "shape": {"ptr": {"kind": "slice", "len_from": "argc"}}},
+                "i_field": {"name": "args", "type": "&[String]"},
+            },
+            {
+                "u_field": {"name": "grid", "type": "*mut *mut c_int",
+                            "shape": {"ptr": {"kind": "slice", "len_from": "rows", "inner_len_from": "cols"}}},
+                "i_field": {"name": "grid", "type": "Vec<Vec<i32>>"},
+            },
+        ],
+    }
+    spec_path = write_json(tmp_path / "argv_spec.json", spec)
+
+    idiomatic_sig = "pub fn run_idiomatic(args: &[String], grid: Vec<Vec<i32>>) -> i32;"
+    c_sig = (
+        "pub unsafe extern \"C\" fn run("
+        "argv: *mut *mut libc::c_char, argc: libc::c_int, "
+        "grid: *mut *mut libc::c_int, rows: libc::c_int, cols: libc::c_int"
+        ") -> i32;"
+    )
+
+    code = generate_function_harness_from_spec_file(
+        "run", idiomatic_sig, c_sig, [], str(spec_path)
+    )
+    assert code is not None
+    assert "// Arg 'args': C string array at argv" in code
+    assert "let args_strs: Vec<String> = if args_len_non_null == 0 {" in code
+    assert "std::slice::from_raw_parts(argv as *const *const std::ffi::c_char, args_len_non_null)" in code
+    # a negative count would make a huge slice length
+    assert "let args_len = if argc <= 0 { 0 } else { argc as usize };" in code
+    assert "// Arg 'grid': rows from grid with row len cols as usize" in code
+    assert "let grid_row_len = if cols <= 0 { 0 } else { cols as usize };" in code
+    assert "std::slice::from_raw_parts(*p, grid_row_len)" in code
+    assert "let grid_rows: Vec<Vec<i32>> = if grid_len_non_null == 0 {" in code
+    assert "grid as *const *const libc::c_int" in code
+    assert "let __ret = run_idiomatic(&args_strs, grid_rows);" in code
+    assert "TODO" not in code
+
+
 def test_render_len_expression_supports_composite_product():
     field_types = {"rows": "usize", "cols": "usize"}
     prefix = f"{_C_STRUCT_BIND}."
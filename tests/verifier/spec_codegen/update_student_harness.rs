@@ -4,6 +4,7 @@ pub extern "C" fn updateStudentInfo(student: *mut CStudent, newName: *const libc
     assert!(!student.is_null());
     let mut student_ref: &'static mut Student = unsafe { CStudent_to_Student_mut(student) };
     let student_val: Student = student_ref.clone();
+    unsafe { drop(Box::from_raw(student_ref as *mut Student)) };
     // Arg 'new_name': optional C string at newName
     let new_name_opt = if !newName.is_null() {
         Some(unsafe { std::ffi::CStr::from_ptr(newName) }.to_string_lossy().into_owned())
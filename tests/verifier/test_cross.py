@@ -0,0 +1,80 @@
+import os
+import subprocess
+
+from sactor import utils
+from sactor.utils import load_default_config
+from sactor.verifier import E2EVerifier, VerifyResult
+from sactor.verifier.cross import CrossTarget, gnu_triple
+
+
+def _cross_config(**cross):
+    base = load_default_config()
+    config = {k: (v.copy() if isinstance(v, dict) else v) for k, v in base.items()}
+    config['cross'] = {'target': '', 'c_compiler': '', 'runner': '', **cross}
+    return config
+
+
+def test_cross_target_from_config():
+    assert CrossTarget.from_config(_cross_config()) is None
+    assert gnu_triple("aarch64-unknown-linux-gnu") == "aarch64-linux-gnu"
+
+    cross = CrossTarget.from_config(_cross_config(
+        target="aarch64-unknown-linux-gnu",
+        runner="qemu-aarch64 -L /usr/aarch64-linux-gnu",
+    ))
+    assert cross.c_compiler == "aarch64-linux-gnu-gcc"
+    assert cross.runner == ["qemu-aarch64", "-L", "/usr/aarch64-linux-gnu"]
+    assert cross.cargo_args() == ["--target", "aarch64-unknown-linux-gnu"]
+    env = cross.cargo_env({})
+    assert env == {"CARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_LINKER": "aarch64-linux-gnu-gcc"}
+    assert cross.target_dir("/b") == "/b/target/aarch64-unknown-linux-gnu/debug"
+
+
+def test_cross_runnable_wraps_executable(tmp_path):
+    program = tmp_path / "program"
+    program.write_text('#!/bin/sh\necho "$CROSS_MARK $1"\n')
+    program.chmod(0o755)
+
+    direct = CrossTarget("aarch64-unknown-linux-gnu", "cc")
+    assert direct.runnable(str(program)) == str(program)
+
+    cross = CrossTarget("aarch64-unknown-linux-gnu", "cc", ["env", "CROSS_MARK=runner"])
+    wrapper = cross.runnable(str(program))
+    assert wrapper == f"{program}.run"
+    result = subprocess.run([wrapper, "arg"], capture_output=True, text=True, check=True)
+    assert result.stdout.strip() == "runner arg"
+
+
+def test_e2e_cross_links_with_target_toolchain(tmp_path, monkeypatch):
+    link_calls: list[list[str]] = []
+    run_calls: list[tuple[str, dict]] = []
+
+    monkeypatch.setattr(E2EVerifier, "try_compile_rust_code",
+                        lambda self, code, executable: (VerifyResult.SUCCESS, None))
+    monkeypatch.setattr(utils, "patched_env", lambda _var, value: {"LD_LIBRARY_PATH": value})
+
+    def fake_run(cmd, *_, **__):
+        link_calls.append(cmd)
+        return subprocess.CompletedProcess(cmd, 0, b"", b"")
+
+    def fake_run_tests(self, target, env=None, test_number=None, valgrind=False):
+        run_calls.append((target, env or {}))
+        return (VerifyResult.SUCCESS, None, None)
+
+    monkeypatch.setattr(subprocess, "run", fake_run)
+    monkeypatch.setattr(E2EVerifier, "_run_tests", fake_run_tests)
+
+    verifier = E2EVerifier(
+        test_cmd_path="tests/verifier/test_cmd.json",
+        config=_cross_config(target="aarch64-unknown-linux-gnu"),
+        build_path=str(tmp_path),
+        is_executable=False,
+        executable_object="tests/verifier/mock_results/test1.o",
+    )
+
+    assert verifier.e2e_verify("fn main() {}") == (VerifyResult.SUCCESS, None)
+    lib_dir = os.path.join(verifier.build_attempt_path, "target", "aarch64-unknown-linux-gnu", "debug")
+    link_cmd = link_calls[0]
+    assert link_cmd[0] == "aarch64-linux-gnu-gcc"
+    assert f"-L{lib_dir}" in link_cmd
+    assert run_calls[0][1]["LD_LIBRARY_PATH"] == lib_dir
@@ -0,0 +1,52 @@
+import json
+import os
+from types import SimpleNamespace
+
+from sactor.c_parser.goto_plan import classify_goto_label
+from sactor.utils import load_default_config
+from sactor.verifier import UnidiomaticVerifier, VerifyResult
+
+FIXTURE = os.path.join(os.path.dirname(__file__), "..", "c_parser", "fixtures", "goto_paths.c")
+
+
+def _retry_function():
+    # `retry` in the fixture: `again:` on line 27, `goto again;` on line 30
+    return SimpleNamespace(
+        name="retry",
+        goto_labels=[classify_goto_label("again", 27, [30], True, False)],
+        node=SimpleNamespace(location=SimpleNamespace(file=SimpleNamespace(name=os.path.abspath(FIXTURE)))),
+    )
+
+
+def _verifier(tmp_path):
+    test_cmd = tmp_path / "test_cmd.json"
+    test_cmd.write_text(json.dumps([
+        {"command": "%t 1"},
+        {"command": "%t 3"},
+    ]))
+    return UnidiomaticVerifier(str(test_cmd), config=load_default_config(), build_path=str(tmp_path / "build"))
+
+
+def test_goto_targeted_tests(tmp_path):
+    verifier = _verifier(tmp_path)
+    function = _retry_function()
+    # only `retry(3)` jumps back to `again:`
+    assert verifier._goto_targeted_tests(function) == {"again": [1]}
+    assert verifier._goto_coverage["retry"] == {"again": [1]}
+
+
+def test_goto_targeted_failure_names_the_path(tmp_path, monkeypatch):
+    verifier = _verifier(tmp_path)
+    function = _retry_function()
+    runs = []
+
+    def fake_run_tests_with_rust(self, target, test_number=None, valgrind=False, rust_lib_dir=None, sanitize=False):
+        runs.append(test_number)
+        return (VerifyResult.TEST_ERROR, "expected 3", test_number)
+
+    monkeypatch.setattr(UnidiomaticVerifier, "_run_tests_with_rust", fake_run_tests_with_rust)
+    result = verifier._run_goto_targeted_tests(function, "harness", "lib", False)
+    assert runs == [1]
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert result[2] == 1
+    assert "Test 1 takes `goto again` (loop)" in result[1]
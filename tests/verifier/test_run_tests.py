@@ -1,3 +1,7 @@
+import json
+import os
+import time
+
 from sactor import utils
 from sactor.verifier import UnidiomaticVerifier, VerifyResult
 
@@ -50,3 +54,191 @@ def test_run_timeout():
     print(result[1])
 
 
+
+
+def test_run_tests_sanitizer_report(tmp_path, monkeypatch):
+    verifier = get_unidiomatic_verifier("tests/verifier/mock_results/return0stdout.json")
+    verifier.embed_test_c_dir = str(tmp_path)
+
+    def fake_run_tests(target, env=None, test_number=None, valgrind=False):
+        # the stdout still matches, but the sanitizer wrote a report
+        log_path = env["ASAN_OPTIONS"].split("log_path=")[1]
+        with open(f"{log_path}.1234", "w") as f:
+            f.write("==1234==ERROR: AddressSanitizer: heap-buffer-overflow\n")
+        return (VerifyResult.SUCCESS, None, None)
+
+    monkeypatch.setattr(verifier, "_run_tests", fake_run_tests)
+    result = verifier._run_tests_with_rust("", sanitize=True)
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert "heap-buffer-overflow" in result[1]
+    assert result[2] is None
+
+    monkeypatch.setattr(
+        verifier, "_run_tests", lambda *args, **kwargs: (VerifyResult.SUCCESS, None, None))
+    result = verifier._run_tests_with_rust("", sanitize=True)
+    assert result[0] == VerifyResult.SUCCESS
+
+
+def _write_fixture_task(tmp_path, expected):
+    (tmp_path / "fixtures").mkdir()
+    (tmp_path / "fixtures" / "words.txt").write_text("world\n")
+    task = [{
+        "command": ["sh", "-c", "cat greeting.txt data/words.txt > out.txt"],
+        "input_files": {
+            "greeting.txt": "hello\n",
+            "data/words.txt": {"path": "fixtures/words.txt"},
+        },
+        "output_files": {"out.txt": expected},
+    }]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    return str(task_path)
+
+
+def test_run_tests_file_fixtures(tmp_path):
+    verifier = get_unidiomatic_verifier(_write_fixture_task(tmp_path, "hello\nworld\n"))
+    result = verifier._run_tests("")
+    assert result[0] == VerifyResult.SUCCESS
+    # the command ran in a temporary directory, not next to the task file
+    assert not os.path.exists(tmp_path / "out.txt")
+
+
+def test_run_tests_file_fixtures_mismatch(tmp_path):
+    verifier = get_unidiomatic_verifier(_write_fixture_task(tmp_path, "hello\nthere\n"))
+    result = verifier._run_tests("")
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert "Output file 'out.txt' differs" in result[1]
+    assert "-there" in result[1]
+    assert "+world" in result[1]
+    assert result[2] == 0
+
+
+
+def test_run_tests_file_fixtures_mode_and_absent(tmp_path):
+    task = [{
+        "command": ["sh", "-c", 'test ! -w locked.txt || test "$(id -u)" = 0; cat locked.txt > out.txt'],
+        "input_files": {"locked.txt": {"contents": "data\n", "mode": "0444"}},
+        "output_files": {"out.txt": "data\n", "scratch.tmp": None},
+    }]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    verifier = get_unidiomatic_verifier(str(task_path))
+    assert verifier.verify_test_cmd(str(task_path))
+    assert verifier._run_tests("")[0] == VerifyResult.SUCCESS
+
+    task[0]["command"] = ["sh", "-c", "cat locked.txt > out.txt; touch scratch.tmp"]
+    task_path.write_text(json.dumps(task))
+    result = verifier._run_tests("")
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert "Output file 'scratch.tmp' should not exist" in result[1]
+
+
+def test_run_tests_task_options(tmp_path):
+    (tmp_path / "sub").mkdir()
+    (tmp_path / "sub" / "marker").write_text("")
+    task = [
+        {
+            "command": ["sh", "-c", 'test "$GREETING" = hello && test -f marker'],
+            "cwd": "sub",
+            "env": {"GREETING": "hello"},
+        },
+        {"command": ["sleep", "5"], "timeout": 1},
+    ]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    verifier = get_unidiomatic_verifier(str(task_path))
+    assert verifier.verify_test_cmd(str(task_path))
+
+    result = verifier._run_tests("", test_number=0)
+    assert result[0] == VerifyResult.SUCCESS
+    result = verifier._run_tests("")
+    assert result[0] == VerifyResult.TEST_TIMEOUT
+    assert result[2] == 1
+
+
+def test_run_tests_signals(tmp_path):
+    loop = "while :; do sleep 0.05; done"
+    task = [
+        {
+            "command": ["sh", "-c", f"trap 'exit 0' USR1; {loop}"],
+            "signals": [{"signal": "SIGUSR1", "after": 0.2}],
+            "timeout": 10,
+        },
+        {
+            "command": ["sh", "-c", loop],
+            "signals": [{"signal": "TERM", "after": 0.2, "group": True}],
+            "timeout": 10,
+        },
+    ]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    verifier = get_unidiomatic_verifier(str(task_path))
+    assert verifier.verify_test_cmd(str(task_path))
+
+    assert verifier._run_tests("", test_number=0)[0] == VerifyResult.SUCCESS
+    result = verifier._run_tests("", test_number=1)
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert result[1] == "Killed by SIGTERM"
+
+    task[0]["signals"] = [{"signal": "SIGNOPE"}]
+    task_path.write_text(json.dumps(task))
+    assert not verifier.verify_test_cmd(str(task_path))
+
+
+def test_run_tests_memory_limit(tmp_path):
+    task = [{
+        "command": ["python3", "-c", "bytearray(1024 * 1024 * 1024)"],
+        "memory_limit_mb": 256,
+    }]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    verifier = get_unidiomatic_verifier(str(task_path))
+    result = verifier._run_tests("")
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert "MemoryError" in result[1]
+
+
+def test_verify_test_cmd_bad_options(tmp_path):
+    task_path = tmp_path / "test_task.json"
+    for bad in ({"timeout": -1}, {"memory_limit_mb": "1G"}, {"env": {"A": 1}}, {"cwd": 3}):
+        task_path.write_text(json.dumps([{"command": "true", **bad}]))
+        assert not get_unidiomatic_verifier(str(task_path)).verify_test_cmd(str(task_path))
+
+
+def test_run_tests_parallel(tmp_path):
+    task = [{"command": ["sleep", "1"]} for _ in range(4)]
+    task += [
+        {"command": ["sh", "-c", "sleep 0.5; echo slow >&2; exit 1"]},
+        {"command": ["sh", "-c", "echo fast >&2; exit 1"]},
+    ]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    verifier = get_unidiomatic_verifier(str(task_path))
+    verifier.config['general']['test_jobs'] = 6
+
+    start = time.monotonic()
+    result = verifier._run_tests("")
+    assert time.monotonic() - start < 3
+    # the first failing command wins, as in a serial run
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert result[1] == "slow\n"
+    assert result[2] == 4
+
+    assert verifier._run_tests("", test_number=2)[0] == VerifyResult.SUCCESS
+
+
+def test_run_tests_parallel_isolated(tmp_path):
+    (tmp_path / "input.txt").write_text("data\n")
+    # both commands write out.txt; sharing a directory, one would read the other's
+    task = [
+        {"command": ["sh", "-c", f"cat input.txt > out.txt; echo {i} >> out.txt; sleep 0.5; "
+                     f"test \"$(cat out.txt)\" = \"$(printf 'data\\n{i}')\""]}
+        for i in range(2)
+    ]
+    task_path = tmp_path / "test_task.json"
+    task_path.write_text(json.dumps(task))
+    verifier = get_unidiomatic_verifier(str(task_path))
+    verifier.config['general']['test_jobs'] = 2
+
+    assert verifier._run_tests("") == (VerifyResult.SUCCESS, None, None)
+    assert not (tmp_path / "out.txt").exists()
@@ -0,0 +1,52 @@
+import pytest
+
+from sactor.utils import load_default_config
+from sactor.verifier import E2EVerifier, VerifyResult
+
+
+@pytest.fixture
+def config():
+    base = load_default_config()
+    config = {k: (v.copy() if isinstance(v, dict) else v) for k, v in base.items()}
+    config['general']['thread_test_repetitions'] = 3
+    return config
+
+
+def _verifier(tmp_path, monkeypatch, config, results):
+    runs: list[str] = []
+    monkeypatch.setattr(
+        E2EVerifier, "try_compile_rust_code", lambda self, code, executable: (VerifyResult.SUCCESS, None))
+
+    def fake_run_tests(self, target, env=None, test_number=None, valgrind=False):
+        runs.append(target)
+        return results[len(runs) - 1]
+
+    monkeypatch.setattr(E2EVerifier, "_run_tests", fake_run_tests)
+    verifier = E2EVerifier(
+        test_cmd_path="tests/verifier/test_cmd.json",
+        config=config,
+        build_path=str(tmp_path),
+        is_executable=True,
+    )
+    return verifier, runs
+
+
+def test_threaded_tests_repeat(tmp_path, monkeypatch, config):
+    passed = (VerifyResult.SUCCESS, None, None)
+    verifier, runs = _verifier(tmp_path, monkeypatch, config, [passed] * 4)
+    assert verifier.e2e_verify("fn main() {}") == (VerifyResult.SUCCESS, None)
+    assert len(runs) == 1
+
+    assert verifier.e2e_verify("fn main() {}", threaded=True) == (VerifyResult.SUCCESS, None)
+    assert len(runs) == 1 + 3
+
+
+def test_threaded_divergence_is_reported(tmp_path, monkeypatch, config):
+    passed = (VerifyResult.SUCCESS, None, None)
+    failed = (VerifyResult.TEST_ERROR, "expected 4000, got 3998", 0)
+    verifier, runs = _verifier(tmp_path, monkeypatch, config, [passed, failed, passed])
+    result = verifier.e2e_verify("fn main() {}", threaded=True)
+    assert len(runs) == 2
+    assert result[0] == VerifyResult.TEST_ERROR
+    assert result[1].startswith("expected 4000, got 3998\n")
+    assert "failed on run 2 of 3" in result[1]
@@ -8,7 +8,7 @@ from types import SimpleNamespace
 
 import pytest
 
-from sactor import rust_ast_parser
+from sactor import rust_ast_parser, utils
 from sactor.utils import read_file
 from sactor.c_parser import CParser
 from sactor.combiner.partial_combiner import PartialCombiner
@@ -26,6 +26,8 @@ def test_verify_test_cmd():
     assert not Verifier.verify_test_cmd(cmd_bad2_path)
     cmd_bad3_path = "tests/verifier/test_cmd_bad3.json"
     assert not Verifier.verify_test_cmd(cmd_bad3_path)
+    cmd_bad4_path = "tests/verifier/test_cmd_bad4.json"
+    assert not Verifier.verify_test_cmd(cmd_bad4_path)
 
 @pytest.fixture
 def c_parser():
@@ -215,3 +217,132 @@ fn my_function() -> i32 {
         function_dependency_uses=dependency_uses,
         has_prefix=False
     )
+
+
+def test_parse_miri_output():
+    output = """running 2 tests
+test tests::test_ok ... ok
+test tests::test_sum ...
+error: Undefined Behavior: memory access failed: alloc1234 has been freed, so this pointer is dangling
+  --> src/lib.rs:12:9
+   |
+12 |         *ptr
+   |         ^^^^ memory access failed
+error: memory leaked: alloc88 (Rust heap, size: 16, align: 8), allocated here:
+  --> /rustc/library/alloc/src/alloc.rs:99:9
+"""
+    findings = Verifier.parse_miri_output(output)
+    assert findings == [
+        {
+            "kind": "undefined_behavior",
+            "message": "memory access failed: alloc1234 has been freed, so this pointer is dangling",
+            "test": "tests::test_sum",
+            "location": "src/lib.rs:12:9",
+        },
+        {
+            "kind": "memory_leak",
+            "message": "alloc88 (Rust heap, size: 16, align: 8), allocated here",
+            "test": None,
+            "location": "/rustc/library/alloc/src/alloc.rs:99:9",
+        },
+    ]
+    assert Verifier.parse_miri_output("test tests::a ... ok\n") == []
+
+
+def test_format_rustc_diagnostics():
+    diagnostic = {
+        "kind": "type_mismatch",
+        "code": "E0308",
+        "message": "mismatched types",
+        "item": "total",
+        "line": 8,
+        "column": 9,
+        "label": "expected `i64`, found `i32`",
+        "notes": ["you can convert an `i32` to an `i64`: `.into()`"],
+    }
+    summary = Verifier.format_rustc_diagnostics([diagnostic, diagnostic], limit=1)
+    assert summary == (
+        "error[E0308] (type mismatch) in `total` at line 8:9: mismatched types\n"
+        "    expected `i64`, found `i32`\n"
+        "    note: you can convert an `i32` to an `i64`: `.into()`\n"
+        "... and 1 more errors\n"
+    )
+
+
+def test_rendered_compiler_messages():
+    output = "\n".join([
+        json.dumps({"reason": "compiler-artifact", "target": {"name": "dep"}}),
+        json.dumps({"reason": "compiler-message", "message": {
+            "level": "warning", "rendered": "warning: unused variable: `x`\n"}}),
+        "not json",
+        json.dumps({"reason": "compiler-message", "message": {
+            "level": "error", "rendered": "error: linking with `cc` failed\n"}}),
+        json.dumps({"reason": "build-finished", "success": False}),
+    ])
+    assert Verifier.rendered_compiler_messages(output) == (
+        "warning: unused variable: `x`\n"
+        "error: linking with `cc` failed\n"
+    )
+    assert Verifier.rendered_compiler_messages("") == ""
+
+
+def test_clippy_fix(tmp_path, config):
+    verifier = UnidiomaticVerifier(
+        "tests/verifier/test_cmd.json", config, build_path=str(tmp_path))
+    context = "pub struct Stack {\n    pub items: Vec<i32>,\n}\n"
+    code = '''pub fn stack_is_empty(stack: &Stack) -> bool {
+    // length check
+    stack.items.len() == 0
+}
+'''
+    fixed = verifier.clippy_fix("stack_is_empty", code, context)
+    assert fixed == '''pub fn stack_is_empty(stack: &Stack) -> bool {
+    // length check
+    stack.items.is_empty()
+}
+'''
+    assert verifier.clippy_fix("stack_is_empty", fixed, context) is None
+
+    # only the configured lints are applied
+    config["general"]["clippy_fix_lints"] = ["needless_return"]
+    assert verifier.clippy_fix("stack_is_empty", code, context) is None
+
+
+def test_stable_output_rejects_nightly_code(tmp_path, config):
+    verifier = UnidiomaticVerifier(
+        "tests/verifier/test_cmd.json", config, build_path=str(tmp_path))
+    code = 'pub unsafe extern "C" fn sum(count: i32, mut args: ...) -> i32 {\n    count\n}\n'
+    previous = os.environ.get("RUSTUP_TOOLCHAIN")
+    utils.set_stable_output("")
+    try:
+        assert os.environ["RUSTUP_TOOLCHAIN"] == "stable"
+        result, message = verifier.try_compile_rust_code(code)
+        assert result == VerifyResult.COMPILE_ERROR
+        assert "line 1: C-variadic function `sum`" in message
+        assert verifier.try_compile_rust_code("pub fn one() -> i32 {\n    1\n}\n")[0] == VerifyResult.SUCCESS
+    finally:
+        utils.set_stable_output(None)
+    assert os.environ.get("RUSTUP_TOOLCHAIN") == previous
+
+
+def test_try_build_no_std(tmp_path, config):
+    host = subprocess.run(["rustc", "-vV"], capture_output=True, text=True, check=True).stdout
+    config["general"]["profile"] = "no_std"
+    # building for the host still proves nothing from std is used
+    config["no_std"]["target"] = next(
+        line.split(": ")[1] for line in host.splitlines() if line.startswith("host: "))
+    verifier = UnidiomaticVerifier(
+        "tests/verifier/test_cmd.json", config, build_path=str(tmp_path))
+
+    assert verifier.try_build_no_std(
+        "pub fn total(values: &[i32]) -> Vec<i32> {\n    values.to_vec()\n}\n"
+    ) == (VerifyResult.SUCCESS, None)
+
+    result, message = verifier.try_build_no_std(
+        "pub fn read(path: &str) -> usize {\n    std::fs::read(path).unwrap().len()\n}\n")
+    assert result == VerifyResult.COMPILE_ERROR
+    assert "`std::fs::read` needs the standard library" in message
+
+    result, message = verifier.try_build_no_std("pub fn root(x: f64) -> f64 {\n    x.sqrt()\n}\n")
+    assert result == VerifyResult.COMPILE_ERROR
+    assert "no method named `sqrt`" in message